@@ -0,0 +1,200 @@
+//! This crate generates test vectors for the proof system over the Vesta curve,
+//! to check that other implementations (or later versions of this one) derive the same Fiat-Shamir transcript
+//! and produce the same proofs bit for bit.
+//!
+//! For each of the fixed small circuits of [circuits], [generate] records a [Vector]
+//! with the digest of the verifier index, the witness, the challenges of the transcript and the serialized proof.
+//! The randomness of the prover is drawn from a ChaCha20 generator seeded with [SEED],
+//! so that the vectors are reproducible.
+
+use ark_ff::{BigInteger, PrimeField};
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use kimchi::circuits::{builder::CircuitBuilder, gate::CircuitGate, wires::COLUMNS};
+use kimchi::prover::ProverProof;
+use kimchi::prover_index::testing::new_index_for_test;
+use kimchi::verifier::batch_verify;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+/// The seed of the randomness of the prover.
+pub const SEED: [u8; 32] = [42; 32];
+
+/// A fixed circuit, along with a satisfying witness.
+pub struct Circuit {
+    /// The name of the circuit in the vectors
+    pub name: &'static str,
+    /// The gates of the circuit
+    pub gates: Vec<CircuitGate<Fp>>,
+    /// The values of the public inputs
+    pub public: Vec<Fp>,
+    /// The witness
+    pub witness: [Vec<Fp>; COLUMNS],
+}
+
+/// The test vector of a circuit, with field elements and bytes in hexadecimal
+/// (field elements as their canonical representation in little-endian).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Vector {
+    /// The name of the circuit
+    pub name: String,
+    /// The digest of the verifier index, in the base field
+    pub index_digest: String,
+    /// The values of the public inputs
+    pub public: Vec<String>,
+    /// The columns of the witness
+    pub witness: Vec<Vec<String>>,
+    /// The challenges of the transcript
+    pub challenges: Challenges,
+    /// The proof, serialized with MessagePack
+    pub proof: String,
+}
+
+/// The challenges of the transcript of a proof, in the order they are derived.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Challenges {
+    pub beta: String,
+    pub gamma: String,
+    pub alpha: String,
+    pub zeta: String,
+    pub v: String,
+    pub u: String,
+}
+
+fn hex_field<F: PrimeField>(x: &F) -> String {
+    hex::encode(x.into_repr().to_bytes_le())
+}
+
+/// y = x^3, for a public y and a private x.
+fn cube() -> Circuit {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let y = b.public_input();
+    let x = b.private_input();
+    let x2 = b.mul(x, x);
+    let x3 = b.mul(x2, x);
+    b.assert_equal(x3, y);
+    let (gates, generator) = b.build();
+
+    let public = vec![Fp::from(27u64)];
+    let witness = generator.generate(&public, &[Fp::from(3u64)]);
+    Circuit {
+        name: "cube",
+        gates,
+        public,
+        witness,
+    }
+}
+
+/// y = a * x + 5, for public a and y and a private x.
+fn affine() -> Circuit {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let a = b.public_input();
+    let y = b.public_input();
+    let x = b.private_input();
+    let ax = b.mul(a, x);
+    let res = b.add_constant(ax, Fp::from(5u64));
+    b.assert_equal(res, y);
+    let (gates, generator) = b.build();
+
+    let public = vec![Fp::from(2u64), Fp::from(19u64)];
+    let witness = generator.generate(&public, &[Fp::from(7u64)]);
+    Circuit {
+        name: "affine",
+        gates,
+        public,
+        witness,
+    }
+}
+
+/// x * (x - 1) = 0, for a private x and no public input.
+fn boolean() -> Circuit {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let x = b.private_input();
+    let one = b.constant(Fp::from(1u64));
+    let x_minus_one = b.sub(x, one);
+    let product = b.mul(x, x_minus_one);
+    b.assert_zero(product);
+    let (gates, generator) = b.build();
+
+    let witness = generator.generate(&[], &[Fp::from(1u64)]);
+    Circuit {
+        name: "boolean",
+        gates,
+        public: vec![],
+        witness,
+    }
+}
+
+/// Returns the circuits of the vectors.
+pub fn circuits() -> Vec<Circuit> {
+    vec![cube(), affine(), boolean()]
+}
+
+/// Proves `circuit` and records its test vector.
+pub fn generate(circuit: &Circuit) -> Vector {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(circuit.gates.clone(), circuit.public.len());
+    let verifier_index = index.verifier_index();
+
+    let mut rng = ChaCha20Rng::from_seed(SEED);
+    let proof = ProverProof::create_with_rng::<BaseSponge, ScalarSponge, _>(
+        &group_map,
+        circuit.witness.clone(),
+        &index,
+        vec![],
+        &mut rng,
+    )
+    .expect("the witness of a conformance circuit is valid");
+    let oracles = proof
+        .challenges::<BaseSponge, ScalarSponge>(&verifier_index)
+        .expect("the proof matches its index");
+
+    Vector {
+        name: circuit.name.to_string(),
+        index_digest: hex_field(&verifier_index.digest()),
+        public: circuit.public.iter().map(hex_field).collect(),
+        witness: circuit
+            .witness
+            .iter()
+            .map(|col| col.iter().map(hex_field).collect())
+            .collect(),
+        challenges: Challenges {
+            beta: hex_field(&oracles.beta),
+            gamma: hex_field(&oracles.gamma),
+            alpha: hex_field(&oracles.alpha),
+            zeta: hex_field(&oracles.zeta),
+            v: hex_field(&oracles.v),
+            u: hex_field(&oracles.u),
+        },
+        proof: hex::encode(rmp_serde::to_vec(&proof).expect("the proof is serializable")),
+    }
+}
+
+/// Returns the test vectors of all the [circuits].
+pub fn vectors() -> Vec<Vector> {
+    circuits().iter().map(generate).collect()
+}
+
+/// Deserializes the proof of `vector` and verifies it against the index of `circuit`.
+pub fn verify(circuit: &Circuit, vector: &Vector) -> Result<(), String> {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(circuit.gates.clone(), circuit.public.len());
+    let verifier_index = index.verifier_index();
+
+    let bytes = hex::decode(&vector.proof).map_err(|e| e.to_string())?;
+    let proof: ProverProof<Affine> = rmp_serde::from_slice(&bytes).map_err(|e| e.to_string())?;
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .map_err(|e| e.to_string())
+}
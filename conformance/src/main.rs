@@ -0,0 +1,21 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Usage: cargo run --bin conformance -- <OUTPUT_FILE>
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1) {
+        Some(output_file) if args.len() == 2 => {
+            let vectors = conformance::vectors();
+            let writer: Box<dyn Write> = match output_file.as_str() {
+                "-" => Box::new(io::stdout()),
+                _ => Box::new(File::create(output_file).expect("could not create file")),
+            };
+            serde_json::to_writer_pretty(writer, &vectors).expect("could not write to file");
+        }
+        _ => {
+            println!("usage: cargo run --bin conformance -- <OUTPUT_FILE>");
+        }
+    }
+}
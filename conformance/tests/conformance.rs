@@ -0,0 +1,28 @@
+use conformance::{circuits, generate, vectors, verify, Vector};
+use std::fs::File;
+use std::path::Path;
+
+#[test]
+fn test_vectors_are_reproducible() {
+    let circuits = circuits();
+    let vectors: Vec<_> = circuits.iter().map(generate).collect();
+    for (circuit, vector) in circuits.iter().zip(&vectors) {
+        assert_eq!(*vector, generate(circuit), "{}", circuit.name);
+        verify(circuit, vector).unwrap();
+    }
+
+    // a proof only verifies against its circuit
+    assert!(verify(&circuits[1], &vectors[0]).is_err());
+}
+
+#[test]
+fn test_vectors_did_not_change() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("vectors.json");
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        // the vectors have not been exported yet (see the README)
+        Err(_) => return,
+    };
+    let expected: Vec<Vector> = serde_json::from_reader(file).unwrap();
+    assert_eq!(vectors(), expected);
+}
@@ -0,0 +1,254 @@
+//! This crate implements a daemon proving and verifying over the Vesta curve for a single circuit,
+//! so that services written in other languages can use the prover without linking to it.
+//!
+//! The [Daemon] loads a prover index written by [ProverIndex::to_file] at startup,
+//! and answers [JSON-RPC 2.0](https://www.jsonrpc.org/specification) requests,
+//! one per line of a TCP connection (see [Daemon::serve]), with the methods:
+//!
+//! - `digest`, without parameters, which returns the digest of the verifier index;
+//! - `prove`, with the parameters `{"witness": [[...], ...]}`, the columns of the witness,
+//!   which returns `{"proof": "..."}`;
+//! - `verify`, with the parameters `{"proof": "..."}`, which returns `true`
+//!   or fails with the reason the proof was rejected.
+//!
+//! Field elements and bytes are encoded in hexadecimal, field elements as their canonical representation in little-endian,
+//! and the proofs are serialized with MessagePack.
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::CanonicalDeserialize;
+use commitment_dlog::{commitment::CommitmentCurve, srs::SRS};
+use groupmap::GroupMap;
+use kimchi::circuits::wires::COLUMNS;
+use kimchi::prover::ProverProof;
+use kimchi::prover_index::ProverIndex;
+use kimchi::verifier::batch_verify;
+use kimchi::verifier_index::VerifierIndex;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+/// The error code of a request that is not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// The error code of an unknown method.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The error code of malformed parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// The error code of a witness that could not be proven.
+pub const PROOF_FAILED: i64 = 1;
+/// The error code of a proof that was rejected.
+pub const VERIFICATION_FAILED: i64 = 2;
+
+/// A JSON-RPC request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// The error of a failed JSON-RPC request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// A JSON-RPC response, with either a result or an error.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct ProveParams {
+    witness: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct VerifyParams {
+    proof: String,
+}
+
+fn hex_field<F: PrimeField>(x: &F) -> String {
+    hex::encode(x.into_repr().to_bytes_le())
+}
+
+fn parse_field(s: &str) -> Result<Fp, String> {
+    let bytes = hex::decode(s).map_err(|e| e.to_string())?;
+    Fp::deserialize(&bytes[..]).map_err(|_| format!("{s} is not a field element"))
+}
+
+fn invalid_params(e: impl ToString) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    }
+}
+
+/// The keys of the circuit of the daemon.
+pub struct Daemon {
+    prover_key: ProverIndex<Affine>,
+    verifier_key: VerifierIndex<Affine>,
+    group_map: <Affine as CommitmentCurve>::Map,
+}
+
+impl Daemon {
+    /// Loads the prover index of `path`, creating the SRS of the size it was created with.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let index = ProverIndex::<Affine>::read(
+            BufReader::new(file),
+            |size| Ok(Arc::new(SRS::create(size))),
+            oracle::pasta::fq_kimchi::params(),
+            oracle::pasta::fp_kimchi::params(),
+        )?;
+        Ok(Self::new(index))
+    }
+
+    /// Creates a daemon proving with `index`, whose SRS must have the Lagrange basis of the domain of the circuit.
+    pub fn new(index: ProverIndex<Affine>) -> Self {
+        let (prover_key, verifier_key) = index.into_keys();
+        Self {
+            prover_key,
+            verifier_key,
+            group_map: <Affine as CommitmentCurve>::Map::setup(),
+        }
+    }
+
+    fn prove(&self, params: Value) -> Result<Value, RpcError> {
+        let params: ProveParams = serde_json::from_value(params).map_err(invalid_params)?;
+        if params.witness.len() != COLUMNS {
+            return Err(invalid_params(format!(
+                "the witness has {} columns instead of {COLUMNS}",
+                params.witness.len()
+            )));
+        }
+        let mut witness: [Vec<Fp>; COLUMNS] = Default::default();
+        for (column, values) in witness.iter_mut().zip(&params.witness) {
+            *column = values
+                .iter()
+                .map(|x| parse_field(x))
+                .collect::<Result<_, _>>()
+                .map_err(invalid_params)?;
+        }
+        let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+            &self.group_map,
+            witness,
+            &self.prover_key,
+            vec![],
+        )
+        .map_err(|e| RpcError {
+            code: PROOF_FAILED,
+            message: e.to_string(),
+        })?;
+        let bytes = rmp_serde::to_vec(&proof).expect("the proof is serializable");
+        Ok(json!({ "proof": hex::encode(bytes) }))
+    }
+
+    fn verify(&self, params: Value) -> Result<Value, RpcError> {
+        let params: VerifyParams = serde_json::from_value(params).map_err(invalid_params)?;
+        let bytes = hex::decode(&params.proof).map_err(invalid_params)?;
+        let proof: ProverProof<Affine> = rmp_serde::from_slice(&bytes).map_err(invalid_params)?;
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(
+            &self.group_map,
+            &[(&self.verifier_key, &proof)],
+        )
+        .map_err(|e| RpcError {
+            code: VERIFICATION_FAILED,
+            message: e.to_string(),
+        })?;
+        Ok(Value::Bool(true))
+    }
+
+    /// Answers a request.
+    pub fn handle(&self, request: Request) -> Response {
+        let result = match request.method.as_str() {
+            "digest" => Ok(Value::String(hex_field(
+                &self.prover_key.verifier_index_digest(),
+            ))),
+            "prove" => self.prove(request.params),
+            "verify" => self.verify(request.params),
+            method => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method {method}"),
+            }),
+        };
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            result,
+            error,
+        }
+    }
+
+    /// Answers a line of JSON-RPC.
+    pub fn handle_line(&self, line: &str) -> Response {
+        match serde_json::from_str(line) {
+            Ok(request) => self.handle(request),
+            Err(e) => Response {
+                jsonrpc: "2.0".to_string(),
+                id: Value::Null,
+                result: None,
+                error: Some(RpcError {
+                    code: PARSE_ERROR,
+                    message: e.to_string(),
+                }),
+            },
+        }
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = serde_json::to_string(&self.handle_line(&line))
+                .expect("the response is serializable");
+            writeln!(writer, "{response}")?;
+        }
+        Ok(())
+    }
+
+    /// Answers the connections of `listener`, each on its own thread.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let daemon = Arc::clone(&self);
+            thread::spawn(move || {
+                // a connection that fails only closes itself
+                let _ = daemon.serve_connection(stream);
+            });
+        }
+        Ok(())
+    }
+}
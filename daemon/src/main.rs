@@ -0,0 +1,24 @@
+use kimchi_daemon::Daemon;
+use std::env;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Usage: cargo run --bin kimchi-daemon -- <INDEX_FILE> <ADDRESS>
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    match &args[1..] {
+        [index_file, address] => {
+            let daemon =
+                Daemon::from_file(Path::new(index_file)).expect("could not load the index");
+            let listener = TcpListener::bind(address).expect("could not bind the address");
+            println!("listening on {}", address);
+            Arc::new(daemon)
+                .serve(listener)
+                .expect("could not accept a connection");
+        }
+        _ => {
+            println!("usage: cargo run --bin kimchi-daemon -- <INDEX_FILE> <ADDRESS>");
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use ark_ff::{BigInteger, PrimeField};
+use kimchi::circuits::polynomials::generic::testing::{create_circuit, create_circuit_and_witness};
+use kimchi::prover_index::testing::new_index_for_test;
+use kimchi_daemon::{Daemon, Response, METHOD_NOT_FOUND, PARSE_ERROR, VERIFICATION_FAILED};
+use mina_curves::pasta::fp::Fp;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+fn daemon() -> Daemon {
+    let index = new_index_for_test(create_circuit(0, 5), 5);
+    let path = std::env::temp_dir().join("kimchi_daemon_test_index");
+    index.to_file(&path).unwrap();
+    let daemon = Daemon::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    daemon
+}
+
+fn witness() -> Vec<Vec<String>> {
+    let (_, witness, _) = create_circuit_and_witness::<Fp>(5);
+    witness
+        .iter()
+        .map(|col| {
+            col.iter()
+                .map(|x| hex::encode(x.into_repr().to_bytes_le()))
+                .collect()
+        })
+        .collect()
+}
+
+fn call(daemon: &Daemon, method: &str, params: Value) -> Response {
+    let request = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+    daemon.handle_line(&request.to_string())
+}
+
+#[test]
+fn test_prove_and_verify() {
+    let daemon = daemon();
+    let response = call(&daemon, "prove", json!({ "witness": witness() }));
+    assert_eq!(response.id, json!(7));
+    let proof = response.result.unwrap()["proof"].clone();
+    let response = call(&daemon, "verify", json!({ "proof": proof.clone() }));
+    assert_eq!(response.result, Some(Value::Bool(true)));
+
+    // a proof of another circuit is rejected
+    let other = Daemon::new(new_index_for_test(create_circuit(0, 4), 4));
+    let response = call(&other, "verify", json!({ "proof": proof }));
+    assert_eq!(response.error.unwrap().code, VERIFICATION_FAILED);
+
+    let response = call(&daemon, "sign", Value::Null);
+    assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+    let response = daemon.handle_line("{");
+    assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+}
+
+#[test]
+fn test_serve() {
+    let daemon = Arc::new(daemon());
+    let digest = call(&daemon, "digest", Value::Null).result.unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || daemon.serve(listener));
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    writeln!(
+        stream,
+        r#"{{"jsonrpc": "2.0", "id": 1, "method": "digest"}}"#
+    )
+    .unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).unwrap();
+    let response: Response = serde_json::from_str(&line).unwrap();
+    assert_eq!(response.result, Some(digest));
+}
@@ -0,0 +1,40 @@
+/* The C interface of the kimchi prover and verifier, for circuits over the Vesta curve (see src/lib.rs). */
+
+#ifndef KIMCHI_H
+#define KIMCHI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define KIMCHI_OK 0
+#define KIMCHI_NULL_POINTER 1
+#define KIMCHI_DESERIALIZATION_FAILED 2
+#define KIMCHI_PROOF_FAILED 3
+#define KIMCHI_VERIFICATION_FAILED 4
+#define KIMCHI_PANIC 5
+
+/* A buffer of bytes allocated by the library, freed with kimchi_buffer_free. */
+typedef struct {
+    uint8_t *data;
+    size_t len;
+} KimchiBuffer;
+
+typedef struct KimchiProverIndex KimchiProverIndex;
+typedef struct KimchiVerifierIndex KimchiVerifierIndex;
+
+int32_t kimchi_last_error(KimchiBuffer *out);
+void kimchi_buffer_free(KimchiBuffer buffer);
+
+int32_t kimchi_prover_index_load(const uint8_t *data, size_t len, KimchiProverIndex **out);
+void kimchi_prover_index_free(KimchiProverIndex *index);
+
+int32_t kimchi_verifier_index_create(const KimchiProverIndex *index, KimchiVerifierIndex **out);
+int32_t kimchi_verifier_index_load(const uint8_t *data, size_t len, KimchiVerifierIndex **out);
+int32_t kimchi_verifier_index_write(const KimchiVerifierIndex *index, KimchiBuffer *out);
+void kimchi_verifier_index_free(KimchiVerifierIndex *index);
+
+/* The witness is serialized with MessagePack, as an array of 15 columns of field elements. */
+int32_t kimchi_prove(const KimchiProverIndex *index, const uint8_t *witness, size_t len, KimchiBuffer *out);
+int32_t kimchi_verify(const KimchiVerifierIndex *index, const uint8_t *proof, size_t len);
+
+#endif
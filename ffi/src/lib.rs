@@ -0,0 +1,409 @@
+//! This crate exports the kimchi prover and verifier through a C ABI, for circuits over the Vesta curve,
+//! so that applications in other languages can link to it (see `include/kimchi.h`).
+//!
+//! Indexes are loaded from their serialized form, as written by [ProverIndex::write] and [VerifierIndex::write],
+//! into opaque handles that the caller frees with [kimchi_prover_index_free] and [kimchi_verifier_index_free].
+//! Witnesses and proofs are passed as byte buffers serialized with MessagePack,
+//! and the buffers returned by the library are freed with [kimchi_buffer_free].
+//!
+//! Every function returns a status code, [KIMCHI_OK] on success.
+//! On failure, [kimchi_last_error] returns the message of the error on the calling thread.
+
+use commitment_dlog::{
+    commitment::CommitmentCurve,
+    srs::{endos, SRS},
+};
+use groupmap::GroupMap;
+use kimchi::{
+    circuits::wires::COLUMNS, prover::ProverProof, prover_index::ProverIndex,
+    verifier::batch_verify, verifier_index::VerifierIndex,
+};
+use mina_curves::pasta::{
+    fp::Fp,
+    pallas::Affine as Other,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use std::{cell::RefCell, panic, ptr, slice, sync::Arc};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// The call succeeded.
+pub const KIMCHI_OK: i32 = 0;
+/// A pointer argument is null.
+pub const KIMCHI_NULL_POINTER: i32 = 1;
+/// An index, a witness or a proof could not be deserialized.
+pub const KIMCHI_DESERIALIZATION_FAILED: i32 = 2;
+/// The witness could not be proven.
+pub const KIMCHI_PROOF_FAILED: i32 = 3;
+/// The proof was rejected.
+pub const KIMCHI_VERIFICATION_FAILED: i32 = 4;
+/// The library panicked.
+pub const KIMCHI_PANIC: i32 = 5;
+
+/// The serialized form of a witness.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+pub struct Witness(
+    #[serde_as(as = "[Vec<o1_utils::serialization::SerdeAs>; COLUMNS]")] pub [Vec<Fp>; COLUMNS],
+);
+
+/// A buffer of bytes allocated by the library.
+#[repr(C)]
+pub struct KimchiBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl KimchiBuffer {
+    fn new(bytes: Vec<u8>) -> Self {
+        let bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        Self {
+            data: Box::into_raw(bytes) as *mut u8,
+            len,
+        }
+    }
+}
+
+/// The opaque handle of a prover index.
+pub struct KimchiProverIndex(ProverIndex<Affine>);
+
+/// The opaque handle of a verifier index.
+pub struct KimchiVerifierIndex(VerifierIndex<Affine>);
+
+/// A failed call, with its status code and the message of [kimchi_last_error].
+struct Error(i32, String);
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = RefCell::new(String::new());
+}
+
+/// Runs `f`, recording its error on failure and catching its panics.
+fn status(f: impl FnOnce() -> Result<(), Error>) -> i32 {
+    // the handles are not used again after a panic of a call on them
+    let Error(code, message) = match panic::catch_unwind(panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return KIMCHI_OK,
+        Ok(Err(e)) => e,
+        Err(_) => Error(KIMCHI_PANIC, "the library panicked".to_string()),
+    };
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    code
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], Error> {
+    if data.is_null() && len > 0 {
+        return Err(null_pointer());
+    }
+    if len == 0 {
+        return Ok(&[]);
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn writable<'a, T>(out: *mut T) -> Result<&'a mut T, Error> {
+    out.as_mut().ok_or_else(null_pointer)
+}
+
+fn null_pointer() -> Error {
+    Error(
+        KIMCHI_NULL_POINTER,
+        "a pointer argument is null".to_string(),
+    )
+}
+
+fn deserialization_failed(e: impl ToString) -> Error {
+    Error(KIMCHI_DESERIALIZATION_FAILED, e.to_string())
+}
+
+/// Copies the message of the last error on the calling thread into `out`.
+///
+/// # Safety
+///
+/// `out` must point to a writable [KimchiBuffer].
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_last_error(out: *mut KimchiBuffer) -> i32 {
+    match out.as_mut() {
+        Some(out) => {
+            *out = KimchiBuffer::new(LAST_ERROR.with(|last| last.borrow().clone().into_bytes()));
+            KIMCHI_OK
+        }
+        None => KIMCHI_NULL_POINTER,
+    }
+}
+
+/// Frees a buffer returned by the library.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by the library, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_buffer_free(buffer: KimchiBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Loads the serialized prover index of the `len` bytes at `data`.
+/// The SRS is recreated (deterministically) with the size the index was created with.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `out` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_prover_index_load(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut KimchiProverIndex,
+) -> i32 {
+    status(|| {
+        let out = writable(out)?;
+        let index = ProverIndex::<Affine>::read(
+            bytes(data, len)?,
+            |srs_size| Ok(Arc::new(SRS::create(srs_size))),
+            oracle::pasta::fq_kimchi::params(),
+            oracle::pasta::fp_kimchi::params(),
+        )
+        .map_err(deserialization_failed)?;
+        *out = Box::into_raw(Box::new(KimchiProverIndex(index)));
+        Ok(())
+    })
+}
+
+/// Frees a prover index.
+///
+/// # Safety
+///
+/// `index` must be null or have been returned by [kimchi_prover_index_load], and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_prover_index_free(index: *mut KimchiProverIndex) {
+    if !index.is_null() {
+        drop(Box::from_raw(index));
+    }
+}
+
+/// Derives the verifier index of a prover index.
+///
+/// # Safety
+///
+/// `index` must be a live prover index, and `out` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_verifier_index_create(
+    index: *const KimchiProverIndex,
+    out: *mut *mut KimchiVerifierIndex,
+) -> i32 {
+    status(|| {
+        let index = index.as_ref().ok_or_else(null_pointer)?;
+        let out = writable(out)?;
+        *out = Box::into_raw(Box::new(KimchiVerifierIndex(index.0.verifier_index())));
+        Ok(())
+    })
+}
+
+/// Loads the serialized verifier index of the `len` bytes at `data`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `out` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_verifier_index_load(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut KimchiVerifierIndex,
+) -> i32 {
+    status(|| {
+        let out = writable(out)?;
+        let (endo_q, _) = endos::<Other>();
+        let index = VerifierIndex::<Affine>::read(
+            bytes(data, len)?,
+            |index| {
+                let mut srs = SRS::create(index.max_poly_size);
+                srs.add_lagrange_basis(index.domain);
+                Arc::new(srs)
+            },
+            endo_q,
+            oracle::pasta::fq_kimchi::params(),
+            oracle::pasta::fp_kimchi::params(),
+        )
+        .map_err(deserialization_failed)?;
+        *out = Box::into_raw(Box::new(KimchiVerifierIndex(index)));
+        Ok(())
+    })
+}
+
+/// Serializes a verifier index into `out`.
+///
+/// # Safety
+///
+/// `index` must be a live verifier index, and `out` must point to a writable [KimchiBuffer].
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_verifier_index_write(
+    index: *const KimchiVerifierIndex,
+    out: *mut KimchiBuffer,
+) -> i32 {
+    status(|| {
+        let index = index.as_ref().ok_or_else(null_pointer)?;
+        let out = writable(out)?;
+        let mut bytes = vec![];
+        index
+            .0
+            .write(&mut bytes)
+            .expect("the verifier index is serializable");
+        *out = KimchiBuffer::new(bytes);
+        Ok(())
+    })
+}
+
+/// Frees a verifier index.
+///
+/// # Safety
+///
+/// `index` must be null or have been returned by the library, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_verifier_index_free(index: *mut KimchiVerifierIndex) {
+    if !index.is_null() {
+        drop(Box::from_raw(index));
+    }
+}
+
+/// Proves the serialized [Witness] of the `len` bytes at `witness`, and writes the serialized proof into `out`.
+///
+/// # Safety
+///
+/// `index` must be a live prover index, `witness` must point to `len` readable bytes,
+/// and `out` must point to a writable [KimchiBuffer].
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_prove(
+    index: *const KimchiProverIndex,
+    witness: *const u8,
+    len: usize,
+    out: *mut KimchiBuffer,
+) -> i32 {
+    status(|| {
+        let index = index.as_ref().ok_or_else(null_pointer)?;
+        let out = writable(out)?;
+        let Witness(witness) =
+            rmp_serde::from_read_ref(bytes(witness, len)?).map_err(deserialization_failed)?;
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let proof =
+            ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index.0, vec![])
+                .map_err(|e| Error(KIMCHI_PROOF_FAILED, e.to_string()))?;
+        *out = KimchiBuffer::new(rmp_serde::to_vec(&proof).expect("the proof is serializable"));
+        Ok(())
+    })
+}
+
+/// Verifies the serialized proof of the `len` bytes at `proof` against a verifier index.
+/// Returns [KIMCHI_VERIFICATION_FAILED] if the proof is rejected.
+///
+/// # Safety
+///
+/// `index` must be a live verifier index, and `proof` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kimchi_verify(
+    index: *const KimchiVerifierIndex,
+    proof: *const u8,
+    len: usize,
+) -> i32 {
+    status(|| {
+        let index = index.as_ref().ok_or_else(null_pointer)?;
+        let proof: ProverProof<Affine> =
+            rmp_serde::from_read_ref(bytes(proof, len)?).map_err(deserialization_failed)?;
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&index.0, &proof)])
+            .map_err(|e| Error(KIMCHI_VERIFICATION_FAILED, e.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kimchi::circuits::polynomials::generic::testing::create_circuit_and_witness;
+    use kimchi::prover_index::testing::new_index_for_test;
+
+    unsafe fn buffer(buffer: &KimchiBuffer) -> Vec<u8> {
+        slice::from_raw_parts(buffer.data, buffer.len).to_vec()
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (gates, witness, public) = create_circuit_and_witness(5);
+        let witness = rmp_serde::to_vec(&Witness(witness)).unwrap();
+
+        let mut index_bytes = vec![];
+        new_index_for_test(gates, public.len())
+            .write(&mut index_bytes)
+            .unwrap();
+
+        unsafe {
+            let mut index = ptr::null_mut();
+            let status =
+                kimchi_prover_index_load(index_bytes.as_ptr(), index_bytes.len(), &mut index);
+            assert_eq!(status, KIMCHI_OK);
+            let mut verifier_index = ptr::null_mut();
+            assert_eq!(
+                kimchi_verifier_index_create(index, &mut verifier_index),
+                KIMCHI_OK
+            );
+
+            let mut proof = KimchiBuffer::new(vec![]);
+            let status = kimchi_prove(index, witness.as_ptr(), witness.len(), &mut proof);
+            assert_eq!(status, KIMCHI_OK);
+            assert_eq!(
+                kimchi_verify(verifier_index, proof.data, proof.len),
+                KIMCHI_OK
+            );
+
+            // the verifier index survives a round trip through bytes
+            let mut vk_bytes = KimchiBuffer::new(vec![]);
+            assert_eq!(
+                kimchi_verifier_index_write(verifier_index, &mut vk_bytes),
+                KIMCHI_OK
+            );
+            let mut loaded = ptr::null_mut();
+            assert_eq!(
+                kimchi_verifier_index_load(vk_bytes.data, vk_bytes.len, &mut loaded),
+                KIMCHI_OK
+            );
+            assert_eq!(kimchi_verify(loaded, proof.data, proof.len), KIMCHI_OK);
+
+            // a tampered proof is rejected, and the error is reported
+            let mut tampered: ProverProof<Affine> = rmp_serde::from_slice(&buffer(&proof)).unwrap();
+            tampered.public[0] = Fp::from(4u8);
+            let tampered = rmp_serde::to_vec(&tampered).unwrap();
+            assert_eq!(
+                kimchi_verify(loaded, tampered.as_ptr(), tampered.len()),
+                KIMCHI_VERIFICATION_FAILED
+            );
+            let mut message = KimchiBuffer::new(vec![]);
+            assert_eq!(kimchi_last_error(&mut message), KIMCHI_OK);
+            assert!(!buffer(&message).is_empty());
+
+            assert_eq!(
+                kimchi_verify(loaded, proof.data, proof.len - 1),
+                KIMCHI_DESERIALIZATION_FAILED
+            );
+            assert_eq!(
+                kimchi_verify(ptr::null(), proof.data, proof.len),
+                KIMCHI_NULL_POINTER
+            );
+
+            for buffer in [proof, vk_bytes, message] {
+                kimchi_buffer_free(buffer);
+            }
+            kimchi_verifier_index_free(loaded);
+            kimchi_verifier_index_free(verifier_index);
+            kimchi_prover_index_free(index);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Measures the index creation, the prover (as a whole and phase by phase) and the verifier
+//! on circuits of 2^10 to 2^20 rows, over both curves of the Pasta cycle.
+//! The largest size can be lowered with the `KIMCHI_BENCH_MAX_LOG_SIZE` environment variable.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, SamplingMode};
+use kimchi::bench::ScalingCtx;
+use kimchi::pasta::{
+    PallasBaseSponge, PallasScalarSponge, PastaCurve, VestaBaseSponge, VestaScalarSponge,
+};
+use kimchi::plonk_sponge::FrSponge;
+use mina_curves::pasta::{pallas, vesta};
+use oracle::FqSponge;
+use std::time::Duration;
+
+type Fr<G> = <G as ark_ec::AffineCurve>::ScalarField;
+type Fq<G> = <G as ark_ec::AffineCurve>::BaseField;
+
+const MIN_LOG_SIZE: usize = 10;
+const MAX_LOG_SIZE: usize = 20;
+
+/// The phases of the prover (see [kimchi::metrics::PROVER_PHASE_SECONDS]).
+const PHASES: [&str; 7] = [
+    "witness",
+    "interpolation",
+    "lookup",
+    "permutation",
+    "quotient",
+    "evaluations",
+    "opening",
+];
+
+fn log_sizes() -> std::ops::RangeInclusive<usize> {
+    let max = std::env::var("KIMCHI_BENCH_MAX_LOG_SIZE")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(MAX_LOG_SIZE);
+    MIN_LOG_SIZE..=max
+}
+
+fn bench_curve<G, EFqSponge, EFrSponge>(c: &mut Criterion, curve: &str)
+where
+    G: PastaCurve,
+    G::BaseField: ark_ff::PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    // one size at a time, as the indexes of the largest circuits take gigabytes
+    for log_size in log_sizes() {
+        let ctx = ScalingCtx::<G, EFqSponge, EFrSponge>::new(log_size);
+        let mut group = c.benchmark_group(format!("{} 2^{}", curve, log_size));
+        group.sample_size(10).sampling_mode(SamplingMode::Flat); // for slow benchmarks
+
+        group.bench_function("index creation", |b| {
+            b.iter(|| black_box(ctx.create_index()))
+        });
+
+        group.bench_function("proof creation", |b| {
+            b.iter(|| black_box(ctx.create_proof()))
+        });
+
+        // the durations of the phases are measured by the prover itself
+        for phase in PHASES {
+            group.bench_function(format!("proof creation/{}", phase), |b| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| ctx.create_proof().1.phase(phase).unwrap_or_default())
+                        .sum::<Duration>()
+                })
+            });
+        }
+
+        let (proof, _) = ctx.create_proof();
+        group.bench_function("proof verification", |b| {
+            b.iter(|| ctx.verify(black_box(&proof)))
+        });
+        group.finish();
+    }
+}
+
+pub fn bench_vesta(c: &mut Criterion) {
+    bench_curve::<vesta::Affine, VestaBaseSponge, VestaScalarSponge>(c, "vesta");
+}
+
+pub fn bench_pallas(c: &mut Criterion) {
+    bench_curve::<pallas::Affine, PallasBaseSponge, PallasScalarSponge>(c, "pallas");
+}
+
+criterion_group!(benches, bench_vesta, bench_pallas);
+criterion_main!(benches);
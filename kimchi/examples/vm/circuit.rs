@@ -0,0 +1,221 @@
+//! The circuit running a program for a fixed number of steps,
+//! from the registers given as its first public inputs to the registers given as the next ones.
+//!
+//! The program and the registers live in two memories (see [Memory]), the program one being only read.
+//! Each step fetches the words of its instruction from the program memory at `5 pc`,
+//! reads its operands and the register it writes to, and writes its result back.
+//! A row of the [Step] gate then decodes and executes the instruction:
+//!
+//! | 0  | 1      | 2    | 3    | 4           | 5          | 6      | 7 - 10              | 11     |
+//! |----|--------|------|------|-------------|------------|--------|---------------------|--------|
+//! | pc | opcode | `ra` | `rb` | `rd` before | `rd` after | target | one flag per opcode | `1/ra` |
+//!
+//! The cells wired to the memory accesses are in the first columns, which the permutation covers.
+//! The rows of the steps are consecutive, so that the program counter of a step is the one its predecessor computes.
+
+use crate::isa::{Instruction, INSTRUCTION_WORDS, REGISTERS};
+use ark_ff::{FftField, One, PrimeField};
+use kimchi::circuits::{
+    builder::{CircuitBuilder, Var, WitnessBuilder},
+    custom::GateConstraint,
+    expr::prologue::*,
+    gadgets::memory::Memory,
+    gate::{CircuitGate, GateType},
+    wires::COLUMNS,
+};
+use oracle::poseidon::ArithmeticSpongeParams;
+
+const PC: usize = 0;
+const OPCODE: usize = 1;
+const RA: usize = 2;
+const RB: usize = 3;
+const RD_BEFORE: usize = 4;
+const RD_AFTER: usize = 5;
+const TARGET: usize = 6;
+const FLAGS: [usize; 4] = [7, 8, 9, 10];
+const RA_INV: usize = 11;
+
+/// The gate executing an instruction, constraining the program counter of the next row.
+pub struct Step;
+
+impl<F: FftField> GateConstraint<F> for Step {
+    fn name(&self) -> &'static str {
+        "vm step"
+    }
+
+    fn constraints(&self) -> Vec<E<F>> {
+        let w = witness_curr::<F>;
+        let one = E::<F>::one();
+        let [add, mul, jnz, halt] = FLAGS.map(w);
+        let (pc, ra, rb) = (w(PC), w(RA), w(RB));
+
+        // the opcode is decoded to a single flag
+        let mut constraints: Vec<_> = [&add, &mul, &jnz, &halt]
+            .iter()
+            .map(|flag| (*flag).clone() * ((*flag).clone() - one.clone()))
+            .collect();
+        constraints.push(add.clone() + mul.clone() + jnz.clone() + halt.clone() - one.clone());
+        constraints.push(
+            w(OPCODE)
+                - (mul.clone()
+                    + E::literal(F::from(2u64)) * jnz.clone()
+                    + E::literal(F::from(3u64)) * halt.clone()),
+        );
+
+        // the arithmetic instructions write their result, the others leave `rd` unchanged
+        constraints.push(
+            w(RD_AFTER)
+                - (add.clone() * (ra.clone() + rb.clone())
+                    + mul.clone() * ra.clone() * rb
+                    + (jnz.clone() + halt.clone()) * w(RD_BEFORE)),
+        );
+
+        // `taken = ra / ra` is one if `ra` is not zero, and zero otherwise
+        let taken = ra.clone() * w(RA_INV);
+        constraints.push(ra * (one.clone() - taken.clone()));
+        let next = pc.clone() + one.clone();
+        constraints.push(
+            witness_next(PC)
+                - ((add + mul) * next.clone()
+                    + halt * pc
+                    + jnz * (taken.clone() * w(TARGET) + (one - taken) * next)),
+        );
+        constraints
+    }
+}
+
+/// Returns the circuit running `program` for `steps` steps, and its witness builder.
+/// The Poseidon gates of the memories are only satisfied if `params` are the sponge parameters
+/// of the constraint system.
+pub fn circuit<F: PrimeField>(
+    program: &[Instruction],
+    steps: usize,
+    params: &ArithmeticSpongeParams<F>,
+) -> (Vec<CircuitGate<F>>, WitnessBuilder<F>) {
+    let mut b = CircuitBuilder::<F>::new();
+    let init: Vec<_> = (0..REGISTERS).map(|_| b.public_input()).collect();
+    let result: Vec<_> = (0..REGISTERS).map(|_| b.public_input()).collect();
+
+    let words: Vec<F> = program.iter().flat_map(|i| i.encode::<F>()).collect();
+    let words: Vec<_> = words.into_iter().map(|word| b.constant(word)).collect();
+    let mut code = Memory::new(&mut b, &words);
+    let mut registers = Memory::new(&mut b, &init);
+
+    // the rows of the steps are only added once all of them are computed, to be consecutive
+    let mut pc = b.constant(F::zero());
+    let mut rows = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        let row = b.namespace("step", |b| {
+            let base = b.weighted_sum(&[(F::from(INSTRUCTION_WORDS as u64), pc)]);
+            let words: Vec<Var> = (0..INSTRUCTION_WORDS)
+                .map(|k| {
+                    let addr = if k == 0 {
+                        base
+                    } else {
+                        b.add_constant(base, F::from(k as u64))
+                    };
+                    code.read(b, addr)
+                })
+                .collect();
+            let (opcode, d, a, rb_addr, target) =
+                (words[0], words[1], words[2], words[3], words[4]);
+            let ra = registers.read(b, a);
+            let rb = registers.read(b, rb_addr);
+            let rd_before = registers.read(b, d);
+
+            let outputs = b.compute(&[opcode, pc, ra, rb, rd_before, target], 7, |v| {
+                let (opcode, pc, ra, rb, rd_before, target) = (v[0], v[1], v[2], v[3], v[4], v[5]);
+                let opcode = (0..FLAGS.len() as u64)
+                    .position(|i| F::from(i) == opcode)
+                    .unwrap_or(FLAGS.len() - 1);
+                let (rd_after, next) = match opcode {
+                    0 => (ra + rb, pc + F::one()),
+                    1 => (ra * rb, pc + F::one()),
+                    2 if ra.is_zero() => (rd_before, pc + F::one()),
+                    2 => (rd_before, target),
+                    _ => (rd_before, pc),
+                };
+                let mut outputs: Vec<F> = (0..FLAGS.len())
+                    .map(|i| F::from((i == opcode) as u64))
+                    .collect();
+                outputs.extend([ra.inverse().unwrap_or_else(F::zero), rd_after, next]);
+                outputs
+            });
+            registers.write(d, outputs[5]);
+
+            let mut cells = [None; COLUMNS];
+            for (col, var) in [pc, opcode, ra, rb, rd_before, outputs[5], target]
+                .into_iter()
+                .enumerate()
+            {
+                cells[col] = Some(var);
+            }
+            for (col, var) in FLAGS.iter().zip(&outputs[..4]) {
+                cells[*col] = Some(*var);
+            }
+            cells[RA_INV] = Some(outputs[4]);
+            pc = outputs[6];
+            cells
+        });
+        rows.push(row);
+    }
+
+    let result_vars: Vec<_> = (0..REGISTERS)
+        .map(|r| {
+            let addr = b.constant(F::from(r as u64));
+            registers.read(&mut b, addr)
+        })
+        .collect();
+
+    // the first coefficient of a custom row is the id of its gate, 0 for the only one registered
+    for cells in rows {
+        b.gate(GateType::Custom, vec![F::zero()], cells);
+    }
+    let mut last = [None; COLUMNS];
+    last[PC] = Some(pc);
+    b.gate(GateType::Zero, vec![], last);
+
+    code.finalize(&mut b, params);
+    registers.finalize(&mut b, params);
+    for (var, expected) in result_vars.into_iter().zip(result) {
+        b.assert_equal(var, expected);
+    }
+    b.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::{power, run};
+    use kimchi::circuits::{check::check_witness, constraints::ConstraintSystem};
+    use mina_curves::pasta::fp::Fp;
+    use std::sync::Arc;
+
+    /// Checks the run of [power] on `r0 = n` and `r2 = x`, claiming the final registers `result`.
+    fn check(n: u64, x: u64, result: [Fp; REGISTERS]) -> bool {
+        let params = oracle::pasta::fp_kimchi::params();
+        let steps = 3 * n as usize + 1;
+        let (gates, generator) = circuit(&power(), steps, &params);
+        let mut cs = ConstraintSystem::create(gates, vec![], params, 2 * REGISTERS).unwrap();
+        cs.register_custom_gate(Arc::new(Step));
+
+        let init = [Fp::from(n), Fp::one(), Fp::from(x), -Fp::one()];
+        let public: Vec<Fp> = init.into_iter().chain(result).collect();
+        let witness = generator.generate(&public, &[]);
+        check_witness(&witness, &cs, &public).is_ok()
+    }
+
+    #[test]
+    fn test_power_program() {
+        let init = [Fp::from(4u64), Fp::one(), Fp::from(3u64), -Fp::one()];
+        let end = run(&power(), init, 13);
+        assert_eq!(end.pc, 3);
+        assert_eq!(end.registers[1], Fp::from(81u64));
+        assert!(check(4, 3, end.registers));
+
+        // the claimed registers must be the ones the program computes
+        let mut wrong = end.registers;
+        wrong[1] += Fp::one();
+        assert!(!check(4, 3, wrong));
+    }
+}
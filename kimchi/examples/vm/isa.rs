@@ -0,0 +1,97 @@
+//! The instruction set of the machine, and its native interpreter.
+
+use ark_ff::PrimeField;
+
+/// The number of registers of the machine.
+pub const REGISTERS: usize = 4;
+
+/// The number of words encoding an instruction in the program memory.
+pub const INSTRUCTION_WORDS: usize = 5;
+
+/// An instruction over the registers `r0` to `r3`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// `rd = ra + rb`, then go to the next instruction
+    Add { d: usize, a: usize, b: usize },
+    /// `rd = ra * rb`, then go to the next instruction
+    Mul { d: usize, a: usize, b: usize },
+    /// go to the instruction `target` if `ra` is not zero, and to the next instruction otherwise
+    Jnz { a: usize, target: usize },
+    /// stay on this instruction
+    Halt,
+}
+
+impl Instruction {
+    /// Returns the opcode of the instruction, as decoded by the step gate.
+    pub fn opcode(&self) -> usize {
+        match self {
+            Instruction::Add { .. } => 0,
+            Instruction::Mul { .. } => 1,
+            Instruction::Jnz { .. } => 2,
+            Instruction::Halt => 3,
+        }
+    }
+
+    /// Encodes the instruction as the words `[opcode, d, a, b, target]`,
+    /// the fields that the instruction does not use being zero.
+    pub fn encode<F: PrimeField>(&self) -> [F; INSTRUCTION_WORDS] {
+        let (d, a, b, target) = match *self {
+            Instruction::Add { d, a, b } | Instruction::Mul { d, a, b } => (d, a, b, 0),
+            Instruction::Jnz { a, target } => (0, a, 0, target),
+            Instruction::Halt => (0, 0, 0, 0),
+        };
+        [self.opcode(), d, a, b, target].map(|word| F::from(word as u64))
+    }
+}
+
+/// Returns the program raising `r2` to the power `r0` in `r1`, with `r1 = 1` and `r3 = -1`.
+/// On `r0 = n`, it halts after `3 n` steps.
+pub fn power() -> Vec<Instruction> {
+    vec![
+        Instruction::Mul { d: 1, a: 1, b: 2 },
+        Instruction::Add { d: 0, a: 0, b: 3 },
+        Instruction::Jnz { a: 0, target: 0 },
+        Instruction::Halt,
+    ]
+}
+
+/// The state of the machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State<F> {
+    /// the index of the next instruction
+    pub pc: usize,
+    /// the values of the registers
+    pub registers: [F; REGISTERS],
+}
+
+impl<F: PrimeField> State<F> {
+    /// Executes the instruction of `program` at the program counter.
+    pub fn step(&self, program: &[Instruction]) -> Self {
+        let r = &self.registers;
+        let mut next = *self;
+        match program[self.pc] {
+            Instruction::Add { d, a, b } => {
+                next.registers[d] = r[a] + r[b];
+                next.pc += 1;
+            }
+            Instruction::Mul { d, a, b } => {
+                next.registers[d] = r[a] * r[b];
+                next.pc += 1;
+            }
+            Instruction::Jnz { a, target } => {
+                next.pc = if r[a].is_zero() { self.pc + 1 } else { target };
+            }
+            Instruction::Halt => (),
+        }
+        next
+    }
+}
+
+/// Runs `program` for `steps` steps from the registers `registers`.
+pub fn run<F: PrimeField>(
+    program: &[Instruction],
+    registers: [F; REGISTERS],
+    steps: usize,
+) -> State<F> {
+    (0..steps).fold(State { pc: 0, registers }, |state, _| state.step(program))
+}
@@ -0,0 +1,60 @@
+//! A tiny register machine, whose runs are proven in a circuit:
+//! a demonstration of custom gates and of the memory argument of the circuit builder.
+//!
+//! The machine has four registers and the instructions of [isa::Instruction].
+//! The circuit (see [circuit]) runs a program for a fixed number of steps, one row of a custom gate per step,
+//! with the program and the registers in memories whose consistency is checked by the circuit.
+//! The instructions are fetched through the memory argument rather than looked up in a table,
+//! as the lookup argument has no tables that custom gates can use.
+//!
+//! ```console
+//! $ cargo run --release -p kimchi --example vm
+//! ```
+
+mod circuit;
+mod isa;
+
+use ark_ff::One;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use kimchi::pasta::{VestaBaseSponge, VestaScalarSponge};
+use kimchi::prover::ProverProof;
+use kimchi::prover_index::testing::new_index_for_test_with_custom_gates;
+use kimchi::verifier::batch_verify;
+use mina_curves::pasta::{fp::Fp, vesta::Affine};
+use std::sync::Arc;
+
+fn main() {
+    let (n, x) = (5u64, 3u64);
+    let program = isa::power();
+    let steps = 3 * n as usize + 1;
+    let init = [Fp::from(n), Fp::one(), Fp::from(x), -Fp::one()];
+    let end = isa::run(&program, init, steps);
+    println!("{}^{} = {}", x, n, end.registers[1]);
+
+    let params = oracle::pasta::fp_kimchi::params();
+    let (gates, generator) = circuit::circuit(&program, steps, &params);
+    let public: Vec<Fp> = init.into_iter().chain(end.registers).collect();
+    let witness = generator.generate(&public, &[]);
+    let index =
+        new_index_for_test_with_custom_gates(gates, public.len(), vec![Arc::new(circuit::Step)]);
+    println!(
+        "{} steps in a circuit of {} rows",
+        steps, index.cs.domain.d1.size
+    );
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof = ProverProof::create::<VestaBaseSponge, VestaScalarSponge>(
+        &group_map,
+        witness,
+        &index,
+        vec![],
+    )
+    .expect("the run of the program is a valid witness");
+    batch_verify::<Affine, VestaBaseSponge, VestaScalarSponge>(
+        &group_map,
+        &[(&index.verifier_index(), &proof)],
+    )
+    .expect("the proof of the run verifies");
+    println!("the proof of the run verifies");
+}
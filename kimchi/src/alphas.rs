@@ -24,7 +24,7 @@ use crate::circuits::{argument::ArgumentType, gate::GateType};
 use ark_ff::Field;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     iter::{Cloned, Skip, Take},
     ops::Range,
     slice::Iter,
@@ -42,8 +42,9 @@ pub struct Alphas<F> {
     /// The next power of alpha to use
     /// the end result will be [1, alpha^{next_power - 1}]
     next_power: u32,
-    /// The mapping between constraint types and powers of alpha
-    mapping: HashMap<ArgumentType, (u32, u32)>,
+    /// The mapping between constraint types and powers of alpha,
+    /// ordered so that the serialization of the mapping does not depend on the machine
+    mapping: BTreeMap<ArgumentType, (u32, u32)>,
     /// The powers of alpha: 1, alpha, alpha^2, etc.
     /// If set to [Some], you can't register new constraints.
     alphas: Option<Vec<F>>,
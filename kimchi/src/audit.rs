@@ -0,0 +1,354 @@
+//! This module implements the audit of a published [VerifierIndex] against a claimed circuit.
+//!
+//! The owner of the [ProverIndex] opens all the selector, coefficient and permutation polynomials
+//! at a point chosen by the auditor ([ProverIndex::open_selectors]).
+//! The auditor checks the opening against the commitments of the [VerifierIndex]
+//! ([VerifierIndex::verify_selector_opening]),
+//! and compares the evaluations with the ones of the claimed gates ([selector_evaluations]),
+//! which only takes a linear pass over the gates: no FFT and no commitment is needed.
+//! A random point makes it unlikely that two different circuits agree on it.
+//!
+//! The lookup tables and selectors, and the selectors of the custom gates, are not covered by the audit.
+
+use crate::{
+    circuits::{
+        constraints::{Shifts, ZK_ROWS},
+        domains::EvaluationDomains,
+        gate::{CircuitGate, GateType},
+        gates::poseidon::ROUNDS_PER_ROW,
+        wires::{Wire, COLUMNS, PERMUTS},
+    },
+    prover_index::ProverIndex,
+    verifier_index::VerifierIndex,
+};
+use ark_ec::AffineCurve;
+use ark_ff::{FftField, One, PrimeField, SquareRootField, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Evaluations, Radix2EvaluationDomain as D,
+};
+use array_init::array_init;
+use commitment_dlog::{
+    commitment::{CommitmentCurve, PolyComm},
+    evaluation_proof::OpeningProof,
+};
+use o1_utils::ExtendedDensePolynomial;
+use oracle::FqSponge;
+use rand::thread_rng;
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// The opening of the selector polynomials of an index at a point chosen by an auditor.
+///
+/// The polynomials are, in order:
+/// the [PERMUTS] permutation polynomials, the [COLUMNS] coefficient polynomials,
+/// the selectors of the generic, Poseidon, complete addition, variable base scalar multiplication,
+/// endoscaling and endoscaling scalar gates,
+/// and, if the circuit uses them, the 4 selectors of the ChaCha gates,
+/// the [COLUMNS] weight polynomials of the linear combination gates,
+/// the [ROUNDS_PER_ROW] selectors of the partial rounds of the Poseidon gates
+/// and the selector of the boolean gates.
+pub struct SelectorOpening<G: AffineCurve> {
+    /// the evaluation point
+    pub point: Fr<G>,
+    /// the scaling factor combining the polynomials in the opening proof
+    pub polyscale: Fr<G>,
+    /// the chunked evaluations of each polynomial at the point
+    pub evals: Vec<Vec<Fr<G>>>,
+    /// the opening proof
+    pub proof: OpeningProof<G>,
+}
+
+impl<G: AffineCurve> SelectorOpening<G> {
+    /// Returns the evaluation of each polynomial, combining its chunks of `max_poly_size` coefficients.
+    pub fn evaluations(&self, max_poly_size: usize) -> Vec<Fr<G>> {
+        let shift = self.point.pow([max_poly_size as u64]);
+        self.evals
+            .iter()
+            .map(|chunks| DensePolynomial::eval_polynomial(chunks, shift))
+            .collect()
+    }
+}
+
+/// Restricts evaluations over a multiple of `domain` to `domain`, and interpolates them.
+fn interpolate_d1<F: FftField>(evals: &Evaluations<F, D<F>>, domain: D<F>) -> DensePolynomial<F> {
+    let step = evals.domain().size() / domain.size();
+    let evals = evals.evals.iter().step_by(step).copied().collect();
+    Evaluations::from_vec_and_domain(evals, domain).interpolate()
+}
+
+impl<G: CommitmentCurve> ProverIndex<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Opens the selector polynomials of the index at `point`,
+    /// combining them with `polyscale` in the opening proof.
+    /// Both should be chosen at random by the auditor (see [SelectorOpening] for the list of polynomials).
+    pub fn open_selectors<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>>(
+        &self,
+        group_map: &G::Map,
+        point: Fr<G>,
+        polyscale: Fr<G>,
+    ) -> SelectorOpening<G> {
+        let cs = &self.cs;
+        let d1 = cs.domain.d1;
+
+        let mut polys: Vec<DensePolynomial<Fr<G>>> = cs.sigmam.to_vec();
+        polys.extend(cs.coefficients8.iter().map(|e| interpolate_d1(e, d1)));
+        polys.push(cs.genericm.clone());
+        polys.push(cs.psm.clone());
+        polys.extend(
+            [
+                &cs.complete_addl4,
+                &cs.mull8,
+                &cs.emull,
+                &cs.endomul_scalar8,
+            ]
+            .into_iter()
+            .map(|e| interpolate_d1(e, d1)),
+        );
+        if let Some(chacha8) = &cs.chacha8 {
+            polys.extend(chacha8.iter().map(|e| interpolate_d1(e, d1)));
+        }
+        if let Some(linear_combination8) = &cs.linear_combination8 {
+            polys.extend(linear_combination8.iter().map(|e| interpolate_d1(e, d1)));
+        }
+        if let Some(partial_rounds8) = &cs.partial_rounds8 {
+            polys.extend(partial_rounds8.iter().map(|e| interpolate_d1(e, d1)));
+        }
+        if let Some(boolean8) = &cs.boolean8 {
+            polys.push(interpolate_d1(boolean8, d1));
+        }
+
+        let evals = polys
+            .iter()
+            .map(|p| p.eval(point, self.max_poly_size))
+            .collect();
+
+        let non_hiding = |p: &DensePolynomial<Fr<G>>| PolyComm {
+            unshifted: vec![Fr::<G>::zero(); p.eval(point, self.max_poly_size).len()],
+            shifted: None,
+        };
+        let polynomials: Vec<_> = polys.iter().map(|p| (p, None, non_hiding(p))).collect();
+        let proof = self.srs.open(
+            group_map,
+            &polynomials,
+            &[point],
+            polyscale,
+            Fr::<G>::one(),
+            EFqSponge::new(self.fq_sponge_params.clone()),
+            &mut thread_rng(),
+        );
+
+        SelectorOpening {
+            point,
+            polyscale,
+            evals,
+            proof,
+        }
+    }
+}
+
+impl<G: CommitmentCurve> VerifierIndex<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Returns the commitments opened by [ProverIndex::open_selectors], in the same order.
+    fn selector_commitments(&self) -> Vec<&PolyComm<G>> {
+        let mut comms: Vec<_> = self.sigma_comm.iter().collect();
+        comms.extend(&self.coefficients_comm);
+        comms.extend([
+            &self.generic_comm,
+            &self.psm_comm,
+            &self.complete_add_comm,
+            &self.mul_comm,
+            &self.emul_comm,
+            &self.endomul_scalar_comm,
+        ]);
+        if let Some(chacha_comm) = &self.chacha_comm {
+            comms.extend(chacha_comm.iter());
+        }
+        if let Some(linear_combination_comm) = &self.linear_combination_comm {
+            comms.extend(linear_combination_comm.iter());
+        }
+        if let Some(partial_rounds_comm) = &self.partial_rounds_comm {
+            comms.extend(partial_rounds_comm.iter());
+        }
+        comms.extend(&self.boolean_comm);
+        comms
+    }
+
+    /// Verifies that `opening` opens the selector commitments of this index.
+    /// The auditor must also check that the point and the scaling factor of the opening are the ones they chose.
+    pub fn verify_selector_opening<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>>(
+        &self,
+        group_map: &G::Map,
+        opening: &SelectorOpening<G>,
+    ) -> bool {
+        let comms = self.selector_commitments();
+        if comms.len() != opening.evals.len() {
+            return false;
+        }
+        let evaluations = comms
+            .into_iter()
+            .zip(&opening.evals)
+            .map(|(comm, evals)| (comm, vec![evals], None))
+            .collect();
+        let mut batch = vec![(
+            EFqSponge::new(self.fq_sponge_params.clone()),
+            vec![opening.point],
+            opening.polyscale,
+            Fr::<G>::one(),
+            evaluations,
+            &opening.proof,
+        )];
+        self.srs.verify(group_map, &mut batch, &mut thread_rng())
+    }
+}
+
+/// Returns the evaluations at `point` of the selector polynomials of the circuit made of `gates`
+/// (in the order of [SelectorOpening]), as [ConstraintSystem::create](crate::circuits::constraints::ConstraintSystem::create) would compute them,
+/// or [None] if no domain is large enough for the circuit.
+pub fn selector_evaluations<F: FftField + SquareRootField>(
+    gates: &[CircuitGate<F>],
+    point: F,
+) -> Option<Vec<F>> {
+    let domain = EvaluationDomains::<F>::create(gates.len() + ZK_ROWS as usize)?.d1;
+    let n = domain.size();
+    let lagrange = domain.evaluate_all_lagrange_coefficients(point);
+    let shifts = Shifts::new(&domain);
+    let shifts = shifts.shifts();
+    let omegas: Vec<F> = domain.elements().collect();
+
+    // the padding of the constraint system is made of zero gates wired to themselves
+    let wire = |row: usize, col: usize| match gates.get(row) {
+        Some(gate) => gate.wires[col],
+        None => Wire { row, col },
+    };
+    let typ = |row: usize| gates.get(row).map_or(GateType::Zero, |gate| gate.typ);
+    let coeff = |row: usize, col: usize| {
+        gates
+            .get(row)
+            .and_then(|gate| gate.coeffs.get(col))
+            .copied()
+            .unwrap_or_else(F::zero)
+    };
+    let combine = |value: &dyn Fn(usize) -> F| -> F {
+        (0..n)
+            .map(|row| value(row) * lagrange[row])
+            .fold(F::zero(), |acc, x| acc + x)
+    };
+    let selector = |t: GateType| {
+        combine(&|row| {
+            if typ(row) == t {
+                F::one()
+            } else {
+                F::zero()
+            }
+        })
+    };
+
+    let sigma: [F; PERMUTS] = array_init(|col| {
+        combine(&|row| {
+            let w = wire(row, col);
+            shifts[w.col] * omegas[w.row]
+        })
+    });
+    let mut evals = sigma.to_vec();
+    evals.extend((0..COLUMNS).map(|col| combine(&|row| coeff(row, col))));
+    evals.extend(
+        [
+            GateType::Generic,
+            GateType::Poseidon,
+            GateType::CompleteAdd,
+            GateType::VarBaseMul,
+            GateType::EndoMul,
+            GateType::EndoMulScalar,
+        ]
+        .map(selector),
+    );
+
+    let chacha = [
+        GateType::ChaCha0,
+        GateType::ChaCha1,
+        GateType::ChaCha2,
+        GateType::ChaChaFinal,
+    ];
+    if gates.iter().any(|gate| chacha.contains(&gate.typ)) {
+        evals.extend(chacha.map(selector));
+    }
+
+    if gates
+        .iter()
+        .any(|gate| gate.typ == GateType::LinearCombination)
+    {
+        evals.extend((0..COLUMNS).map(|col| {
+            combine(&|row| match typ(row) {
+                GateType::LinearCombination => coeff(row, col),
+                _ => F::zero(),
+            })
+        }));
+    }
+
+    if gates
+        .iter()
+        .any(|gate| gate.partial_rounds().contains(&true))
+    {
+        evals.extend((0..ROUNDS_PER_ROW).map(|round| {
+            combine(&|row| match gates.get(row) {
+                Some(gate) if gate.partial_rounds()[round] => F::one(),
+                _ => F::zero(),
+            })
+        }));
+    }
+
+    if gates.iter().any(|gate| gate.typ == GateType::Boolean) {
+        evals.push(selector(GateType::Boolean));
+    }
+
+    Some(evals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::polynomials::generic::testing::create_circuit;
+    use crate::prover_index::testing::new_index_for_test;
+    use ark_ff::UniformRand;
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{
+        fp::Fp,
+        vesta::{Affine, VestaParameters},
+    };
+    use oracle::{poseidon::PlonkSpongeConstantsKimchi, sponge::DefaultFqSponge};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+
+    #[test]
+    fn test_selector_audit() {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+        let gates = create_circuit::<Fp>(0, 3);
+        let index = new_index_for_test(gates.clone(), 3);
+        let verifier_index = index.verifier_index();
+
+        let (point, polyscale) = (Fp::rand(rng), Fp::rand(rng));
+        let opening = index.open_selectors::<BaseSponge>(&group_map, point, polyscale);
+        assert!(verifier_index.verify_selector_opening::<BaseSponge>(&group_map, &opening));
+
+        let evals = opening.evaluations(verifier_index.max_poly_size);
+        assert_eq!(selector_evaluations(&gates, point), Some(evals.clone()));
+
+        // the verifier index does not match another circuit
+        let mut other = gates;
+        other[4].coeffs[0] += Fp::one();
+        assert_ne!(selector_evaluations(&other, point), Some(evals));
+
+        // and the opening does not verify with tampered evaluations
+        let mut tampered = opening;
+        tampered.evals[PERMUTS][0] += Fp::one();
+        assert!(!verifier_index.verify_selector_opening::<BaseSponge>(&group_map, &tampered));
+    }
+}
@@ -1,26 +1,36 @@
 use crate::{
     circuits::{
+        constraints::{ConstraintSystem, ZK_ROWS},
         gate::CircuitGate,
         polynomials::generic::GenericGateSpec,
         wires::{Wire, COLUMNS},
     },
+    metrics::ProverMetrics,
+    pasta::PastaCurve,
+    plonk_sponge::FrSponge,
     prover::ProverProof,
     prover_index::{testing::new_index_for_test, ProverIndex},
     verifier::batch_verify,
     verifier_index::VerifierIndex,
 };
-use ark_ff::UniformRand;
+use ark_ec::AffineCurve;
+use ark_ff::{One, PrimeField, UniformRand};
 use ark_poly::{univariate::DensePolynomial, UVPolynomial};
 use array_init::array_init;
-use commitment_dlog::commitment::{b_poly_coefficients, ceil_log2, CommitmentCurve};
+use commitment_dlog::{
+    commitment::{b_poly_coefficients, ceil_log2, CommitmentCurve},
+    srs::SRS,
+};
 use groupmap::{BWParameters, GroupMap};
 use mina_curves::pasta::vesta::VestaParameters;
 use mina_curves::pasta::{fp::Fp, vesta::Affine};
+use oracle::FqSponge;
 use oracle::{
     poseidon::PlonkSpongeConstantsKimchi,
     sponge::{DefaultFqSponge, DefaultFrSponge},
 };
 use rand::{rngs::StdRng, SeedableRng};
+use std::{marker::PhantomData, sync::Arc};
 
 type SpongeParams = PlonkSpongeConstantsKimchi;
 type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
@@ -117,6 +127,93 @@ impl BenchmarkCtx {
     }
 }
 
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// A circuit filling a domain of `2^log_size` rows with generic gates over the curve `G`,
+/// proven with the sponges `EFqSponge` and `EFrSponge`,
+/// to measure how the index creation, the prover and the verifier scale with the size of the circuit.
+/// The SRS is as large as the domain, so that the polynomials are committed in a single chunk.
+pub struct ScalingCtx<G: PastaCurve, EFqSponge, EFrSponge> {
+    group_map: G::Map,
+    srs: Arc<SRS<G>>,
+    gates: Vec<CircuitGate<Fr<G>>>,
+    index: ProverIndex<G>,
+    verifier_index: VerifierIndex<G>,
+    sponges: PhantomData<fn() -> (EFqSponge, EFrSponge)>,
+}
+
+impl<G, EFqSponge, EFrSponge> ScalingCtx<G, EFqSponge, EFrSponge>
+where
+    G: PastaCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    /// Creates the circuit of `2^log_size` rows, its SRS and its index.
+    pub fn new(log_size: usize) -> Self {
+        // leave room for the zero-knowledge rows
+        let gates: Vec<_> = (0..(1 << log_size) - ZK_ROWS as usize)
+            .map(|row| {
+                CircuitGate::create_generic_gadget(
+                    Wire::new(row),
+                    GenericGateSpec::Const(Fr::<G>::one()),
+                    None,
+                )
+            })
+            .collect();
+        let cs = Self::constraint_system(&gates);
+        assert_eq!(cs.domain.d1.size, 1 << log_size);
+        let mut srs = SRS::<G>::create(cs.domain.d1.size as usize);
+        srs.add_lagrange_basis(cs.domain.d1);
+        let srs = Arc::new(srs);
+
+        let index = ProverIndex::create(cs, G::fq_sponge_params(), G::endo_q(), srs.clone());
+        let verifier_index = index.verifier_index();
+        Self {
+            group_map: G::Map::setup(),
+            srs,
+            gates,
+            index,
+            verifier_index,
+            sponges: PhantomData,
+        }
+    }
+
+    fn constraint_system(gates: &[CircuitGate<Fr<G>>]) -> ConstraintSystem<Fr<G>> {
+        ConstraintSystem::create(gates.to_vec(), vec![], G::fr_sponge_params(), 0)
+            .expect("the circuit is valid")
+    }
+
+    /// Creates the constraint system and the index of the circuit again.
+    pub fn create_index(&self) -> ProverIndex<G> {
+        ProverIndex::create(
+            Self::constraint_system(&self.gates),
+            G::fq_sponge_params(),
+            G::endo_q(),
+            self.srs.clone(),
+        )
+    }
+
+    /// Produces a proof, along with the durations of the phases of the prover.
+    pub fn create_proof(&self) -> (ProverProof<G>, ProverMetrics) {
+        let witness: [Vec<Fr<G>>; COLUMNS] = array_init(|_| vec![Fr::<G>::one(); self.gates.len()]);
+        ProverProof::create_with_metrics::<EFqSponge, EFrSponge>(
+            &self.group_map,
+            witness,
+            &self.index,
+            vec![],
+        )
+        .expect("the witness is valid")
+    }
+
+    /// Verifies a proof of the circuit.
+    pub fn verify(&self, proof: &ProverProof<G>) {
+        batch_verify::<G, EFqSponge, EFrSponge>(&self.group_map, &[(&self.verifier_index, proof)])
+            .expect("the proof is valid");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Instant;
@@ -140,4 +237,16 @@ mod tests {
         ctx.batch_verification(vec![proof.clone()]);
         println!("proof verified in {}", start.elapsed().as_millis());
     }
+
+    #[test]
+    fn test_scaling_ctx() {
+        use crate::pasta::{PallasBaseSponge, PallasScalarSponge};
+        use mina_curves::pasta::pallas;
+
+        let ctx = ScalingCtx::<pallas::Affine, PallasBaseSponge, PallasScalarSponge>::new(5);
+        assert_eq!(ctx.create_index().cs.domain.d1.size, 32);
+        let (proof, metrics) = ctx.create_proof();
+        assert!(metrics.phase("quotient").is_some());
+        ctx.verify(&proof);
+    }
 }
@@ -11,7 +11,7 @@ use serde::{Deserialize, Serialize};
 use super::gate::GateType;
 
 /// A constraint type represents a polynomial that will be part of the final equation f (the circuit equation)
-#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum ArgumentType {
     /// Gates in the PLONK constraint system.
     /// As gates are mutually exclusive (a single gate is set per row),
@@ -21,6 +21,8 @@ pub enum ArgumentType {
     Permutation,
     /// The lookup argument
     Lookup,
+    /// The boundary constraints of the circuit
+    Boundary,
 }
 
 /// The interface for a minimal argument implementation.
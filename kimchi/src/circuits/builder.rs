@@ -0,0 +1,1049 @@
+//! This module implements a small circuit builder on top of [CircuitGate].
+//!
+//! Gadgets written against the [CircuitBuilder] manipulate [Var]s instead of cells:
+//! the builder takes care of laying out the gates in rows
+//! (packing two generic gates per row),
+//! of deriving the wiring of the permutation argument from the variables that are used in several cells,
+//! and of recording how to compute every variable so that the witness can be filled
+//! once the inputs of the circuit are known.
+//!
+//! Gadgets register the computation of their outputs from their inputs with [CircuitBuilder::compute],
+//! and [WitnessBuilder::solve] runs these computations in order to fill every cell of the witness,
+//! so that the witness never has to be laid out by hand.
+//!
+//! Gadgets can be composed hierarchically by laying out each part in a [CircuitBuilder::namespace].
+//! The builder remembers the path of namespaces (like `merkle/level3/hash`) in which each row was laid out
+//! and each variable was named (see [CircuitBuilder::name]), so that [WitnessBuilder::explain]
+//! can point to the gadget responsible for an unsatisfied constraint.
+//! It also remembers where in the source each gate was added (see [WitnessBuilder::row_locations]):
+//! the methods of the builder that add gates are `#[track_caller]`, so that the location is the one of the gadget
+//! calling them rather than one in the builder.
+//! This metadata is kept out of the gates, and thus out of the index and its commitments.
+
+use crate::circuits::{
+    constraints::GateError,
+    gate::{CircuitGate, GateType},
+    polynomials::generic::{FUSED_COEFFS, FUSED_REGISTER, GENERIC_COEFFS, GENERIC_REGISTERS},
+    public_input::PublicInputLayout,
+    wires::{GateWires, Wire, COLUMNS, PERMUTS},
+};
+use crate::error::WitnessError;
+use ark_ff::FftField;
+use array_init::array_init;
+use std::collections::{HashMap, HashSet};
+use std::panic::Location;
+
+pub mod optimizer;
+
+/// A variable of a circuit under construction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Var(usize);
+
+impl Var {
+    /// The index of the variable in the builder that created it.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// A function computing the values of some variables from the values of others.
+pub type HintFn<F> = Box<dyn Fn(&[F]) -> Vec<F> + Send + Sync>;
+
+/// Where the value of a variable comes from.
+enum Source<F> {
+    /// The i-th public input
+    Public(usize),
+    /// The i-th private input
+    Private(usize),
+    /// A constant
+    Constant(F),
+    /// The output of a [Hint]
+    Computed,
+}
+
+/// Computes `outputs` from `inputs` during witness generation.
+struct Hint<F> {
+    inputs: Vec<Var>,
+    outputs: Vec<Var>,
+    f: HintFn<F>,
+}
+
+/// A row of the circuit, with the variables placed in its cells.
+struct Row<F> {
+    typ: GateType,
+    coeffs: Vec<F>,
+    cells: [Option<Var>; COLUMNS],
+    /// the namespaces in which the gates of the row were added
+    /// (two generic gates sharing a row can come from different namespaces)
+    paths: Vec<String>,
+    /// where in the source the gates of the row were added
+    locations: Vec<&'static Location<'static>>,
+}
+
+/// Builds a circuit (a vector of [CircuitGate]) along with the means to compute its witness.
+pub struct CircuitBuilder<F: FftField> {
+    /// the number of public inputs
+    public: usize,
+    /// the names of the public inputs
+    public_layout: PublicInputLayout,
+    /// the number of private inputs
+    private: usize,
+    /// the rows laid out so far
+    rows: Vec<Row<F>>,
+    /// a generic row with a free second gate, if any
+    half_generic: Option<usize>,
+    /// the source of each variable
+    sources: Vec<Source<F>>,
+    /// union-find forest of the variables that are constrained to be equal
+    parents: Vec<usize>,
+    /// the witness computations, in the order they were registered
+    hints: Vec<Hint<F>>,
+    /// constants that already have a variable
+    constants: HashMap<F, Var>,
+    /// the variables already constrained to be booleans
+    booleans: HashSet<Var>,
+    /// the current stack of namespaces
+    namespaces: Vec<String>,
+    /// the path-qualified names of the variables
+    names: HashMap<Var, String>,
+}
+
+impl<F: FftField> Default for CircuitBuilder<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FftField> CircuitBuilder<F> {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self {
+            public: 0,
+            public_layout: PublicInputLayout::new(),
+            private: 0,
+            rows: vec![],
+            half_generic: None,
+            sources: vec![],
+            parents: vec![],
+            hints: vec![],
+            constants: HashMap::new(),
+            booleans: HashSet::new(),
+            namespaces: vec![],
+            names: HashMap::new(),
+        }
+    }
+
+    /// The number of public inputs declared so far.
+    pub fn public_inputs(&self) -> usize {
+        self.public
+    }
+
+    /// The number of private inputs declared so far.
+    pub fn private_inputs(&self) -> usize {
+        self.private
+    }
+
+    /// The number of rows laid out so far.
+    pub fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Lays out `f` in the namespace `name`, nested in the current one.
+    /// Namespaces only label the rows and the variables, and do not change the circuit.
+    pub fn namespace<T>(&mut self, name: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.namespaces.push(name.to_string());
+        let res = f(self);
+        self.namespaces.pop();
+        res
+    }
+
+    /// The path of the current namespace, its components separated by `/`
+    /// (empty at the root).
+    pub fn path(&self) -> String {
+        self.namespaces.join("/")
+    }
+
+    fn qualify(&self, name: &str) -> String {
+        if self.namespaces.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{name}", self.path())
+        }
+    }
+
+    /// Names `var` in the current namespace, replacing any previous name.
+    pub fn name(&mut self, var: Var, name: &str) {
+        let name = self.qualify(name);
+        self.names.insert(var, name);
+    }
+
+    /// The path-qualified name of `var`, if it was named.
+    pub fn var_name(&self, var: Var) -> Option<&str> {
+        self.names.get(&var).map(String::as_str)
+    }
+
+    #[track_caller]
+    fn new_row(&mut self, typ: GateType, coeffs: Vec<F>, cells: [Option<Var>; COLUMNS]) {
+        let paths = vec![self.path()];
+        self.rows.push(Row {
+            typ,
+            coeffs,
+            cells,
+            paths,
+            locations: vec![Location::caller()],
+        });
+    }
+
+    fn new_var(&mut self, source: Source<F>) -> Var {
+        let var = Var(self.sources.len());
+        self.sources.push(source);
+        self.parents.push(var.0);
+        var
+    }
+
+    fn find(&self, var: Var) -> usize {
+        let mut idx = var.0;
+        while self.parents[idx] != idx {
+            idx = self.parents[idx];
+        }
+        idx
+    }
+
+    /// Declares a new public input.
+    /// Public inputs occupy the first rows of the circuit,
+    /// they must thus be declared before any other gate is added.
+    #[track_caller]
+    pub fn public_input(&mut self) -> Var {
+        self.declare_public_input(None)
+    }
+
+    /// Declares a new public input named `name` (see [PublicInputLayout]).
+    ///
+    /// # Panics
+    ///
+    /// Will panic if another public input has the same name.
+    #[track_caller]
+    pub fn named_public_input(&mut self, name: &str) -> Var {
+        self.declare_public_input(Some(name))
+    }
+
+    #[track_caller]
+    fn declare_public_input(&mut self, name: Option<&str>) -> Var {
+        assert_eq!(
+            self.rows.len(),
+            self.public,
+            "public inputs must be declared before any other gate"
+        );
+        self.public_layout
+            .push(name)
+            .unwrap_or_else(|e| panic!("{e}"));
+        let var = self.new_var(Source::Public(self.public));
+        self.public += 1;
+
+        let mut coeffs = vec![F::zero(); GENERIC_COEFFS * 2];
+        coeffs[0] = F::one();
+        let mut cells = [None; COLUMNS];
+        cells[0] = Some(var);
+        self.new_row(GateType::Generic, coeffs, cells);
+        if let Some(name) = name {
+            self.name(var, name);
+        }
+        self.half_generic = Some(self.rows.len() - 1);
+
+        var
+    }
+
+    /// Declares a new private input.
+    pub fn private_input(&mut self) -> Var {
+        let var = self.new_var(Source::Private(self.private));
+        self.private += 1;
+        var
+    }
+
+    /// Returns a variable constrained to be equal to `value`.
+    /// Constants are deduplicated, so that each one only costs a single generic gate.
+    #[track_caller]
+    pub fn constant(&mut self, value: F) -> Var {
+        if let Some(var) = self.constants.get(&value) {
+            return *var;
+        }
+        let var = self.new_var(Source::Constant(value));
+        self.generic(
+            [F::one(), F::zero(), F::zero(), F::zero(), -value],
+            [Some(var), None, None],
+        );
+        self.constants.insert(value, var);
+        if value.is_zero() || value.is_one() {
+            self.booleans.insert(var);
+        }
+        var
+    }
+
+    /// Records that `var` is constrained to be a boolean,
+    /// returning false if it already was (so that the constraint is only added once).
+    pub(crate) fn mark_boolean(&mut self, var: Var) -> bool {
+        self.booleans.insert(var)
+    }
+
+    /// Registers a witness computation producing `outputs` new variables from the values of `inputs`.
+    /// The returned variables are unconstrained: it is up to the caller to add the gates that constrain them.
+    pub fn compute(
+        &mut self,
+        inputs: &[Var],
+        outputs: usize,
+        f: impl Fn(&[F]) -> Vec<F> + Send + Sync + 'static,
+    ) -> Vec<Var> {
+        let outputs: Vec<_> = (0..outputs)
+            .map(|_| self.new_var(Source::Computed))
+            .collect();
+        self.hints.push(Hint {
+            inputs: inputs.to_vec(),
+            outputs: outputs.clone(),
+            f: Box::new(f),
+        });
+        outputs
+    }
+
+    /// Same as [Self::compute], for a single output.
+    pub fn compute_one(
+        &mut self,
+        inputs: &[Var],
+        f: impl Fn(&[F]) -> F + Send + Sync + 'static,
+    ) -> Var {
+        self.compute(inputs, 1, move |values| vec![f(values)])[0]
+    }
+
+    /// Adds a single generic gate enforcing
+    /// `c[0] * l + c[1] * r + c[2] * o + c[3] * l * r + c[4] = 0`
+    /// on the variables `[l, r, o]` (unused registers can be left empty).
+    /// Two consecutive generic gates share the same row.
+    #[track_caller]
+    pub fn generic(&mut self, coeffs: [F; GENERIC_COEFFS], vars: [Option<Var>; GENERIC_REGISTERS]) {
+        let (row, coeffs_offset, register_offset) = match self.half_generic.take() {
+            Some(row) => (row, GENERIC_COEFFS, GENERIC_REGISTERS),
+            None => {
+                self.new_row(
+                    GateType::Generic,
+                    vec![F::zero(); GENERIC_COEFFS * 2],
+                    [None; COLUMNS],
+                );
+                let row = self.rows.len() - 1;
+                self.half_generic = Some(row);
+                (row, 0, 0)
+            }
+        };
+
+        let path = self.path();
+        let row = &mut self.rows[row];
+        if !row.paths.contains(&path) {
+            row.paths.push(path);
+        }
+        let location = Location::caller();
+        if !row.locations.contains(&location) {
+            row.locations.push(location);
+        }
+        row.coeffs[coeffs_offset..coeffs_offset + GENERIC_COEFFS].copy_from_slice(&coeffs);
+        row.cells[register_offset..register_offset + GENERIC_REGISTERS].copy_from_slice(&vars);
+    }
+
+    /// Adds a full row containing a gate of type `typ`,
+    /// with the given variables placed in its cells.
+    /// Only the first [PERMUTS] cells can hold variables that are used elsewhere in the circuit.
+    #[track_caller]
+    pub fn gate(&mut self, typ: GateType, coeffs: Vec<F>, cells: [Option<Var>; COLUMNS]) {
+        self.half_generic = None;
+        self.new_row(typ, coeffs, cells);
+    }
+
+    /// Constrains two variables to be equal.
+    /// This is enforced by the permutation argument, and is thus free.
+    pub fn assert_equal(&mut self, a: Var, b: Var) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parents[b] = a;
+        }
+    }
+
+    /// Returns `a + b`.
+    #[track_caller]
+    pub fn add(&mut self, a: Var, b: Var) -> Var {
+        self.linear_combination(F::one(), a, F::one(), b)
+    }
+
+    /// Returns `a - b`.
+    #[track_caller]
+    pub fn sub(&mut self, a: Var, b: Var) -> Var {
+        self.linear_combination(F::one(), a, -F::one(), b)
+    }
+
+    /// Returns `ca * a + cb * b`.
+    #[track_caller]
+    pub fn linear_combination(&mut self, ca: F, a: Var, cb: F, b: Var) -> Var {
+        let o = self.compute_one(&[a, b], move |v| ca * v[0] + cb * v[1]);
+        self.generic(
+            [ca, cb, -F::one(), F::zero(), F::zero()],
+            [Some(a), Some(b), Some(o)],
+        );
+        o
+    }
+
+    /// Returns `sum_i c_i * x_i` for the `terms` `(c_i, x_i)`, with [linear combination](crate::circuits::polynomials::linear_combination) gates.
+    /// Each row adds as many terms as fit in its wired cells to the sum of the previous row,
+    /// so that the number of rows is about a fifth of the number of terms, against half with generic gates.
+    #[track_caller]
+    pub fn weighted_sum(&mut self, terms: &[(F, Var)]) -> Var {
+        let mut terms = terms;
+        let mut sum: Option<Var> = None;
+        loop {
+            // the last wired cell holds the sum of the row
+            let inputs: Vec<(F, Var)> = sum
+                .map(|sum| (F::one(), sum))
+                .into_iter()
+                .chain(terms.iter().copied())
+                .take(PERMUTS - 1)
+                .collect();
+            terms = &terms[inputs.len() - usize::from(sum.is_some())..];
+
+            let (weights, vars): (Vec<F>, Vec<Var>) = inputs.into_iter().unzip();
+            let out = {
+                let weights = weights.clone();
+                self.compute_one(&vars, move |v| {
+                    v.iter()
+                        .zip(&weights)
+                        .fold(F::zero(), |acc, (x, c)| acc + *c * x)
+                })
+            };
+
+            let mut coeffs = vec![F::zero(); COLUMNS];
+            let mut cells = [None; COLUMNS];
+            for (col, (c, var)) in weights.into_iter().zip(vars).enumerate() {
+                coeffs[col] = c;
+                cells[col] = Some(var);
+            }
+            coeffs[PERMUTS - 1] = -F::one();
+            cells[PERMUTS - 1] = Some(out);
+            self.gate(GateType::LinearCombination, coeffs, cells);
+
+            if terms.is_empty() {
+                return out;
+            }
+            sum = Some(out);
+        }
+    }
+
+    /// Returns `a + c` for a constant `c`.
+    #[track_caller]
+    pub fn add_constant(&mut self, a: Var, c: F) -> Var {
+        let o = self.compute_one(&[a], move |v| v[0] + c);
+        self.generic(
+            [F::one(), F::zero(), -F::one(), F::zero(), c],
+            [Some(a), None, Some(o)],
+        );
+        o
+    }
+
+    /// Returns `a * b`.
+    #[track_caller]
+    pub fn mul(&mut self, a: Var, b: Var) -> Var {
+        let o = self.compute_one(&[a, b], |v| v[0] * v[1]);
+        self.generic(
+            [F::zero(), F::zero(), -F::one(), F::one(), F::zero()],
+            [Some(a), Some(b), Some(o)],
+        );
+        o
+    }
+
+    /// Returns `(ca * a + cb * b + c) * m`, with a single generic gate and its fused product
+    /// (see [generic](crate::circuits::polynomials::generic)).
+    /// The fused product is only available to the first generic gate of a row:
+    /// the gate starts a new row, whose second half is left to the next generic gate.
+    #[track_caller]
+    pub fn affine_mul(&mut self, ca: F, a: Var, cb: F, b: Var, c: F, m: Var) -> Var {
+        let o = self.compute_one(&[a, b, m], move |v| (ca * v[0] + cb * v[1] + c) * v[2]);
+        let mut coeffs = vec![F::zero(); 2 * GENERIC_COEFFS + FUSED_COEFFS];
+        coeffs[2] = -F::one();
+        coeffs[2 * GENERIC_COEFFS..].copy_from_slice(&[ca, cb, c]);
+        let mut cells = [None; COLUMNS];
+        cells[..GENERIC_REGISTERS].copy_from_slice(&[Some(a), Some(b), Some(o)]);
+        cells[FUSED_REGISTER] = Some(m);
+        self.gate(GateType::Generic, coeffs, cells);
+        self.half_generic = Some(self.rows.len() - 1);
+        o
+    }
+
+    /// Constrains `a` to be zero.
+    #[track_caller]
+    pub fn assert_zero(&mut self, a: Var) {
+        self.generic(
+            [F::one(), F::zero(), F::zero(), F::zero(), F::zero()],
+            [Some(a), None, None],
+        );
+    }
+
+    /// Lays out `n` instances of a gadget one after the other,
+    /// calling `f` with the index of each instance, and returns their outputs.
+    /// Since the instances are laid out contiguously,
+    /// their generic gates are packed together.
+    pub fn repeat<T>(&mut self, n: usize, mut f: impl FnMut(&mut Self, usize) -> T) -> Vec<T> {
+        (0..n).map(|i| f(self, i)).collect()
+    }
+
+    /// Unrolls a loop of exactly `n` iterations,
+    /// threading a state from one iteration to the next.
+    pub fn unroll<S>(
+        &mut self,
+        n: usize,
+        init: S,
+        mut body: impl FnMut(&mut Self, usize, S) -> S,
+    ) -> S {
+        (0..n).fold(init, |state, i| body(self, i, state))
+    }
+
+    /// Unrolls a loop of at most `max` iterations, that runs `body` while `cond` holds.
+    /// `cond` must return a boolean variable.
+    ///
+    /// All `max` iterations are laid out, and the iterations that run after the condition
+    /// first failed leave the state unchanged.
+    /// Iterations past `max` are silently dropped:
+    /// to enforce that the loop terminated,
+    /// constrain `cond` to be false on the returned state.
+    pub fn bounded_while(
+        &mut self,
+        max: usize,
+        init: Vec<Var>,
+        mut cond: impl FnMut(&mut Self, &[Var]) -> Var,
+        mut body: impl FnMut(&mut Self, usize, &[Var]) -> Vec<Var>,
+    ) -> Vec<Var> {
+        let mut active = self.constant(F::one());
+        let mut state = init;
+        for i in 0..max {
+            let holds = cond(self, &state);
+            active = self.and(active, holds);
+            let next = body(self, i, &state);
+            assert_eq!(
+                next.len(),
+                state.len(),
+                "the loop changed the size of its state"
+            );
+            state = next
+                .iter()
+                .zip(&state)
+                .map(|(next, current)| self.select(active, *next, *current))
+                .collect();
+        }
+        state
+    }
+
+    /// Lays out the circuit, returning its gates and a [WitnessBuilder] to fill its witness.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a variable placed in a column that is not part of the permutation
+    /// is also used in another cell, as this equality could not be enforced.
+    pub fn build(self) -> (Vec<CircuitGate<F>>, WitnessBuilder<F>) {
+        // gather the cells of each class of equal variables
+        let mut classes: HashMap<usize, Vec<Wire>> = HashMap::new();
+        for (row, r) in self.rows.iter().enumerate() {
+            for (col, var) in r.cells.iter().enumerate() {
+                if let Some(var) = var {
+                    classes
+                        .entry(self.find(*var))
+                        .or_default()
+                        .push(Wire { row, col });
+                }
+            }
+        }
+
+        // each class forms a cycle in the permutation
+        let mut wires: Vec<GateWires> = (0..self.rows.len()).map(Wire::new).collect();
+        for cells in classes.values() {
+            if cells.len() < 2 {
+                continue;
+            }
+            if let Some(cell) = cells.iter().find(|cell| cell.col >= PERMUTS) {
+                let row = &self.rows[cell.row];
+                panic!(
+                    "the variable in row {} column {} (in `{}`, added at {}) is copied, but that column is not wired",
+                    cell.row,
+                    cell.col,
+                    row.paths.join("`, `"),
+                    join_locations(&row.locations)
+                );
+            }
+            for (i, cell) in cells.iter().enumerate() {
+                wires[cell.row][cell.col] = cells[(i + 1) % cells.len()];
+            }
+        }
+
+        let mut gates = Vec::with_capacity(self.rows.len());
+        let mut layout = Vec::with_capacity(self.rows.len());
+        let mut paths = Vec::with_capacity(self.rows.len());
+        let mut locations = Vec::with_capacity(self.rows.len());
+        for (row, wires) in self.rows.into_iter().zip(wires) {
+            gates.push(CircuitGate {
+                typ: row.typ,
+                wires,
+                coeffs: row.coeffs,
+            });
+            layout.push(row.cells);
+            paths.push(row.paths);
+            locations.push(row.locations);
+        }
+
+        let witness_builder = WitnessBuilder {
+            public: self.public,
+            public_layout: self.public_layout,
+            private: self.private,
+            sources: self.sources,
+            hints: self.hints,
+            layout,
+            paths,
+            locations,
+            names: self.names,
+        };
+
+        (gates, witness_builder)
+    }
+}
+
+/// Solves the witness of a circuit created by a [CircuitBuilder].
+pub struct WitnessBuilder<F> {
+    public: usize,
+    public_layout: PublicInputLayout,
+    private: usize,
+    sources: Vec<Source<F>>,
+    hints: Vec<Hint<F>>,
+    layout: Vec<[Option<Var>; COLUMNS]>,
+    paths: Vec<Vec<String>>,
+    locations: Vec<Vec<&'static Location<'static>>>,
+    names: HashMap<Var, String>,
+}
+
+fn join_locations(locations: &[&'static Location<'static>]) -> String {
+    locations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl<F: FftField> WitnessBuilder<F> {
+    /// The number of public inputs of the circuit.
+    pub fn public_inputs(&self) -> usize {
+        self.public
+    }
+
+    /// The number of private inputs of the circuit.
+    pub fn private_inputs(&self) -> usize {
+        self.private
+    }
+
+    /// The names of the public inputs of the circuit,
+    /// to embed in its index with [ProverIndex::set_public_layout](crate::prover_index::ProverIndex::set_public_layout).
+    pub fn public_layout(&self) -> &PublicInputLayout {
+        &self.public_layout
+    }
+
+    /// Computes the value of every variable of the circuit.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the values cannot be computed (see [Self::solve_values]).
+    pub fn values(&self, public: &[F], private: &[F]) -> Vec<F> {
+        self.solve_values(public, private)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Computes the value of every variable of the circuit,
+    /// running the witness computations of the gadgets in the order they were registered.
+    pub fn solve_values(&self, public: &[F], private: &[F]) -> Result<Vec<F>, WitnessError> {
+        if public.len() != self.public {
+            return Err(WitnessError::PublicInputs {
+                expected: self.public,
+                got: public.len(),
+            });
+        }
+        if private.len() != self.private {
+            return Err(WitnessError::PrivateInputs {
+                expected: self.private,
+                got: private.len(),
+            });
+        }
+
+        let mut values: Vec<Option<F>> = self
+            .sources
+            .iter()
+            .map(|source| match source {
+                Source::Public(i) => Some(public[*i]),
+                Source::Private(i) => Some(private[*i]),
+                Source::Constant(c) => Some(*c),
+                Source::Computed => None,
+            })
+            .collect();
+
+        for (i, hint) in self.hints.iter().enumerate() {
+            // the inputs of a hint are created before it, and thus already computed
+            let inputs: Vec<F> = hint
+                .inputs
+                .iter()
+                .map(|var| values[var.0].expect("hint input computed after the hint"))
+                .collect();
+            let outputs = (hint.f)(&inputs);
+            if outputs.len() != hint.outputs.len() {
+                return Err(WitnessError::HintOutputs {
+                    hint: i,
+                    expected: hint.outputs.len(),
+                    got: outputs.len(),
+                });
+            }
+            for (var, value) in hint.outputs.iter().zip(outputs) {
+                values[var.0] = Some(value);
+            }
+        }
+
+        // every variable is an input, a constant, or the output of a hint
+        Ok(values
+            .into_iter()
+            .map(|value| value.expect("variable never computed"))
+            .collect())
+    }
+
+    /// Computes the full witness of the circuit from its public and private inputs.
+    /// Cells that do not hold a variable are set to zero.
+    pub fn solve(&self, public: &[F], private: &[F]) -> Result<[Vec<F>; COLUMNS], WitnessError> {
+        let values = self.solve_values(public, private)?;
+        Ok(self.layout_values(&values))
+    }
+
+    /// Lays out the `values` of the variables in the cells of the witness, as [Self::solve] does.
+    /// The values computed by [Self::values] can be tampered with beforehand,
+    /// to check that the circuit rejects them.
+    pub fn layout_values(&self, values: &[F]) -> [Vec<F>; COLUMNS] {
+        array_init(|col| {
+            self.layout
+                .iter()
+                .map(|cells| cells[col].map(|var| values[var.0]).unwrap_or_else(F::zero))
+                .collect()
+        })
+    }
+
+    /// Same as [Self::solve], with the public inputs given by name (see [PublicInputLayout::assemble]).
+    pub fn solve_named(
+        &self,
+        public: &HashMap<&str, F>,
+        private: &[F],
+    ) -> Result<[Vec<F>; COLUMNS], WitnessError> {
+        let public = self.public_layout.assemble(public)?;
+        self.solve(&public, private)
+    }
+
+    /// The namespaces in which the gates of `row` were laid out
+    /// (the root namespace is the empty path).
+    pub fn row_paths(&self, row: usize) -> &[String] {
+        self.paths.get(row).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Where in the source the gates of `row` were added,
+    /// as the locations of the calls to the builder (or to the `#[track_caller]` gadgets calling it).
+    pub fn row_locations(&self, row: usize) -> &[&'static Location<'static>] {
+        self.locations
+            .get(row)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The path-qualified name of the variable placed in the cell `wire`, if it was named.
+    pub fn cell_name(&self, wire: Wire) -> Option<&str> {
+        let var = self.layout.get(wire.row)?[wire.col]?;
+        self.names.get(&var).map(String::as_str)
+    }
+
+    fn describe_row(&self, row: usize) -> String {
+        match self.row_paths(row) {
+            [] => format!("row {row}"),
+            [path] if path.is_empty() => format!("row {row}"),
+            paths => format!("{} (row {row})", paths.join(", ")),
+        }
+    }
+
+    fn describe_cell(&self, wire: Wire) -> String {
+        match self.cell_name(wire) {
+            Some(name) => format!(
+                "`{name}` ({}, column {})",
+                self.describe_row(wire.row),
+                wire.col
+            ),
+            None => format!("{}, column {}", self.describe_row(wire.row), wire.col),
+        }
+    }
+
+    /// Describes an error returned when checking the witness of the circuit
+    /// (for example by [check_witness](crate::circuits::check::check_witness)),
+    /// with the namespaces of the rows and the names of the cells involved,
+    /// like `merkle/level3/hash/round2 (row 42): constraint 1 of the Poseidon gate is not satisfied`,
+    /// followed by where the gates of the row were added (see [Self::row_locations]).
+    pub fn explain(&self, err: &GateError) -> String {
+        match err {
+            GateError::DisconnectedWires(a, b) => format!(
+                "{} and {} are wired, but hold different values",
+                self.describe_cell(*a),
+                self.describe_cell(*b)
+            ),
+            GateError::IncorrectPublic(row) => {
+                format!("{}: incorrect public input", self.describe_row(*row))
+            }
+            GateError::Custom { row, err } => match self.row_locations(*row) {
+                [] => format!("{}: {err}", self.describe_row(*row)),
+                locations => format!(
+                    "{}: {err} (added at {})",
+                    self.describe_row(*row),
+                    join_locations(locations)
+                ),
+            },
+            GateError::Boundary(cell) => format!(
+                "{} does not hold the value it is pinned to",
+                self.describe_cell(*cell)
+            ),
+        }
+    }
+
+    /// Same as [Self::solve].
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the witness cannot be computed.
+    pub fn generate(&self, public: &[F], private: &[F]) -> [Vec<F>; COLUMNS] {
+        self.solve(public, private)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Computes the witness of a circuit without private inputs,
+    /// which proves a computation that the verifier could redo from the public inputs alone.
+    /// The proof is still created in zero-knowledge: the blinders only hide the intermediate values.
+    pub fn generate_public(&self, public: &[F]) -> [Vec<F>; COLUMNS] {
+        assert_eq!(
+            self.private, 0,
+            "the circuit has private inputs, use `generate` instead"
+        );
+        self.generate(public, &[])
+    }
+}
+
+pub mod testing {
+    use super::*;
+
+    /// Checks that a witness satisfies the generic and boolean gates and the wiring of a circuit,
+    /// without having to create its constraint system.
+    pub fn verify_generic_circuit<F: FftField>(
+        gates: &[CircuitGate<F>],
+        witness: &[Vec<F>; COLUMNS],
+        public: &[F],
+    ) -> Result<(), String> {
+        for (row, gate) in gates.iter().enumerate() {
+            for col in 0..PERMUTS {
+                let wire = gate.wires[col];
+                if witness[col][row] != witness[wire.col][wire.row] {
+                    return Err(format!("row {row} column {col} is disconnected"));
+                }
+            }
+            match gate.typ {
+                GateType::Generic => gate.verify_generic(row, witness, public),
+                GateType::Boolean => gate.verify_boolean(row, witness),
+                _ => Ok(()),
+            }
+            .map_err(|e| format!("row {row}: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{testing::verify_generic_circuit, *};
+    use mina_curves::pasta::fp::Fp;
+
+    #[test]
+    fn builder_wires_and_fills_witness() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.public_input();
+        let y = b.private_input();
+        let x2 = b.mul(x, x);
+        let s = b.add(x2, y);
+        let out = b.add_constant(s, 5u64.into());
+        let expected = b.constant(30u64.into());
+        b.assert_equal(out, expected);
+
+        let (gates, generator) = b.build();
+        let public = [4u64.into()];
+        let witness = generator.generate(&public, &[9u64.into()]);
+        verify_generic_circuit(&gates, &witness, &public).unwrap();
+
+        let witness = generator.generate(&public, &[8u64.into()]);
+        assert!(verify_generic_circuit(&gates, &witness, &public).is_err());
+    }
+
+    #[test]
+    fn affine_mul_fills_half_a_row() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        let y = b.private_input();
+        let m = b.private_input();
+        let out = b.affine_mul(2u64.into(), x, 3u64.into(), y, 1u64.into(), m);
+        assert_eq!(b.rows(), 1);
+        // the constant takes the second half of the row,
+        // where the affine term and its product would have taken a whole row without the fused product
+        let expected = b.constant(90u64.into());
+        assert_eq!(b.rows(), 1);
+        b.assert_equal(out, expected);
+
+        // (2 * 4 + 3 * 3 + 1) * 5
+        let (gates, generator) = b.build();
+        let witness = generator.generate(&[], &[4u64.into(), 3u64.into(), 5u64.into()]);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+        let witness = generator.generate(&[], &[4u64.into(), 3u64.into(), 6u64.into()]);
+        assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+    }
+
+    #[test]
+    fn witness_builder_solves_named_inputs() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.named_public_input("x");
+        let y = b.private_input();
+        let xy = b.mul(x, y);
+        let expected = b.constant(6u64.into());
+        b.assert_equal(xy, expected);
+
+        let (gates, witness_builder) = b.build();
+        let public: HashMap<_, _> = [("x", Fp::from(2u64))].into_iter().collect();
+        let witness = witness_builder
+            .solve_named(&public, &[Fp::from(3u64)])
+            .unwrap();
+        assert_eq!(
+            witness,
+            witness_builder
+                .solve(&[Fp::from(2u64)], &[Fp::from(3u64)])
+                .unwrap()
+        );
+        verify_generic_circuit(&gates, &witness, &[Fp::from(2u64)]).unwrap();
+
+        assert_eq!(
+            witness_builder.solve(&[], &[Fp::from(3u64)]),
+            Err(WitnessError::PublicInputs {
+                expected: 1,
+                got: 0
+            })
+        );
+        let unknown: HashMap<_, _> = [("z", Fp::from(2u64))].into_iter().collect();
+        assert!(matches!(
+            witness_builder.solve_named(&unknown, &[Fp::from(3u64)]),
+            Err(WitnessError::PublicInput(_))
+        ));
+
+        // a witness computation returning the wrong number of values
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        let broken = b.compute(&[x], 2, |v| vec![v[0]]);
+        b.assert_zero(broken[0]);
+        let (_, witness_builder) = b.build();
+        assert_eq!(
+            witness_builder.solve(&[], &[Fp::from(3u64)]),
+            Err(WitnessError::HintOutputs {
+                hint: 0,
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn namespaces_label_rows_and_variables() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        b.name(x, "x");
+        let y = b.namespace("merkle", |b| {
+            b.namespace("level3", |b| {
+                assert_eq!(b.path(), "merkle/level3");
+                let y = b.mul(x, x);
+                b.name(y, "y");
+                y
+            })
+        });
+        // packed in the same row as the multiplication
+        b.namespace("check", |b| b.assert_zero(y));
+        assert_eq!(b.path(), "");
+        assert_eq!(b.var_name(x), Some("x"));
+        assert_eq!(b.var_name(y), Some("merkle/level3/y"));
+
+        let (gates, witness_builder) = b.build();
+        assert_eq!(witness_builder.row_paths(0), ["merkle/level3", "check"]);
+        assert_eq!(
+            witness_builder.cell_name(Wire { row: 0, col: 2 }),
+            Some("merkle/level3/y")
+        );
+
+        let index = crate::prover_index::testing::new_index_for_test(gates, 0);
+        let witness = witness_builder.solve(&[], &[Fp::from(2u64)]).unwrap();
+        let err = crate::circuits::check::check_witness(&witness, &index.cs, &[]).unwrap_err();
+        assert!(witness_builder
+            .explain(&err)
+            .starts_with("merkle/level3, check (row 0): "));
+    }
+
+    #[test]
+    fn rows_record_where_their_gates_were_added() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        let line = line!();
+        let y = b.mul(x, x);
+        b.assert_zero(y);
+        let (gates, witness_builder) = b.build();
+
+        let locations = witness_builder.row_locations(0);
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].file(), file!());
+        assert_eq!(locations[0].line(), line + 1);
+        assert_eq!(locations[1].line(), line + 2);
+
+        let index = crate::prover_index::testing::new_index_for_test(gates, 0);
+        let witness = witness_builder.solve(&[], &[Fp::from(2u64)]).unwrap();
+        let err = crate::circuits::check::check_witness(&witness, &index.cs, &[]).unwrap_err();
+        assert!(witness_builder
+            .explain(&err)
+            .ends_with(&format!("(added at {}, {})", locations[0], locations[1])));
+    }
+
+    #[test]
+    fn builder_loops() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+
+        // x, x^2, x^3
+        let powers = b.repeat(3, |b, i| (0..i).fold(x, |acc, _| b.mul(acc, x)));
+
+        // 1 + 1 + 1 + 1
+        let one = b.constant(Fp::from(1u64));
+        let four = b.unroll(3, one, |b, _, acc| b.add(acc, one));
+
+        // count while the private flags are set, for at most 4 iterations
+        let flags: Vec<_> = (0..4).map(|_| b.private_input()).collect();
+        let mut flags = flags.into_iter();
+        let zero = b.constant(Fp::from(0u64));
+        let count = b.bounded_while(
+            4,
+            vec![zero],
+            |_, _| flags.next().unwrap(),
+            |b, _, state| vec![b.add(state[0], one)],
+        );
+
+        let (gates, generator) = b.build();
+        let private: Vec<Fp> = [2u64, 1, 1, 0, 1].iter().map(|x| (*x).into()).collect();
+        let witness = generator.generate(&[], &private);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+        let values = generator.values(&[], &private);
+        let powers: Vec<_> = powers.iter().map(|p| values[p.index()]).collect();
+        assert_eq!(powers, vec![2u64.into(), 4u64.into(), 8u64.into()]);
+        assert_eq!(values[four.index()], 4u64.into());
+        assert_eq!(values[count[0].index()], 2u64.into());
+    }
+}
@@ -0,0 +1,511 @@
+//! This module implements [CircuitBuilder::optimize], which simplifies the generic gates of a circuit before it is built.
+//!
+//! A generic gate *defines* a variable when it determines its value from the other cells of the gate
+//! (its output, like the gates of [CircuitBuilder::mul] or [CircuitBuilder::add],
+//! or the single variable of a gate like the ones of [CircuitBuilder::constant]).
+//! Such a gate can be satisfied whatever the values of its other cells,
+//! so that it can be removed without changing what the rest of the circuit accepts when:
+//!
+//! - its variable is not used anywhere else (and is not an input of the circuit),
+//! - its inputs are constants, in which case its variable is also a constant,
+//!   equal to an existing constant or constrained by a constant gate,
+//! - an identical gate defines a variable from the same inputs, in which case both variables are wired together.
+//!
+//! These simplifications are applied until none applies anymore, and the remaining generic gates are packed again,
+//! two per row, without moving them across the other gates.
+//! The rows of generic gates with a fused product (see [CircuitBuilder::affine_mul]) are left untouched, like the other gates.
+//! The witness computations are left untouched, so that the witness of the optimized circuit is solved as before.
+
+use super::{CircuitBuilder, Row, Source, Var};
+use crate::circuits::{
+    gate::GateType,
+    polynomials::generic::{GENERIC_COEFFS, GENERIC_REGISTERS},
+    wires::COLUMNS,
+};
+use ark_ff::FftField;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic::Location;
+
+/// How [CircuitBuilder::optimize] changed a circuit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptimizationReport {
+    /// The number of rows before the optimization
+    pub rows_before: usize,
+    /// The number of rows after the optimization
+    pub rows_after: usize,
+    /// The number of generic gates removed because the variable they defined was not used
+    pub dead: usize,
+    /// The number of generic gates whose inputs were all constants
+    pub folded: usize,
+    /// The number of generic gates removed because an identical gate defined a variable from the same inputs
+    pub merged: usize,
+    /// The number of generic gates removed, by the namespaces of the rows they were in
+    /// (joined by `, ` when the two gates of a row come from different namespaces, the root namespace being the empty path)
+    pub removed: BTreeMap<String, usize>,
+}
+
+/// The namespaces and the locations in the source of the gates of a row.
+type Origin = (Vec<String>, Vec<&'static Location<'static>>);
+
+/// Whether `row` is a row of generic gates with a fused product, which the optimization leaves untouched.
+fn is_fused<F: FftField>(row: &Row<F>) -> bool {
+    row.typ == GateType::Generic
+        && row.coeffs[2 * GENERIC_COEFFS..]
+            .iter()
+            .any(|c| !c.is_zero())
+}
+
+/// A generic gate, and the row it was laid out in.
+#[derive(Clone)]
+struct Half<F> {
+    coeffs: [F; GENERIC_COEFFS],
+    vars: [Option<Var>; GENERIC_REGISTERS],
+    row: usize,
+}
+
+impl<F: FftField> Half<F> {
+    /// The register of the variable defined by the gate, if any.
+    fn defined(&self) -> Option<usize> {
+        let c = &self.coeffs;
+        if self.vars[2].is_some() && !c[2].is_zero() {
+            Some(2)
+        } else if self.vars[0].is_some()
+            && !c[0].is_zero()
+            && c[1].is_zero()
+            && c[2].is_zero()
+            && c[3].is_zero()
+        {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the value of the register `i` matters to the gate.
+    fn reads(&self, i: usize) -> bool {
+        let c = &self.coeffs;
+        match i {
+            0 => !c[0].is_zero() || !c[3].is_zero(),
+            1 => !c[1].is_zero() || !c[3].is_zero(),
+            _ => !c[2].is_zero(),
+        }
+    }
+
+    /// The value of the output of the gate, given the values of its inputs.
+    fn output(&self, l: F, r: F) -> F {
+        let c = &self.coeffs;
+        -(c[0] * l + c[1] * r + c[3] * l * r + c[4]) / c[2]
+    }
+}
+
+/// The state of the optimization: the classes of equal variables, and how many cells each class occupies.
+struct Classes<'a, F: FftField> {
+    builder: &'a mut CircuitBuilder<F>,
+    /// the number of cells holding a variable of each class
+    counts: HashMap<usize, usize>,
+    /// the value of the classes that are constants
+    constants: HashMap<usize, F>,
+    /// the classes containing an input of the circuit
+    inputs: HashSet<usize>,
+}
+
+impl<'a, F: FftField> Classes<'a, F> {
+    fn find(&self, var: Var) -> usize {
+        self.builder.find(var)
+    }
+
+    fn remove_cells(&mut self, half: &Half<F>) {
+        for var in half.vars.iter().flatten() {
+            let root = self.find(*var);
+            *self.counts.get_mut(&root).unwrap() -= 1;
+        }
+    }
+
+    /// Wires `a` and `b`, merging their classes.
+    fn union(&mut self, a: Var, b: Var) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return;
+        }
+        self.builder.assert_equal(a, b);
+        let (root, other) = if self.find(a) == ra {
+            (ra, rb)
+        } else {
+            (rb, ra)
+        };
+        let count = self.counts.remove(&other).unwrap_or(0);
+        *self.counts.entry(root).or_default() += count;
+        if let Some(value) = self.constants.remove(&other) {
+            self.constants.insert(root, value);
+        }
+        if self.inputs.remove(&other) {
+            self.inputs.insert(root);
+        }
+    }
+
+    /// Forgets that the variables of the class `root` are constants or booleans,
+    /// once the gate constraining them is removed.
+    fn forget(&mut self, root: usize) {
+        self.constants.remove(&root);
+        let builder = &mut *self.builder;
+        let mut constants = std::mem::take(&mut builder.constants);
+        constants.retain(|_, var| builder.find(*var) != root);
+        builder.constants = constants;
+        let mut booleans = std::mem::take(&mut builder.booleans);
+        booleans.retain(|var| builder.find(*var) != root);
+        builder.booleans = booleans;
+    }
+
+    /// The value of the register `i` of `half`, if it is a constant or does not matter.
+    fn constant(&self, half: &Half<F>, i: usize) -> Option<F> {
+        if !half.reads(i) {
+            return Some(F::zero());
+        }
+        match half.vars[i] {
+            Some(var) => self.constants.get(&self.find(var)).copied(),
+            // an empty cell is zero
+            None => Some(F::zero()),
+        }
+    }
+}
+
+impl<F: FftField> CircuitBuilder<F> {
+    /// Removes the generic gates that are not needed to constrain the circuit
+    /// (see the [module documentation](crate::circuits::builder::optimizer)), and reports the number of rows saved.
+    /// The rows of the public inputs and the gates of other types are left untouched.
+    pub fn optimize(&mut self) -> OptimizationReport {
+        let mut report = OptimizationReport {
+            rows_before: self.rows.len(),
+            ..Default::default()
+        };
+
+        // extract the generic gates that can be optimized, with the rows they are in
+        let mut halves = vec![];
+        let mut rows = vec![];
+        for (row, r) in self.rows.iter().enumerate().skip(self.public) {
+            if r.typ != GateType::Generic || is_fused(r) {
+                continue;
+            }
+            for h in 0..2 {
+                let mut coeffs = [F::zero(); GENERIC_COEFFS];
+                coeffs.copy_from_slice(&r.coeffs[h * GENERIC_COEFFS..(h + 1) * GENERIC_COEFFS]);
+                let mut vars = [None; GENERIC_REGISTERS];
+                vars.copy_from_slice(&r.cells[h * GENERIC_REGISTERS..(h + 1) * GENERIC_REGISTERS]);
+                if coeffs.iter().any(|c| !c.is_zero()) || vars.iter().any(Option::is_some) {
+                    halves.push(Some(Half { coeffs, vars, row }));
+                    rows.push(row);
+                }
+            }
+        }
+
+        let mut classes = Classes {
+            counts: HashMap::new(),
+            constants: HashMap::new(),
+            inputs: HashSet::new(),
+            builder: self,
+        };
+        for r in &classes.builder.rows {
+            for var in r.cells.iter().flatten() {
+                *classes
+                    .counts
+                    .entry(classes.builder.find(*var))
+                    .or_default() += 1;
+            }
+        }
+        for (i, source) in classes.builder.sources.iter().enumerate() {
+            let root = classes.builder.find(Var(i));
+            match source {
+                Source::Constant(c) => {
+                    classes.constants.insert(root, *c);
+                }
+                Source::Public(_) | Source::Private(_) => {
+                    classes.inputs.insert(root);
+                }
+                Source::Computed => (),
+            }
+        }
+
+        loop {
+            let mut changed = false;
+            let mut seen: HashMap<_, Var> = HashMap::new();
+            for slot in halves.iter_mut() {
+                let half = match slot {
+                    Some(half) => half.clone(),
+                    None => continue,
+                };
+                let register = match half.defined() {
+                    Some(register) => register,
+                    None => continue,
+                };
+                let var = half.vars[register].unwrap();
+                let root = classes.find(var);
+                let inputs: Vec<_> = (0..GENERIC_REGISTERS)
+                    .filter(|i| *i != register && half.reads(*i))
+                    .collect();
+                if inputs
+                    .iter()
+                    .any(|i| half.vars[*i].map(|v| classes.find(v)) == Some(root))
+                {
+                    continue;
+                }
+
+                // the variable is not used anywhere else
+                if classes.counts[&root] == 1 && !classes.inputs.contains(&root) {
+                    classes.remove_cells(&half);
+                    classes.forget(root);
+                    *slot = None;
+                    report.dead += 1;
+                    changed = true;
+                    continue;
+                }
+
+                if register != 2 {
+                    continue;
+                }
+
+                // the inputs are constants
+                if let (Some(l), Some(r)) = (classes.constant(&half, 0), classes.constant(&half, 1))
+                {
+                    let value = half.output(l, r);
+                    match classes.constants.get(&root) {
+                        // the gate is implied by the constant gate of its output
+                        Some(c) if *c == value => {
+                            classes.remove_cells(&half);
+                            *slot = None;
+                        }
+                        // the circuit cannot be satisfied, leave it so
+                        Some(_) => continue,
+                        None => match classes.builder.constants.get(&value).copied() {
+                            Some(constant) => {
+                                classes.remove_cells(&half);
+                                classes.union(constant, var);
+                                *slot = None;
+                            }
+                            None => {
+                                classes.remove_cells(&half);
+                                let gate = Half {
+                                    coeffs: [F::one(), F::zero(), F::zero(), F::zero(), -value],
+                                    vars: [Some(var), None, None],
+                                    row: half.row,
+                                };
+                                *classes.counts.get_mut(&root).unwrap() += 1;
+                                classes.constants.insert(root, value);
+                                classes.builder.constants.insert(value, var);
+                                *slot = Some(gate);
+                            }
+                        },
+                    }
+                    report.folded += 1;
+                    changed = true;
+                    continue;
+                }
+
+                // an identical gate defines a variable from the same inputs
+                let key = (
+                    half.coeffs,
+                    half.vars[0].map(|v| classes.find(v)),
+                    half.vars[1].map(|v| classes.find(v)),
+                );
+                match seen.get(&key) {
+                    Some(other) if classes.find(*other) != root => {
+                        classes.remove_cells(&half);
+                        classes.union(*other, var);
+                        *slot = None;
+                        report.merged += 1;
+                        changed = true;
+                    }
+                    Some(_) => (),
+                    None => {
+                        seen.insert(key, var);
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        for (row, slot) in rows.into_iter().zip(&halves) {
+            if slot.is_none() {
+                *report
+                    .removed
+                    .entry(self.rows[row].paths.join(", "))
+                    .or_default() += 1;
+            }
+        }
+        self.pack(halves.into_iter().flatten().collect());
+        report.rows_after = self.rows.len();
+        report
+    }
+
+    /// Lays out `halves` again, packing the gates sharing a run of consecutive generic rows in as few rows as possible.
+    fn pack(&mut self, halves: Vec<Half<F>>) {
+        let old_rows = std::mem::take(&mut self.rows);
+        let mut halves = halves.into_iter().peekable();
+        let mut rows: Vec<Row<F>> = vec![];
+        let mut run: Vec<(Half<F>, Origin)> = vec![];
+
+        let flush = |rows: &mut Vec<Row<F>>, run: &mut Vec<(Half<F>, Origin)>, keep: bool| {
+            if run.is_empty() && keep {
+                // an empty row, so that the row following the previous gate stays a generic row
+                rows.push(Row {
+                    typ: GateType::Generic,
+                    coeffs: vec![F::zero(); GENERIC_COEFFS * 2],
+                    cells: [None; COLUMNS],
+                    paths: vec![],
+                    locations: vec![],
+                });
+            }
+            for pair in run.chunks(2) {
+                let mut row = Row {
+                    typ: GateType::Generic,
+                    coeffs: vec![F::zero(); GENERIC_COEFFS * 2],
+                    cells: [None; COLUMNS],
+                    paths: vec![],
+                    locations: vec![],
+                };
+                for (h, (half, (paths, locations))) in pair.iter().enumerate() {
+                    row.coeffs[h * GENERIC_COEFFS..(h + 1) * GENERIC_COEFFS]
+                        .copy_from_slice(&half.coeffs);
+                    row.cells[h * GENERIC_REGISTERS..(h + 1) * GENERIC_REGISTERS]
+                        .copy_from_slice(&half.vars);
+                    for path in paths {
+                        if !row.paths.contains(path) {
+                            row.paths.push(path.clone());
+                        }
+                    }
+                    for location in locations {
+                        if !row.locations.contains(location) {
+                            row.locations.push(*location);
+                        }
+                    }
+                }
+                rows.push(row);
+            }
+            run.clear();
+        };
+
+        let mut in_run = false;
+        let mut keep = false;
+        for (i, row) in old_rows.into_iter().enumerate() {
+            if i < self.public || row.typ != GateType::Generic || is_fused(&row) {
+                if in_run {
+                    flush(&mut rows, &mut run, keep);
+                    in_run = false;
+                }
+                rows.push(row);
+                continue;
+            }
+            if !in_run {
+                in_run = true;
+                // the row following a gate of another type may be read by that gate
+                keep = rows.last().map_or(false, |last| {
+                    !matches!(
+                        last.typ,
+                        GateType::Zero
+                            | GateType::Generic
+                            | GateType::LinearCombination
+                            | GateType::Boolean
+                            | GateType::Lookup
+                    )
+                });
+            }
+            while let Some(half) = halves.next_if(|half| half.row == i) {
+                run.push((half, (row.paths.clone(), row.locations.clone())));
+            }
+        }
+        if in_run {
+            flush(&mut rows, &mut run, keep);
+        }
+
+        self.rows = rows;
+        self.half_generic = match self.rows.last() {
+            Some(last)
+                if self.rows.len() > self.public
+                    && last.typ == GateType::Generic
+                    && last.cells[GENERIC_REGISTERS..].iter().all(Option::is_none)
+                    && last.coeffs[GENERIC_COEFFS..].iter().all(|c| c.is_zero()) =>
+            {
+                Some(self.rows.len() - 1)
+            }
+            _ if self.rows.len() == self.public => self.half_generic,
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::verify_generic_circuit;
+    use super::*;
+    use mina_curves::pasta::fp::Fp;
+
+    #[test]
+    fn optimizer_removes_gates() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+
+        // an unused output
+        let _unused = b.mul(x, x);
+        // 2 * 3, folded into a constant gate (and the gates of 2 and 3 then become unused)
+        let two = b.constant(2u64.into());
+        let three = b.constant(3u64.into());
+        let six = b.mul(two, three);
+        // twice the same computation
+        let s1 = b.add(x, six);
+        let s2 = b.add(x, six);
+        let out = b.mul(s1, s2);
+        let expected = b.constant(100u64.into());
+        b.assert_equal(out, expected);
+
+        let report = b.optimize();
+        assert_eq!(
+            report,
+            OptimizationReport {
+                rows_before: 4,
+                rows_after: 2,
+                dead: 3,
+                folded: 1,
+                merged: 1,
+                removed: BTreeMap::from([(String::new(), 4)]),
+            }
+        );
+
+        let (gates, witness_builder) = b.build();
+        assert_eq!(gates.len(), 2);
+        let witness = witness_builder.generate(&[], &[4u64.into()]);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+        let witness = witness_builder.generate(&[], &[5u64.into()]);
+        assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+    }
+
+    #[test]
+    fn optimizer_keeps_inputs_and_other_gates() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.public_input();
+        let y = b.private_input();
+        // constrains the private input, and must be kept
+        b.assert_zero(y);
+        let z = b.weighted_sum(&[(Fp::from(2u64), x), (Fp::from(3u64), x)]);
+        let _unused = b.add(z, z);
+
+        let report = b.optimize();
+        assert_eq!(report.dead, 1);
+        assert_eq!(report.rows_after, report.rows_before - 1);
+
+        // more gates can be added after the optimization
+        let w = b.mul(z, x);
+        let expected = b.constant(20u64.into());
+        b.assert_equal(w, expected);
+
+        let (gates, witness_builder) = b.build();
+        assert_eq!(gates[1].typ, GateType::LinearCombination);
+        let witness = witness_builder.generate(&[2u64.into()], &[0u64.into()]);
+        let index = crate::prover_index::testing::new_index_for_test(gates.clone(), 1);
+        crate::circuits::check::check_witness(&witness, &index.cs, &[2u64.into()]).unwrap();
+        let witness = witness_builder.generate(&[2u64.into()], &[1u64.into()]);
+        assert!(
+            crate::circuits::check::check_witness(&witness, &index.cs, &[2u64.into()]).is_err()
+        );
+    }
+}
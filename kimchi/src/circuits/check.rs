@@ -0,0 +1,513 @@
+//! This module implements [check_witness], a fast check that a witness satisfies a circuit.
+//!
+//! Instead of interpolating the witness and evaluating the constraints over the larger domains like the prover does,
+//! the constraints of each gate are evaluated on the rows of the witness directly,
+//! which is much cheaper and is meant for testing circuits and validating inputs before attempting a proof.
+//! The lookups of the ChaCha gates and of the runtime tables are not checked, nor are the boundary constraints on the permutation aggregation polynomial.
+//! The custom gates are checked with their constraints and [GateConstraint::verify](crate::circuits::custom::GateConstraint::verify).
+//! [ConstraintSystem::verify_rows] runs the same checks on a range of rows, and tells which of them fail,
+//! to find all the failing rows of a large circuit at once, and [ConstraintSystem::verify] is [check_witness].
+//!
+//! When a proof cannot be created because the quotient polynomial does not divide,
+//! [counterexample] tells which term of the quotient (a gate, the permutation, the public input, or a boundary constraint)
+//! fails on which row, along with the values of the cells involved.
+
+use crate::circuits::{
+    argument::Argument,
+    constraints::{eval_vanishes_on_last_4_rows, ConstraintSystem, GateError},
+    custom,
+    expr::{prologue::*, Column, Constants, Expr, Op2, Variable},
+    gate::{CircuitGate, CurrOrNext, GateType},
+    polynomials::{
+        boolean::Boolean,
+        boundary,
+        chacha::{ChaCha0, ChaCha1, ChaCha2, ChaChaFinal},
+        complete_add::CompleteAdd,
+        endomul_scalar::EndomulScalar,
+        endosclmul::EndosclMul,
+        linear_combination,
+        lookup_gate::{self, LOOKUP_WIDTH},
+        poseidon::{self, Poseidon},
+        varbasemul::VarbaseMul,
+    },
+    wires::{Wire, COLUMNS, PERMUTS},
+};
+use ark_ff::{FftField, Field, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain as D};
+use array_init::array_init;
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+};
+
+/// Returns the constraints enforced by a gate of type `typ`, or [None] for the gates checked separately.
+pub(crate) fn gate_constraints<F: FftField>(typ: GateType) -> Option<Vec<E<F>>> {
+    use GateType::*;
+    match typ {
+        Zero | Generic | Custom | Lookup => None,
+        LinearCombination => Some(vec![linear_combination::constraint()]),
+        // the corrections of the partial rounds vanish on the other rounds
+        Poseidon => Some(
+            Poseidon::<F>::constraints()
+                .into_iter()
+                .zip(poseidon::partial_round_corrections())
+                .map(|(constraint, correction)| constraint + correction)
+                .collect(),
+        ),
+        CompleteAdd => Some(CompleteAdd::<F>::constraints()),
+        VarBaseMul => Some(VarbaseMul::<F>::constraints()),
+        EndoMul => Some(EndosclMul::<F>::constraints()),
+        EndoMulScalar => Some(EndomulScalar::<F>::constraints()),
+        ChaCha0 => Some(ChaCha0::<F>::constraints()),
+        ChaCha1 => Some(ChaCha1::<F>::constraints()),
+        ChaCha2 => Some(ChaCha2::<F>::constraints()),
+        ChaChaFinal => Some(ChaChaFinal::<F>::constraints()),
+        Boolean => Some(Boolean::<F>::constraints()),
+    }
+}
+
+/// The cells of the witness and of the circuit around a row.
+struct Row<'a, F: FftField> {
+    witness: &'a [Vec<F>; COLUMNS],
+    gates: &'a [CircuitGate<F>],
+    domain: D<F>,
+    row: usize,
+}
+
+impl<'a, F: FftField> Row<'a, F> {
+    fn cell(&self, v: &Variable) -> Result<F, String> {
+        let row = match v.row {
+            CurrOrNext::Curr => self.row,
+            CurrOrNext::Next => (self.row + 1) % self.domain.size(),
+        };
+        let gate = self.gates.get(row);
+        match v.col {
+            Column::Witness(i) => Ok(self.witness[i][row]),
+            Column::Index(typ) => Ok(match gate {
+                Some(gate) if gate.typ == typ => F::one(),
+                _ => F::zero(),
+            }),
+            Column::Coefficient(i) => Ok(gate
+                .and_then(|gate| gate.coeffs.get(i))
+                .copied()
+                .unwrap_or_else(F::zero)),
+            Column::Weight(i) => Ok(match gate {
+                Some(gate) if gate.typ == GateType::LinearCombination => {
+                    gate.coeffs.get(i).copied().unwrap_or_else(F::zero)
+                }
+                _ => F::zero(),
+            }),
+            Column::PartialRound(round) => Ok(match gate {
+                Some(gate) if gate.partial_rounds()[round] => F::one(),
+                _ => F::zero(),
+            }),
+            Column::CustomIndex(id) => Ok(match gate {
+                Some(gate) if custom::selects(gate, id) => F::one(),
+                _ => F::zero(),
+            }),
+            col => Err(format!("unexpected column {col:?} in a gate constraint")),
+        }
+    }
+
+    /// Evaluates `expr` at the point of the domain corresponding to the row.
+    fn evaluate(&self, expr: &E<F>, c: &Constants<F>) -> Result<F, String> {
+        use Expr::*;
+        let d = self.domain;
+        let pt = d.element(self.row);
+        Ok(match expr {
+            Constant(x) => x.value(c),
+            Cell(v) => self.cell(v)?,
+            Double(x) => self.evaluate(x, c)?.double(),
+            Square(x) => self.evaluate(x, c)?.square(),
+            BinOp(Op2::Add, x, y) => self.evaluate(x, c)? + self.evaluate(y, c)?,
+            BinOp(Op2::Mul, x, y) => self.evaluate(x, c)? * self.evaluate(y, c)?,
+            BinOp(Op2::Sub, x, y) => self.evaluate(x, c)? - self.evaluate(y, c)?,
+            VanishesOnLast4Rows => eval_vanishes_on_last_4_rows(d, pt),
+            // over the domain, (x^n - 1) / (x - w^i) only differs from zero at w^i, where it is n w^{-i}
+            UnnormalizedLagrangeBasis(i) => {
+                if self.row == i % d.size() {
+                    d.size_as_field_element * d.group_gen_inv.pow(&[*i as u64])
+                } else {
+                    F::zero()
+                }
+            }
+            Pow(x, p) => self.evaluate(x, c)?.pow(&[*p as u64]),
+            Cache(_, e) => self.evaluate(e, c)?,
+        })
+    }
+}
+
+/// The checks of the rows of a witness, with what they need computed once for all the rows.
+struct RowChecker<'a, F: FftField> {
+    cs: &'a ConstraintSystem<F>,
+    witness: &'a [Vec<F>; COLUMNS],
+    public: &'a [F],
+    constants: Constants<F>,
+    /// the constraints of the gate types and of the custom gates of the circuit
+    constraints: HashMap<GateType, Option<Vec<E<F>>>>,
+    custom_constraints: HashMap<usize, Vec<E<F>>>,
+    /// the entries of the lookup tables, if the circuit has lookup gates
+    lookup_entries: Option<HashSet<[F; LOOKUP_WIDTH + 1]>>,
+}
+
+impl<'a, F: FftField> RowChecker<'a, F> {
+    fn new(cs: &'a ConstraintSystem<F>, witness: &'a [Vec<F>; COLUMNS], public: &'a [F]) -> Self {
+        let mut constraints = HashMap::new();
+        let mut custom_constraints = HashMap::new();
+        for gate in &cs.gates {
+            match cs.custom_gate(gate) {
+                Some((id, custom)) => {
+                    custom_constraints
+                        .entry(id)
+                        .or_insert_with(|| custom.constraints());
+                }
+                None => {
+                    constraints
+                        .entry(gate.typ)
+                        .or_insert_with(|| gate_constraints(gate.typ));
+                }
+            }
+        }
+        RowChecker {
+            cs,
+            witness,
+            public,
+            constants: Constants {
+                alpha: F::zero(),
+                beta: F::zero(),
+                gamma: F::zero(),
+                joint_combiner: F::zero(),
+                endo_coefficient: cs.endo,
+                mds: cs.fr_sponge_params.mds.clone(),
+            },
+            constraints,
+            custom_constraints,
+            lookup_entries: lookup_gate::table_entries(cs),
+        }
+    }
+
+    /// Checks the wiring and the gate of the row `row`.
+    fn check(&self, row: usize) -> Result<(), GateError> {
+        let (cs, witness) = (self.cs, self.witness);
+        let n = cs.domain.d1.size();
+        let gate = &cs.gates[row];
+
+        // copy constraints
+        for (col, wire) in gate.wires.iter().enumerate() {
+            if wire.col >= PERMUTS || wire.row >= n {
+                return Err(GateError::Custom {
+                    row,
+                    err: format!("the wire of column {col} points out of the permutation"),
+                });
+            }
+            if witness[col][row] != witness[wire.col][wire.row] {
+                return Err(GateError::DisconnectedWires(Wire { col, row }, *wire));
+            }
+        }
+
+        // for public gates, only the left wire is toggled
+        if row < cs.public && gate.coeffs.first() != Some(&F::one()) {
+            return Err(GateError::IncorrectPublic(row));
+        }
+
+        // gate constraints
+        let custom_err = |err| GateError::Custom { row, err };
+        let expected = match gate.typ {
+            GateType::Generic => {
+                return gate
+                    .verify_generic(row, witness, self.public)
+                    .map_err(custom_err);
+            }
+            GateType::Lookup if gate.looks_up_runtime_table(cs) => return Ok(()),
+            GateType::Lookup => {
+                let entries = self.lookup_entries.as_ref().ok_or_else(|| {
+                    custom_err("lookup: the circuit has no lookup table".to_string())
+                })?;
+                return gate
+                    .verify_lookup(row, witness, entries)
+                    .map_err(custom_err);
+            }
+            GateType::Custom => {
+                let (id, custom) = cs
+                    .custom_gate(gate)
+                    .ok_or_else(|| custom_err("the custom gate is not registered".to_string()))?;
+                custom.verify(row, witness).map_err(custom_err)?;
+                self.custom_constraints.get(&id)
+            }
+            typ => self.constraints.get(&typ).and_then(Option::as_ref),
+        };
+        let cells = Row {
+            witness,
+            gates: &cs.gates,
+            domain: cs.domain.d1,
+            row,
+        };
+        for (i, constraint) in expected.into_iter().flatten().enumerate() {
+            let value = cells
+                .evaluate(constraint, &self.constants)
+                .map_err(custom_err)?;
+            if !value.is_zero() {
+                return Err(custom_err(format!(
+                    "constraint {i} of the {:?} gate is not satisfied",
+                    gate.typ
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pads the columns of `witness` with zeros to the size of the domain of `cs`,
+/// if it does not have more rows than the domain.
+fn pad_witness<F: FftField>(
+    witness: &[Vec<F>; COLUMNS],
+    cs: &ConstraintSystem<F>,
+) -> Result<[Vec<F>; COLUMNS], GateError> {
+    let n = cs.domain.d1.size();
+    if let Some(col) = witness.iter().find(|col| col.len() > n) {
+        return Err(GateError::Custom {
+            row: col.len(),
+            err: format!("the witness has more rows than the domain ({n})"),
+        });
+    }
+    Ok(array_init(|i| {
+        let mut col = witness[i].clone();
+        col.resize(n, F::zero());
+        col
+    }))
+}
+
+/// Checks that `witness` satisfies the gates, the wiring and the boundary constraints of `cs`, for the public input `public`,
+/// without interpolating the witness.
+/// The witness can be shorter than the domain, in which case it is padded with zeros.
+pub fn check_witness<F: FftField>(
+    witness: &[Vec<F>; COLUMNS],
+    cs: &ConstraintSystem<F>,
+    public: &[F],
+) -> Result<(), GateError> {
+    let witness = pad_witness(witness, cs)?;
+    cs.check_rows(&witness, public, 0..cs.gates.len())
+        .into_iter()
+        .collect::<Result<(), _>>()?;
+
+    // boundary constraints
+    boundary::check(&cs.boundary, &witness)
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    /// Checks the gates and the wiring of the rows `rows` of `witness`, which is padded to the size of the domain,
+    /// in parallel, returning the outcome of each of these rows.
+    /// This is the check of the rows of [check_witness], [ConstraintSystem::verify_rows] and [ConstraintSystem::verify].
+    fn check_rows(
+        &self,
+        witness: &[Vec<F>; COLUMNS],
+        public: &[F],
+        rows: Range<usize>,
+    ) -> Vec<Result<(), GateError>> {
+        let checker = RowChecker::new(self, witness, public);
+        rows.into_par_iter().map(|row| checker.check(row)).collect()
+    }
+
+    /// Checks the gates and the wiring of the rows `rows` of `witness`, like [check_witness] does, in parallel,
+    /// returning for each of these rows whether it fails: the row `rows.start + i` fails if the entry `i` is true.
+    /// The public input is read from the witness, so that the gates of the public input only fail
+    /// if they do not have the form of a public input gate, and the boundary constraints are not checked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the witness has more rows than the domain, or if `rows` goes past the rows of the circuit.
+    pub fn verify_rows(&self, witness: &[Vec<F>; COLUMNS], rows: Range<usize>) -> Vec<bool> {
+        assert!(
+            rows.end <= self.gates.len(),
+            "the circuit has {} rows",
+            self.gates.len()
+        );
+        let witness = pad_witness(witness, self).unwrap_or_else(|err| panic!("{err:?}"));
+        let public = &witness[0][..self.public];
+        self.check_rows(&witness, public, rows)
+            .iter()
+            .map(Result::is_err)
+            .collect()
+    }
+}
+
+/// A term of the quotient polynomial.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QuotientTerm {
+    /// The constraints of a gate
+    Gate(GateType),
+    /// The permutation argument, that enforces the wiring
+    Permutation,
+    /// The public input, that the first rows must hold
+    PublicInput,
+    /// The boundary constraints, that pin cells to values
+    Boundary,
+}
+
+/// A row of the witness that does not satisfy a term of the quotient, see [counterexample].
+#[derive(Clone, Debug)]
+pub struct CounterExample<F> {
+    /// the term that is not satisfied
+    pub term: QuotientTerm,
+    /// the row on which it is not satisfied
+    pub row: usize,
+    /// a description of the failure
+    pub reason: String,
+    /// the values of the witness on the rows involved (the row and its next row, or the rows that are wired together)
+    pub cells: Vec<(usize, [F; COLUMNS])>,
+}
+
+impl<F: FftField> fmt::Display for CounterExample<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:?} term unsatisfied on row {}: {}",
+            self.term, self.row, self.reason
+        )?;
+        for (row, cells) in &self.cells {
+            write!(f, "  row {row}:")?;
+            for (col, value) in cells.iter().enumerate() {
+                write!(f, " w{col}={value}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks `witness` with [check_witness], and if it does not satisfy `cs`,
+/// returns the term of the quotient that fails and the row on which it fails, with the offending values.
+/// This is meant for debugging circuits whose proofs cannot be created.
+pub fn counterexample<F: FftField>(
+    witness: &[Vec<F>; COLUMNS],
+    cs: &ConstraintSystem<F>,
+    public: &[F],
+) -> Option<CounterExample<F>> {
+    let err = check_witness(witness, cs, public).err()?;
+    let n = cs.domain.d1.size();
+    let cells = |row: usize| {
+        let values = array_init(|col| witness[col].get(row).copied().unwrap_or_else(F::zero));
+        (row, values)
+    };
+    let typ = |row: usize| cs.gates.get(row).map_or(GateType::Zero, |gate| gate.typ);
+    Some(match err {
+        GateError::DisconnectedWires(a, b) => CounterExample {
+            term: QuotientTerm::Permutation,
+            row: a.row,
+            reason: format!(
+                "the cell in column {} is wired to row {} column {}, but they differ",
+                a.col, b.row, b.col
+            ),
+            cells: if a.row == b.row {
+                vec![cells(a.row)]
+            } else {
+                vec![cells(a.row), cells(b.row)]
+            },
+        },
+        GateError::IncorrectPublic(row) => CounterExample {
+            term: QuotientTerm::PublicInput,
+            row,
+            reason: "the gate of a public input must only use its left wire".to_string(),
+            cells: vec![cells(row)],
+        },
+        GateError::Custom { row, err } => CounterExample {
+            term: if row < cs.public {
+                QuotientTerm::PublicInput
+            } else {
+                QuotientTerm::Gate(typ(row))
+            },
+            row,
+            reason: err,
+            cells: if row < n {
+                vec![cells(row), cells((row + 1) % n)]
+            } else {
+                vec![]
+            },
+        },
+        GateError::Boundary(cell) => CounterExample {
+            term: QuotientTerm::Boundary,
+            row: cell.row,
+            reason: format!(
+                "the cell in column {} is not the value it is pinned to",
+                cell.col
+            ),
+            cells: vec![cells(cell.row)],
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::polynomials::generic::testing::create_circuit_and_witness;
+    use mina_curves::pasta::fp::Fp;
+
+    #[test]
+    fn test_check_witness() {
+        let (gates, witness, public) = create_circuit_and_witness(5);
+
+        let fp_sponge_params = oracle::pasta::fp_kimchi::params();
+        let cs = ConstraintSystem::create(gates, vec![], fp_sponge_params, public.len()).unwrap();
+        check_witness(&witness, &cs, &public).unwrap();
+
+        // a wrong public input
+        let mut wrong_public = public.clone();
+        wrong_public[1] += Fp::from(1u8);
+        assert!(check_witness(&witness, &cs, &wrong_public).is_err());
+
+        // a wrong constant
+        let mut wrong = witness.clone();
+        let last = wrong[0].len() - 1;
+        wrong[0][last] += Fp::from(1u8);
+        assert!(matches!(
+            check_witness(&wrong, &cs, &public),
+            Err(GateError::Custom { row, .. }) if row == last
+        ));
+        let example = counterexample(&wrong, &cs, &public).unwrap();
+        assert_eq!(example.term, QuotientTerm::Gate(GateType::Generic));
+        assert_eq!(example.row, last);
+        assert_eq!(example.cells[0].1[0], wrong[0][last]);
+        assert!(example
+            .to_string()
+            .starts_with(&format!("Gate(Generic) term unsatisfied on row {last}")));
+        assert_eq!(
+            counterexample(&witness, &cs, &wrong_public).map(|c| c.term),
+            Some(QuotientTerm::PublicInput)
+        );
+        assert!(counterexample(&witness, &cs, &public).is_none());
+
+        // cells that are wired together but differ
+        let row = public.len();
+        let mut cs = cs;
+        cs.gates[row].wires[0] = Wire { col: 1, row };
+        assert!(matches!(
+            check_witness(&witness, &cs, &public),
+            Err(GateError::DisconnectedWires(..))
+        ));
+        assert_eq!(
+            counterexample(&witness, &cs, &public).map(|c| (c.term, c.row)),
+            Some((QuotientTerm::Permutation, row))
+        );
+    }
+
+    #[test]
+    fn test_verify_rows() {
+        let (gates, witness, public) = create_circuit_and_witness(5);
+
+        let fp_sponge_params = oracle::pasta::fp_kimchi::params();
+        let cs = ConstraintSystem::create(gates, vec![], fp_sponge_params, public.len()).unwrap();
+        let rows = witness[0].len();
+        assert_eq!(cs.verify_rows(&witness, 0..rows), vec![false; rows]);
+
+        // wrong constants on two rows
+        let mut wrong = witness.clone();
+        wrong[0][rows - 1] += Fp::from(1u8);
+        wrong[0][rows - 3] += Fp::from(1u8);
+        let failures = cs.verify_rows(&wrong, 0..rows);
+        let failing: Vec<_> = (0..rows).filter(|row| failures[*row]).collect();
+        assert_eq!(failing, vec![rows - 3, rows - 1]);
+        assert_eq!(cs.verify_rows(&wrong, rows - 2..rows), vec![false, true]);
+    }
+}
@@ -1,12 +1,24 @@
 //! This module implements Plonk circuit constraint primitive.
 
 use crate::circuits::{
+    check,
+    custom::CustomGates,
     domains::EvaluationDomains,
-    gate::{CircuitGate, GateType, LookupInfo, LookupsUsed},
+    fft,
+    gate::{get_table, CircuitGate, GateType, LookupInfo, LookupsUsed},
+    gates::poseidon::ROUNDS_PER_ROW,
     polynomial::{WitnessEvals, WitnessOverDomains, WitnessShifts},
+    polynomials::{
+        boundary::BoundaryConstraint,
+        lookup,
+        lookup_gate::LOOKUP_WIDTH,
+        runtime_tables::{self, RuntimeTableCfg, RuntimeTableInfo},
+    },
+    sparse::SparseEvals,
     wires::*,
 };
-use ark_ff::{FftField, SquareRootField, Zero};
+use crate::error::{InvalidWire, SetupError};
+use ark_ff::{FftField, Field, SquareRootField, Zero};
 use ark_poly::UVPolynomial;
 use ark_poly::{
     univariate::DensePolynomial as DP, EvaluationDomain, Evaluations as E,
@@ -18,6 +30,7 @@ use o1_utils::ExtendedEvaluations;
 use oracle::poseidon::ArithmeticSpongeParams;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::serde_as;
+use std::collections::BTreeSet;
 
 //
 // Constants
@@ -54,6 +67,18 @@ pub struct LookupConstraintSystem<F: FftField> {
     pub max_lookups_per_row: usize,
     /// The maximum number of elements in a vector lookup
     pub max_joint_size: usize,
+
+    /// The runtime table that the lookup gates look up, if they look up one
+    /// (see [runtime_tables](crate::circuits::polynomials::runtime_tables)).
+    pub runtime_table: Option<RuntimeTableInfo>,
+    /// The selector of the rows of the runtime table, if any.
+    #[serde_as(as = "Option<o1_utils::serialization::SerdeAs>")]
+    pub runtime_selector: Option<E<F, D<F>>>,
+
+    /// Whether the circuit looks up several tables.
+    /// The table of the lookup argument is then the concatenation of these tables,
+    /// with [max_joint_size](Self::max_joint_size) columns followed by a column of the id of the table of each entry.
+    pub table_ids: bool,
 }
 
 #[serde_as]
@@ -84,6 +109,10 @@ pub struct ConstraintSystem<F: FftField> {
     /// coefficients polynomials in evaluation form
     #[serde_as(as = "[o1_utils::serialization::SerdeAs; COLUMNS]")]
     pub coefficients8: [E<F, D<F>>; COLUMNS],
+    /// the nonzero coefficients over domain d1, to skip the zero columns and commit to the sparse ones
+    /// (not serialized, as they are the evaluations of [ConstraintSystem::coefficients8] at every 8th row)
+    #[serde(skip)]
+    pub coefficients_sparse: [SparseEvals<F>; COLUMNS],
 
     // Generic constraint selector polynomials
     // ---------------------------------------
@@ -101,6 +130,9 @@ pub struct ConstraintSystem<F: FftField> {
     /// multiplication evaluations over domain.d4
     #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub generic4: E<F, D<F>>,
+    /// whether some generic gates have a fused product (see [generic](crate::circuits::polynomials::generic)),
+    /// which is then part of the constraint of all the generic gates
+    pub fused_generic: bool,
 
     // permutation polynomials
     // -----------------------
@@ -134,6 +166,15 @@ pub struct ConstraintSystem<F: FftField> {
     /// ChaCha indexes
     #[serde_as(as = "Option<[o1_utils::serialization::SerdeAs; 4]>")]
     pub chacha8: Option<[E<F, D<F>>; 4]>,
+    /// weights of the linear combination gates over domain.d8, if the circuit uses them
+    #[serde_as(as = "Option<[o1_utils::serialization::SerdeAs; COLUMNS]>")]
+    pub linear_combination8: Option<[E<F, D<F>>; COLUMNS]>,
+    /// selectors of the partial rounds of the Poseidon gates over domain.d8, if the circuit has some
+    #[serde_as(as = "Option<[o1_utils::serialization::SerdeAs; ROUNDS_PER_ROW]>")]
+    pub partial_rounds8: Option<[E<F, D<F>>; ROUNDS_PER_ROW]>,
+    /// boolean selector evaluations over domain.d8, if the circuit uses the gate
+    #[serde_as(as = "Option<o1_utils::serialization::SerdeAs>")]
+    pub boolean8: Option<E<F, D<F>>>,
     /// EC point addition selector evaluations w over domain.d8
     #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub endomul_scalar8: E<F, D<F>>,
@@ -162,6 +203,10 @@ pub struct ConstraintSystem<F: FftField> {
     /// the polynomial that vanishes on the last four rows
     #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub vanishes_on_last_4_rows: E<F, D<F>>,
+    /// the polynomials `Z_H / (x - 1)` and `Z_H / (x - sid[n-3])` over domain.d8,
+    /// used by the boundary constraints of the permutation
+    #[serde_as(as = "[o1_utils::serialization::SerdeAs; 2]")]
+    pub perm_bnd8: [E<F, D<F>>; 2],
 
     /// wire coordinate shifts
     #[serde_as(as = "[o1_utils::serialization::SerdeAs; PERMUTS]")]
@@ -177,6 +222,17 @@ pub struct ConstraintSystem<F: FftField> {
     /// lookup constraint system
     #[serde(bound = "LookupConstraintSystem<F>: Serialize + DeserializeOwned")]
     pub lookup_constraint_system: Option<LookupConstraintSystem<F>>,
+
+    /// the registered custom gates (see [ConstraintSystem::register_custom_gate])
+    #[serde(skip)]
+    pub custom_gates: CustomGates<F>,
+    /// the selector evaluations of each custom gate over domain.d8
+    #[serde(skip)]
+    pub custom8: Vec<E<F, D<F>>>,
+
+    /// the boundary constraints of the circuit (see [ConstraintSystem::add_boundary_constraint])
+    #[serde(default, bound = "BoundaryConstraint<F>: Serialize + DeserializeOwned")]
+    pub boundary: Vec<BoundaryConstraint<F>>,
 }
 
 /// Shifts represent the shifts required in the permutation argument of PLONK.
@@ -205,12 +261,16 @@ where
         let mut i: u32 = 7;
         for idx in 1..(PERMUTS) {
             let mut shift = Self::sample(domain, &mut i);
-            // they have to be distincts
-            while shifts.contains(&shift) {
+            // they have to create distinct cosets
+            while shifts[..idx]
+                .iter()
+                .any(|k| Self::same_coset(domain, *k, shift))
+            {
                 shift = Self::sample(domain, &mut i);
             }
             shifts[idx] = shift;
         }
+        debug_assert_eq!(Self::check(domain, &shifts), Ok(()));
 
         // create a map of cells to their shifted value
         let map: [Vec<F>; PERMUTS] =
@@ -251,6 +311,33 @@ where
     }
 }
 
+impl<F: FftField> Shifts<F> {
+    /// Returns `true` if `k1` and `k2` create the same coset of `domain`, that is, if `k1 / k2` is in the domain.
+    /// With `n` the size of the domain, this is the case when $k_1^n = k_2^n$.
+    fn same_coset(domain: &D<F>, k1: F, k2: F) -> bool {
+        k1.pow([domain.size]) == k2.pow([domain.size])
+    }
+
+    /// Checks that `shifts` can be used in the permutation argument over `domain`:
+    /// the first one is the identity, and they all create disjoint cosets of the domain.
+    /// Otherwise two different cells could be represented by the same field element.
+    pub fn check(domain: &D<F>, shifts: &[F; PERMUTS]) -> Result<(), SetupError> {
+        if shifts[0] != F::one() {
+            return Err(SetupError::InvalidShifts);
+        }
+        for (i, k) in shifts.iter().enumerate() {
+            if k.is_zero()
+                || shifts[..i]
+                    .iter()
+                    .any(|other| Self::same_coset(domain, *other, *k))
+            {
+                return Err(SetupError::InvalidShifts);
+            }
+        }
+        Ok(())
+    }
+}
+
 ///
 
 /// Returns the end of the circuit, which is used for introducing zero-knowledge in the permutation polynomial
@@ -317,27 +404,83 @@ pub enum GateError {
     IncorrectPublic(usize),
     /// A specific gate did not verify correctly
     Custom { row: usize, err: String },
+    /// A cell does not hold the value that a boundary constraint pins it to
+    Boundary(Wire),
 }
 
 impl<F: FftField + SquareRootField> LookupConstraintSystem<F> {
+    /// Creates the lookup constraint system of the circuit `gates`, if it has lookups.
+    /// The lookup gates refer to the `lookup_tables` of the circuit by their position, followed by its `runtime_tables`,
+    /// and only the tables that the circuit looks up, including the tables of its gates, are part of the constraint system.
+    /// For now, a circuit can only look up a single runtime table.
     pub fn create(
         gates: &[CircuitGate<F>],
-        lookup_tables: Vec<Vec<Vec<F>>>,
+        mut lookup_tables: Vec<Vec<Vec<F>>>,
+        runtime_tables: Vec<RuntimeTableCfg<F>>,
         domain: &EvaluationDomains<F>,
-    ) -> Option<Self> {
+    ) -> Result<Option<Self>, SetupError> {
         let lookup_info = LookupInfo::<F>::create();
         match lookup_info.lookup_used(gates) {
-            None => None,
+            None => Ok(None),
             Some(lookup_used) => {
                 let d1_size = domain.d1.size();
 
                 let (lookup_selectors, gate_lookup_tables) =
                     lookup_info.selector_polynomials_and_tables(domain, gates);
 
+                // the tables that the lookup gates refer to
+                let fixed_tables = lookup_tables.len();
+                let mut ids = BTreeSet::new();
+                for (row, gate) in gates.iter().enumerate() {
+                    if gate.typ == GateType::Lookup {
+                        let id = gate
+                            .lookup_table_id(fixed_tables + runtime_tables.len())
+                            .ok_or(SetupError::UnknownLookupTable { row })?;
+                        ids.insert(id);
+                    }
+                }
+
+                // the tables of the lookup argument, with their ids
+                let mut tables = Vec::with_capacity(ids.len() + gate_lookup_tables.len());
+                let mut runtime_table = None;
+                for id in ids {
+                    let table = if id < fixed_tables {
+                        std::mem::take(&mut lookup_tables[id])
+                    } else if runtime_table.is_none() {
+                        let cfg = &runtime_tables[id - fixed_tables];
+                        let len = cfg.first_column.len();
+                        runtime_table = Some(RuntimeTableInfo { id, len });
+                        cfg.table()
+                    } else {
+                        return Err(SetupError::SeveralRuntimeTables);
+                    };
+                    check_lookup_table(id, &table, d1_size)?;
+                    tables.push((F::from(id as u64), table));
+                }
+                // the runtime table, which has the largest id, comes first,
+                // so that its entries are on the rows that its selector marks
+                if runtime_table.is_some() {
+                    tables.rotate_right(1);
+                }
+                tables.extend(
+                    gate_lookup_tables
+                        .into_iter()
+                        .map(|(kind, table)| (kind.id(), table)),
+                );
+                let runtime_selector =
+                    runtime_table.map(|info| runtime_tables::selector(info.len, domain));
+
+                let table_ids = tables.len() > 1;
+                let table = if table_ids {
+                    concat_lookup_tables(tables, lookup_info.max_joint_size)
+                } else {
+                    tables.pop().map(|(_, table)| table).unwrap_or_default()
+                };
+                let tables = vec![table];
+
                 // get the last entry in each column of each table
-                let dummy_lookup_values: Vec<Vec<F>> = gate_lookup_tables
+                let dummy_lookup_values: Vec<Vec<F>> = tables
                     .iter()
-                    .chain(lookup_tables.iter())
                     .map(|table| table.iter().map(|col| col[col.len() - 1]).collect())
                     .collect();
 
@@ -345,11 +488,7 @@ impl<F: FftField + SquareRootField> LookupConstraintSystem<F> {
                 let mut lookup_tables_polys: Vec<Vec<DP<F>>> = vec![];
                 let mut lookup_tables8: Vec<Vec<E<F, D<F>>>> = vec![];
 
-                for (table, dummies) in gate_lookup_tables
-                    .into_iter()
-                    .chain(lookup_tables.into_iter())
-                    .zip(&dummy_lookup_values)
-                {
+                for (table, dummies) in tables.into_iter().zip(&dummy_lookup_values) {
                     let mut table_poly = vec![];
                     let mut table_eval = vec![];
                     for (mut col, dummy) in table.into_iter().zip(dummies) {
@@ -366,7 +505,7 @@ impl<F: FftField + SquareRootField> LookupConstraintSystem<F> {
                 }
 
                 // generate the look up selector polynomials
-                Some(Self {
+                Ok(Some(Self {
                     lookup_selectors,
                     dummy_lookup_values,
                     lookup_tables8,
@@ -374,29 +513,234 @@ impl<F: FftField + SquareRootField> LookupConstraintSystem<F> {
                     lookup_used,
                     max_lookups_per_row: lookup_info.max_per_row as usize,
                     max_joint_size: lookup_info.max_joint_size,
+                    runtime_table,
+                    runtime_selector,
+                    table_ids,
+                }))
+            }
+        }
+    }
+}
+
+/// Checks that the table `id` of a circuit can be looked up by its lookup gates, in a domain of size `d1_size`:
+/// its columns are not empty, have the same length, there are at most [LOOKUP_WIDTH] of them,
+/// and the lookup argument has room for all of its entries.
+fn check_lookup_table<F>(id: usize, table: &[Vec<F>], d1_size: usize) -> Result<(), SetupError> {
+    let invalid = |reason| Err(SetupError::InvalidLookupTable { id, reason });
+    let len = table.first().map_or(0, Vec::len);
+    if len == 0 || table.iter().any(|col| col.len() != len) {
+        return invalid("its columns must have the same, non-zero, length");
+    }
+    if table.len() > LOOKUP_WIDTH {
+        return invalid("it has more columns than the cells of a lookup");
+    }
+    if len + lookup::ZK_ROWS + 1 > d1_size {
+        return invalid("it has more entries than the domain has rows");
+    }
+    Ok(())
+}
+
+/// Concatenates the `tables` of a circuit that looks up several tables into the table of the lookup argument:
+/// each table is padded with zero columns to `width` columns, and its entries are followed by its id.
+fn concat_lookup_tables<F: Field>(tables: Vec<(F, Vec<Vec<F>>)>, width: usize) -> Vec<Vec<F>> {
+    let mut columns = vec![vec![]; width + 1];
+    for (id, table) in tables {
+        let len = table.first().map_or(0, Vec::len);
+        let mut table = table.into_iter();
+        for (j, column) in columns.iter_mut().enumerate() {
+            match table.next() {
+                _ if j == width => column.extend(std::iter::repeat(id).take(len)),
+                Some(col) => column.extend(col),
+                None => column.extend(std::iter::repeat(F::zero()).take(len)),
+            }
+        }
+    }
+    columns
+}
+
+/// The number of rows that the domain of a circuit needs to hold the entries of the tables that `gates` look up,
+/// besides the zero-knowledge rows.
+fn lookup_table_rows<F: FftField>(
+    gates: &[CircuitGate<F>],
+    lookup_tables: &[Vec<Vec<F>>],
+    runtime_tables: &[RuntimeTableCfg<F>],
+) -> usize {
+    let ids: BTreeSet<_> = gates
+        .iter()
+        .filter(|gate| gate.typ == GateType::Lookup)
+        .filter_map(|gate| gate.lookup_table_id(lookup_tables.len() + runtime_tables.len()))
+        .collect();
+    let len = |id: usize| match lookup_tables.get(id) {
+        Some(table) => table.first().map_or(0, Vec::len),
+        None => runtime_tables[id - lookup_tables.len()].first_column.len(),
+    };
+    let gate_tables = LookupInfo::<F>::create().gate_tables(gates);
+    let entries: usize = ids.into_iter().map(len).sum::<usize>()
+        + gate_tables
+            .into_iter()
+            .map(|table| get_table::<F>(table).first().map_or(0, Vec::len))
+            .sum::<usize>();
+    if entries == 0 {
+        0
+    } else {
+        entries + 1
+    }
+}
+
+/// Checks that the wires of `gates` only point to the wired columns of the rows of `gates`,
+/// and that they form a permutation of these cells (see [check_permutation]),
+/// returning all the offending wires otherwise.
+pub fn check_wiring<F: FftField>(gates: &[CircuitGate<F>]) -> Result<(), SetupError> {
+    check_bounds(gates)?;
+    check_permutation(gates)
+}
+
+fn check_bounds<F: FftField>(gates: &[CircuitGate<F>]) -> Result<(), SetupError> {
+    let wires: Vec<_> = gates
+        .iter()
+        .enumerate()
+        .flat_map(|(row, gate)| {
+            gate.wires
+                .iter()
+                .enumerate()
+                .filter(|(_, wire)| wire.row >= gates.len() || wire.col >= PERMUTS)
+                .map(move |(col, wire)| InvalidWire {
+                    row,
+                    col,
+                    wire: *wire,
+                })
+        })
+        .collect();
+    if wires.is_empty() {
+        Ok(())
+    } else {
+        Err(SetupError::InvalidWires {
+            rows: gates.len(),
+            cols: PERMUTS,
+            wires,
+        })
+    }
+}
+
+/// Checks that every wired cell of `gates` is pointed to by exactly one wire.
+/// Otherwise, the wires do not form a permutation,
+/// and the permutation argument would not enforce the copy constraints they were meant to.
+/// The wires are assumed to point to cells of the circuit (see [check_wiring]).
+pub fn check_permutation<F: FftField>(gates: &[CircuitGate<F>]) -> Result<(), SetupError> {
+    let mut sources = vec![[0usize; PERMUTS]; gates.len()];
+    for gate in gates {
+        for wire in &gate.wires {
+            sources[wire.row][wire.col] += 1;
+        }
+    }
+
+    let wires: Vec<_> = gates
+        .iter()
+        .enumerate()
+        .flat_map(|(row, gate)| {
+            let sources = &sources;
+            gate.wires
+                .iter()
+                .enumerate()
+                .filter(move |(_, wire)| sources[wire.row][wire.col] > 1)
+                .map(move |(col, wire)| InvalidWire {
+                    row,
+                    col,
+                    wire: *wire,
                 })
+        })
+        .collect();
+    if wires.is_empty() {
+        Ok(())
+    } else {
+        Err(SetupError::NotAPermutation { wires })
+    }
+}
+
+/// Returns the cycles of the permutation formed by the wires of `gates`,
+/// that is, the classes of cells that the permutation argument constrains to be equal.
+/// Each cycle starts from its first cell (in the order of the rows, then of the columns)
+/// and follows the wires, and the cycles of a single cell (the cells that are not copied) are omitted.
+pub fn permutation_cycles<F: FftField>(
+    gates: &[CircuitGate<F>],
+) -> Result<Vec<Vec<Wire>>, SetupError> {
+    check_wiring(gates)?;
+
+    let mut visited = vec![[false; PERMUTS]; gates.len()];
+    let mut cycles = vec![];
+    for row in 0..gates.len() {
+        for col in 0..PERMUTS {
+            let mut cycle = vec![];
+            let mut cell = Wire { row, col };
+            while !visited[cell.row][cell.col] {
+                visited[cell.row][cell.col] = true;
+                cycle.push(cell);
+                cell = gates[cell.row].wires[cell.col];
+            }
+            if cycle.len() > 1 {
+                cycles.push(cycle);
             }
         }
     }
+    Ok(cycles)
+}
+
+/// Describes the `cycles` returned by [permutation_cycles], one per line,
+/// like `cycle 0: (0, 2) -> (3, 0) -> (5, 1)` for the cells `(row, column)` of a cycle.
+pub fn describe_cycles(cycles: &[Vec<Wire>]) -> String {
+    cycles
+        .iter()
+        .enumerate()
+        .map(|(i, cycle)| {
+            let cells: Vec<_> = cycle
+                .iter()
+                .map(|cell| format!("({}, {})", cell.row, cell.col))
+                .collect();
+            format!("cycle {i}: {}\n", cells.join(" -> "))
+        })
+        .collect()
 }
 
 impl<F: FftField + SquareRootField> ConstraintSystem<F> {
     /// creates a constraint system from a vector of gates ([CircuitGate]), some sponge parameters ([ArithmeticSpongeParams]), and the number of public inputs.
     pub fn create(
+        gates: Vec<CircuitGate<F>>,
+        lookup_tables: Vec<Vec<Vec<F>>>,
+        fr_sponge_params: ArithmeticSpongeParams<F>,
+        public: usize,
+    ) -> Result<Self, SetupError> {
+        Self::create_with_runtime_tables(gates, lookup_tables, vec![], fr_sponge_params, public)
+    }
+
+    /// Same as [ConstraintSystem::create], for a circuit that also has `runtime_tables`,
+    /// whose second column is given by the prover (see [runtime_tables]).
+    /// Their ids follow the ids of the `lookup_tables`.
+    pub fn create_with_runtime_tables(
         mut gates: Vec<CircuitGate<F>>,
         lookup_tables: Vec<Vec<Vec<F>>>,
+        runtime_tables: Vec<RuntimeTableCfg<F>>,
         fr_sponge_params: ArithmeticSpongeParams<F>,
         public: usize,
-    ) -> Option<Self> {
+    ) -> Result<Self, SetupError> {
         // for some reason we need more than 1 gate for the circuit to work, see TODO below
         assert!(gates.len() > 1);
 
-        //~ 1. +3 on gates.len() here to ensure that we have room for the zero-knowledge entries of the permutation polynomial
+        //~ 1. Check that every wire of the gates points to a wired cell of the circuit,
+        //~    and that the wires form a permutation of these cells.
+        check_wiring(&gates)?;
+
+        //~ 2. +3 on gates.len() here to ensure that we have room for the zero-knowledge entries of the permutation polynomial
         //~    see https://minaprotocol.com/blog/a-more-efficient-approach-to-zero-knowledge-for-plonk
-        let domain = EvaluationDomains::<F>::create(gates.len() + ZK_ROWS as usize)?;
+        //~    and, if the circuit has lookup tables, enough rows for their entries.
+        let rows = std::cmp::max(
+            gates.len(),
+            lookup_table_rows(&gates, &lookup_tables, &runtime_tables),
+        ) + ZK_ROWS as usize;
+        let domain =
+            EvaluationDomains::<F>::create(rows).ok_or(SetupError::DomainCreation(rows))?;
         assert!(domain.d1.size > ZK_ROWS);
 
-        //~ 2. pad the rows: add zero gates to reach the domain size
+        //~ 3. pad the rows: add zero gates to reach the domain size
         let d1_size = domain.d1.size();
         let mut padding = (gates.len()..d1_size)
             .map(|i| {
@@ -441,14 +785,24 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
             ]
         };
 
-        let sigmam: [DP<F>; PERMUTS] = array_init(|i| sigmal1[i].clone().interpolate());
+        let sigmam: [DP<F>; PERMUTS] = fft::interpolate(&sigmal1);
 
-        let sigmal8 = array_init(|i| sigmam[i].evaluate_over_domain_by_ref(domain.d8));
+        let sigmal8 = fft::evaluate(&sigmam, domain.d8);
 
         // x^3 - x^2(w1+w2+w3) + x(w1w2+w1w3+w2w3) - w1w2w3
         let zkpm = zk_polynomial(domain.d1);
         let zkpl = zkpm.evaluate_over_domain_by_ref(domain.d8);
 
+        // for a in H, Z_H / (x - a) = sum_i a^(n - 1 - i) x^i
+        let n = domain.d1.size as usize;
+        let perm_bnd8 = [F::one(), sid[n - 3]].map(|a| {
+            let mut coeffs: Vec<_> = std::iter::successors(Some(F::one()), |c| Some(*c * a))
+                .take(n)
+                .collect();
+            coeffs.reverse();
+            DP::from_coefficients_vec(coeffs).evaluate_over_domain(domain.d8)
+        });
+
         //
         // Gates
         //
@@ -507,6 +861,7 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
         )
         .interpolate();
         let generic4 = genericm.evaluate_over_domain_by_ref(domain.d4);
+        let fused_generic = gates.iter().any(CircuitGate::has_fused_product);
 
         let chacha8 = {
             use GateType::*;
@@ -538,16 +893,93 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
             }
         };
 
-        let coefficientsm: [_; COLUMNS] = array_init(|i| {
-            let padded = gates
+        let boolean8 = if gates.iter().any(|gate| gate.typ == GateType::Boolean) {
+            Some(
+                E::<F, D<F>>::from_vec_and_domain(
+                    gates
+                        .iter()
+                        .map(|gate| {
+                            if gate.typ == GateType::Boolean {
+                                F::one()
+                            } else {
+                                F::zero()
+                            }
+                        })
+                        .collect(),
+                    domain.d1,
+                )
+                .interpolate()
+                .evaluate_over_domain(domain.d8),
+            )
+        } else {
+            None
+        };
+
+        // the weights are zero on the other rows, and thus act as the selector of the gate
+        let linear_combination8 = if gates
+            .iter()
+            .any(|gate| gate.typ == GateType::LinearCombination)
+        {
+            Some(array_init(|i| {
+                E::<F, D<F>>::from_vec_and_domain(
+                    gates
+                        .iter()
+                        .map(|gate| match gate.typ {
+                            GateType::LinearCombination => {
+                                gate.coeffs.get(i).cloned().unwrap_or_else(F::zero)
+                            }
+                            _ => F::zero(),
+                        })
+                        .collect(),
+                    domain.d1,
+                )
+                .interpolate()
+                .evaluate_over_domain(domain.d8)
+            }))
+        } else {
+            None
+        };
+
+        // the selectors of the partial rounds are zero on the other rows,
+        // and thus also act as the selector of their corrections to the Poseidon constraints
+        let partial_rounds8 = if gates
+            .iter()
+            .any(|gate| gate.partial_rounds().contains(&true))
+        {
+            Some(array_init(|round| {
+                E::<F, D<F>>::from_vec_and_domain(
+                    gates
+                        .iter()
+                        .map(|gate| {
+                            if gate.partial_rounds()[round] {
+                                F::one()
+                            } else {
+                                F::zero()
+                            }
+                        })
+                        .collect(),
+                    domain.d1,
+                )
+                .interpolate()
+                .evaluate_over_domain(domain.d8)
+            }))
+        } else {
+            None
+        };
+
+        let coefficients: [Vec<F>; COLUMNS] = array_init(|i| {
+            let mut padded: Vec<_> = gates
                 .iter()
                 .map(|gate| gate.coeffs.get(i).cloned().unwrap_or_else(F::zero))
                 .collect();
-            let eval = E::from_vec_and_domain(padded, domain.d1);
-            eval.interpolate()
+            padded.resize(domain.d1.size(), F::zero());
+            padded
         });
+        let coefficients_sparse = array_init(|i| SparseEvals::from_evals(&coefficients[i]));
+        // the zero columns are interpolated and extended for free (see fft)
+        let coefficientsm = fft::interpolate_columns(&coefficients, domain.d1);
         // TODO: This doesn't need to be degree 8 but that would require some changes in expr
-        let coefficients8 = array_init(|i| coefficientsm[i].evaluate_over_domain_by_ref(domain.d8));
+        let coefficients8 = fft::evaluate(&coefficientsm, domain.d8);
 
         let ps8 = psm.evaluate_over_domain_by_ref(domain.d8);
 
@@ -581,14 +1013,17 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
         //
 
         let lookup_constraint_system =
-            LookupConstraintSystem::create(&gates, lookup_tables, &domain);
+            LookupConstraintSystem::create(&gates, lookup_tables, runtime_tables, &domain)?;
 
         //
         // return result
         //
 
-        Some(ConstraintSystem {
+        Ok(ConstraintSystem {
             chacha8,
+            linear_combination8,
+            partial_rounds8,
+            boolean8,
             endomul_scalar8,
             domain,
             public,
@@ -598,7 +1033,9 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
             sigmam,
             genericm,
             generic4,
+            fused_generic,
             coefficients8,
+            coefficients_sparse,
             ps8,
             psm,
             complete_addl4,
@@ -612,11 +1049,15 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
             zkpl,
             zkpm,
             vanishes_on_last_4_rows,
+            perm_bnd8,
             gates,
             shift: shifts.shifts,
             endo,
             fr_sponge_params,
             lookup_constraint_system,
+            custom_gates: CustomGates::default(),
+            custom8: vec![],
+            boundary: vec![],
         })
     }
 
@@ -624,61 +1065,15 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
     /// assignements (witness) against the constraints
     ///     witness: wire assignement witness
     ///     RETURN: verification status
+    /// It runs the same checks as [check_witness](crate::circuits::check::check_witness).
     pub fn verify(&self, witness: &[Vec<F>; COLUMNS], public: &[F]) -> Result<(), GateError> {
-        // pad the witness
-        let pad = vec![F::zero(); self.domain.d1.size as usize - witness[0].len()];
-        let witness: [Vec<F>; COLUMNS] = array_init(|i| {
-            let mut w = witness[i].to_vec();
-            w.extend_from_slice(&pad);
-            w
-        });
-
-        // check each rows' wiring
-        for (row, gate) in self.gates.iter().enumerate() {
-            // check if wires are connected
-            for col in 0..PERMUTS {
-                let wire = gate.wires[col];
-
-                if wire.col >= PERMUTS {
-                    return Err(GateError::Custom {
-                        row,
-                        err: format!(
-                            "a wire can only be connected to the first {} columns",
-                            PERMUTS
-                        ),
-                    });
-                }
-
-                if witness[col][row] != witness[wire.col][wire.row] {
-                    return Err(GateError::DisconnectedWires(
-                        Wire { col, row },
-                        Wire {
-                            col: wire.col,
-                            row: wire.row,
-                        },
-                    ));
-                }
-            }
-
-            // for public gates, only the left wire is toggled
-            if row < self.public && gate.coeffs[0] != F::one() {
-                return Err(GateError::IncorrectPublic(row));
-            }
-
-            // check the gate's satisfiability
-            gate.verify(row, &witness, self, public)
-                .map_err(|err| GateError::Custom { row, err })?;
-        }
-
-        // all good!
-        Ok(())
+        check::check_witness(witness, self, public)
     }
 
     /// evaluate witness polynomials over domains
     pub fn evaluate(&self, w: &[DP<F>; COLUMNS], z: &DP<F>) -> WitnessOverDomains<F> {
         // compute shifted witness polynomials
-        let w8: [E<F, D<F>>; COLUMNS] =
-            array_init(|i| w[i].evaluate_over_domain_by_ref(self.domain.d8));
+        let w8: [E<F, D<F>>; COLUMNS] = fft::evaluate(w, self.domain.d8);
         let z8 = z.evaluate_over_domain_by_ref(self.domain.d8);
 
         let w4: [E<F, D<F>>; COLUMNS] = array_init(|i| {
@@ -736,4 +1131,125 @@ pub mod tests {
             Self::for_testing(fp_sponge_params, gates)
         }
     }
+
+    #[test]
+    fn test_invalid_wiring() {
+        use crate::circuits::polynomials::generic::testing::create_circuit;
+
+        let mut gates = create_circuit::<Fp>(0, 0);
+        let rows = gates.len();
+        let out_of_circuit = Wire {
+            row: rows + 40,
+            col: 0,
+        };
+        let unwired_column = Wire {
+            row: 0,
+            col: COLUMNS - 1,
+        };
+        gates[2].wires[1] = out_of_circuit;
+        gates[5].wires[0] = unwired_column;
+
+        let fp_sponge_params = oracle::pasta::fp_kimchi::params();
+        match ConstraintSystem::create(gates, vec![], fp_sponge_params, 0) {
+            Err(SetupError::InvalidWires { rows: r, wires, .. }) => {
+                assert_eq!(r, rows);
+                assert_eq!(
+                    wires,
+                    vec![
+                        InvalidWire {
+                            row: 2,
+                            col: 1,
+                            wire: out_of_circuit
+                        },
+                        InvalidWire {
+                            row: 5,
+                            col: 0,
+                            wire: unwired_column
+                        },
+                    ]
+                );
+            }
+            _ => panic!("the invalid wires were not reported"),
+        }
+    }
+
+    #[test]
+    fn test_wiring_must_be_a_permutation() {
+        use crate::circuits::polynomials::generic::testing::create_circuit;
+
+        // two wires pointing to the same cell
+        let mut gates = create_circuit::<Fp>(0, 0);
+        let target = gates[3].wires[2];
+        gates[1].wires[0] = target;
+
+        let fp_sponge_params = oracle::pasta::fp_kimchi::params();
+        assert_eq!(
+            ConstraintSystem::create(gates, vec![], fp_sponge_params, 0).map(|_| ()),
+            Err(SetupError::NotAPermutation {
+                wires: vec![
+                    InvalidWire {
+                        row: 1,
+                        col: 0,
+                        wire: target
+                    },
+                    InvalidWire {
+                        row: 3,
+                        col: 2,
+                        wire: target
+                    },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_permutation_cycles() {
+        use crate::circuits::builder::CircuitBuilder;
+
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.public_input();
+        let y = b.mul(x, x);
+        b.assert_zero(y);
+        let (gates, _) = b.build();
+
+        // the multiplication shares the row of the public input x, and the assertion on y starts the next row
+        let cycles = permutation_cycles(&gates).unwrap();
+        assert_eq!(
+            cycles,
+            vec![
+                vec![
+                    Wire { row: 0, col: 0 },
+                    Wire { row: 0, col: 3 },
+                    Wire { row: 0, col: 4 }
+                ],
+                vec![Wire { row: 0, col: 5 }, Wire { row: 1, col: 0 }],
+            ]
+        );
+        assert_eq!(
+            describe_cycles(&cycles),
+            "cycle 0: (0, 0) -> (0, 3) -> (0, 4)\ncycle 1: (0, 5) -> (1, 0)\n"
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        for log_size in [2, 10, 16] {
+            let domain = D::<Fp>::new(1 << log_size).unwrap();
+            let shifts = Shifts::new(&domain);
+            assert_eq!(Shifts::check(&domain, shifts.shifts()), Ok(()));
+
+            // the shifts must create disjoint cosets
+            let mut shifts = *shifts.shifts();
+            shifts[2] = shifts[1] * domain.group_gen;
+            assert_eq!(
+                Shifts::check(&domain, &shifts),
+                Err(SetupError::InvalidShifts)
+            );
+            shifts[2] = Fp::zero();
+            assert_eq!(
+                Shifts::check(&domain, &shifts),
+                Err(SetupError::InvalidShifts)
+            );
+        }
+    }
 }
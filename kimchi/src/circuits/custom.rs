@@ -0,0 +1,297 @@
+//! This module implements the gates that downstream crates define on their own.
+//!
+//! A custom gate implements [GateConstraint], and is registered with
+//! [ConstraintSystem::register_custom_gate] before the [ProverIndex](crate::prover_index::ProverIndex) is created.
+//! Registration numbers the custom gates from zero, and a row of the circuit uses the custom gate `id`
+//! if it is a [GateType::Custom] gate whose first coefficient is `id` (see [custom_gate]).
+//! Each custom gate gets its own selector polynomial, which the verifier index commits to.
+//!
+//! The constraints of a custom gate are used everywhere the constraints of the built-in gates are:
+//! they are added to the quotient polynomial by the prover, linearized for the verifier,
+//! and evaluated on the rows of the witness by [check_witness](crate::circuits::check::check_witness).
+//! As the verifier only gets the evaluations of the witness (and not of the coefficients),
+//! the constraints can only use the witness cells of the current and next rows,
+//! and must be of degree at most 7 so that the prover can evaluate them over the domain d8.
+//!
+//! Custom gates are not serialized along with the constraint system, and must be registered again.
+
+use crate::alphas::Alphas;
+use crate::circuits::{
+    argument::ArgumentType,
+    constraints::ConstraintSystem,
+    expr::{prologue::*, Column},
+    gate::{CircuitGate, CurrOrNext, GateType},
+    wires::{GateWires, COLUMNS},
+};
+use ark_ff::FftField;
+use ark_poly::{Evaluations, Radix2EvaluationDomain as D};
+use std::{fmt, ops::Deref, sync::Arc};
+
+/// A gate defined outside of this crate.
+pub trait GateConstraint<F: FftField>: Send + Sync {
+    /// The name of the gate, for error messages.
+    fn name(&self) -> &'static str;
+
+    /// The constraints that every row using the gate must satisfy
+    /// (they are multiplied by the selector of the gate, and combined with powers of alpha).
+    fn constraints(&self) -> Vec<E<F>>;
+
+    /// Checks the row `row` of the witness beyond the constraints, which are checked anyway.
+    /// This can be used to check the values that the constraints do not cover,
+    /// to give better error messages when testing circuits.
+    fn verify(&self, row: usize, witness: &[Vec<F>; COLUMNS]) -> Result<(), String> {
+        let _ = (row, witness);
+        Ok(())
+    }
+}
+
+/// The custom gates registered with a constraint system, numbered in registration order.
+#[derive(Clone)]
+pub struct CustomGates<F>(Vec<Arc<dyn GateConstraint<F>>>);
+
+impl<F> Default for CustomGates<F> {
+    fn default() -> Self {
+        Self(vec![])
+    }
+}
+
+impl<F> fmt::Debug for CustomGates<F>
+where
+    F: FftField,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|gate| gate.name()))
+            .finish()
+    }
+}
+
+impl<F> Deref for CustomGates<F> {
+    type Target = [Arc<dyn GateConstraint<F>>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<F: FftField> CustomGates<F> {
+    /// The largest number of constraints of a custom gate.
+    pub fn max_constraints(&self) -> u32 {
+        self.0
+            .iter()
+            .map(|gate| gate.constraints().len() as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the constraints of each custom gate,
+    /// combined with the powers of alpha of the gates and filtered by the selector of the gate.
+    pub fn combined_constraints(&self, alphas: &Alphas<F>) -> Vec<E<F>> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(id, gate)| {
+                let constraints = gate.constraints();
+                let alphas = alphas.get_exponents(
+                    ArgumentType::Gate(GateType::Custom),
+                    constraints.len() as u32,
+                );
+                E::cell(Column::CustomIndex(id), CurrOrNext::Curr)
+                    * E::combine_constraints(alphas, constraints)
+            })
+            .collect()
+    }
+}
+
+/// Returns true if `gate` uses the custom gate `id`.
+pub fn selects<F: FftField>(gate: &CircuitGate<F>, id: usize) -> bool {
+    gate.typ == GateType::Custom && gate.coeffs.first() == Some(&F::from(id as u64))
+}
+
+/// Returns a row using the custom gate `id`, with the given coefficients after the one holding `id`.
+pub fn custom_gate<F: FftField>(id: usize, wires: GateWires, coeffs: &[F]) -> CircuitGate<F> {
+    let mut all_coeffs = vec![F::from(id as u64)];
+    all_coeffs.extend_from_slice(coeffs);
+    CircuitGate {
+        typ: GateType::Custom,
+        wires,
+        coeffs: all_coeffs,
+    }
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    /// Registers a custom gate, returning the identifier of the gate to use in [custom_gate].
+    /// This computes the selector polynomial of the gate from the rows that use it.
+    pub fn register_custom_gate(&mut self, gate: Arc<dyn GateConstraint<F>>) -> usize {
+        let id = self.custom_gates.0.len();
+        let selector: Vec<F> = self
+            .gates
+            .iter()
+            .map(|g| if selects(g, id) { F::one() } else { F::zero() })
+            .collect();
+        let selector8 = Evaluations::<F, D<F>>::from_vec_and_domain(selector, self.domain.d1)
+            .interpolate()
+            .evaluate_over_domain(self.domain.d8);
+        self.custom_gates.0.push(gate);
+        self.custom8.push(selector8);
+        id
+    }
+
+    /// Returns the custom gate used by the row `gate`, if any.
+    pub fn custom_gate(&self, gate: &CircuitGate<F>) -> Option<(usize, &dyn GateConstraint<F>)> {
+        if gate.typ != GateType::Custom {
+            return None;
+        }
+        (0..self.custom_gates.len())
+            .find(|id| selects(gate, *id))
+            .map(|id| (id, self.custom_gates[id].as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuits::{check::check_witness, wires::Wire},
+        error::SetupError,
+        prover::ProverProof,
+        prover_index::{
+            testing::{new_index_for_test_with_custom_gates, try_new_index_for_test_with_cs},
+            ProverIndex,
+        },
+        verifier::batch_verify,
+    };
+    use ark_ff::{Field, One, Zero};
+    use array_init::array_init;
+    use commitment_dlog::commitment::CommitmentCurve;
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{
+        fp::Fp,
+        vesta::{Affine, VestaParameters},
+    };
+    use oracle::{
+        poseidon::PlonkSpongeConstantsKimchi,
+        sponge::{DefaultFqSponge, DefaultFrSponge},
+    };
+
+    type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+    type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+    /// Constrains `w_1 = w_0^5` and `w_0` of the next row to be `w_1`.
+    struct Fifth;
+
+    impl GateConstraint<Fp> for Fifth {
+        fn name(&self) -> &'static str {
+            "fifth"
+        }
+
+        fn constraints(&self) -> Vec<E<Fp>> {
+            let w = |i, row| E::cell(Column::Witness(i), row);
+            vec![
+                w(0, CurrOrNext::Curr).pow(5) - w(1, CurrOrNext::Curr),
+                w(0, CurrOrNext::Next) - w(1, CurrOrNext::Curr),
+            ]
+        }
+    }
+
+    /// Constrains `w_1 = w_0^k`.
+    struct Power(u64);
+
+    impl GateConstraint<Fp> for Power {
+        fn name(&self) -> &'static str {
+            "power"
+        }
+
+        fn constraints(&self) -> Vec<E<Fp>> {
+            let w = |i| E::cell(Column::Witness(i), CurrOrNext::Curr);
+            vec![w(0).pow(self.0) - w(1)]
+        }
+    }
+
+    #[test]
+    fn test_custom_gate() {
+        // x -> x^5 -> x^25, then a zero gate to hold the result
+        let rows = 8;
+        let mut gates: Vec<_> = (0..2)
+            .map(|row| custom_gate(0, Wire::new(row), &[]))
+            .collect();
+        gates.extend((2..rows).map(|row| CircuitGate::zero(Wire::new(row))));
+
+        let x = Fp::from(3u64);
+        let mut witness: [Vec<Fp>; COLUMNS] = array_init(|_| vec![Fp::zero(); rows]);
+        witness[0][0] = x;
+        witness[1][0] = x.pow([5]);
+        witness[0][1] = witness[1][0];
+        witness[1][1] = x.pow([25]);
+        witness[0][2] = witness[1][1];
+
+        let index = new_index_for_test_with_custom_gates(gates, 0, vec![Arc::new(Fifth)]);
+        assert_eq!(
+            index.cs.custom_gate(&index.cs.gates[1]).map(|(id, _)| id),
+            Some(0)
+        );
+        check_witness(&witness, &index.cs, &[]).unwrap();
+
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+            &group_map,
+            witness.clone(),
+            &index,
+            vec![],
+        )
+        .unwrap();
+        let verifier_index = index.verifier_index();
+        assert_eq!(verifier_index.custom_comm.len(), 1);
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+            .unwrap();
+
+        // the constraints of the custom gate are enforced
+        let mut wrong = witness;
+        wrong[1][1] += Fp::one();
+        wrong[0][2] = wrong[1][1];
+        assert!(check_witness(&wrong, &index.cs, &[]).is_err());
+    }
+
+    #[test]
+    fn test_custom_gate_degree() {
+        let rows = 8;
+        let mut gates = vec![custom_gate(0, Wire::new(0), &[])];
+        gates.extend((1..rows).map(|row| CircuitGate::zero(Wire::new(row))));
+        let index = |k| {
+            let mut cs = ConstraintSystem::<Fp>::create(
+                gates.clone(),
+                vec![],
+                oracle::pasta::fp_kimchi::params(),
+                0,
+            )
+            .unwrap();
+            cs.register_custom_gate(Arc::new(Power(k)));
+            try_new_index_for_test_with_cs(cs, ProverIndex::try_create)
+        };
+
+        // with its selector, the constraint of degree 8 does not fit in the domain of size 8n
+        assert_eq!(
+            index(8).err(),
+            Some(SetupError::ConstraintDegree { degree: 9, max: 8 })
+        );
+
+        // the one of degree 7 does, and its quotient fits in the commitment
+        let index = index(7).unwrap();
+        let n = index.cs.domain.d1.size as usize;
+        assert_eq!(index.max_quot_size, 7 * n);
+
+        let x = Fp::from(2u64);
+        let mut witness: [Vec<Fp>; COLUMNS] = array_init(|_| vec![Fp::zero(); rows]);
+        witness[0][0] = x;
+        witness[1][0] = x.pow([7]);
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let proof =
+            ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+                .unwrap();
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(
+            &group_map,
+            &[(&index.verifier_index(), &proof)],
+        )
+        .unwrap();
+    }
+}
@@ -2,6 +2,7 @@ use crate::circuits::{
     constraints::eval_vanishes_on_last_4_rows,
     domains::EvaluationDomains,
     gate::{CurrOrNext, GateType},
+    gates::poseidon::ROUNDS_PER_ROW,
     scalars::ProofEvaluations,
     wires::COLUMNS,
 };
@@ -12,7 +13,7 @@ use ark_poly::{
 use itertools::Itertools;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter::FromIterator;
 use std::ops::{Add, AddAssign, Mul, Neg, Sub};
 use CurrOrNext::*;
@@ -46,6 +47,10 @@ pub struct LookupEnvironment<'a, F: FftField> {
     pub selectors: &'a Vec<Evaluations<F, D<F>>>,
     /// The evaluations of the combined lookup table polynomial.
     pub table: &'a Evaluations<F, D<F>>,
+    /// The selector of the rows of the runtime table, if the circuit looks up one.
+    pub runtime_selector: Option<&'a Evaluations<F, D<F>>>,
+    /// The second column of the runtime table, given by the prover, if the circuit looks up one.
+    pub runtime_table: Option<&'a Evaluations<F, D<F>>>,
 }
 
 /// The collection of polynomials (all in evaluation form) and constants
@@ -72,6 +77,12 @@ pub struct Environment<'a, F: FftField> {
     pub domain: EvaluationDomains<F>,
     /// Lookup specific polynomials
     pub lookup: Option<LookupEnvironment<'a, F>>,
+    /// The selector polynomials of the custom gates.
+    pub custom: &'a [Evaluations<F, D<F>>],
+    /// The weights of the linear combination gates, if the circuit uses them.
+    pub linear_combination: Option<&'a [Evaluations<F, D<F>>; COLUMNS]>,
+    /// The selectors of the partial rounds of the Poseidon gates, if the circuit has some.
+    pub partial_rounds: Option<&'a [Evaluations<F, D<F>>; ROUNDS_PER_ROW]>,
 }
 
 impl<'a, F: FftField> Environment<'a, F> {
@@ -86,10 +97,15 @@ impl<'a, F: FftField> Environment<'a, F> {
             LookupSorted(i) => lookup.map(|l| &l.sorted[*i]),
             LookupAggreg => lookup.map(|l| l.aggreg),
             LookupTable => lookup.map(|l| l.table),
+            LookupRuntimeSelector => lookup.and_then(|l| l.runtime_selector),
+            LookupRuntimeTable => lookup.and_then(|l| l.runtime_table),
             Index(t) => match self.index.get(t) {
                 None => None,
                 Some(e) => Some(e),
             },
+            CustomIndex(i) => self.custom.get(*i),
+            Weight(i) => self.linear_combination.map(|w| &w[*i]),
+            PartialRound(i) => self.partial_rounds.map(|p| &p[*i]),
         }
     }
 }
@@ -125,6 +141,17 @@ pub enum Column {
     LookupKindIndex(usize),
     Index(GateType),
     Coefficient(usize),
+    /// The selector of the custom gate with the given identifier (see [crate::circuits::custom])
+    CustomIndex(usize),
+    /// The weights of the linear combination gate (see [crate::circuits::polynomials::linear_combination])
+    Weight(usize),
+    /// The selector of the partial rounds of the Poseidon gate with the given position in a row
+    /// (see [crate::circuits::polynomials::poseidon])
+    PartialRound(usize),
+    /// The selector of the rows of the runtime table (see [crate::circuits::polynomials::runtime_tables])
+    LookupRuntimeSelector,
+    /// The second column of the runtime table, committed by the prover
+    LookupRuntimeTable,
 }
 
 impl Column {
@@ -147,6 +174,11 @@ impl Column {
                 format!("{:?}", gate)
             }
             Column::Coefficient(i) => format!("c_{{{}}}", i),
+            Column::CustomIndex(i) => format!("custom_{{{}}}", i),
+            Column::Weight(i) => format!("q_{{{}}}", i),
+            Column::PartialRound(i) => format!("p_{{{}}}", i),
+            Column::LookupRuntimeSelector => "rs".to_string(),
+            Column::LookupRuntimeTable => "r".to_string(),
         }
     }
 }
@@ -403,12 +435,27 @@ impl Variable {
         match self.col {
             Witness(i) => Ok(evals.w[i]),
             Z => Ok(evals.z),
-            LookupSorted(i) => l.map(|l| l.sorted[i]),
+            LookupSorted(i) => l.and_then(|l| {
+                l.sorted
+                    .get(i)
+                    .copied()
+                    .ok_or("Lookup sorted polynomial out of range")
+            }),
             LookupAggreg => l.map(|l| l.aggreg),
             LookupTable => l.map(|l| l.table),
+            LookupRuntimeTable => l.and_then(|l| {
+                l.runtime
+                    .ok_or("The runtime table should not have been used")
+            }),
             Index(GateType::Poseidon) => Ok(evals.poseidon_selector),
             Index(GateType::Generic) => Ok(evals.generic_selector),
-            Coefficient(_) | LookupKindIndex(_) | Index(_) => {
+            Coefficient(_)
+            | LookupKindIndex(_)
+            | Index(_)
+            | CustomIndex(_)
+            | Weight(_)
+            | PartialRound(_)
+            | LookupRuntimeSelector => {
                 Err("Cannot get index evaluation (should have been linearized away)")
             }
         }
@@ -497,7 +544,9 @@ impl<C> Expr<C> {
         Expr::Constant(c)
     }
 
-    fn degree(&self, d1_size: u64) -> u64 {
+    /// The degree of the polynomial corresponding to this expression (at most),
+    /// for columns of degree `d1_size`.
+    pub fn degree(&self, d1_size: u64) -> u64 {
         use Expr::*;
         match self {
             Double(x) => x.degree(d1_size),
@@ -1217,6 +1266,35 @@ impl<F: FftField> Expr<ConstantExpr<F>> {
         }
     }
 
+    /// Evaluate an expression as a field element against the constants,
+    /// given the evaluation of each of its cells by `cell`.
+    /// Unlike [Expr::evaluate_], this can evaluate the columns of the index.
+    pub fn evaluate_cells<Err>(
+        &self,
+        d: D<F>,
+        pt: F,
+        cell: &impl Fn(&Variable) -> Result<F, Err>,
+        c: &Constants<F>,
+    ) -> Result<F, Err> {
+        use Expr::*;
+        let eval = |e: &Self| e.evaluate_cells(d, pt, cell, c);
+        Ok(match self {
+            Constant(x) => x.value(c),
+            Cell(v) => cell(v)?,
+            Double(x) => eval(x)?.double(),
+            Square(x) => eval(x)?.square(),
+            BinOp(Op2::Add, x, y) => eval(x)? + eval(y)?,
+            BinOp(Op2::Mul, x, y) => eval(x)? * eval(y)?,
+            BinOp(Op2::Sub, x, y) => eval(x)? - eval(y)?,
+            VanishesOnLast4Rows => eval_vanishes_on_last_4_rows(d, pt),
+            UnnormalizedLagrangeBasis(i) => {
+                d.evaluate_vanishing_polynomial(pt) / (pt - d.group_gen.pow(&[*i as u64]))
+            }
+            Pow(x, p) => eval(x)?.pow(&[*p as u64]),
+            Cache(_, e) => eval(e)?,
+        })
+    }
+
     /// Evaluate the constant expressions in this expression down into field elements.
     pub fn evaluate_constants(&self, env: &Environment<F>) -> Expr<F> {
         self.evaluate_constants_(&env.constants)
@@ -1432,7 +1510,7 @@ impl<F: FftField> Expr<F> {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// A "linearization", which is linear combination with `E` coefficients of
 /// columns.
 pub struct Linearization<E> {
@@ -1540,13 +1618,14 @@ impl<F: One> Expr<F> {
     }
 }
 
-type Monomials<F> = HashMap<Vec<Variable>, Expr<F>>;
+// the monomials are ordered, so that the linearization of an expression is always the same expression
+type Monomials<F> = BTreeMap<Vec<Variable>, Expr<F>>;
 
 fn mul_monomials<F: Neg<Output = F> + Clone + One + Zero + PartialEq>(
     e1: &Monomials<F>,
     e2: &Monomials<F>,
 ) -> Monomials<F> {
-    let mut res: HashMap<_, Expr<F>> = HashMap::new();
+    let mut res: Monomials<F> = BTreeMap::new();
     for (m1, c1) in e1.iter() {
         for (m2, c2) in e2.iter() {
             let mut m = m1.clone();
@@ -1580,9 +1659,9 @@ impl<F: Neg<Output = F> + Clone + One + Zero + PartialEq> Expr<F> {
         }
     }
 
-    fn monomials(&self, ev: &HashSet<Column>) -> HashMap<Vec<Variable>, Expr<F>> {
+    fn monomials(&self, ev: &HashSet<Column>) -> Monomials<F> {
         let sing = |v: Vec<Variable>, c: Expr<F>| {
-            let mut h = HashMap::new();
+            let mut h = BTreeMap::new();
             h.insert(v, c);
             h
         };
@@ -1615,7 +1694,7 @@ impl<F: Neg<Output = F> + Clone + One + Zero + PartialEq> Expr<F> {
                 acc
             }
             Double(e) => {
-                HashMap::from_iter(e.monomials(ev).into_iter().map(|(m, c)| (m, c.double())))
+                BTreeMap::from_iter(e.monomials(ev).into_iter().map(|(m, c)| (m, c.double())))
             }
             Cache(_, e) => e.monomials(ev),
             UnnormalizedLagrangeBasis(i) => constant(UnnormalizedLagrangeBasis(*i)),
@@ -1683,7 +1762,7 @@ impl<F: Neg<Output = F> + Clone + One + Zero + PartialEq> Expr<F> {
     /// compute it in that way. Instead, it computes it by reducing the expression into
     /// a sum of monomials with `F` coefficients, and then factors the monomials.
     pub fn linearize(&self, evaluated: HashSet<Column>) -> Result<Linearization<Expr<F>>, &str> {
-        let mut res: HashMap<Column, Expr<F>> = HashMap::new();
+        let mut res: BTreeMap<Column, Expr<F>> = BTreeMap::new();
         let mut constant_term: Expr<F> = Self::zero();
         let monomials = self.monomials(&evaluated);
 
@@ -1716,7 +1795,7 @@ impl<F: Neg<Output = F> + Clone + One + Zero + PartialEq> Expr<F> {
                         // with the above that moves v out of the map with .remove and
                         // into v + c.
                         //
-                        // I'm not sure if there's a way to do it with the HashMap API
+                        // I'm not sure if there's a way to do it with the map API
                         // without calling remove.
                     }
                 }
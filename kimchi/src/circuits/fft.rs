@@ -0,0 +1,106 @@
+//! This module implements helpers to run the many FFTs of the protocol in parallel.
+//!
+//! Each FFT is a radix-2 FFT from arkworks (itself multi-threaded),
+//! but there are often not enough butterflies in a single FFT to keep all the threads busy:
+//! these helpers additionally distribute independent polynomials (typically the columns of the witness)
+//! over the threads.
+//!
+//! When an FFT backend is installed (see [commitment_dlog::backend]), the FFTs are computed by that backend instead.
+
+use ark_ff::{FftField, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Evaluations, Radix2EvaluationDomain as D,
+    UVPolynomial,
+};
+use commitment_dlog::backend::fft_backend;
+use rayon::prelude::*;
+
+fn to_array<T, const N: usize>(v: Vec<T>) -> [T; N] {
+    array_init::from_iter(v).expect("one result per input")
+}
+
+// the zero polynomials, such as the unused columns of the witness or of the coefficients, skip their FFT
+fn ifft<F: FftField>(domain: D<F>, evals: &[F]) -> DensePolynomial<F> {
+    if evals.iter().all(|x| x.is_zero()) {
+        return DensePolynomial::zero();
+    }
+    let coeffs = match fft_backend::<F>() {
+        Some(backend) => backend.ifft(domain, evals),
+        None => domain.ifft(evals),
+    };
+    DensePolynomial::from_coefficients_vec(coeffs)
+}
+
+fn fft<F: FftField>(domain: D<F>, poly: &DensePolynomial<F>) -> Evaluations<F, D<F>> {
+    if poly.is_zero() {
+        return Evaluations::from_vec_and_domain(vec![F::zero(); domain.size()], domain);
+    }
+    match fft_backend::<F>() {
+        Some(backend) => {
+            Evaluations::from_vec_and_domain(backend.fft(domain, &poly.coeffs), domain)
+        }
+        None => poly.evaluate_over_domain_by_ref(domain),
+    }
+}
+
+/// Interpolates each column of evaluations over `domain`.
+pub fn interpolate_columns<F: FftField, const N: usize>(
+    columns: &[Vec<F>; N],
+    domain: D<F>,
+) -> [DensePolynomial<F>; N] {
+    to_array(
+        columns[..]
+            .par_iter()
+            .map(|col| ifft(domain, col))
+            .collect(),
+    )
+}
+
+/// Interpolates each of the `evals`.
+pub fn interpolate<F: FftField, const N: usize>(
+    evals: &[Evaluations<F, D<F>>; N],
+) -> [DensePolynomial<F>; N] {
+    to_array(
+        evals[..]
+            .par_iter()
+            .map(|evals| ifft(evals.domain(), &evals.evals))
+            .collect(),
+    )
+}
+
+/// Evaluates each of the `polys` over `domain`.
+pub fn evaluate<F: FftField, const N: usize>(
+    polys: &[DensePolynomial<F>; N],
+    domain: D<F>,
+) -> [Evaluations<F, D<F>>; N] {
+    to_array(polys[..].par_iter().map(|poly| fft(domain, poly)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use array_init::array_init;
+    use mina_curves::pasta::fp::Fp;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn parallel_ffts_match_sequential_ones() {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let d1 = D::<Fp>::new(16).unwrap();
+        let d4 = D::<Fp>::new(64).unwrap();
+
+        let columns: [Vec<Fp>; 5] = array_init(|_| (0..16).map(|_| Fp::rand(rng)).collect());
+        let polys = interpolate_columns(&columns, d1);
+        let evals4 = evaluate(&polys, d4);
+
+        for ((col, poly), evals4) in columns.iter().zip(&polys).zip(&evals4) {
+            let evals = Evaluations::from_vec_and_domain(col.clone(), d1);
+            assert_eq!(*poly, evals.interpolate_by_ref());
+            assert_eq!(*evals4, poly.evaluate_over_domain_by_ref(d4));
+        }
+
+        let evals1 = evaluate(&polys, d1);
+        assert_eq!(interpolate(&evals1), polys);
+    }
+}
@@ -0,0 +1,358 @@
+//! This module implements boolean gadgets on top of the generic gate.
+//!
+//! Besides [CircuitBuilder::assert_boolean], [CircuitBuilder::assert_bits], [CircuitBuilder::select] and [CircuitBuilder::unpack],
+//! these gadgets assume that their inputs are booleans:
+//! it is up to the caller to constrain the inputs of a circuit.
+//! Words are represented as vectors of bits, least significant bit first.
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gate::GateType,
+    wires::{COLUMNS, PERMUTS},
+};
+use ark_ff::{BigInteger, FftField, PrimeField};
+
+impl<F: FftField> CircuitBuilder<F> {
+    /// Constrains `b` to be a boolean, via `b * b - b = 0`.
+    /// This is a no-op if `b` is already constrained, or is the constant zero or one.
+    #[track_caller]
+    pub fn assert_boolean(&mut self, b: Var) {
+        if !self.mark_boolean(b) {
+            return;
+        }
+        self.generic(
+            [-F::one(), F::zero(), F::zero(), F::one(), F::zero()],
+            [Some(b), Some(b), None],
+        );
+    }
+
+    /// Constrains all the `bits` to be booleans, [PERMUTS] per row with [boolean](crate::circuits::polynomials::boolean) gates,
+    /// against two per row with [Self::assert_boolean].
+    /// The bits that are already constrained are skipped, and the last one or two bits use generic gates.
+    #[track_caller]
+    pub fn assert_bits(&mut self, bits: &[Var]) {
+        let bits: Vec<Var> = bits
+            .iter()
+            .copied()
+            .filter(|b| self.mark_boolean(*b))
+            .collect();
+        for chunk in bits.chunks(PERMUTS) {
+            if chunk.len() <= 2 {
+                for b in chunk {
+                    self.generic(
+                        [-F::one(), F::zero(), F::zero(), F::one(), F::zero()],
+                        [Some(*b), Some(*b), None],
+                    );
+                }
+            } else {
+                let mut cells = [None; COLUMNS];
+                for (cell, b) in cells.iter_mut().zip(chunk) {
+                    *cell = Some(*b);
+                }
+                self.gate(GateType::Boolean, vec![], cells);
+            }
+        }
+    }
+
+    /// Returns `a xor b`, computed as `a + b - 2ab`.
+    #[track_caller]
+    pub fn xor(&mut self, a: Var, b: Var) -> Var {
+        let o = self.compute_one(&[a, b], |v| v[0] + v[1] - (v[0] * v[1]).double());
+        self.generic(
+            [F::one(), F::one(), -F::one(), -F::from(2u64), F::zero()],
+            [Some(a), Some(b), Some(o)],
+        );
+        o
+    }
+
+    /// Returns `a and b`.
+    #[track_caller]
+    pub fn and(&mut self, a: Var, b: Var) -> Var {
+        self.mul(a, b)
+    }
+
+    /// Returns `(not a) and b`, computed as `b - ab`.
+    #[track_caller]
+    pub fn and_not(&mut self, a: Var, b: Var) -> Var {
+        let o = self.compute_one(&[a, b], |v| v[1] - v[0] * v[1]);
+        self.generic(
+            [F::zero(), F::one(), -F::one(), -F::one(), F::zero()],
+            [Some(a), Some(b), Some(o)],
+        );
+        o
+    }
+
+    /// Returns `a or b`, computed as `a + b - ab`.
+    #[track_caller]
+    pub fn or(&mut self, a: Var, b: Var) -> Var {
+        let o = self.compute_one(&[a, b], |v| v[0] + v[1] - v[0] * v[1]);
+        self.generic(
+            [F::one(), F::one(), -F::one(), -F::one(), F::zero()],
+            [Some(a), Some(b), Some(o)],
+        );
+        o
+    }
+
+    /// Returns `not a`, computed as `1 - a`.
+    #[track_caller]
+    pub fn not(&mut self, a: Var) -> Var {
+        let o = self.compute_one(&[a], |v| F::one() - v[0]);
+        self.generic(
+            [F::one(), F::zero(), F::one(), F::zero(), -F::one()],
+            [Some(a), None, Some(o)],
+        );
+        o
+    }
+
+    /// Returns `a` if `cond` is set, and `b` otherwise, computed as `cond * (a - b) + b`.
+    /// `cond` is constrained to be a boolean (see [Self::assert_boolean]),
+    /// so selecting several times on the same condition only constrains it once.
+    #[track_caller]
+    pub fn select(&mut self, cond: Var, a: Var, b: Var) -> Var {
+        self.assert_boolean(cond);
+        let diff = self.sub(a, b);
+        let t = self.mul(cond, diff);
+        self.add(t, b)
+    }
+
+    /// Returns the `n` bits of the constant `value`, least significant bit first.
+    pub fn constant_bits(&mut self, value: u64, n: usize) -> Vec<Var> {
+        (0..n)
+            .map(|i| {
+                let bit = i < 64 && (value >> i) & 1 == 1;
+                self.constant(if bit { F::one() } else { F::zero() })
+            })
+            .collect()
+    }
+
+    /// Returns `sum_i 2^i bits[i]`.
+    pub fn pack(&mut self, bits: &[Var]) -> Var {
+        let (msb, rest) = bits.split_last().expect("cannot pack an empty word");
+        let two = F::from(2u64);
+        rest.iter().rev().fold(*msb, |acc, bit| {
+            self.linear_combination(two, acc, F::one(), *bit)
+        })
+    }
+}
+
+impl<F: PrimeField> CircuitBuilder<F> {
+    /// Decomposes `x` into `n` bits, least significant bit first.
+    /// The bits are constrained to be booleans and to recompose to `x`.
+    pub fn unpack(&mut self, x: Var, n: usize) -> Vec<Var> {
+        assert!(
+            n < F::size_in_bits(),
+            "the decomposition would not be unique"
+        );
+        self.decompose(x, n)
+    }
+
+    /// Decomposes `x` into the bits of its canonical representation, least significant bit first.
+    /// Unlike [Self::unpack], which cannot decompose the elements of the field in as many bits as the modulus has,
+    /// the bits are constrained to recompose to `x` as an integer smaller than the modulus.
+    pub fn unpack_canonical(&mut self, x: Var) -> Vec<Var> {
+        let bits = self.decompose(x, F::size_in_bits());
+        // compare the bits with those of the modulus minus one, from the most significant one,
+        // `equal` holding whether the bits seen so far are equal to those of the modulus minus one
+        let max = (-F::one()).into_repr();
+        let mut equal = None;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            match (max.get_bit(i), equal) {
+                (true, None) => equal = Some(*bit),
+                (true, Some(eq)) => equal = Some(self.mul(eq, *bit)),
+                // the bit must be zero while the bits are equal
+                (false, None) => self.assert_zero(*bit),
+                (false, Some(eq)) => self.generic(
+                    [F::zero(), F::zero(), F::zero(), F::one(), F::zero()],
+                    [Some(eq), Some(*bit), None],
+                ),
+            }
+        }
+        bits
+    }
+
+    fn decompose(&mut self, x: Var, n: usize) -> Vec<Var> {
+        let bits = self.compute(&[x], n, move |v| {
+            let repr = v[0].into_repr();
+            (0..n)
+                .map(|i| if repr.get_bit(i) { F::one() } else { F::zero() })
+                .collect()
+        });
+        self.assert_bits(&bits);
+        let packed = self.pack(&bits);
+        self.assert_equal(packed, x);
+        bits
+    }
+
+    /// Returns the sum of `words` and of the constant `constant`, modulo `2^n`
+    /// where `n` is the length of the words.
+    pub fn add_words<W: AsRef<[Var]>>(&mut self, words: &[W], constant: u64) -> Vec<Var> {
+        let n = words.first().expect("nothing to add").as_ref().len();
+        assert!(words.iter().all(|word| word.as_ref().len() == n));
+
+        let mut terms = words.len();
+        let packed: Vec<_> = words.iter().map(|word| self.pack(word.as_ref())).collect();
+        let mut sum = packed[1..]
+            .iter()
+            .fold(packed[0], |acc, word| self.add(acc, *word));
+        if constant != 0 {
+            sum = self.add_constant(sum, F::from(constant));
+            terms += 1;
+        }
+
+        // the sum of `terms` words of `n` bits fits in `n + log2(terms)` bits
+        let carry_bits = (64 - (terms as u64 - 1).leading_zeros()) as usize;
+        let mut bits = self.unpack(sum, n + carry_bits);
+        bits.truncate(n);
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::builder::testing::verify_generic_circuit;
+    use ark_ff::FpParameters;
+    use mina_curves::pasta::fp::Fp;
+
+    #[test]
+    fn boolean_gadgets() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        let y = b.private_input();
+        b.assert_boolean(x);
+        b.assert_boolean(y);
+        let outs = [
+            b.xor(x, y),
+            b.and(x, y),
+            b.and_not(x, y),
+            b.or(x, y),
+            b.not(x),
+            b.select(x, y, x),
+        ];
+        let (gates, generator) = b.build();
+
+        for (x, y) in [(0u64, 0u64), (0, 1), (1, 0), (1, 1)] {
+            let private = [x.into(), y.into()];
+            let witness = generator.generate(&[], &private);
+            verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+            let values = generator.values(&[], &private);
+            let expected = [x ^ y, x & y, (1 - x) & y, x | y, 1 - x, x * y + (1 - x) * x];
+            for (out, expected) in outs.iter().zip(expected) {
+                assert_eq!(values[out.index()], expected.into());
+            }
+        }
+
+        let witness = generator.generate(&[], &[2u64.into(), 0u64.into()]);
+        assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+    }
+
+    #[test]
+    fn assert_bits_packs_the_bits() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let bits: Vec<_> = (0..16).map(|_| b.private_input()).collect();
+        b.assert_boolean(bits[0]);
+        let rows = b.rows();
+        b.assert_bits(&bits);
+        // 15 bits left, in two boolean gates and a generic gate
+        assert_eq!(b.rows() - rows, 3);
+        let (gates, generator) = b.build();
+        assert_eq!(
+            gates[rows..]
+                .iter()
+                .filter(|gate| gate.typ == GateType::Boolean)
+                .count(),
+            2
+        );
+
+        let private: Vec<Fp> = (0..16u64).map(|i| (i % 2).into()).collect();
+        let witness = generator.generate(&[], &private);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+        // a non-boolean value is caught in both kinds of gates
+        for i in [3, 15] {
+            let mut private = private.clone();
+            private[i] = 2u64.into();
+            let witness = generator.generate(&[], &private);
+            assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+        }
+    }
+
+    #[test]
+    fn select_constrains_the_condition() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let cond = b.private_input();
+        let x = b.private_input();
+        let y = b.private_input();
+        let first = b.select(cond, x, y);
+        let rows = b.rows();
+        let second = b.select(cond, y, x);
+        let (gates, generator) = b.build();
+
+        // the condition is only constrained by the first selection
+        assert_eq!(gates.len() - rows, 2);
+
+        for cond in [0u64, 1] {
+            let private = [cond.into(), 5u64.into(), 7u64.into()];
+            let witness = generator.generate(&[], &private);
+            verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+            let values = generator.values(&[], &private);
+            let (a, b) = if cond == 1 { (5u64, 7u64) } else { (7, 5) };
+            assert_eq!(values[first.index()], a.into());
+            assert_eq!(values[second.index()], b.into());
+        }
+
+        // a non-boolean condition would interpolate between the values
+        let witness = generator.generate(&[], &[2u64.into(), 5u64.into(), 7u64.into()]);
+        assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+    }
+
+    #[test]
+    fn unpack_canonical_rejects_the_other_representative() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        let bits = b.unpack_canonical(x);
+        let (gates, generator) = b.build();
+        assert_eq!(bits.len(), 255);
+
+        for x in [Fp::from(0u64), Fp::from(12345u64), -Fp::from(1u64)] {
+            let witness = generator.generate(&[], &[x]);
+            verify_generic_circuit(&gates, &witness, &[]).unwrap();
+        }
+
+        // small values can also be written as their sum with the modulus, which fits in the bits
+        let mut values = generator.values(&[], &[Fp::from(1u64)]);
+        let mut modulus_plus_one = <Fp as PrimeField>::Params::MODULUS;
+        assert!(!modulus_plus_one.add_nocarry(&1u64.into()));
+        for (i, bit) in bits.iter().enumerate() {
+            values[bit.index()] = Fp::from(modulus_plus_one.get_bit(i) as u64);
+        }
+        let witness = generator.layout_values(&values);
+        assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+    }
+
+    #[test]
+    fn add_words_wraps_around() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let words: Vec<Vec<Var>> = (0..3)
+            .map(|_| (0..8).map(|_| b.private_input()).collect())
+            .collect();
+        let sum = b.add_words(&words, 7);
+        let (gates, generator) = b.build();
+
+        let inputs = [200u64, 100, 31];
+        let private: Vec<Fp> = inputs
+            .iter()
+            .flat_map(|x| (0..8).map(move |i| ((x >> i) & 1).into()))
+            .collect();
+        let witness = generator.generate(&[], &private);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+        let values = generator.values(&[], &private);
+        let expected = (200u64 + 100 + 31 + 7) % 256;
+        for (i, bit) in sum.iter().enumerate() {
+            assert_eq!(values[bit.index()], ((expected >> i) & 1).into());
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! This module implements arithmetic modulo a foreign modulus `p` of at most 256 bits
+//! (for example the base field of secp256k1 or of ed25519) in a circuit over a native field `F`.
+//!
+//! An element of the foreign field is represented by [LIMBS] limbs of [LIMB_BITS] bits,
+//! least significant limb first, and is always kept reduced (smaller than `p`).
+//! The limbs are range-checked by decomposing them into bits (see [CircuitBuilder::unpack]).
+//!
+//! Each operation `a op b` is witnessed as its quotient `q` and its remainder `r`,
+//! such that `a op b = q p + r` over the integers:
+//! - the identity is checked limb by limb, carrying the (range-checked) overflow of each limb into the next one,
+//!   which proves it modulo `2^(LIMBS * LIMB_BITS)`;
+//! - for the multiplications, whose products overflow the limbs, the identity is also checked in the native field,
+//!   and as both sides are smaller than `2^(LIMBS * LIMB_BITS)` times the native modulus,
+//!   this proves the identity over the integers (by the Chinese remainder theorem).
+//!
+//! The native field must thus be large enough (about 250 bits, like the Pasta fields).
+
+use crate::circuits::builder::{CircuitBuilder, Var};
+use ark_ff::{Field, PrimeField};
+use num_bigint::BigUint;
+
+/// The number of bits of each limb.
+pub const LIMB_BITS: usize = 88;
+/// The number of limbs of an element.
+pub const LIMBS: usize = 3;
+
+/// The bits of the carries of the multiplications, whose limbs sum up to three products of limbs.
+const MUL_CARRY_BITS: usize = LIMB_BITS + 4;
+/// The bits of the carries of the additions and subtractions.
+const ADD_CARRY_BITS: usize = 3;
+
+/// An element of a foreign field, as its limbs.
+#[derive(Clone, Copy, Debug)]
+pub struct ForeignElement {
+    pub limbs: [Var; LIMBS],
+}
+
+/// The foreign field of modulus `modulus`.
+#[derive(Clone, Debug)]
+pub struct ForeignField {
+    modulus: BigUint,
+    bits: usize,
+}
+
+fn two_to<F: Field>(n: usize) -> F {
+    F::from(2u64).pow([n as u64])
+}
+
+/// The number of bits of the limb `i` of a number of `bits` bits.
+fn limb_bits(i: usize, bits: usize) -> usize {
+    bits.saturating_sub(i * LIMB_BITS).min(LIMB_BITS)
+}
+
+/// Constrains `x` to fit in `bits` bits.
+fn range_check<F: PrimeField>(b: &mut CircuitBuilder<F>, x: Var, bits: usize) {
+    if bits == 0 {
+        b.assert_zero(x);
+    } else {
+        b.unpack(x, bits);
+    }
+}
+
+/// Returns `sum_i c_i x_i` for the (at least two) `terms` `(c_i, x_i)`.
+fn lincomb<F: PrimeField>(b: &mut CircuitBuilder<F>, terms: &[(F, Var)]) -> Var {
+    let ((c0, x0), (c1, x1)) = (terms[0], terms[1]);
+    let first = b.linear_combination(c0, x0, c1, x1);
+    terms[2..].iter().fold(first, |acc, (c, x)| {
+        b.linear_combination(F::one(), acc, *c, *x)
+    })
+}
+
+/// Returns the carry `c = (z + prev) / 2^LIMB_BITS`, constrained to be an integer of `bits` bits (signed).
+fn carry<F: PrimeField>(b: &mut CircuitBuilder<F>, z: Var, prev: Option<Var>, bits: usize) -> Var {
+    let inputs: Vec<_> = std::iter::once(z).chain(prev).collect();
+    let inv = two_to::<F>(LIMB_BITS)
+        .inverse()
+        .expect("the native field is not binary");
+    let c = b.compute_one(&inputs, move |v| {
+        v.iter().fold(F::zero(), |acc, x| acc + x) * inv
+    });
+    let prev_coeff = if prev.is_some() { -F::one() } else { F::zero() };
+    b.generic(
+        [
+            two_to(LIMB_BITS),
+            -F::one(),
+            prev_coeff,
+            F::zero(),
+            F::zero(),
+        ],
+        [Some(c), Some(z), prev],
+    );
+    let shifted = b.add_constant(c, two_to(bits - 1));
+    range_check(b, shifted, bits);
+    c
+}
+
+impl ForeignField {
+    /// Creates the foreign field of modulus `modulus`, of at most 256 bits.
+    pub fn new(modulus: BigUint) -> Self {
+        let bits = modulus.bits() as usize;
+        assert!(bits >= 2, "the modulus is too small");
+        assert!(bits <= 256, "the modulus does not fit in the limbs");
+        Self { modulus, bits }
+    }
+
+    /// The modulus of the field.
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    /// Returns the limbs of `x`, to be given as inputs of the circuit (see [ForeignField::private_input]).
+    pub fn to_limbs<F: PrimeField>(x: &BigUint) -> [F; LIMBS] {
+        let mask = (BigUint::from(1u8) << LIMB_BITS) - 1u8;
+        array_init::array_init(|i| F::from((x >> (i * LIMB_BITS)) & &mask))
+    }
+
+    /// Returns the number represented by `limbs`.
+    pub fn from_limbs<F: PrimeField>(limbs: &[F]) -> BigUint {
+        limbs.iter().rev().fold(BigUint::from(0u8), |acc, limb| {
+            let limb: BigUint = (*limb).into();
+            (acc << LIMB_BITS) + limb
+        })
+    }
+
+    fn check_native<F: PrimeField>(&self) {
+        assert!(
+            2 * self.bits + 2 < LIMBS * LIMB_BITS + F::size_in_bits() - 1,
+            "the native field is too small for this foreign field"
+        );
+    }
+
+    /// Constrains each limb of `x` to the bits of the elements of the field.
+    fn range_check<F: PrimeField>(&self, b: &mut CircuitBuilder<F>, x: &ForeignElement) {
+        for (i, limb) in x.limbs.iter().enumerate() {
+            range_check(b, *limb, limb_bits(i, self.bits));
+        }
+    }
+
+    /// Constrains `x`, whose limbs are range-checked, to be smaller than the modulus,
+    /// by checking that `x + 2^bits - p` still fits in `bits` bits.
+    fn assert_reduced<F: PrimeField>(&self, b: &mut CircuitBuilder<F>, x: &ForeignElement) {
+        let offset = (BigUint::from(1u8) << self.bits) - &self.modulus;
+        let offset_limbs: [F; LIMBS] = Self::to_limbs(&offset);
+
+        // the limbs of x + offset and the carries between them
+        let outputs = b.compute(&x.limbs, 2 * LIMBS - 1, move |v| {
+            let sum = Self::from_limbs(v) + &offset;
+            let limbs: [F; LIMBS] = Self::to_limbs(&sum);
+            let mut outputs = limbs.to_vec();
+            // the limb i overflows if the low limbs of x + offset do
+            for i in 1..LIMBS {
+                let low = |y: &BigUint| y % (BigUint::from(1u8) << (i * LIMB_BITS));
+                let overflow = low(&Self::from_limbs(v)) + low(&offset)
+                    >= BigUint::from(1u8) << (i * LIMB_BITS);
+                outputs.push(if overflow { F::one() } else { F::zero() });
+            }
+            outputs
+        });
+        let (sum, carries) = outputs.split_at(LIMBS);
+        for carry in carries {
+            b.assert_boolean(*carry);
+        }
+        for (i, offset) in offset_limbs.iter().enumerate() {
+            // x_i + carry_{i-1} + offset_i - 2^LIMB_BITS carry_i = sum_i
+            let input = match i {
+                0 => x.limbs[0],
+                _ => b.add(x.limbs[i], carries[i - 1]),
+            };
+            let (carry, carry_coeff) = match carries.get(i) {
+                Some(carry) => (Some(*carry), -two_to::<F>(LIMB_BITS)),
+                None => (None, F::zero()),
+            };
+            b.generic(
+                [F::one(), carry_coeff, -F::one(), F::zero(), *offset],
+                [Some(input), carry, Some(sum[i])],
+            );
+            range_check(b, sum[i], limb_bits(i, self.bits));
+        }
+    }
+
+    /// Allocates a private input, constrained to be an element of the field.
+    /// Its value is given as its limbs (see [ForeignField::to_limbs]).
+    pub fn private_input<F: PrimeField>(&self, b: &mut CircuitBuilder<F>) -> ForeignElement {
+        let x = ForeignElement {
+            limbs: array_init::array_init(|_| b.private_input()),
+        };
+        self.range_check(b, &x);
+        self.assert_reduced(b, &x);
+        x
+    }
+
+    /// Returns the constant `x`, reduced modulo `p`.
+    pub fn constant<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &BigUint,
+    ) -> ForeignElement {
+        let limbs: [F; LIMBS] = Self::to_limbs(&(x % &self.modulus));
+        ForeignElement {
+            limbs: array_init::array_init(|i| b.constant(limbs[i])),
+        }
+    }
+
+    /// Constrains `x` and `y` to be equal.
+    pub fn assert_equal<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &ForeignElement,
+        y: &ForeignElement,
+    ) {
+        // both are reduced, so their limbs are equal
+        for (x, y) in x.limbs.iter().zip(&y.limbs) {
+            b.assert_equal(*x, *y);
+        }
+    }
+
+    /// Witnesses the quotient and the remainder of `f(x, y)` by `p`,
+    /// the quotient having `quotient_limbs` limbs, and constrains the remainder to be an element of the field.
+    fn divide<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &ForeignElement,
+        y: &ForeignElement,
+        quotient_limbs: usize,
+        f: impl Fn(BigUint, BigUint) -> BigUint + Send + Sync + 'static,
+    ) -> (Vec<Var>, ForeignElement) {
+        let modulus = self.modulus.clone();
+        let inputs: Vec<_> = x.limbs.iter().chain(&y.limbs).copied().collect();
+        let outputs = b.compute(&inputs, quotient_limbs + LIMBS, move |v| {
+            let value = f(Self::from_limbs(&v[..LIMBS]), Self::from_limbs(&v[LIMBS..]));
+            let q: [F; LIMBS] = Self::to_limbs(&(&value / &modulus));
+            let r: [F; LIMBS] = Self::to_limbs(&(&value % &modulus));
+            q[..quotient_limbs].iter().chain(&r).copied().collect()
+        });
+        let r = ForeignElement {
+            limbs: array_init::array_init(|i| outputs[quotient_limbs + i]),
+        };
+        self.range_check(b, &r);
+        self.assert_reduced(b, &r);
+        (outputs[..quotient_limbs].to_vec(), r)
+    }
+
+    /// Returns `x + y`, or `x - y` if `negate`, witnessed as `x + y = q p + r` or `x - y + p = q p + r`
+    /// with `q` a boolean.
+    fn add_sub<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &ForeignElement,
+        y: &ForeignElement,
+        negate: bool,
+    ) -> ForeignElement {
+        let modulus = self.modulus.clone();
+        let (q, r) = self.divide(b, x, y, 1, move |x, y| {
+            if negate {
+                // x - y + p, whose quotient is 1 if x >= y and 0 otherwise
+                x + &modulus - y
+            } else {
+                x + y
+            }
+        });
+        let q = q[0];
+        b.assert_boolean(q);
+        let p: [F; LIMBS] = Self::to_limbs(&self.modulus);
+        let (sign, q_sign) = if negate {
+            // x - y + p = r + q p, i.e. x - y - r + (1 - q) p = 0
+            (-F::one(), F::one())
+        } else {
+            (F::one(), -F::one())
+        };
+        let not_q = if negate { b.not(q) } else { q };
+
+        // z_i = x_i +/- y_i -/+ p_i q - r_i
+        let z: Vec<_> = (0..LIMBS)
+            .map(|i| {
+                lincomb(
+                    b,
+                    &[
+                        (F::one(), x.limbs[i]),
+                        (sign, y.limbs[i]),
+                        (q_sign * p[i], not_q),
+                        (-F::one(), r.limbs[i]),
+                    ],
+                )
+            })
+            .collect();
+        let c0 = carry(b, z[0], None, ADD_CARRY_BITS);
+        let c1 = carry(b, z[1], Some(c0), ADD_CARRY_BITS);
+        // the sum does not overflow the limbs, so nothing is carried out of the last one
+        b.generic(
+            [F::one(), F::one(), F::zero(), F::zero(), F::zero()],
+            [Some(z[2]), Some(c1), None],
+        );
+        r
+    }
+
+    /// Returns `x + y` modulo `p`.
+    pub fn add<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &ForeignElement,
+        y: &ForeignElement,
+    ) -> ForeignElement {
+        self.add_sub(b, x, y, false)
+    }
+
+    /// Returns `x - y` modulo `p`.
+    pub fn sub<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &ForeignElement,
+        y: &ForeignElement,
+    ) -> ForeignElement {
+        self.add_sub(b, x, y, true)
+    }
+
+    /// Returns `x * y` modulo `p`.
+    pub fn mul<F: PrimeField>(
+        &self,
+        b: &mut CircuitBuilder<F>,
+        x: &ForeignElement,
+        y: &ForeignElement,
+    ) -> ForeignElement {
+        self.check_native::<F>();
+        let (q, r) = self.divide(b, x, y, LIMBS, |x, y| x * y);
+        let q = ForeignElement {
+            limbs: array_init::array_init(|i| q[i]),
+        };
+        // the quotient is smaller than p, as x and y are
+        self.range_check(b, &q);
+        let p: [F; LIMBS] = Self::to_limbs(&self.modulus);
+
+        // z_k = sum_{i + j = k} x_i y_j - p_i q_j, minus r_k
+        let z: Vec<_> = (0..LIMBS)
+            .map(|k| {
+                let mut terms = vec![(-F::one(), r.limbs[k])];
+                for i in 0..=k {
+                    let product = b.mul(x.limbs[i], y.limbs[k - i]);
+                    terms.push((F::one(), product));
+                    terms.push((-p[i], q.limbs[k - i]));
+                }
+                lincomb(b, &terms)
+            })
+            .collect();
+        let c0 = carry(b, z[0], None, MUL_CARRY_BITS);
+        let c1 = carry(b, z[1], Some(c0), MUL_CARRY_BITS);
+        // the identity holds modulo 2^(LIMBS * LIMB_BITS), whatever is carried out of the last limb
+        carry(b, z[2], Some(c1), MUL_CARRY_BITS);
+
+        // and in the native field
+        let native = |b: &mut CircuitBuilder<F>, x: &ForeignElement| {
+            let terms: Vec<_> = (0..LIMBS)
+                .map(|i| (two_to::<F>(i * LIMB_BITS), x.limbs[i]))
+                .collect();
+            lincomb(b, &terms)
+        };
+        let (x_n, y_n, q_n, r_n) = (native(b, x), native(b, y), native(b, &q), native(b, &r));
+        let xy = b.mul(x_n, y_n);
+        b.generic(
+            [
+                F::one(),
+                -F::from(self.modulus.clone()),
+                -F::one(),
+                F::zero(),
+                F::zero(),
+            ],
+            [Some(xy), Some(q_n), Some(r_n)],
+        );
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::builder::testing::verify_generic_circuit;
+    use mina_curves::pasta::fp::Fp;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    fn check_arithmetic(modulus: BigUint) {
+        let field = ForeignField::new(modulus.clone());
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = field.private_input(&mut b);
+        let y = field.private_input(&mut b);
+        let expected: Vec<_> = (0..3).map(|_| field.private_input(&mut b)).collect();
+        let sum = field.add(&mut b, &x, &y);
+        let difference = field.sub(&mut b, &x, &y);
+        let product = field.mul(&mut b, &x, &y);
+        for (result, expected) in [sum, difference, product].iter().zip(&expected) {
+            field.assert_equal(&mut b, result, expected);
+        }
+        let (gates, generator) = b.build();
+
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let mut random = || {
+            let mut bytes = [0u8; 40];
+            rng.fill_bytes(&mut bytes);
+            BigUint::from_bytes_le(&bytes) % &modulus
+        };
+        let mut pairs: Vec<_> = (0..3).map(|_| (random(), random())).collect();
+        let max = &modulus - 1u8;
+        pairs.push((max.clone(), max));
+        pairs.push((BigUint::from(0u8), BigUint::from(1u8)));
+
+        for (a, c) in pairs {
+            let results = [
+                (&a + &c) % &modulus,
+                (&a + &modulus - &c) % &modulus,
+                (&a * &c) % &modulus,
+            ];
+            let mut private: Vec<Fp> = vec![];
+            for value in [&a, &c].into_iter().chain(&results) {
+                private.extend(ForeignField::to_limbs::<Fp>(value));
+            }
+            let witness = generator.generate(&[], &private);
+            verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+            // a wrong product is rejected
+            let mut wrong = private.clone();
+            wrong[4 * LIMBS] += Fp::from(1u64);
+            let witness = generator.generate(&[], &wrong);
+            assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+        }
+
+        // unreduced inputs are rejected
+        let mut private: Vec<Fp> = ForeignField::to_limbs::<Fp>(&modulus).to_vec();
+        private.extend([Fp::from(0u64); 4 * LIMBS]);
+        let witness = generator.generate(&[], &private);
+        assert!(verify_generic_circuit(&gates, &witness, &[]).is_err());
+    }
+
+    #[test]
+    fn secp256k1_arithmetic() {
+        let p = BigUint::parse_bytes(
+            b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            16,
+        )
+        .unwrap();
+        check_arithmetic(p);
+    }
+
+    #[test]
+    fn small_modulus_arithmetic() {
+        // 2^127 - 1, whose elements do not use the last limb
+        check_arithmetic((BigUint::from(1u8) << 127) - 1u8);
+    }
+}
@@ -0,0 +1,253 @@
+//! This module implements the Keccak-f\[1600\] permutation gadget,
+//! and the Keccak-256 and SHA3-256 hashes built on top of it.
+//!
+//! The state is made of 25 lanes of 64 bits (least significant bit first),
+//! the lane `(x, y)` being stored at index `x + 5 * y`.
+
+use crate::circuits::builder::{CircuitBuilder, Var};
+use ark_ff::FftField;
+
+/// Number of bits in a lane
+const LANE: usize = 64;
+
+/// Number of lanes in the state
+pub const LANES: usize = 25;
+
+/// Number of rounds of the permutation
+pub const ROUNDS: usize = 24;
+
+/// The rate (in bits) of Keccak-256 and SHA3-256
+pub const RATE_256: usize = 1088;
+
+/// The rotation offsets of the rho step, indexed by lane
+const RHO: [usize; LANES] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// The round constants of the iota step
+pub const RC: [u64; ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+fn xor_lanes<F: FftField>(b: &mut CircuitBuilder<F>, x: &[Var], y: &[Var]) -> Vec<Var> {
+    x.iter().zip(y).map(|(x, y)| b.xor(*x, *y)).collect()
+}
+
+fn rotl(lane: &[Var], r: usize) -> Vec<Var> {
+    (0..LANE).map(|i| lane[(i + LANE - r) % LANE]).collect()
+}
+
+/// Creates the gates of a single round of the permutation.
+fn round<F: FftField>(b: &mut CircuitBuilder<F>, a: &[Vec<Var>], rc: u64) -> Vec<Vec<Var>> {
+    // theta
+    let c: Vec<_> = (0..5)
+        .map(|x| (1..5).fold(a[x].clone(), |acc, y| xor_lanes(b, &acc, &a[x + 5 * y])))
+        .collect();
+    let d: Vec<_> = (0..5)
+        .map(|x| xor_lanes(b, &c[(x + 4) % 5], &rotl(&c[(x + 1) % 5], 1)))
+        .collect();
+    let a: Vec<_> = (0..LANES).map(|i| xor_lanes(b, &a[i], &d[i % 5])).collect();
+
+    // rho and pi
+    let mut p = vec![vec![]; LANES];
+    for x in 0..5 {
+        for y in 0..5 {
+            p[y + 5 * ((2 * x + 3 * y) % 5)] = rotl(&a[x + 5 * y], RHO[x + 5 * y]);
+        }
+    }
+
+    // chi
+    let mut a: Vec<Vec<_>> = (0..LANES)
+        .map(|i| {
+            let (x, y) = (i % 5, i / 5);
+            (0..LANE)
+                .map(|z| {
+                    let t = b.and_not(p[(x + 1) % 5 + 5 * y][z], p[(x + 2) % 5 + 5 * y][z]);
+                    b.xor(p[i][z], t)
+                })
+                .collect()
+        })
+        .collect();
+
+    // iota
+    for (z, bit) in a[0].iter_mut().enumerate() {
+        if (rc >> z) & 1 == 1 {
+            *bit = b.not(*bit);
+        }
+    }
+
+    a
+}
+
+/// Creates the gates of the Keccak-f\[1600\] permutation of `state` (25 lanes).
+/// Returns the permuted state.
+pub fn keccak_f<F: FftField>(b: &mut CircuitBuilder<F>, state: &[Vec<Var>]) -> Vec<Vec<Var>> {
+    assert_eq!(state.len(), LANES);
+    b.namespace("keccak_f", |b| {
+        RC.iter()
+            .enumerate()
+            .fold(state.to_vec(), |state, (i, rc)| {
+                b.namespace(&format!("round{i}"), |b| round(b, &state, *rc))
+            })
+    })
+}
+
+/// Creates the gates of a Keccak sponge absorbing `message` with the given `rate`,
+/// after appending the domain separation `suffix` and the pad10*1 padding.
+/// The message bits are constrained to be booleans.
+/// Returns the first `out` bits of the squeezed state.
+pub fn sponge<F: FftField>(
+    b: &mut CircuitBuilder<F>,
+    message: &[Var],
+    suffix: &[bool],
+    rate: usize,
+    out: usize,
+) -> Vec<Var> {
+    assert!(rate % LANE == 0 && rate < LANES * LANE);
+    assert!(
+        out <= rate,
+        "squeezing more than one block is not supported"
+    );
+
+    for bit in message {
+        b.assert_boolean(*bit);
+    }
+
+    let zero = b.constant(F::zero());
+    let one = b.constant(F::one());
+
+    // padding
+    let mut padded = message.to_vec();
+    padded.extend(suffix.iter().map(|bit| if *bit { one } else { zero }));
+    let zeros = (rate - (padded.len() + 2) % rate) % rate;
+    padded.push(one);
+    padded.extend(std::iter::repeat(zero).take(zeros));
+    padded.push(one);
+
+    // absorb
+    let mut state: Option<Vec<Vec<Var>>> = None;
+    for block in padded.chunks(rate) {
+        let lanes = block.chunks(LANE).map(|lane| lane.to_vec());
+        let absorbed = match state {
+            // the initial state is zero, so the first block is absorbed for free
+            None => lanes
+                .chain(std::iter::repeat(vec![zero; LANE]))
+                .take(LANES)
+                .collect(),
+            Some(state) => {
+                let mut state = state;
+                for (i, lane) in lanes.enumerate() {
+                    state[i] = xor_lanes(b, &state[i], &lane);
+                }
+                state
+            }
+        };
+        state = Some(keccak_f(b, &absorbed));
+    }
+
+    // squeeze
+    state
+        .expect("the padding is never empty")
+        .concat()
+        .into_iter()
+        .take(out)
+        .collect()
+}
+
+/// Creates the gates of a Keccak-256 hash (as used in Ethereum) of `message`,
+/// given as bits in little-endian order (see [super::bytes_to_bits_le]).
+/// Returns the 256 bits of the digest, in the same order.
+pub fn keccak256<F: FftField>(b: &mut CircuitBuilder<F>, message: &[Var]) -> Vec<Var> {
+    sponge(b, message, &[], RATE_256, 256)
+}
+
+/// Creates the gates of a SHA3-256 hash of `message`,
+/// given as bits in little-endian order (see [super::bytes_to_bits_le]).
+/// Returns the 256 bits of the digest, in the same order.
+pub fn sha3_256<F: FftField>(b: &mut CircuitBuilder<F>, message: &[Var]) -> Vec<Var> {
+    sponge(b, message, &[false, true], RATE_256, 256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{
+        builder::testing::verify_generic_circuit,
+        gadgets::hash::{bits_le_to_bytes, bytes_to_bits_le},
+    };
+    use mina_curves::pasta::fp::Fp;
+
+    type Hash = fn(&mut CircuitBuilder<Fp>, &[Var]) -> Vec<Var>;
+
+    fn check_vector(hash: Hash, message: &[u8], digest: &str) {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let input: Vec<_> = (0..message.len() * 8).map(|_| b.private_input()).collect();
+        let output = hash(&mut b, &input);
+        let (gates, generator) = b.build();
+
+        let private = bytes_to_bits_le(message);
+        let witness = generator.generate(&[], &private);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+        let values = generator.values(&[], &private);
+        let output: Vec<Fp> = output.iter().map(|bit| values[bit.index()]).collect();
+        assert_eq!(hex::encode(bits_le_to_bytes(&output)), digest);
+    }
+
+    #[test]
+    fn keccak_f_zero_state() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let zero = b.constant(Fp::from(0u64));
+        let state = vec![vec![zero; LANE]; LANES];
+        let output = keccak_f(&mut b, &state);
+        let (gates, generator) = b.build();
+
+        let witness = generator.generate(&[], &[]);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+        let values = generator.values(&[], &[]);
+        let lane: Vec<Fp> = output[0].iter().map(|bit| values[bit.index()]).collect();
+        assert_eq!(hex::encode(bits_le_to_bytes(&lane)), "e7dde140798f25f1");
+    }
+
+    #[test]
+    fn keccak256_empty() {
+        check_vector(
+            keccak256,
+            b"",
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+        );
+    }
+
+    #[test]
+    fn sha3_256_abc() {
+        check_vector(
+            sha3_256,
+            b"abc",
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+        );
+    }
+}
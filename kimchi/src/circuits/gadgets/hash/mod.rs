@@ -0,0 +1,49 @@
+//! This module implements hash function gadgets out of boolean gadgets
+//! (and, for [pedersen], out of additions on an elliptic curve).
+//!
+//! Messages and digests are given as bits,
+//! in the order specified by each hash function
+//! (see [bytes_to_bits_be] and [bytes_to_bits_le] to encode byte strings).
+
+use ark_ff::Field;
+
+pub mod keccak;
+pub mod pedersen;
+pub mod sha256;
+
+/// Encodes bytes as bits, most significant bit of each byte first (as in SHA-256).
+pub fn bytes_to_bits_be<F: Field>(bytes: &[u8]) -> Vec<F> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| F::from(((byte >> i) & 1) as u64)))
+        .collect()
+}
+
+/// Encodes bytes as bits, least significant bit of each byte first (as in Keccak).
+pub fn bytes_to_bits_le<F: Field>(bytes: &[u8]) -> Vec<F> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| F::from(((byte >> i) & 1) as u64)))
+        .collect()
+}
+
+/// Decodes bits encoded by [bytes_to_bits_be].
+pub fn bits_be_to_bytes<F: Field>(bits: &[F]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|bits| {
+            bits.iter()
+                .fold(0u8, |byte, bit| (byte << 1) | u8::from(!bit.is_zero()))
+        })
+        .collect()
+}
+
+/// Decodes bits encoded by [bytes_to_bits_le].
+pub fn bits_le_to_bytes<F: Field>(bits: &[F]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|bits| {
+            bits.iter()
+                .rev()
+                .fold(0u8, |byte, bit| (byte << 1) | u8::from(!bit.is_zero()))
+        })
+        .collect()
+}
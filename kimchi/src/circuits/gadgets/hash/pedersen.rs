@@ -0,0 +1,323 @@
+//! This module implements the windowed Pedersen hash, in a circuit and natively,
+//! over a short Weierstrass curve `y^2 = x^3 + b` whose base field is the field of the circuit.
+//!
+//! The message is split into chunks of three bits `(s0, s1, s2)`, each encoding the non-zero integer
+//! `enc(s) = (1 - 2 s2) (1 + s0 + 2 s1)`, between -4 and 4.
+//! The chunks are grouped into segments of [chunks_per_segment] chunks, and the segment `j`
+//! is mapped to the point `sum_k enc(s_k) 16^k G_j`, where `G_j` is the `j`-th generator of the
+//! [PedersenParameters]. The hash is the sum of the points of all segments.
+//! As the integers `sum_k enc(s_k) 16^k` of distinct segments are distinct and smaller than half the order of the group,
+//! finding a collision amounts to finding a discrete logarithm relation between the generators,
+//! which are hashed to the curve so that nobody knows any.
+//!
+//! There is no fixed-base multiplication gate: in the circuit, the multiples `16^k G_j`, ..., `4 16^k G_j`
+//! of each chunk are constants, among which the bits `s0` and `s1` select with a bilinear interpolation
+//! of the coordinates, before `s2` conditionally negates the point.
+//! A chunk so costs three generic constraints (a row and a half) to select its point and a complete addition ([GateType::CompleteAdd])
+//! to add it to the sum (on top of the booleanity of its bits).
+//! The partial sums of a segment never hit the point at infinity, nor an addition of a point with its opposite,
+//! while those of distinct segments only do with negligible probability.
+//!
+//! A message and its extension by zeros to a multiple of three bits have the same hash:
+//! the messages hashed for a given purpose are expected to have a fixed length.
+//!
+//! [GateType::CompleteAdd]: crate::circuits::gate::GateType::CompleteAdd
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gadgets::schnorr::{add, PointVars},
+};
+use ark_ec::{
+    short_weierstrass_jacobian::{GroupAffine, GroupProjective},
+    AffineCurve, ProjectiveCurve, SWModelParameters,
+};
+use ark_ff::{Field, One, PrimeField, Zero};
+use blake2::{Blake2b512, Digest};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+type ScalarField<P> = <P as ark_ec::ModelParameters>::ScalarField;
+
+/// The number of bits of a chunk of the message.
+pub const CHUNK_BITS: usize = 3;
+
+/// Returns the number of chunks of a segment,
+/// the largest one for which `4 (16^n - 1) / 15` is smaller than half the order of the group.
+pub fn chunks_per_segment<P: SWModelParameters>() -> usize {
+    (ScalarField::<P>::size_in_bits() - 1) / 4
+}
+
+/// The generators of the Pedersen hash over the curve of parameters `P`, one per segment of the messages.
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PedersenParameters<P: SWModelParameters> {
+    /// the string the generators are derived from, which separates the uses of the hash
+    pub personalization: String,
+    /// the generators, in the order of the segments
+    #[serde_as(as = "Vec<o1_utils::serialization::SerdeAs>")]
+    pub generators: Vec<GroupAffine<P>>,
+}
+
+impl<P> PedersenParameters<P>
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    /// Derives the generators of `segments` segments from `personalization`.
+    pub fn new(personalization: &str, segments: usize) -> Self {
+        let generators = (0..segments)
+            .map(|j| hash_to_curve(personalization, j as u32))
+            .collect();
+        Self {
+            personalization: personalization.to_string(),
+            generators,
+        }
+    }
+
+    /// Returns whether the generators are the ones derived from the personalization,
+    /// which parameters read from an untrusted source must be checked for.
+    pub fn is_valid(&self) -> bool {
+        self.generators == Self::new(&self.personalization, self.generators.len()).generators
+    }
+
+    /// Returns the maximal number of bits of the messages.
+    pub fn max_bits(&self) -> usize {
+        self.generators.len() * chunks_per_segment::<P>() * CHUNK_BITS
+    }
+
+    /// Returns the points `m 16^k G_j`, for `m` from 1 to 4, of the `k`-th chunk of the segment `j`
+    /// (the `i`-th chunk of the message).
+    fn window(&self, i: usize) -> [GroupAffine<P>; 4] {
+        let per_segment = chunks_per_segment::<P>();
+        let k = (i % per_segment) as u64;
+        let base = self.generators[i / per_segment].mul(ScalarField::<P>::from(16u64).pow([k]));
+        let double = base.double();
+        [
+            base.into_affine(),
+            double.into_affine(),
+            (double + base).into_affine(),
+            double.double().into_affine(),
+        ]
+    }
+
+    fn check_length(&self, len: usize) {
+        assert!(len > 0, "the empty message has no hash");
+        assert!(
+            len <= self.max_bits(),
+            "the message is longer than the {} bits the generators can hash",
+            self.max_bits()
+        );
+    }
+}
+
+/// Hashes `personalization` and `index` to a point of the curve, by trying successive counters
+/// until the hash is the x-coordinate of a point.
+fn hash_to_curve<P>(personalization: &str, index: u32) -> GroupAffine<P>
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    for counter in 0u32.. {
+        let digest = Blake2b512::new()
+            .chain_update(personalization.as_bytes())
+            .chain_update(index.to_le_bytes())
+            .chain_update(counter.to_le_bytes())
+            .finalize();
+        let x = P::BaseField::from_le_bytes_mod_order(&digest);
+        if let Some(p) = GroupAffine::<P>::get_point_from_x(x, false) {
+            let p = p.mul_by_cofactor();
+            if !p.is_zero() {
+                return p;
+            }
+        }
+    }
+    unreachable!("half of the x-coordinates are those of points")
+}
+
+/// Returns the Pedersen hash of `bits`.
+pub fn hash<P>(params: &PedersenParameters<P>, bits: &[bool]) -> GroupAffine<P>
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    params.check_length(bits.len());
+    bits.chunks(CHUNK_BITS)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let bit = |j: usize| chunk.get(j).copied().unwrap_or(false);
+            let p = params.window(i)[bit(0) as usize + 2 * bit(1) as usize];
+            if bit(2) {
+                -p.into_projective()
+            } else {
+                p.into_projective()
+            }
+        })
+        .sum::<GroupProjective<P>>()
+        .into_affine()
+}
+
+/// Returns the point `(1 - 2 s2) window[s0 + 2 s1]`, for boolean `s0`, `s1` and `s2`.
+fn lookup<P>(
+    b: &mut CircuitBuilder<P::BaseField>,
+    window: &[GroupAffine<P>; 4],
+    [s0, s1, s2]: [Var; 3],
+) -> PointVars
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    // the coordinate c[s0 + 2 s1] is c0 + (c1 - c0) s0 + (c2 - c0) s1 + (c3 - c2 - c1 + c0) s0 s1
+    let mut select = |c: [P::BaseField; 4]| {
+        let coeffs = [c[1] - c[0], c[2] - c[0], c[3] - c[2] - c[1] + c[0], c[0]];
+        let o = b.compute_one(&[s0, s1], move |v| {
+            coeffs[0] * v[0] + coeffs[1] * v[1] + coeffs[2] * v[0] * v[1] + coeffs[3]
+        });
+        b.generic(
+            [
+                coeffs[0],
+                coeffs[1],
+                -P::BaseField::one(),
+                coeffs[2],
+                coeffs[3],
+            ],
+            [Some(s0), Some(s1), Some(o)],
+        );
+        o
+    };
+    let x = select(window.map(|p| p.x));
+    let y = select(window.map(|p| p.y));
+
+    let one = P::BaseField::one();
+    let signed_y = b.compute_one(&[y, s2], |v| v[0] - (v[0] * v[1]).double());
+    b.generic(
+        [
+            one,
+            P::BaseField::zero(),
+            -one,
+            -one.double(),
+            P::BaseField::zero(),
+        ],
+        [Some(y), Some(s2), Some(signed_y)],
+    );
+    (x, signed_y)
+}
+
+/// Returns the Pedersen hash of `bits`, which are constrained to be booleans.
+pub fn pedersen_hash<P>(
+    b: &mut CircuitBuilder<P::BaseField>,
+    params: &PedersenParameters<P>,
+    bits: &[Var],
+) -> PointVars
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    assert!(
+        P::COEFF_A.is_zero(),
+        "the complete addition gate is only defined over curves y^2 = x^3 + b"
+    );
+    params.check_length(bits.len());
+    b.namespace("pedersen", |b| {
+        let zero = b.constant(P::BaseField::zero());
+        let points: Vec<_> = bits
+            .chunks(CHUNK_BITS)
+            .enumerate()
+            .map(|(i, chunk)| {
+                for bit in chunk {
+                    b.assert_boolean(*bit);
+                }
+                let bit = |j: usize| chunk.get(j).copied().unwrap_or(zero);
+                lookup(b, &params.window(i), [bit(0), bit(1), bit(2)])
+            })
+            .collect();
+        points[1..].iter().fold(points[0], |acc, p| add(b, acc, *p))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{check::check_witness, constraints::ConstraintSystem};
+    use mina_curves::pasta::{
+        fp::Fp,
+        fq::Fq,
+        pallas::{Affine as Pallas, PallasParameters},
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn params() -> PedersenParameters<PallasParameters> {
+        PedersenParameters::new("kimchi pedersen test", 2)
+    }
+
+    #[test]
+    fn pedersen_parameters_are_derived_from_the_personalization() {
+        let params = params();
+        assert!(params.is_valid());
+        assert_ne!(params.generators[0], params.generators[1]);
+        assert_ne!(
+            params.generators,
+            PedersenParameters::new("other", 2).generators
+        );
+
+        let bytes = rmp_serde::to_vec(&params).unwrap();
+        let read: PedersenParameters<PallasParameters> = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(read.personalization, params.personalization);
+        assert_eq!(read.generators, params.generators);
+
+        let mut forged = read;
+        forged.generators[1] = Pallas::prime_subgroup_generator();
+        assert!(!forged.is_valid());
+    }
+
+    #[test]
+    fn pedersen_hash_is_the_sum_of_the_encoded_chunks() {
+        let params = params();
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let bits: Vec<bool> = (0..200).map(|_| rng.gen()).collect();
+
+        let per_segment = chunks_per_segment::<PallasParameters>();
+        let expected: GroupProjective<PallasParameters> = bits
+            .chunks(CHUNK_BITS)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let bit = |j: usize| chunk.get(j).copied().unwrap_or(false) as u64;
+                let enc = Fq::from(1 + bit(0) + 2 * bit(1)) * (Fq::one() - Fq::from(2 * bit(2)));
+                let k = (i % per_segment) as u64;
+                params.generators[i / per_segment].mul(enc * Fq::from(16u64).pow([k]))
+            })
+            .sum();
+        assert_eq!(hash(&params, &bits), expected.into_affine());
+    }
+
+    #[test]
+    fn pedersen_hash_in_circuit() {
+        let params = params();
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        // the message spans both segments, and its last chunk is partial
+        let bits: Vec<bool> = (0..200).map(|_| rng.gen()).collect();
+        let digest = hash(&params, &bits);
+
+        let mut b = CircuitBuilder::<Fp>::new();
+        let digest_var = (b.public_input(), b.public_input());
+        let bit_vars: Vec<_> = bits.iter().map(|_| b.private_input()).collect();
+        let out = pedersen_hash(&mut b, &params, &bit_vars);
+        b.assert_equal(out.0, digest_var.0);
+        b.assert_equal(out.1, digest_var.1);
+        let (gates, generator) = b.build();
+        let cs =
+            ConstraintSystem::create(gates, vec![], oracle::pasta::fp_kimchi::params(), 2).unwrap();
+
+        let public = [digest.x, digest.y];
+        let private: Vec<Fp> = bits.iter().map(|bit| Fp::from(*bit as u64)).collect();
+        let witness = generator.generate(&public, &private);
+        check_witness(&witness, &cs, &public).unwrap();
+
+        // the digest is bound to every bit of the message
+        for i in [0, 100, 199] {
+            let mut other = private.clone();
+            other[i] = Fp::one() - other[i];
+            let witness = generator.generate(&public, &other);
+            assert!(check_witness(&witness, &cs, &public).is_err());
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! This module implements the SHA-256 gadget.
+//!
+//! Internally, 32-bit words are vectors of bits, least significant bit first,
+//! so that rotations and shifts are free re-indexings of the bits.
+//! The gates are laid out in namespaces such as `sha256/block0/round17/sigma0`,
+//! which name the step of the hash responsible for an unsatisfied constraint.
+
+use crate::circuits::builder::{CircuitBuilder, Var};
+use ark_ff::PrimeField;
+
+/// The initial hash value
+pub const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The round constants
+pub const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Number of bits in a word
+const WORD: usize = 32;
+
+/// Number of bits in a block
+pub const BLOCK_BITS: usize = 512;
+
+/// Number of bits in a digest
+pub const DIGEST_BITS: usize = 256;
+
+/// `rotr(w, r1) ^ rotr(w, r2) ^ rotr(w, r3)`
+fn big_sigma<F: PrimeField>(
+    b: &mut CircuitBuilder<F>,
+    w: &[Var],
+    (r1, r2, r3): (usize, usize, usize),
+) -> Vec<Var> {
+    (0..WORD)
+        .map(|i| {
+            let x = b.xor(w[(i + r1) % WORD], w[(i + r2) % WORD]);
+            b.xor(x, w[(i + r3) % WORD])
+        })
+        .collect()
+}
+
+/// `rotr(w, r1) ^ rotr(w, r2) ^ shr(w, s)`
+fn small_sigma<F: PrimeField>(
+    b: &mut CircuitBuilder<F>,
+    w: &[Var],
+    (r1, r2, s): (usize, usize, usize),
+) -> Vec<Var> {
+    (0..WORD)
+        .map(|i| {
+            let x = b.xor(w[(i + r1) % WORD], w[(i + r2) % WORD]);
+            // the shifted-in bits are zeros
+            if i + s < WORD {
+                b.xor(x, w[i + s])
+            } else {
+                x
+            }
+        })
+        .collect()
+}
+
+/// `(e & f) ^ (!e & g)`, where the two terms are never set together
+fn ch<F: PrimeField>(b: &mut CircuitBuilder<F>, e: &[Var], f: &[Var], g: &[Var]) -> Vec<Var> {
+    (0..WORD)
+        .map(|i| {
+            let x = b.and(e[i], f[i]);
+            let y = b.and_not(e[i], g[i]);
+            b.add(x, y)
+        })
+        .collect()
+}
+
+/// `(a & b) ^ (c & (a ^ b))`, where the two terms are never set together
+fn maj<F: PrimeField>(b: &mut CircuitBuilder<F>, x: &[Var], y: &[Var], z: &[Var]) -> Vec<Var> {
+    (0..WORD)
+        .map(|i| {
+            let xy = b.and(x[i], y[i]);
+            let x_xor_y = b.xor(x[i], y[i]);
+            let t = b.and(z[i], x_xor_y);
+            b.add(xy, t)
+        })
+        .collect()
+}
+
+/// Creates the gates of the SHA-256 compression function,
+/// applied on the `state` (8 words) and the message `block` (16 words).
+/// Returns the new state.
+pub fn compress<F: PrimeField>(
+    b: &mut CircuitBuilder<F>,
+    state: &[Vec<Var>],
+    block: &[Vec<Var>],
+) -> Vec<Vec<Var>> {
+    assert_eq!(state.len(), 8);
+    assert_eq!(block.len(), 16);
+
+    // message schedule
+    let mut schedule = block.to_vec();
+    for t in 16..64 {
+        let word = b.namespace(&format!("schedule{t}"), |b| {
+            let s0 = small_sigma(b, &schedule[t - 15], (7, 18, 3));
+            let s1 = small_sigma(b, &schedule[t - 2], (17, 19, 10));
+            b.add_words(&[&s1, &schedule[t - 7], &s0, &schedule[t - 16]], 0)
+        });
+        schedule.push(word);
+    }
+
+    // rounds
+    let mut v = state.to_vec();
+    for (t, (w, k)) in schedule.iter().zip(K).enumerate() {
+        let (a, e) = b.namespace(&format!("round{t}"), |b| {
+            let s1 = b.namespace("sigma1", |b| big_sigma(b, &v[4], (6, 11, 25)));
+            let ch = b.namespace("ch", |b| ch(b, &v[4], &v[5], &v[6]));
+            let s0 = b.namespace("sigma0", |b| big_sigma(b, &v[0], (2, 13, 22)));
+            let maj = b.namespace("maj", |b| maj(b, &v[0], &v[1], &v[2]));
+
+            // a = t1 + t2 and e = d + t1, with t1 = h + s1 + ch + k + w and t2 = s0 + maj
+            let a = b.add_words(&[&v[7], &s1, &ch, w, &s0, &maj], k as u64);
+            let e = b.add_words(&[&v[7], &s1, &ch, w, &v[3]], k as u64);
+            (a, e)
+        });
+
+        v.pop();
+        v.insert(0, a);
+        v[4] = e;
+    }
+
+    state
+        .iter()
+        .zip(v)
+        .map(|(h, v)| b.add_words(&[h, &v], 0))
+        .collect()
+}
+
+/// Creates the gates of a SHA-256 hash of `message`,
+/// given as bits in big-endian order (see [super::bytes_to_bits_be]).
+/// The message bits are constrained to be booleans.
+/// Returns the 256 bits of the digest, in the same order.
+pub fn sha256<F: PrimeField>(b: &mut CircuitBuilder<F>, message: &[Var]) -> Vec<Var> {
+    for bit in message {
+        b.assert_boolean(*bit);
+    }
+
+    // padding: a one, zeros, and the length of the message on 64 bits
+    let len = message.len();
+    let zeros = (BLOCK_BITS + 448 - (len + 1) % BLOCK_BITS) % BLOCK_BITS;
+    let mut padded = message.to_vec();
+    padded.push(b.constant(F::one()));
+    padded.extend((0..zeros).map(|_| b.constant(F::zero())));
+    let mut len_bits = b.constant_bits(len as u64, 64);
+    len_bits.reverse();
+    padded.extend(len_bits);
+
+    // words are big-endian
+    let to_word = |bits: &[Var]| bits.iter().rev().copied().collect::<Vec<_>>();
+
+    let mut state: Vec<Vec<Var>> = IV
+        .iter()
+        .map(|h| b.constant_bits(*h as u64, WORD))
+        .collect();
+    b.namespace("sha256", |b| {
+        for (i, block) in padded.chunks(BLOCK_BITS).enumerate() {
+            let block: Vec<_> = block.chunks(WORD).map(to_word).collect();
+            state = b.namespace(&format!("block{i}"), |b| compress(b, &state, &block));
+        }
+    });
+
+    state.iter().flat_map(|word| to_word(word)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{
+        builder::testing::verify_generic_circuit,
+        gadgets::hash::{bits_be_to_bytes, bytes_to_bits_be},
+    };
+    use mina_curves::pasta::fp::Fp;
+
+    fn check_vector(message: &[u8], digest: &str) {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let input: Vec<_> = (0..message.len() * 8).map(|_| b.private_input()).collect();
+        let output = sha256(&mut b, &input);
+        let (gates, generator) = b.build();
+
+        let private = bytes_to_bits_be(message);
+        let witness = generator.generate(&[], &private);
+        verify_generic_circuit(&gates, &witness, &[]).unwrap();
+
+        let values = generator.values(&[], &private);
+        let output: Vec<Fp> = output.iter().map(|bit| values[bit.index()]).collect();
+        assert_eq!(hex::encode(bits_be_to_bytes(&output)), digest);
+    }
+
+    #[test]
+    fn sha256_empty() {
+        check_vector(
+            b"",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        );
+    }
+
+    #[test]
+    fn sha256_abc() {
+        check_vector(
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        );
+    }
+
+    #[test]
+    fn sha256_two_blocks() {
+        check_vector(
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        );
+    }
+}
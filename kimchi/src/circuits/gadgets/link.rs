@@ -0,0 +1,200 @@
+//! This module implements the linking of values shared by the witnesses of different circuits.
+//!
+//! The shared values are committed to once, with a hiding Poseidon commitment
+//! (the sponge absorbs a random blinding factor, then the values).
+//! Each circuit takes the commitment as a public input,
+//! and constrains its own private copy of the values to open it (see [link]).
+//! Two proofs whose public commitments are equal (see [linked]) are then about the same values,
+//! without revealing them:
+//! for example, a proof that a message has some hash and a proof that the same message is signed.
+
+use crate::{
+    circuits::{
+        builder::{CircuitBuilder, Var},
+        gadgets::poseidon::Sponge,
+    },
+    prover::ProverProof,
+};
+use ark_ec::AffineCurve;
+use ark_ff::{FftField, UniformRand};
+use oracle::poseidon::{
+    ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, Sponge as _,
+};
+use rand::{CryptoRng, RngCore};
+
+/// Values shared by the witnesses of several circuits, with the blinding factor of their commitment.
+#[derive(Clone, Debug)]
+pub struct LinkedWitness<F> {
+    /// the shared values
+    pub values: Vec<F>,
+    /// the blinding factor of the commitment
+    pub blinding: F,
+}
+
+impl<F: FftField> LinkedWitness<F> {
+    /// Shares `values`, with a fresh blinding factor.
+    pub fn new(values: Vec<F>, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self {
+            values,
+            blinding: F::rand(rng),
+        }
+    }
+
+    /// Returns the commitment to the values, the public input of each linked circuit.
+    pub fn commitment(&self, params: &ArithmeticSpongeParams<F>) -> F {
+        let mut sponge = ArithmeticSponge::<F, PlonkSpongeConstantsKimchi>::new(params.clone());
+        sponge.absorb(&[self.blinding]);
+        sponge.absorb(&self.values);
+        sponge.squeeze()
+    }
+
+    /// Returns the private inputs of a [Link], in the order [link] declares them.
+    pub fn private_inputs(&self) -> Vec<F> {
+        let mut inputs = vec![self.blinding];
+        inputs.extend(&self.values);
+        inputs
+    }
+}
+
+/// The variables of a circuit linked to other circuits.
+pub struct Link {
+    /// the index of the commitment among the public inputs of the circuit
+    pub public_index: usize,
+    /// the commitment to the shared values, a public input
+    pub commitment: Var,
+    /// the shared values, private inputs that open the commitment
+    pub values: Vec<Var>,
+}
+
+/// Declares `n` values shared with other circuits.
+///
+/// This declares the commitment as the last public input of the circuit,
+/// so it must be called after the other public inputs are declared, and before any gate is added.
+/// The blinding factor and the values are then declared as private inputs (see [LinkedWitness::private_inputs]).
+/// Since the gadget uses Poseidon gates, `params` must be the sponge parameters of the constraint system.
+pub fn link<F: FftField>(
+    b: &mut CircuitBuilder<F>,
+    params: &ArithmeticSpongeParams<F>,
+    n: usize,
+) -> Link {
+    let public_index = b.public_inputs();
+    let commitment = b.public_input();
+    let blinding = b.private_input();
+    let values: Vec<_> = (0..n).map(|_| b.private_input()).collect();
+
+    let mut sponge = Sponge::new(b, params.clone());
+    sponge.absorb(b, &[blinding]);
+    sponge.absorb(b, &values);
+    let opened = sponge.squeeze(b);
+    b.assert_equal(opened, commitment);
+
+    Link {
+        public_index,
+        commitment,
+        values,
+    }
+}
+
+/// Returns true if the two proofs are linked,
+/// that is if the commitment at position `a_index` of the public input of `a`
+/// is the one at position `b_index` of the public input of `b`.
+/// The proofs themselves still have to be verified.
+pub fn linked<G: AffineCurve>(
+    a: &ProverProof<G>,
+    a_index: usize,
+    b: &ProverProof<G>,
+    b_index: usize,
+) -> bool {
+    matches!(
+        (a.public.get(a_index), b.public.get(b_index)),
+        (Some(x), Some(y)) if x == y
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuits::{builder::WitnessBuilder, check::check_witness, gate::CircuitGate},
+        prover_index::testing::new_index_for_test,
+        verifier::batch_verify,
+    };
+    use commitment_dlog::commitment::CommitmentCurve;
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{
+        fp::Fp,
+        vesta::{Affine, VestaParameters},
+    };
+    use oracle::sponge::{DefaultFqSponge, DefaultFrSponge};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+    type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+    /// A circuit proving that the shared value `m` satisfies `f(m) = out`, for a public `out`.
+    fn circuit(
+        f: impl Fn(&mut CircuitBuilder<Fp>, Var) -> Var,
+    ) -> (Vec<CircuitGate<Fp>>, WitnessBuilder<Fp>) {
+        let params = oracle::pasta::fp_kimchi::params();
+        let mut b = CircuitBuilder::new();
+        let out = b.public_input();
+        let link = link(&mut b, &params, 1);
+        assert_eq!(link.public_index, 1);
+        let res = f(&mut b, link.values[0]);
+        b.assert_equal(res, out);
+        b.build()
+    }
+
+    #[test]
+    fn test_linked_proofs() {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let params = oracle::pasta::fp_kimchi::params();
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+        let m = Fp::from(12u64);
+        let shared = LinkedWitness::new(vec![m], rng);
+        let commitment = shared.commitment(&params);
+
+        // one circuit squares the shared value, the other one increments it
+        let (square_gates, square) = circuit(|b, m| b.mul(m, m));
+        let (incr_gates, incr) = circuit(|b, m| b.add_constant(m, Fp::from(1u64)));
+
+        let mut proofs = vec![];
+        for (gates, generator, out) in [
+            (square_gates, square, m * m),
+            (incr_gates, incr, m + Fp::from(1u64)),
+        ] {
+            let public = [out, commitment];
+            let witness = generator.generate(&public, &shared.private_inputs());
+            let index = new_index_for_test(gates, public.len());
+            check_witness(&witness, &index.cs, &public).unwrap();
+
+            // another value does not open the commitment
+            let other = LinkedWitness {
+                values: vec![m + Fp::from(1u64)],
+                ..shared.clone()
+            };
+            let wrong = generator.generate(&public, &other.private_inputs());
+            assert!(check_witness(&wrong, &index.cs, &public).is_err());
+
+            let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+                &group_map,
+                witness,
+                &index,
+                vec![],
+            )
+            .unwrap();
+            let verifier_index = index.verifier_index();
+            batch_verify::<Affine, BaseSponge, ScalarSponge>(
+                &group_map,
+                &[(&verifier_index, &proof)],
+            )
+            .unwrap();
+            proofs.push(proof);
+        }
+
+        assert!(linked(&proofs[0], 1, &proofs[1], 1));
+        assert!(!linked(&proofs[0], 0, &proofs[1], 1));
+        assert!(!linked(&proofs[0], 2, &proofs[1], 1));
+    }
+}
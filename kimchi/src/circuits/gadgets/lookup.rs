@@ -0,0 +1,49 @@
+//! This module implements the gadgets that look up variables in the lookup tables of a circuit,
+//! with [lookup gates](crate::circuits::polynomials::lookup_gate).
+//!
+//! The tables are not known to the builder: they are given to [ConstraintSystem::create](crate::circuits::constraints::ConstraintSystem::create)
+//! along with the gates, and a gadget only refers to a table by its position.
+//! The gadgets of a circuit can look up different tables.
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gate::GateType,
+    polynomials::lookup_gate::{LOOKUPS_PER_ROW, LOOKUP_WIDTH, TABLE_ID_COLUMN},
+    wires::COLUMNS,
+};
+use ark_ff::FftField;
+
+impl<F: FftField> CircuitBuilder<F> {
+    /// Constrains each of the `values` to be an entry of the table `table_id` of the circuit,
+    /// which has a single column.
+    #[track_caller]
+    pub fn lookup(&mut self, table_id: usize, values: &[Var]) {
+        let entries: Vec<_> = values.iter().map(|v| [Some(*v), None]).collect();
+        self.lookup_entries(table_id, &entries);
+    }
+
+    /// Constrains each of the pairs `entries` to be an entry of the table `table_id` of the circuit,
+    /// which has two columns.
+    #[track_caller]
+    pub fn lookup_pairs(&mut self, table_id: usize, entries: &[(Var, Var)]) {
+        let entries: Vec<_> = entries.iter().map(|(a, b)| [Some(*a), Some(*b)]).collect();
+        self.lookup_entries(table_id, &entries);
+    }
+
+    /// Looks up the `entries` [LOOKUPS_PER_ROW] per row, a missing cell holding zero.
+    /// The last row repeats its first entry in the lookups that it does not need.
+    /// The cell of the id of the table is wired to a constant, so that the lookups cannot be in another table.
+    #[track_caller]
+    fn lookup_entries(&mut self, table_id: usize, entries: &[[Option<Var>; LOOKUP_WIDTH]]) {
+        let id = self.constant(F::from(table_id as u64));
+        for chunk in entries.chunks(LOOKUPS_PER_ROW) {
+            let mut cells = [None; COLUMNS];
+            cells[TABLE_ID_COLUMN] = Some(id);
+            for i in 0..LOOKUPS_PER_ROW {
+                let entry = chunk.get(i).unwrap_or(&chunk[0]);
+                cells[LOOKUP_WIDTH * i..LOOKUP_WIDTH * (i + 1)].copy_from_slice(entry);
+            }
+            self.gate(GateType::Lookup, vec![F::from(table_id as u64)], cells);
+        }
+    }
+}
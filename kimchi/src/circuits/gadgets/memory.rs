@@ -0,0 +1,269 @@
+//! This module implements a random-access memory of field elements in a circuit,
+//! whose reads and writes at variable addresses are checked with a memory-consistency argument.
+//!
+//! Every access is recorded in a trace, as a tuple `(addr, time, value, write)`
+//! where the time is the position of the access in the trace and `write` tells writes from reads.
+//! The memory starts with a write of its initial value at each address, in order.
+//! When the accesses are done, [Memory::finalize] checks the trace against a copy of it
+//! sorted by address then time, computed by the witness:
+//!
+//! - the sorted trace is a permutation of the trace: the products of `beta - c` over both traces are equal,
+//!   where `c` compresses an access with powers of `alpha`,
+//!   and `alpha` and `beta` are squeezed from an in-circuit Poseidon sponge absorbing both traces
+//!   (as in plookup, a prover that does not sort its trace only passes with negligible probability);
+//! - consecutive accesses of the sorted trace are to the same address or to the next one,
+//!   from address `0` to the last address of the memory, so that no access is out of bounds;
+//! - the first access to an address is a write, and accesses to the same address are in increasing time;
+//! - a read returns the value of the access to its address just before it.
+//!
+//! The argument costs a few dozen rows per access, most of them in the Poseidon gates of the challenges.
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gadgets::poseidon::Sponge,
+};
+use ark_ff::PrimeField;
+use oracle::poseidon::ArithmeticSpongeParams;
+
+/// An access to the memory.
+#[derive(Clone, Copy, Debug)]
+struct Access {
+    addr: Var,
+    value: Var,
+    write: bool,
+}
+
+/// A random-access memory, whose accesses are only constrained once [Memory::finalize] is called.
+#[derive(Clone, Debug)]
+pub struct Memory {
+    size: usize,
+    trace: Vec<Access>,
+}
+
+impl Memory {
+    /// Creates a memory of `init.len()` cells, holding `init`.
+    pub fn new<F: PrimeField>(b: &mut CircuitBuilder<F>, init: &[Var]) -> Self {
+        assert!(!init.is_empty(), "the memory has no cell");
+        let trace = init
+            .iter()
+            .enumerate()
+            .map(|(addr, value)| Access {
+                addr: b.constant(F::from(addr as u64)),
+                value: *value,
+                write: true,
+            })
+            .collect();
+        Self {
+            size: init.len(),
+            trace,
+        }
+    }
+
+    /// Returns the number of cells of the memory.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the value of the cell at `addr`.
+    pub fn read<F: PrimeField>(&mut self, b: &mut CircuitBuilder<F>, addr: Var) -> Var {
+        // the last value written at the address, or zero if it is out of bounds
+        let mut inputs = vec![addr];
+        inputs.extend(
+            self.trace
+                .iter()
+                .filter(|access| access.write)
+                .flat_map(|access| [access.addr, access.value]),
+        );
+        let value = b.compute_one(&inputs, |v| {
+            v[1..]
+                .chunks(2)
+                .rev()
+                .find(|write| write[0] == v[0])
+                .map(|write| write[1])
+                .unwrap_or_else(F::zero)
+        });
+        self.trace.push(Access {
+            addr,
+            value,
+            write: false,
+        });
+        value
+    }
+
+    /// Writes `value` to the cell at `addr`.
+    pub fn write(&mut self, addr: Var, value: Var) {
+        self.trace.push(Access {
+            addr,
+            value,
+            write: true,
+        });
+    }
+
+    /// Constrains the reads to return the last values written at their addresses,
+    /// and the addresses to be smaller than the size of the memory.
+    /// The Poseidon gates of the challenges are only satisfied if `params` are the sponge parameters
+    /// of the constraint system.
+    pub fn finalize<F: PrimeField>(
+        self,
+        b: &mut CircuitBuilder<F>,
+        params: &ArithmeticSpongeParams<F>,
+    ) {
+        b.namespace("memory", |b| {
+            let n = self.trace.len();
+            let zero = b.constant(F::zero());
+            let one = b.constant(F::one());
+            let trace: Vec<[Var; 4]> = self
+                .trace
+                .iter()
+                .enumerate()
+                .map(|(time, access)| {
+                    let time = b.constant(F::from(time as u64));
+                    let write = if access.write { one } else { zero };
+                    [access.addr, time, access.value, write]
+                })
+                .collect();
+
+            // the sort is stable, so that the accesses to an address stay in increasing time
+            let inputs: Vec<_> = self
+                .trace
+                .iter()
+                .flat_map(|access| [access.addr, access.value])
+                .collect();
+            let writes: Vec<_> = self.trace.iter().map(|access| access.write).collect();
+            let sorted = b.compute(&inputs, 4 * n, move |v| {
+                let mut accesses: Vec<[F; 4]> = v
+                    .chunks(2)
+                    .zip(&writes)
+                    .enumerate()
+                    .map(|(time, (access, write))| {
+                        [
+                            access[0],
+                            F::from(time as u64),
+                            access[1],
+                            F::from(*write as u64),
+                        ]
+                    })
+                    .collect();
+                accesses.sort_by_key(|access| access[0].into_repr());
+                accesses.into_iter().flatten().collect()
+            });
+            let sorted: Vec<[Var; 4]> = sorted
+                .chunks(4)
+                .map(|access| [access[0], access[1], access[2], access[3]])
+                .collect();
+
+            // the sorted trace is a permutation of the trace
+            // (the times and kinds of the accesses of the trace are constants)
+            let mut sponge = Sponge::new(b, params.clone());
+            sponge.absorb(b, &inputs);
+            sponge.absorb(b, &sorted.concat());
+            let alpha = sponge.squeeze(b);
+            let beta = sponge.squeeze(b);
+            let product = |b: &mut CircuitBuilder<F>, accesses: &[[Var; 4]]| {
+                accesses.iter().fold(one, |acc, access| {
+                    let c = access[..3].iter().rev().fold(access[3], |c, x| {
+                        let c = b.mul(c, alpha);
+                        b.add(c, *x)
+                    });
+                    let term = b.sub(beta, c);
+                    b.mul(acc, term)
+                })
+            };
+            let trace_product = product(b, &trace);
+            let sorted_product = product(b, &sorted);
+            b.assert_equal(trace_product, sorted_product);
+
+            // the addresses go from zero to the last address of the memory
+            b.assert_zero(sorted[0][0]);
+            b.assert_equal(sorted[0][3], one);
+            let last = b.constant(F::from(self.size as u64 - 1));
+            b.assert_equal(sorted[n - 1][0], last);
+
+            let time_bits = (usize::BITS - n.leading_zeros()) as usize;
+            for pair in sorted.windows(2) {
+                let ([addr0, time0, value0, _], [addr1, time1, value1, write1]) =
+                    (pair[0], pair[1]);
+                let step = b.sub(addr1, addr0);
+                b.assert_boolean(step);
+                // an address is first written to
+                let uninit = b.and_not(write1, step);
+                b.assert_zero(uninit);
+
+                // the time grows between accesses to the same address
+                let same = b.not(step);
+                let elapsed = b.sub(time1, time0);
+                let gap = b.add_constant(elapsed, -F::one());
+                let gap = b.mul(same, gap);
+                b.unpack(gap, time_bits);
+
+                // a read returns the value of the previous access
+                let read = b.and_not(write1, same);
+                let change = b.sub(value1, value0);
+                let inconsistency = b.mul(read, change);
+                b.assert_zero(inconsistency);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{
+        builder::WitnessBuilder, check::check_witness, constraints::ConstraintSystem,
+    };
+    use mina_curves::pasta::fp::Fp;
+
+    /// Builds a program over a memory of four cells holding its private inputs, whose public outputs are:
+    /// the cell at the public address `a`, then, after doubling it into the cell `a + 1`, that cell,
+    /// then, after writing 7 to the cell 0, the cells 0 and 1.
+    fn program() -> (ConstraintSystem<Fp>, WitnessBuilder<Fp>) {
+        let params = oracle::pasta::fp_kimchi::params();
+        let mut b = CircuitBuilder::<Fp>::new();
+        let a = b.public_input();
+        let outputs: Vec<_> = (0..4).map(|_| b.public_input()).collect();
+        let init: Vec<_> = (0..4).map(|_| b.private_input()).collect();
+
+        let mut memory = Memory::new(&mut b, &init);
+        let x = memory.read(&mut b, a);
+        let doubled = b.add(x, x);
+        let next = b.add_constant(a, Fp::from(1u64));
+        memory.write(next, doubled);
+        let y = memory.read(&mut b, next);
+        let zero = b.constant(Fp::from(0u64));
+        let seven = b.constant(Fp::from(7u64));
+        memory.write(zero, seven);
+        let one = b.constant(Fp::from(1u64));
+        let z = memory.read(&mut b, zero);
+        let t = memory.read(&mut b, one);
+        memory.finalize(&mut b, &params);
+        for (var, output) in [x, y, z, t].into_iter().zip(outputs) {
+            b.assert_equal(var, output);
+        }
+
+        let (gates, generator) = b.build();
+        let cs = ConstraintSystem::create(gates, vec![], params, 5).unwrap();
+        (cs, generator)
+    }
+
+    fn check(public: [u64; 5]) -> bool {
+        let (cs, generator) = program();
+        let public: Vec<Fp> = public.into_iter().map(Fp::from).collect();
+        let private: Vec<Fp> = [10u64, 20, 30, 40].into_iter().map(Fp::from).collect();
+        let witness = generator.generate(&public, &private);
+        check_witness(&witness, &cs, &public).is_ok()
+    }
+
+    #[test]
+    fn memory_accesses_are_consistent() {
+        assert!(check([2, 30, 60, 7, 20]));
+        // the doubled value overwrites the cell 1, read last
+        assert!(check([0, 10, 20, 7, 20]));
+    }
+
+    #[test]
+    fn out_of_bounds_accesses_are_rejected() {
+        // the last cell is doubled into the cell 4, which does not exist
+        assert!(!check([3, 40, 80, 7, 20]));
+    }
+}
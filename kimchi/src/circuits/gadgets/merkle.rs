@@ -0,0 +1,323 @@
+//! This module implements the verification of Merkle paths in a circuit,
+//! for trees of any arity and over any hash function with a gadget (see [MerkleHash]).
+//!
+//! A node of a tree of arity `k` is the hash of its `k` children, in order.
+//! The path of a leaf gives, from the leaves up to the root,
+//! the position of the current node among its siblings (a digit in `0..k`)
+//! and the `k - 1` siblings, in order.
+//! The circuit recomputes the root from the leaf and its path:
+//! each position is decomposed into `k` booleans of which exactly one is set,
+//! which place the current node among its siblings before hashing them.
+//!
+//! [MerkleTree] builds trees natively, and the paths of their leaves,
+//! to fill in the witness of the inputs allocated by [MerklePathVars::private_input].
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gadgets::poseidon::Sponge,
+};
+use ark_ff::{FftField, Field};
+use oracle::poseidon::{
+    ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, Sponge as _,
+};
+
+/// A hash function that can be computed both natively and in a circuit.
+pub trait MerkleHash<F: FftField> {
+    /// Returns the hash of `children`.
+    fn hash(&self, children: &[F]) -> F;
+
+    /// Returns the hash of `children` computed in a circuit, like [MerkleHash::hash].
+    fn gadget(&self, b: &mut CircuitBuilder<F>, children: &[Var]) -> Var;
+}
+
+/// The Poseidon sponge: a node is the first element squeezed after absorbing its children.
+/// The gates are only satisfied if `params` are the sponge parameters of the constraint system.
+#[derive(Clone)]
+pub struct PoseidonHash<F: Field> {
+    pub params: ArithmeticSpongeParams<F>,
+}
+
+impl<F: FftField> MerkleHash<F> for PoseidonHash<F> {
+    fn hash(&self, children: &[F]) -> F {
+        let mut sponge =
+            ArithmeticSponge::<F, PlonkSpongeConstantsKimchi>::new(self.params.clone());
+        sponge.absorb(children);
+        sponge.squeeze()
+    }
+
+    fn gadget(&self, b: &mut CircuitBuilder<F>, children: &[Var]) -> Var {
+        let mut sponge = Sponge::new(b, self.params.clone());
+        sponge.absorb(b, children);
+        sponge.squeeze(b)
+    }
+}
+
+/// The path of a leaf, from the leaves up to the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath<F> {
+    /// the position of the node among its siblings, at each level
+    pub positions: Vec<usize>,
+    /// the siblings of the node, in order, at each level
+    pub siblings: Vec<Vec<F>>,
+}
+
+impl<F: FftField> MerklePath<F> {
+    /// Returns the values of the inputs allocated by [MerklePathVars::private_input].
+    pub fn inputs(&self) -> Vec<F> {
+        self.positions
+            .iter()
+            .zip(&self.siblings)
+            .flat_map(|(position, siblings)| {
+                std::iter::once(F::from(*position as u64)).chain(siblings.iter().copied())
+            })
+            .collect()
+    }
+
+    /// Returns the root of the tree of `leaf` along this path.
+    pub fn root(&self, hash: &impl MerkleHash<F>, leaf: F) -> F {
+        self.positions
+            .iter()
+            .zip(&self.siblings)
+            .fold(leaf, |node, (position, siblings)| {
+                let mut children = siblings.clone();
+                children.insert(*position, node);
+                hash.hash(&children)
+            })
+    }
+}
+
+/// A Merkle tree of arity `arity` and of depth `depth`, built natively.
+pub struct MerkleTree<F> {
+    arity: usize,
+    /// the nodes of each level, from the leaves up to the root
+    levels: Vec<Vec<F>>,
+}
+
+impl<F: FftField> MerkleTree<F> {
+    /// Builds the tree of `leaves`, padded with zeros to the `arity^depth` leaves of the tree.
+    pub fn new(hash: &impl MerkleHash<F>, arity: usize, depth: usize, leaves: &[F]) -> Self {
+        assert!(
+            arity >= 2,
+            "a Merkle tree has at least two children per node"
+        );
+        let size = arity.pow(depth as u32);
+        assert!(leaves.len() <= size, "too many leaves for the tree");
+
+        let mut level = leaves.to_vec();
+        level.resize(size, F::zero());
+        let mut levels = vec![level];
+        for _ in 0..depth {
+            let next = levels[levels.len() - 1]
+                .chunks(arity)
+                .map(|children| hash.hash(children))
+                .collect();
+            levels.push(next);
+        }
+        Self { arity, levels }
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> F {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns the path of the leaf `index`.
+    pub fn path(&self, mut index: usize) -> MerklePath<F> {
+        assert!(index < self.levels[0].len(), "no such leaf");
+        let mut path = MerklePath {
+            positions: vec![],
+            siblings: vec![],
+        };
+        for level in &self.levels[..self.levels.len() - 1] {
+            let position = index % self.arity;
+            let first = index - position;
+            let mut siblings = level[first..first + self.arity].to_vec();
+            siblings.remove(position);
+            path.positions.push(position);
+            path.siblings.push(siblings);
+            index /= self.arity;
+        }
+        path
+    }
+}
+
+/// The variables of a Merkle path, laid out like [MerklePath].
+#[derive(Clone, Debug)]
+pub struct MerklePathVars {
+    /// the position of the node among its siblings, at each level
+    pub positions: Vec<Var>,
+    /// the siblings of the node, in order, at each level
+    pub siblings: Vec<Vec<Var>>,
+}
+
+impl MerklePathVars {
+    /// Allocates the private inputs of a path in a tree of arity `arity` and of depth `depth`:
+    /// for each level, the position followed by the siblings (see [MerklePath::inputs]).
+    pub fn private_input<F: FftField>(
+        b: &mut CircuitBuilder<F>,
+        arity: usize,
+        depth: usize,
+    ) -> Self {
+        let mut path = Self {
+            positions: vec![],
+            siblings: vec![],
+        };
+        for _ in 0..depth {
+            path.positions.push(b.private_input());
+            path.siblings
+                .push((1..arity).map(|_| b.private_input()).collect());
+        }
+        path
+    }
+}
+
+/// Returns the `arity` booleans that are set exactly at the index `position`,
+/// constraining `position` to be in `0..arity`.
+fn one_hot<F: FftField>(b: &mut CircuitBuilder<F>, position: Var, arity: usize) -> Vec<Var> {
+    let selectors = b.compute(&[position], arity, move |v| {
+        (0..arity)
+            .map(|j| {
+                if v[0] == F::from(j as u64) {
+                    F::one()
+                } else {
+                    F::zero()
+                }
+            })
+            .collect()
+    });
+    for s in &selectors {
+        b.assert_boolean(*s);
+    }
+    // exactly one selector is set, and its index is the position
+    let (count, index) = selectors[1..].iter().zip(1u64..).fold(
+        (selectors[0], b.constant(F::zero())),
+        |(count, index), (s, j)| {
+            let count = b.add(count, *s);
+            let index = b.linear_combination(F::one(), index, F::from(j), *s);
+            (count, index)
+        },
+    );
+    let count = b.add_constant(count, -F::one());
+    b.assert_zero(count);
+    b.assert_equal(index, position);
+    selectors
+}
+
+/// Returns the root of the tree of `leaf` along `path`.
+pub fn root<F: FftField>(
+    b: &mut CircuitBuilder<F>,
+    hash: &impl MerkleHash<F>,
+    leaf: Var,
+    path: &MerklePathVars,
+) -> Var {
+    b.namespace("merkle", |b| {
+        let mut node = leaf;
+        for (level, (position, siblings)) in path.positions.iter().zip(&path.siblings).enumerate() {
+            node = b.namespace(&format!("level{level}"), |b| {
+                let arity = siblings.len() + 1;
+                let selectors = one_hot(b, *position, arity);
+                // the child j is the node if it is at position j,
+                // and otherwise the sibling j - 1 if the node comes before j, or the sibling j
+                let mut before = None;
+                let children: Vec<_> = (0..arity)
+                    .map(|j| {
+                        let sibling = match (j, before) {
+                            (0, _) => siblings[0],
+                            (j, _) if j == arity - 1 => siblings[j - 1],
+                            (j, Some(before)) => b.select(before, siblings[j - 1], siblings[j]),
+                            (_, None) => {
+                                unreachable!("the node comes before any child but the first")
+                            }
+                        };
+                        let child = b.select(selectors[j], node, sibling);
+                        before = Some(match before {
+                            None => selectors[j],
+                            Some(before) => b.add(before, selectors[j]),
+                        });
+                        child
+                    })
+                    .collect();
+                hash.gadget(b, &children)
+            });
+        }
+        node
+    })
+}
+
+/// Constrains `leaf` to be a leaf of the tree of root `root`, along `path`.
+pub fn assert_membership<F: FftField>(
+    b: &mut CircuitBuilder<F>,
+    hash: &impl MerkleHash<F>,
+    leaf: Var,
+    path: &MerklePathVars,
+    root: Var,
+) {
+    let computed = self::root(b, hash, leaf, path);
+    b.assert_equal(computed, root);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{check::check_witness, constraints::ConstraintSystem};
+    use mina_curves::pasta::fp::Fp;
+
+    fn check_membership(arity: usize, depth: usize) {
+        let params = oracle::pasta::fp_kimchi::params();
+        let hash = PoseidonHash {
+            params: params.clone(),
+        };
+        let leaves: Vec<_> = (0..arity.pow(depth as u32) - 1)
+            .map(|i| Fp::from(i as u64 + 100))
+            .collect();
+        let tree = MerkleTree::new(&hash, arity, depth, &leaves);
+
+        let mut b = CircuitBuilder::<Fp>::new();
+        let root_var = b.public_input();
+        let leaf = b.private_input();
+        let path = MerklePathVars::private_input(&mut b, arity, depth);
+        assert_membership(&mut b, &hash, leaf, &path, root_var);
+        let (gates, generator) = b.build();
+        let cs = ConstraintSystem::create(gates, vec![], params, 1).unwrap();
+
+        for index in [0, leaves.len() / 2, leaves.len() - 1] {
+            let merkle_path = tree.path(index);
+            assert_eq!(merkle_path.root(&hash, leaves[index]), tree.root());
+
+            let mut private = vec![leaves[index]];
+            private.extend(merkle_path.inputs());
+            let public = [tree.root()];
+            let witness = generator.generate(&public, &private);
+            check_witness(&witness, &cs, &public).unwrap();
+
+            // the leaf is not at another position
+            let mut wrong = private.clone();
+            wrong[1] = Fp::from(((merkle_path.positions[0] + 1) % arity) as u64);
+            let witness = generator.generate(&public, &wrong);
+            assert!(check_witness(&witness, &cs, &public).is_err());
+
+            // nor in another tree
+            let mut wrong = private.clone();
+            wrong[2] += Fp::from(1u64);
+            let witness = generator.generate(&public, &wrong);
+            assert!(check_witness(&witness, &cs, &public).is_err());
+        }
+
+        // the positions are digits
+        let mut private = vec![leaves[0]];
+        private.extend(tree.path(0).inputs());
+        private[1] = Fp::from(arity as u64);
+        let witness = generator.generate(&[tree.root()], &private);
+        assert!(check_witness(&witness, &cs, &[tree.root()]).is_err());
+    }
+
+    #[test]
+    fn binary_merkle_membership() {
+        check_membership(2, 3);
+    }
+
+    #[test]
+    fn quaternary_merkle_membership() {
+        check_membership(4, 2);
+    }
+}
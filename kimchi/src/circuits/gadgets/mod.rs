@@ -0,0 +1,11 @@
+//! This module contains gadgets built with the [CircuitBuilder](crate::circuits::builder::CircuitBuilder).
+
+pub mod bits;
+pub mod foreign_field;
+pub mod hash;
+pub mod link;
+pub mod lookup;
+pub mod memory;
+pub mod merkle;
+pub mod poseidon;
+pub mod schnorr;
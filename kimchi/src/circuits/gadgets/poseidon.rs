@@ -0,0 +1,238 @@
+//! This module implements the Poseidon permutation gadget, built out of Poseidon gates,
+//! and an in-circuit sponge that absorbs field elements and curve points
+//! exactly like the native [DefaultFqSponge](oracle::sponge::DefaultFqSponge).
+//!
+//! A recursive verifier absorbing the commitments of a proof into its transcript
+//! must encode those commitments the same way the prover did, or the challenges will not match.
+//! The points absorbed in a circuit over `F` are the points of the curve whose base field is `F`
+//! (e.g. Pallas points in a Vesta circuit, whose scalar field is the Pallas base field),
+//! so their coordinates are native field elements:
+//! a point is absorbed as its `x` then its `y` coordinate,
+//! and the point at infinity is absorbed as the fake point `(0, 0)` (see [coordinates]).
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gate::GateType,
+    gates::poseidon::{round_to_cols, POS_ROWS_PER_HASH, ROUNDS_PER_ROW, SPONGE_WIDTH},
+    wires::COLUMNS,
+};
+use ark_ec::{short_weierstrass_jacobian::GroupAffine, SWModelParameters};
+use ark_ff::{FftField, Zero};
+use array_init::array_init;
+use oracle::poseidon::{
+    ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, Sponge as _,
+    SpongeConstants,
+};
+
+/// The rate of the sponge
+const RATE: usize = PlonkSpongeConstantsKimchi::SPONGE_RATE;
+
+/// Returns the coordinates of `g` the way the native sponge absorbs them,
+/// to be passed as inputs to [Sponge::absorb_g].
+pub fn coordinates<P: SWModelParameters>(g: &GroupAffine<P>) -> (P::BaseField, P::BaseField) {
+    if g.infinity {
+        (P::BaseField::zero(), P::BaseField::zero())
+    } else {
+        (g.x, g.y)
+    }
+}
+
+/// Creates the gates of the Poseidon permutation of `state`, and returns the permuted state.
+///
+/// The permutation takes [POS_ROWS_PER_HASH] Poseidon gates, followed by a zero gate holding the output.
+/// The gates are only satisfied if `params` are the sponge parameters of the constraint system.
+pub fn permutation<F: FftField>(
+    b: &mut CircuitBuilder<F>,
+    params: &ArithmeticSpongeParams<F>,
+    state: [Var; SPONGE_WIDTH],
+) -> [Var; SPONGE_WIDTH] {
+    // every intermediate state, starting with the state after the first round
+    let rounds = POS_ROWS_PER_HASH * ROUNDS_PER_ROW;
+    let witness_params = params.clone();
+    let states = b.compute(&state, rounds * SPONGE_WIDTH, move |input| {
+        let mut sponge =
+            ArithmeticSponge::<F, PlonkSpongeConstantsKimchi>::new(witness_params.clone());
+        sponge.state = input.to_vec();
+        (0..rounds)
+            .flat_map(|round| {
+                sponge.full_round(round);
+                sponge.state.clone()
+            })
+            .collect()
+    });
+    let state_after = |round: usize| -> [Var; SPONGE_WIDTH] {
+        array_init(|i| states[(round - 1) * SPONGE_WIDTH + i])
+    };
+
+    let mut input = state;
+    for row in 0..POS_ROWS_PER_HASH {
+        let mut cells = [None; COLUMNS];
+        for round in 0..ROUNDS_PER_ROW {
+            let vars = if round == 0 {
+                input
+            } else {
+                state_after(row * ROUNDS_PER_ROW + round)
+            };
+            for (cell, var) in cells[round_to_cols(round)].iter_mut().zip(vars) {
+                *cell = Some(var);
+            }
+        }
+        let coeffs = (0..ROUNDS_PER_ROW)
+            .flat_map(|round| params.round_constants[row * ROUNDS_PER_ROW + round].clone())
+            .collect();
+        b.gate(GateType::Poseidon, coeffs, cells);
+        input = state_after((row + 1) * ROUNDS_PER_ROW);
+    }
+
+    let mut cells = [None; COLUMNS];
+    for (cell, var) in cells.iter_mut().zip(input) {
+        *cell = Some(var);
+    }
+    b.gate(GateType::Zero, vec![], cells);
+
+    input
+}
+
+/// Where the sponge is in its absorb/squeeze cycle.
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    Absorbed(usize),
+    Squeezed(usize),
+}
+
+/// An in-circuit Poseidon sponge, following the
+/// [ArithmeticSponge] of [PlonkSpongeConstantsKimchi] step for step.
+pub struct Sponge<F: FftField> {
+    params: ArithmeticSpongeParams<F>,
+    state: [Var; SPONGE_WIDTH],
+    mode: Mode,
+}
+
+impl<F: FftField> Sponge<F> {
+    /// Creates a sponge with an all-zero state.
+    pub fn new(b: &mut CircuitBuilder<F>, params: ArithmeticSpongeParams<F>) -> Self {
+        let zero = b.constant(F::zero());
+        Self {
+            params,
+            state: [zero; SPONGE_WIDTH],
+            mode: Mode::Absorbed(0),
+        }
+    }
+
+    fn permute(&mut self, b: &mut CircuitBuilder<F>) {
+        self.state = permutation(b, &self.params, self.state);
+    }
+
+    /// Absorbs the field elements `xs`.
+    pub fn absorb(&mut self, b: &mut CircuitBuilder<F>, xs: &[Var]) {
+        for x in xs {
+            let i = match self.mode {
+                Mode::Absorbed(n) if n == RATE => {
+                    self.permute(b);
+                    0
+                }
+                Mode::Absorbed(n) => n,
+                Mode::Squeezed(_) => 0,
+            };
+            self.state[i] = b.add(self.state[i], *x);
+            self.mode = Mode::Absorbed(i + 1);
+        }
+    }
+
+    /// Absorbs the curve points `points`, given by their [coordinates].
+    pub fn absorb_g(&mut self, b: &mut CircuitBuilder<F>, points: &[(Var, Var)]) {
+        for (x, y) in points {
+            self.absorb(b, &[*x, *y]);
+        }
+    }
+
+    /// Squeezes a field element out of the sponge.
+    pub fn squeeze(&mut self, b: &mut CircuitBuilder<F>) -> Var {
+        let i = match self.mode {
+            Mode::Squeezed(n) if n < RATE => n,
+            _ => {
+                self.permute(b);
+                0
+            }
+        };
+        self.mode = Mode::Squeezed(i + 1);
+        self.state[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{check::check_witness, constraints::ConstraintSystem};
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use mina_curves::pasta::{
+        fp::Fp,
+        pallas::{Affine as Pallas, PallasParameters},
+    };
+    use oracle::{poseidon::Sponge as _, sponge::DefaultFqSponge, FqSponge as _};
+
+    #[test]
+    fn test_absorb_g_matches_native_sponge() {
+        let params = oracle::pasta::fp_kimchi::params();
+        let g = Pallas::prime_subgroup_generator();
+        let points = vec![
+            g,
+            g.mul(5u64).into_affine(),
+            Pallas::zero(),
+            g.mul(7u64).into_affine(),
+        ];
+        let scalars = [Fp::from(42u64), Fp::from(1337u64), Fp::from(7u64)];
+
+        // the native transcript
+        let mut native =
+            DefaultFqSponge::<PallasParameters, PlonkSpongeConstantsKimchi>::new(params.clone());
+        native.absorb_g(&points[..3]);
+        let first = native.squeeze_field();
+        native.sponge.absorb(&scalars);
+        native.absorb_g(&points[3..]);
+        let second = native.squeeze_field();
+        let third = native.squeeze_field();
+
+        // the same transcript in a circuit
+        let mut b = CircuitBuilder::<Fp>::new();
+        let coords: Vec<_> = points
+            .iter()
+            .map(|_| (b.private_input(), b.private_input()))
+            .collect();
+        let xs: Vec<_> = scalars.iter().map(|_| b.private_input()).collect();
+        let mut sponge = Sponge::new(&mut b, params.clone());
+        sponge.absorb_g(&mut b, &coords[..3]);
+        let out1 = sponge.squeeze(&mut b);
+        sponge.absorb(&mut b, &xs);
+        sponge.absorb_g(&mut b, &coords[3..]);
+        let out2 = sponge.squeeze(&mut b);
+        let out3 = sponge.squeeze(&mut b);
+        let (gates, generator) = b.build();
+
+        let mut private = vec![];
+        for point in &points {
+            let (x, y) = coordinates(point);
+            private.extend([x, y]);
+        }
+        private.extend(scalars);
+        let values = generator.values(&[], &private);
+        assert_eq!(values[out1.index()], first);
+        assert_eq!(values[out2.index()], second);
+        assert_eq!(values[out3.index()], third);
+
+        // the witness satisfies the Poseidon gates
+        let witness = generator.generate(&[], &private);
+        let cs = ConstraintSystem::create(gates, vec![], params, 0).unwrap();
+        check_witness(&witness, &cs, &[]).unwrap();
+
+        // and a wrong intermediate state breaks them
+        let mut wrong = witness;
+        let row = cs
+            .gates
+            .iter()
+            .position(|gate| gate.typ == GateType::Poseidon)
+            .unwrap();
+        wrong[COLUMNS - 1][row] += Fp::from(1u64);
+        assert!(check_witness(&wrong, &cs, &[]).is_err());
+    }
+}
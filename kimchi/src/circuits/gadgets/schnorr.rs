@@ -0,0 +1,326 @@
+//! This module implements the verification of Schnorr signatures in a circuit,
+//! over a short Weierstrass curve `y^2 = x^3 + b` whose base field is the field of the circuit
+//! (e.g. Pallas signatures in a circuit over the Pallas base field),
+//! along with the native signer producing these signatures.
+//!
+//! A signature of the message `m` (a field element, typically the hash of the actual message)
+//! under the public key `pk = sk G` is a pair `(R, s)` such that `s G = R + e pk`,
+//! where the challenge `e = H(pk, R, m)` is computed with the Poseidon sponge of the circuit
+//! (see [challenge]).
+//!
+//! The circuit computes both sides of the equation with complete additions ([GateType::CompleteAdd]),
+//! by doubling and adding over the bits of the scalars.
+//! As the accumulators cannot start at the point at infinity, they both start at the same fixed point,
+//! which ends up multiplied by the same power of two on both sides.
+//! Every addition is constrained not to result in the point at infinity,
+//! which honest signatures only hit with negligible probability.
+
+use crate::circuits::{
+    builder::{CircuitBuilder, Var},
+    gadgets::poseidon::Sponge,
+    gate::GateType,
+    wires::COLUMNS,
+};
+use ark_ec::{
+    short_weierstrass_jacobian::GroupAffine, AffineCurve, ProjectiveCurve, SWModelParameters,
+};
+use ark_ff::{BigInteger, Field, PrimeField, UniformRand, Zero};
+use oracle::poseidon::{
+    ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, Sponge as _,
+};
+use rand::{CryptoRng, RngCore};
+
+type ScalarField<P> = <P as ark_ec::ModelParameters>::ScalarField;
+
+/// A Schnorr signature.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature<P: SWModelParameters> {
+    /// the commitment to the nonce
+    pub r: GroupAffine<P>,
+    /// the response
+    pub s: ScalarField<P>,
+}
+
+/// Returns the challenge `H(pk, R, m)`, absorbing the coordinates of the points then the message.
+pub fn challenge<P>(
+    params: &ArithmeticSpongeParams<P::BaseField>,
+    pk: &GroupAffine<P>,
+    r: &GroupAffine<P>,
+    msg: P::BaseField,
+) -> P::BaseField
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    let mut sponge =
+        ArithmeticSponge::<P::BaseField, PlonkSpongeConstantsKimchi>::new(params.clone());
+    sponge.absorb(&[pk.x, pk.y, r.x, r.y, msg]);
+    sponge.squeeze()
+}
+
+/// Returns the challenge as a scalar, as it multiplies the public key in the circuit.
+fn challenge_scalar<P: SWModelParameters>(e: P::BaseField) -> ScalarField<P>
+where
+    P::BaseField: PrimeField,
+{
+    ScalarField::<P>::from_le_bytes_mod_order(&e.into_repr().to_bytes_le())
+}
+
+/// Signs the message `msg` with the secret key `sk`.
+pub fn sign<P, R>(
+    params: &ArithmeticSpongeParams<P::BaseField>,
+    sk: ScalarField<P>,
+    msg: P::BaseField,
+    rng: &mut R,
+) -> Signature<P>
+where
+    P: SWModelParameters,
+    R: RngCore + CryptoRng,
+    P::BaseField: PrimeField,
+{
+    let g = GroupAffine::<P>::prime_subgroup_generator();
+    let pk = g.mul(sk).into_affine();
+    let k = ScalarField::<P>::rand(rng);
+    let r = g.mul(k).into_affine();
+    let e = challenge_scalar::<P>(challenge(params, &pk, &r, msg));
+    Signature { r, s: k + e * sk }
+}
+
+/// Verifies natively that `sig` is a signature of `msg` under `pk`.
+pub fn verify<P>(
+    params: &ArithmeticSpongeParams<P::BaseField>,
+    pk: &GroupAffine<P>,
+    msg: P::BaseField,
+    sig: &Signature<P>,
+) -> bool
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    let e = challenge_scalar::<P>(challenge(params, pk, &sig.r, msg));
+    let g = GroupAffine::<P>::prime_subgroup_generator();
+    g.mul(sig.s) == sig.r.into_projective() + pk.mul(e)
+}
+
+/// The variables of a point, as its coordinates.
+pub type PointVars = (Var, Var);
+
+/// The variables of a signature: the coordinates of `R` and the bits of `s`, least significant bit first.
+#[derive(Clone, Debug)]
+pub struct SignatureVars {
+    pub r: PointVars,
+    pub s: Vec<Var>,
+}
+
+impl SignatureVars {
+    /// Allocates the private inputs of a signature over the curve of parameters `P`
+    /// (see [signature_inputs]).
+    pub fn private_input<P>(b: &mut CircuitBuilder<P::BaseField>) -> Self
+    where
+        P: SWModelParameters,
+        P::BaseField: PrimeField,
+    {
+        let r = (b.private_input(), b.private_input());
+        let s = (0..ScalarField::<P>::size_in_bits())
+            .map(|_| b.private_input())
+            .collect();
+        Self { r, s }
+    }
+}
+
+/// Returns the values of the inputs allocated by [SignatureVars::private_input].
+pub fn signature_inputs<P>(sig: &Signature<P>) -> Vec<P::BaseField>
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    let s = sig.s.into_repr();
+    let bits =
+        (0..ScalarField::<P>::size_in_bits()).map(|i| P::BaseField::from(s.get_bit(i) as u64));
+    [sig.r.x, sig.r.y].into_iter().chain(bits).collect()
+}
+
+/// Constrains `p` to be on the curve `y^2 = x^3 + b`.
+fn assert_on_curve<P>(b: &mut CircuitBuilder<P::BaseField>, p: PointVars)
+where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    let (x, y) = p;
+    let y2 = b.mul(y, y);
+    let x2 = b.mul(x, x);
+    let x3 = b.mul(x2, x);
+    let one = P::BaseField::one();
+    b.generic(
+        [
+            one,
+            -one,
+            P::BaseField::zero(),
+            P::BaseField::zero(),
+            -P::COEFF_B,
+        ],
+        [Some(y2), Some(x3), None],
+    );
+}
+
+/// Returns `p + q`, computed with a complete addition gate
+/// constrained not to result in the point at infinity.
+pub(crate) fn add<F: PrimeField>(
+    b: &mut CircuitBuilder<F>,
+    p: PointVars,
+    q: PointVars,
+) -> PointVars {
+    let ((x1, y1), (x2, y2)) = (p, q);
+    let outputs = b.compute(&[x1, y1, x2, y2], 6, |v| {
+        let (x1, y1, x2, y2) = (v[0], v[1], v[2], v[3]);
+        let same_x = x1 == x2;
+        let s = if same_x {
+            let x1_squared = x1.square();
+            (x1_squared.double() + x1_squared) / y1.double()
+        } else {
+            (y2 - y1) / (x2 - x1)
+        };
+        let x3 = s.square() - x1 - x2;
+        let y3 = s * (x1 - x3) - y1;
+        let inf_z = if same_x && y1 != y2 {
+            (y2 - y1).inverse().unwrap()
+        } else {
+            F::zero()
+        };
+        let x21_inv = (x2 - x1).inverse().unwrap_or_else(F::zero);
+        vec![x3, y3, F::from(same_x as u64), s, inf_z, x21_inv]
+    });
+    let inf = b.constant(F::zero());
+    let mut cells = [None; COLUMNS];
+    for (cell, var) in cells.iter_mut().zip(
+        [x1, y1, x2, y2, outputs[0], outputs[1], inf]
+            .iter()
+            .chain(&outputs[2..]),
+    ) {
+        *cell = Some(*var);
+    }
+    b.gate(GateType::CompleteAdd, vec![], cells);
+    (outputs[0], outputs[1])
+}
+
+/// Returns `2^n offset + k base`, for the `n` bits of `k`.
+fn mul_with_offset<F: PrimeField>(
+    b: &mut CircuitBuilder<F>,
+    base: PointVars,
+    bits: &[Var],
+    offset: PointVars,
+) -> PointVars {
+    bits.iter().rev().fold(offset, |acc, bit| {
+        let doubled = add(b, acc, acc);
+        let sum = add(b, doubled, base);
+        (
+            b.select(*bit, sum.0, doubled.0),
+            b.select(*bit, sum.1, doubled.1),
+        )
+    })
+}
+
+/// Returns the point of the curve at which the accumulators start.
+fn offset<P: SWModelParameters>() -> GroupAffine<P> {
+    let k = ScalarField::<P>::from_le_bytes_mod_order(b"kimchi schnorr offset");
+    GroupAffine::<P>::prime_subgroup_generator()
+        .mul(k)
+        .into_affine()
+}
+
+/// Constrains `sig` to be a signature of `msg` under the public key `pk`.
+/// The Poseidon gates of the challenge are only satisfied if `params` are the sponge parameters
+/// of the constraint system.
+pub fn verify_schnorr<P>(
+    b: &mut CircuitBuilder<P::BaseField>,
+    params: &ArithmeticSpongeParams<P::BaseField>,
+    pk: PointVars,
+    msg: Var,
+    sig: &SignatureVars,
+) where
+    P: SWModelParameters,
+    P::BaseField: PrimeField,
+{
+    assert!(
+        P::COEFF_A.is_zero(),
+        "the complete addition gate is only defined over curves y^2 = x^3 + b"
+    );
+    b.namespace("schnorr", |b| {
+        assert_on_curve::<P>(b, pk);
+        assert_on_curve::<P>(b, sig.r);
+        for bit in &sig.s {
+            b.assert_boolean(*bit);
+        }
+
+        let mut sponge = Sponge::new(b, params.clone());
+        sponge.absorb(b, &[pk.0, pk.1, sig.r.0, sig.r.1, msg]);
+        let e = sponge.squeeze(b);
+        let mut e_bits = b.unpack_canonical(e);
+
+        // both scalars take as many doublings, so that the offsets cancel out
+        let mut s_bits = sig.s.clone();
+        let n = s_bits.len().max(e_bits.len());
+        let zero = b.constant(P::BaseField::zero());
+        s_bits.resize(n, zero);
+        e_bits.resize(n, zero);
+
+        let constant = |b: &mut CircuitBuilder<P::BaseField>, p: GroupAffine<P>| {
+            (b.constant(p.x), b.constant(p.y))
+        };
+        let g = constant(b, GroupAffine::<P>::prime_subgroup_generator());
+        let offset = constant(b, offset::<P>());
+
+        let lhs = mul_with_offset(b, g, &s_bits, offset);
+        let e_pk = mul_with_offset(b, pk, &e_bits, offset);
+        let rhs = add(b, e_pk, sig.r);
+        b.assert_equal(lhs.0, rhs.0);
+        b.assert_equal(lhs.1, rhs.1);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{check::check_witness, constraints::ConstraintSystem};
+    use mina_curves::pasta::{
+        fp::Fp,
+        fq::Fq,
+        pallas::{Affine as Pallas, PallasParameters},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn schnorr_signatures_verify_in_circuit() {
+        let params = oracle::pasta::fp_kimchi::params();
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let sk = Fq::rand(rng);
+        let pk = Pallas::prime_subgroup_generator().mul(sk).into_affine();
+        let msg = Fp::from(42u64);
+        let sig = sign::<PallasParameters, _>(&params, sk, msg, rng);
+        assert!(verify(&params, &pk, msg, &sig));
+        assert!(!verify(&params, &pk, msg + Fp::from(1u64), &sig));
+
+        let mut b = CircuitBuilder::<Fp>::new();
+        let pk_var = (b.public_input(), b.public_input());
+        let msg_var = b.public_input();
+        let sig_vars = SignatureVars::private_input::<PallasParameters>(&mut b);
+        verify_schnorr::<PallasParameters>(&mut b, &params, pk_var, msg_var, &sig_vars);
+        let (gates, generator) = b.build();
+        let cs = ConstraintSystem::create(gates, vec![], params.clone(), 3).unwrap();
+
+        let public = [pk.x, pk.y, msg];
+        let private = signature_inputs(&sig);
+        let witness = generator.generate(&public, &private);
+        check_witness(&witness, &cs, &public).unwrap();
+
+        // the signature is bound to the message
+        let other = [pk.x, pk.y, msg + Fp::from(1u64)];
+        let witness = generator.generate(&other, &private);
+        assert!(check_witness(&witness, &cs, &other).is_err());
+
+        // and to the key
+        let forged = sign::<PallasParameters, _>(&params, Fq::rand(rng), msg, rng);
+        let witness = generator.generate(&public, &signature_inputs(&forged));
+        assert!(check_witness(&witness, &cs, &public).is_err());
+    }
+}
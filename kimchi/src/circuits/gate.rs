@@ -1,6 +1,8 @@
 //! This module implements Plonk constraint gate primitive.
 
-use crate::circuits::{constraints::ConstraintSystem, domains::EvaluationDomains, wires::*};
+use crate::circuits::{
+    constraints::ConstraintSystem, domains::EvaluationDomains, polynomials::lookup_gate, wires::*,
+};
 use ark_ff::bytes::ToBytes;
 use ark_ff::{FftField, Field};
 use ark_poly::{Evaluations as E, Radix2EvaluationDomain as D};
@@ -84,24 +86,53 @@ impl<F: Field> SingleLookup<F> {
     }
 }
 
+/// The table that a joint lookup looks up.
+///
+/// When a circuit looks up several tables, its lookups are combined with the id of their table,
+/// like the entries of the table of the lookup argument are combined with the id of the table they come from
+/// (see [LookupConstraintSystem::table_ids](crate::circuits::constraints::LookupConstraintSystem::table_ids)).
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum LookupTableId {
+    /// A table of the gates
+    Gate(GateLookupTable),
+    /// A table of the circuit, whose id is in a cell
+    Cell(LocalPosition),
+}
+
+impl LookupTableId {
+    /// Evaluate the id of the table.
+    pub fn evaluate<F: Field, G: Fn(LocalPosition) -> F>(&self, eval: G) -> F {
+        match self {
+            LookupTableId::Gate(table) => table.id(),
+            LookupTableId::Cell(pos) => eval(*pos),
+        }
+    }
+}
+
 /// A spec for checking that the given vector belongs to a vector-valued lookup table.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct JointLookup<F> {
-    pub table_id: usize,
+    pub table_id: LookupTableId,
     pub entry: Vec<SingleLookup<F>>,
 }
 
 impl<F: Field> JointLookup<F> {
-    // TODO: Support multiple tables
     /// Evaluate the combined value of a joint-lookup.
-    pub fn evaluate<G: Fn(LocalPosition) -> F>(&self, joint_combiner: F, eval: &G) -> F {
+    /// The id of its table is added with the coefficient `table_id_combiner`,
+    /// which is zero when the circuit only looks up a single table.
+    pub fn evaluate<G: Fn(LocalPosition) -> F>(
+        &self,
+        joint_combiner: F,
+        table_id_combiner: F,
+        eval: &G,
+    ) -> F {
         let mut res = F::zero();
         let mut c = F::one();
         for s in self.entry.iter() {
             res += c * s.evaluate(eval);
             c *= joint_combiner;
         }
-        res
+        res + table_id_combiner * self.table_id.evaluate(eval)
     }
 }
 
@@ -151,6 +182,14 @@ pub enum GateType {
     ChaCha1 = 8,
     ChaCha2 = 9,
     ChaChaFinal = 10,
+    /// A gate defined outside of this crate (see [crate::circuits::custom])
+    Custom = 11,
+    /// Weighted sum of the cells of a row (see [crate::circuits::polynomials::linear_combination])
+    LinearCombination = 12,
+    /// Booleanity of the wired cells of a row (see [crate::circuits::polynomials::boolean])
+    Boolean = 13,
+    /// Lookups of the cells of a row in a table of the circuit (see [crate::circuits::polynomials::lookup_gate])
+    Lookup = 14,
 }
 
 /// Describes the desired lookup configuration.
@@ -191,6 +230,17 @@ pub enum GateLookupTable {
     Xor,
 }
 
+impl GateLookupTable {
+    /// The id of the table, when the circuit looks up several tables.
+    /// The ids of the gate tables are negative, so that they differ from the ids of the tables of a circuit,
+    /// which are their positions.
+    pub fn id<F: Field>(self) -> F {
+        match self {
+            GateLookupTable::Xor => -F::one(),
+        }
+    }
+}
+
 pub type LookupTable<F> = Vec<Vec<F>>;
 
 pub fn get_table<F: FftField>(table_name: GateLookupTable) -> LookupTable<F> {
@@ -242,15 +292,15 @@ impl<F: FftField> LookupInfo<F> {
     }
 
     /// Each entry in `kinds` has a corresponding selector polynomial that controls whether that
-    /// lookup kind should be enforced at a given row. This computes those selector polynomials.
+    /// lookup kind should be enforced at a given row. This computes those selector polynomials,
+    /// along with the tables of the gates that `gates` look up.
     pub fn selector_polynomials_and_tables(
         &self,
         domain: &EvaluationDomains<F>,
         gates: &[CircuitGate<F>],
-    ) -> (Vec<Evaluations<F>>, Vec<LookupTable<F>>) {
+    ) -> (Vec<Evaluations<F>>, Vec<(GateLookupTable, LookupTable<F>)>) {
         let n = domain.d1.size as usize;
         let mut selector_values: Vec<_> = self.kinds.iter().map(|_| vec![F::zero(); n]).collect();
-        let mut gate_tables = HashSet::new();
 
         // TODO: is take(n) useful here? I don't see why we need this
         for (i, gate) in gates.iter().enumerate().take(n) {
@@ -262,13 +312,6 @@ impl<F: FftField> LookupInfo<F> {
             if let Some(selector_index) = self.kinds_map.get(&(typ, CurrOrNext::Next)) {
                 selector_values[*selector_index][i + 1] = F::one();
             }
-
-            if let Some(table_kind) = self.kinds_tables.get(&(typ, CurrOrNext::Curr)) {
-                gate_tables.insert(*table_kind);
-            }
-            if let Some(table_kind) = self.kinds_tables.get(&(typ, CurrOrNext::Next)) {
-                gate_tables.insert(*table_kind);
-            }
         }
 
         // Actually, don't need to evaluate over domain 8 here.
@@ -281,10 +324,23 @@ impl<F: FftField> LookupInfo<F> {
                     .evaluate_over_domain(domain.d8)
             })
             .collect();
-        let res_tables: Vec<_> = gate_tables.into_iter().map(get_table).collect();
+        let res_tables: Vec<_> = self
+            .gate_tables(gates)
+            .into_iter()
+            .map(|table| (table, get_table(table)))
+            .collect();
         (selector_values8, res_tables)
     }
 
+    /// The tables of the gates that the lookups of `gates` look up.
+    pub fn gate_tables(&self, gates: &[CircuitGate<F>]) -> HashSet<GateLookupTable> {
+        gates
+            .iter()
+            .flat_map(|gate| [(gate.typ, CurrOrNext::Curr), (gate.typ, CurrOrNext::Next)])
+            .filter_map(|location| self.kinds_tables.get(&location).copied())
+            .collect()
+    }
+
     /// For each row in the circuit, which lookup-constraints should be enforced at that row.
     pub fn by_row<'a>(&'a self, gates: &[CircuitGate<F>]) -> Vec<&'a Vec<JointLookup<F>>> {
         let mut kinds = vec![&self.empty; gates.len() + 1];
@@ -322,8 +378,8 @@ pub struct GatesLookupMaps {
 
 impl GateType {
     /// Which lookup-patterns should be applied on which rows.
-    /// Currently there is only the lookup pattern used in the ChaCha rows, and it
-    /// is applied to each ChaCha row and its successor.
+    /// There are the lookup patterns used in the ChaCha rows, applied to each ChaCha row and its successor,
+    /// and the lookups of the lookup gate, applied to its row.
     ///
     /// See circuits/kimchi/src/polynomials/chacha.rs for an explanation of
     /// how these work.
@@ -349,7 +405,7 @@ impl GateType {
                     value: vec![(F::one(), loc)],
                 };
                 JointLookup {
-                    table_id: 0,
+                    table_id: LookupTableId::Gate(GateLookupTable::Xor),
                     entry: vec![l(left), l(right), l(output)],
                 }
             })
@@ -377,7 +433,7 @@ impl GateType {
                     value: vec![(one_half, nybble), (neg_one_half, low_bit)],
                 };
                 JointLookup {
-                    table_id: 0,
+                    table_id: LookupTableId::Gate(GateLookupTable::Xor),
                     entry: vec![x.clone(), x, SingleLookup { value: vec![] }],
                 }
             })
@@ -388,6 +444,9 @@ impl GateType {
             chacha_final_where.insert((ChaChaFinal, *r));
         }
 
+        // the table of the lookup gates is given with the circuit
+        let lookup_where = [(Lookup, Curr)].into_iter().collect();
+
         let lookups = [
             (chacha_pattern, chacha_where, Some(GateLookupTable::Xor)),
             (
@@ -395,6 +454,7 @@ impl GateType {
                 chacha_final_where,
                 Some(GateLookupTable::Xor),
             ),
+            (lookup_gate::lookups(), lookup_where, None),
         ];
 
         // Convert from an array of tuples to a tuple of vectors
@@ -505,6 +565,19 @@ impl<F: FftField> CircuitGate<F> {
             EndoMulScalar => self.verify_endomul_scalar(row, witness, cs),
             // TODO: implement the verification for chacha
             ChaCha0 | ChaCha1 | ChaCha2 | ChaChaFinal => Ok(()),
+            // the constraints of custom gates are checked by `check_witness`
+            Custom => match cs.custom_gate(self) {
+                Some((_, gate)) => gate.verify(row, witness),
+                None => Err("the custom gate is not registered".to_string()),
+            },
+            LinearCombination => self.verify_linear_combination(row, witness),
+            Boolean => self.verify_boolean(row, witness),
+            // the lookups of a runtime table are checked by the prover
+            Lookup if self.looks_up_runtime_table(cs) => Ok(()),
+            Lookup => match lookup_gate::table_entries(cs) {
+                Some(entries) => self.verify_lookup(row, witness, &entries),
+                None => Err("lookup: the circuit has no lookup table".to_string()),
+            },
         }
     }
 }
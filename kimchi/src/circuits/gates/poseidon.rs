@@ -7,10 +7,7 @@ use crate::circuits::{
 };
 use ark_ff::{FftField, Field};
 use array_init::array_init;
-use oracle::poseidon::{
-    sbox, ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, Sponge,
-    SpongeConstants,
-};
+use oracle::poseidon::{sbox, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, SpongeConstants};
 use std::ops::Range;
 
 //
@@ -53,10 +50,29 @@ impl<F: FftField> CircuitGate<F> {
         // Coefficients are passed in in the logical order
         coeffs: [[F; SPONGE_WIDTH]; ROUNDS_PER_ROW],
     ) -> Self {
+        Self::create_poseidon_rounds(wires, coeffs, [false; ROUNDS_PER_ROW])
+    }
+
+    /// Creates a Poseidon gate whose round `i` is a partial round if `partial[i]` is true.
+    /// The flags of the partial rounds are stored after the round constants in the coefficients of the gate,
+    /// and are only stored if the gate has a partial round.
+    pub fn create_poseidon_rounds(
+        wires: GateWires,
+        coeffs: [[F; SPONGE_WIDTH]; ROUNDS_PER_ROW],
+        partial: [bool; ROUNDS_PER_ROW],
+    ) -> Self {
+        let mut coeffs: Vec<F> = coeffs.iter().flatten().copied().collect();
+        if partial.contains(&true) {
+            coeffs.extend(
+                partial
+                    .iter()
+                    .map(|p| if *p { F::one() } else { F::zero() }),
+            );
+        }
         CircuitGate {
             typ: GateType::Poseidon,
             wires,
-            coeffs: coeffs.iter().flatten().copied().collect(),
+            coeffs,
         }
     }
 
@@ -73,11 +89,34 @@ impl<F: FftField> CircuitGate<F> {
         first_and_last_row: [GateWires; 2],
         round_constants: &[Vec<F>],
     ) -> (Vec<Self>, usize) {
+        Self::create_poseidon_gadget_with_rounds(
+            row,
+            first_and_last_row,
+            round_constants,
+            &[false; ROUNDS_PER_HASH],
+        )
+    }
+
+    /// Same as [CircuitGate::create_poseidon_gadget], for an instance of Poseidon
+    /// whose round `i` is a partial round if `partial[i]` is true.
+    /// The number of rounds, `partial.len()`, must be a multiple of [ROUNDS_PER_ROW].
+    pub fn create_poseidon_gadget_with_rounds(
+        row: usize,
+        first_and_last_row: [GateWires; 2],
+        round_constants: &[Vec<F>],
+        partial: &[bool],
+    ) -> (Vec<Self>, usize) {
+        assert_eq!(
+            partial.len() % ROUNDS_PER_ROW,
+            0,
+            "the rounds do not fill the Poseidon rows"
+        );
         let mut gates = vec![];
 
         // create the gates
-        let relative_rows = 0..POS_ROWS_PER_HASH;
-        let last_row = row + POS_ROWS_PER_HASH;
+        let rows = partial.len() / ROUNDS_PER_ROW;
+        let relative_rows = 0..rows;
+        let last_row = row + rows;
         let absolute_rows = row..last_row;
 
         for (abs_row, rel_row) in absolute_rows.zip(relative_rows) {
@@ -93,9 +132,10 @@ impl<F: FftField> CircuitGate<F> {
                 let round = rel_row * ROUNDS_PER_ROW + offset;
                 array_init(|field_el| round_constants[round][field_el])
             });
+            let partial = array_init(|offset| partial[rel_row * ROUNDS_PER_ROW + offset]);
 
             // create poseidon gate for this row
-            gates.push(CircuitGate::create_poseidon(wires, coeffs));
+            gates.push(CircuitGate::create_poseidon_rounds(wires, coeffs, partial));
         }
 
         // final (zero) gate that contains the output of poseidon
@@ -134,15 +174,21 @@ impl<F: FftField> CircuitGate<F> {
 
         // round constants
         let rc = self.rc();
+        let partial = self.partial_rounds();
 
         // for each round, check that the permutation was applied correctly
         for round in 0..ROUNDS_PER_ROW {
             for (i, mds_row) in cs.fr_sponge_params.mds.iter().enumerate() {
                 // i-th(new_state) = i-th(rc) + mds(sbox(state))
+                // (a partial round only applies the sbox to the first element)
                 let state = &states[round];
                 let mut new_state = rc[round][i];
-                for (&s, mds) in state.iter().zip(mds_row.iter()) {
-                    let sboxed = sbox::<F, PlonkSpongeConstantsKimchi>(s);
+                for (j, (&s, mds)) in state.iter().zip(mds_row.iter()).enumerate() {
+                    let sboxed = if j == 0 || !partial[round] {
+                        sbox::<F, PlonkSpongeConstantsKimchi>(s)
+                    } else {
+                        s
+                    };
                     new_state += sboxed * mds;
                 }
 
@@ -182,6 +228,14 @@ impl<F: FftField> CircuitGate<F> {
             })
         })
     }
+
+    /// Whether each round of this specific gate is a partial round
+    /// (see [CircuitGate::create_poseidon_rounds]).
+    pub fn partial_rounds(&self) -> [bool; ROUNDS_PER_ROW] {
+        array_init(|round| {
+            self.typ == GateType::Poseidon && self.coeffs.get(COLUMNS + round) == Some(&F::one())
+        })
+    }
 }
 
 /// `generate_witness(row, params, witness_cols, input)` uses a sponge initialized with
@@ -193,17 +247,30 @@ pub fn generate_witness<F: Field>(
     witness_cols: &mut [Vec<F>; COLUMNS],
     input: [F; SPONGE_WIDTH],
 ) {
+    generate_witness_with_rounds(row, &params, witness_cols, input, &[false; ROUNDS_PER_HASH])
+}
+
+/// Same as [generate_witness], for the gates of [CircuitGate::create_poseidon_gadget_with_rounds]
+/// whose round `i` is a partial round if `partial[i]` is true.
+pub fn generate_witness_with_rounds<F: Field>(
+    row: usize,
+    params: &ArithmeticSpongeParams<F>,
+    witness_cols: &mut [Vec<F>; COLUMNS],
+    input: [F; SPONGE_WIDTH],
+    partial: &[bool],
+) {
+    if PlonkSpongeConstantsKimchi::PERM_INITIAL_ARK {
+        panic!("this won't work if the circuit has an INITIAL_ARK")
+    }
+
     // add the input into the witness
     witness_cols[0][row] = input[0];
     witness_cols[1][row] = input[1];
     witness_cols[2][row] = input[2];
 
-    // set the sponge state
-    let mut sponge = ArithmeticSponge::<F, PlonkSpongeConstantsKimchi>::new(params);
-    sponge.state = input.into();
-
     // for the poseidon rows
-    for row_idx in 0..POS_ROWS_PER_HASH {
+    let mut state = input;
+    for row_idx in 0..partial.len() / ROUNDS_PER_ROW {
         let row = row + row_idx;
         for round in 0..ROUNDS_PER_ROW {
             // the last round makes use of the next row
@@ -216,17 +283,32 @@ pub fn generate_witness<F: Field>(
             //
             let abs_round = round + row_idx * ROUNDS_PER_ROW;
 
-            // apply the sponge and record the result in the witness
-            if PlonkSpongeConstantsKimchi::PERM_INITIAL_ARK {
-                panic!("this won't work if the circuit has an INITIAL_ARK")
-            }
-            sponge.full_round(abs_round);
+            // apply the round: sbox, mds, then the round constants
+            let sboxed: Vec<F> = state
+                .iter()
+                .enumerate()
+                .map(|(j, s)| {
+                    if j == 0 || !partial[abs_round] {
+                        sbox::<F, PlonkSpongeConstantsKimchi>(*s)
+                    } else {
+                        *s
+                    }
+                })
+                .collect();
+            state = array_init(|i| {
+                params.mds[i]
+                    .iter()
+                    .zip(&sboxed)
+                    .fold(params.round_constants[abs_round][i], |acc, (m, s)| {
+                        acc + *m * s
+                    })
+            });
 
-            // apply the sponge and record the result in the witness
+            // record the result in the witness
             let cols_to_update = round_to_cols((round + 1) % ROUNDS_PER_ROW);
             witness_cols[cols_to_update]
                 .iter_mut()
-                .zip(sponge.state.iter())
+                .zip(state.iter())
                 // update the state (last update is on the next row)
                 .for_each(|(w, s)| w[maybe_next_row] = *s);
         }
@@ -0,0 +1,199 @@
+//! This module implements a textual format for circuits,
+//! so that circuits can be generated by external tools and proven with this crate.
+//!
+//! A circuit is written as a header, the number of public inputs, and one line per gate:
+//!
+//! ```text
+//! kimchi-circuit 1
+//! public 1
+//! # the public input, then x + y = 5
+//! Generic 0,0 0,1 0,2 0,3 0,4 0,5 0,6 | 1 0 0 0 0
+//! Generic 1,0 1,1 1,2 1,3 1,4 1,5 1,6 | 1 1 0 0 5
+//! ```
+//!
+//! Each gate gives its type (the name of a [GateType]), which determines its selectors,
+//! then the [PERMUTS] cells (`row,col`) its wired cells are connected to, which determine the permutation,
+//! and finally, after a `|`, its coefficients as decimal numbers smaller than the modulus of the field.
+//! Empty lines and lines starting with `#` are ignored.
+
+use crate::circuits::{
+    constraints::ConstraintSystem,
+    gate::{CircuitGate, GateType},
+    wires::{Wire, PERMUTS},
+};
+use crate::error::IrError;
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use num_traits::FromPrimitive;
+use std::fmt::Write;
+
+/// The first line of a circuit in the textual format, which identifies the version of the format.
+pub const HEADER: &str = "kimchi-circuit 1";
+
+/// Writes the circuit made of `gates`, with `public` public inputs, in the textual format.
+pub fn to_ir<F: PrimeField>(gates: &[CircuitGate<F>], public: usize) -> String {
+    let mut ir = format!("{HEADER}\npublic {public}\n");
+    for gate in gates {
+        write!(ir, "{:?}", gate.typ).unwrap();
+        for wire in &gate.wires {
+            write!(ir, " {},{}", wire.row, wire.col).unwrap();
+        }
+        ir.push_str(" |");
+        for coeff in &gate.coeffs {
+            let coeff: BigUint = (*coeff).into();
+            write!(ir, " {coeff}").unwrap();
+        }
+        ir.push('\n');
+    }
+    ir
+}
+
+/// Reads a circuit written in the textual format, returning its gates and its number of public inputs.
+pub fn from_ir<F: PrimeField>(ir: &str) -> Result<(Vec<CircuitGate<F>>, usize), IrError> {
+    let mut lines = ir
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    match lines.next() {
+        Some((_, line)) if line == HEADER => (),
+        _ => return Err(IrError::Header(HEADER)),
+    }
+
+    let public = match lines.next() {
+        Some((line, text)) => text
+            .strip_prefix("public ")
+            .and_then(|n| n.trim().parse().ok())
+            .ok_or_else(|| syntax(line, "expected the number of public inputs"))?,
+        None => return Err(syntax(1, "expected the number of public inputs")),
+    };
+
+    let gates = lines
+        .map(|(line, text)| parse_gate(text).map_err(|err| syntax(line, &err)))
+        .collect::<Result<_, _>>()?;
+
+    Ok((gates, public))
+}
+
+fn syntax(line: usize, err: &str) -> IrError {
+    IrError::Syntax {
+        line,
+        err: err.to_string(),
+    }
+}
+
+/// Returns the gate type named `name`.
+fn gate_type(name: &str) -> Option<GateType> {
+    (0..)
+        .map_while(GateType::from_u32)
+        .find(|typ| format!("{typ:?}") == name)
+}
+
+fn parse_gate<F: PrimeField>(text: &str) -> Result<CircuitGate<F>, String> {
+    let (cells, coeffs) = text
+        .split_once('|')
+        .ok_or("expected a `|` before the coefficients")?;
+    let mut cells = cells.split_whitespace();
+
+    let name = cells.next().ok_or("expected a gate type")?;
+    let typ = gate_type(name).ok_or_else(|| format!("unknown gate type {name}"))?;
+
+    let wires: Vec<Wire> = cells
+        .map(|cell| {
+            let (row, col) = cell
+                .split_once(',')
+                .and_then(|(row, col)| Some((row.parse().ok()?, col.parse().ok()?)))
+                .ok_or_else(|| format!("invalid cell {cell}"))?;
+            Ok(Wire { row, col })
+        })
+        .collect::<Result<_, String>>()?;
+    let wires = wires
+        .try_into()
+        .map_err(|wires: Vec<_>| format!("expected {PERMUTS} wires, got {}", wires.len()))?;
+
+    let coeffs = coeffs
+        .split_whitespace()
+        .map(|coeff| {
+            let n: BigUint = coeff
+                .parse()
+                .map_err(|_| format!("invalid coefficient {coeff}"))?;
+            let x = F::from(n.clone());
+            let reduced: BigUint = x.into();
+            if reduced != n {
+                return Err(format!("the coefficient {coeff} is not reduced"));
+            }
+            Ok(x)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(CircuitGate { typ, wires, coeffs })
+}
+
+impl<F: PrimeField> ConstraintSystem<F> {
+    /// Writes the gates of the constraint system (including the padding to the size of the domain) in the textual format.
+    pub fn to_ir(&self) -> String {
+        to_ir(&self.gates, self.public)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::polynomials::generic::testing::create_circuit;
+    use ark_ff::One;
+    use mina_curves::pasta::fp::Fp;
+
+    #[test]
+    fn test_ir_round_trip() {
+        let gates = create_circuit::<Fp>(0, 3);
+        let ir = to_ir(&gates, 3);
+        assert!(ir.starts_with(HEADER));
+
+        let (parsed, public) = from_ir::<Fp>(&ir).unwrap();
+        assert_eq!(public, 3);
+        assert_eq!(parsed.len(), gates.len());
+        for (parsed, gate) in parsed.iter().zip(&gates) {
+            assert_eq!(parsed.typ, gate.typ);
+            assert_eq!(parsed.wires, gate.wires);
+            assert_eq!(parsed.coeffs, gate.coeffs);
+        }
+
+        // the constraint system of the parsed circuit is the same
+        let params = || oracle::pasta::fp_kimchi::params();
+        let cs = ConstraintSystem::create(gates, vec![], params(), 3).unwrap();
+        let other = ConstraintSystem::create(parsed, vec![], params(), 3).unwrap();
+        assert_eq!(cs.to_ir(), other.to_ir());
+    }
+
+    #[test]
+    fn test_ir_errors() {
+        assert_eq!(
+            from_ir::<Fp>("public 0\n").map(|_| ()),
+            Err(IrError::Header(HEADER))
+        );
+
+        let ir = format!("{HEADER}\n# a comment\npublic 0\nGeneric 0,0 | 1\n");
+        assert_eq!(
+            from_ir::<Fp>(&ir).map(|_| ()),
+            Err(IrError::Syntax {
+                line: 4,
+                err: "expected 7 wires, got 1".to_string()
+            })
+        );
+
+        let ir = format!("{HEADER}\npublic 0\nAdd 0,0 0,1 0,2 0,3 0,4 0,5 0,6 |\n");
+        assert!(matches!(
+            from_ir::<Fp>(&ir),
+            Err(IrError::Syntax { line: 3, .. })
+        ));
+
+        // the coefficients must be given modulo the field
+        let p: BigUint = (-Fp::one()).into();
+        let ir = format!(
+            "{HEADER}\npublic 0\nGeneric 0,0 0,1 0,2 0,3 0,4 0,5 0,6 | {}\n",
+            p + 1u8
+        );
+        assert!(from_ir::<Fp>(&ir).is_err());
+    }
+}
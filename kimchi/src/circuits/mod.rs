@@ -2,12 +2,23 @@
 pub mod macros;
 
 pub mod argument;
+pub mod builder;
+pub mod check;
 pub mod constraints;
+pub mod custom;
 pub mod domains;
 pub mod expr;
+pub mod fft;
+pub mod gadgets;
 pub mod gate;
 pub mod gates;
+pub mod ir;
 pub mod polynomial;
 pub mod polynomials;
+pub mod public_input;
 pub mod scalars;
+pub mod sparse;
+pub mod stats;
+pub mod unconstrained;
 pub mod wires;
+pub mod witness;
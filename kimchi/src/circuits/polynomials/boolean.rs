@@ -0,0 +1,61 @@
+//! This module implements the boolean gate,
+//! which constrains each of the wired cells of a row to be a boolean:
+//!
+//! $$w_i \cdot (w_i - 1) = 0 \text{ for } i = 0, \ldots, 6$$
+//!
+//! A row thus checks [PERMUTS] booleans, against two for a generic gate
+//! (see [CircuitBuilder::assert_bits](crate::circuits::builder::CircuitBuilder::assert_bits)).
+//! The cells that are not used hold zero, which is a boolean.
+//! Its selector is only created (and committed to) for the circuits that use the gate.
+
+use std::marker::PhantomData;
+
+use crate::circuits::{
+    argument::{Argument, ArgumentType},
+    expr::prologue::*,
+    gate::{CircuitGate, GateType},
+    wires::{GateWires, COLUMNS, PERMUTS},
+};
+use ark_ff::{FftField, One, Zero};
+
+impl<F: FftField> CircuitGate<F> {
+    /// Creates a boolean gate, constraining the wired cells of the row to be booleans.
+    pub fn create_boolean(wires: GateWires) -> Self {
+        CircuitGate {
+            typ: GateType::Boolean,
+            wires,
+            coeffs: vec![],
+        }
+    }
+
+    /// Checks that the wired cells of the row `row` of the witness are booleans.
+    pub fn verify_boolean(&self, row: usize, witness: &[Vec<F>; COLUMNS]) -> Result<(), String> {
+        ensure_eq!(self.typ, GateType::Boolean, "boolean: incorrect gate");
+        for (col, w) in witness.iter().take(PERMUTS).enumerate() {
+            let x = w[row];
+            if x * (x - F::one()) != F::zero() {
+                return Err(format!(
+                    "boolean: the cell of column {col} is not a boolean"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Implementation of the boolean gate.
+pub struct Boolean<F>(PhantomData<F>);
+
+impl<F> Argument<F> for Boolean<F>
+where
+    F: FftField,
+{
+    const ARGUMENT_TYPE: ArgumentType = ArgumentType::Gate(GateType::Boolean);
+    const CONSTRAINTS: u32 = PERMUTS as u32;
+
+    fn constraints() -> Vec<E<F>> {
+        (0..PERMUTS)
+            .map(|i| witness_curr(i) * (witness_curr(i) - E::one()))
+            .collect()
+    }
+}
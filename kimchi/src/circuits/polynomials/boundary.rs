@@ -0,0 +1,113 @@
+//! This module implements the boundary constraints of a circuit,
+//! which pin the value of a cell of the witness, or of the permutation aggregation polynomial $z$, on a given row:
+//!
+//! $$\frac{Z_H(x)}{x - \omega^i} \cdot (c(x) - v) = 0$$
+//!
+//! for the column $c$ pinned to the value $v$ on the row $i$.
+//! The unnormalized Lagrange polynomial of the row vanishes on all the other rows,
+//! so that each boundary constraint only checks its own cell.
+//!
+//! The permutation argument keeps its own boundary constraints,
+//! that $z$ starts at one on the first row and ends at one before the zero-knowledge rows.
+//! A circuit can add others, for example to pin the output of its last row
+//! (see [ConstraintSystem::add_boundary_constraint]).
+
+use crate::{
+    alphas::Alphas,
+    circuits::{
+        argument::ArgumentType,
+        constraints::{ConstraintSystem, GateError, ZK_ROWS},
+        expr::{prologue::*, Column, Expr},
+        gate::CurrOrNext,
+        wires::{Wire, COLUMNS},
+    },
+    error::SetupError,
+};
+use ark_ff::{FftField, Field};
+use ark_poly::EvaluationDomain;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+/// A cell of the circuit pinned to a value.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundaryConstraint<F: Field> {
+    /// the row of the cell
+    pub row: usize,
+    /// the column of the cell, a column of the witness or [Column::Z]
+    pub column: Column,
+    /// the value of the cell
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
+    pub value: F,
+}
+
+impl<F: Field> BoundaryConstraint<F> {
+    /// The index of the column of the cell, the columns of the witness being followed by $z$.
+    pub fn column_index(&self) -> usize {
+        match self.column {
+            Column::Witness(i) => i,
+            _ => COLUMNS,
+        }
+    }
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    /// Pins the cell of `column` on `row` to `value`.
+    /// The column must be a column of the witness or [Column::Z],
+    /// and the row must not be one of the zero-knowledge rows of the domain.
+    pub fn add_boundary_constraint(
+        &mut self,
+        row: usize,
+        column: Column,
+        value: F,
+    ) -> Result<(), SetupError> {
+        let rows = self.domain.d1.size() - ZK_ROWS as usize;
+        let pinnable = match column {
+            Column::Witness(i) => i < COLUMNS,
+            Column::Z => true,
+            _ => false,
+        };
+        if !pinnable || row >= rows {
+            return Err(SetupError::InvalidBoundary { row, column });
+        }
+        self.boundary
+            .push(BoundaryConstraint { row, column, value });
+        Ok(())
+    }
+}
+
+/// Checks that the cells of the witness `witness` hold the values that the boundary constraints `boundary` pin them to,
+/// skipping the cells of $z$, which only the prover computes.
+pub fn check<F: Field>(
+    boundary: &[BoundaryConstraint<F>],
+    witness: &[Vec<F>; COLUMNS],
+) -> Result<(), GateError> {
+    for b in boundary {
+        if let Column::Witness(col) = b.column {
+            if witness[col].get(b.row) != Some(&b.value) {
+                return Err(GateError::Boundary(Wire { row: b.row, col }));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the constraints of the boundary constraints `boundary`, in order.
+pub fn constraints<F: Field>(boundary: &[BoundaryConstraint<F>]) -> Vec<E<F>> {
+    boundary
+        .iter()
+        .map(|b| {
+            E::UnnormalizedLagrangeBasis(b.row)
+                * (E::cell(b.column, CurrOrNext::Curr) - E::literal(b.value))
+        })
+        .collect()
+}
+
+/// Returns the boundary constraints `boundary` combined with the powers of alpha registered for them in `alphas`.
+pub fn combined_constraints<F: Field>(
+    boundary: &[BoundaryConstraint<F>],
+    alphas: &Alphas<F>,
+) -> E<F> {
+    let alphas = alphas.get_exponents(ArgumentType::Boundary, boundary.len() as u32);
+    Expr::combine_constraints(alphas, constraints(boundary))
+}
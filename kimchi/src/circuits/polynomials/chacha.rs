@@ -595,6 +595,7 @@ mod tests {
                     .collect(),
                 aggreg: F::rand(rng),
                 table: F::rand(rng),
+                runtime: None,
             }),
         };
         let evals = vec![eval(), eval()];
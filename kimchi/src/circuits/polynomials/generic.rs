@@ -32,6 +32,16 @@
 //~ with m1 (resp. m2) the mul selector for the first (resp. second) gate,
 //~ and c1 (resp. c2) the constant selector for the first (resp. second) gate.
 //~
+//~ The first generic gate can also multiply an affine term of its left and right registers
+//~ by the register $m$ of the column 6, adding to its equation the fused product
+//~
+//~ $$(c_{10} \cdot l + c_{11} \cdot r + c_{12}) \cdot m$$
+//~
+//~ so that $o = (a \cdot l + b \cdot r + c) \cdot m$ only takes half a row,
+//~ instead of a generic gate for the affine term and another for its product.
+//~ The fused product is only part of the constraint of the circuits that use it
+//~ (which the flag `fused_generic` of the constraint system records), and the other circuits keep the same proofs.
+//~
 
 use crate::circuits::{
     constraints::ConstraintSystem,
@@ -55,6 +65,13 @@ pub const GENERIC_REGISTERS: usize = 3;
 /// and one for the constant.
 pub const GENERIC_COEFFS: usize = GENERIC_REGISTERS + 1 /* mul */ + 1 /* cst */;
 
+/// Number of coefficients of the fused product of the first generic gate,
+/// which follow the coefficients of the two generic gates.
+pub const FUSED_COEFFS: usize = 3;
+
+/// The column of the register that the fused product of the first generic gate multiplies.
+pub const FUSED_REGISTER: usize = 2 * GENERIC_REGISTERS;
+
 /// The different type of computation that are possible with a generic gate.
 /// This type is useful to create a generic gate via the [CircuitGate::create_generic_gadget] function.
 pub enum GenericGateSpec<F> {
@@ -152,6 +169,33 @@ impl<F: FftField> CircuitGate<F> {
         };
         Self::create_generic(wires, coeffs)
     }
+
+    /// Creates two generic gates, the first of which also has the fused product of coefficients `fused`
+    /// (see the [module documentation](self)).
+    pub fn create_generic_fused(
+        wires: GateWires,
+        c: [F; GENERIC_COEFFS * 2],
+        fused: [F; FUSED_COEFFS],
+    ) -> Self {
+        let mut coeffs = c.to_vec();
+        coeffs.extend(fused);
+        CircuitGate {
+            typ: GateType::Generic,
+            wires,
+            coeffs,
+        }
+    }
+
+    /// Returns whether this gate is a generic gate with a fused product.
+    pub fn has_fused_product(&self) -> bool {
+        self.typ == GateType::Generic
+            && self
+                .coeffs
+                .iter()
+                .skip(2 * GENERIC_COEFFS)
+                .take(FUSED_COEFFS)
+                .any(|c| !c.is_zero())
+    }
 }
 
 // -------------------------------------------------
@@ -159,6 +203,7 @@ impl<F: FftField> CircuitGate<F> {
 //~ The constraints:
 //~
 //~ * $w_0 \cdot c_0 + w_1 \cdot c_1 + w_2 \cdot c_2 + w_0 \cdot w_1 \cdot c_3 + c_4$
+//~   (plus $(w_0 \cdot c_{10} + w_1 \cdot c_{11} + c_{12}) \cdot w_6$ in the circuits with fused products)
 //~ * $w_3 \cdot c_5 + w_4 \cdot c_6 + w_5 \cdot c_7 + w_3 w_4 c_8 + c_9$
 //~
 //~ where the $c_i$ are the [coefficients]().
@@ -176,13 +221,20 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
                 self.domain.d4,
             );
 
+            // the columns of zero coefficients are skipped
+            let zero = |col: usize| self.coefficients_sparse[col].is_zero();
+
             // addition
-            for (witness_d4, selector_d8) in witness_cols_d4
+            for (i, (witness_d4, selector_d8)) in witness_cols_d4
                 .iter()
                 .skip(register_offset)
                 .zip(self.coefficients8.iter().skip(coeff_offset))
                 .take(GENERIC_REGISTERS)
+                .enumerate()
             {
+                if zero(coeff_offset + i) {
+                    continue;
+                }
                 res.evals
                     .par_iter_mut()
                     .enumerate()
@@ -190,20 +242,41 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
             }
 
             // multiplication
-            let mut mul = &witness_cols_d4[register_offset] * &witness_cols_d4[register_offset + 1];
-            let mul_selector_d8 = &self.coefficients8[coeff_offset + 3];
-            mul.evals
-                .par_iter_mut()
-                .enumerate()
-                .for_each(|(i, eval)| *eval *= mul_selector_d8[2 * i]);
-            res += &mul;
+            if !zero(coeff_offset + 3) {
+                let mut mul =
+                    &witness_cols_d4[register_offset] * &witness_cols_d4[register_offset + 1];
+                let mul_selector_d8 = &self.coefficients8[coeff_offset + 3];
+                mul.evals
+                    .par_iter_mut()
+                    .enumerate()
+                    .for_each(|(i, eval)| *eval *= mul_selector_d8[2 * i]);
+                res += &mul;
+            }
 
             // constant
-            let constant_d8 = &self.coefficients8[coeff_offset + 4];
-            res.evals
-                .par_iter_mut()
-                .enumerate()
-                .for_each(|(i, e)| *e += constant_d8[2 * i]);
+            if !zero(coeff_offset + 4) {
+                let constant_d8 = &self.coefficients8[coeff_offset + 4];
+                res.evals
+                    .par_iter_mut()
+                    .enumerate()
+                    .for_each(|(i, e)| *e += constant_d8[2 * i]);
+            }
+
+            // fused product, of the first gate
+            if coeff_offset == 0 && self.fused_generic {
+                let fused = 2 * GENERIC_COEFFS;
+                let l = &witness_cols_d4[0];
+                let r = &witness_cols_d4[1];
+                let m = &witness_cols_d4[FUSED_REGISTER];
+                let (cl, cr, cc) = (
+                    &self.coefficients8[fused],
+                    &self.coefficients8[fused + 1],
+                    &self.coefficients8[fused + 2],
+                );
+                res.evals.par_iter_mut().enumerate().for_each(|(i, e)| {
+                    *e += (cl[2 * i] * l.evals[i] + cr[2 * i] * r.evals[i] + cc[2 * i]) * m.evals[i]
+                });
+            }
 
             // alpha
             let alpha_pow = {
@@ -237,10 +310,13 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
     /// alpha * generic(zeta) * w[1](zeta),
     /// alpha * generic(zeta) * w[2](zeta)
     /// ```
+    ///
+    /// the scalars of the coefficients in order, followed by the ones of the fused product if `fused`.
     pub fn gnrc_scalars(
         mut alphas: impl Iterator<Item = F>,
         w_zeta: &[F; COLUMNS],
         generic_zeta: F,
+        fused: bool,
     ) -> Vec<F> {
         // setup
         let mut res = vec![];
@@ -270,6 +346,13 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
             .expect("not enough alpha powers for generic gate");
         generic_gate(alpha_pow2, GENERIC_REGISTERS);
 
+        if fused {
+            let alpha_generic_m = alpha_pow1 * generic_zeta * w_zeta[FUSED_REGISTER];
+            res.push(alpha_generic_m * w_zeta[0]);
+            res.push(alpha_generic_m * w_zeta[1]);
+            res.push(alpha_generic_m);
+        }
+
         res
     }
 
@@ -284,7 +367,7 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
         let n = d1.size as usize;
 
         // get scalars
-        let scalars = Self::gnrc_scalars(alphas, w_zeta, generic_zeta);
+        let scalars = Self::gnrc_scalars(alphas, w_zeta, generic_zeta, self.fused_generic);
 
         //
         let mut res = Evaluations::from_vec_and_domain(vec![F::zero(); n], d1);
@@ -339,9 +422,20 @@ pub mod testing {
                 } else {
                     F::zero()
                 };
+                let fused = if coeffs_offset == 0 {
+                    let fused = |i| {
+                        self.coeffs
+                            .get(2 * GENERIC_COEFFS + i)
+                            .copied()
+                            .unwrap_or_else(F::zero)
+                    };
+                    (fused(0) * this[0] + fused(1) * this[1] + fused(2)) * this[FUSED_REGISTER]
+                } else {
+                    F::zero()
+                };
                 ensure_eq!(
                     zero,
-                    sum + mul + self.coeffs[coeffs_offset + 4] - public,
+                    sum + mul + fused + self.coeffs[coeffs_offset + 4] - public,
                     "generic: incorrect gate"
                 );
                 Ok(())
@@ -381,6 +475,15 @@ pub mod testing {
             let mut res = generic_gate(0, 0);
             res += &generic_gate(GENERIC_COEFFS, GENERIC_REGISTERS);
 
+            // fused product
+            if self.fused_generic {
+                let fused = 2 * GENERIC_COEFFS;
+                let mut ff = &coefficientsm[fused] * &witness[0];
+                ff += &(&coefficientsm[fused + 1] * &witness[1]);
+                ff += &coefficientsm[fused + 2];
+                res += &(&ff * &witness[FUSED_REGISTER]);
+            }
+
             // public inputs
             res += public;
 
@@ -447,6 +550,18 @@ pub mod testing {
         gates
     }
 
+    /// Creates the circuit of [create_circuit] with `public` public inputs, all equal to 3,
+    /// and returns its gates, its witness (see [fill_in_witness]) and its public inputs.
+    pub fn create_circuit_and_witness<F: FftField>(
+        public: usize,
+    ) -> (Vec<CircuitGate<F>>, [Vec<F>; COLUMNS], Vec<F>) {
+        let public = vec![F::from(3u8); public];
+        let gates = create_circuit(0, public.len());
+        let mut witness: [Vec<F>; COLUMNS] = array_init(|_| vec![F::zero(); gates.len()]);
+        fill_in_witness(0, &mut witness, &public);
+        (gates, witness, public)
+    }
+
     // function to fill in a witness created via [create_circuit]
     pub fn fill_in_witness<F: FftField>(
         start_row: usize,
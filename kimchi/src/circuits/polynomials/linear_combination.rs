@@ -0,0 +1,81 @@
+//! This module implements the linear combination gate,
+//! which constrains a weighted sum of all the cells of a row to be zero:
+//!
+//! $$\sum_{i=0}^{14} q_i \cdot w_i = 0$$
+//!
+//! A weighted sum `out = sum_i q_i * x_i` is obtained by placing `out` in one of the cells with the weight $-1$
+//! (see [CircuitBuilder::weighted_sum](crate::circuits::builder::CircuitBuilder::weighted_sum)).
+//!
+//! The weights $q_i$ can differ on every row, and the verifier does not get their evaluations.
+//! Multiplying them by a selector would thus give a constraint that cannot be linearized,
+//! so they are not stored in the coefficient table (which the generic and Poseidon gates also use),
+//! but in their own polynomials, which are zero on the rows that do not use the gate
+//! and thus act as their own selector.
+//! These polynomials are only created (and committed to) for the circuits that use the gate.
+
+use crate::alphas::Alphas;
+use crate::circuits::{
+    argument::ArgumentType,
+    expr::{prologue::*, Column},
+    gate::{CircuitGate, CurrOrNext, GateType},
+    wires::{GateWires, COLUMNS},
+};
+use ark_ff::{FftField, Zero};
+
+/// Number of constraints produced by the gate.
+pub const CONSTRAINTS: u32 = 1;
+
+/// The weight of the cell of column `i` on the current row.
+pub fn weight<F>(i: usize) -> E<F> {
+    E::<F>::cell(Column::Weight(i), CurrOrNext::Curr)
+}
+
+/// The constraint of the gate, which holds trivially on the rows that do not use it.
+pub fn constraint<F: FftField>() -> E<F> {
+    (0..COLUMNS)
+        .map(|i| weight(i) * witness_curr(i))
+        .fold(E::zero(), |acc, x| acc + x)
+}
+
+/// The constraint of the gate, combined with its power of alpha.
+/// Unlike the other gates, it is not multiplied by a selector.
+pub fn combined_constraints<F: FftField>(alphas: &Alphas<F>) -> E<F> {
+    let alphas = alphas.get_exponents(ArgumentType::Gate(GateType::LinearCombination), CONSTRAINTS);
+    E::combine_constraints(alphas, vec![constraint()])
+}
+
+impl<F: FftField> CircuitGate<F> {
+    /// Creates a linear combination gate, constraining the cells of the row weighted by `weights` to sum to zero.
+    pub fn create_linear_combination(wires: GateWires, weights: [F; COLUMNS]) -> Self {
+        CircuitGate {
+            typ: GateType::LinearCombination,
+            wires,
+            coeffs: weights.to_vec(),
+        }
+    }
+
+    /// Checks that the cells of the row `row` of the witness, weighted by the coefficients of the gate, sum to zero.
+    pub fn verify_linear_combination(
+        &self,
+        row: usize,
+        witness: &[Vec<F>; COLUMNS],
+    ) -> Result<(), String> {
+        ensure_eq!(
+            self.typ,
+            GateType::LinearCombination,
+            "linear combination: incorrect gate"
+        );
+        let sum = self
+            .coeffs
+            .iter()
+            .zip(witness.iter())
+            .map(|(q, w)| *q * w[row])
+            .fold(F::zero(), |acc, x| acc + x);
+        ensure_eq!(
+            sum,
+            F::zero(),
+            "linear combination: the weighted sum is not zero"
+        );
+        Ok(())
+    }
+}
@@ -122,8 +122,13 @@
 
 use crate::{
     circuits::{
+        constraints::LookupConstraintSystem,
         expr::{prologue::*, Column, ConstantExpr, Variable},
-        gate::{CircuitGate, CurrOrNext, JointLookup, LocalPosition, LookupInfo, SingleLookup},
+        gate::{
+            CircuitGate, CurrOrNext, JointLookup, LocalPosition, LookupInfo, LookupTableId,
+            SingleLookup,
+        },
+        polynomials::runtime_tables,
         wires::COLUMNS,
     },
     error::{ProofError, Result},
@@ -134,10 +139,10 @@ use rand::Rng;
 use std::collections::HashMap;
 use CurrOrNext::*;
 
-/// Number of constraints produced by the argument.
+/// Number of constraints produced by the argument,
+/// besides the [CONSTRAINTS](runtime_tables::CONSTRAINTS) of a runtime table.
 pub const CONSTRAINTS: u32 = 7;
 
-// TODO: Update for multiple tables
 fn single_lookup<F: FftField>(s: &SingleLookup<F>) -> E<F> {
     // Combine the linear combination.
     s.value
@@ -152,12 +157,25 @@ fn single_lookup<F: FftField>(s: &SingleLookup<F>) -> E<F> {
         .fold(E::zero(), |acc, e| acc + e)
 }
 
-fn joint_lookup<F: FftField>(j: &JointLookup<F>) -> E<F> {
-    j.entry
+/// The combined joint lookup `j`, followed by the id of its table at the power `table_id_power` of the joint combiner
+/// if the circuit looks up several tables.
+fn joint_lookup<F: FftField>(j: &JointLookup<F>, table_id_power: Option<usize>) -> E<F> {
+    let entry = j
+        .entry
         .iter()
         .enumerate()
         .map(|(i, s)| E::constant(ConstantExpr::JointCombiner.pow(i as u64)) * single_lookup(s))
-        .fold(E::zero(), |acc, x| acc + x)
+        .fold(E::zero(), |acc, x| acc + x);
+    match table_id_power {
+        None => entry,
+        Some(power) => {
+            let id = match j.table_id {
+                LookupTableId::Gate(table) => E::literal(table.id()),
+                LookupTableId::Cell(pos) => E::cell(Column::Witness(pos.column), pos.row),
+            };
+            entry + E::constant(ConstantExpr::JointCombiner.pow(power as u64)) * id
+        }
+    }
 }
 
 struct AdjacentPairs<A, I: Iterator<Item = A>> {
@@ -227,6 +245,7 @@ pub fn verify<F: FftField, I: Iterator<Item = F>, G: Fn() -> I>(
     gates: &[CircuitGate<F>],
     witness: &[Vec<F>; COLUMNS],
     joint_combiner: F,
+    table_id_combiner: F,
     sorted: &[Evaluations<F, D<F>>],
 ) {
     sorted
@@ -298,7 +317,8 @@ pub fn verify<F: FftField, I: Iterator<Item = F>, G: Fn() -> I>(
             witness[pos.column][row]
         };
         for joint_lookup in spec.iter() {
-            let joint_lookup_evaluation = joint_lookup.evaluate(joint_combiner, &eval);
+            let joint_lookup_evaluation =
+                joint_lookup.evaluate(joint_combiner, table_id_combiner, &eval);
             *all_lookups.entry(joint_lookup_evaluation).or_insert(0) += 1
         }
 
@@ -340,10 +360,12 @@ pub trait Entry {
 pub struct CombinedEntry<F>(pub F);
 impl<F: Field> Entry for CombinedEntry<F> {
     type Field = F;
-    type Params = F;
+    /// The joint combiner, and the coefficient of the table id of the lookups
+    /// (see [JointLookup::evaluate]).
+    type Params = (F, F);
 
     fn evaluate(
-        joint_combiner: &F,
+        (joint_combiner, table_id_combiner): &(F, F),
         j: &JointLookup<F>,
         witness: &[Vec<F>; COLUMNS],
         row: usize,
@@ -356,7 +378,7 @@ impl<F: Field> Entry for CombinedEntry<F> {
             witness[pos.column][row]
         };
 
-        CombinedEntry(j.evaluate(*joint_combiner, &eval))
+        CombinedEntry(j.evaluate(*joint_combiner, *table_id_combiner, &eval))
     }
 }
 
@@ -381,7 +403,9 @@ impl<F: Field> Entry for UncombinedEntry<F> {
             witness[pos.column][row]
         };
 
-        UncombinedEntry(j.entry.iter().map(|s| s.evaluate(&eval)).collect())
+        let mut entry: Vec<_> = j.entry.iter().map(|s| s.evaluate(&eval)).collect();
+        entry.push(j.table_id.evaluate(eval));
+        UncombinedEntry(entry)
     }
 }
 
@@ -503,6 +527,7 @@ pub fn aggregation<R: Rng + ?Sized, F: FftField, I: Iterator<Item = F>>(
     gates: &[CircuitGate<F>],
     witness: &[Vec<F>; COLUMNS],
     joint_combiner: F,
+    table_id_combiner: F,
     beta: F,
     gamma: F,
     sorted: &[Evaluations<F, D<F>>],
@@ -528,7 +553,8 @@ pub fn aggregation<R: Rng + ?Sized, F: FftField, I: Iterator<Item = F>>(
             })
             .fold(F::one(), |acc, x| acc * x)
     }));
-    ark_ff::fields::batch_inversion::<F>(&mut lookup_aggreg[1..]);
+    // the sorted lookups depend on the witness
+    crate::circuits::witness::batch_inversion::<F>(&mut lookup_aggreg[1..]);
 
     let lookup_info = LookupInfo::<F>::create();
     let max_lookups_per_row = lookup_info.max_per_row;
@@ -568,7 +594,7 @@ pub fn aggregation<R: Rng + ?Sized, F: FftField, I: Iterator<Item = F>>(
                 // `max_lookups_per_row (=4) * n` field elements of
                 // memory.
                 spec.iter().fold(padding, |acc, j| {
-                    acc * (gamma + j.evaluate(joint_combiner, &eval))
+                    acc * (gamma + j.evaluate(joint_combiner, table_id_combiner, &eval))
                 })
             };
 
@@ -585,8 +611,9 @@ pub fn aggregation<R: Rng + ?Sized, F: FftField, I: Iterator<Item = F>>(
     Ok(zk_patch(lookup_aggreg, d1, rng))
 }
 
-/// Specifies the lookup constraints as expressions.
-pub fn constraints<F: FftField>(dummy_lookup: &[F], d1: D<F>) -> Vec<E<F>> {
+/// Specifies the lookup constraints of the lookup constraint system `lcs` of a circuit of domain `d1` as expressions,
+/// followed by the constraints of the well-formation of the runtime table if the circuit looks up one.
+pub fn constraints<F: FftField>(lcs: &LookupConstraintSystem<F>, d1: D<F>) -> Vec<E<F>> {
     // Something important to keep in mind is that the last 2 rows of
     // all columns will have random values in them to maintain zero-knowledge.
     //
@@ -614,7 +641,10 @@ pub fn constraints<F: FftField>(dummy_lookup: &[F], d1: D<F>) -> Vec<E<F>> {
     let one: E<F> = E::one();
     let non_lookup_indcator = one - lookup_indicator;
 
-    let dummy_lookup: ConstantExpr<F> = dummy_lookup
+    // the ids of the tables follow the entries of the lookups
+    let table_id_power = lcs.table_ids.then(|| lcs.max_joint_size);
+
+    let dummy_lookup: ConstantExpr<F> = lcs.dummy_lookup_values[0]
         .iter()
         .rev()
         .fold(ConstantExpr::zero(), |acc, x| {
@@ -644,7 +674,7 @@ pub fn constraints<F: FftField>(dummy_lookup: &[F], d1: D<F>) -> Vec<E<F>> {
         let padding = complements_with_beta_term[lookup_info.max_per_row - spec.len()].clone();
 
         spec.iter()
-            .map(|j| E::Constant(ConstantExpr::Gamma) + joint_lookup(j))
+            .map(|j| E::Constant(ConstantExpr::Gamma) + joint_lookup(j, table_id_power))
             .fold(E::Constant(padding), |acc: E<F>, x| acc * x)
     };
     let f_chunk = lookup_info
@@ -748,5 +778,8 @@ pub fn constraints<F: FftField>(dummy_lookup: &[F], d1: D<F>) -> Vec<E<F>> {
             * (E::cell(Column::LookupAggreg, Curr) - E::one()),
     ];
     res.extend(compatibility_checks);
+    if lcs.runtime_table.is_some() {
+        res.extend(runtime_tables::constraints(d1));
+    }
     res
 }
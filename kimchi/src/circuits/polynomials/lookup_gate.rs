@@ -0,0 +1,148 @@
+//! This module implements the lookup gate, which looks up the pairs of cells
+//! `(w0, w1)`, `(w2, w3)` and `(w4, w5)` of its row in a lookup table of the circuit.
+//!
+//! The tables of a circuit are given to [ConstraintSystem::create] along with its gates,
+//! and a lookup gate refers to one of them by its position, in its first coefficient.
+//! Only the tables that the circuit looks up are committed to, once, in the index:
+//! the proofs only commit to the sorted lookups and to the aggregation of the lookup argument.
+//!
+//! A circuit can look up several tables, including the table of the ChaCha gates:
+//! the lookup argument then also checks the id of the table of each lookup,
+//! which the gate takes from its cell `w6`.
+//! That cell must hold the position of the table, which is up to the circuit to enforce, with a copy constraint
+//! (see [CircuitBuilder::lookup](crate::circuits::builder::CircuitBuilder::lookup)).
+//!
+//! A table has one or two columns: the entries of a table of a single column are looked up with a zero second cell.
+//! The second column of a table can also be given by the prover (see [runtime_tables](super::runtime_tables)).
+//! A row that does not need all its lookups can repeat one of them.
+
+use crate::circuits::{
+    constraints::ConstraintSystem,
+    gate::{
+        CircuitGate, CurrOrNext, GateType, JointLookup, LocalPosition, LookupTableId, SingleLookup,
+    },
+    wires::{GateWires, COLUMNS},
+};
+use ark_ff::{FftField, Field};
+use array_init::array_init;
+use std::collections::HashSet;
+
+/// The number of lookups of a lookup gate.
+pub const LOOKUPS_PER_ROW: usize = 3;
+
+/// The number of cells of a lookup, which is also the maximum number of columns of a lookup table.
+pub const LOOKUP_WIDTH: usize = 2;
+
+/// The column of the cell holding the id of the table that the gate looks up, after the cells of its lookups.
+pub const TABLE_ID_COLUMN: usize = LOOKUPS_PER_ROW * LOOKUP_WIDTH;
+
+/// Returns the lookups of the lookup gate, in the table whose id is in its cell [TABLE_ID_COLUMN].
+pub fn lookups<F: Field>() -> Vec<JointLookup<F>> {
+    (0..LOOKUPS_PER_ROW)
+        .map(|i| JointLookup {
+            table_id: LookupTableId::Cell(LocalPosition {
+                row: CurrOrNext::Curr,
+                column: TABLE_ID_COLUMN,
+            }),
+            entry: (0..LOOKUP_WIDTH)
+                .map(|j| SingleLookup {
+                    value: vec![(
+                        F::one(),
+                        LocalPosition {
+                            row: CurrOrNext::Curr,
+                            column: LOOKUP_WIDTH * i + j,
+                        },
+                    )],
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+impl<F: FftField> CircuitGate<F> {
+    /// Creates a lookup gate, looking up the pairs of cells of its row in the table `table_id` of the circuit,
+    /// whose id the cell [TABLE_ID_COLUMN] of the row must hold.
+    pub fn create_lookup(wires: GateWires, table_id: usize) -> Self {
+        CircuitGate {
+            typ: GateType::Lookup,
+            wires,
+            coeffs: vec![F::from(table_id as u64)],
+        }
+    }
+
+    /// Returns the table that this lookup gate refers to, if the circuit has `tables` tables.
+    pub fn lookup_table_id(&self, tables: usize) -> Option<usize> {
+        let id = self.coeffs.first()?;
+        (0..tables).find(|i| F::from(*i as u64) == *id)
+    }
+
+    /// Checks that the row `row` of the witness holds the id of the table of the gate,
+    /// and that its lookups are among the `entries` of that table (see [table_entries]).
+    pub fn verify_lookup(
+        &self,
+        row: usize,
+        witness: &[Vec<F>; COLUMNS],
+        entries: &HashSet<[F; LOOKUP_WIDTH + 1]>,
+    ) -> Result<(), String> {
+        ensure_eq!(self.typ, GateType::Lookup, "lookup: incorrect gate");
+        let id = self.coeffs.first().copied().unwrap_or_else(F::zero);
+        ensure_eq!(
+            witness[TABLE_ID_COLUMN][row],
+            id,
+            "lookup: the cell of the table id does not hold the id of the table"
+        );
+        for i in 0..LOOKUPS_PER_ROW {
+            let entry = array_init(|j| {
+                if j < LOOKUP_WIDTH {
+                    witness[LOOKUP_WIDTH * i + j][row]
+                } else {
+                    id
+                }
+            });
+            if !entries.contains(&entry) {
+                return Err(format!("lookup: the lookup {i} is not in the table"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether this lookup gate looks up the runtime table of `cs`, whose entries are only known to the prover.
+    pub fn looks_up_runtime_table(&self, cs: &ConstraintSystem<F>) -> bool {
+        cs.runtime_table().map_or(false, |info| {
+            self.coeffs.first() == Some(&F::from(info.id as u64))
+        })
+    }
+}
+
+/// Returns the entries of the tables that the lookup gates of `cs` look up, followed by the id of their table,
+/// if the circuit has lookup gates.
+/// The entries of a runtime table are only known to the prover, and are not among them.
+pub fn table_entries<F: FftField>(
+    cs: &ConstraintSystem<F>,
+) -> Option<HashSet<[F; LOOKUP_WIDTH + 1]>> {
+    let lcs = cs.lookup_constraint_system.as_ref()?;
+    let table = &lcs.lookup_tables8[0];
+    // with a single table, all the lookup gates have its id
+    let gate = cs.gates.iter().find(|gate| gate.typ == GateType::Lookup)?;
+    let single_id = gate.coeffs.first().copied().unwrap_or_else(F::zero);
+    let id = |k: usize| {
+        if lcs.table_ids {
+            table[lcs.max_joint_size].evals[8 * k]
+        } else {
+            single_id
+        }
+    };
+    let runtime_rows = lcs.runtime_table.map_or(0, |info| info.len);
+    let n = cs.domain.d1.size as usize;
+    Some(
+        (runtime_rows..n)
+            .map(|k| {
+                array_init(|j| match table.get(j) {
+                    _ if j == LOOKUP_WIDTH => id(k),
+                    Some(col) => col.evals[8 * k],
+                    None => F::zero(),
+                })
+            })
+            .collect(),
+    )
+}
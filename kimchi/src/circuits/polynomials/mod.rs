@@ -1,9 +1,14 @@
+pub mod boolean;
+pub mod boundary;
 pub mod chacha;
 pub mod complete_add;
 pub mod endomul_scalar;
 pub mod endosclmul;
 pub mod generic;
+pub mod linear_combination;
 pub mod lookup;
+pub mod lookup_gate;
 pub mod permutation;
 pub mod poseidon;
+pub mod runtime_tables;
 pub mod varbasemul;
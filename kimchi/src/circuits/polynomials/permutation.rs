@@ -7,12 +7,11 @@ use crate::{
     },
     error::{ProofError, Result},
 };
-use ark_ff::{FftField, SquareRootField, Zero};
+use ark_ff::{FftField, SquareRootField};
 use ark_poly::{
-    univariate::{DenseOrSparsePolynomial, DensePolynomial},
-    EvaluationDomain, Evaluations, Radix2EvaluationDomain as D,
+    univariate::DensePolynomial, EvaluationDomain, Evaluations, Polynomial,
+    Radix2EvaluationDomain as D,
 };
-use ark_poly::{Polynomial, UVPolynomial};
 use o1_utils::{ExtendedDensePolynomial, ExtendedEvaluations};
 use rand::{CryptoRng, RngCore};
 
@@ -21,43 +20,19 @@ pub const CONSTRAINTS: u32 = 3;
 
 impl<F: FftField + SquareRootField> ConstraintSystem<F> {
     /// permutation quotient poly contribution computation
-    #[allow(clippy::type_complexity)]
+    ///
+    /// The boundary constraints on the accumulator, `(z(x) - 1) / (x - 1)` and `(z(x) - 1) / (x - sid[n-3])`,
+    /// are multiplied by the vanishing polynomial so that the whole contribution stays in evaluation form,
+    /// and is divided by the vanishing polynomial together with the other constraints.
     pub fn perm_quot(
         &self,
         lagrange: &WitnessOverDomains<F>,
         beta: F,
         gamma: F,
-        z: &DensePolynomial<F>,
         mut alphas: impl Iterator<Item = F>,
-    ) -> Result<(Evaluations<F, D<F>>, DensePolynomial<F>)> {
+    ) -> Evaluations<F, D<F>> {
         // constant gamma in evaluation form (in domain d8)
         let gamma = &self.l08.scale(gamma);
-        let one_poly = DensePolynomial::from_coefficients_slice(&[F::one()]);
-        let z_minus_1 = z - &one_poly;
-
-        // TODO(mimoo): use self.sid[0] instead of 1
-        // accumulator init := (z(x) - 1) / (x - 1)
-        let x_minus_1 = DensePolynomial::from_coefficients_slice(&[-F::one(), F::one()]);
-        let (bnd1, res) = DenseOrSparsePolynomial::divide_with_q_and_r(
-            &z_minus_1.clone().into(),
-            &x_minus_1.into(),
-        )
-        .map_or(Err(ProofError::Permutation("first division")), Ok)?;
-        if !res.is_zero() {
-            return Err(ProofError::Permutation("first division rest"));
-        }
-
-        // accumulator end := (z(x) - 1) / (x - sid[n-3])
-        let denominator = DensePolynomial::from_coefficients_slice(&[
-            -self.sid[self.domain.d1.size as usize - 3],
-            F::one(),
-        ]);
-        let (bnd2, res) =
-            DenseOrSparsePolynomial::divide_with_q_and_r(&z_minus_1.into(), &denominator.into())
-                .map_or(Err(ProofError::Permutation("second division")), Ok)?;
-        if !res.is_zero() {
-            return Err(ProofError::Permutation("second division rest"));
-        }
 
         // shifts = z(x) *
         // (w[0](x) + gamma + x * beta * shift[0]) *
@@ -85,11 +60,14 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
         let alpha1 = alphas.next().expect("missing power of alpha");
         let alpha2 = alphas.next().expect("missing power of alpha");
 
-        let perm = &(&shifts - &sigmas).scale(alpha0) * &self.zkpl;
-        let bnd = &bnd1.scale(alpha1) + &bnd2.scale(alpha2);
+        // accumulator init := (z(x) - 1) / (x - 1)
+        // accumulator end := (z(x) - 1) / (x - sid[n-3])
+        // both multiplied by Z_H
+        let z_minus_1 = &lagrange.d8.this.z - &self.l08;
+        let bnd =
+            &(&self.perm_bnd8[0].scale(alpha1) + &self.perm_bnd8[1].scale(alpha2)) * &z_minus_1;
 
-        //
-        Ok((perm, bnd))
+        &(&(&shifts - &sigmas).scale(alpha0) * &self.zkpl) + &bnd
     }
 
     /// permutation linearization poly contribution computation
@@ -132,14 +110,16 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
         -res
     }
 
-    /// permutation aggregation polynomial computation
+    /// permutation aggregation polynomial computation,
+    /// returning the evaluations of the polynomial over the domain `d1`
+    /// so that it can be committed to without interpolating it first
     pub fn perm_aggreg(
         &self,
         witness: &[Vec<F>; COLUMNS],
         beta: &F,
         gamma: &F,
         rng: &mut (impl RngCore + CryptoRng),
-    ) -> Result<DensePolynomial<F>> {
+    ) -> Result<Evaluations<F, D<F>>> {
         let n = self.domain.d1.size as usize;
 
         // only works if first element is 1
@@ -170,7 +150,8 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
                 .fold(F::one(), |x, y| x * y)
         }
 
-        ark_ff::fields::batch_inversion::<F>(&mut z[1..=n - 3]);
+        // the denominators depend on the witness
+        crate::circuits::witness::batch_inversion::<F>(&mut z[1..=n - 3]);
 
         for j in 0..n - 3 {
             let x = z[j];
@@ -190,7 +171,9 @@ impl<F: FftField + SquareRootField> ConstraintSystem<F> {
         z[n - 2] = F::rand(rng);
         z[n - 1] = F::rand(rng);
 
-        let res = Evaluations::<F, D<F>>::from_vec_and_domain(z, self.domain.d1).interpolate();
-        Ok(res)
+        Ok(Evaluations::<F, D<F>>::from_vec_and_domain(
+            z,
+            self.domain.d1,
+        ))
     }
 }
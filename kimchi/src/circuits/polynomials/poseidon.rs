@@ -25,11 +25,12 @@
 //~ ```
 //~
 
+use crate::alphas::Alphas;
 use crate::circuits::argument::{Argument, ArgumentType};
-use crate::circuits::expr::{prologue::*, Cache, ConstantExpr};
+use crate::circuits::expr::{prologue::*, Cache, Column, ConstantExpr};
 use crate::circuits::gate::{CurrOrNext, GateType};
 use crate::circuits::gates::poseidon::*;
-use ark_ff::{FftField, Field};
+use ark_ff::{FftField, Field, Zero};
 use oracle::poseidon::{PlonkSpongeConstantsKimchi, SpongeConstants};
 use std::marker::PhantomData;
 use CurrOrNext::*;
@@ -174,6 +175,15 @@ where
                 //~ * $w_{2, next} - [r_{14} + (M_{2, 0} w_3^S + M_{2, 1} w_4^S + M_{2, 2} w_5^S)]$
                 //~
                 //~ where $w_{i, next}$ is the polynomial $w_i(\omega x)$ which points to the next row.
+                //~
+                //~ A round can instead be a partial round, which only applies the S-box to the first element of its state,
+                //~ for the instances of Poseidon that use them.
+                //~ The circuits with partial rounds have a selector $p_k$ for each of the 5 rounds $k$ of a row,
+                //~ which is one on the rows where the round $k$ is partial.
+                //~ The constraints of the round $k$ then get the correction
+                //~ $p_k \cdot \sum_{j > 0} M_{i, j} (w_j^S - w_j)$ (for the $j$-th column $w_j$ of the state of the round),
+                //~ which replaces $w_j^S$ by $w_j$.
+                //~ The selectors $p_k$ are zero outside of the Poseidon rows, so the corrections are not multiplied by the selector of the gate.
                 let constraint = witness(col, target_row)
                     - sboxed
                         .iter()
@@ -185,3 +195,48 @@ where
         res
     }
 }
+
+/// Whether the round `round` of the current row is a partial round (see [partial_round_corrections]).
+pub fn partial_round<F>(round: usize) -> E<F> {
+    E::<F>::cell(Column::PartialRound(round), CurrOrNext::Curr)
+}
+
+/// The corrections to add to the [constraints](Poseidon::constraints) of the Poseidon gate, in the same order,
+/// so that the partial rounds of a row only apply the S-box to the first element of their state.
+/// Each correction vanishes on the rounds that are not partial.
+pub fn partial_round_corrections<F: FftField>() -> Vec<E<F>> {
+    let mut res = vec![];
+    let mut cache = Cache::default();
+
+    for e in ROUND_EQUATIONS.iter() {
+        // the state of round `source` is the one it permutes
+        let round = e.source;
+        let unsboxed: Vec<_> = round_to_cols(round)
+            .skip(1)
+            .map(|i| {
+                let sboxed =
+                    cache.cache(witness_curr(i).pow(PlonkSpongeConstantsKimchi::PERM_SBOX as u64));
+                sboxed - witness_curr(i)
+            })
+            .collect();
+
+        for row in 0..SPONGE_WIDTH {
+            let correction = unsboxed.iter().enumerate().fold(E::zero(), |acc, (j, x)| {
+                let mds = ConstantExpr::Mds { row, col: j + 1 };
+                acc + E::Constant(mds) * x.clone()
+            });
+            res.push(partial_round(round) * correction);
+        }
+    }
+    res
+}
+
+/// The corrections of the partial rounds, combined with the powers of alpha of the Poseidon gate.
+/// Unlike the constraints of the gate, they are not multiplied by its selector.
+pub fn combined_partial_round_corrections<F: FftField>(alphas: &Alphas<F>) -> E<F> {
+    let alphas = alphas.get_exponents(
+        ArgumentType::Gate(GateType::Poseidon),
+        Poseidon::<F>::CONSTRAINTS,
+    );
+    E::combine_constraints(alphas, partial_round_corrections())
+}
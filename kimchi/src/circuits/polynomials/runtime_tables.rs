@@ -0,0 +1,128 @@
+//! This module implements the runtime tables of the lookup gates:
+//! tables whose entries are part of the witness, given by the prover for each proof.
+//!
+//! A runtime table has two columns.
+//! Its first column is fixed by the circuit (see [ConstraintSystem::create_with_runtime_tables]),
+//! and its second column is given by the prover along with the witness
+//! (see [ProverProof::create_with_runtime_tables](crate::prover::ProverProof::create_with_runtime_tables)).
+//! Looked up with a [lookup gate](crate::circuits::polynomials::lookup_gate),
+//! a table of addresses and values is a read-only memory, and a table of keys and values a dictionary.
+//!
+//! In the index, the second column of the table is zero, and a selector $s$ marks the rows of the table.
+//! The prover commits to the second column as a polynomial $r$, which is added to the table of the lookup argument:
+//! the entries of the table are combined as $t_0 + j \cdot (t_1 + r)$ with the joint combiner $j$.
+//! The well-formation of $r$ is checked by the constraint
+//!
+//! $$(1 - s(x)) \cdot r(x) = 0$$
+//!
+//! on the rows of the lookup argument, so that the prover can only fill in the entries of the table.
+//! The last rows of $r$ are random, for zero-knowledge.
+
+use crate::{
+    circuits::{
+        constraints::ConstraintSystem,
+        domains::EvaluationDomains,
+        expr::{prologue::*, Column},
+        gate::CurrOrNext,
+        polynomials::lookup,
+    },
+    error::ProofError,
+};
+use ark_ff::{FftField, Field};
+use ark_poly::{Evaluations, Radix2EvaluationDomain as D};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+/// Number of constraints of the well-formation of a runtime table.
+pub const CONSTRAINTS: u32 = 2;
+
+/// A runtime table of a circuit, of which the circuit only fixes the first column.
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeTableCfg<F: Field> {
+    /// The first column of the table, which also gives its number of entries.
+    #[serde_as(as = "Vec<o1_utils::serialization::SerdeAs>")]
+    pub first_column: Vec<F>,
+}
+
+impl<F: Field> RuntimeTableCfg<F> {
+    /// Returns the fixed part of the table, of which the second column is zero.
+    pub fn table(&self) -> Vec<Vec<F>> {
+        vec![
+            self.first_column.clone(),
+            vec![F::zero(); self.first_column.len()],
+        ]
+    }
+}
+
+/// The second column of a runtime table, given by the prover for a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeTable<F> {
+    /// The id of the table, the runtime tables of a circuit following its fixed tables.
+    pub id: usize,
+    /// The second column of the table, of as many entries as its first column.
+    pub data: Vec<F>,
+}
+
+/// The runtime table that the lookup gates of a circuit look up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeTableInfo {
+    /// The id of the table.
+    pub id: usize,
+    /// The number of entries of the table.
+    pub len: usize,
+}
+
+/// Returns the evaluations over d8 of the selector of the first `len` rows, where a runtime table is.
+pub fn selector<F: FftField>(len: usize, domain: &EvaluationDomains<F>) -> Evaluations<F, D<F>> {
+    let evals = (0..domain.d1.size as usize)
+        .map(|row| if row < len { F::one() } else { F::zero() })
+        .collect();
+    Evaluations::from_vec_and_domain(evals, domain.d1)
+        .interpolate()
+        .evaluate_over_domain(domain.d8)
+}
+
+/// Returns the constraints of the well-formation of the runtime table of a circuit of domain `d1`.
+pub fn constraints<F: FftField>(d1: D<F>) -> Vec<E<F>> {
+    let runtime = || E::cell(Column::LookupRuntimeTable, CurrOrNext::Curr);
+    let selector = E::cell(Column::LookupRuntimeSelector, CurrOrNext::Curr);
+    // the lookup argument reads the table up to its last row before the zero-knowledge rows
+    let last_row = d1.size as usize - lookup::ZK_ROWS - 1;
+    vec![
+        E::VanishesOnLast4Rows * (E::one() - selector) * runtime(),
+        E::UnnormalizedLagrangeBasis(last_row) * runtime(),
+    ]
+}
+
+/// Returns the evaluations over d1 of the second column of the runtime table `info`,
+/// among the `runtime_tables` given by the prover, followed by zeros then by random zero-knowledge rows.
+pub fn evaluations<F: FftField, R: Rng + ?Sized>(
+    info: &RuntimeTableInfo,
+    runtime_tables: &[RuntimeTable<F>],
+    d1: D<F>,
+    rng: &mut R,
+) -> Result<Evaluations<F, D<F>>, ProofError> {
+    if let Some(table) = runtime_tables.iter().find(|table| table.id != info.id) {
+        return Err(ProofError::UnknownRuntimeTable { id: table.id });
+    }
+    match runtime_tables {
+        [table] if table.data.len() == info.len => {
+            Ok(lookup::zk_patch(table.data.clone(), d1, rng))
+        }
+        _ => Err(ProofError::MissingRuntimeTable {
+            id: info.id,
+            len: info.len,
+        }),
+    }
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    /// Returns the runtime table that the lookup gates of the circuit look up, if they look up one.
+    pub fn runtime_table(&self) -> Option<RuntimeTableInfo> {
+        self.lookup_constraint_system
+            .as_ref()
+            .and_then(|lcs| lcs.runtime_table)
+    }
+}
@@ -0,0 +1,165 @@
+//! This module implements the naming of the public inputs of a circuit.
+//!
+//! The public inputs of a circuit are the first cells of its witness, in order.
+//! A [PublicInputLayout] names these slots, so that provers and verifiers
+//! can pass the public inputs as a map from names to values
+//! (see [PublicInputLayout::assemble]) instead of relying on their order.
+//! The layout is declared by the [CircuitBuilder](crate::circuits::builder::CircuitBuilder)
+//! and embedded in the [VerifierIndex](crate::verifier_index::VerifierIndex)
+//! (see [ProverIndex::set_public_layout](crate::prover_index::ProverIndex::set_public_layout)).
+
+use crate::error::PublicInputError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The names of the public inputs of a circuit, in order.
+/// Slots can be left unnamed, but a layout with unnamed slots cannot assemble public inputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicInputLayout {
+    slots: Vec<Option<String>>,
+}
+
+impl PublicInputLayout {
+    /// Creates an empty layout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a layout naming each public input.
+    pub fn named(names: &[&str]) -> Result<Self, PublicInputError> {
+        let mut layout = Self::new();
+        for name in names {
+            layout.push(Some(*name))?;
+        }
+        Ok(layout)
+    }
+
+    /// Appends a slot, returning its index: the position of the public input in the witness.
+    pub fn push(&mut self, name: Option<&str>) -> Result<usize, PublicInputError> {
+        if let Some(name) = name {
+            if self.index(name).is_some() {
+                return Err(PublicInputError::DuplicateName(name.to_string()));
+            }
+        }
+        self.slots.push(name.map(ToString::to_string));
+        Ok(self.slots.len() - 1)
+    }
+
+    /// The number of public inputs.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns true if the circuit has no public input.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the index of the public input `name`.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.as_deref() == Some(name))
+    }
+
+    /// Returns the name of the public input at `index`, if it has one.
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.slots.get(index)?.as_deref()
+    }
+
+    /// Orders the named `values` according to the layout.
+    /// Every slot must be named, and every name must have exactly one value.
+    pub fn assemble<F: Copy>(&self, values: &HashMap<&str, F>) -> Result<Vec<F>, PublicInputError> {
+        if let Some(name) = values.keys().find(|name| self.index(name).is_none()) {
+            return Err(PublicInputError::UnknownName(name.to_string()));
+        }
+        self.slots
+            .iter()
+            .enumerate()
+            .map(|(index, slot)| {
+                let name = slot.as_deref().ok_or(PublicInputError::Unnamed(index))?;
+                values
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| PublicInputError::Missing(name.to_string()))
+            })
+            .collect()
+    }
+
+    /// Names the ordered `public` inputs, skipping the unnamed slots.
+    pub fn values<'a, F: Copy>(&'a self, public: &[F]) -> HashMap<&'a str, F> {
+        self.slots
+            .iter()
+            .zip(public)
+            .filter_map(|(slot, value)| Some((slot.as_deref()?, *value)))
+            .collect()
+    }
+
+    /// Checks that `public` are the named `values`,
+    /// as [Self::assemble] would order them.
+    pub fn check<F: Copy + PartialEq>(
+        &self,
+        public: &[F],
+        values: &HashMap<&str, F>,
+    ) -> Result<(), PublicInputError> {
+        let expected = self.assemble(values)?;
+        if expected.len() != public.len() {
+            return Err(PublicInputError::Count {
+                expected: expected.len(),
+                got: public.len(),
+            });
+        }
+        match expected.iter().zip(public).position(|(x, y)| x != y) {
+            // the slots are all named, as assemble succeeded
+            Some(index) => Err(PublicInputError::ValueMismatch(
+                self.name(index).unwrap_or_default().to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_input_layout() {
+        let layout = PublicInputLayout::named(&["root", "nullifier"]).unwrap();
+        assert_eq!(layout.index("nullifier"), Some(1));
+        assert_eq!(layout.name(0), Some("root"));
+
+        let values: HashMap<_, _> = [("nullifier", 2u64), ("root", 1)].into_iter().collect();
+        assert_eq!(layout.assemble(&values), Ok(vec![1, 2]));
+        assert_eq!(layout.values(&[1, 2]), values);
+        assert_eq!(layout.check(&[1, 2], &values), Ok(()));
+        assert_eq!(
+            layout.check(&[1, 3], &values),
+            Err(PublicInputError::ValueMismatch("nullifier".to_string()))
+        );
+
+        let missing: HashMap<_, _> = [("root", 1u64)].into_iter().collect();
+        assert_eq!(
+            layout.assemble(&missing),
+            Err(PublicInputError::Missing("nullifier".to_string()))
+        );
+        let unknown: HashMap<_, _> = [("root", 1u64), ("nullifier", 2), ("leaf", 3)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            layout.assemble(&unknown),
+            Err(PublicInputError::UnknownName("leaf".to_string()))
+        );
+
+        assert_eq!(
+            PublicInputLayout::named(&["root", "root"]),
+            Err(PublicInputError::DuplicateName("root".to_string()))
+        );
+        let mut partial = PublicInputLayout::new();
+        partial.push(None).unwrap();
+        assert_eq!(
+            partial.assemble::<u64>(&HashMap::new()),
+            Err(PublicInputError::Unnamed(0))
+        );
+    }
+}
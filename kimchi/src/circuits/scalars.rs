@@ -6,8 +6,12 @@ use ark_poly::univariate::DensePolynomial;
 use array_init::array_init;
 use o1_utils::ExtendedDensePolynomial;
 use oracle::sponge::ScalarChallenge;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{de::DeserializeAsWrap, ser::SerializeAsWrap, DeserializeAs, SerializeAs};
+use std::collections::HashMap;
+use std::ops::{Add, Mul, Sub};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LookupEvaluations<Field> {
     /// sorted lookup table polynomial
     pub sorted: Vec<Field>,
@@ -16,10 +20,13 @@ pub struct LookupEvaluations<Field> {
     // TODO: May be possible to optimize this away?
     /// lookup table polynomial
     pub table: Field,
+    /// the second column of the runtime table, if the circuit looks up one
+    #[serde(default)]
+    pub runtime: Option<Field>,
 }
 
 // TODO: this should really be vectors here, perhaps create another type for chuncked evaluations?
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProofEvaluations<Field> {
     /// witness polynomials
     pub w: [Field; COLUMNS],
@@ -36,6 +43,280 @@ pub struct ProofEvaluations<Field> {
     pub poseidon_selector: Field,
 }
 
+impl<F> LookupEvaluations<F> {
+    /// Applies `f` to each evaluation.
+    pub fn map_ref<'a, G>(&'a self, f: &impl Fn(&'a F) -> G) -> LookupEvaluations<G> {
+        LookupEvaluations {
+            sorted: self.sorted.iter().map(f).collect(),
+            aggreg: f(&self.aggreg),
+            table: f(&self.table),
+            runtime: self.runtime.as_ref().map(f),
+        }
+    }
+
+    /// Applies `f` to each evaluation, consuming them.
+    pub fn map<G>(self, f: &impl Fn(F) -> G) -> LookupEvaluations<G> {
+        LookupEvaluations {
+            sorted: self.sorted.into_iter().map(f).collect(),
+            aggreg: f(self.aggreg),
+            table: f(self.table),
+            runtime: self.runtime.map(f),
+        }
+    }
+}
+
+impl<F> ProofEvaluations<F> {
+    /// Applies `f` to each evaluation.
+    pub fn map_ref<'a, G>(&'a self, f: &impl Fn(&'a F) -> G) -> ProofEvaluations<G> {
+        ProofEvaluations {
+            w: array_init(|i| f(&self.w[i])),
+            z: f(&self.z),
+            s: array_init(|i| f(&self.s[i])),
+            lookup: self.lookup.as_ref().map(|l| l.map_ref(f)),
+            generic_selector: f(&self.generic_selector),
+            poseidon_selector: f(&self.poseidon_selector),
+        }
+    }
+
+    /// Applies `f` to each evaluation, consuming them.
+    pub fn map<G>(self, f: &impl Fn(F) -> G) -> ProofEvaluations<G> {
+        let mut w = self.w.into_iter();
+        let mut s = self.s.into_iter();
+        ProofEvaluations {
+            w: array_init(|_| f(w.next().unwrap())),
+            z: f(self.z),
+            s: array_init(|_| f(s.next().unwrap())),
+            lookup: self.lookup.map(|l| l.map(f)),
+            generic_selector: f(self.generic_selector),
+            poseidon_selector: f(self.poseidon_selector),
+        }
+    }
+
+    /// Applies `f` to the evaluations of the same polynomial in `self` and `other`.
+    ///
+    /// # Panics
+    ///
+    /// If the lookup evaluations of `self` and `other` are not of the same shape.
+    pub fn zip_with<'a, G, H>(
+        &'a self,
+        other: &'a ProofEvaluations<G>,
+        f: &impl Fn(&'a F, &'a G) -> H,
+    ) -> ProofEvaluations<H> {
+        let lookup = match (&self.lookup, &other.lookup) {
+            (Some(l), Some(m)) => {
+                assert_eq!(
+                    l.sorted.len(),
+                    m.sorted.len(),
+                    "different numbers of sorted lookup evaluations"
+                );
+                Some(LookupEvaluations {
+                    sorted: l
+                        .sorted
+                        .iter()
+                        .zip(&m.sorted)
+                        .map(|(a, b)| f(a, b))
+                        .collect(),
+                    aggreg: f(&l.aggreg, &m.aggreg),
+                    table: f(&l.table, &m.table),
+                    runtime: match (&l.runtime, &m.runtime) {
+                        (Some(a), Some(b)) => Some(f(a, b)),
+                        (None, None) => None,
+                        _ => panic!("only one of the evaluations has a runtime table evaluation"),
+                    },
+                })
+            }
+            (None, None) => None,
+            _ => panic!("only one of the evaluations has lookup evaluations"),
+        };
+        ProofEvaluations {
+            w: array_init(|i| f(&self.w[i], &other.w[i])),
+            z: f(&self.z, &other.z),
+            s: array_init(|i| f(&self.s[i], &other.s[i])),
+            lookup,
+            generic_selector: f(&self.generic_selector, &other.generic_selector),
+            poseidon_selector: f(&self.poseidon_selector, &other.poseidon_selector),
+        }
+    }
+
+    /// Returns the evaluations opened by the evaluation proof, in the order in which the prover
+    /// and the verifier absorb and combine them: the permutation polynomial,
+    /// the generic and poseidon selectors, the witness columns then the permutation polynomials
+    /// (the lookup evaluations are not opened yet).
+    pub fn opened(&self) -> Vec<&F> {
+        let mut opened = vec![&self.z, &self.generic_selector, &self.poseidon_selector];
+        opened.extend(&self.w);
+        opened.extend(&self.s);
+        opened
+    }
+}
+
+// The evaluations of a proof are linear in the polynomials,
+// so that linear combinations of polynomials can be evaluated from their evaluations.
+
+impl<'a, 'b, F: Field> Add<&'a ProofEvaluations<F>> for &'b ProofEvaluations<F> {
+    type Output = ProofEvaluations<F>;
+
+    fn add(self, other: &'a ProofEvaluations<F>) -> ProofEvaluations<F> {
+        self.zip_with(other, &|a, b| *a + b)
+    }
+}
+
+impl<'a, 'b, F: Field> Sub<&'a ProofEvaluations<F>> for &'b ProofEvaluations<F> {
+    type Output = ProofEvaluations<F>;
+
+    fn sub(self, other: &'a ProofEvaluations<F>) -> ProofEvaluations<F> {
+        self.zip_with(other, &|a, b| *a - b)
+    }
+}
+
+impl<'a, F: Field> Mul<F> for &'a ProofEvaluations<F> {
+    type Output = ProofEvaluations<F>;
+
+    fn mul(self, c: F) -> ProofEvaluations<F> {
+        self.map_ref(&|e| *e * c)
+    }
+}
+
+/// The version of the format in which proofs serialize their [ProofEvaluations].
+///
+/// Version 0 serializes the fields of the struct by position, so that adding an evaluation
+/// (for example for a new gate) changes the layout of all the proofs.
+/// Since version 1, each evaluation is serialized along with its name (see [ProofEvaluations::named]),
+/// so that a new evaluation only has to be optional to keep the proofs produced before it readable.
+/// Both versions are read, only the latest one is written.
+pub const EVALUATIONS_VERSION: u8 = 1;
+
+/// The serialized form of [ProofEvaluations].
+/// Both variants are sequences (of 2 elements for the named one, of the fields of the struct for the positional one),
+/// so that they can be told apart without a tag.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SerializedEvaluations<F> {
+    Named(u8, Vec<(String, F)>),
+    Positional(ProofEvaluations<F>),
+}
+
+impl<F> ProofEvaluations<F> {
+    /// Returns the evaluations along with their names, in a fixed order.
+    pub fn named(self) -> Vec<(String, F)> {
+        let mut named = vec![];
+        for (i, w) in self.w.into_iter().enumerate() {
+            named.push((format!("w{i}"), w));
+        }
+        named.push(("z".to_string(), self.z));
+        for (i, s) in self.s.into_iter().enumerate() {
+            named.push((format!("s{i}"), s));
+        }
+        if let Some(lookup) = self.lookup {
+            for (i, sorted) in lookup.sorted.into_iter().enumerate() {
+                named.push((format!("lookup_sorted{i}"), sorted));
+            }
+            named.push(("lookup_aggreg".to_string(), lookup.aggreg));
+            named.push(("lookup_table".to_string(), lookup.table));
+            if let Some(runtime) = lookup.runtime {
+                named.push(("lookup_runtime".to_string(), runtime));
+            }
+        }
+        named.push(("generic_selector".to_string(), self.generic_selector));
+        named.push(("poseidon_selector".to_string(), self.poseidon_selector));
+        named
+    }
+
+    /// The inverse of [ProofEvaluations::named], which fails if an evaluation is missing or unknown.
+    pub fn from_named(named: Vec<(String, F)>) -> Result<Self, String> {
+        let mut evals: HashMap<_, _> = named.into_iter().collect();
+        let mut take = |name: &str| {
+            evals
+                .remove(name)
+                .ok_or_else(|| format!("missing evaluation {name}"))
+        };
+
+        let w = (0..COLUMNS)
+            .map(|i| take(&format!("w{i}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let z = take("z")?;
+        let s = (0..PERMUTS - 1)
+            .map(|i| take(&format!("s{i}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let lookup = match take("lookup_aggreg") {
+            Ok(aggreg) => {
+                let table = take("lookup_table")?;
+                let sorted = (0..)
+                    .map_while(|i| take(&format!("lookup_sorted{i}")).ok())
+                    .collect();
+                let runtime = take("lookup_runtime").ok();
+                Some(LookupEvaluations {
+                    sorted,
+                    aggreg,
+                    table,
+                    runtime,
+                })
+            }
+            Err(_) => None,
+        };
+        let generic_selector = take("generic_selector")?;
+        let poseidon_selector = take("poseidon_selector")?;
+
+        if let Some(name) = evals.keys().next() {
+            return Err(format!("unknown evaluation {name}"));
+        }
+
+        // the lengths were checked by the loops above
+        let mut w = w.into_iter();
+        let mut s = s.into_iter();
+        Ok(ProofEvaluations {
+            w: array_init(|_| w.next().unwrap()),
+            z,
+            s: array_init(|_| s.next().unwrap()),
+            lookup,
+            generic_selector,
+            poseidon_selector,
+        })
+    }
+}
+
+// The evaluations of a proof are field elements, which do not implement serde's traits,
+// so that they are instead serialized with `#[serde_as(as = "ProofEvaluations<...>")]`,
+// in the format of the latest [EVALUATIONS_VERSION].
+
+impl<T, U> SerializeAs<ProofEvaluations<T>> for ProofEvaluations<U>
+where
+    U: SerializeAs<T>,
+{
+    fn serialize_as<S>(source: &ProofEvaluations<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let named = source.map_ref(&|e| SerializeAsWrap::<T, U>::new(e)).named();
+        SerializedEvaluations::Named(EVALUATIONS_VERSION, named).serialize(serializer)
+    }
+}
+
+impl<'de, T, U> DeserializeAs<'de, ProofEvaluations<T>> for ProofEvaluations<U>
+where
+    U: DeserializeAs<'de, T>,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<ProofEvaluations<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let evals = match SerializedEvaluations::<DeserializeAsWrap<T, U>>::deserialize(
+            deserializer,
+        )? {
+            SerializedEvaluations::Positional(evals) => evals,
+            SerializedEvaluations::Named(version, named) => {
+                if version > EVALUATIONS_VERSION {
+                    return Err(D::Error::custom(format!(
+                        "unsupported evaluations version {version} (expected at most {EVALUATIONS_VERSION})"
+                    )));
+                }
+                ProofEvaluations::from_named(named).map_err(D::Error::custom)?
+            }
+        };
+        Ok(evals.map(&DeserializeAsWrap::into_inner))
+    }
+}
+
 impl<F: Zero> ProofEvaluations<F> {
     pub fn dummy_with_witness_evaluations(w: [F; COLUMNS]) -> ProofEvaluations<F> {
         ProofEvaluations {
@@ -50,23 +331,10 @@ impl<F: Zero> ProofEvaluations<F> {
 }
 
 impl<F: FftField> ProofEvaluations<Vec<F>> {
+    /// Combines the evaluations of the chunks of each polynomial,
+    /// where `pt` is the evaluation point to the power of the size of the chunks.
     pub fn combine(&self, pt: F) -> ProofEvaluations<F> {
-        ProofEvaluations::<F> {
-            s: array_init(|i| DensePolynomial::eval_polynomial(&self.s[i], pt)),
-            w: array_init(|i| DensePolynomial::eval_polynomial(&self.w[i], pt)),
-            z: DensePolynomial::eval_polynomial(&self.z, pt),
-            lookup: self.lookup.as_ref().map(|l| LookupEvaluations {
-                table: DensePolynomial::eval_polynomial(&l.table, pt),
-                aggreg: DensePolynomial::eval_polynomial(&l.aggreg, pt),
-                sorted: l
-                    .sorted
-                    .iter()
-                    .map(|x| DensePolynomial::eval_polynomial(x, pt))
-                    .collect(),
-            }),
-            generic_selector: DensePolynomial::eval_polynomial(&self.generic_selector, pt),
-            poseidon_selector: DensePolynomial::eval_polynomial(&self.poseidon_selector, pt),
-        }
+        self.map_ref(&|chunks| DensePolynomial::eval_polynomial(chunks, pt))
     }
 }
 
@@ -122,6 +390,7 @@ pub mod caml {
         pub sorted: Vec<Vec<CamlF>>,
         pub aggreg: Vec<CamlF>,
         pub table: Vec<CamlF>,
+        pub runtime: Option<Vec<CamlF>>,
     }
 
     impl<F, CamlF> From<LookupEvaluations<Vec<F>>> for CamlLookupEvaluations<CamlF>
@@ -138,6 +407,7 @@ pub mod caml {
                     .collect(),
                 aggreg: le.aggreg.into_iter().map(Into::into).collect(),
                 table: le.table.into_iter().map(Into::into).collect(),
+                runtime: le.runtime.map(|r| r.into_iter().map(Into::into).collect()),
             }
         }
     }
@@ -155,6 +425,7 @@ pub mod caml {
                     .collect(),
                 aggreg: pe.aggreg.into_iter().map(Into::into).collect(),
                 table: pe.table.into_iter().map(Into::into).collect(),
+                runtime: pe.runtime.map(|r| r.into_iter().map(Into::into).collect()),
             }
         }
     }
@@ -346,3 +617,99 @@ pub mod caml {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_with::{serde_as, Same};
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize)]
+    struct Evals(#[serde_as(as = "ProofEvaluations<Same>")] ProofEvaluations<u64>);
+
+    fn evals(lookup: bool) -> ProofEvaluations<u64> {
+        ProofEvaluations {
+            w: array_init(|i| i as u64),
+            z: 15,
+            s: array_init(|i| 16 + i as u64),
+            lookup: lookup.then(|| LookupEvaluations {
+                sorted: vec![22, 23, 24],
+                aggreg: 25,
+                table: 26,
+                runtime: Some(29),
+            }),
+            generic_selector: 27,
+            poseidon_selector: 28,
+        }
+    }
+
+    fn round_trip(bytes: &[u8]) -> Result<ProofEvaluations<u64>, rmp_serde::decode::Error> {
+        rmp_serde::from_read_ref::<_, Evals>(bytes).map(|evals| evals.0)
+    }
+
+    #[test]
+    fn test_evaluations_serialization() {
+        for lookup in [false, true] {
+            let bytes = rmp_serde::to_vec(&Evals(evals(lookup))).unwrap();
+            let named = round_trip(&bytes).unwrap().named();
+            assert_eq!(named, evals(lookup).named());
+        }
+    }
+
+    #[test]
+    fn test_positional_evaluations_are_still_read() {
+        for lookup in [false, true] {
+            let bytes = rmp_serde::to_vec(&evals(lookup)).unwrap();
+            let named = round_trip(&bytes).unwrap().named();
+            assert_eq!(named, evals(lookup).named());
+        }
+    }
+
+    #[test]
+    fn test_named_evaluations_are_checked() {
+        // an evaluation of a later version
+        let mut named = evals(false).named();
+        named.push(("custom".to_string(), 29));
+        let bytes = rmp_serde::to_vec(&(EVALUATIONS_VERSION, &named)).unwrap();
+        assert!(round_trip(&bytes).is_err());
+
+        // a missing evaluation
+        let mut named = evals(false).named();
+        named.retain(|(name, _)| name != "z");
+        let bytes = rmp_serde::to_vec(&(EVALUATIONS_VERSION, &named)).unwrap();
+        assert!(round_trip(&bytes).is_err());
+
+        // a later version
+        let bytes = rmp_serde::to_vec(&(EVALUATIONS_VERSION + 1, evals(false).named())).unwrap();
+        assert!(round_trip(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_evaluations_arithmetic() {
+        use mina_curves::pasta::fp::Fp;
+
+        let a = evals(true).map(&Fp::from);
+        let b = evals(true).map(&|e| Fp::from(e * e));
+        let three = Fp::from(3u64);
+        let c = &(&a * three) + &b;
+        for ((a, b), c) in a.opened().into_iter().zip(b.opened()).zip(c.opened()) {
+            assert_eq!(*c, *a * three + b);
+        }
+
+        let d = &c - &b;
+        let lookup = d.lookup.clone().unwrap();
+        assert_eq!(lookup.sorted, [66u64, 69, 72].map(Fp::from));
+        assert_eq!(lookup.table, Fp::from(78u64));
+        assert_eq!(d.named(), (&a * three).named());
+    }
+
+    #[test]
+    fn test_opened_evaluations() {
+        let e = evals(true);
+        let opened: Vec<u64> = e.opened().into_iter().copied().collect();
+        let mut expected = vec![15, 27, 28];
+        expected.extend(0..15);
+        expected.extend(16..22);
+        assert_eq!(opened, expected);
+    }
+}
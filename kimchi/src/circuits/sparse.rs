@@ -0,0 +1,108 @@
+//! This module implements a sparse representation of the evaluations of a polynomial over `domain.d1`.
+//!
+//! The coefficients of the gates (the $c_i$ of the generic gate, see [crate::circuits::polynomials::generic])
+//! are typically zero on most rows of a circuit, and entirely zero for the columns no gate of the circuit uses.
+//! The constraint system detects this when it is created, and keeps the [SparseEvals] of each coefficient column:
+//! - the interpolation and the extension to `domain.d8` of the zero columns are skipped,
+//!   and so are their terms in the quotient;
+//! - the commitments to the sparse columns only go through the points of the Lagrange basis of their nonzero rows
+//!   (see [SRS::commit_sparse_evaluations_non_hiding]).
+
+use ark_ff::{FftField, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain as D};
+use commitment_dlog::{commitment::CommitmentCurve, srs::SRS, PolyComm};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+/// The evaluations are sparse when at most one in `SPARSITY` of them is nonzero.
+pub const SPARSITY: usize = 4;
+
+/// The nonzero evaluations of a polynomial over a domain of `size` elements.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseEvals<F> {
+    /// the size of the domain
+    pub size: usize,
+    /// the rows of the nonzero evaluations, in increasing order, and their values
+    #[serde_as(as = "Vec<(_, o1_utils::serialization::SerdeAs)>")]
+    pub entries: Vec<(usize, F)>,
+}
+
+impl<F: FftField> SparseEvals<F> {
+    /// Keeps the nonzero evaluations of `evals`.
+    pub fn from_evals(evals: &[F]) -> Self {
+        Self {
+            size: evals.len(),
+            entries: evals
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| !x.is_zero())
+                .map(|(row, x)| (row, *x))
+                .collect(),
+        }
+    }
+
+    /// Returns true if all the evaluations are zero.
+    pub fn is_zero(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if few enough evaluations are nonzero for the sparse algorithms to be faster
+    /// (see [SPARSITY]).
+    pub fn is_sparse(&self) -> bool {
+        self.entries.len() * SPARSITY <= self.size
+    }
+
+    /// Returns the dense evaluations over `domain`, which must be of [SparseEvals::size] elements.
+    pub fn to_dense(&self, domain: D<F>) -> Evaluations<F, D<F>> {
+        assert_eq!(domain.size(), self.size);
+        let mut evals = vec![F::zero(); self.size];
+        for (row, x) in &self.entries {
+            evals[*row] = *x;
+        }
+        Evaluations::from_vec_and_domain(evals, domain)
+    }
+
+    /// Commits to the evaluations over `domain` without hiding,
+    /// with a sparse multi-scalar multiplication if they are sparse.
+    pub fn commit<G>(&self, srs: &SRS<G>, domain: D<F>) -> PolyComm<G>
+    where
+        G: CommitmentCurve<ScalarField = F>,
+    {
+        if self.is_sparse() {
+            srs.commit_sparse_evaluations_non_hiding(domain, &self.entries)
+        } else {
+            srs.commit_evaluations_non_hiding(domain, &self.to_dense(domain), None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mina_curves::pasta::{fp::Fp, vesta::Affine};
+
+    #[test]
+    fn sparse_evals_commit_like_dense_ones() {
+        let domain = D::<Fp>::new(16).unwrap();
+        let mut srs = SRS::<Affine>::create(16);
+        srs.add_lagrange_basis(domain);
+
+        let mut evals = vec![Fp::zero(); 16];
+        evals[2] = Fp::from(5u8);
+        evals[11] = Fp::from(7u8);
+        let sparse = SparseEvals::from_evals(&evals);
+        assert_eq!(sparse.entries, vec![(2, evals[2]), (11, evals[11])]);
+        assert!(sparse.is_sparse() && !sparse.is_zero());
+        assert_eq!(sparse.to_dense(domain).evals, evals);
+
+        let dense = Evaluations::from_vec_and_domain(evals.clone(), domain);
+        let expected = srs.commit_evaluations_non_hiding(domain, &dense, None);
+        assert_eq!(sparse.commit(&srs, domain).unshifted, expected.unshifted);
+
+        // past the sparsity threshold, the commitment is dense
+        let full = SparseEvals::from_evals(&[Fp::from(1u8); 16]);
+        assert!(!full.is_sparse());
+        assert!(SparseEvals::<Fp>::from_evals(&[Fp::zero(); 16]).is_zero());
+    }
+}
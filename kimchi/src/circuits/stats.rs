@@ -0,0 +1,180 @@
+//! This module implements [ConstraintSystem::stats], a report of where the rows of a circuit go
+//! and of what it will roughly cost to prove.
+
+use crate::circuits::{
+    constraints::ConstraintSystem,
+    gate::{CircuitGate, GateType},
+    wires::{Wire, COLUMNS, PERMUTS},
+};
+use ark_ff::{FftField, Zero};
+use ark_poly::EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+/// The single-core cost of a curve point in a multi-scalar multiplication, in nanoseconds.
+const MSM_NS_PER_POINT: u64 = 4_000;
+
+/// The single-core cost of an FFT butterfly, in nanoseconds.
+const FFT_NS_PER_BUTTERFLY: u64 = 40;
+
+/// The statistics of a [ConstraintSystem].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// the number of gates of each type, without the padding of the domain
+    pub gates: BTreeMap<GateType, usize>,
+    /// the number of rows of the circuit, without the padding of the domain
+    pub rows: usize,
+    /// the number of public inputs
+    pub public: usize,
+    /// the size of the domain, a power of two that leaves room for the zero-knowledge rows
+    pub domain_size: usize,
+    /// the number of equalities between cells enforced by the permutation argument
+    pub copy_constraints: usize,
+    /// the number of lookup tables, if the circuit uses lookups
+    pub lookup_tables: Option<usize>,
+    /// a rough estimate of the memory needed to create a proof, index included, in bytes
+    pub estimated_memory: usize,
+    /// a rough, single-core estimate of the time needed to create a proof
+    pub estimated_proving_time: Duration,
+}
+
+/// Returns true for the zero gates appended by [ConstraintSystem::create] to fill the domain.
+fn is_padding<F: FftField>(row: usize, gate: &CircuitGate<F>) -> bool {
+    gate.typ == GateType::Zero
+        && gate.coeffs.is_empty()
+        && gate
+            .wires
+            .iter()
+            .enumerate()
+            .all(|(col, wire)| *wire == Wire { row, col })
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    /// Returns the statistics of the circuit.
+    /// The estimates assume a proof without lookups and only give an order of magnitude.
+    pub fn stats(&self) -> CircuitStats {
+        let rows = self
+            .gates
+            .iter()
+            .enumerate()
+            .rposition(|(row, gate)| !is_padding(row, gate))
+            .map_or(0, |last| last + 1);
+
+        let mut gates = BTreeMap::new();
+        for gate in &self.gates[..rows] {
+            *gates.entry(gate.typ).or_insert(0) += 1;
+        }
+
+        // each cycle of k cells in the permutation enforces k - 1 equalities
+        let mut visited = vec![[false; PERMUTS]; self.gates.len()];
+        let mut copy_constraints = 0;
+        for row in 0..self.gates.len() {
+            for col in 0..PERMUTS {
+                let mut cell = Wire { row, col };
+                while !visited[cell.row][cell.col] {
+                    visited[cell.row][cell.col] = true;
+                    cell = self.gates[cell.row].wires[cell.col];
+                    if !visited[cell.row][cell.col] {
+                        copy_constraints += 1;
+                    }
+                }
+            }
+        }
+
+        let n = self.domain.d1.size();
+        let log_n = self.domain.d1.log_size_of_group as usize;
+
+        // the witness and permutation polynomials, over the domains of size n, 4n and 8n,
+        // and the quotient polynomial over the domains of size 4n and 8n
+        let f = F::zero().serialized_size();
+        let estimated_memory = self.size().total() + ((COLUMNS + 1) * 14 + 20) * n * f;
+
+        // the witness, permutation and quotient commitments, and the opening proof
+        let msm_points = (COLUMNS + 1 + PERMUTS + 4) * n;
+        // interpolating and extending the witness and permutation polynomials,
+        // and interpolating the quotient polynomial
+        let fft_butterflies = |size: usize, log: usize| size / 2 * log;
+        let butterflies = (COLUMNS + 1)
+            * (fft_butterflies(n, log_n)
+                + fft_butterflies(4 * n, log_n + 2)
+                + fft_butterflies(8 * n, log_n + 3))
+            + fft_butterflies(4 * n, log_n + 2)
+            + fft_butterflies(8 * n, log_n + 3);
+        let estimated_proving_time = Duration::from_nanos(
+            msm_points as u64 * MSM_NS_PER_POINT + butterflies as u64 * FFT_NS_PER_BUTTERFLY,
+        );
+
+        CircuitStats {
+            gates,
+            rows,
+            public: self.public,
+            domain_size: n,
+            copy_constraints,
+            lookup_tables: self
+                .lookup_constraint_system
+                .as_ref()
+                .map(|lcs| lcs.lookup_tables.len()),
+            estimated_memory,
+            estimated_proving_time,
+        }
+    }
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} rows ({} public) in a domain of {} rows",
+            self.rows, self.public, self.domain_size
+        )?;
+        for (typ, count) in &self.gates {
+            let share = 100. * *count as f64 / self.rows.max(1) as f64;
+            writeln!(
+                f,
+                "  {:<16}{:>10} rows {:>6.1}%",
+                format!("{typ:?}"),
+                count,
+                share
+            )?;
+        }
+        writeln!(f, "{} copy constraints", self.copy_constraints)?;
+        if let Some(tables) = self.lookup_tables {
+            writeln!(f, "{tables} lookup tables")?;
+        }
+        writeln!(
+            f,
+            "estimated proving cost: {:.1?} on a single core, {:.1} MiB",
+            self.estimated_proving_time,
+            self.estimated_memory as f64 / (1024. * 1024.)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuits::{builder::CircuitBuilder, constraints::ConstraintSystem, gate::GateType};
+    use mina_curves::pasta::fp::Fp;
+
+    #[test]
+    fn test_stats() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.public_input();
+        let x2 = b.mul(x, x);
+        let _ = b.add_constant(x2, Fp::from(1u64));
+        let (gates, _) = b.build();
+
+        let fp_sponge_params = oracle::pasta::fp_kimchi::params();
+        let cs = ConstraintSystem::create(gates, vec![], fp_sponge_params, 1).unwrap();
+        let stats = cs.stats();
+
+        assert_eq!(stats.rows, 2);
+        assert_eq!(stats.public, 1);
+        assert_eq!(stats.gates.get(&GateType::Generic), Some(&2));
+        assert_eq!(stats.gates.get(&GateType::Zero), None);
+        assert!(stats.domain_size >= stats.rows + 3);
+        // x is used in 3 cells and x^2 in 2
+        assert_eq!(stats.copy_constraints, 3);
+        assert_eq!(stats.lookup_tables, None);
+        assert!(stats.to_string().contains("Generic"));
+    }
+}
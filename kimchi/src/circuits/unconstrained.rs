@@ -0,0 +1,240 @@
+//! This module detects the variables of a circuit that no constraint reads,
+//! which any prover can set to any value: the classic source of soundness bugs in hand-built circuits.
+//!
+//! A variable is a cycle of the permutation: the cells that the copy constraints connect,
+//! or a single cell if it is not wired to another one.
+//! A variable is constrained if one of its cells is read by the constraints of a gate,
+//! either of its own row, or of the previous row for the gates that read the next row,
+//! or by a lookup. The cells of the generic and linear combination gates are only read if their coefficient is not zero.
+
+use crate::circuits::{
+    check::gate_constraints,
+    constraints::ConstraintSystem,
+    expr::{Column, Expr, E},
+    gate::{CircuitGate, CurrOrNext, GateType, LookupInfo},
+    polynomials::generic::{FUSED_REGISTER, GENERIC_COEFFS, GENERIC_REGISTERS},
+    wires::{Wire, COLUMNS, PERMUTS},
+};
+use ark_ff::{FftField, SquareRootField, Zero};
+use std::collections::HashMap;
+
+/// Marks the cells of the witness read by `expr`, relatively to the row of the gate.
+fn read_by<F>(expr: &E<F>, read: &mut [[bool; COLUMNS]; 2]) {
+    use Expr::*;
+    match expr {
+        Cell(v) => {
+            if let Column::Witness(i) = v.col {
+                read[v.row.shift()][i] = true;
+            }
+        }
+        Double(x) | Square(x) | Pow(x, _) | Cache(_, x) => read_by(x, read),
+        BinOp(_, x, y) => {
+            read_by(x, read);
+            read_by(y, read);
+        }
+        Constant(_) | VanishesOnLast4Rows | UnnormalizedLagrangeBasis(_) => (),
+    }
+}
+
+/// Returns the cells of the current and next rows read by the constraints of `gate`, if they do not depend on its coefficients.
+fn read_by_gate<F: FftField + SquareRootField>(
+    cs: &ConstraintSystem<F>,
+    gate: &CircuitGate<F>,
+) -> [[bool; COLUMNS]; 2] {
+    let mut read = [[false; COLUMNS]; 2];
+    let constraints = if gate.typ == GateType::Custom {
+        cs.custom_gate(gate).map(|(_, custom)| custom.constraints())
+    } else {
+        gate_constraints(gate.typ)
+    };
+    for constraint in constraints.iter().flatten() {
+        read_by(constraint, &mut read);
+    }
+
+    let lookup_info = LookupInfo::<F>::create();
+    for row in [CurrOrNext::Curr, CurrOrNext::Next] {
+        if let Some(kind) = lookup_info.kinds_map.get(&(gate.typ, row)) {
+            let positions = lookup_info.kinds[*kind]
+                .iter()
+                .flat_map(|lookup| &lookup.entry)
+                .flat_map(|single| &single.value);
+            for (_, position) in positions {
+                read[position.row.shift()][position.column] = true;
+            }
+        }
+    }
+    read
+}
+
+/// Returns, for each row of the circuit, the cells that its gate or the gate of the previous row reads.
+fn read_cells<F: FftField + SquareRootField>(cs: &ConstraintSystem<F>) -> Vec<[bool; COLUMNS]> {
+    let rows = cs.gates.len();
+    let mut read = vec![[false; COLUMNS]; rows];
+    let mut by_type = HashMap::new();
+    let coeff =
+        |gate: &CircuitGate<F>, i: usize| gate.coeffs.get(i).map_or(false, |c| !c.is_zero());
+    for (row, gate) in cs.gates.iter().enumerate() {
+        match gate.typ {
+            GateType::Zero => (),
+            GateType::Generic => {
+                for half in 0..2 {
+                    let (c, r) = (half * GENERIC_COEFFS, half * GENERIC_REGISTERS);
+                    for i in 0..GENERIC_REGISTERS {
+                        read[row][r + i] |= coeff(gate, c + i);
+                    }
+                    // the multiplication of the left and right registers
+                    for i in 0..2 {
+                        read[row][r + i] |= coeff(gate, c + 3);
+                    }
+                }
+                // the fused product of the first gate
+                if gate.has_fused_product() {
+                    let fused = 2 * GENERIC_COEFFS;
+                    read[row][0] |= coeff(gate, fused);
+                    read[row][1] |= coeff(gate, fused + 1);
+                    read[row][FUSED_REGISTER] = true;
+                }
+            }
+            GateType::LinearCombination => {
+                for (col, read) in read[row].iter_mut().enumerate() {
+                    *read |= coeff(gate, col);
+                }
+            }
+            typ => {
+                let key = (typ, cs.custom_gate(gate).map(|(id, _)| id));
+                let cells = *by_type.entry(key).or_insert_with(|| read_by_gate(cs, gate));
+                for (shift, cells) in cells.iter().enumerate() {
+                    if let Some(read) = read.get_mut(row + shift) {
+                        for (read, cell) in read.iter_mut().zip(cells) {
+                            *read |= cell;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    read
+}
+
+/// Returns the variables of `cs`, as the cycles of its permutation, and the single cells of the columns that are not wired.
+fn variables<F: FftField>(cs: &ConstraintSystem<F>) -> Vec<Vec<Wire>> {
+    let rows = cs.gates.len();
+    let mut seen = vec![[false; PERMUTS]; rows];
+    let mut variables = vec![];
+    for row in 0..rows {
+        for col in 0..COLUMNS {
+            if col >= PERMUTS {
+                variables.push(vec![Wire { row, col }]);
+                continue;
+            }
+            let mut cell = Wire { row, col };
+            let mut cycle = vec![];
+            // the wires of the constraint system are checked to form a permutation of its cells
+            while !seen[cell.row][cell.col] {
+                seen[cell.row][cell.col] = true;
+                cycle.push(cell);
+                cell = cs.gates[cell.row].wires[cell.col];
+            }
+            if !cycle.is_empty() {
+                variables.push(cycle);
+            }
+        }
+    }
+    variables
+}
+
+impl<F: FftField + SquareRootField> ConstraintSystem<F> {
+    /// Returns the variables that no constraint reads, each as its cells, which are connected by copy constraints.
+    ///
+    /// Only the variables of several cells are returned:
+    /// a single cell that no constraint reads is usually a cell that the circuit does not use,
+    /// which the witness leaves to zero (see [unconstrained_values] to also check the single cells of a witness).
+    pub fn unconstrained_variables(&self) -> Vec<Vec<Wire>> {
+        let read = read_cells(self);
+        variables(self)
+            .into_iter()
+            .filter(|cells| cells.len() > 1)
+            .filter(|cells| !cells.iter().any(|cell| read[cell.row][cell.col]))
+            .collect()
+    }
+}
+
+/// Returns the variables of `cs` that no constraint reads but that have a value other than zero in `witness`,
+/// each as its cells, which are connected by copy constraints.
+/// Unlike [ConstraintSystem::unconstrained_variables], this includes the variables of a single cell.
+pub fn unconstrained_values<F: FftField + SquareRootField>(
+    witness: &[Vec<F>; COLUMNS],
+    cs: &ConstraintSystem<F>,
+) -> Vec<Vec<Wire>> {
+    let read = read_cells(cs);
+    let value = |cell: &Wire| {
+        witness[cell.col]
+            .get(cell.row)
+            .copied()
+            .unwrap_or_else(F::zero)
+    };
+    variables(cs)
+        .into_iter()
+        .filter(|cells| !cells.iter().any(|cell| read[cell.row][cell.col]))
+        .filter(|cells| cells.iter().any(|cell| !value(cell).is_zero()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::builder::CircuitBuilder;
+    use crate::error::SetupError;
+    use crate::prover_index::{testing::try_new_index_for_test_with_cs, ProverIndex};
+    use ark_ff::One;
+    use mina_curves::pasta::fp::Fp;
+
+    fn create(gates: Vec<CircuitGate<Fp>>, public: usize) -> ConstraintSystem<Fp> {
+        let fp_sponge_params = oracle::pasta::fp_kimchi::params();
+        ConstraintSystem::create(gates, vec![], fp_sponge_params, public).unwrap()
+    }
+
+    #[test]
+    fn test_constrained_circuit() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.public_input();
+        let y = b.private_input();
+        let xy = b.mul(x, y);
+        b.unpack(xy, 16);
+        let (gates, generator) = b.build();
+        let cs = create(gates, 1);
+        assert!(cs.unconstrained_variables().is_empty());
+
+        let witness = generator.generate(&[3u64.into()], &[5u64.into()]);
+        assert!(unconstrained_values(&witness, &cs).is_empty());
+    }
+
+    #[test]
+    fn test_unconstrained_variables() {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.public_input();
+        // `y` is copied to two cells that the generic gate ignores
+        let y = b.compute_one(&[x], |v| v[0] + Fp::one());
+        b.generic([Fp::zero(); GENERIC_COEFFS], [Some(y), Some(y), None]);
+        // and `z` is in a single cell
+        let z = b.compute_one(&[x], |v| v[0].double());
+        b.generic([Fp::zero(); GENERIC_COEFFS], [Some(z), None, None]);
+        let (gates, generator) = b.build();
+        let cs = create(gates, 1);
+
+        let unconstrained = cs.unconstrained_variables();
+        assert_eq!(unconstrained.len(), 1);
+        assert_eq!(unconstrained[0].len(), 2);
+
+        let witness = generator.generate(&[3u64.into()], &[]);
+        assert_eq!(unconstrained_values(&witness, &cs).len(), 2);
+
+        let err = try_new_index_for_test_with_cs(cs, ProverIndex::try_create_strict)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            SetupError::UnconstrainedVariables(vec![unconstrained[0][0]])
+        );
+    }
+}
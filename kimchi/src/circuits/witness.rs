@@ -0,0 +1,275 @@
+//! This module implements the handling of the secret values of a witness by the prover.
+//!
+//! [Witness] wraps the columns of a witness and overwrites them with zeros when it is dropped,
+//! so that the values do not linger in freed memory.
+//! [ProverProof::create](crate::prover::ProverProof::create) moves the witness it receives into a [Witness],
+//! which is scrubbed however the function returns,
+//! and scrubs the polynomials it derives from the witness (see [scrub]) once they are no longer needed.
+//! It also replaces the variable-time inversions of [ark_ff] on secret values by [batch_inversion].
+//!
+//! This is a best-effort hardening. The field arithmetic of [ark_ff] is not guaranteed to run in constant time,
+//! and the multi-scalar multiplications that commit to the witness access memory depending on the scalars.
+//! Copies made by the caller before handing over the witness are not scrubbed either.
+//!
+//! The cells of a [Witness] can be addressed by the [Wire] that points to them (see [Witness::cell]),
+//! which spares the caller from picking the column and the row in the right order.
+
+use crate::circuits::{
+    polynomial::{WitnessEvals, WitnessOverDomains},
+    wires::{Wire, COLUMNS},
+};
+use ark_ff::{FftField, Field, One, Zero};
+use ark_poly::{univariate::DensePolynomial, EvaluationDomain, Evaluations};
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+/// The columns of a witness, overwritten with zeros when dropped.
+pub struct Witness<F: Field>([Vec<F>; COLUMNS]);
+
+impl<F: Field> Witness<F> {
+    /// Wraps `columns`. The columns are moved, not copied, so no unscrubbed copy is left behind.
+    pub fn new(columns: [Vec<F>; COLUMNS]) -> Self {
+        Self(columns)
+    }
+
+    /// Returns the columns, leaving the wrapper empty.
+    /// The columns are then no longer scrubbed on drop.
+    pub fn into_columns(mut self) -> [Vec<F>; COLUMNS] {
+        std::mem::replace(&mut self.0, array_init::array_init(|_| vec![]))
+    }
+
+    /// Returns the number of rows of the witness, that is, the length of its first column.
+    pub fn rows(&self) -> usize {
+        self.0[0].len()
+    }
+
+    /// Returns the value of the cell `wire` points to, or `None` if the cell is out of the witness.
+    pub fn cell(&self, wire: Wire) -> Option<&F> {
+        self.0.get(wire.col)?.get(wire.row)
+    }
+
+    /// Returns a mutable reference to the value of the cell `wire` points to,
+    /// or `None` if the cell is out of the witness.
+    pub fn cell_mut(&mut self, wire: Wire) -> Option<&mut F> {
+        self.0.get_mut(wire.col)?.get_mut(wire.row)
+    }
+
+    /// Returns the values of the row `row`, or `None` if a column is shorter than `row + 1`.
+    pub fn row(&self, row: usize) -> Option<[F; COLUMNS]> {
+        let mut values = [F::zero(); COLUMNS];
+        for (value, col) in values.iter_mut().zip(self.0.iter()) {
+            *value = *col.get(row)?;
+        }
+        Some(values)
+    }
+}
+
+impl<F: Field> From<[Vec<F>; COLUMNS]> for Witness<F> {
+    fn from(columns: [Vec<F>; COLUMNS]) -> Self {
+        Self::new(columns)
+    }
+}
+
+impl<F: Field> Deref for Witness<F> {
+    type Target = [Vec<F>; COLUMNS];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<F: Field> DerefMut for Witness<F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<F: Field> Zeroize for Witness<F> {
+    fn zeroize(&mut self) {
+        self.0.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl<F: Field> Drop for Witness<F> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Values derived from a witness that the prover overwrites with zeros once it is done with them.
+pub trait Scrub {
+    /// Overwrites the values with zeros.
+    fn scrub(&mut self);
+}
+
+impl<F: Field> Scrub for DensePolynomial<F> {
+    fn scrub(&mut self) {
+        self.coeffs.zeroize();
+    }
+}
+
+impl<F: Field, D: EvaluationDomain<F>> Scrub for Evaluations<F, D> {
+    fn scrub(&mut self) {
+        self.evals.zeroize();
+    }
+}
+
+impl<T: Scrub> Scrub for [T] {
+    fn scrub(&mut self) {
+        self.iter_mut().for_each(Scrub::scrub);
+    }
+}
+
+impl<T: Scrub> Scrub for Vec<T> {
+    fn scrub(&mut self) {
+        self.as_mut_slice().scrub();
+    }
+}
+
+impl<T: Scrub> Scrub for Option<T> {
+    fn scrub(&mut self) {
+        if let Some(x) = self {
+            x.scrub();
+        }
+    }
+}
+
+impl<F: FftField> Scrub for WitnessEvals<F> {
+    fn scrub(&mut self) {
+        self.w.scrub();
+        self.z.scrub();
+    }
+}
+
+impl<F: FftField> Scrub for WitnessOverDomains<F> {
+    fn scrub(&mut self) {
+        for shifts in [&mut self.d4, &mut self.d8] {
+            shifts.this.scrub();
+            shifts.next.scrub();
+        }
+    }
+}
+
+/// Overwrites `values` with zeros.
+pub fn scrub<T: Scrub + ?Sized>(values: &mut T) {
+    values.scrub();
+}
+
+/// Returns the inverse of `x`, or zero if `x` is zero, as `x^(p - 2)`.
+///
+/// Unlike [Field::inverse], whose extended Euclidean algorithm
+/// branches on the bits of its input, the exponentiation only branches on the bits of the public exponent.
+/// `F` must be a prime field.
+pub fn inverse<F: Field>(x: F) -> F {
+    debug_assert_eq!(F::extension_degree(), 1);
+    let mut exp = F::characteristic().to_vec();
+    // p - 2, with p odd
+    let mut borrow = 2;
+    for limb in exp.iter_mut() {
+        let (res, overflow) = limb.overflowing_sub(borrow);
+        *limb = res;
+        borrow = overflow as u64;
+    }
+    x.pow(&exp)
+}
+
+/// Inverts all the elements of `v` in place,
+/// combining them with Montgomery's trick so that a single [inverse] is needed.
+///
+/// This only branches on the values if their product is zero, which the prover only hits with negligible probability
+/// on random challenges: the elements are then inverted with [ark_ff::fields::batch_inversion], which leaves zeros untouched.
+pub fn batch_inversion<F: Field>(v: &mut [F]) {
+    let mut prefix = Vec::with_capacity(v.len());
+    let mut acc = F::one();
+    for x in v.iter() {
+        prefix.push(acc);
+        acc *= x;
+    }
+
+    if acc.is_zero() {
+        prefix.zeroize();
+        ark_ff::fields::batch_inversion(v);
+        return;
+    }
+
+    let mut inv = inverse(acc);
+    for (x, before) in v.iter_mut().zip(prefix.iter()).rev() {
+        let x_inv = inv * before;
+        inv *= *x;
+        *x = x_inv;
+    }
+
+    prefix.zeroize();
+    acc.zeroize();
+    inv.zeroize();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_poly::UVPolynomial;
+    use mina_curves::pasta::fp::Fp;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_batch_inversion() {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let v: Vec<_> = (0..17).map(|_| Fp::rand(rng)).collect();
+
+        let mut ours = v.clone();
+        batch_inversion(&mut ours);
+        for (x, x_inv) in v.iter().zip(&ours) {
+            assert_eq!(*x * x_inv, Fp::one());
+        }
+
+        assert_eq!(inverse(Fp::zero()), Fp::zero());
+        assert_eq!(inverse(v[0]), v[0].inverse().unwrap());
+
+        // zeros are left untouched
+        let mut with_zero = v.clone();
+        with_zero[3] = Fp::zero();
+        let mut expected = with_zero.clone();
+        batch_inversion(&mut with_zero);
+        ark_ff::fields::batch_inversion(&mut expected);
+        assert_eq!(with_zero, expected);
+    }
+
+    #[test]
+    fn test_witness_is_scrubbed() {
+        let mut witness = Witness::new(array_init::array_init(|_| vec![Fp::from(42u64); 4]));
+        witness[1][2] = Fp::from(7u64);
+        assert_eq!(witness[1][2], Fp::from(7u64));
+
+        witness.zeroize();
+        assert!(witness.iter().all(|col| col.is_empty()));
+
+        let mut poly = DensePolynomial::from_coefficients_vec(vec![Fp::one(); 3]);
+        scrub(&mut poly);
+        assert!(poly.coeffs.is_empty());
+    }
+
+    #[test]
+    fn test_witness_cells() {
+        let mut witness = Witness::new(array_init::array_init(|col| vec![Fp::from(col as u64); 4]));
+        assert_eq!(witness.rows(), 4);
+
+        let wire = Wire { row: 2, col: 5 };
+        *witness.cell_mut(wire).unwrap() = Fp::from(7u64);
+        assert_eq!(witness.cell(wire), Some(&Fp::from(7u64)));
+        assert_eq!(witness[5][2], Fp::from(7u64));
+        assert_eq!(witness.row(2).unwrap()[5], Fp::from(7u64));
+        assert_eq!(witness.row(1).unwrap()[5], Fp::from(5u64));
+
+        // cells out of the witness
+        assert_eq!(witness.cell(Wire { row: 4, col: 0 }), None);
+        assert_eq!(
+            witness.cell(Wire {
+                row: 0,
+                col: COLUMNS
+            }),
+            None
+        );
+        assert!(witness.row(4).is_none());
+    }
+}
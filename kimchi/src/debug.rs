@@ -0,0 +1,588 @@
+//! This module implements a debug proving mode, to localize the bugs of new gates.
+//!
+//! The verifier never sees the terms of the quotient one by one:
+//! the linearization folds the polynomials of the index into a single commitment,
+//! so that a gate whose constraints the prover and the verifier evaluate differently
+//! only shows up as a failed opening proof (see [crate::error::VerifyError::CommitmentOpeningFailed]).
+//!
+//! A [DebugProof] is a proof that additionally reveals the evaluations at $\zeta$ and $\zeta\omega$
+//! of every polynomial of the index and of the quotient, along with an opening proof of them against their commitments,
+//! and the evaluation at $\zeta$ of each term of the numerator of the quotient, as the prover computes it over the domain.
+//! Without the linearization, [DebugProof::verify] recomputes each term from these evaluations,
+//! and reports the terms on which it disagrees with the prover.
+//!
+//! A debug proof is larger and slower to create than a regular proof, and it does not support lookups.
+
+use crate::{
+    alphas::Alphas,
+    circuits::{
+        argument::ArgumentType,
+        check::QuotientTerm,
+        constraints::ConstraintSystem,
+        expr::{Column, Constants, Variable, E},
+        gate::GateType,
+        gates::poseidon::ROUNDS_PER_ROW,
+        polynomials::{
+            generic::{self, FUSED_REGISTER, GENERIC_COEFFS, GENERIC_REGISTERS},
+            permutation,
+        },
+        scalars::ProofEvaluations,
+        wires::{COLUMNS, PERMUTS},
+    },
+    error::{DebugError, ProofError, Result},
+    plonk_sponge::FrSponge,
+    prover::{environment, testing::ProverPolynomials, GateConstraints, ProverProof},
+    prover_index::ProverIndex,
+    verifier::check_shape,
+    verifier_index::VerifierIndex,
+};
+use ark_ec::AffineCurve;
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Evaluations, Polynomial,
+    Radix2EvaluationDomain as D,
+};
+use commitment_dlog::{
+    commitment::{CommitmentCurve, PolyComm},
+    evaluation_proof::OpeningProof,
+};
+use o1_utils::ExtendedDensePolynomial;
+use oracle::FqSponge;
+use rand::rngs::OsRng;
+use std::collections::HashMap;
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// A polynomial of the index, whose evaluations a [DebugProof] reveals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IndexPolynomial {
+    /// The selector of a gate
+    Selector(GateType),
+    /// The column of coefficients with the given index
+    Coefficient(usize),
+    /// The permutation polynomial of the given column
+    Sigma(usize),
+    /// The column of weights of the linear combination gates with the given index
+    Weight(usize),
+    /// The selector of the partial rounds of the Poseidon gates at the given position in a row
+    PartialRound(usize),
+    /// The selector of the custom gate registered at the given position
+    Custom(usize),
+}
+
+impl IndexPolynomial {
+    /// Returns the polynomials of the index of `cs`.
+    pub fn all<F: PrimeField>(cs: &ConstraintSystem<F>) -> Vec<Self> {
+        use GateType::*;
+        let mut polys: Vec<_> = [
+            Generic,
+            Poseidon,
+            CompleteAdd,
+            VarBaseMul,
+            EndoMul,
+            EndoMulScalar,
+        ]
+        .iter()
+        .map(|typ| Self::Selector(*typ))
+        .collect();
+        if cs.chacha8.is_some() {
+            polys.extend(
+                [ChaCha0, ChaCha1, ChaCha2, ChaChaFinal]
+                    .iter()
+                    .map(|typ| Self::Selector(*typ)),
+            );
+        }
+        if cs.boolean8.is_some() {
+            polys.push(Self::Selector(Boolean));
+        }
+        polys.extend((0..COLUMNS).map(Self::Coefficient));
+        polys.extend((0..PERMUTS).map(Self::Sigma));
+        if cs.linear_combination8.is_some() {
+            polys.extend((0..COLUMNS).map(Self::Weight));
+        }
+        if cs.partial_rounds8.is_some() {
+            polys.extend((0..ROUNDS_PER_ROW).map(Self::PartialRound));
+        }
+        polys.extend((0..cs.custom8.len()).map(Self::Custom));
+        polys
+    }
+
+    /// Returns the polynomial in coefficient form, if `cs` has it.
+    fn polynomial<F: PrimeField>(self, cs: &ConstraintSystem<F>) -> Option<DensePolynomial<F>> {
+        use GateType::*;
+        let chacha = |i: usize| cs.chacha8.as_ref().map(|c| c[i].interpolate_by_ref());
+        Some(match self {
+            Self::Selector(Generic) => cs.genericm.clone(),
+            Self::Selector(Poseidon) => cs.psm.clone(),
+            Self::Selector(CompleteAdd) => cs.complete_addl4.interpolate_by_ref(),
+            Self::Selector(VarBaseMul) => cs.mull8.interpolate_by_ref(),
+            Self::Selector(EndoMul) => cs.emull.interpolate_by_ref(),
+            Self::Selector(EndoMulScalar) => cs.endomul_scalar8.interpolate_by_ref(),
+            Self::Selector(ChaCha0) => chacha(0)?,
+            Self::Selector(ChaCha1) => chacha(1)?,
+            Self::Selector(ChaCha2) => chacha(2)?,
+            Self::Selector(ChaChaFinal) => chacha(3)?,
+            Self::Selector(Boolean) => cs.boolean8.as_ref()?.interpolate_by_ref(),
+            Self::Selector(_) => return None,
+            Self::Coefficient(i) => cs.coefficients8.get(i)?.interpolate_by_ref(),
+            Self::Sigma(i) => cs.sigmam.get(i)?.clone(),
+            Self::Weight(i) => cs
+                .linear_combination8
+                .as_ref()?
+                .get(i)?
+                .interpolate_by_ref(),
+            Self::PartialRound(i) => cs.partial_rounds8.as_ref()?.get(i)?.interpolate_by_ref(),
+            Self::Custom(i) => cs.custom8.get(i)?.interpolate_by_ref(),
+        })
+    }
+
+    /// Returns the commitment to the polynomial, if `index` has it.
+    fn commitment<G: CommitmentCurve>(self, index: &VerifierIndex<G>) -> Option<&PolyComm<G>> {
+        use GateType::*;
+        let chacha = |i: usize| index.chacha_comm.as_ref().map(|c| &c[i]);
+        match self {
+            Self::Selector(Generic) => Some(&index.generic_comm),
+            Self::Selector(Poseidon) => Some(&index.psm_comm),
+            Self::Selector(CompleteAdd) => Some(&index.complete_add_comm),
+            Self::Selector(VarBaseMul) => Some(&index.mul_comm),
+            Self::Selector(EndoMul) => Some(&index.emul_comm),
+            Self::Selector(EndoMulScalar) => Some(&index.endomul_scalar_comm),
+            Self::Selector(ChaCha0) => chacha(0),
+            Self::Selector(ChaCha1) => chacha(1),
+            Self::Selector(ChaCha2) => chacha(2),
+            Self::Selector(ChaChaFinal) => chacha(3),
+            Self::Selector(Boolean) => index.boolean_comm.as_ref(),
+            Self::Selector(_) => None,
+            Self::Coefficient(i) => index.coefficients_comm.get(i),
+            Self::Sigma(i) => index.sigma_comm.get(i),
+            Self::Weight(i) => index.linear_combination_comm.as_ref()?.get(i),
+            Self::PartialRound(i) => index.partial_rounds_comm.as_ref()?.get(i),
+            Self::Custom(i) => index.custom_comm.get(i),
+        }
+    }
+}
+
+/// A proof that reveals the evaluations the linearization hides (see the [module documentation](self)).
+#[derive(Clone)]
+pub struct DebugProof<G: AffineCurve> {
+    /// The regular proof
+    pub proof: ProverProof<G>,
+    /// The chunked evaluations at $\zeta$ and $\zeta\omega$ of the polynomials of the index
+    pub index_evals: Vec<(IndexPolynomial, [Vec<Fr<G>>; 2])>,
+    /// The chunked evaluations at $\zeta$ and $\zeta\omega$ of the quotient polynomial
+    pub quotient_evals: [Vec<Fr<G>>; 2],
+    /// The evaluation at $\zeta$ of each term of the numerator of the quotient, as computed by the prover
+    pub terms: Vec<(QuotientTerm, Fr<G>)>,
+    /// The scaling factor for the polynomials of the opening proof
+    pub polyscale: Fr<G>,
+    /// The scaling factor for the evaluation points of the opening proof
+    pub evalscale: Fr<G>,
+    /// The opening proof of the revealed evaluations
+    pub opening: OpeningProof<G>,
+}
+
+impl<G: CommitmentCurve> DebugProof<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Creates a proof of `witness` (see [ProverProof::create]), and reveals the evaluations of the index and of the quotient.
+    pub fn create<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>, EFrSponge: FrSponge<Fr<G>>>(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        index: &ProverIndex<G>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+    ) -> Result<Self> {
+        if index.cs.lookup_constraint_system.is_some() {
+            return Err(ProofError::Prover(
+                "the debug mode does not support lookups",
+            ));
+        }
+        let (proof, polys) = ProverProof::create_with_polynomials::<EFqSponge, EFrSponge>(
+            group_map,
+            witness,
+            index,
+            prev_challenges,
+        )?;
+
+        // the challenges are those the verifier derives
+        let verifier_index = index.verifier_index();
+        let oracles_result = proof
+            .oracles::<EFqSponge, EFrSponge>(&verifier_index, &proof.public_comm(&verifier_index));
+        let oracles = oracles_result.oracles;
+        let zeta = oracles.zeta;
+        let points = [zeta, zeta * index.cs.domain.d1.group_gen];
+
+        let constants = Constants {
+            alpha: oracles.alpha,
+            beta: oracles.beta,
+            gamma: oracles.gamma,
+            joint_combiner: oracles.joint_combiner.1,
+            endo_coefficient: index.cs.endo,
+            mds: index.cs.fr_sponge_params.mds.clone(),
+        };
+        let terms = prover_terms(index, &polys, constants, &oracles_result.all_alphas, zeta);
+
+        // the polynomials of the index are not hidden, the quotient is
+        let eval = |p: &DensePolynomial<Fr<G>>| points.map(|pt| p.eval(pt, index.max_poly_size));
+        let index_polys: Vec<_> = IndexPolynomial::all(&index.cs)
+            .into_iter()
+            .filter_map(|which| which.polynomial(&index.cs).map(|p| (which, p)))
+            .collect();
+        let index_evals: Vec<_> = index_polys
+            .iter()
+            .map(|(which, p)| (*which, eval(p)))
+            .collect();
+        let quotient_evals = eval(&polys.t);
+
+        let non_hiding = |chunks: usize| PolyComm {
+            unshifted: vec![Fr::<G>::zero(); chunks],
+            shifted: None,
+        };
+        let mut plnms: Vec<_> = index_polys
+            .iter()
+            .zip(&index_evals)
+            .map(|((_, p), (_, evals))| (p, None, non_hiding(evals[0].len())))
+            .collect();
+        plnms.push((
+            &polys.t,
+            None,
+            PolyComm {
+                unshifted: polys.t_blinders.unshifted[..quotient_evals[0].len()].to_vec(),
+                shifted: None,
+            },
+        ));
+
+        let rng = &mut OsRng;
+        let polyscale = Fr::<G>::rand(rng);
+        let evalscale = Fr::<G>::rand(rng);
+        let opening = index.srs.open(
+            group_map,
+            &plnms,
+            &points,
+            polyscale,
+            evalscale,
+            EFqSponge::new(index.fq_sponge_params.clone()),
+            rng,
+        );
+
+        Ok(Self {
+            proof,
+            index_evals,
+            quotient_evals,
+            terms,
+            polyscale,
+            evalscale,
+            opening,
+        })
+    }
+
+    /// Checks the revealed evaluations against the commitments of `index` and of the proof,
+    /// recomputes each term of the quotient from them and from the evaluations of the proof,
+    /// and checks that the prover computed the same terms, and that these add up to the quotient.
+    ///
+    /// This does not verify the proof itself (see [crate::verifier::batch_verify]),
+    /// which fails whenever one of these checks does.
+    pub fn verify<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>, EFrSponge: FrSponge<Fr<G>>>(
+        &self,
+        group_map: &G::Map,
+        index: &ProverIndex<G>,
+    ) -> std::result::Result<(), DebugError> {
+        if index.cs.lookup_constraint_system.is_some() {
+            return Err(DebugError::Unsupported("lookups"));
+        }
+        let verifier_index = index.verifier_index();
+        check_shape(&verifier_index, &self.proof).map_err(DebugError::Verify)?;
+
+        let oracles_result = self.proof.oracles::<EFqSponge, EFrSponge>(
+            &verifier_index,
+            &self.proof.public_comm(&verifier_index),
+        );
+        let oracles = oracles_result.oracles;
+        let zeta = oracles.zeta;
+        let points = [zeta, zeta * index.cs.domain.d1.group_gen];
+
+        //
+        // opening of the revealed evaluations
+        //
+
+        let revealed: HashMap<_, _> = self.index_evals.iter().map(|(p, e)| (*p, e)).collect();
+        let mut openings = vec![];
+        for which in IndexPolynomial::all(&index.cs) {
+            let evals = *revealed
+                .get(&which)
+                .ok_or(DebugError::MissingEvaluations(which))?;
+            let comm = which
+                .commitment(&verifier_index)
+                .ok_or(DebugError::MissingEvaluations(which))?;
+            openings.push((which, comm, evals));
+        }
+
+        let truncate = |comm: &PolyComm<G>, evals: &[Vec<Fr<G>>; 2]| {
+            let chunks = evals[0].len();
+            if evals[1].len() != chunks || chunks > comm.unshifted.len() {
+                return Err(DebugError::OpeningFailed);
+            }
+            Ok(PolyComm {
+                unshifted: comm.unshifted[..chunks].to_vec(),
+                shifted: None,
+            })
+        };
+        let mut comms = vec![];
+        for (_, comm, evals) in &openings {
+            comms.push(truncate(comm, evals)?);
+        }
+        comms.push(truncate(
+            &self.proof.commitments.t_comm,
+            &self.quotient_evals,
+        )?);
+
+        let mut evaluations: Vec<_> = openings.iter().map(|(_, _, evals)| *evals).collect();
+        evaluations.push(&self.quotient_evals);
+        let mut batch = vec![(
+            EFqSponge::new(index.fq_sponge_params.clone()),
+            points.to_vec(),
+            self.polyscale,
+            self.evalscale,
+            comms
+                .iter()
+                .zip(evaluations)
+                .map(|(comm, evals)| (comm, evals.iter().collect(), None))
+                .collect(),
+            &self.opening,
+        )];
+        if !index.srs.verify(group_map, &mut batch, &mut OsRng) {
+            return Err(DebugError::OpeningFailed);
+        }
+
+        //
+        // terms of the quotient
+        //
+
+        let powers = points.map(|pt| pt.pow(&[index.max_poly_size as u64]));
+        let combine = |evals: &[Vec<Fr<G>>; 2]| {
+            [0, 1].map(|i| DensePolynomial::eval_polynomial(&evals[i], powers[i]))
+        };
+        let index_evals: HashMap<_, _> = openings
+            .iter()
+            .map(|(which, _, evals)| (*which, combine(evals)))
+            .collect();
+        let evals = [0, 1].map(|i| self.proof.evals[i].combine(powers[i]));
+        let verifier_terms = verifier_terms(
+            index,
+            &self.proof.public,
+            &evals,
+            &index_evals,
+            &oracles_result.all_alphas,
+            Constants {
+                alpha: oracles.alpha,
+                beta: oracles.beta,
+                gamma: oracles.gamma,
+                joint_combiner: oracles.joint_combiner.1,
+                endo_coefficient: index.cs.endo,
+                mds: index.cs.fr_sponge_params.mds.clone(),
+            },
+            zeta,
+        )?;
+
+        let prover_terms: HashMap<_, _> = self.terms.iter().copied().collect();
+        let mismatches: Vec<_> = verifier_terms
+            .iter()
+            .filter(|(term, value)| prover_terms.get(term) != Some(value))
+            .map(|(term, _)| *term)
+            .collect();
+        if !mismatches.is_empty() {
+            return Err(DebugError::TermMismatch(mismatches));
+        }
+
+        let numerator: Fr<G> = self.terms.iter().map(|(_, value)| *value).sum();
+        let quotient = combine(&self.quotient_evals)[0];
+        if numerator != quotient * index.cs.domain.d1.evaluate_vanishing_polynomial(zeta) {
+            return Err(DebugError::QuotientMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Evaluates each term of the numerator of the quotient at `zeta`,
+/// from its evaluations over the domain, as the prover computes them.
+fn prover_terms<G: CommitmentCurve>(
+    index: &ProverIndex<G>,
+    polys: &ProverPolynomials<G>,
+    constants: Constants<Fr<G>>,
+    all_alphas: &Alphas<Fr<G>>,
+    zeta: Fr<G>,
+) -> Vec<(QuotientTerm, Fr<G>)> {
+    let at_zeta = |evals: Evaluations<Fr<G>, D<Fr<G>>>| evals.interpolate().evaluate(&zeta);
+    let (beta, gamma) = (constants.beta, constants.gamma);
+    let lagrange = index.cs.evaluate(&polys.witness, &polys.z);
+    let env = environment(index, &lagrange, constants, None);
+    let constraints = GateConstraints::new(index);
+
+    let alphas = all_alphas.get_alphas(ArgumentType::Gate(GateType::Generic), generic::CONSTRAINTS);
+    let mut terms = vec![(
+        QuotientTerm::Gate(GateType::Generic),
+        at_zeta(index.cs.gnrc_quot(alphas, &lagrange.d4.this.w)),
+    )];
+    let alphas = all_alphas.get_alphas(ArgumentType::Permutation, permutation::CONSTRAINTS);
+    terms.push((
+        QuotientTerm::Permutation,
+        at_zeta(index.cs.perm_quot(&lagrange, beta, gamma, alphas)),
+    ));
+
+    for (typ, expr) in gate_constraints(&constraints) {
+        terms.push((QuotientTerm::Gate(typ), at_zeta(expr.evaluations(&env))));
+    }
+    if !constraints.custom.is_empty() {
+        let custom = constraints
+            .custom
+            .iter()
+            .map(|expr| at_zeta(expr.evaluations(&env)))
+            .sum::<Fr<G>>();
+        terms.push((QuotientTerm::Gate(GateType::Custom), custom));
+    }
+    if let Some(boundary) = &constraints.boundary {
+        terms.push((QuotientTerm::Boundary, at_zeta(boundary.evaluations(&env))));
+    }
+
+    terms.push((QuotientTerm::PublicInput, polys.public.evaluate(&zeta)));
+    terms
+}
+
+/// Evaluates each term of the numerator of the quotient at `zeta`,
+/// from the (combined) evaluations of the proof and of the index.
+fn verifier_terms<G: CommitmentCurve>(
+    index: &ProverIndex<G>,
+    public: &[Fr<G>],
+    evals: &[ProofEvaluations<Fr<G>>; 2],
+    index_evals: &HashMap<IndexPolynomial, [Fr<G>; 2]>,
+    all_alphas: &Alphas<Fr<G>>,
+    constants: Constants<Fr<G>>,
+    zeta: Fr<G>,
+) -> std::result::Result<Vec<(QuotientTerm, Fr<G>)>, DebugError> {
+    let d1 = index.cs.domain.d1;
+    let index_eval = |which: IndexPolynomial, row: usize| {
+        index_evals
+            .get(&which)
+            .map(|e| e[row])
+            .ok_or(DebugError::MissingEvaluations(which))
+    };
+    let coefficients = (0..COLUMNS)
+        .map(|i| index_eval(IndexPolynomial::Coefficient(i), 0))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    // generic gates
+    let generic = {
+        let mut alphas =
+            all_alphas.get_alphas(ArgumentType::Gate(GateType::Generic), generic::CONSTRAINTS);
+        let gate = |alpha: Fr<G>, c: &[Fr<G>], w: &[Fr<G>]| {
+            let sum: Fr<G> = (0..GENERIC_REGISTERS).map(|i| c[i] * w[i]).sum();
+            alpha * (sum + c[GENERIC_REGISTERS] * w[0] * w[1] + c[GENERIC_REGISTERS + 1])
+        };
+        let alpha = alphas.next().unwrap();
+        let mut first = gate(alpha, &coefficients, &evals[0].w);
+        if index.cs.fused_generic {
+            let (c, w) = (&coefficients[2 * GENERIC_COEFFS..], &evals[0].w);
+            first += alpha * (c[0] * w[0] + c[1] * w[1] + c[2]) * w[FUSED_REGISTER];
+        }
+        let second = gate(
+            alphas.next().unwrap(),
+            &coefficients[GENERIC_COEFFS..],
+            &evals[0].w[GENERIC_REGISTERS..],
+        );
+        evals[0].generic_selector * (first + second)
+    };
+    let mut terms = vec![(QuotientTerm::Gate(GateType::Generic), generic)];
+
+    // permutation
+    let perm = {
+        let mut alphas = all_alphas.get_alphas(ArgumentType::Permutation, permutation::CONSTRAINTS);
+        let (beta, gamma) = (constants.beta, constants.gamma);
+        let w = &evals[0].w;
+        let shifts = (0..PERMUTS)
+            .map(|i| w[i] + gamma + beta * zeta * index.cs.shift[i])
+            .fold(evals[0].z, |acc, x| acc * x);
+        let mut sigmas = evals[1].z;
+        for i in 0..PERMUTS {
+            sigmas *= w[i] + gamma + beta * index_eval(IndexPolynomial::Sigma(i), 0)?;
+        }
+        let (alpha0, alpha1, alpha2) = (
+            alphas.next().unwrap(),
+            alphas.next().unwrap(),
+            alphas.next().unwrap(),
+        );
+        let last = d1.group_gen.pow(&[d1.size - 3]);
+        alpha0 * index.cs.zkpm.evaluate(&zeta) * (shifts - sigmas)
+            + (evals[0].z - Fr::<G>::one())
+                * d1.evaluate_vanishing_polynomial(zeta)
+                * (alpha1 / (zeta - Fr::<G>::one()) + alpha2 / (zeta - last))
+    };
+    terms.push((QuotientTerm::Permutation, perm));
+
+    // the other gates, from their constraint expressions
+    let cell = |v: &Variable| {
+        let row = v.row.shift();
+        match v.col {
+            Column::Witness(i) => Ok(evals[row].w[i]),
+            Column::Z => Ok(evals[row].z),
+            Column::Coefficient(i) => index_eval(IndexPolynomial::Coefficient(i), row),
+            Column::Weight(i) => index_eval(IndexPolynomial::Weight(i), row),
+            Column::PartialRound(i) => index_eval(IndexPolynomial::PartialRound(i), row),
+            Column::Index(typ) => index_eval(IndexPolynomial::Selector(typ), row),
+            Column::CustomIndex(i) => index_eval(IndexPolynomial::Custom(i), row),
+            _ => Err(DebugError::Unsupported("lookups")),
+        }
+    };
+    let constraints = GateConstraints::new(index);
+    for (typ, expr) in gate_constraints(&constraints) {
+        let value = expr.evaluate_cells(d1, zeta, &cell, &constants)?;
+        terms.push((QuotientTerm::Gate(typ), value));
+    }
+    if !constraints.custom.is_empty() {
+        let mut custom = Fr::<G>::zero();
+        for expr in &constraints.custom {
+            custom += expr.evaluate_cells(d1, zeta, &cell, &constants)?;
+        }
+        terms.push((QuotientTerm::Gate(GateType::Custom), custom));
+    }
+    if let Some(boundary) = &constraints.boundary {
+        let value = boundary.evaluate_cells(d1, zeta, &cell, &constants)?;
+        terms.push((QuotientTerm::Boundary, value));
+    }
+
+    // public input
+    let mut public = public.to_vec();
+    public.resize(d1.size(), Fr::<G>::zero());
+    let public = -Evaluations::from_vec_and_domain(public, d1).interpolate();
+    terms.push((QuotientTerm::PublicInput, public.evaluate(&zeta)));
+
+    Ok(terms)
+}
+
+/// Returns the constraints of the gates of this crate that are expressions, by gate type.
+fn gate_constraints<F: PrimeField>(constraints: &GateConstraints<F>) -> Vec<(GateType, &E<F>)> {
+    use GateType::*;
+    let mut gates = vec![
+        (CompleteAdd, &constraints.complete_add),
+        (VarBaseMul, &constraints.varbase_mul),
+        (EndoMul, &constraints.endomul),
+        (EndoMulScalar, &constraints.endomul_scalar),
+        (Poseidon, &constraints.poseidon),
+    ];
+    if let Some(chacha) = &constraints.chacha {
+        gates.extend(
+            [ChaCha0, ChaCha1, ChaCha2, ChaChaFinal]
+                .iter()
+                .copied()
+                .zip(chacha),
+        );
+    }
+    if let Some(linear_combination) = &constraints.linear_combination {
+        gates.push((LinearCombination, linear_combination));
+    }
+    if let Some(boolean) = &constraints.boolean {
+        gates.push((Boolean, boolean));
+    }
+    gates
+}
@@ -0,0 +1,208 @@
+//! This module implements the first steps of a two-party prover,
+//! for which the witness is additively secret-shared between two machines,
+//! so that neither of them sees the whole secret witness.
+//!
+//! The witness is split with [share_witness] into two [WitnessShare]s whose sum is the witness.
+//! The public inputs are not secret, and are left in the first share.
+//! As the commitments and the FFTs are linear, each party computes them on its own share:
+//! - [WitnessShare::commit] commits to the columns of a share with fresh blinders,
+//!   and [combine_commitments] adds up the commitments of both parties
+//!   into hiding commitments to the witness, blinded by the sum of the blinders of both parties;
+//! - [WitnessShare::interpolate] interpolates the columns of a share,
+//!   the sum of which are the witness polynomials.
+//!
+//! The parties then run a single transcript, in which they absorb the combined commitments
+//! exactly like [ProverProof::create](crate::prover::ProverProof::create) absorbs the witness commitments
+//! (see [absorb_witness_commitments]).
+//!
+//! The following rounds of the prover (the permutation and lookup aggregations, the quotient, the evaluations)
+//! are not linear in the witness, and would require a multiplication protocol between the parties
+//! (for example with Beaver triples): they are not implemented yet, and the proof cannot be completed over shares.
+
+use crate::circuits::wires::COLUMNS;
+use crate::prover_index::ProverIndex;
+use ark_ec::AffineCurve;
+use ark_ff::{FftField, UniformRand, Zero};
+use ark_poly::{univariate::DensePolynomial, Evaluations, Radix2EvaluationDomain as D};
+use array_init::array_init;
+use commitment_dlog::commitment::{CommitmentCurve, PolyComm};
+use oracle::FqSponge;
+use rand::{CryptoRng, RngCore};
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// The share of the witness held by one of the two provers.
+#[derive(Clone)]
+pub struct WitnessShare<F> {
+    /// the shares of the columns of the witness
+    pub columns: [Vec<F>; COLUMNS],
+}
+
+/// Splits `witness` into two shares whose sum is the witness,
+/// leaving the `public` first cells of the first column in the first share.
+pub fn share_witness<F: FftField>(
+    witness: &[Vec<F>; COLUMNS],
+    public: usize,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> [WitnessShare<F>; 2] {
+    let mut first = witness.clone();
+    let mut second: [Vec<F>; COLUMNS] = array_init(|i| vec![F::zero(); witness[i].len()]);
+    for (col, (first, second)) in first.iter_mut().zip(second.iter_mut()).enumerate() {
+        let skip = if col == 0 { public } else { 0 };
+        for (x, y) in first.iter_mut().zip(second.iter_mut()).skip(skip) {
+            let mask = F::rand(rng);
+            *x -= mask;
+            *y = mask;
+        }
+    }
+    [
+        WitnessShare { columns: first },
+        WitnessShare { columns: second },
+    ]
+}
+
+/// The commitments of a party to its share of the witness, with their blinders.
+pub struct ShareCommitments<G: CommitmentCurve> {
+    /// the hiding commitments to the columns of the share
+    pub commitments: [PolyComm<G>; COLUMNS],
+    /// the blinders of the commitments, which the party keeps for the opening
+    pub blinders: [PolyComm<Fr<G>>; COLUMNS],
+}
+
+impl<F: FftField> WitnessShare<F> {
+    /// Adds up two shares, recovering the witness.
+    pub fn reconstruct(&self, other: &Self) -> [Vec<F>; COLUMNS] {
+        array_init(|i| {
+            self.columns[i]
+                .iter()
+                .zip(&other.columns[i])
+                .map(|(x, y)| *x + y)
+                .collect()
+        })
+    }
+
+    /// Interpolates the columns of the share over `domain`.
+    pub fn interpolate(&self, domain: D<F>) -> [DensePolynomial<F>; COLUMNS] {
+        array_init(|i| {
+            Evaluations::<F, D<F>>::from_vec_and_domain(self.columns[i].clone(), domain)
+                .interpolate()
+        })
+    }
+
+    /// Commits to the columns of the share, with fresh blinders.
+    pub fn commit<G>(
+        &self,
+        index: &ProverIndex<G>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> ShareCommitments<G>
+    where
+        G: CommitmentCurve<ScalarField = F>,
+    {
+        let domain = index.cs.domain.d1;
+        let mut commitments: [PolyComm<G>; COLUMNS] = array_init(|_| PolyComm {
+            unshifted: vec![],
+            shifted: None,
+        });
+        let mut blinders: [PolyComm<F>; COLUMNS] = array_init(|_| PolyComm {
+            unshifted: vec![],
+            shifted: None,
+        });
+        for (i, column) in self.columns.iter().enumerate() {
+            let evals = Evaluations::<F, D<F>>::from_vec_and_domain(column.clone(), domain);
+            let (comm, blinder) = index.srs.commit_evaluations(domain, &evals, None, rng);
+            commitments[i] = comm;
+            blinders[i] = blinder;
+        }
+        ShareCommitments {
+            commitments,
+            blinders,
+        }
+    }
+}
+
+/// Adds up the commitments of both parties, returning the hiding commitments to the witness,
+/// and the blinders of these commitments (the sum of the blinders of both parties,
+/// to be revealed to neither party outside of the opening protocol).
+pub fn combine_commitments<G: CommitmentCurve>(
+    first: &ShareCommitments<G>,
+    second: &ShareCommitments<G>,
+) -> ([PolyComm<G>; COLUMNS], [PolyComm<Fr<G>>; COLUMNS]) {
+    let commitments = array_init(|i| &first.commitments[i] + &second.commitments[i]);
+    let blinders = array_init(|i| {
+        let (a, b) = (&first.blinders[i], &second.blinders[i]);
+        let len = a.unshifted.len().max(b.unshifted.len());
+        let chunk = |c: &PolyComm<Fr<G>>, j: usize| {
+            c.unshifted.get(j).copied().unwrap_or_else(Fr::<G>::zero)
+        };
+        PolyComm {
+            unshifted: (0..len).map(|j| chunk(a, j) + chunk(b, j)).collect(),
+            shifted: None,
+        }
+    });
+    (commitments, blinders)
+}
+
+/// Absorbs the combined witness commitments in the transcript shared by the parties,
+/// as the prover does for the commitments to the witness.
+pub fn absorb_witness_commitments<G, EFqSponge>(
+    sponge: &mut EFqSponge,
+    commitments: &[PolyComm<G>; COLUMNS],
+) where
+    G: CommitmentCurve,
+    EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+{
+    for comm in commitments {
+        sponge.absorb_g(&comm.unshifted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::polynomials::generic::testing::create_circuit_and_witness;
+    use crate::prover_index::testing::new_index_for_test;
+    use mina_curves::pasta::{fp::Fp, vesta::Affine};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_shared_witness_commitments() {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let (gates, mut witness, public) = create_circuit_and_witness(5);
+        let index = new_index_for_test(gates, public.len());
+        let n = index.cs.domain.d1.size as usize;
+        witness.iter_mut().for_each(|w| w.resize(n, Fp::zero()));
+
+        let [first, second] = share_witness(&witness, public.len(), rng);
+        assert_eq!(first.reconstruct(&second), witness);
+        assert_eq!(first.columns[0][..public.len()], public[..]);
+        assert_ne!(first.columns[1], witness[1]);
+
+        // the interpolation of the shares adds up to the witness polynomials
+        let domain = index.cs.domain.d1;
+        let (a, b) = (first.interpolate(domain), second.interpolate(domain));
+        let expected = WitnessShare {
+            columns: witness.clone(),
+        }
+        .interpolate(domain);
+        for i in 0..COLUMNS {
+            assert_eq!(&a[i] + &b[i], expected[i]);
+        }
+
+        // the combined commitments are hiding commitments to the witness
+        let (commitments, blinders) =
+            combine_commitments(&first.commit(&index, rng), &second.commit(&index, rng));
+        for i in 0..COLUMNS {
+            let evals = Evaluations::<Fp, D<Fp>>::from_vec_and_domain(witness[i].clone(), domain);
+            let mut comm = index
+                .srs
+                .commit_evaluations_non_hiding(domain, &evals, None);
+            // the commitment to a zero column has no chunks, but its shares do
+            if comm.unshifted.is_empty() {
+                comm.unshifted.push(Affine::zero());
+            }
+            let expected = index.srs.mask_custom(comm, &blinders[i]).unwrap();
+            assert_eq!(commitments[i].unshifted, expected.unshifted);
+        }
+    }
+}
@@ -1,23 +1,270 @@
-//! This module implements the [ProofError] type.
+//! This module implements the [ProofError], [VerifyError], [SetupError], [PublicInputError], [IrError], [DebugError] and [ServiceError] types.
 
+use crate::circuits::{check::QuotientTerm, expr::Column, wires::Wire};
+use crate::debug::IndexPolynomial;
+use std::fmt;
 use thiserror::Error;
 
 /// The result of a proof creation or verification.
 pub type Result<T> = std::result::Result<T, ProofError>;
 
-// TODO(mimoo): move this out of oracle
-#[derive(Error, Debug, Clone, Copy)]
+/// The reason why a proof could not be created.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProofError {
-    #[error("the circuit is too large")]
-    NoRoomForZkInWitness,
-    #[error("the witness columns are not all the same size")]
-    WitnessCsInconsistent,
+    /// The witness does not leave room for the zero-knowledge rows
+    /// ([ZK_ROWS](crate::circuits::constraints::ZK_ROWS)) in the domain of the circuit.
+    #[error("the witness has {rows} rows, which leaves no room for the zero-knowledge rows in the domain of size {domain}")]
+    DomainTooSmall { rows: usize, domain: usize },
+    #[error("column {col} of the witness has {got} rows, but the first column has {expected}")]
+    WitnessLengthMismatch {
+        col: usize,
+        expected: usize,
+        got: usize,
+    },
+    #[error(
+        "the witness has {rows} rows, but the circuit has {public} public inputs in its first rows"
+    )]
+    WitnessTooShort { rows: usize, public: usize },
+    /// The SRS can be smaller than the domain (the polynomials are then committed in chunks),
+    /// but the prover needs its Lagrange basis for the domain to commit to the witness.
+    #[error("the SRS has no Lagrange basis for the domain of size {domain}")]
+    MissingLagrangeBasis { domain: usize },
+    /// The witness does not satisfy `term` of the quotient on `row`
+    /// (see [counterexample](crate::circuits::check::counterexample) for the values involved).
+    #[error("the witness does not satisfy the {term:?} term of the quotient at row {row}")]
+    UnsatisfiedGate { row: usize, term: QuotientTerm },
     #[error("the proof could not be constructed: {0}")]
     Prover(&'static str),
     #[error("the permutation was not constructed correctly: {0}")]
     Permutation(&'static str),
-    #[error("the opening proof failed to verify")]
-    OpenProof,
     #[error("the lookup failed to find a match in the table")]
     ValueNotInTable,
+    /// The circuit looks up a runtime table, but the prover did not give its second column,
+    /// or not with as many entries as its first column.
+    #[error("the circuit looks up the runtime table {id}, whose second column of {len} entries is missing")]
+    MissingRuntimeTable { id: usize, len: usize },
+    #[error("the circuit does not look up the runtime table {id}")]
+    UnknownRuntimeTable { id: usize },
+}
+
+impl ProofError {
+    /// A short name for the kind of error, to label metrics with.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ProofError::DomainTooSmall { .. } => "domain_too_small",
+            ProofError::WitnessLengthMismatch { .. } => "witness_length_mismatch",
+            ProofError::WitnessTooShort { .. } => "witness_too_short",
+            ProofError::MissingLagrangeBasis { .. } => "missing_lagrange_basis",
+            ProofError::UnsatisfiedGate { .. } => "unsatisfied_gate",
+            ProofError::Prover(_) => "prover",
+            ProofError::Permutation(_) => "permutation",
+            ProofError::ValueNotInTable => "value_not_in_table",
+            ProofError::MissingRuntimeTable { .. } => "missing_runtime_table",
+            ProofError::UnknownRuntimeTable { .. } => "unknown_runtime_table",
+        }
+    }
+}
+
+/// The reason why the verifier rejected a proof.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("the proof has {got} public inputs, but the circuit has {expected}")]
+    PublicInputMismatch { expected: usize, got: usize },
+    #[error("the proof does not follow the transcript of the circuit: {0}")]
+    TranscriptMismatch(&'static str),
+    #[error("the quotient commitment has {got} chunks, but the circuit expects {expected}")]
+    QuotientMismatch { expected: usize, got: usize },
+    /// The proof is not well-formed, for instance because its vectors do not have the expected lengths.
+    /// The verifier rejects such proofs before running the protocol, to never panic on them.
+    #[error("the proof is malformed: {0}")]
+    MalformedProof(&'static str),
+    /// The opening proof does not verify.
+    /// As the verifier evaluates the quotient identity through the linearization,
+    /// a proof that does not satisfy the constraints is also rejected here.
+    #[error("the opening proof of the proof at position {which} in the batch failed to verify")]
+    CommitmentOpeningFailed { which: usize },
+    #[error("the public input of the proof does not match the named values: {0}")]
+    PublicInput(PublicInputError),
+}
+
+impl VerifyError {
+    /// A short name for the kind of error, to label metrics with.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            VerifyError::PublicInputMismatch { .. } => "public_input_mismatch",
+            VerifyError::TranscriptMismatch(_) => "transcript_mismatch",
+            VerifyError::QuotientMismatch { .. } => "quotient_mismatch",
+            VerifyError::MalformedProof(_) => "malformed_proof",
+            VerifyError::CommitmentOpeningFailed { .. } => "commitment_opening_failed",
+            VerifyError::PublicInput(_) => "public_input",
+        }
+    }
+}
+
+/// A wire of a gate that points to a cell outside of the wired columns of the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidWire {
+    /// the row of the gate
+    pub row: usize,
+    /// the column of the wire in the gate
+    pub col: usize,
+    /// the cell the wire points to
+    pub wire: Wire,
+}
+
+impl fmt::Display for InvalidWire {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row {} column {} is wired to row {} column {}",
+            self.row, self.col, self.wire.row, self.wire.col
+        )
+    }
+}
+
+/// Lists the offending wires of a [SetupError::InvalidWires] or a [SetupError::NotAPermutation].
+fn list_wires(wires: &[InvalidWire]) -> String {
+    wires
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists the cells of a [SetupError::UnconstrainedVariables].
+fn list_cells(cells: &[Wire]) -> String {
+    cells
+        .iter()
+        .map(|cell| format!("row {} column {}", cell.row, cell.col))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The reason why a constraint system could not be created from a circuit.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SetupError {
+    #[error("no evaluation domain is large enough for {0} rows")]
+    DomainCreation(usize),
+    #[error("{} wires point outside of the circuit ({} rows, {} wired columns): {}", .wires.len(), .rows, .cols, list_wires(.wires))]
+    InvalidWires {
+        rows: usize,
+        cols: usize,
+        wires: Vec<InvalidWire>,
+    },
+    #[error(
+        "the public input layout has {slots} slots, but the circuit has {public} public inputs"
+    )]
+    PublicInputLayout { slots: usize, public: usize },
+    #[error("the wires are not a permutation, {} wires point to cells that other wires point to: {}", .wires.len(), list_wires(.wires))]
+    NotAPermutation { wires: Vec<InvalidWire> },
+    #[error("the shifts of the permutation do not create disjoint cosets of the domain")]
+    InvalidShifts,
+    /// The prover evaluates the constraints over a domain of `max` times the size of the circuit,
+    /// which bounds their degree (counted in multiples of the size of the circuit, including the selector of the gate).
+    #[error("the constraints of the gates have degree {degree}, but the prover can only evaluate constraints of degree up to {max}")]
+    ConstraintDegree { degree: u64, max: u64 },
+    /// The variables that no constraint reads, each given by one of its cells
+    /// (see [ConstraintSystem::unconstrained_variables](crate::circuits::constraints::ConstraintSystem::unconstrained_variables)).
+    #[error("{} variables are not constrained by any gate: {}", .0.len(), list_cells(.0))]
+    UnconstrainedVariables(Vec<Wire>),
+    /// Creating the index again from the same constraint system gave another result
+    /// (see [ProverIndex::check_reproducible](crate::prover_index::ProverIndex::check_reproducible)).
+    #[error("recreating the index gives another {0}")]
+    NotReproducible(&'static str),
+    /// Only the cells of the witness and of the permutation aggregation polynomial can be pinned,
+    /// on the rows before the zero-knowledge rows
+    /// (see [ConstraintSystem::add_boundary_constraint](crate::circuits::constraints::ConstraintSystem::add_boundary_constraint)).
+    #[error("the cell of {column:?} on row {row} cannot be pinned")]
+    InvalidBoundary { row: usize, column: Column },
+    /// The first coefficient of a lookup gate is the position of its table among the tables of the circuit
+    /// (see [lookup_gate](crate::circuits::polynomials::lookup_gate)).
+    #[error("the lookup gate of row {row} refers to no lookup table of the circuit")]
+    UnknownLookupTable { row: usize },
+    #[error("the lookup table {id} cannot be looked up: {reason}")]
+    InvalidLookupTable { id: usize, reason: &'static str },
+    #[error(
+        "the circuit looks up several runtime tables, but the lookup argument only supports one"
+    )]
+    SeveralRuntimeTables,
+    /// The polynomials of a circuit larger than the SRS are committed in chunks,
+    /// whose evaluations the zero-knowledge rows of the witness are not enough to hide
+    /// (see [ProverIndex::try_create_without_zk](crate::prover_index::ProverIndex::try_create_without_zk)).
+    #[error("the SRS of size {srs} is smaller than the domain of size {domain}, so the proofs cannot hide the witness")]
+    ChunkedZeroKnowledge { srs: usize, domain: usize },
+}
+
+/// The reason why named public inputs do not fit a [PublicInputLayout](crate::circuits::public_input::PublicInputLayout).
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PublicInputError {
+    #[error("the public input {0} is declared twice")]
+    DuplicateName(String),
+    #[error("the public input at position {0} has no name")]
+    Unnamed(usize),
+    #[error("no value is given for the public input {0}")]
+    Missing(String),
+    #[error("the circuit has no public input named {0}")]
+    UnknownName(String),
+    #[error("the public input {0} has another value")]
+    ValueMismatch(String),
+    #[error("there are {got} public inputs, but the layout has {expected}")]
+    Count { expected: usize, got: usize },
+    #[error("the circuit does not name its public inputs")]
+    NoLayout,
+}
+
+/// The reason why the witness of a circuit built with a [CircuitBuilder](crate::circuits::builder::CircuitBuilder)
+/// could not be computed.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WitnessError {
+    #[error("the circuit has {expected} public inputs, but {got} were given")]
+    PublicInputs { expected: usize, got: usize },
+    #[error("the circuit has {expected} private inputs, but {got} were given")]
+    PrivateInputs { expected: usize, got: usize },
+    #[error("the witness computation {hint} returned {got} values instead of {expected}")]
+    HintOutputs {
+        hint: usize,
+        expected: usize,
+        got: usize,
+    },
+    #[error(transparent)]
+    PublicInput(#[from] PublicInputError),
+}
+
+/// The reason why a circuit could not be read from its [textual format](crate::circuits::ir).
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IrError {
+    #[error("the circuit does not start with `{0}`")]
+    Header(&'static str),
+    #[error("line {line}: {err}")]
+    Syntax { line: usize, err: String },
+}
+
+/// The reason why a [DebugProof](crate::debug::DebugProof) was rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DebugError {
+    #[error("the debug mode does not support {0}")]
+    Unsupported(&'static str),
+    #[error("the proof cannot be checked: {0}")]
+    Verify(VerifyError),
+    #[error("the debug proof does not reveal the evaluations of {0:?}")]
+    MissingEvaluations(IndexPolynomial),
+    #[error("the revealed evaluations do not match their commitments")]
+    OpeningFailed,
+    /// The prover and the verifier evaluate the given terms of the quotient differently at $\zeta$.
+    #[error("the prover and the verifier disagree on the {0:?} terms of the quotient")]
+    TermMismatch(Vec<QuotientTerm>),
+    /// The terms agree, but they do not add up to the committed quotient (times the vanishing polynomial).
+    #[error("the terms of the quotient do not add up to the quotient")]
+    QuotientMismatch,
+}
+
+/// The reason why a job of the [ProverService](crate::service::ProverService) did not return a proof.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    #[error("the job needs {required} bytes, more than the limit of {limit} bytes")]
+    MemoryLimitExceeded { required: usize, limit: usize },
+    #[error("the proof could not be created: {0}")]
+    Proof(#[from] ProofError),
+    #[error("the job was cancelled or its prover panicked")]
+    Cancelled,
 }
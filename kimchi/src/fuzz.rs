@@ -0,0 +1,173 @@
+//! This module implements the harness functions of the [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz) targets
+//! of the `fuzz` directory (see the README of the crate).
+//!
+//! Each function takes arbitrary bytes, as crafted by a malicious prover,
+//! and feeds them to the deserialization of a proof or of a verifier index, or to the verifier.
+//! The fuzzer reports a function that panics: the verifier must reject malformed inputs with an error.
+//! The proofs are verified against a small circuit, whose serialized honest proof ([seed_proof]) is a good start for the corpus.
+
+use crate::{
+    circuits::builder::CircuitBuilder, prover::ProverProof,
+    prover_index::testing::new_index_for_test, verifier::batch_verify,
+    verifier_index::VerifierIndex,
+};
+use commitment_dlog::{
+    commitment::CommitmentCurve,
+    srs::{endos, SRS},
+};
+use groupmap::{BWParameters, GroupMap};
+use mina_curves::pasta::{
+    fp::Fp,
+    pallas::Affine as Other,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use std::sync::Arc;
+
+type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+/// The circuit the fuzzed proofs are verified against, with an honest proof of it.
+struct Context {
+    group_map: BWParameters<VestaParameters>,
+    index: VerifierIndex<Affine>,
+    proof: ProverProof<Affine>,
+}
+
+impl Context {
+    /// Proves that y = x^3 + x, for a public y and a private x.
+    fn new() -> Self {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let y = b.public_input();
+        let x = b.private_input();
+        let x2 = b.mul(x, x);
+        let x3 = b.mul(x2, x);
+        let res = b.add(x3, x);
+        b.assert_equal(res, y);
+        let (gates, generator) = b.build();
+
+        let public = [Fp::from(30u64)];
+        let witness = generator.generate(&public, &[Fp::from(3u64)]);
+        let index = new_index_for_test(gates, public.len());
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let proof =
+            ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+                .expect("the witness of the fuzzed circuit is valid");
+
+        Context {
+            group_map,
+            index: index.verifier_index(),
+            proof,
+        }
+    }
+}
+
+thread_local! {
+    // the fuzzer calls the harness once per input, so the circuit is only set up once
+    static CONTEXT: Context = Context::new();
+}
+
+/// Returns the serialized honest proof of the circuit of [fuzz_verify].
+pub fn seed_proof() -> Vec<u8> {
+    CONTEXT.with(|ctx| rmp_serde::to_vec(&ctx.proof).expect("the proof is serializable"))
+}
+
+/// Deserializes a proof from `data`.
+pub fn fuzz_proof_deserialization(data: &[u8]) {
+    let _ = rmp_serde::from_slice::<ProverProof<Affine>>(data);
+}
+
+/// Deserializes a verifier index from `data`, with the SRS of a small circuit.
+pub fn fuzz_verifier_index_deserialization(data: &[u8]) {
+    CONTEXT.with(|ctx| {
+        let srs: Arc<SRS<Affine>> = ctx.index.srs.clone();
+        let (endo_q, _endo_r) = endos::<Other>();
+        let _ = VerifierIndex::<Affine>::read(
+            data,
+            |_| srs,
+            endo_q,
+            oracle::pasta::fq_kimchi::params(),
+            oracle::pasta::fp_kimchi::params(),
+        );
+    });
+}
+
+/// Deserializes a proof from `data` and verifies it against a small circuit.
+pub fn fuzz_verify(data: &[u8]) {
+    let proof = match rmp_serde::from_slice::<ProverProof<Affine>>(data) {
+        Ok(proof) => proof,
+        Err(_) => return,
+    };
+    CONTEXT.with(|ctx| {
+        let _ = batch_verify::<Affine, BaseSponge, ScalarSponge>(
+            &ctx.group_map,
+            &[(&ctx.index, &proof)],
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::VerifyError;
+    use ark_ff::One;
+
+    fn verify(proof: &ProverProof<Affine>) -> Result<(), VerifyError> {
+        CONTEXT.with(|ctx| {
+            batch_verify::<Affine, BaseSponge, ScalarSponge>(&ctx.group_map, &[(&ctx.index, proof)])
+        })
+    }
+
+    #[test]
+    fn test_fuzz_harness_on_corrupted_proofs() {
+        let seed = seed_proof();
+        let proof: ProverProof<Affine> = rmp_serde::from_slice(&seed).unwrap();
+        assert_eq!(verify(&proof), Ok(()));
+
+        // truncated proofs, and proofs with a flipped byte
+        for len in (0..seed.len()).step_by(97) {
+            fuzz_verify(&seed[..len]);
+            fuzz_verifier_index_deserialization(&seed[..len]);
+        }
+        for i in (0..seed.len()).step_by(13) {
+            let mut data = seed.clone();
+            data[i] ^= 0x5a;
+            fuzz_proof_deserialization(&data);
+            fuzz_verify(&data);
+        }
+    }
+
+    #[test]
+    fn test_malformed_proofs_are_rejected() {
+        let proof: ProverProof<Affine> = rmp_serde::from_slice(&seed_proof()).unwrap();
+        let is_malformed = |proof: &ProverProof<Affine>| {
+            matches!(verify(proof), Err(VerifyError::MalformedProof(_)))
+        };
+
+        // a long opening proof would make the verifier allocate the coefficients of its challenge polynomial
+        let mut long = proof.clone();
+        let round = long.proof.lr[0];
+        long.proof.lr.extend(vec![round; 40]);
+        assert!(is_malformed(&long));
+
+        let mut short = proof.clone();
+        short.proof.lr.pop();
+        assert!(is_malformed(&short));
+
+        let mut prev = proof.clone();
+        prev.prev_challenges
+            .push((vec![Fp::one(); 64], proof.commitments.z_comm.clone()));
+        assert!(is_malformed(&prev));
+
+        // combining the evaluations at both points requires them to have the same number of chunks
+        let mut evals = proof.clone();
+        evals.evals[1].w[3].push(Fp::one());
+        assert!(is_malformed(&evals));
+        let mut evals = proof;
+        evals.evals[0].z.clear();
+        assert!(is_malformed(&evals));
+    }
+}
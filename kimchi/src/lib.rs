@@ -4,13 +4,23 @@
 extern crate num_derive;
 
 pub mod alphas;
+pub mod audit;
 pub mod bench;
 pub mod circuits;
+pub mod debug;
+pub mod distributed;
 pub mod error;
+pub mod fuzz;
 pub mod linearization;
+pub mod metrics;
+pub mod pasta;
 pub mod plonk_sponge;
 pub mod prover;
 pub mod prover_index;
+pub mod registry;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod size;
 pub mod verifier;
 pub mod verifier_index;
 
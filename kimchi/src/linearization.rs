@@ -2,16 +2,20 @@
 
 use crate::alphas::Alphas;
 use crate::circuits::argument::{Argument, ArgumentType};
+use crate::circuits::polynomials::boolean::Boolean;
+use crate::circuits::polynomials::boundary::{self, BoundaryConstraint};
 use crate::circuits::polynomials::chacha::{ChaCha0, ChaCha1, ChaCha2, ChaChaFinal};
 use crate::circuits::polynomials::complete_add::CompleteAdd;
 use crate::circuits::polynomials::endomul_scalar::EndomulScalar;
 use crate::circuits::polynomials::endosclmul::EndosclMul;
+use crate::circuits::polynomials::linear_combination;
 use crate::circuits::polynomials::lookup;
 use crate::circuits::polynomials::permutation;
-use crate::circuits::polynomials::poseidon::Poseidon;
+use crate::circuits::polynomials::poseidon::{self, Poseidon};
 use crate::circuits::polynomials::varbasemul::VarbaseMul;
 use crate::circuits::{
     constraints::LookupConstraintSystem,
+    custom::CustomGates,
     expr::{Column, ConstantExpr, Expr, Linearization, PolishToken},
     gate::GateType,
     wires::*,
@@ -22,19 +26,28 @@ use ark_poly::Radix2EvaluationDomain as D;
 pub fn constraints_expr<F: FftField + SquareRootField>(
     domain: D<F>,
     chacha: bool,
+    linear_combination: bool,
+    partial_rounds: bool,
+    boolean: bool,
     lookup_constraint_system: &Option<LookupConstraintSystem<F>>,
+    custom_gates: &CustomGates<F>,
+    boundary: &[BoundaryConstraint<F>],
 ) -> (Expr<ConstantExpr<F>>, Alphas<F>) {
     // register powers of alpha so that we don't reuse them across mutually inclusive constraints
     let mut powers_of_alpha = Alphas::<F>::default();
 
     // gates
-    let highest_constraints = VarbaseMul::<F>::CONSTRAINTS;
+    let highest_constraints =
+        std::cmp::max(VarbaseMul::<F>::CONSTRAINTS, custom_gates.max_constraints());
     powers_of_alpha.register(
         ArgumentType::Gate(GateType::VarBaseMul),
         highest_constraints,
     );
 
     let mut expr = Poseidon::combined_constraints(&powers_of_alpha);
+    if partial_rounds {
+        expr += poseidon::combined_partial_round_corrections(&powers_of_alpha);
+    }
     expr += VarbaseMul::combined_constraints(&powers_of_alpha);
     expr += CompleteAdd::combined_constraints(&powers_of_alpha);
     expr += EndosclMul::combined_constraints(&powers_of_alpha);
@@ -47,19 +60,38 @@ pub fn constraints_expr<F: FftField + SquareRootField>(
         expr += ChaChaFinal::combined_constraints(&powers_of_alpha);
     }
 
+    if linear_combination {
+        expr += linear_combination::combined_constraints(&powers_of_alpha);
+    }
+
+    if boolean {
+        expr += Boolean::combined_constraints(&powers_of_alpha);
+    }
+
+    for custom in custom_gates.combined_constraints(&powers_of_alpha) {
+        expr += custom;
+    }
+
     // permutation
     powers_of_alpha.register(ArgumentType::Permutation, permutation::CONSTRAINTS);
 
     // lookup
     if let Some(lcs) = lookup_constraint_system.as_ref() {
-        powers_of_alpha.register(ArgumentType::Lookup, lookup::CONSTRAINTS);
-        let alphas = powers_of_alpha.get_exponents(ArgumentType::Lookup, lookup::CONSTRAINTS);
+        let constraints = lookup::constraints(lcs, domain);
+        let num_constraints = constraints.len() as u32;
+        powers_of_alpha.register(ArgumentType::Lookup, num_constraints);
+        let alphas = powers_of_alpha.get_exponents(ArgumentType::Lookup, num_constraints);
 
-        let constraints = lookup::constraints(&lcs.dummy_lookup_values[0], domain);
         let combined = Expr::combine_constraints(alphas, constraints);
         expr += combined;
     }
 
+    // boundary constraints
+    if !boundary.is_empty() {
+        powers_of_alpha.register(ArgumentType::Boundary, boundary.len() as u32);
+        expr += boundary::combined_constraints(boundary, &powers_of_alpha);
+    }
+
     // return the expression
     (expr, powers_of_alpha)
 }
@@ -78,6 +110,9 @@ pub fn linearization_columns<F: FftField + SquareRootField>(
             for i in 0..(lcs.max_lookups_per_row + 1) {
                 h.insert(LookupSorted(i));
             }
+            if lcs.runtime_table.is_some() {
+                h.insert(LookupRuntimeTable);
+            }
         }
     }
     h.insert(Z);
@@ -91,11 +126,25 @@ pub fn linearization_columns<F: FftField + SquareRootField>(
 pub fn expr_linearization<F: FftField + SquareRootField>(
     domain: D<F>,
     chacha: bool,
+    linear_combination: bool,
+    partial_rounds: bool,
+    boolean: bool,
     lookup_constraint_system: &Option<LookupConstraintSystem<F>>,
+    custom_gates: &CustomGates<F>,
+    boundary: &[BoundaryConstraint<F>],
 ) -> (Linearization<Vec<PolishToken<F>>>, Alphas<F>) {
     let evaluated_cols = linearization_columns::<F>(lookup_constraint_system);
 
-    let (expr, powers_of_alpha) = constraints_expr(domain, chacha, lookup_constraint_system);
+    let (expr, powers_of_alpha) = constraints_expr(
+        domain,
+        chacha,
+        linear_combination,
+        partial_rounds,
+        boolean,
+        lookup_constraint_system,
+        custom_gates,
+        boundary,
+    );
 
     let linearization = expr
         .linearize(evaluated_cols)
@@ -0,0 +1,96 @@
+//! This module lists the metrics reported by the prover and the verifier
+//! to the recorder installed with [o1_utils::metrics::set_recorder], if any.
+//!
+//! | name | kind | labels |
+//! |------|------|--------|
+//! | [PROOFS_CREATED] | counter | |
+//! | [PROOF_CREATION_FAILURES] | counter | `reason` ([ProofError::reason]) |
+//! | [PROVER_PHASE_SECONDS] | histogram | `phase` |
+//! | [PROOFS_VERIFIED] | counter | |
+//! | [VERIFICATION_FAILURES] | counter | `reason` ([VerifyError::reason]) |
+//!
+//! The sizes of the multi-scalar multiplications are reported by [commitment_dlog::msm::MSM_SIZE].
+//!
+//! The durations of the phases of a single proof are also returned as [ProverMetrics]
+//! by [ProverProof::create_with_metrics](crate::prover::ProverProof::create_with_metrics),
+//! whether a recorder is installed or not.
+
+use crate::error::{ProofError, VerifyError};
+use o1_utils::metrics::{counter, peak_memory, reset_peak_memory, Phases};
+use std::time::Duration;
+
+/// The number of proofs created.
+pub const PROOFS_CREATED: &str = "kimchi_proofs_created_total";
+
+/// The number of proofs that could not be created.
+pub const PROOF_CREATION_FAILURES: &str = "kimchi_proof_creation_failures_total";
+
+/// The duration of each phase of the prover, in seconds:
+/// `witness` (commitment to the witness), `interpolation` (of the witness), `lookup`, `permutation`,
+/// `quotient` (computation and commitment of the quotient), `evaluations` and `opening`.
+pub const PROVER_PHASE_SECONDS: &str = "kimchi_prover_phase_seconds";
+
+/// The number of proofs verified successfully.
+pub const PROOFS_VERIFIED: &str = "kimchi_proofs_verified_total";
+
+/// The number of batches rejected by the verifier.
+pub const VERIFICATION_FAILURES: &str = "kimchi_verification_failures_total";
+
+/// The measures of the creation of a proof.
+#[derive(Clone, Debug, Default)]
+pub struct ProverMetrics {
+    /// the phases of the prover (see [PROVER_PHASE_SECONDS]) and their durations, in order
+    pub phases: Vec<(&'static str, Duration)>,
+    /// the peak of the memory allocated by the process while the proof was created, in bytes,
+    /// if [o1_utils::metrics::PeakAllocator] is the global allocator
+    pub peak_memory: Option<usize>,
+}
+
+impl ProverMetrics {
+    /// Returns the duration of the phase `name`, if the prover went through it.
+    pub fn phase(&self, name: &str) -> Option<Duration> {
+        self.phases
+            .iter()
+            .find(|(phase, _)| *phase == name)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// Returns the total duration of the phases.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+/// Starts measuring the phases of a proof, collecting them if `metrics` are requested.
+pub(crate) fn start_proof(metrics: bool) -> Phases {
+    if metrics {
+        reset_peak_memory();
+        Phases::collect(PROVER_PHASE_SECONDS)
+    } else {
+        Phases::start(PROVER_PHASE_SECONDS)
+    }
+}
+
+/// Returns the [ProverMetrics] of a proof whose phases were measured by `phases`.
+pub(crate) fn end_proof(phases: Phases) -> ProverMetrics {
+    ProverMetrics {
+        phases: phases.durations().unwrap_or_default(),
+        peak_memory: peak_memory(),
+    }
+}
+
+/// Reports the outcome of a proof creation.
+pub(crate) fn record_proof<T>(res: &Result<T, ProofError>) {
+    match res {
+        Ok(_) => counter(PROOFS_CREATED, &[], 1),
+        Err(e) => counter(PROOF_CREATION_FAILURES, &[("reason", e.reason())], 1),
+    }
+}
+
+/// Reports the outcome of the verification of a batch of `proofs` proofs.
+pub(crate) fn record_verification<T>(proofs: usize, res: &Result<T, VerifyError>) {
+    match res {
+        Ok(_) => counter(PROOFS_VERIFIED, &[], proofs as u64),
+        Err(e) => counter(VERIFICATION_FAILURES, &[("reason", e.reason())], 1),
+    }
+}
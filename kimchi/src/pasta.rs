@@ -0,0 +1,80 @@
+//! This module instantiates the proof system over the two curves of the Pasta cycle.
+//!
+//! The commitments are discrete-logarithm commitments over a curve whose scalar field is the field of the circuit,
+//! and the Fiat-Shamir transcript goes through a Poseidon sponge over each field of the curve:
+//! [PastaCurve] gives the sponge parameters and the endomorphism coefficient that go with each curve,
+//! which are easy to swap by mistake (the Fr-sponge of Vesta is the Fq-sponge of Pallas, and conversely).
+
+use crate::{prover::ProverProof, prover_index::ProverIndex, verifier_index::VerifierIndex};
+use commitment_dlog::{commitment::CommitmentCurve, srs::endos};
+use mina_curves::pasta::{
+    pallas::{self, PallasParameters},
+    vesta::{self, VestaParameters},
+    Fp, Fq,
+};
+use oracle::{
+    poseidon::{ArithmeticSpongeParams, PlonkSpongeConstantsKimchi},
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+/// A curve of the Pasta cycle, with the parameters of the proofs over it.
+pub trait PastaCurve: CommitmentCurve {
+    /// The parameters of the Poseidon sponge over the scalar field (see [crate::plonk_sponge::FrSponge]).
+    fn fr_sponge_params() -> ArithmeticSpongeParams<Self::ScalarField>;
+
+    /// The parameters of the Poseidon sponge over the base field (see [oracle::FqSponge]).
+    fn fq_sponge_params() -> ArithmeticSpongeParams<Self::BaseField>;
+
+    /// The endomorphism coefficient of the scalar field, given to [ProverIndex::create].
+    fn endo_q() -> Self::ScalarField;
+}
+
+impl PastaCurve for vesta::Affine {
+    fn fr_sponge_params() -> ArithmeticSpongeParams<Fp> {
+        oracle::pasta::fp_kimchi::params()
+    }
+
+    fn fq_sponge_params() -> ArithmeticSpongeParams<Fq> {
+        oracle::pasta::fq_kimchi::params()
+    }
+
+    fn endo_q() -> Fp {
+        endos::<pallas::Affine>().0
+    }
+}
+
+impl PastaCurve for pallas::Affine {
+    fn fr_sponge_params() -> ArithmeticSpongeParams<Fq> {
+        oracle::pasta::fq_kimchi::params()
+    }
+
+    fn fq_sponge_params() -> ArithmeticSpongeParams<Fp> {
+        oracle::pasta::fp_kimchi::params()
+    }
+
+    fn endo_q() -> Fq {
+        endos::<vesta::Affine>().0
+    }
+}
+
+/// The Fq-sponge of the proofs over Vesta.
+pub type VestaBaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+/// The Fr-sponge of the proofs over Vesta.
+pub type VestaScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+/// A proof over Vesta, of a circuit over [Fp].
+pub type VestaProof = ProverProof<vesta::Affine>;
+/// A prover index over Vesta.
+pub type VestaProverIndex = ProverIndex<vesta::Affine>;
+/// A verifier index over Vesta.
+pub type VestaVerifierIndex = VerifierIndex<vesta::Affine>;
+
+/// The Fq-sponge of the proofs over Pallas.
+pub type PallasBaseSponge = DefaultFqSponge<PallasParameters, PlonkSpongeConstantsKimchi>;
+/// The Fr-sponge of the proofs over Pallas.
+pub type PallasScalarSponge = DefaultFrSponge<Fq, PlonkSpongeConstantsKimchi>;
+/// A proof over Pallas, of a circuit over [Fq].
+pub type PallasProof = ProverProof<pallas::Affine>;
+/// A prover index over Pallas.
+pub type PallasProverIndex = ProverIndex<pallas::Affine>;
+/// A verifier index over Pallas.
+pub type PallasVerifierIndex = VerifierIndex<pallas::Affine>;
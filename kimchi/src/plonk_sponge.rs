@@ -1,5 +1,7 @@
 use crate::circuits::scalars::ProofEvaluations;
 use ark_ff::{Field, PrimeField};
+use blake2::Digest;
+use oracle::digest_sponge::DigestFrSponge;
 use oracle::poseidon::{
     ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi as SC, Sponge,
 };
@@ -42,35 +44,33 @@ impl<Fr: PrimeField> FrSponge<Fr> for DefaultFrSponge<Fr, SC> {
         self.last_squeezed = vec![];
         self.sponge.absorb(p);
 
-        let points = [
-            &e.z,
-            &e.generic_selector,
-            &e.poseidon_selector,
-            &e.w[0],
-            &e.w[1],
-            &e.w[2],
-            &e.w[3],
-            &e.w[4],
-            &e.w[5],
-            &e.w[6],
-            &e.w[7],
-            &e.w[8],
-            &e.w[9],
-            &e.w[10],
-            &e.w[11],
-            &e.w[12],
-            &e.w[13],
-            &e.w[14],
-            &e.s[0],
-            &e.s[1],
-            &e.s[2],
-            &e.s[3],
-            &e.s[4],
-            &e.s[5],
-        ];
-
-        for p in &points {
+        for p in e.opened() {
             self.sponge.absorb(p);
         }
     }
 }
+
+impl<Fr: PrimeField, D: Digest + Clone> FrSponge<Fr> for DigestFrSponge<Fr, D> {
+    fn new(_params: ArithmeticSpongeParams<Fr>) -> Self {
+        DigestFrSponge::new()
+    }
+
+    fn absorb(&mut self, x: &Fr) {
+        self.sponge.absorb_field(x);
+    }
+
+    fn challenge(&mut self) -> ScalarChallenge<Fr> {
+        ScalarChallenge(self.sponge.squeeze_challenge())
+    }
+
+    fn absorb_evaluations(&mut self, p: &[Fr], e: &ProofEvaluations<Vec<Fr>>) {
+        for x in p {
+            self.sponge.absorb_field(x);
+        }
+        for p in e.opened() {
+            for x in p.iter() {
+                self.sponge.absorb_field(x);
+            }
+        }
+    }
+}
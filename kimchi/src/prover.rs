@@ -3,27 +3,35 @@
 use crate::{
     circuits::{
         argument::{Argument, ArgumentType},
-        constraints::{LookupConstraintSystem, ZK_ROWS},
-        expr::{l0_1, Constants, Environment, LookupEnvironment},
+        check::counterexample,
+        constraints::{ConstraintSystem, LookupConstraintSystem, ZK_ROWS},
+        expr::{l0_1, Constants, Environment, LookupEnvironment, E},
+        fft,
         gate::{combine_table_entry, GateType, LookupsUsed},
+        polynomial::WitnessOverDomains,
         polynomials::{
+            boolean::Boolean,
+            boundary,
             chacha::{ChaCha0, ChaCha1, ChaCha2, ChaChaFinal},
             complete_add::CompleteAdd,
             endomul_scalar::EndomulScalar,
             endosclmul::EndosclMul,
-            generic, lookup, permutation,
-            poseidon::Poseidon,
+            generic, linear_combination, lookup, permutation,
+            poseidon::{self, Poseidon},
+            runtime_tables::{self, RuntimeTable},
             varbasemul::VarbaseMul,
         },
         scalars::{LookupEvaluations, ProofEvaluations},
-        wires::{COLUMNS, PERMUTS},
+        wires::COLUMNS,
+        witness::{scrub, Witness},
     },
     error::{ProofError, Result},
+    metrics::{self, ProverMetrics},
     plonk_sponge::FrSponge,
-    prover_index::ProverIndex,
+    prover_index::{quotient_chunks, ProverIndex},
 };
 use ark_ec::AffineCurve;
-use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_ff::{FftField, Field, One, PrimeField, UniformRand, Zero};
 use ark_poly::{
     univariate::DensePolynomial, Evaluations, Polynomial, Radix2EvaluationDomain as D, UVPolynomial,
 };
@@ -32,22 +40,51 @@ use commitment_dlog::{
     commitment::{b_poly_coefficients, CommitmentCurve, PolyComm},
     evaluation_proof::OpeningProof,
 };
+use groupmap::GroupMap;
 use itertools::Itertools;
 use lookup::CombinedEntry;
 use o1_utils::ExtendedDensePolynomial;
-use oracle::{sponge::ScalarChallenge, FqSponge};
-use std::collections::HashMap;
+use oracle::{poseidon::ArithmeticSpongeParams, sponge::ScalarChallenge, FqSponge};
+use rand::{CryptoRng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_with::serde_as;
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
 
 type Fr<G> = <G as AffineCurve>::ScalarField;
 type Fq<G> = <G as AffineCurve>::BaseField;
 
-#[derive(Clone)]
+/// Creates the Fq-Sponge of the transcript of a proof bound to an application `message`
+/// (see [ProverProof::create_with_message]).
+/// The sponge absorbs the length of the message, then the message in chunks of as many bytes as fit in a field element,
+/// each read as a little-endian field element, so that distinct messages are absorbed differently.
+pub fn message_transcript<Fq, G, Fr, EFqSponge>(
+    params: ArithmeticSpongeParams<Fq>,
+    message: &[u8],
+) -> EFqSponge
+where
+    Fq: PrimeField,
+    EFqSponge: FqSponge<Fq, G, Fr>,
+{
+    let chunk_bytes = (Fq::size_in_bits() - 1) / 8;
+    let mut sponge = EFqSponge::new(params);
+    let mut elements = vec![Fq::from(message.len() as u64)];
+    elements.extend(message.chunks(chunk_bytes).map(Fq::from_le_bytes_mod_order));
+    sponge.absorb_fq(&elements);
+    sponge
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
 pub struct LookupCommitments<G: AffineCurve> {
     pub sorted: Vec<PolyComm<G>>,
     pub aggreg: PolyComm<G>,
+    /// The commitment to the second column of the runtime table, if the circuit looks up one
+    #[serde(default)]
+    pub runtime: Option<PolyComm<G>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
 pub struct ProverCommitments<G: AffineCurve> {
     /// The commitments to the witness (execution trace)
     pub w_comm: [PolyComm<G>; COLUMNS],
@@ -59,7 +96,9 @@ pub struct ProverCommitments<G: AffineCurve> {
     pub lookup: Option<LookupCommitments<G>>,
 }
 
-#[derive(Clone)]
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
 pub struct ProverProof<G: AffineCurve> {
     /// All the polynomial commitments required in the proof
     pub commitments: ProverCommitments<G>,
@@ -69,15 +108,19 @@ pub struct ProverProof<G: AffineCurve> {
 
     /// Two evaluations over a number of committed polynomials
     // TODO(mimoo): that really should be a type Evals { z: PE, zw: PE }
+    #[serde_as(as = "[ProofEvaluations<Vec<o1_utils::serialization::SerdeAs>>; 2]")]
     pub evals: [ProofEvaluations<Vec<Fr<G>>>; 2],
 
     /// Required evaluation for [Maller's optimization](https://o1-labs.github.io/mina-book/crypto/plonk/maller_15.html#the-evaluation-of-l)
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub ft_eval1: Fr<G>,
 
     /// The public input
+    #[serde_as(as = "Vec<o1_utils::serialization::SerdeAs>")]
     pub public: Vec<Fr<G>>,
 
     /// The challenges underlying the optional polynomials folded into the proof
+    #[serde_as(as = "Vec<(Vec<o1_utils::serialization::SerdeAs>, _)>")]
     pub prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
 }
 
@@ -86,72 +129,300 @@ where
     G::BaseField: PrimeField,
 {
     /// This function constructs prover's zk-proof from the witness & the ProverIndex against SRS instance
-    ///     witness: computation witness
+    ///     witness: computation witness, overwritten with zeros once the proof is created (see [Witness])
     ///     index: ProverIndex
     ///     RETURN: prover's zk-proof
     pub fn create<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>, EFrSponge: FrSponge<Fr<G>>>(
         group_map: &G::Map,
-        mut witness: [Vec<Fr<G>>; COLUMNS],
+        witness: [Vec<Fr<G>>; COLUMNS],
+        index: &ProverIndex<G>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+    ) -> Result<Self> {
+        let constraints = GateConstraints::new(index);
+        Self::create_inner::<EFqSponge, EFrSponge, _>(
+            group_map,
+            witness,
+            &[],
+            index,
+            &constraints,
+            prev_challenges,
+            None,
+            None,
+            &mut rand::rngs::OsRng,
+        )
+        .map(|(proof, _)| proof)
+    }
+
+    /// Same as [ProverProof::create], but the randomness of the proof is drawn from `rng` instead of the operating system.
+    /// With a seeded `rng`, the proof is reproducible (see the `conformance` crate).
+    ///
+    /// A proof cannot be rerandomized without the witness:
+    /// the challenges of the transcript are derived from the commitments,
+    /// so that re-blinded commitments require new evaluations and a new opening proof, which only a prover can compute.
+    /// An unlinkable proof of the same statement is a new proof, created from the witness with fresh randomness
+    /// (as long as the index hides the witness, see [ProverIndex::set_zk]).
+    pub fn create_with_rng<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        index: &ProverIndex<G>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+        rng: &mut RNG,
+    ) -> Result<Self> {
+        let constraints = GateConstraints::new(index);
+        Self::create_inner::<EFqSponge, EFrSponge, RNG>(
+            group_map,
+            witness,
+            &[],
+            index,
+            &constraints,
+            prev_challenges,
+            None,
+            None,
+            rng,
+        )
+        .map(|(proof, _)| proof)
+    }
+
+    /// Same as [ProverProof::create], for a circuit that looks up a runtime table:
+    /// `runtime_tables` holds the second column of the table for this proof
+    /// (see [runtime_tables](crate::circuits::polynomials::runtime_tables)).
+    pub fn create_with_runtime_tables<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        runtime_tables: &[RuntimeTable<Fr<G>>],
+        index: &ProverIndex<G>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+    ) -> Result<Self> {
+        let constraints = GateConstraints::new(index);
+        Self::create_inner::<EFqSponge, EFrSponge, _>(
+            group_map,
+            witness,
+            runtime_tables,
+            index,
+            &constraints,
+            prev_challenges,
+            None,
+            None,
+            &mut rand::rngs::OsRng,
+        )
+        .map(|(proof, _)| proof)
+    }
+
+    /// Same as [ProverProof::create], but also returns how long each phase of the prover took
+    /// and the peak of the memory allocated meanwhile (see [ProverMetrics]).
+    pub fn create_with_metrics<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        index: &ProverIndex<G>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+    ) -> Result<(Self, ProverMetrics)> {
+        let constraints = GateConstraints::new(index);
+        let mut prover_metrics = ProverMetrics::default();
+        let (proof, _) = Self::create_inner::<EFqSponge, EFrSponge, _>(
+            group_map,
+            witness,
+            &[],
+            index,
+            &constraints,
+            prev_challenges,
+            None,
+            Some(&mut prover_metrics),
+            &mut rand::rngs::OsRng,
+        )?;
+        Ok((proof, prover_metrics))
+    }
+
+    /// Same as [ProverProof::create], for a proof that is one of several statements proven in one session:
+    /// the Fiat-Shamir transcript of the proof starts from the state of `transcript` instead of an empty sponge.
+    /// Returns the proof along with the state of the transcript at the end of the proof
+    /// (see [ProverProof::conclude_transcript]), from which the next proof of the session should start,
+    /// so that the proofs are bound together.
+    /// The verifier replays the session with [crate::verifier::verify_in_transcript].
+    pub fn create_in_transcript<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        index: &ProverIndex<G>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+        mut transcript: EFqSponge,
+    ) -> Result<(Self, EFqSponge)> {
+        let constraints = GateConstraints::new(index);
+        let (proof, _) = Self::create_inner::<EFqSponge, EFrSponge, _>(
+            group_map,
+            witness,
+            &[],
+            index,
+            &constraints,
+            prev_challenges,
+            Some(&mut transcript),
+            None,
+            &mut rand::rngs::OsRng,
+        )?;
+        Ok((proof, transcript))
+    }
+
+    /// Same as [ProverProof::create], for a proof bound to the application `message` (for example the hash of a transaction):
+    /// the transcript of the proof starts with the message (see [message_transcript]),
+    /// so that the proof only verifies with the same message (see [crate::verifier::verify_with_message])
+    /// and cannot be replayed in another context.
+    pub fn create_with_message<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
         index: &ProverIndex<G>,
         prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+        message: &[u8],
     ) -> Result<Self> {
+        let transcript = message_transcript::<Fq<G>, G, Fr<G>, EFqSponge>(
+            index.fq_sponge_params.clone(),
+            message,
+        );
+        Self::create_in_transcript::<EFqSponge, EFrSponge>(
+            group_map,
+            witness,
+            index,
+            prev_challenges,
+            transcript,
+        )
+        .map(|(proof, _)| proof)
+    }
+
+    /// Creates the proof, and also returns the committed polynomials (see [testing::ProverPolynomials]).
+    /// If a `transcript` is given, the proof starts from its state, and it is replaced by the state at the end of the proof.
+    /// If `prover_metrics` are given, they are filled in with the measures of the proof.
+    /// The outcome is reported to the metrics (see [crate::metrics]).
+    fn create_inner<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        runtime_tables: &[RuntimeTable<Fr<G>>],
+        index: &ProverIndex<G>,
+        constraints: &GateConstraints<Fr<G>>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+        transcript: Option<&mut EFqSponge>,
+        prover_metrics: Option<&mut ProverMetrics>,
+        rng: &mut RNG,
+    ) -> Result<(Self, testing::ProverPolynomials<G>)> {
+        let res = Self::create_unrecorded::<EFqSponge, EFrSponge, RNG>(
+            group_map,
+            witness,
+            runtime_tables,
+            index,
+            constraints,
+            prev_challenges,
+            transcript,
+            prover_metrics,
+            rng,
+        );
+        metrics::record_proof(&res);
+        res
+    }
+
+    fn create_unrecorded<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    >(
+        group_map: &G::Map,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        runtime_tables: &[RuntimeTable<Fr<G>>],
+        index: &ProverIndex<G>,
+        constraints: &GateConstraints<Fr<G>>,
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+        transcript: Option<&mut EFqSponge>,
+        prover_metrics: Option<&mut ProverMetrics>,
+        rng: &mut RNG,
+    ) -> Result<(Self, testing::ProverPolynomials<G>)> {
+        let mut phases = metrics::start_proof(prover_metrics.is_some());
         let d1_size = index.cs.domain.d1.size as usize;
-        // TODO: rng should be passed as arg
-        let rng = &mut rand::rngs::OsRng;
 
-        // double-check the witness
-        if cfg!(test) {
-            let public = witness[0][0..index.cs.public].to_vec();
-            index
-                .cs
-                .verify(&witness, &public)
-                .expect("incorrect witness");
+        // the witness is scrubbed when dropped, including on early returns
+        let mut witness = Witness::new(witness);
+
+        // the commitments to the witness are computed from its evaluations
+        if index.srs.lagrange_basis_chunks(d1_size).is_none() {
+            return Err(ProofError::MissingLagrangeBasis { domain: d1_size });
         }
 
-        //~ 1. Ensure we have room in the witness for the zero-knowledge rows.
+        //~ 1. Ensure the witness has the shape of the circuit:
+        //~    its columns all have the same length, and their first rows hold the public input.
+        //~    Ensure we have room in the witness for the zero-knowledge rows.
         //~    We currently expect the witness not to be of the same length as the domain,
         //~    but instead be of the length of the (smaller) circuit.
         //~    If we cannot add `ZK_ROWS` rows to the columns of the witness before reaching
         //~    the size of the domain, abort.
-        let length_witness = witness[0].len();
-        let length_padding = d1_size
-            .checked_sub(length_witness)
-            .ok_or(ProofError::NoRoomForZkInWitness)?;
-        if length_padding < ZK_ROWS as usize {
-            return Err(ProofError::NoRoomForZkInWitness);
-        }
+        let length_padding = witness_padding(&witness, &index.cs)?;
 
-        //~ 2. Pad the witness columns with Zero gates to make them the same length as the domain.
-        //~    Then, randomize the last `ZK_ROWS` of each columns.
-        for w in &mut witness {
-            if w.len() != length_witness {
-                return Err(ProofError::WitnessCsInconsistent);
+        // double-check the witness
+        if cfg!(test) {
+            let public = witness[0][0..index.cs.public].to_vec();
+            if index.cs.verify(&witness, &public).is_err() {
+                return Err(unsatisfied(&witness, &index.cs, &public));
             }
+        }
 
+        //~ 2. Pad the witness columns with Zero gates to make them the same length as the domain.
+        //~    Then, randomize the last `ZK_ROWS` of each columns (unless the index disables zero-knowledge).
+        for w in witness.iter_mut() {
             // padding
             w.extend(std::iter::repeat(Fr::<G>::zero()).take(length_padding));
 
             // zk-rows
-            for row in w.iter_mut().rev().take(ZK_ROWS as usize) {
-                *row = Fr::<G>::rand(rng);
+            if index.zk {
+                for row in w.iter_mut().rev().take(ZK_ROWS as usize) {
+                    *row = Fr::<G>::rand(rng);
+                }
             }
         }
 
         //~ 3. Setup the Fq-Sponge.
-        let mut fq_sponge = EFqSponge::new(index.fq_sponge_params.clone());
+        //~    (When the proof is part of a session, the Fq-Sponge is the transcript of the session instead.)
+        let mut fq_sponge = match &transcript {
+            Some(transcript) => EFqSponge::clone(transcript),
+            None => EFqSponge::new(index.fq_sponge_params.clone()),
+        };
+        //~    Depending on the transcript version of the index, absorb the digest of the verifier index
+        //~    and the public input (the first `public_input_size` cells of the first column of the witness)
+        //~    with the Fq-Sponge, so that the proof is bound to its circuit and to its statement.
+        let public = witness[0][0..index.cs.public].to_vec();
+        index.transcript_version.absorb_header::<G, _, _>(
+            &mut fq_sponge,
+            || index.verifier_index_digest(),
+            &public,
+        );
 
         //~ 4. Compute the negated public input polynomial as
         //~    the polynomial that evaluates to $-p_i$ for the first `public_input_size` values of the domain,
         //~    and $0$ for the rest.
-        let public = witness[0][0..index.cs.public].to_vec();
-        let public_poly = -Evaluations::<Fr<G>, D<Fr<G>>>::from_vec_and_domain(
-            public.clone(),
+        let public_evals = Evaluations::<Fr<G>, D<Fr<G>>>::from_vec_and_domain(
+            public.iter().map(|p| -*p).collect(),
             index.cs.domain.d1,
-        )
-        .interpolate();
+        );
 
         //~ 5. Commit (non-hidding) to the negated public input polynomial. **TODO: seems unecessary**
-        let public_comm = index.srs.commit_non_hiding(&public_poly, None);
+        //~    As for the witness, the commitment is computed from the evaluations.
+        let public_comm =
+            index
+                .srs
+                .commit_evaluations_non_hiding(index.cs.domain.d1, &public_evals, None);
+        let public_poly = public_evals.interpolate();
 
         //~ 6. Absorb the public polynomial with the Fq-Sponge. **TODO: seems unecessary**
         fq_sponge.absorb_g(&public_comm.unshifted);
@@ -160,31 +431,58 @@ where
         //~    Note: since the witness is in evaluation form,
         //~    we can use the `commit_evaluation` optimization.
         let w_comm: [(PolyComm<G>, PolyComm<Fr<G>>); COLUMNS] = array_init(|i| {
-            let e = Evaluations::<Fr<G>, D<Fr<G>>>::from_vec_and_domain(
+            let mut e = Evaluations::<Fr<G>, D<Fr<G>>>::from_vec_and_domain(
                 witness[i].clone(),
                 index.cs.domain.d1,
             );
-            index
-                .srs
-                .commit_evaluations(index.cs.domain.d1, &e, None, rng)
+            let comm = index.mask(
+                index
+                    .srs
+                    .commit_evaluations_non_hiding(index.cs.domain.d1, &e, None),
+                rng,
+            );
+            scrub(&mut e);
+            comm
         });
 
         //~ 8. Absorb the witness commitments with the Fq-Sponge.
         w_comm
             .iter()
             .for_each(|c| fq_sponge.absorb_g(&c.0.unshifted));
+        phases.end("witness");
 
         //~ 9. Compute the witness polynomials by interpolating each `COLUMNS` of the witness.
-        //~    TODO: why not do this first, and the commit? Why commit from evaluation directly?
-        let witness_poly: [DensePolynomial<Fr<G>>; COLUMNS] = array_init(|i| {
-            Evaluations::<Fr<G>, D<Fr<G>>>::from_vec_and_domain(
-                witness[i].clone(),
-                index.cs.domain.d1,
-            )
-            .interpolate()
-        });
+        //~    The commitments do not need them: committing from the evaluations, with the Lagrange basis of the SRS,
+        //~    costs as much as committing from the coefficients.
+        let witness_poly: [DensePolynomial<Fr<G>>; COLUMNS] =
+            fft::interpolate_columns(&witness, index.cs.domain.d1);
+
+        phases.end("interpolation");
 
         //~ 10. TODO: lookup
+        //~     If the circuit looks up a runtime table, commit (hidding) to the second column of the table
+        //~     given by the prover, with zero-knowledge rows, and absorb the commitment with the Fq-Sponge.
+        let runtime_table = match index.cs.runtime_table() {
+            Some(info) => {
+                let evals =
+                    runtime_tables::evaluations(&info, runtime_tables, index.cs.domain.d1, rng)?;
+                let comm = index.mask(
+                    index
+                        .srs
+                        .commit_evaluations_non_hiding(index.cs.domain.d1, &evals, None),
+                    rng,
+                );
+                fq_sponge.absorb_g(&comm.0.unshifted);
+                let coeffs = evals.interpolate_by_ref();
+                let evals8 = coeffs.evaluate_over_domain_by_ref(index.cs.domain.d8);
+                Some((evals, coeffs, comm, evals8))
+            }
+            None => match runtime_tables.first() {
+                Some(table) => return Err(ProofError::UnknownRuntimeTable { id: table.id }),
+                None => None,
+            },
+        };
+
         let joint_combiner_ = {
             // TODO: how will the verifier circuit handle these kind of things? same with powers of alpha...
             let s = match index.cs.lookup_constraint_system.as_ref() {
@@ -204,6 +502,12 @@ where
         // TODO: that seems like an unecessary line
         let joint_combiner: Fr<G> = joint_combiner_.1;
 
+        // when the circuit looks up several tables, the ids of the tables follow the entries of the lookups
+        let table_id_combiner = match index.cs.lookup_constraint_system.as_ref() {
+            Some(lcs) if lcs.table_ids => joint_combiner.pow([lcs.max_joint_size as u64]),
+            _ => Fr::<G>::zero(),
+        };
+
         // TODO: Looking-up a tuple (f_0, f_1, ..., f_{m-1}) in a tuple of tables (T_0, ..., T_{m-1}) is
         // reduced to a single lookup
         // sum_i joint_combiner^i f_i
@@ -245,6 +549,17 @@ where
         // whether we should combine the scalars before the multi-exp or not, like computing
         // their average length or something like that.
 
+        // the entry of the table of the lookup argument on row `i`, combined with the joint combiner,
+        // where the runtime table adds its second column
+        let combined_table_entry = |lcs: &LookupConstraintSystem<Fr<G>>, i: usize| {
+            let row = lcs.lookup_tables8[0].iter().map(|e| &e.evals[8 * i]);
+            let entry = combine_table_entry(joint_combiner, row);
+            match &runtime_table {
+                Some((runtime, ..)) => entry + joint_combiner * runtime.evals[i],
+                None => entry,
+            }
+        };
+
         let dummy_lookup_value = {
             let x = match index.cs.lookup_constraint_system.as_ref() {
                 None => Fr::<G>::zero(),
@@ -253,16 +568,12 @@ where
             CombinedEntry(x)
         };
 
-        let (lookup_sorted, lookup_sorted_coeffs, lookup_sorted_comm, lookup_sorted8) =
+        let (lookup_sorted, mut lookup_sorted_coeffs, lookup_sorted_comm, mut lookup_sorted8) =
             match index.cs.lookup_constraint_system.as_ref() {
                 None => (None, None, None, None),
                 Some(lcs) => {
-                    let iter_lookup_table = || {
-                        (0..d1_size).map(|i| {
-                            let row = lcs.lookup_tables8[0].iter().map(|e| &e.evals[8 * i]);
-                            CombinedEntry(combine_table_entry(joint_combiner, row))
-                        })
-                    };
+                    let iter_lookup_table =
+                        || (0..d1_size).map(|i| CombinedEntry(combined_table_entry(lcs, i)));
 
                     // TODO: Once we switch to committing using lagrange commitments,
                     // `witness` will be consumed when we interpolate, so interpolation will
@@ -273,7 +584,7 @@ where
                         index.cs.domain.d1,
                         &index.cs.gates,
                         &witness,
-                        joint_combiner,
+                        (joint_combiner, table_id_combiner),
                     )?;
 
                     let lookup_sorted: Vec<_> = lookup_sorted
@@ -287,9 +598,14 @@ where
                     let comm: Vec<_> = lookup_sorted
                         .iter()
                         .map(|v| {
-                            index
-                                .srs
-                                .commit_evaluations(index.cs.domain.d1, v, None, rng)
+                            index.mask(
+                                index.srs.commit_evaluations_non_hiding(
+                                    index.cs.domain.d1,
+                                    v,
+                                    None,
+                                ),
+                                rng,
+                            )
                         })
                         .collect();
                     let coeffs : Vec<_> =
@@ -307,6 +623,8 @@ where
                 }
             };
 
+        phases.end("lookup");
+
         //~ 11. Sample $\beta$ with the Fq-Sponge.
         let beta = fq_sponge.challenge();
 
@@ -314,15 +632,12 @@ where
         let gamma = fq_sponge.challenge();
 
         //~ 13. TODO: lookup
-        let (lookup_aggreg_coeffs, lookup_aggreg_comm, lookup_aggreg8) =
+        let (mut lookup_aggreg_coeffs, lookup_aggreg_comm, mut lookup_aggreg8) =
             // compute lookup aggregation polynomial
             match (index.cs.lookup_constraint_system.as_ref(), lookup_sorted) {
                 (None, None) | (None, Some(_)) | (Some(_), None) => (None, None, None),
-                (Some(lcs), Some(lookup_sorted)) => {
-                    let iter_lookup_table = || (0..d1_size).map(|i| {
-                        let row = lcs.lookup_tables8[0].iter().map(|e| & e.evals[8 * i]);
-                        combine_table_entry(joint_combiner, row)
-                    });
+                (Some(lcs), Some(mut lookup_sorted)) => {
+                    let iter_lookup_table = || (0..d1_size).map(|i| combined_table_entry(lcs, i));
 
                     let aggreg =
                         lookup::aggregation::<_, Fr<G>, _>(
@@ -332,15 +647,17 @@ where
                             &index.cs.gates,
                             &witness,
                             joint_combiner,
+                            table_id_combiner,
                             beta, gamma,
                             &lookup_sorted,
                             rng)?;
+                    scrub(&mut lookup_sorted);
 
                     if aggreg.evals[d1_size - (ZK_ROWS as usize + 1)] != Fr::<G>::one() {
                         panic!("aggregation incorrect: {}", aggreg.evals[d1_size-(ZK_ROWS as usize + 1)]);
                     }
 
-                    let comm = index.srs.commit_evaluations(index.cs.domain.d1, &aggreg, None, rng);
+                    let comm = index.mask(index.srs.commit_evaluations_non_hiding(index.cs.domain.d1, &aggreg, None), rng);
                     fq_sponge.absorb_g(&comm.0.unshifted);
 
                     let coeffs = aggreg.interpolate();
@@ -352,15 +669,23 @@ where
                 },
             };
 
-        //~ 14. Compute the permutation aggregation polynomial $z$.
-        let z_poly = index.cs.perm_aggreg(&witness, &beta, &gamma, rng)?;
+        //~ 14. Compute the evaluations of the permutation aggregation polynomial $z$.
+        let z_evals = index.cs.perm_aggreg(&witness, &beta, &gamma, rng)?;
 
-        //~ 15. Commit (hidding) to the permutation aggregation polynomial $z$.
-        let z_comm = index.srs.commit(&z_poly, None, rng);
+        //~ 15. Commit (hidding) to the permutation aggregation polynomial $z$, from its evaluations.
+        let z_comm = index.mask(
+            index
+                .srs
+                .commit_evaluations_non_hiding(index.cs.domain.d1, &z_evals, None),
+            rng,
+        );
+        let z_poly = z_evals.interpolate();
 
         //~ 16. Absorb the permutation aggregation polynomial $z$ with the Fq-Sponge.
         fq_sponge.absorb_g(&z_comm.0.unshifted);
 
+        phases.end("permutation");
+
         //~ 17. Sample $\alpha'$ with the Fq-Sponge.
         let alpha_chal = ScalarChallenge(fq_sponge.challenge());
 
@@ -372,13 +697,19 @@ where
         all_alphas.instantiate(alpha);
 
         //~ 20. TODO: this is just an optimization, ignore?
-        let lagrange = index.cs.evaluate(&witness_poly, &z_poly);
+        let mut lagrange = index.cs.evaluate(&witness_poly, &z_poly);
 
         //~ 21. TODO: lookup
         let lookup_table_combined = index.cs.lookup_constraint_system.as_ref().map(|lcs| {
             let joint_table = &lcs.lookup_tables8[0];
             let mut res = joint_table[joint_table.len() - 1].clone();
-            for col in joint_table.iter().rev().skip(1) {
+            for (i, col) in joint_table.iter().enumerate().rev().skip(1) {
+                // the runtime table adds to the second column of the table
+                if i == 0 {
+                    if let Some((.., runtime8)) = &runtime_table {
+                        res += runtime8;
+                    }
+                }
                 res.evals.iter_mut().for_each(|e| *e *= joint_combiner);
                 res += col;
             }
@@ -397,47 +728,26 @@ where
                         sorted: lookup_sorted,
                         table: lookup_table_combined,
                         selectors: &lcs.lookup_selectors,
+                        runtime_selector: lcs.runtime_selector.as_ref(),
+                        runtime_table: runtime_table.as_ref().map(|(.., runtime8)| runtime8),
                     }
                 },
             );
 
         //~ 22. TODO: setup the env
-        let env = {
-            let mut index_evals = HashMap::new();
-            use GateType::*;
-            index_evals.insert(Poseidon, &index.cs.ps8);
-            index_evals.insert(CompleteAdd, &index.cs.complete_addl4);
-            index_evals.insert(VarBaseMul, &index.cs.mull8);
-            index_evals.insert(EndoMul, &index.cs.emull);
-            index_evals.insert(EndoMulScalar, &index.cs.endomul_scalar8);
-            [ChaCha0, ChaCha1, ChaCha2, ChaChaFinal]
-                .iter()
-                .enumerate()
-                .for_each(|(i, g)| {
-                    if let Some(c) = &index.cs.chacha8 {
-                        index_evals.insert(*g, &c[i]);
-                    }
-                });
-
-            Environment {
-                constants: Constants {
-                    alpha,
-                    beta,
-                    gamma,
-                    joint_combiner,
-                    endo_coefficient: index.cs.endo,
-                    mds: index.cs.fr_sponge_params.mds.clone(),
-                },
-                witness: &lagrange.d8.this.w,
-                coefficient: &index.cs.coefficients8,
-                vanishes_on_last_4_rows: &index.cs.vanishes_on_last_4_rows,
-                z: &lagrange.d8.this.z,
-                l0_1: l0_1(index.cs.domain.d1),
-                domain: index.cs.domain,
-                index: index_evals,
-                lookup: lookup_env,
-            }
-        };
+        let env = environment(
+            index,
+            &lagrange,
+            Constants {
+                alpha,
+                beta,
+                gamma,
+                joint_combiner,
+                endo_coefficient: index.cs.endo,
+                mds: index.cs.fr_sponge_params.mds.clone(),
+            },
+            lookup_env,
+        );
 
         //~ 23. Compute the quotient polynomial (the $t$ in $f = Z_H \cdot t$).
         //~     The quotient polynomial is computed by adding all these polynomials together:
@@ -446,7 +756,6 @@ where
         //~     - TODO: lookup
         //~     - the negated public polynomial
         //~     and by then dividing the resulting polynomial with the vanishing polynomial $Z_H$.
-        //~     TODO: specify the split of the permutation polynomial into perm and bnd?
         let quotient_poly = {
             // generic
             let alphas =
@@ -465,8 +774,7 @@ where
             }
 
             // complete addition
-            let add_constraint = CompleteAdd::combined_constraints(&all_alphas);
-            let add4 = add_constraint.evaluations(&env);
+            let add4 = constraints.complete_add.evaluations(&env);
             t4 += &add4;
 
             if cfg!(test) {
@@ -482,10 +790,7 @@ where
 
             // permutation
             let alphas = all_alphas.get_alphas(ArgumentType::Permutation, permutation::CONSTRAINTS);
-            let (perm, bnd) = index
-                .cs
-                .perm_quot(&lagrange, beta, gamma, &z_poly, alphas)?;
-            let mut t8 = perm;
+            let mut t8 = index.cs.perm_quot(&lagrange, beta, gamma, alphas);
 
             if cfg!(test) {
                 let (_, res) = t8
@@ -497,7 +802,7 @@ where
             }
 
             // scalar multiplication
-            let mul8 = VarbaseMul::combined_constraints(&all_alphas).evaluations(&env);
+            let mul8 = constraints.varbase_mul.evaluations(&env);
             t8 += &mul8;
 
             if cfg!(test) {
@@ -512,7 +817,7 @@ where
             drop(mul8);
 
             // endoscaling
-            let emul8 = EndosclMul::combined_constraints(&all_alphas).evaluations(&env);
+            let emul8 = constraints.endomul.evaluations(&env);
             t8 += &emul8;
 
             if cfg!(test) {
@@ -527,7 +832,7 @@ where
             drop(emul8);
 
             // endoscaling scalar computation
-            let emulscalar8 = EndomulScalar::combined_constraints(&all_alphas).evaluations(&env);
+            let emulscalar8 = constraints.endomul_scalar.evaluations(&env);
             t8 += &emulscalar8;
 
             if cfg!(test) {
@@ -542,7 +847,7 @@ where
             drop(emulscalar8);
 
             // poseidon
-            let pos8 = Poseidon::combined_constraints(&all_alphas).evaluations(&env);
+            let pos8 = constraints.poseidon.evaluations(&env);
             t8 += &pos8;
 
             if cfg!(test) {
@@ -557,17 +862,17 @@ where
             drop(pos8);
 
             // chacha
-            if index.cs.chacha8.as_ref().is_some() {
-                let chacha0 = ChaCha0::combined_constraints(&all_alphas).evaluations(&env);
+            if let Some(chacha) = &constraints.chacha {
+                let chacha0 = chacha[0].evaluations(&env);
                 t4 += &chacha0;
 
-                let chacha1 = ChaCha1::combined_constraints(&all_alphas).evaluations(&env);
+                let chacha1 = chacha[1].evaluations(&env);
                 t4 += &chacha1;
 
-                let chacha2 = ChaCha2::combined_constraints(&all_alphas).evaluations(&env);
+                let chacha2 = chacha[2].evaluations(&env);
                 t4 += &chacha2;
 
-                let chacha_final = ChaChaFinal::combined_constraints(&all_alphas).evaluations(&env);
+                let chacha_final = chacha[3].evaluations(&env);
                 t4 += &chacha_final;
 
                 if cfg!(test) {
@@ -601,14 +906,52 @@ where
                 }
             }
 
+            // linear combination
+            if let Some(linear_combination) = &constraints.linear_combination {
+                let lincomb8 = linear_combination.evaluations(&env);
+
+                if cfg!(test) {
+                    let (_, res) = lincomb8
+                        .clone()
+                        .interpolate()
+                        .divide_by_vanishing_poly(index.cs.domain.d1)
+                        .unwrap();
+                    assert!(res.is_zero());
+                }
+
+                if lincomb8.domain().size == t4.domain().size {
+                    t4 += &lincomb8;
+                } else {
+                    t8 += &lincomb8;
+                }
+            }
+
+            // boolean
+            if let Some(boolean) = &constraints.boolean {
+                let boolean4 = boolean.evaluations(&env);
+
+                if cfg!(test) {
+                    let (_, res) = boolean4
+                        .clone()
+                        .interpolate()
+                        .divide_by_vanishing_poly(index.cs.domain.d1)
+                        .unwrap();
+                    assert!(res.is_zero());
+                }
+
+                if boolean4.domain().size == t4.domain().size {
+                    t4 += &boolean4;
+                } else {
+                    t8 += &boolean4;
+                }
+            }
+
             // lookup
-            if let Some(lcs) = index.cs.lookup_constraint_system.as_ref() {
+            if let Some(lookup_constraints) = &constraints.lookup {
                 let lookup_alphas =
-                    all_alphas.get_alphas(ArgumentType::Lookup, lookup::CONSTRAINTS);
-                let constraints =
-                    lookup::constraints(&lcs.dummy_lookup_values[0], index.cs.domain.d1);
+                    all_alphas.get_alphas(ArgumentType::Lookup, lookup_constraints.len() as u32);
 
-                for (constraint, alpha_pow) in constraints.into_iter().zip_eq(lookup_alphas) {
+                for (constraint, alpha_pow) in lookup_constraints.iter().zip_eq(lookup_alphas) {
                     let mut eval = constraint.evaluations(&env);
                     eval.evals.iter_mut().for_each(|x| *x *= alpha_pow);
 
@@ -622,43 +965,98 @@ where
                 }
             }
 
+            // custom gates
+            for constraint in &constraints.custom {
+                let eval = constraint.evaluations(&env);
+
+                if cfg!(test) {
+                    let (_, res) = eval
+                        .clone()
+                        .interpolate()
+                        .divide_by_vanishing_poly(index.cs.domain.d1)
+                        .unwrap();
+                    assert!(res.is_zero());
+                }
+
+                if eval.domain().size == t4.domain().size {
+                    t4 += &eval;
+                } else if eval.domain().size == t8.domain().size {
+                    t8 += &eval;
+                } else {
+                    panic!("Bad evaluation")
+                }
+            }
+
+            // boundary constraints
+            if let Some(boundary) = &constraints.boundary {
+                let eval = boundary.evaluations(&env);
+
+                if cfg!(test) {
+                    let (_, res) = eval
+                        .clone()
+                        .interpolate()
+                        .divide_by_vanishing_poly(index.cs.domain.d1)
+                        .unwrap();
+                    assert!(res.is_zero());
+                }
+
+                if eval.domain().size == t4.domain().size {
+                    t4 += &eval;
+                } else {
+                    t8 += &eval;
+                }
+            }
+
             // public polynomial
             let mut f = t4.interpolate() + t8.interpolate();
             f += &public_poly;
 
             // divide contributions with vanishing polynomial
-            let (mut quotient, res) = f
+            let (quotient, res) = f
                 .divide_by_vanishing_poly(index.cs.domain.d1)
                 .ok_or(ProofError::Prover("division by vanishing polynomial"))?;
+            // the witness does not satisfy the circuit
             if !res.is_zero() {
+                return Err(unsatisfied(&witness, &index.cs, &public));
+            }
+
+            // a constraint of higher degree than the index supports would not fit in the commitment
+            if quotient.coeffs.len() > index.max_quot_size {
                 return Err(ProofError::Prover(
-                    "rest of division by vanishing polynomial",
+                    "the quotient polynomial is of higher degree than the index supports",
                 ));
             }
 
-            quotient += &bnd; // already divided by Z_H
             quotient
         };
 
-        //~ 24. commit (hiding) to the quotient polynomial $t$
+        //~ 24. commit (hiding) to the quotient polynomial $t$,
+        //~     in as many chunks as the degree bound of $t$ requires.
         //~     TODO: specify the dummies
         let t_comm = {
-            let (mut t_comm, mut omega_t) = index.srs.commit(&quotient_poly, None, rng);
+            let (mut t_comm, mut omega_t) =
+                index.mask(index.srs.commit_non_hiding(&quotient_poly, None), rng);
 
-            let expected_t_size = PERMUTS;
+            let expected_t_size = index.quotient_chunks();
             let dummies = expected_t_size - t_comm.unshifted.len();
             // Add `dummies` many hiding commitments to the 0 polynomial, since if the
             // number of commitments in `t_comm` is less than the max size, it means that
             // the higher degree coefficients of `t` are 0.
             for _ in 0..dummies {
                 use ark_ec::ProjectiveCurve;
-                let w = Fr::<G>::rand(rng);
+                let w = if index.zk {
+                    Fr::<G>::rand(rng)
+                } else {
+                    Fr::<G>::zero()
+                };
                 t_comm.unshifted.push(index.srs.h.mul(w).into_affine());
                 omega_t.unshifted.push(w);
             }
             (t_comm, omega_t)
         };
 
+        phases.end("quotient");
+
         //~ 25. Absorb the the commitment of the quotient polynomial with the Fq-Sponge.
         fq_sponge.absorb_g(&t_comm.0.unshifted);
 
@@ -677,13 +1075,11 @@ where
                 .as_ref()
                 .zip(lookup_sorted_coeffs.as_ref())
                 .zip(index.cs.lookup_constraint_system.as_ref())
-                .map(|((aggreg, sorted), lcs)| LookupEvaluations {
-                    aggreg: aggreg.eval(e, index.max_poly_size),
-                    sorted: sorted
-                        .iter()
-                        .map(|c| c.eval(e, index.max_poly_size))
-                        .collect(),
-                    table: lcs.lookup_tables[0]
+                .map(|((aggreg, sorted), lcs)| {
+                    let runtime = runtime_table
+                        .as_ref()
+                        .map(|(_, coeffs, ..)| coeffs.eval(e, index.max_poly_size));
+                    let mut table = lcs.lookup_tables[0]
                         .iter()
                         .map(|p| p.eval(e, index.max_poly_size))
                         .rev()
@@ -692,7 +1088,22 @@ where
                                 .zip(x.iter())
                                 .map(|(acc, x)| acc * joint_combiner + x)
                                 .collect()
-                        }),
+                        });
+                    if let Some(runtime) = &runtime {
+                        table
+                            .iter_mut()
+                            .zip(runtime)
+                            .for_each(|(t, r)| *t += joint_combiner * r);
+                    }
+                    LookupEvaluations {
+                        aggreg: aggreg.eval(e, index.max_poly_size),
+                        sorted: sorted
+                            .iter()
+                            .map(|c| c.eval(e, index.max_poly_size))
+                            .collect(),
+                        table,
+                        runtime,
+                    }
                 })
         };
 
@@ -714,31 +1125,21 @@ where
         //~     $$(f_0(x), f_1(x), f_2(x), \ldots)$$
         //~
         //~      TODO: do we want to specify more on that? It seems unecessary except for the t polynomial (or if for some reason someone sets that to a low value)
-        let chunked_evals = {
-            let chunked_evals_zeta = ProofEvaluations::<Vec<Fr<G>>> {
-                s: array_init(|i| {
-                    index.cs.sigmam[0..PERMUTS - 1][i].eval(zeta, index.max_poly_size)
-                }),
-                w: array_init(|i| witness_poly[i].eval(zeta, index.max_poly_size)),
-                z: z_poly.eval(zeta, index.max_poly_size),
-                lookup: lookup_evals(zeta),
-                generic_selector: index.cs.genericm.eval(zeta, index.max_poly_size),
-                poseidon_selector: index.cs.psm.eval(zeta, index.max_poly_size),
-            };
-            let chunked_evals_zeta_omega = ProofEvaluations::<Vec<Fr<G>>> {
-                s: array_init(|i| {
-                    index.cs.sigmam[0..PERMUTS - 1][i].eval(zeta_omega, index.max_poly_size)
-                }),
-                w: array_init(|i| witness_poly[i].eval(zeta_omega, index.max_poly_size)),
-                z: z_poly.eval(zeta_omega, index.max_poly_size),
-                lookup: lookup_evals(zeta_omega),
-                generic_selector: index.cs.genericm.eval(zeta_omega, index.max_poly_size),
-                poseidon_selector: index.cs.psm.eval(zeta_omega, index.max_poly_size),
-            };
-
-            [chunked_evals_zeta, chunked_evals_zeta_omega]
+        let evaluated_polys = ProofEvaluations {
+            s: array_init(|i| &index.cs.sigmam[i]),
+            w: array_init(|i| &witness_poly[i]),
+            z: &z_poly,
+            lookup: None,
+            generic_selector: &index.cs.genericm,
+            poseidon_selector: &index.cs.psm,
         };
+        let chunked_evals = [zeta, zeta_omega].map(|pt| ProofEvaluations {
+            lookup: lookup_evals(pt),
+            ..evaluated_polys.map_ref(&|p| p.eval(pt, index.max_poly_size))
+        });
 
+        scrub(&mut lookup_aggreg_coeffs);
+        scrub(&mut lookup_sorted_coeffs);
         drop(lookup_aggreg_coeffs);
         drop(lookup_sorted_coeffs);
 
@@ -752,30 +1153,15 @@ where
             let power_of_eval_points_for_chunks = [zeta_to_srs_len, zeta_omega_to_srs_len];
             &chunked_evals
                 .iter()
-                .zip(power_of_eval_points_for_chunks.iter())
-                .map(|(es, &e1)| ProofEvaluations::<Fr<G>> {
-                    s: array_init(|i| DensePolynomial::eval_polynomial(&es.s[i], e1)),
-                    w: array_init(|i| DensePolynomial::eval_polynomial(&es.w[i], e1)),
-                    z: DensePolynomial::eval_polynomial(&es.z, e1),
-                    lookup: es.lookup.as_ref().map(|l| LookupEvaluations {
-                        table: DensePolynomial::eval_polynomial(&l.table, e1),
-                        aggreg: DensePolynomial::eval_polynomial(&l.aggreg, e1),
-                        sorted: l
-                            .sorted
-                            .iter()
-                            .map(|p| DensePolynomial::eval_polynomial(p, e1))
-                            .collect(),
-                    }),
-                    generic_selector: DensePolynomial::eval_polynomial(&es.generic_selector, e1),
-                    poseidon_selector: DensePolynomial::eval_polynomial(&es.poseidon_selector, e1),
-                })
+                .zip(power_of_eval_points_for_chunks)
+                .map(|(es, pt)| es.combine(pt))
                 .collect::<Vec<_>>()
         };
 
         //~ 31. Compute the ft polynomial.
         //~     This is to implement [Maller's optimization](https://o1-labs.github.io/mina-book/crypto/plonk/maller_15.html).
         //~     (See in particular the [section on evaluating L](https://o1-labs.github.io/mina-book/crypto/plonk/maller_15.html#the-evaluation-of-l).)
-        let ft: DensePolynomial<Fr<G>> = {
+        let mut ft: DensePolynomial<Fr<G>> = {
             let f_chunked = {
                 // TODO: compute the linearization polynomial in evaluation form so
                 // that we can drop the coefficient forms of the index polynomials from
@@ -801,6 +1187,9 @@ where
                 };
 
                 drop(env);
+                scrub(&mut lagrange);
+                scrub(&mut lookup_sorted8);
+                scrub(&mut lookup_aggreg8);
                 drop(lookup_sorted8);
                 drop(lookup_aggreg8);
                 drop(lookup_table_combined);
@@ -832,18 +1221,20 @@ where
 
         //~ 34. Setup the Fr-Sponge
         let fq_sponge_before_evaluations = fq_sponge.clone();
+        let concluded = transcript.as_ref().map(|_| fq_sponge.clone());
         let mut fr_sponge = EFrSponge::new(index.cs.fr_sponge_params.clone());
 
         //~ 35. Squeeze the Fq-sponge and absorb the result with the Fr-Sponge.
         fr_sponge.absorb(&fq_sponge.digest());
 
-        //~ 36. Evaluate the negated public polynomial (if present) at $\zeta$ and $\zeta\omega$.
+        //~ 36. Evaluate the negated public polynomial (if present) at $\zeta$ and $\zeta\omega$,
+        //~     chunk by chunk if the domain is larger than the SRS.
         let public_evals = if public_poly.is_zero() {
             [Vec::new(), Vec::new()]
         } else {
             [
-                vec![public_poly.evaluate(&zeta)],
-                vec![public_poly.evaluate(&zeta_omega)],
+                public_poly.eval(zeta, index.max_poly_size),
+                public_poly.eval(zeta_omega, index.max_poly_size),
             ]
         };
 
@@ -904,25 +1295,27 @@ where
         //~     - the poseidon selector
         //~     - the 15 registers/witness columns
         //~     - the 6 s
-        polynomials.extend(vec![(&public_poly, None, non_hiding(1))]);
+        let chunks = quotient_chunks(d1_size, index.max_poly_size);
+        polynomials.extend(vec![(&public_poly, None, non_hiding(chunks))]);
         polynomials.extend(vec![(&ft, None, blinding_ft)]);
-        polynomials.extend(vec![(&z_poly, None, z_comm.1)]);
-        polynomials.extend(vec![(&index.cs.genericm, None, non_hiding(1))]);
-        polynomials.extend(vec![(&index.cs.psm, None, non_hiding(1))]);
-        polynomials.extend(
-            witness_poly
-                .iter()
-                .zip(w_comm.iter())
-                .map(|(w, c)| (w, None, c.1.clone()))
-                .collect::<Vec<_>>(),
-        );
+        let blindings = ProofEvaluations {
+            s: array_init(|_| non_hiding(chunks)),
+            w: array_init(|i| w_comm[i].1.clone()),
+            z: z_comm.1.clone(),
+            lookup: None,
+            generic_selector: non_hiding(chunks),
+            poseidon_selector: non_hiding(chunks),
+        };
         polynomials.extend(
-            index.cs.sigmam[0..PERMUTS - 1]
-                .iter()
-                .map(|w| (w, None, non_hiding(1)))
-                .collect::<Vec<_>>(),
+            evaluated_polys
+                .opened()
+                .into_iter()
+                .zip(blindings.opened())
+                .map(|(p, blinding)| (*p, None, blinding.clone())),
         );
 
+        phases.end("evaluations");
+
         //~ 44. Create an aggregated evaluation proof for all of these polynomials at $\zeta$ and $\zeta\omega$ using $u$ and $v$.
         let proof = index.srs.open(
             group_map,
@@ -933,8 +1326,18 @@ where
             fq_sponge_before_evaluations,
             rng,
         );
+        scrub(&mut ft);
+        phases.end("opening");
+
+        // the runtime table is part of the witness
+        let runtime_comm = runtime_table.map(|(mut evals, mut coeffs, comm, mut evals8)| {
+            scrub(&mut evals);
+            scrub(&mut coeffs);
+            scrub(&mut evals8);
+            comm.0
+        });
 
-        Ok(Self {
+        let proof = Self {
             commitments: ProverCommitments {
                 w_comm: array_init(|i| w_comm[i].0.clone()),
                 z_comm: z_comm.0,
@@ -943,6 +1346,7 @@ where
                     LookupCommitments {
                         aggreg: a.0,
                         sorted: s.iter().map(|(x, _)| x.clone()).collect(),
+                        runtime: runtime_comm,
                     }
                 }),
             },
@@ -951,7 +1355,440 @@ where
             ft_eval1,
             public,
             prev_challenges,
-        })
+        };
+
+        let polys = testing::ProverPolynomials {
+            public: public_poly,
+            witness: witness_poly,
+            witness_blinders: array_init(|i| w_comm[i].1.clone()),
+            z: z_poly,
+            z_blinders: z_comm.1,
+            t: quotient_poly,
+            t_blinders: t_comm.1,
+        };
+
+        if let (Some(transcript), Some(mut concluded)) = (transcript, concluded) {
+            proof.conclude_transcript(&mut concluded);
+            *transcript = concluded;
+        }
+        if let Some(prover_metrics) = prover_metrics {
+            *prover_metrics = metrics::end_proof(phases);
+        }
+
+        Ok((proof, polys))
+    }
+}
+
+/// Checks that the columns of `witness` have the same length, which covers the public input of `cs`
+/// and leaves room for the zero-knowledge rows in its domain,
+/// and returns the number of rows to pad them with to reach the size of the domain.
+fn witness_padding<F: FftField>(
+    witness: &[Vec<F>; COLUMNS],
+    cs: &ConstraintSystem<F>,
+) -> Result<usize> {
+    let rows = witness[0].len();
+    if let Some((col, w)) = witness.iter().enumerate().find(|(_, w)| w.len() != rows) {
+        return Err(ProofError::WitnessLengthMismatch {
+            col,
+            expected: rows,
+            got: w.len(),
+        });
+    }
+    if rows < cs.public {
+        return Err(ProofError::WitnessTooShort {
+            rows,
+            public: cs.public,
+        });
+    }
+    let domain = cs.domain.d1.size as usize;
+    domain
+        .checked_sub(rows)
+        .filter(|padding| *padding >= ZK_ROWS as usize)
+        .ok_or(ProofError::DomainTooSmall { rows, domain })
+}
+
+/// Returns the error of a witness that does not satisfy `cs`,
+/// with the row on which it does not (see [counterexample]) when it can be found without the lookups.
+fn unsatisfied<F: FftField>(
+    witness: &[Vec<F>; COLUMNS],
+    cs: &ConstraintSystem<F>,
+    public: &[F],
+) -> ProofError {
+    match counterexample(witness, cs, public) {
+        Some(counterexample) => ProofError::UnsatisfiedGate {
+            row: counterexample.row,
+            term: counterexample.term,
+        },
+        None => ProofError::Prover("the witness does not satisfy the circuit"),
+    }
+}
+
+/// Returns the environment in which the constraints of the gates are evaluated over the domain,
+/// from the evaluations of the witness and of the permutation aggregation polynomial in `lagrange`.
+pub(crate) fn environment<'a, G: CommitmentCurve>(
+    index: &'a ProverIndex<G>,
+    lagrange: &'a WitnessOverDomains<Fr<G>>,
+    constants: Constants<Fr<G>>,
+    lookup: Option<LookupEnvironment<'a, Fr<G>>>,
+) -> Environment<'a, Fr<G>> {
+    let mut index_evals = HashMap::new();
+    use GateType::*;
+    index_evals.insert(Poseidon, &index.cs.ps8);
+    index_evals.insert(CompleteAdd, &index.cs.complete_addl4);
+    index_evals.insert(VarBaseMul, &index.cs.mull8);
+    index_evals.insert(EndoMul, &index.cs.emull);
+    index_evals.insert(EndoMulScalar, &index.cs.endomul_scalar8);
+    [ChaCha0, ChaCha1, ChaCha2, ChaChaFinal]
+        .iter()
+        .enumerate()
+        .for_each(|(i, g)| {
+            if let Some(c) = &index.cs.chacha8 {
+                index_evals.insert(*g, &c[i]);
+            }
+        });
+    if let Some(boolean8) = &index.cs.boolean8 {
+        index_evals.insert(Boolean, boolean8);
+    }
+
+    Environment {
+        constants,
+        witness: &lagrange.d8.this.w,
+        coefficient: &index.cs.coefficients8,
+        vanishes_on_last_4_rows: &index.cs.vanishes_on_last_4_rows,
+        z: &lagrange.d8.this.z,
+        l0_1: l0_1(index.cs.domain.d1),
+        domain: index.cs.domain,
+        index: index_evals,
+        custom: &index.cs.custom8,
+        linear_combination: index.cs.linear_combination8.as_ref(),
+        partial_rounds: index.cs.partial_rounds8.as_ref(),
+        lookup,
+    }
+}
+
+/// The combined constraints of the gates (including the custom gates), of the lookup argument
+/// and of the boundary constraints, as symbolic expressions over the powers of alpha.
+/// They only depend on the circuit, and can thus be shared by all its proofs.
+pub(crate) struct GateConstraints<F: FftField> {
+    pub(crate) complete_add: E<F>,
+    pub(crate) varbase_mul: E<F>,
+    pub(crate) endomul: E<F>,
+    pub(crate) endomul_scalar: E<F>,
+    pub(crate) poseidon: E<F>,
+    pub(crate) chacha: Option<[E<F>; 4]>,
+    pub(crate) linear_combination: Option<E<F>>,
+    pub(crate) boolean: Option<E<F>>,
+    pub(crate) lookup: Option<Vec<E<F>>>,
+    pub(crate) custom: Vec<E<F>>,
+    pub(crate) boundary: Option<E<F>>,
+}
+
+impl<F: FftField> GateConstraints<F> {
+    pub(crate) fn new<G: CommitmentCurve<ScalarField = F>>(index: &ProverIndex<G>) -> Self {
+        let alphas = &index.powers_of_alpha;
+        Self {
+            complete_add: CompleteAdd::combined_constraints(alphas),
+            varbase_mul: VarbaseMul::combined_constraints(alphas),
+            endomul: EndosclMul::combined_constraints(alphas),
+            endomul_scalar: EndomulScalar::combined_constraints(alphas),
+            poseidon: match &index.cs.partial_rounds8 {
+                None => Poseidon::combined_constraints(alphas),
+                Some(_) => {
+                    Poseidon::combined_constraints(alphas)
+                        + poseidon::combined_partial_round_corrections(alphas)
+                }
+            },
+            chacha: index.cs.chacha8.as_ref().map(|_| {
+                [
+                    ChaCha0::combined_constraints(alphas),
+                    ChaCha1::combined_constraints(alphas),
+                    ChaCha2::combined_constraints(alphas),
+                    ChaChaFinal::combined_constraints(alphas),
+                ]
+            }),
+            linear_combination: index
+                .cs
+                .linear_combination8
+                .as_ref()
+                .map(|_| linear_combination::combined_constraints(alphas)),
+            boolean: index
+                .cs
+                .boolean8
+                .as_ref()
+                .map(|_| Boolean::combined_constraints(alphas)),
+            lookup: index
+                .cs
+                .lookup_constraint_system
+                .as_ref()
+                .map(|lcs| lookup::constraints(lcs, index.cs.domain.d1)),
+            custom: index.cs.custom_gates.combined_constraints(alphas),
+            boundary: (!index.cs.boundary.is_empty())
+                .then(|| boundary::combined_constraints(&index.cs.boundary, alphas)),
+        }
+    }
+}
+
+/// A prover for a single circuit, to create many proofs with different witnesses.
+///
+/// Everything that [ProverProof::create] recomputes on each call but that only depends on the circuit
+/// is computed once when the prover is created:
+/// the combined gate constraints, the (hash-to-curve) group map, and the Lagrange basis of the SRS.
+pub struct Prover<G: CommitmentCurve, EFqSponge, EFrSponge> {
+    index: Arc<ProverIndex<G>>,
+    group_map: G::Map,
+    constraints: GateConstraints<Fr<G>>,
+    sponges: PhantomData<fn() -> (EFqSponge, EFrSponge)>,
+}
+
+impl<G, EFqSponge, EFrSponge> Prover<G, EFqSponge, EFrSponge>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    /// Creates a prover for the circuit of `index`.
+    /// If the SRS of the index lacks the Lagrange basis of the domain,
+    /// it is computed here (on a copy of the SRS if it is shared).
+    pub fn new(mut index: ProverIndex<G>) -> Self {
+        index.add_lagrange_basis();
+        let constraints = GateConstraints::new(&index);
+        Self {
+            index: Arc::new(index),
+            group_map: G::Map::setup(),
+            constraints,
+            sponges: PhantomData,
+        }
+    }
+
+    /// The index of the circuit.
+    pub fn index(&self) -> &Arc<ProverIndex<G>> {
+        &self.index
+    }
+
+    /// The group map used in the opening proofs, to pass to the verifier.
+    pub fn group_map(&self) -> &G::Map {
+        &self.group_map
+    }
+
+    /// Creates a proof for `witness`.
+    pub fn prove(&self, witness: [Vec<Fr<G>>; COLUMNS]) -> Result<ProverProof<G>> {
+        self.prove_with_prev_challenges(witness, vec![])
+    }
+
+    /// Creates a proof for `witness`, folding in the challenges of previous (recursive) proofs.
+    pub fn prove_with_prev_challenges(
+        &self,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+    ) -> Result<ProverProof<G>> {
+        ProverProof::create_inner::<EFqSponge, EFrSponge, _>(
+            &self.group_map,
+            witness,
+            &[],
+            &self.index,
+            &self.constraints,
+            prev_challenges,
+            None,
+            None,
+            &mut rand::rngs::OsRng,
+        )
+        .map(|(proof, _)| proof)
+    }
+}
+
+/// Test helpers to look inside a proof
+pub mod testing {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    /// The polynomials committed to in a proof, along with their blinders,
+    /// as kept by the prover (see [ProverProof::create_with_polynomials]).
+    pub struct ProverPolynomials<G: AffineCurve> {
+        /// The negated public input polynomial
+        pub public: DensePolynomial<Fr<G>>,
+        /// The witness polynomials
+        pub witness: [DensePolynomial<Fr<G>>; COLUMNS],
+        /// The blinders of the witness commitments
+        pub witness_blinders: [PolyComm<Fr<G>>; COLUMNS],
+        /// The permutation aggregation polynomial
+        pub z: DensePolynomial<Fr<G>>,
+        /// The blinders of the permutation commitment
+        pub z_blinders: PolyComm<Fr<G>>,
+        /// The quotient polynomial
+        pub t: DensePolynomial<Fr<G>>,
+        /// The blinders of the quotient commitment
+        pub t_blinders: PolyComm<Fr<G>>,
+    }
+
+    impl<G: AffineCurve> Drop for ProverPolynomials<G> {
+        fn drop(&mut self) {
+            scrub(&mut self.witness[..]);
+            scrub(&mut self.z);
+            scrub(&mut self.t);
+        }
+    }
+
+    /// A polynomial committed to in a proof.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Committed {
+        /// The witness column with the given index
+        Witness(usize),
+        /// The permutation aggregation polynomial
+        Permutation,
+        /// The quotient polynomial
+        Quotient,
+    }
+
+    /// The opening of a committed polynomial at caller-chosen points.
+    pub struct Opening<G: AffineCurve> {
+        /// The polynomial that is opened
+        pub poly: Committed,
+        /// The evaluation points
+        pub points: Vec<Fr<G>>,
+        /// The chunked evaluations of the polynomial at each point
+        pub evals: Vec<Vec<Fr<G>>>,
+        /// The scaling factor for the chunks of the polynomial
+        pub polyscale: Fr<G>,
+        /// The scaling factor for the evaluation points
+        pub evalscale: Fr<G>,
+        /// The evaluation proof
+        pub proof: OpeningProof<G>,
+    }
+
+    impl<G: CommitmentCurve> ProverProof<G>
+    where
+        G::BaseField: PrimeField,
+    {
+        /// Same as [ProverProof::create], but also returns the committed polynomials
+        /// so that tests can open them at other points than the ones of the protocol.
+        pub fn create_with_polynomials<
+            EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+            EFrSponge: FrSponge<Fr<G>>,
+        >(
+            group_map: &G::Map,
+            witness: [Vec<Fr<G>>; COLUMNS],
+            index: &ProverIndex<G>,
+            prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+        ) -> Result<(Self, ProverPolynomials<G>)> {
+            let constraints = GateConstraints::new(index);
+            Self::create_inner::<EFqSponge, EFrSponge, _>(
+                group_map,
+                witness,
+                &[],
+                index,
+                &constraints,
+                prev_challenges,
+                None,
+                None,
+                &mut rand::rngs::OsRng,
+            )
+        }
+
+        /// Returns the first `chunks` chunks of the commitment to `poly` in this proof.
+        pub fn commitment(&self, poly: Committed, chunks: usize) -> PolyComm<G> {
+            let comm = match poly {
+                Committed::Witness(col) => &self.commitments.w_comm[col],
+                Committed::Permutation => &self.commitments.z_comm,
+                Committed::Quotient => &self.commitments.t_comm,
+            };
+            PolyComm {
+                unshifted: comm.unshifted[..chunks].to_vec(),
+                shifted: None,
+            }
+        }
+    }
+
+    impl<G: CommitmentCurve> ProverPolynomials<G>
+    where
+        G::BaseField: PrimeField,
+    {
+        /// Returns a committed polynomial and its blinders.
+        pub fn get(&self, poly: Committed) -> (&DensePolynomial<Fr<G>>, &PolyComm<Fr<G>>) {
+            match poly {
+                Committed::Witness(col) => (&self.witness[col], &self.witness_blinders[col]),
+                Committed::Permutation => (&self.z, &self.z_blinders),
+                Committed::Quotient => (&self.t, &self.t_blinders),
+            }
+        }
+
+        /// Opens `poly` at `points`.
+        /// Only the chunks of the commitment that cover the polynomial are opened
+        /// (the quotient commitment is padded with commitments to zero).
+        pub fn open<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>>(
+            &self,
+            index: &ProverIndex<G>,
+            group_map: &G::Map,
+            poly: Committed,
+            points: &[Fr<G>],
+        ) -> Opening<G> {
+            let rng = &mut OsRng;
+            let (p, blinders) = self.get(poly);
+
+            let chunks = std::cmp::min(
+                blinders.unshifted.len(),
+                (p.coeffs.len() + index.max_poly_size - 1) / index.max_poly_size,
+            );
+            let blinders = PolyComm {
+                unshifted: blinders.unshifted[..chunks].to_vec(),
+                shifted: None,
+            };
+
+            let polyscale = Fr::<G>::rand(rng);
+            let evalscale = Fr::<G>::rand(rng);
+            let evals = points
+                .iter()
+                .map(|pt| p.eval(*pt, index.max_poly_size))
+                .collect();
+            let sponge = EFqSponge::new(index.fq_sponge_params.clone());
+            let proof = index.srs.open(
+                group_map,
+                &[(p, None, blinders)],
+                points,
+                polyscale,
+                evalscale,
+                sponge,
+                rng,
+            );
+
+            Opening {
+                poly,
+                points: points.to_vec(),
+                evals,
+                polyscale,
+                evalscale,
+                proof,
+            }
+        }
+    }
+
+    impl<G: CommitmentCurve> Opening<G>
+    where
+        G::BaseField: PrimeField,
+    {
+        /// Verifies the opening against the corresponding commitment of `proof`.
+        pub fn verify<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>>(
+            &self,
+            index: &ProverIndex<G>,
+            group_map: &G::Map,
+            proof: &ProverProof<G>,
+        ) -> bool {
+            let chunks = self.evals.first().map(|e| e.len()).unwrap_or(0);
+            if self.evals.iter().any(|e| e.len() != chunks) {
+                return false;
+            }
+            let comm = proof.commitment(self.poly, chunks);
+            let sponge = EFqSponge::new(index.fq_sponge_params.clone());
+            let mut batch = vec![(
+                sponge,
+                self.points.clone(),
+                self.polyscale,
+                self.evalscale,
+                vec![(&comm, self.evals.iter().collect(), None)],
+                &self.proof,
+            )];
+            index.srs.verify(group_map, &mut batch, &mut OsRng)
+        }
     }
 }
 
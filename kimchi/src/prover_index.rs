@@ -2,18 +2,33 @@
 
 use crate::alphas::Alphas;
 use crate::circuits::{
-    constraints::ConstraintSystem,
+    constraints::{ConstraintSystem, Shifts},
     expr::{Linearization, PolishToken},
+    gate::GateType,
+    public_input::PublicInputLayout,
+    sparse::SparseEvals,
     wires::*,
 };
-use crate::linearization::expr_linearization;
+use crate::error::SetupError;
+use crate::linearization::{constraints_expr, expr_linearization};
+use crate::verifier_index::TranscriptVersion;
 use ark_ec::AffineCurve;
-use ark_ff::PrimeField;
-use commitment_dlog::{commitment::CommitmentCurve, srs::SRS};
+use ark_ff::{PrimeField, Zero};
+use array_init::array_init;
+use commitment_dlog::{
+    commitment::{CommitmentCurve, PolyComm},
+    srs::SRS,
+};
 use oracle::poseidon::ArithmeticSpongeParams;
+use rand::{CryptoRng, RngCore};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::serde_as;
-use std::sync::Arc;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+};
 
 type Fr<G> = <G as AffineCurve>::ScalarField;
 type Fq<G> = <G as AffineCurve>::BaseField;
@@ -49,73 +64,419 @@ pub struct ProverIndex<G: CommitmentCurve> {
     /// maximal size of the quotient polynomial according to the supported constraints
     pub max_quot_size: usize,
 
+    /// the names of the public inputs, if the circuit declares them
+    pub public_layout: Option<PublicInputLayout>,
+
     /// random oracle argument parameters
     #[serde(skip)]
     pub fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+
+    /// the digest of the verifier index, computed once the SRS has the Lagrange basis of the domain
+    /// (see [ProverIndex::verifier_index_digest])
+    #[serde(skip)]
+    verifier_index_digest: Option<Fq<G>>,
+
+    /// the format of the transcript of the proofs (see [ProverIndex::set_transcript_version])
+    pub transcript_version: TranscriptVersion,
+
+    /// whether the proofs hide the witness (see [ProverIndex::set_zk])
+    pub zk: bool,
 }
 //~spec:endcode
 
+/// Returns the number of chunks of `max_poly_size` coefficients
+/// needed to commit to a quotient polynomial of degree less than `max_quot_size`.
+pub(crate) fn quotient_chunks(max_quot_size: usize, max_poly_size: usize) -> usize {
+    (max_quot_size + max_poly_size - 1) / max_poly_size
+}
+
+/// Rejects the zero-knowledge of the proofs of a domain of size `domain` over an SRS of size `srs`,
+/// if the polynomials are committed in chunks.
+fn check_chunks(zk: bool, srs: usize, domain: usize) -> Result<(), SetupError> {
+    if zk && srs < domain {
+        return Err(SetupError::ChunkedZeroKnowledge { srs, domain });
+    }
+    Ok(())
+}
+
+/// The version of the serialization format of the [ProverIndex].
+/// It must be bumped whenever the serialized fields of the index change,
+/// so that stale cached indexes are rejected instead of being misread.
+pub const PROVER_INDEX_VERSION: u32 = 13;
+
 impl<'a, G: CommitmentCurve> ProverIndex<G>
 where
     G::BaseField: PrimeField,
 {
-    /// this function compiles the index from constraints
+    /// this function compiles the index from constraints.
+    /// The SRS must be as large as the domain of the circuit (see [ProverIndex::try_create_without_zk] otherwise).
+    ///
+    /// Panics if the shifts of the permutation of `cs` do not pass [Shifts::check]
+    /// (see [ProverIndex::try_create] for a version that returns an error instead).
     pub fn create(
-        mut cs: ConstraintSystem<Fr<G>>,
+        cs: ConstraintSystem<Fr<G>>,
         fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
         endo_q: Fr<G>,
         srs: Arc<SRS<G>>,
     ) -> Self {
+        Self::try_create(cs, fq_sponge_params, endo_q, srs)
+            .unwrap_or_else(|e| panic!("invalid constraint system: {}", e))
+    }
+
+    /// Same as [ProverIndex::create], returning an error if the constraint system is invalid.
+    pub fn try_create(
+        cs: ConstraintSystem<Fr<G>>,
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        endo_q: Fr<G>,
+        srs: Arc<SRS<G>>,
+    ) -> Result<Self, SetupError> {
+        Self::create_inner(cs, fq_sponge_params, endo_q, srs, true)
+    }
+
+    /// Same as [ProverIndex::try_create], for proofs that do not hide the witness (see [ProverIndex::set_zk]).
+    /// The SRS can then be smaller than the domain of the circuit:
+    /// the polynomials of the circuit are committed in chunks of the size of the SRS.
+    /// The proofs reveal the evaluations of each chunk, which the zero-knowledge rows of the witness
+    /// ([ZK_ROWS](crate::circuits::constraints::ZK_ROWS)) are not enough to hide.
+    pub fn try_create_without_zk(
+        cs: ConstraintSystem<Fr<G>>,
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        endo_q: Fr<G>,
+        srs: Arc<SRS<G>>,
+    ) -> Result<Self, SetupError> {
+        Self::create_inner(cs, fq_sponge_params, endo_q, srs, false)
+    }
+
+    fn create_inner(
+        mut cs: ConstraintSystem<Fr<G>>,
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        endo_q: Fr<G>,
+        srs: Arc<SRS<G>>,
+        zk: bool,
+    ) -> Result<Self, SetupError> {
+        Shifts::check(&cs.domain.d1, &cs.shift)?;
+        check_chunks(zk, srs.g.len(), cs.domain.d1.size as usize)?;
         let max_poly_size = srs.g.len();
-        if cs.public > 0 {
-            assert!(
-                max_poly_size >= cs.domain.d1.size as usize,
-                "polynomial segment size has to be not smaller that that of the circuit!"
-            );
-        }
         cs.endo = endo_q;
 
         //~ 1. compute the linearization
         let (linearization, powers_of_alpha) = expr_linearization(
             cs.domain.d1,
             cs.chacha8.is_some(),
+            cs.linear_combination8.is_some(),
+            cs.partial_rounds8.is_some(),
+            cs.boolean8.is_some(),
             &cs.lookup_constraint_system,
+            &cs.custom_gates,
+            &cs.boundary,
         );
 
-        //~ 2. set `max_quot_size` to the degree of the quotient polynomial,
+        //~ 2. check that the constraints of the gates (including the custom gates)
+        //~    can be evaluated over the largest domain, of size $8n$ for a circuit of $n$ rows.
+        let n = cs.domain.d1.size;
+        let (constraints, _) = constraints_expr(
+            cs.domain.d1,
+            cs.chacha8.is_some(),
+            cs.linear_combination8.is_some(),
+            cs.partial_rounds8.is_some(),
+            cs.boolean8.is_some(),
+            &cs.lookup_constraint_system,
+            &cs.custom_gates,
+            &cs.boundary,
+        );
+        let degree = constraints.degree(n);
+        if degree > 8 * n {
+            return Err(SetupError::ConstraintDegree {
+                degree: (degree + n - 1) / n,
+                max: 8,
+            });
+        }
+
+        //~ 3. set `max_quot_size` to the degree of the quotient polynomial,
         //~    which is obtained by looking at the highest monomial in the sum
         //~     $$\sum_{i=0}^{PERMUTS} (w_i(x) + \beta k_i x + \gamma)$$
         //~    where the $w_i(x)$ are of degree the size of the domain.
-        let max_quot_size = PERMUTS * cs.domain.d1.size as usize;
+        //~    The constraints of the gates, of degree at most $8n$, leave a quotient of degree at most $7n$,
+        //~    which is the same bound for `PERMUTS` = 7.
+        let max_quot_size = std::cmp::max(PERMUTS as u64 * n, degree.saturating_sub(n)) as usize;
 
-        ProverIndex {
+        let mut index = ProverIndex {
             cs,
             linearization,
             powers_of_alpha,
             srs,
             max_poly_size,
             max_quot_size,
+            public_layout: None,
             fq_sponge_params,
+            verifier_index_digest: None,
+            transcript_version: TranscriptVersion::LATEST,
+            zk,
+        };
+        if index.srs.lagrange_bases.contains_key(&(n as usize)) {
+            index.verifier_index_digest = Some(index.verifier_index().digest());
         }
+        Ok(index)
+    }
+
+    /// Same as [ProverIndex::try_create], also rejecting the circuits with variables that no constraint reads
+    /// (see [ConstraintSystem::unconstrained_variables]).
+    pub fn try_create_strict(
+        cs: ConstraintSystem<Fr<G>>,
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        endo_q: Fr<G>,
+        srs: Arc<SRS<G>>,
+    ) -> Result<Self, SetupError> {
+        let unconstrained = cs.unconstrained_variables();
+        if !unconstrained.is_empty() {
+            return Err(SetupError::UnconstrainedVariables(
+                unconstrained.into_iter().map(|cells| cells[0]).collect(),
+            ));
+        }
+        Self::try_create(cs, fq_sponge_params, endo_q, srs)
+    }
+
+    /// Returns the [digest](crate::verifier_index::VerifierIndex::digest) of the verifier index, which the proofs absorb first.
+    /// It is computed once, when the index is created or read with an SRS that has the Lagrange basis of its domain.
+    /// Otherwise, it is computed from the verifier index on each call, which panics without the Lagrange basis.
+    pub fn verifier_index_digest(&self) -> Fq<G> {
+        self.verifier_index_digest
+            .unwrap_or_else(|| self.verifier_index().digest())
+    }
+
+    /// Adds the Lagrange basis of the domain to the SRS if it lacks it (on a copy of the SRS if it is shared),
+    /// and computes the digest of the verifier index, which needs it.
+    pub(crate) fn add_lagrange_basis(&mut self) {
+        let domain = self.cs.domain.d1;
+        if !self
+            .srs
+            .lagrange_bases
+            .contains_key(&(domain.size as usize))
+        {
+            Arc::make_mut(&mut self.srs).add_lagrange_basis(domain);
+        }
+        if self.verifier_index_digest.is_none() {
+            self.verifier_index_digest = Some(self.verifier_index().digest());
+        }
+    }
+
+    /// Creates the proofs in the format of the transcript `version`, instead of the [latest](TranscriptVersion::LATEST) one,
+    /// for verifiers that do not support it yet.
+    /// The verifier index must be derived again, as it records the version.
+    pub fn set_transcript_version(&mut self, version: TranscriptVersion) {
+        self.transcript_version = version;
+    }
+
+    /// Disables (or enables again) the zero-knowledge of the proofs, which is enabled by default.
+    /// Without zero-knowledge, the zero-knowledge rows of the witness are left to zero
+    /// and the commitments of the proofs are not blinded, which saves a scalar multiplication per commitment.
+    /// This is meant for the proofs that do not need to hide their witness, like the ones aggregating other proofs.
+    /// The proofs are verified the same way, and the circuit still reserves the zero-knowledge rows.
+    /// Fails to enable the zero-knowledge if the SRS is smaller than the domain (see [SetupError::ChunkedZeroKnowledge]).
+    pub fn set_zk(&mut self, zk: bool) -> Result<(), SetupError> {
+        check_chunks(zk, self.max_poly_size, self.cs.domain.d1.size as usize)?;
+        self.zk = zk;
+        Ok(())
+    }
+
+    /// Blinds the commitment `comm` if the proofs are zero-knowledge,
+    /// and returns it along with its blinders (which are zero otherwise).
+    pub(crate) fn mask(
+        &self,
+        comm: PolyComm<G>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> (PolyComm<G>, PolyComm<Fr<G>>) {
+        if self.zk {
+            self.srs.mask(comm, rng)
+        } else {
+            let blinders = comm.map(|_| Fr::<G>::zero());
+            (comm, blinders)
+        }
+    }
+
+    /// The number of chunks of the commitment to the quotient polynomial in the proofs of this index.
+    pub fn quotient_chunks(&self) -> usize {
+        quotient_chunks(self.max_quot_size, self.max_poly_size)
+    }
+
+    /// Names the public inputs of the circuit with `layout`,
+    /// which is passed on to the [VerifierIndex](crate::verifier_index::VerifierIndex).
+    pub fn set_public_layout(&mut self, layout: PublicInputLayout) -> Result<(), SetupError> {
+        if layout.len() != self.cs.public {
+            return Err(SetupError::PublicInputLayout {
+                slots: layout.len(),
+                public: self.cs.public,
+            });
+        }
+        self.public_layout = Some(layout);
+        Ok(())
+    }
+
+    /// Writes a [ProverIndex] to a file, so that the preprocessing does not have to be redone.
+    /// The file starts with the [PROVER_INDEX_VERSION] and the size of the SRS used by the index,
+    /// the SRS itself is not written.
+    pub fn to_file(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        self.write(&mut writer)?;
+        writer.flush().map_err(|e| e.to_string())
+    }
+
+    /// Writes a [ProverIndex] to `writer`, in the format of [ProverIndex::to_file].
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), String> {
+        let mut serializer = rmp_serde::Serializer::new(writer);
+
+        PROVER_INDEX_VERSION
+            .serialize(&mut serializer)
+            .map_err(|e| e.to_string())?;
+        self.srs
+            .g
+            .len()
+            .serialize(&mut serializer)
+            .map_err(|e| e.to_string())?;
+        self.serialize(&mut serializer).map_err(|e| e.to_string())
+    }
+
+    /// Reads a [ProverIndex] written by [ProverIndex::to_file], given a pointer to the SRS it was created with.
+    /// Fails if the file was written with another version of the format, or for an SRS of a different size.
+    pub fn from_file(
+        srs: Arc<SRS<G>>,
+        path: &Path,
+        // TODO: we shouldn't have to pass these
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        fr_sponge_params: ArithmeticSpongeParams<Fr<G>>,
+    ) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let check_srs = |srs_size| {
+            if srs_size != srs.g.len() {
+                return Err(format!(
+                    "the prover index was created with an SRS of size {srs_size}, not {}",
+                    srs.g.len()
+                ));
+            }
+            Ok(srs)
+        };
+        Self::read(
+            BufReader::new(file),
+            check_srs,
+            fq_sponge_params,
+            fr_sponge_params,
+        )
+    }
+
+    /// Reads a [ProverIndex] written by [ProverIndex::write].
+    /// As the SRS is not part of the serialized index, `srs` is given the size of the SRS the index was created with,
+    /// and returns the SRS to use (or fails).
+    /// The Lagrange basis of the domain is added to the SRS if it lacks it.
+    pub fn read<R: Read>(
+        reader: R,
+        srs: impl FnOnce(usize) -> Result<Arc<SRS<G>>, String>,
+        // TODO: we shouldn't have to pass these
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        fr_sponge_params: ArithmeticSpongeParams<Fr<G>>,
+    ) -> Result<Self, String> {
+        let mut deserializer = rmp_serde::Deserializer::new(reader);
+
+        // header
+        let version = u32::deserialize(&mut deserializer).map_err(|e| e.to_string())?;
+        if version != PROVER_INDEX_VERSION {
+            return Err(format!(
+                "unsupported prover index version {version} (expected {PROVER_INDEX_VERSION})"
+            ));
+        }
+        let srs_size = usize::deserialize(&mut deserializer).map_err(|e| e.to_string())?;
+        let srs = srs(srs_size)?;
+
+        // deserialize
+        let mut index = Self::deserialize(&mut deserializer).map_err(|e| e.to_string())?;
+
+        // the custom gates are not serialized
+        if index.cs.gates.iter().any(|g| g.typ == GateType::Custom) {
+            return Err(
+                "the prover index uses custom gates, which cannot be deserialized".to_string(),
+            );
+        }
+        Shifts::check(&index.cs.domain.d1, &index.cs.shift).map_err(|e| e.to_string())?;
+
+        // fill in the rest
+        // (domain d1 is every 8th row of domain d8)
+        index.cs.coefficients_sparse = array_init(|i| {
+            let evals: Vec<_> = index.cs.coefficients8[i]
+                .evals
+                .iter()
+                .step_by(8)
+                .cloned()
+                .collect();
+            SparseEvals::from_evals(&evals)
+        });
+        let (linearization, powers_of_alpha) = expr_linearization(
+            index.cs.domain.d1,
+            index.cs.chacha8.is_some(),
+            index.cs.linear_combination8.is_some(),
+            index.cs.partial_rounds8.is_some(),
+            index.cs.boolean8.is_some(),
+            &index.cs.lookup_constraint_system,
+            &index.cs.custom_gates,
+            &index.cs.boundary,
+        );
+        index.linearization = linearization;
+        index.powers_of_alpha = powers_of_alpha;
+        index.srs = srs;
+        index.fq_sponge_params = fq_sponge_params;
+        index.cs.fr_sponge_params = fr_sponge_params;
+        index.add_lagrange_basis();
+
+        Ok(index)
     }
 }
 
 pub mod testing {
     use super::*;
-    use crate::circuits::gate::CircuitGate;
+    use crate::circuits::{custom::GateConstraint, gate::CircuitGate};
     use commitment_dlog::srs::endos;
     use mina_curves::pasta::{pallas::Affine as Other, vesta::Affine, Fp};
 
     pub fn new_index_for_test(gates: Vec<CircuitGate<Fp>>, public: usize) -> ProverIndex<Affine> {
+        new_index_for_test_with_custom_gates(gates, public, vec![])
+    }
+
+    /// Same as [new_index_for_test], registering the `custom_gates` in order.
+    pub fn new_index_for_test_with_custom_gates(
+        gates: Vec<CircuitGate<Fp>>,
+        public: usize,
+        custom_gates: Vec<Arc<dyn GateConstraint<Fp>>>,
+    ) -> ProverIndex<Affine> {
         let fp_sponge_params = oracle::pasta::fp_kimchi::params();
-        let cs = ConstraintSystem::<Fp>::create(gates, vec![], fp_sponge_params, public).unwrap();
+        let mut cs =
+            ConstraintSystem::<Fp>::create(gates, vec![], fp_sponge_params, public).unwrap();
+        for gate in custom_gates {
+            cs.register_custom_gate(gate);
+        }
+        new_index_for_test_with_cs(cs)
+    }
+
+    /// Same as [new_index_for_test], for the constraint system `cs`.
+    pub fn new_index_for_test_with_cs(cs: ConstraintSystem<Fp>) -> ProverIndex<Affine> {
+        try_new_index_for_test_with_cs(cs, ProverIndex::try_create).unwrap()
+    }
 
+    /// Same as [new_index_for_test_with_cs], creating the index with `create`
+    /// (like [ProverIndex::try_create] or [ProverIndex::try_create_strict]).
+    pub fn try_new_index_for_test_with_cs(
+        cs: ConstraintSystem<Fp>,
+        create: fn(
+            ConstraintSystem<Fp>,
+            ArithmeticSpongeParams<Fq<Affine>>,
+            Fp,
+            Arc<SRS<Affine>>,
+        ) -> Result<ProverIndex<Affine>, SetupError>,
+    ) -> Result<ProverIndex<Affine>, SetupError> {
         let mut srs = SRS::<Affine>::create(cs.domain.d1.size as usize);
         srs.add_lagrange_basis(cs.domain.d1);
         let srs = Arc::new(srs);
 
         let fq_sponge_params = oracle::pasta::fq_kimchi::params();
         let (endo_q, _endo_r) = endos::<Other>();
-        ProverIndex::<Affine>::create(cs, fq_sponge_params, endo_q, srs)
+        create(cs, fq_sponge_params, endo_q, srs)
     }
 }
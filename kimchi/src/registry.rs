@@ -0,0 +1,503 @@
+//! This module implements a [CircuitRegistry], which stores the verifier indexes of many circuits
+//! under a name and a semantic [Version].
+//!
+//! Names are hierarchical: `payments/transfer` and `payments/refund` both live under `payments`.
+//! Each registered index is pinned by the digest of its serialization,
+//! so that a version can never silently point to a different circuit,
+//! and two versions of a circuit are interchangeable only if they pin the same digest.
+//!
+//! A [ProofRegistry] instead identifies circuits by their digest alone:
+//! proofs are tagged with the digest of the circuit they were created for ([TaggedProof]),
+//! so that a service verifying the proofs of many circuits (or many versions of a circuit)
+//! dispatches each proof to its verifier index, and never verifies a proof against the wrong one.
+
+use crate::error::VerifyError;
+use crate::plonk_sponge::FrSponge;
+use crate::prover::ProverProof;
+use crate::verifier::batch_verify;
+use crate::verifier_index::VerifierIndex;
+use ark_ec::AffineCurve;
+use ark_ff::PrimeField;
+use blake2::{Blake2b512, Digest};
+use commitment_dlog::{commitment::CommitmentCurve, PolyComm};
+use oracle::FqSponge;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_with::serde_as;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// The digest of a serialized [VerifierIndex].
+pub type CircuitDigest = [u8; 64];
+
+/// Returns the digest pinning `index` in a [CircuitRegistry].
+/// The SRS and the other parameters that are not serialized with the index are not part of the digest.
+pub fn circuit_digest<G>(index: &VerifierIndex<G>) -> CircuitDigest
+where
+    G: CommitmentCurve,
+    VerifierIndex<G>: Serialize,
+{
+    let bytes = rmp_serde::to_vec(index).expect("a verifier index can always be serialized");
+    let mut digest = [0u8; 64];
+    digest.copy_from_slice(&Blake2b512::digest(&bytes));
+    digest
+}
+
+/// The errors of a [CircuitRegistry].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    #[error("invalid version {0}, expected major.minor.patch")]
+    InvalidVersion(String),
+    #[error("circuit {name} has no version {version}")]
+    NotFound { name: String, version: Version },
+    #[error("circuit {name} {version} is already registered with another digest")]
+    AlreadyRegistered { name: String, version: Version },
+    #[error("circuit {name} {version} does not have the pinned digest")]
+    DigestMismatch { name: String, version: Version },
+    #[error("no circuit with the digest {0} is registered")]
+    UnknownCircuit(String),
+    #[error(transparent)]
+    Verify(#[from] VerifyError),
+}
+
+/// Formats the first bytes of a digest, enough to tell circuits apart in error messages.
+fn short_digest(digest: &CircuitDigest) -> String {
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// A semantic version `major.minor.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Returns true if `other` is a semver-compatible upgrade of `self`:
+    /// the same major version (or the same minor version for `0.x` versions), and not older.
+    pub fn accepts(&self, other: &Version) -> bool {
+        let same_series = if self.major == 0 {
+            other.major == 0 && self.minor == other.minor
+        } else {
+            self.major == other.major
+        };
+        same_series && other >= self
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<_> = s
+            .split('.')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| RegistryError::InvalidVersion(s.to_string()))?;
+        match parts[..] {
+            [major, minor, patch] => Ok(Self::new(major, minor, patch)),
+            _ => Err(RegistryError::InvalidVersion(s.to_string())),
+        }
+    }
+}
+
+/// A circuit registered in a [CircuitRegistry].
+pub struct RegisteredCircuit<G: CommitmentCurve> {
+    /// the digest pinning the circuit
+    pub digest: CircuitDigest,
+    /// the verifier index of the circuit
+    pub index: Arc<VerifierIndex<G>>,
+}
+
+/// A registry of verifier indexes, keyed by circuit name and [Version].
+pub struct CircuitRegistry<G: CommitmentCurve> {
+    circuits: BTreeMap<String, BTreeMap<Version, RegisteredCircuit<G>>>,
+}
+
+impl<G: CommitmentCurve> Default for CircuitRegistry<G> {
+    fn default() -> Self {
+        Self {
+            circuits: BTreeMap::new(),
+        }
+    }
+}
+
+impl<G> CircuitRegistry<G>
+where
+    G: CommitmentCurve,
+    VerifierIndex<G>: Serialize,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `index` as the version `version` of the circuit `name`, and returns its digest.
+    /// Registering the same circuit twice is a no-op,
+    /// but a version can not be registered again with another circuit.
+    pub fn register(
+        &mut self,
+        name: &str,
+        version: Version,
+        index: Arc<VerifierIndex<G>>,
+    ) -> Result<CircuitDigest, RegistryError> {
+        let digest = circuit_digest(&index);
+        let versions = self.circuits.entry(name.to_string()).or_default();
+        match versions.get(&version) {
+            Some(registered) if registered.digest != digest => {
+                Err(RegistryError::AlreadyRegistered {
+                    name: name.to_string(),
+                    version,
+                })
+            }
+            Some(_) => Ok(digest),
+            None => {
+                versions.insert(version, RegisteredCircuit { digest, index });
+                Ok(digest)
+            }
+        }
+    }
+
+    /// Returns the version `version` of the circuit `name`.
+    pub fn get(
+        &self,
+        name: &str,
+        version: &Version,
+    ) -> Result<&RegisteredCircuit<G>, RegistryError> {
+        self.circuits
+            .get(name)
+            .and_then(|versions| versions.get(version))
+            .ok_or_else(|| RegistryError::NotFound {
+                name: name.to_string(),
+                version: *version,
+            })
+    }
+
+    /// Returns the version `version` of the circuit `name`, checking that it has the `expected` digest.
+    pub fn get_pinned(
+        &self,
+        name: &str,
+        version: &Version,
+        expected: &CircuitDigest,
+    ) -> Result<&RegisteredCircuit<G>, RegistryError> {
+        let circuit = self.get(name, version)?;
+        if circuit.digest != *expected {
+            return Err(RegistryError::DigestMismatch {
+                name: name.to_string(),
+                version: *version,
+            });
+        }
+        Ok(circuit)
+    }
+
+    /// Returns the latest version of the circuit `name` that `version` accepts (see [Version::accepts]).
+    pub fn latest_compatible(
+        &self,
+        name: &str,
+        version: &Version,
+    ) -> Option<(&Version, &RegisteredCircuit<G>)> {
+        self.circuits
+            .get(name)?
+            .iter()
+            .rev()
+            .find(|(v, _)| version.accepts(v))
+    }
+
+    /// Returns true if the verifier index of `verifier` can verify the proofs created for `prover`,
+    /// two versions of the circuit `name`.
+    /// This is the case if both versions are semver-compatible and pin the same circuit.
+    pub fn can_verify(
+        &self,
+        name: &str,
+        verifier: &Version,
+        prover: &Version,
+    ) -> Result<bool, RegistryError> {
+        let verifier_circuit = self.get(name, verifier)?;
+        let prover_circuit = self.get(name, prover)?;
+        let compatible = verifier.accepts(prover) || prover.accepts(verifier);
+        Ok(compatible && verifier_circuit.digest == prover_circuit.digest)
+    }
+
+    /// Returns the names of the circuits registered under `prefix`, in the hierarchy of names.
+    pub fn circuits_under<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let prefix = prefix.trim_end_matches('/');
+        self.circuits
+            .keys()
+            .map(String::as_str)
+            .filter(move |name| {
+                prefix.is_empty()
+                    || *name == prefix
+                    || name
+                        .strip_prefix(prefix)
+                        .map_or(false, |rest| rest.starts_with('/'))
+            })
+    }
+
+    /// Returns the registered versions of the circuit `name`, in increasing order.
+    pub fn versions<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Version> + 'a {
+        self.circuits.get(name).into_iter().flat_map(BTreeMap::keys)
+    }
+}
+
+/// A proof tagged with the digest of the circuit it was created for.
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
+pub struct TaggedProof<G: AffineCurve> {
+    /// the digest of the verifier index of the circuit (see [circuit_digest])
+    #[serde_as(as = "serde_with::Bytes")]
+    pub circuit: CircuitDigest,
+    /// the proof
+    pub proof: ProverProof<G>,
+}
+
+/// The verifier indexes of many circuits, keyed by their digest, to which tagged proofs are dispatched.
+pub struct ProofRegistry<G: CommitmentCurve> {
+    indexes: HashMap<CircuitDigest, Arc<VerifierIndex<G>>>,
+}
+
+impl<G: CommitmentCurve> Default for ProofRegistry<G> {
+    fn default() -> Self {
+        Self {
+            indexes: HashMap::new(),
+        }
+    }
+}
+
+impl<G> ProofRegistry<G>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    VerifierIndex<G>: Serialize,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `index`, and returns its digest. Registering the same circuit twice is a no-op.
+    pub fn register(&mut self, index: Arc<VerifierIndex<G>>) -> CircuitDigest {
+        let digest = circuit_digest(&index);
+        self.indexes.entry(digest).or_insert(index);
+        digest
+    }
+
+    /// Returns the verifier index of the circuit with the digest `circuit`.
+    pub fn get(&self, circuit: &CircuitDigest) -> Result<&Arc<VerifierIndex<G>>, RegistryError> {
+        self.indexes
+            .get(circuit)
+            .ok_or_else(|| RegistryError::UnknownCircuit(short_digest(circuit)))
+    }
+
+    /// The number of registered circuits.
+    pub fn len(&self) -> usize {
+        self.indexes.len()
+    }
+
+    /// Returns true if no circuit is registered.
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    /// Tags `proof` as a proof of the circuit `index`, which must be registered.
+    pub fn tag(
+        &self,
+        index: &VerifierIndex<G>,
+        proof: ProverProof<G>,
+    ) -> Result<TaggedProof<G>, RegistryError> {
+        let circuit = circuit_digest(index);
+        self.get(&circuit)?;
+        Ok(TaggedProof { circuit, proof })
+    }
+
+    /// Verifies the tagged proofs, each one against the verifier index of the circuit it is tagged with.
+    /// The proofs can be of different circuits, and are verified in a single batch.
+    pub fn verify<EFqSponge, EFrSponge>(
+        &self,
+        group_map: &G::Map,
+        proofs: &[TaggedProof<G>],
+    ) -> Result<(), RegistryError>
+    where
+        EFqSponge: Clone + FqSponge<G::BaseField, G, G::ScalarField>,
+        EFrSponge: FrSponge<G::ScalarField>,
+    {
+        let batch = proofs
+            .iter()
+            .map(|tagged| Ok((self.get(&tagged.circuit)?.as_ref(), &tagged.proof)))
+            .collect::<Result<Vec<_>, RegistryError>>()?;
+        batch_verify::<G, EFqSponge, EFrSponge>(group_map, &batch)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::polynomials::generic::testing::create_circuit;
+    use crate::prover_index::testing::new_index_for_test;
+    use mina_curves::pasta::vesta::Affine;
+
+    fn index(public: usize) -> Arc<VerifierIndex<Affine>> {
+        Arc::new(new_index_for_test(create_circuit(0, public), public).verifier_index())
+    }
+
+    #[test]
+    fn test_version_parsing() {
+        let v: Version = "1.2.3".parse().unwrap();
+        assert_eq!(v, Version::new(1, 2, 3));
+        assert_eq!(v.to_string(), "1.2.3");
+        assert!("1.2".parse::<Version>().is_err());
+        assert!("1.2.x".parse::<Version>().is_err());
+
+        assert!(Version::new(1, 2, 0).accepts(&Version::new(1, 3, 0)));
+        assert!(!Version::new(1, 3, 0).accepts(&Version::new(1, 2, 0)));
+        assert!(!Version::new(1, 2, 0).accepts(&Version::new(2, 0, 0)));
+        assert!(!Version::new(0, 1, 0).accepts(&Version::new(0, 2, 0)));
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = CircuitRegistry::new();
+        let (v1_2, v1_3, v2_0) = (
+            Version::new(1, 2, 0),
+            Version::new(1, 3, 0),
+            Version::new(2, 0, 0),
+        );
+
+        let digest = registry
+            .register("payments/transfer", v1_2, index(0))
+            .unwrap();
+        registry
+            .register("payments/transfer", v1_3, index(0))
+            .unwrap();
+        let other = registry
+            .register("payments/transfer", v2_0, index(3))
+            .unwrap();
+        registry
+            .register("payments/refund", v1_2, index(3))
+            .unwrap();
+        registry.register("payroll", v1_2, index(0)).unwrap();
+        assert_ne!(digest, other);
+
+        // versions are pinned
+        assert!(registry
+            .register("payments/transfer", v1_2, index(0))
+            .is_ok());
+        assert_eq!(
+            registry.register("payments/transfer", v1_2, index(3)),
+            Err(RegistryError::AlreadyRegistered {
+                name: "payments/transfer".to_string(),
+                version: v1_2
+            })
+        );
+        assert!(registry
+            .get_pinned("payments/transfer", &v1_3, &digest)
+            .is_ok());
+        assert!(registry
+            .get_pinned("payments/transfer", &v2_0, &digest)
+            .is_err());
+
+        // compatibility queries
+        assert_eq!(
+            registry.can_verify("payments/transfer", &v1_2, &v1_3),
+            Ok(true)
+        );
+        assert_eq!(
+            registry.can_verify("payments/transfer", &v1_3, &v2_0),
+            Ok(false)
+        );
+        assert!(registry
+            .can_verify("payments/transfer", &v1_2, &Version::new(1, 4, 0))
+            .is_err());
+        let (latest, _) = registry
+            .latest_compatible("payments/transfer", &v1_2)
+            .unwrap();
+        assert_eq!(*latest, v1_3);
+
+        // the hierarchy of names
+        let names: Vec<_> = registry.circuits_under("payments").collect();
+        assert_eq!(names, vec!["payments/refund", "payments/transfer"]);
+        assert_eq!(registry.circuits_under("").count(), 3);
+        let versions: Vec<_> = registry.versions("payments/transfer").copied().collect();
+        assert_eq!(versions, vec![v1_2, v1_3, v2_0]);
+    }
+
+    #[test]
+    fn test_proof_registry() {
+        use crate::circuits::polynomials::generic::testing::create_circuit_and_witness;
+        use groupmap::GroupMap;
+        use mina_curves::pasta::{fp::Fp, vesta::VestaParameters};
+        use oracle::{
+            poseidon::PlonkSpongeConstantsKimchi,
+            sponge::{DefaultFqSponge, DefaultFrSponge},
+        };
+        type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+        type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let mut registry = ProofRegistry::new();
+        let mut tagged = vec![];
+        for public in [0, 5] {
+            let (gates, witness, _) = create_circuit_and_witness(public);
+            let index = new_index_for_test(gates, public);
+            let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+                &group_map,
+                witness,
+                &index,
+                vec![],
+            )
+            .unwrap();
+
+            let verifier_index = Arc::new(index.verifier_index());
+            assert!(matches!(
+                registry.tag(&verifier_index, proof.clone()),
+                Err(RegistryError::UnknownCircuit(_))
+            ));
+            registry.register(Arc::clone(&verifier_index));
+            tagged.push(registry.tag(&verifier_index, proof).unwrap());
+        }
+        assert_eq!(registry.len(), 2);
+
+        // the proofs of both circuits are dispatched to their indexes, also after serialization
+        let bytes = rmp_serde::to_vec(&tagged).unwrap();
+        let tagged: Vec<TaggedProof<Affine>> = rmp_serde::from_slice(&bytes).unwrap();
+        registry
+            .verify::<BaseSponge, ScalarSponge>(&group_map, &tagged)
+            .unwrap();
+
+        // a proof tagged with the wrong circuit is rejected
+        let mut mixed = tagged[0].clone();
+        mixed.circuit = tagged[1].circuit;
+        assert!(matches!(
+            registry.verify::<BaseSponge, ScalarSponge>(&group_map, &[mixed]),
+            Err(RegistryError::Verify(_))
+        ));
+        let mut unknown = tagged[0].clone();
+        unknown.circuit = [0; 64];
+        assert_eq!(
+            registry
+                .verify::<BaseSponge, ScalarSponge>(&group_map, &[unknown])
+                .err(),
+            Some(RegistryError::UnknownCircuit(
+                "0000000000000000".to_string()
+            ))
+        );
+    }
+}
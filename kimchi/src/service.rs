@@ -0,0 +1,173 @@
+//! This module implements a [ProverService], which proves witnesses asynchronously
+//! for applications running on a [tokio] runtime (behind the `service` feature).
+//!
+//! A job is queued until one of the `max_jobs` slots of the service is free,
+//! then proven on the blocking pool of the runtime, so that the CPU-bound prover does not stall the async tasks.
+//! Jobs whose [estimated memory](job_memory) exceed the limit of the service are rejected before they are queued.
+
+use crate::circuits::wires::COLUMNS;
+use crate::error::ServiceError;
+use crate::plonk_sponge::FrSponge;
+use crate::prover::ProverProof;
+use crate::prover_index::ProverIndex;
+use ark_ec::AffineCurve;
+use ark_ff::PrimeField;
+use commitment_dlog::commitment::{CommitmentCurve, PolyComm};
+use oracle::FqSponge;
+use std::{future::Future, marker::PhantomData, mem::size_of, sync::Arc};
+use tokio::{sync::Semaphore, task};
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// Estimates the memory of a job proving with `key`, in bytes:
+/// the witness columns and the permutation aggregation,
+/// as vectors, polynomials and evaluations over the domain `d8`, and the quotient, which dominate the memory of the prover.
+pub fn job_memory<G: CommitmentCurve>(key: &ProverIndex<G>) -> usize {
+    let n = key.cs.domain.d1.size();
+    let d8 = key.cs.domain.d8.size();
+    let columns = COLUMNS + 1;
+    (columns * (2 * n + d8) + 2 * d8) * size_of::<Fr<G>>()
+}
+
+/// A service proving witnesses with a shared [ProverIndex].
+pub struct ProverService<G: CommitmentCurve, EFqSponge, EFrSponge> {
+    key: Arc<ProverIndex<G>>,
+    group_map: Arc<G::Map>,
+    slots: Arc<Semaphore>,
+    memory_limit: Option<usize>,
+    sponges: PhantomData<fn() -> (EFqSponge, EFrSponge)>,
+}
+
+impl<G, EFqSponge, EFrSponge> ProverService<G, EFqSponge, EFrSponge>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    G::Map: Send + Sync + 'static,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>> + 'static,
+    EFrSponge: FrSponge<Fr<G>> + 'static,
+{
+    /// Creates a service proving at most `max_jobs` witnesses at a time with `key`.
+    pub fn new(key: Arc<ProverIndex<G>>, group_map: G::Map, max_jobs: usize) -> Self {
+        assert!(max_jobs > 0, "the service cannot prove any job");
+        Self {
+            key,
+            group_map: Arc::new(group_map),
+            slots: Arc::new(Semaphore::new(max_jobs)),
+            memory_limit: None,
+            sponges: PhantomData,
+        }
+    }
+
+    /// Rejects the jobs that need more than `limit` bytes (see [job_memory]).
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    /// Returns the key the service proves with.
+    pub fn key(&self) -> &Arc<ProverIndex<G>> {
+        &self.key
+    }
+
+    /// Queues the proof of `witness`, and returns the future of the proof.
+    /// The proof is created even if the future is dropped once the job started.
+    pub fn prove(
+        &self,
+        witness: [Vec<Fr<G>>; COLUMNS],
+        prev_challenges: Vec<(Vec<Fr<G>>, PolyComm<G>)>,
+    ) -> impl Future<Output = Result<ProverProof<G>, ServiceError>> + Send + 'static {
+        let key = Arc::clone(&self.key);
+        let group_map = Arc::clone(&self.group_map);
+        let slots = Arc::clone(&self.slots);
+        let required = job_memory(&key);
+        let memory_limit = self.memory_limit;
+        async move {
+            if let Some(limit) = memory_limit {
+                if required > limit {
+                    return Err(ServiceError::MemoryLimitExceeded { required, limit });
+                }
+            }
+            let slot = slots
+                .acquire_owned()
+                .await
+                .map_err(|_| ServiceError::Cancelled)?;
+            task::spawn_blocking(move || {
+                let proof = ProverProof::create::<EFqSponge, EFrSponge>(
+                    &group_map,
+                    witness,
+                    &key,
+                    prev_challenges,
+                );
+                drop(slot);
+                proof
+            })
+            .await
+            .map_err(|_| ServiceError::Cancelled)?
+            .map_err(ServiceError::from)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::polynomials::generic::testing::{create_circuit, fill_in_witness};
+    use crate::prover_index::testing::new_index_for_test;
+    use crate::verifier::batch_verify;
+    use ark_ff::Zero;
+    use array_init::array_init;
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{
+        fp::Fp,
+        vesta::{Affine, VestaParameters},
+    };
+    use oracle::{
+        poseidon::PlonkSpongeConstantsKimchi,
+        sponge::{DefaultFqSponge, DefaultFrSponge},
+    };
+
+    type SpongeParams = PlonkSpongeConstantsKimchi;
+    type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+    type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+    fn witness(rows: usize, public: u64) -> [Vec<Fp>; COLUMNS] {
+        let mut witness: [Vec<Fp>; COLUMNS] = array_init(|_| vec![Fp::zero(); rows]);
+        fill_in_witness(0, &mut witness, &[Fp::from(public); 5]);
+        witness
+    }
+
+    #[test]
+    fn test_prover_service() {
+        let (prover_key, verifier_key) = new_index_for_test(create_circuit(0, 5), 5).into_keys();
+        let rows = prover_key.cs.gates.len();
+        let service = ProverService::<Affine, BaseSponge, ScalarSponge>::new(
+            Arc::new(prover_key),
+            <Affine as CommitmentCurve>::Map::setup(),
+            2,
+        );
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        // more jobs than slots
+        let jobs: Vec<_> = (0..4)
+            .map(|i| service.prove(witness(rows, i), vec![]))
+            .collect();
+        let proofs: Vec<_> = jobs
+            .into_iter()
+            .map(|job| runtime.block_on(job).unwrap())
+            .collect();
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let batch: Vec<_> = proofs.iter().map(|proof| (&verifier_key, proof)).collect();
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &batch).unwrap();
+
+        // a job over the memory limit is rejected
+        let required = job_memory(service.key());
+        let service = service.with_memory_limit(required - 1);
+        assert!(matches!(
+            runtime.block_on(service.prove(witness(rows, 0), vec![])),
+            Err(ServiceError::MemoryLimitExceeded { required: r, .. }) if r == required
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+//! This module implements the size reporting of proofs and indexes,
+//! as the sum of the sizes of the (compressed) curve points and field elements they contain.
+//! Sizes do not account for the framing overhead of a particular serialization format.
+
+use crate::circuits::{
+    constraints::ConstraintSystem,
+    gate::CircuitGate,
+    wires::{Wire, PERMUTS},
+};
+use crate::prover::ProverProof;
+use crate::prover_index::ProverIndex;
+use crate::verifier_index::VerifierIndex;
+use ark_ec::AffineCurve;
+use ark_ff::{FftField, Zero};
+use ark_poly::{univariate::DensePolynomial, Evaluations, Radix2EvaluationDomain as D};
+use ark_serialize::CanonicalSerialize;
+use commitment_dlog::commitment::{CommitmentCurve, PolyComm};
+use std::mem::size_of;
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+
+/// The size of a serialized field element.
+fn field_size<F: Zero + CanonicalSerialize>() -> usize {
+    F::zero().serialized_size()
+}
+
+/// The size of a serialized curve point.
+fn point_size<G: AffineCurve>() -> usize {
+    G::prime_subgroup_generator().serialized_size()
+}
+
+fn comm_size<G: AffineCurve>(comm: &PolyComm<G>) -> usize {
+    (comm.unshifted.len() + usize::from(comm.shifted.is_some())) * point_size::<G>()
+}
+
+fn poly_size<F: FftField>(poly: &DensePolynomial<F>) -> usize {
+    poly.coeffs.len() * field_size::<F>()
+}
+
+fn evals_size<F: FftField>(evals: &Evaluations<F, D<F>>) -> usize {
+    evals.evals.len() * field_size::<F>()
+}
+
+/// The size of a [ProverProof], broken down by component.
+///
+/// Proofs are already in the compressed form of the PLONK proofs:
+/// the evaluation of the linearization polynomial is not sent,
+/// as the verifier recomputes it (`ft_eval0`) from the other evaluations (Maller's optimization),
+/// and all the evaluations are opened with a single batched opening proof.
+/// Without lookups and previous challenges, and with the polynomials fitting in one chunk, a proof thus has
+/// - `COLUMNS + 1 + q` points for the commitments, with `q` the number of chunks of the quotient,
+/// - `2 log2(n) + 2` points and two scalars for the opening proof, for an SRS of size `n`,
+/// - `2 (COLUMNS + PERMUTS + 2) + 1` evaluations, the last one being `ft_eval1`,
+///
+/// on top of the public input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofSize {
+    /// the commitments to the witness, permutation, quotient and lookup polynomials
+    pub commitments: usize,
+    /// the batched opening proof
+    pub opening: usize,
+    /// the evaluations of the committed polynomials, including `ft_eval1`
+    pub evaluations: usize,
+    /// the public inputs
+    pub public: usize,
+    /// the challenges and commitments of the previous (recursive) proofs
+    pub prev_challenges: usize,
+}
+
+impl ProofSize {
+    pub fn total(&self) -> usize {
+        self.commitments + self.opening + self.evaluations + self.public + self.prev_challenges
+    }
+}
+
+impl<G: CommitmentCurve> ProverProof<G> {
+    /// Returns the size of the proof, broken down by component.
+    pub fn size(&self) -> ProofSize {
+        let f = field_size::<Fr<G>>();
+
+        let c = &self.commitments;
+        let mut commitments = c.w_comm.iter().map(comm_size).sum::<usize>()
+            + comm_size(&c.z_comm)
+            + comm_size(&c.t_comm);
+        if let Some(lookup) = &c.lookup {
+            commitments +=
+                lookup.sorted.iter().map(comm_size).sum::<usize>() + comm_size(&lookup.aggreg);
+        }
+
+        let p = &self.proof;
+        let opening = (2 * p.lr.len() + 2) * point_size::<G>() + 2 * f;
+
+        let evaluations = self
+            .evals
+            .iter()
+            .map(|e| {
+                let mut len = e.w.iter().chain(&e.s).map(Vec::len).sum::<usize>()
+                    + e.z.len()
+                    + e.generic_selector.len()
+                    + e.poseidon_selector.len();
+                if let Some(l) = &e.lookup {
+                    len += l.sorted.iter().map(Vec::len).sum::<usize>() + 2;
+                }
+                len * f
+            })
+            .sum::<usize>()
+            + f;
+
+        let prev_challenges = self
+            .prev_challenges
+            .iter()
+            .map(|(chals, comm)| chals.len() * f + comm_size(comm))
+            .sum();
+
+        ProofSize {
+            commitments,
+            opening,
+            evaluations,
+            public: self.public.len() * f,
+            prev_challenges,
+        }
+    }
+
+    /// Returns the size of the proof, in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size().total()
+    }
+}
+
+/// The size of a [ProverIndex], broken down by component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexSize {
+    /// the gates of the circuit (types, wiring and coefficients)
+    pub gates: usize,
+    /// the selector and coefficient polynomials, in coefficient and evaluation form
+    pub selectors: usize,
+    /// the permutation polynomials and the wire shifts
+    pub permutation: usize,
+    /// the other polynomials precomputed over the domains (Lagrange, vanishing and zero-knowledge polynomials)
+    pub precomputations: usize,
+    /// the lookup tables and selectors
+    pub lookup: usize,
+    /// the SRS the index refers to, which is shared between indexes and not part of [IndexSize::total]
+    pub srs: usize,
+}
+
+impl IndexSize {
+    pub fn total(&self) -> usize {
+        self.gates + self.selectors + self.permutation + self.precomputations + self.lookup
+    }
+}
+
+fn gates_size<F: FftField>(gates: &[CircuitGate<F>]) -> usize {
+    // one byte for the type of the gate
+    let wiring = 1 + PERMUTS * size_of::<Wire>();
+    gates
+        .iter()
+        .map(|g| wiring + g.coeffs.len() * field_size::<F>())
+        .sum()
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    pub(crate) fn size(&self) -> IndexSize {
+        let f = field_size::<F>();
+
+        let mut selectors = self.coefficients8.iter().map(evals_size).sum::<usize>()
+            + poly_size(&self.genericm)
+            + poly_size(&self.psm)
+            + [
+                &self.generic4,
+                &self.ps8,
+                &self.complete_addl4,
+                &self.mull8,
+                &self.emull,
+                &self.endomul_scalar8,
+            ]
+            .into_iter()
+            .map(evals_size)
+            .sum::<usize>();
+        if let Some(chacha8) = &self.chacha8 {
+            selectors += chacha8.iter().map(evals_size).sum::<usize>();
+        }
+        if let Some(partial_rounds8) = &self.partial_rounds8 {
+            selectors += partial_rounds8.iter().map(evals_size).sum::<usize>();
+        }
+        if let Some(boolean8) = &self.boolean8 {
+            selectors += evals_size(boolean8);
+        }
+
+        let permutation = self.sigmam.iter().map(poly_size).sum::<usize>()
+            + self
+                .sigmal1
+                .iter()
+                .chain(&self.sigmal8)
+                .map(evals_size)
+                .sum::<usize>()
+            + (self.sid.len() + self.shift.len()) * f;
+
+        let precomputations = poly_size(&self.zkpm)
+            + [
+                &self.l1,
+                &self.l04,
+                &self.l08,
+                &self.zero4,
+                &self.zero8,
+                &self.zkpl,
+                &self.vanishes_on_last_4_rows,
+            ]
+            .into_iter()
+            .chain(&self.perm_bnd8)
+            .map(evals_size)
+            .sum::<usize>();
+
+        let lookup = self.lookup_constraint_system.as_ref().map_or(0, |l| {
+            l.dummy_lookup_values.iter().map(Vec::len).sum::<usize>() * f
+                + l.lookup_tables
+                    .iter()
+                    .flatten()
+                    .map(poly_size)
+                    .sum::<usize>()
+                + l.lookup_tables8
+                    .iter()
+                    .flatten()
+                    .chain(&l.lookup_selectors)
+                    .map(evals_size)
+                    .sum::<usize>()
+        });
+
+        IndexSize {
+            gates: gates_size(&self.gates),
+            selectors,
+            permutation,
+            precomputations,
+            lookup,
+            srs: 0,
+        }
+    }
+}
+
+impl<G: CommitmentCurve> ProverIndex<G> {
+    /// Returns the size of the index, broken down by component.
+    pub fn size(&self) -> IndexSize {
+        IndexSize {
+            srs: (self.srs.g.len() + 1) * point_size::<G>(),
+            ..self.cs.size()
+        }
+    }
+
+    /// Returns the size of the index, in bytes, without the SRS it refers to.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size().total()
+    }
+}
+
+impl<G: CommitmentCurve> VerifierIndex<G> {
+    /// Returns the size of the commitments of the index, in bytes, without the SRS it refers to.
+    pub fn size_in_bytes(&self) -> usize {
+        let mut comms: Vec<&PolyComm<G>> = self.sigma_comm.iter().collect();
+        comms.extend(&self.coefficients_comm);
+        comms.extend([
+            &self.generic_comm,
+            &self.psm_comm,
+            &self.complete_add_comm,
+            &self.mul_comm,
+            &self.emul_comm,
+            &self.endomul_scalar_comm,
+        ]);
+        if let Some(chacha_comm) = &self.chacha_comm {
+            comms.extend(chacha_comm.iter());
+        }
+        if let Some(linear_combination_comm) = &self.linear_combination_comm {
+            comms.extend(linear_combination_comm.iter());
+        }
+        if let Some(partial_rounds_comm) = &self.partial_rounds_comm {
+            comms.extend(partial_rounds_comm.iter());
+        }
+        comms.extend(&self.boolean_comm);
+        if let Some(lookup) = &self.lookup_index {
+            comms.extend(lookup.lookup_tables.iter().flatten());
+            comms.extend(&lookup.lookup_selectors);
+        }
+        comms.into_iter().map(comm_size).sum::<usize>() + self.shift.len() * field_size::<Fr<G>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuits::polynomials::generic::testing::create_circuit_and_witness;
+    use crate::circuits::wires::COLUMNS;
+    use crate::prover::ProverProof;
+    use crate::prover_index::testing::new_index_for_test;
+    use commitment_dlog::commitment::CommitmentCurve;
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{
+        fp::Fp,
+        vesta::{Affine, VestaParameters},
+    };
+    use oracle::{
+        poseidon::PlonkSpongeConstantsKimchi,
+        sponge::{DefaultFqSponge, DefaultFrSponge},
+    };
+
+    type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+    type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+    #[test]
+    fn test_proof_size() {
+        let (gates, witness, public) = create_circuit_and_witness(5);
+
+        let index = new_index_for_test(gates, public.len());
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let proof =
+            ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+                .unwrap();
+
+        // 32-byte field elements and compressed points
+        let size = proof.size();
+        assert_eq!(size.public, 5 * 32);
+        assert_eq!(size.commitments, (COLUMNS + 1 + 7) * 32);
+        assert_eq!(size.prev_challenges, 0);
+        assert_eq!(proof.size_in_bytes(), size.total());
+
+        let index_size = index.size();
+        assert_eq!(index_size.srs, (index.srs.g.len() + 1) * 32);
+        assert!(index.size_in_bytes() > index.verifier_index().size_in_bytes());
+    }
+}
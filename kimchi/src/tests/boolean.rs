@@ -0,0 +1,63 @@
+use crate::circuits::{
+    builder::CircuitBuilder, check::check_witness, gate::GateType,
+    polynomials::generic::testing::create_circuit,
+};
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test;
+use crate::verifier::batch_verify;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// Proves the decomposition of a public value into 32 bits.
+#[test]
+fn test_unpack_with_boolean_gates() {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let x = b.public_input();
+    b.unpack(x, 32);
+    let (gates, generator) = b.build();
+
+    let public = [Fp::from(0xdead_beefu64)];
+    let witness = generator.generate(&public, &[]);
+    let index = new_index_for_test(gates, public.len());
+    assert!(index.cs.boolean8.is_some());
+    check_witness(&witness, &index.cs, &public).unwrap();
+
+    // a cell of a boolean gate that is not a boolean is rejected
+    let mut wrong = witness.clone();
+    let row = index
+        .cs
+        .gates
+        .iter()
+        .position(|gate| gate.typ == GateType::Boolean)
+        .unwrap();
+    wrong[0][row] += Fp::from(2u64);
+    assert!(check_witness(&wrong, &index.cs, &public).is_err());
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+    assert!(verifier_index.boolean_comm.is_some());
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+}
+
+#[test]
+fn test_boolean_selector_is_only_committed_when_used() {
+    let index = new_index_for_test(create_circuit(0, 0), 0);
+    assert!(index.cs.boolean8.is_none());
+    assert!(index.verifier_index().boolean_comm.is_none());
+}
@@ -0,0 +1,85 @@
+use crate::circuits::{
+    check::{check_witness, counterexample, QuotientTerm},
+    constraints::{ConstraintSystem, GateError, ZK_ROWS},
+    expr::Column,
+    gate::CircuitGate,
+    polynomials::generic::testing::{create_circuit, create_circuit_and_witness},
+    wires::COLUMNS,
+};
+use crate::error::SetupError;
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test_with_cs;
+use crate::verifier::batch_verify;
+use ark_ff::{One, Zero};
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+fn create(gates: Vec<CircuitGate<Fp>>, public: usize) -> ConstraintSystem<Fp> {
+    ConstraintSystem::create(gates, vec![], oracle::pasta::fp_kimchi::params(), public).unwrap()
+}
+
+/// Pins the output of the last row of a circuit, and the permutation aggregation on several rows.
+#[test]
+fn test_boundary_constraints() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+    let last = gates.len() - 1;
+
+    let mut cs = create(gates, public.len());
+    cs.add_boundary_constraint(last, Column::Witness(2), witness[2][last])
+        .unwrap();
+    cs.add_boundary_constraint(0, Column::Z, Fp::one()).unwrap();
+    check_witness(&witness, &cs, &public).unwrap();
+
+    // another output does not satisfy the circuit
+    let mut wrong = witness.clone();
+    wrong[2][last] += Fp::one();
+    let err = check_witness(&wrong, &cs, &public).unwrap_err();
+    assert!(matches!(err, GateError::Boundary(cell) if cell.row == last && cell.col == 2));
+    let counterexample = counterexample(&wrong, &cs, &public).unwrap();
+    assert_eq!(counterexample.term, QuotientTerm::Boundary);
+    assert_eq!(counterexample.row, last);
+
+    let index = new_index_for_test_with_cs(cs);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+    assert_eq!(verifier_index.boundary.len(), 2);
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // the pinned values are part of the circuit
+    let mut other = verifier_index;
+    other.boundary[0].value += Fp::one();
+    assert_ne!(other.digest(), index.verifier_index().digest());
+}
+
+#[test]
+fn test_invalid_boundary_constraints() {
+    let mut cs = create(create_circuit(0, 0), 0);
+    let zk_row = (cs.domain.d1.size - ZK_ROWS) as usize;
+    assert_eq!(
+        cs.add_boundary_constraint(zk_row, Column::Witness(0), Fp::zero()),
+        Err(SetupError::InvalidBoundary {
+            row: zk_row,
+            column: Column::Witness(0)
+        })
+    );
+    assert!(cs
+        .add_boundary_constraint(0, Column::Coefficient(0), Fp::zero())
+        .is_err());
+    assert!(cs.boundary.is_empty());
+}
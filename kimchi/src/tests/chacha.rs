@@ -1,3 +1,4 @@
+use crate::circuits::check::check_witness;
 use crate::{
     circuits::{
         gate::CircuitGate,
@@ -81,6 +82,8 @@ fn chacha_prover() {
 
     let group_map = <Affine as CommitmentCurve>::Map::setup();
 
+    check_witness(&witness, &index.cs, &[]).unwrap();
+
     let start = Instant::now();
     let proof =
         ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
@@ -0,0 +1,71 @@
+use crate::circuits::{
+    constraints::ConstraintSystem, polynomials::generic::testing::create_circuit_and_witness,
+    wires::COLUMNS,
+};
+use crate::pasta::{
+    PallasBaseSponge, PallasScalarSponge, PastaCurve, VestaBaseSponge, VestaScalarSponge,
+};
+use crate::plonk_sponge::FrSponge;
+use crate::prover::ProverProof;
+use crate::prover_index::ProverIndex;
+use crate::verifier::batch_verify;
+use crate::verifier_index::VerifierIndex;
+use ark_ec::AffineCurve;
+use ark_ff::{One, PrimeField};
+use commitment_dlog::srs::SRS;
+use groupmap::GroupMap;
+use mina_curves::pasta::{pallas, vesta};
+use oracle::FqSponge;
+use std::sync::Arc;
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// Proves and verifies the same generic circuit over `G`, with the sponges of `G`.
+fn prove_and_verify<G, EFqSponge, EFrSponge>()
+where
+    G: PastaCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let cs = ConstraintSystem::create(gates, vec![], G::fr_sponge_params(), public.len()).unwrap();
+    let mut srs = SRS::<G>::create(cs.domain.d1.size as usize);
+    srs.add_lagrange_basis(cs.domain.d1);
+    let index = ProverIndex::<G>::create(cs, G::fq_sponge_params(), G::endo_q(), Arc::new(srs));
+
+    let group_map = <G as commitment_dlog::commitment::CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<EFqSponge, EFrSponge>(&group_map, witness, &index, vec![]).unwrap();
+
+    // the verifier index survives a round trip through serialization
+    let mut bytes = vec![];
+    index.verifier_index().write(&mut bytes).unwrap();
+    let verifier_index = VerifierIndex::<G>::read(
+        &bytes[..],
+        |_| index.srs.clone(),
+        index.cs.endo,
+        G::fq_sponge_params(),
+        G::fr_sponge_params(),
+    )
+    .unwrap();
+    batch_verify::<G, EFqSponge, EFrSponge>(&group_map, &[(&verifier_index, &proof)]).unwrap();
+
+    let mut wrong = proof.clone();
+    wrong.public[0] += Fr::<G>::one();
+    assert!(
+        batch_verify::<G, EFqSponge, EFrSponge>(&group_map, &[(&verifier_index, &wrong)]).is_err()
+    );
+}
+
+#[test]
+fn test_vesta() {
+    prove_and_verify::<vesta::Affine, VestaBaseSponge, VestaScalarSponge>();
+}
+
+#[test]
+fn test_pallas() {
+    prove_and_verify::<pallas::Affine, PallasBaseSponge, PallasScalarSponge>();
+}
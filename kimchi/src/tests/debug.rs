@@ -0,0 +1,125 @@
+use crate::circuits::{
+    builder::CircuitBuilder,
+    check::QuotientTerm,
+    gate::{CircuitGate, GateType},
+    polynomials::generic::testing::create_circuit_and_witness,
+    wires::COLUMNS,
+};
+use crate::debug::{DebugProof, IndexPolynomial};
+use crate::error::DebugError;
+use crate::prover_index::{testing::new_index_for_test, ProverIndex};
+use ark_ff::One;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+fn prove(
+    gates: Vec<CircuitGate<Fp>>,
+    witness: [Vec<Fp>; COLUMNS],
+    public: usize,
+) -> (ProverIndex<Affine>, DebugProof<Affine>) {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public);
+    let proof = DebugProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+        .unwrap();
+    (index, proof)
+}
+
+fn verify(index: &ProverIndex<Affine>, proof: &DebugProof<Affine>) -> Result<(), DebugError> {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    proof.verify::<BaseSponge, ScalarSponge>(&group_map, index)
+}
+
+fn term(proof: &mut DebugProof<Affine>, term: QuotientTerm) -> &mut Fp {
+    &mut proof
+        .terms
+        .iter_mut()
+        .find(|(t, _)| *t == term)
+        .expect("the prover reveals every term")
+        .1
+}
+
+#[test]
+fn test_debug_proof_generic_gates() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+    let (index, proof) = prove(gates, witness, public.len());
+    assert_eq!(verify(&index, &proof), Ok(()));
+
+    // a discrepancy is localized to its term
+    let generic = QuotientTerm::Gate(GateType::Generic);
+    let mut tampered = proof.clone();
+    *term(&mut tampered, generic) += Fp::one();
+    assert_eq!(
+        verify(&index, &tampered),
+        Err(DebugError::TermMismatch(vec![generic]))
+    );
+
+    // even when the terms still add up to the quotient
+    *term(&mut tampered, QuotientTerm::PublicInput) -= Fp::one();
+    assert_eq!(
+        verify(&index, &tampered),
+        Err(DebugError::TermMismatch(vec![
+            generic,
+            QuotientTerm::PublicInput
+        ]))
+    );
+
+    // the revealed evaluations are bound to the commitments
+    let mut tampered = proof.clone();
+    tampered.index_evals[0].1[0][0] += Fp::one();
+    assert_eq!(verify(&index, &tampered), Err(DebugError::OpeningFailed));
+
+    let mut tampered = proof.clone();
+    tampered.quotient_evals[0][0] += Fp::one();
+    assert_eq!(verify(&index, &tampered), Err(DebugError::OpeningFailed));
+
+    let mut tampered = proof;
+    tampered.index_evals.pop();
+    assert!(matches!(
+        verify(&index, &tampered),
+        Err(DebugError::MissingEvaluations(IndexPolynomial::Sigma(_)))
+    ));
+}
+
+#[test]
+fn test_debug_proof_weighted_sum() {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let out = b.public_input();
+    let xs: Vec<_> = (0..8).map(|_| b.private_input()).collect();
+    let terms: Vec<_> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (Fp::from(i as u64 + 1), *x))
+        .collect();
+    let sum = b.weighted_sum(&terms);
+    b.assert_equal(sum, out);
+    let (gates, generator) = b.build();
+
+    // sum_i i * i for i = 1..8
+    let private: Vec<Fp> = (1..=8u64).map(Fp::from).collect();
+    let public = [Fp::from(204u64)];
+    let witness = generator.generate(&public, &private);
+    let (index, proof) = prove(gates, witness, public.len());
+
+    let lincomb = QuotientTerm::Gate(GateType::LinearCombination);
+    assert!(proof.terms.iter().any(|(t, _)| *t == lincomb));
+    assert_eq!(verify(&index, &proof), Ok(()));
+
+    let mut tampered = proof;
+    *term(&mut tampered, lincomb) += Fp::one();
+    assert_eq!(
+        verify(&index, &tampered),
+        Err(DebugError::TermMismatch(vec![lincomb]))
+    );
+}
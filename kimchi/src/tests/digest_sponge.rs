@@ -0,0 +1,70 @@
+use crate::circuits::polynomials::generic::testing::create_circuit_and_witness;
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test;
+use crate::verifier::batch_verify;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    digest_sponge::{Blake2bFqSponge, Blake2bFrSponge, Sha256FqSponge, Sha256FrSponge},
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type PoseidonBaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+type PoseidonScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+type BaseSponge = Blake2bFqSponge<VestaParameters>;
+type ScalarSponge = Blake2bFrSponge<Fp>;
+
+#[test]
+fn test_digest_sponges() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public.len());
+    let verifier_index = index.verifier_index();
+
+    // Blake2b
+    let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &index,
+        vec![],
+    )
+    .unwrap();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // the challenges of the proof are bound to the hash function
+    assert!(
+        batch_verify::<Affine, PoseidonBaseSponge, PoseidonScalarSponge>(
+            &group_map,
+            &[(&verifier_index, &proof)]
+        )
+        .is_err()
+    );
+
+    // SHA-256
+    type Sha256BaseSponge = Sha256FqSponge<VestaParameters>;
+    type Sha256ScalarSponge = Sha256FrSponge<Fp>;
+    let proof = ProverProof::create::<Sha256BaseSponge, Sha256ScalarSponge>(
+        &group_map,
+        witness,
+        &index,
+        vec![],
+    )
+    .unwrap();
+    batch_verify::<Affine, Sha256BaseSponge, Sha256ScalarSponge>(
+        &group_map,
+        &[(&verifier_index, &proof)],
+    )
+    .unwrap();
+    assert!(batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&verifier_index, &proof)]
+    )
+    .is_err());
+}
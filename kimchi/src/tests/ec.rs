@@ -1,3 +1,4 @@
+use crate::circuits::check::check_witness;
 use crate::{
     circuits::{
         gate::{CircuitGate, GateType},
@@ -167,6 +168,7 @@ fn ec_test() {
     }
 
     index.cs.verify(&witness, &[]).unwrap();
+    check_witness(&witness, &index.cs, &[]).unwrap();
 
     let start = Instant::now();
     let proof =
@@ -1,3 +1,4 @@
+use crate::circuits::check::check_witness;
 use crate::prover::ProverProof;
 use crate::verifier::batch_verify;
 use crate::{
@@ -131,6 +132,8 @@ fn endomul_test() {
         assert_eq!(x.into_repr(), res.n.into_repr());
     }
 
+    check_witness(&witness, &index.cs, &[]).unwrap();
+
     let start = Instant::now();
     let proof =
         ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
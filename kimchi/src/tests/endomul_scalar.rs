@@ -1,3 +1,4 @@
+use crate::circuits::check::check_witness;
 use crate::{
     circuits::{
         gate::{CircuitGate, GateType},
@@ -78,6 +79,8 @@ fn endomul_scalar_test() {
         );
     }
 
+    check_witness(&witness, &index.cs, &[]).unwrap();
+
     let start = Instant::now();
     let proof =
         ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
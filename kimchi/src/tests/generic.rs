@@ -1,22 +1,42 @@
-use crate::circuits::polynomials::generic::testing::{create_circuit, fill_in_witness};
-use crate::circuits::{gate::CircuitGate, wires::COLUMNS};
-use crate::prover::ProverProof;
-use crate::prover_index::testing::new_index_for_test;
-use crate::verifier::batch_verify;
-use ark_ff::{UniformRand, Zero};
-use ark_poly::{univariate::DensePolynomial, UVPolynomial};
+use crate::circuits::check::check_witness;
+use crate::circuits::polynomials::generic::testing::{
+    create_circuit, create_circuit_and_witness, fill_in_witness,
+};
+use crate::circuits::{
+    builder::CircuitBuilder,
+    constraints::ConstraintSystem,
+    gate::CircuitGate,
+    wires::{COLUMNS, PERMUTS},
+};
+use crate::error::{ProofError, SetupError, VerifyError};
+use crate::prover::{testing::Committed, Prover, ProverProof};
+use crate::prover_index::{testing::new_index_for_test, ProverIndex};
+use crate::verifier::{
+    batch_verify, batch_verify_with_rng, verify_in_transcript, verify_with_message,
+};
+use crate::verifier_index::{TranscriptVersion, VerifierIndex};
+use ark_ff::{One, UniformRand, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Evaluations, Polynomial, UVPolynomial,
+};
 use array_init::array_init;
-use commitment_dlog::commitment::{b_poly_coefficients, ceil_log2, CommitmentCurve};
+use commitment_dlog::{
+    commitment::{b_poly_coefficients, ceil_log2, CommitmentCurve},
+    srs::{endos, SRS},
+};
 use groupmap::GroupMap;
 use mina_curves::pasta::{
     fp::Fp,
+    pallas::Affine as Pallas,
     vesta::{Affine, VestaParameters},
 };
 use oracle::{
     poseidon::PlonkSpongeConstantsKimchi,
     sponge::{DefaultFqSponge, DefaultFrSponge},
+    FqSponge,
 };
 use rand::{rngs::StdRng, SeedableRng};
+use std::sync::Arc;
 
 // aliases
 
@@ -49,6 +69,580 @@ fn test_generic_gate_pub() {
     verify_proof(gates, witness, &public);
 }
 
+#[test]
+fn test_generic_gate_chunked() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    // an SRS of half the size of the domain
+    let cs = ConstraintSystem::<Fp>::create(
+        gates,
+        vec![],
+        oracle::pasta::fp_kimchi::params(),
+        public.len(),
+    )
+    .unwrap();
+    let domain = cs.domain.d1.size as usize;
+    let mut srs = SRS::<Affine>::create(domain / 2);
+    srs.add_lagrange_basis(cs.domain.d1);
+    let srs = Arc::new(srs);
+    let (endo_q, _endo_r) = endos::<Pallas>();
+
+    // the chunks cannot be hidden
+    let chunked = Some(SetupError::ChunkedZeroKnowledge {
+        srs: domain / 2,
+        domain,
+    });
+    let err = ProverIndex::<Affine>::try_create(
+        cs.clone(),
+        oracle::pasta::fq_kimchi::params(),
+        endo_q,
+        srs.clone(),
+    )
+    .err();
+    assert_eq!(err, chunked);
+    let mut index = ProverIndex::<Affine>::try_create_without_zk(
+        cs,
+        oracle::pasta::fq_kimchi::params(),
+        endo_q,
+        srs,
+    )
+    .unwrap();
+    assert_eq!(index.set_zk(true).err(), chunked);
+    assert!(!index.zk);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    assert!(proof
+        .commitments
+        .w_comm
+        .iter()
+        .all(|c| c.unshifted.len() == 2));
+    assert!(proof.evals.iter().all(|e| e.z.len() == 2));
+
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // the public input is still bound to the proof
+    let mut wrong = proof.clone();
+    wrong.public[0] += Fp::one();
+    assert!(batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&verifier_index, &wrong)]
+    )
+    .is_err());
+}
+
+#[test]
+fn test_generic_gate_metrics() {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let (gates, witness, _) = create_circuit_and_witness(0);
+
+    let index = new_index_for_test(gates, 0);
+    let (proof, metrics) = ProverProof::create_with_metrics::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness,
+        &index,
+        vec![],
+    )
+    .unwrap();
+
+    // the phases are measured without a recorder, in the order of the prover
+    let phases: Vec<_> = metrics.phases.iter().map(|(phase, _)| *phase).collect();
+    assert_eq!(
+        phases,
+        vec![
+            "witness",
+            "interpolation",
+            "lookup",
+            "permutation",
+            "quotient",
+            "evaluations",
+            "opening"
+        ]
+    );
+    assert!(metrics.phase("quotient").unwrap() <= metrics.total());
+    assert_eq!(metrics.peak_memory, None);
+
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+}
+
+#[test]
+fn test_generic_gate_openings() {
+    let rng = &mut StdRng::from_seed([0u8; 32]);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+    let (gates, witness, _) = create_circuit_and_witness(0);
+
+    let index = new_index_for_test(gates, 0);
+    let (proof, polys) = ProverProof::create_with_polynomials::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &index,
+        vec![],
+    )
+    .unwrap();
+
+    // the witness polynomials interpolate the witness
+    let domain = index.cs.domain.d1;
+    for (col, poly) in witness.iter().zip(&polys.witness) {
+        for (row, value) in col.iter().enumerate() {
+            assert_eq!(poly.evaluate(&domain.element(row)), *value);
+        }
+    }
+
+    // the permutation aggregation starts at one
+    assert_eq!(polys.z.evaluate(&Fp::one()), Fp::one());
+
+    // open some committed polynomials at random points
+    let points = [Fp::rand(rng), Fp::rand(rng)];
+    for poly in [
+        Committed::Witness(0),
+        Committed::Witness(COLUMNS - 1),
+        Committed::Permutation,
+        Committed::Quotient,
+    ] {
+        let mut opening = polys.open::<BaseSponge>(&index, &group_map, poly, &points);
+        assert!(opening.verify::<BaseSponge>(&index, &group_map, &proof));
+
+        opening.evals[0][0] += Fp::one();
+        assert!(!opening.verify::<BaseSponge>(&index, &group_map, &proof));
+    }
+}
+
+#[test]
+fn test_generic_gate_verify_errors() {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let index = new_index_for_test(gates, public.len());
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+    let verify = |proofs: &[&ProverProof<Affine>]| {
+        let batch: Vec<_> = proofs.iter().map(|p| (&verifier_index, *p)).collect();
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &batch)
+    };
+    assert_eq!(verify(&[&proof, &proof]), Ok(()));
+
+    // the batching randomness can come from any cryptographic generator
+    let rng = &mut StdRng::from_seed([1u8; 32]);
+    batch_verify_with_rng::<Affine, BaseSponge, ScalarSponge, _>(
+        &group_map,
+        &[(&verifier_index, &proof)],
+        rng,
+    )
+    .unwrap();
+
+    // a missing public input
+    let mut wrong_public = proof.clone();
+    wrong_public.public.pop();
+    assert_eq!(
+        verify(&[&wrong_public]),
+        Err(VerifyError::PublicInputMismatch {
+            expected: 5,
+            got: 4
+        })
+    );
+
+    // a quotient commitment with a missing chunk
+    // (with an SRS as large as the domain, t of degree less than 7n is committed in 7 chunks)
+    assert_eq!(verifier_index.quotient_chunks(), PERMUTS);
+    assert_eq!(
+        crate::prover_index::quotient_chunks(PERMUTS * 16 + 1, 16),
+        PERMUTS + 1
+    );
+    let mut wrong_quotient = proof.clone();
+    wrong_quotient.commitments.t_comm.unshifted.pop();
+    assert_eq!(
+        verify(&[&wrong_quotient]),
+        Err(VerifyError::QuotientMismatch {
+            expected: PERMUTS,
+            got: PERMUTS - 1
+        })
+    );
+
+    // a wrong evaluation is caught by the opening proof, which points to the faulty proof
+    let mut wrong_eval = proof.clone();
+    wrong_eval.evals[0].z[0] += Fp::one();
+    assert_eq!(
+        verify(&[&proof, &wrong_eval]),
+        Err(VerifyError::CommitmentOpeningFailed { which: 1 })
+    );
+}
+
+#[test]
+fn test_generic_gate_prove_errors() {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+    let (gates, witness, public) = create_circuit_and_witness(5);
+    let rows = gates.len();
+
+    let index = new_index_for_test(gates.clone(), public.len());
+    let domain = index.cs.domain.d1.size as usize;
+    let prove = |witness: [Vec<Fp>; COLUMNS]| {
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .map(|_| ())
+    };
+
+    let mut short = witness.clone();
+    short[4].pop();
+    assert_eq!(
+        prove(short),
+        Err(ProofError::WitnessLengthMismatch {
+            col: 4,
+            expected: rows,
+            got: rows - 1
+        })
+    );
+
+    // the public input is read from the first rows of the witness
+    let empty: [Vec<Fp>; COLUMNS] = array_init(|_| vec![Fp::zero(); 3]);
+    assert_eq!(
+        prove(empty),
+        Err(ProofError::WitnessTooShort { rows: 3, public: 5 })
+    );
+
+    // the last rows of the domain are reserved for zero-knowledge
+    let mut long = witness.clone();
+    for col in long.iter_mut() {
+        col.resize(domain - 1, Fp::zero());
+    }
+    assert_eq!(
+        prove(long),
+        Err(ProofError::DomainTooSmall {
+            rows: domain - 1,
+            domain
+        })
+    );
+
+    let mut wrong = witness.clone();
+    wrong[2][public.len()] += Fp::one();
+    assert!(matches!(
+        prove(wrong),
+        Err(ProofError::UnsatisfiedGate { .. })
+    ));
+
+    // an SRS without the Lagrange basis of the domain
+    let cs = index.cs.clone();
+    let (endo_q, _endo_r) = endos::<Pallas>();
+    let without_basis = ProverIndex::<Affine>::try_create(
+        cs,
+        oracle::pasta::fq_kimchi::params(),
+        endo_q,
+        Arc::new(SRS::<Affine>::create(domain)),
+    )
+    .unwrap();
+    assert_eq!(
+        ProverProof::create::<BaseSponge, ScalarSponge>(
+            &group_map,
+            witness,
+            &without_basis,
+            vec![]
+        )
+        .map(|_| ()),
+        Err(ProofError::MissingLagrangeBasis { domain })
+    );
+}
+
+#[test]
+fn test_generic_gate_prover() {
+    let gates = create_circuit(0, 5);
+    let rows = gates.len();
+    let index = new_index_for_test(gates, 5);
+    let prover = Prover::<Affine, BaseSponge, ScalarSponge>::new(index);
+    let verifier_index = prover.index().verifier_index();
+
+    // the same prover creates proofs for different witnesses
+    for public in [vec![Fp::from(3u8); 5], vec![Fp::from(7u8); 5]] {
+        let mut witness: [Vec<Fp>; COLUMNS] = array_init(|_| vec![Fp::zero(); rows]);
+        fill_in_witness(0, &mut witness, &public);
+
+        let proof = prover.prove(witness).unwrap();
+        assert_eq!(proof.public, public);
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(
+            prover.group_map(),
+            &[(&verifier_index, &proof)],
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_generic_gate_challenges() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public.len());
+    let verifier_index = index.verifier_index();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+
+    let oracles = proof
+        .challenges::<BaseSponge, ScalarSponge>(&verifier_index)
+        .unwrap();
+
+    // the first challenges only depend on the circuit, the public input and the witness commitments
+    let mut fq_sponge = BaseSponge::new(verifier_index.fq_sponge_params.clone());
+    fq_sponge.absorb_fq(&[verifier_index.digest()]);
+    fq_sponge.absorb_fr(&proof.public);
+    fq_sponge.absorb_g(&proof.public_comm(&verifier_index).unshifted);
+    for comm in &proof.commitments.w_comm {
+        fq_sponge.absorb_g(&comm.unshifted);
+    }
+    assert_eq!(oracles.beta, fq_sponge.challenge());
+    assert_eq!(oracles.gamma, fq_sponge.challenge());
+
+    // changing the transcript changes the challenges
+    let mut other = proof.clone();
+    other.commitments.z_comm = other.commitments.t_comm.clone();
+    other.commitments.z_comm.unshifted.truncate(1);
+    let other_oracles = other
+        .challenges::<BaseSponge, ScalarSponge>(&verifier_index)
+        .unwrap();
+    assert_eq!(other_oracles.beta, oracles.beta);
+    assert_ne!(other_oracles.alpha, oracles.alpha);
+    assert_ne!(other_oracles.zeta, oracles.zeta);
+
+    // a proof of the wrong shape can not be replayed
+    let mut malformed = proof;
+    malformed.public.pop();
+    assert!(malformed
+        .challenges::<BaseSponge, ScalarSponge>(&verifier_index)
+        .is_err());
+}
+
+#[test]
+fn test_generic_gate_without_zk() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let mut index = new_index_for_test(gates, public.len());
+    index.set_zk(false).unwrap();
+    let prove = || {
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness.clone(), &index, vec![])
+            .unwrap()
+    };
+    let proof = prove();
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // the commitments are not blinded: they only depend on the witness
+    let d1 = index.cs.domain.d1;
+    let mut padded = witness[0].clone();
+    padded.resize(d1.size(), Fp::zero());
+    let evals = Evaluations::from_vec_and_domain(padded, d1);
+    assert_eq!(
+        proof.commitments.w_comm[0].unshifted,
+        index
+            .srs
+            .commit_evaluations_non_hiding(d1, &evals, None)
+            .unshifted
+    );
+    let other = prove();
+    for (a, b) in other
+        .commitments
+        .w_comm
+        .iter()
+        .zip(&proof.commitments.w_comm)
+    {
+        assert_eq!(a.unshifted, b.unshifted);
+    }
+}
+
+#[test]
+fn test_generic_gate_transcript_versions() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let mut index = new_index_for_test(gates, public.len());
+    assert_eq!(index.transcript_version, TranscriptVersion::LATEST);
+    let latest = index.verifier_index();
+
+    for version in [
+        TranscriptVersion::V0,
+        TranscriptVersion::V1,
+        TranscriptVersion::V2,
+    ] {
+        index.set_transcript_version(version);
+        let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+            &group_map,
+            witness.clone(),
+            &index,
+            vec![],
+        )
+        .unwrap();
+
+        // the verifier follows the version of its index
+        let verifier_index = index.verifier_index();
+        assert_eq!(verifier_index.transcript_version, version);
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+            .unwrap();
+
+        // and rejects the proofs in another format
+        let verified =
+            batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&latest, &proof)]);
+        assert_eq!(verified.is_ok(), version == TranscriptVersion::LATEST);
+    }
+
+    // the version survives a round trip through serialization
+    let mut index_bytes = vec![];
+    index.verifier_index().write(&mut index_bytes).unwrap();
+    let verifier_index = VerifierIndex::<Affine>::read(
+        &index_bytes[..],
+        |_| index.srs.clone(),
+        index.cs.endo,
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+    .unwrap();
+    assert_eq!(verifier_index.transcript_version, TranscriptVersion::V2);
+}
+
+#[test]
+fn test_generic_gate_shared_transcript() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public.len());
+    let verifier_index = index.verifier_index();
+
+    // two proofs in a session that starts from a statement of the protocol
+    let mut session = BaseSponge::new(index.fq_sponge_params.clone());
+    session.absorb_fr(&[Fp::from(42u8)]);
+    let (first, transcript) = ProverProof::create_in_transcript::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &index,
+        vec![],
+        session.clone(),
+    )
+    .unwrap();
+    let (second, mut transcript) = ProverProof::create_in_transcript::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness,
+        &index,
+        vec![],
+        transcript,
+    )
+    .unwrap();
+
+    // the verifier replays the session, and ends up in the same state
+    let verified = verify_in_transcript::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &verifier_index,
+        &first,
+        session.clone(),
+    )
+    .unwrap();
+    let mut verified = verify_in_transcript::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &verifier_index,
+        &second,
+        verified,
+    )
+    .unwrap();
+    assert_eq!(transcript.challenge(), verified.challenge());
+
+    // the proofs are bound to their place in the session
+    assert!(batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&verifier_index, &first)]
+    )
+    .is_err());
+    assert!(verify_in_transcript::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &verifier_index,
+        &second,
+        session,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_generic_gate_message() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public.len());
+    let verifier_index = index.verifier_index();
+    let message = [7u8; 40];
+    let proof = ProverProof::create_with_message::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness,
+        &index,
+        vec![],
+        &message,
+    )
+    .unwrap();
+    let verify = |message: &[u8]| {
+        verify_with_message::<Affine, BaseSponge, ScalarSponge>(
+            &group_map,
+            &verifier_index,
+            &proof,
+            message,
+        )
+    };
+    assert_eq!(verify(&message), Ok(()));
+
+    // the proof does not verify for another message, even one that differs by trailing zeros
+    let mut other = message;
+    other[39] = 8;
+    assert!(verify(&other).is_err());
+    assert!(verify(&[message.to_vec(), vec![0]].concat()).is_err());
+    assert!(batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&verifier_index, &proof)]
+    )
+    .is_err());
+}
+
+/// Proves that `(2 * x + 3 * y + 1) * m` is public, with a fused generic gate.
+#[test]
+fn test_generic_gate_fused() {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let out = b.public_input();
+    let x = b.private_input();
+    let y = b.private_input();
+    let m = b.private_input();
+    let rows = b.rows();
+    let prod = b.affine_mul(2u64.into(), x, 3u64.into(), y, Fp::one(), m);
+    // the square takes the second half of the row of the fused gate
+    b.mul(prod, prod);
+    assert_eq!(b.rows() - rows, 1);
+    b.assert_equal(prod, out);
+    let (gates, generator) = b.build();
+
+    let public = [Fp::from(90u64)];
+    let private = [4u64.into(), 3u64.into(), 5u64.into()];
+    let witness = generator.generate(&public, &private);
+
+    let index = new_index_for_test(gates.clone(), public.len());
+    assert!(index.cs.fused_generic);
+    assert!(index.verifier_index().fused_generic);
+
+    // a wrong claim is caught by the fused product
+    let wrong = [public[0] + Fp::one()];
+    let wrong_witness = generator.generate(&wrong, &private);
+    assert!(index.cs.verify(&wrong_witness, &wrong).is_err());
+    assert!(check_witness(&wrong_witness, &index.cs, &wrong).is_err());
+
+    verify_proof(gates, witness, &public);
+
+    // the circuits without fused products keep their constraints
+    let index = new_index_for_test(create_circuit(0, 0), 0);
+    assert!(!index.cs.fused_generic);
+    assert!(!index.verifier_index().fused_generic);
+}
+
 fn verify_proof(gates: Vec<CircuitGate<Fp>>, witness: [Vec<Fp>; COLUMNS], public: &[Fp]) {
     // set up
     let rng = &mut StdRng::from_seed([0u8; 32]);
@@ -59,6 +653,7 @@ fn verify_proof(gates: Vec<CircuitGate<Fp>>, witness: [Vec<Fp>; COLUMNS], public
 
     // verify the circuit satisfiability by the computed witness
     index.cs.verify(&witness, public).unwrap();
+    check_witness(&witness, &index.cs, public).unwrap();
 
     // previous opening for recursion
     let prev = {
@@ -0,0 +1,71 @@
+use crate::circuits::{
+    builder::CircuitBuilder, check::check_witness, polynomials::generic::testing::create_circuit,
+};
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test;
+use crate::verifier::batch_verify;
+use ark_ff::One;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// Proves that the dot product of a private vector with the vector `(1, 2, ..., 16)` is public.
+#[test]
+fn test_weighted_sum() {
+    let n = 16;
+
+    let mut b = CircuitBuilder::<Fp>::new();
+    let out = b.public_input();
+    let xs: Vec<_> = (0..n).map(|_| b.private_input()).collect();
+    let terms: Vec<_> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (Fp::from(i as u64 + 1), *x))
+        .collect();
+    let rows = b.rows();
+    let sum = b.weighted_sum(&terms);
+    // the first row takes 6 terms, and the next ones 5 terms and the sum of the previous row
+    assert_eq!(b.rows() - rows, 3);
+    b.assert_equal(sum, out);
+    let (gates, generator) = b.build();
+
+    // sum_i i * i for i = 1..16
+    let private: Vec<Fp> = (1..=n as u64).map(Fp::from).collect();
+    let public = [Fp::from(1496u64)];
+    let witness = generator.generate(&public, &private);
+
+    let index = new_index_for_test(gates, public.len());
+    check_witness(&witness, &index.cs, &public).unwrap();
+
+    // a wrong claim is caught by the gates
+    let wrong = [public[0] + Fp::one()];
+    let wrong_witness = generator.generate(&wrong, &private);
+    assert!(check_witness(&wrong_witness, &index.cs, &wrong).is_err());
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+    assert!(verifier_index.linear_combination_comm.is_some());
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+}
+
+#[test]
+fn test_weights_are_only_committed_when_used() {
+    let index = new_index_for_test(create_circuit(0, 0), 0);
+    assert!(index.cs.linear_combination8.is_none());
+    assert!(index.verifier_index().linear_combination_comm.is_none());
+}
@@ -0,0 +1,219 @@
+use crate::circuits::{
+    builder::CircuitBuilder,
+    check::check_witness,
+    constraints::{ConstraintSystem, GateError},
+    gate::{CircuitGate, GateType},
+    polynomials::runtime_tables::RuntimeTableCfg,
+};
+use crate::error::{ProofError, SetupError};
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test_with_cs;
+use crate::verifier::batch_verify;
+use ark_ff::One;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+fn create(
+    gates: Vec<CircuitGate<Fp>>,
+    tables: Vec<Vec<Vec<Fp>>>,
+    public: usize,
+) -> Result<ConstraintSystem<Fp>, SetupError> {
+    ConstraintSystem::create(gates, tables, oracle::pasta::fp_kimchi::params(), public)
+}
+
+/// A table of the values `0..n`.
+fn range_table(n: u64) -> Vec<Vec<Fp>> {
+    vec![(0..n).map(Fp::from).collect()]
+}
+
+/// A table of the pairs `(x, x^2)` for `x` in `0..n`.
+fn square_table(n: u64) -> Vec<Vec<Fp>> {
+    vec![
+        (0..n).map(Fp::from).collect(),
+        (0..n).map(|x| Fp::from(x * x)).collect(),
+    ]
+}
+
+/// Proves that four private values are bytes, with a table of 256 entries,
+/// in a circuit that also has a table it does not look up.
+#[test]
+fn test_range_lookup() {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let bytes: Vec<_> = (0..4).map(|_| b.private_input()).collect();
+    b.lookup(0, &bytes);
+    let (gates, generator) = b.build();
+    let tables = vec![range_table(256), square_table(16)];
+    let cs = create(gates, tables, 0).unwrap();
+    // the domain holds the entries of the table
+    assert!(cs.domain.d1.size >= 256);
+
+    let private = [0u64, 17, 128, 255].map(Fp::from);
+    let witness = generator.generate(&[], &private);
+    check_witness(&witness, &cs, &[]).unwrap();
+
+    // a value that is not a byte is rejected
+    let out_of_range = generator.generate(&[], &[0u64, 17, 256, 255].map(Fp::from));
+    assert!(matches!(
+        check_witness(&out_of_range, &cs, &[]),
+        Err(GateError::Custom { .. })
+    ));
+
+    let index = new_index_for_test_with_cs(cs);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+    assert!(verifier_index.lookup_index.is_some());
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+}
+
+/// Proves the squares of five values, with a table of two columns.
+#[test]
+fn test_pair_lookup() {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let xs: Vec<_> = (0..5).map(|_| b.private_input()).collect();
+    let pairs: Vec<_> = xs
+        .iter()
+        .map(|x| (*x, b.compute_one(&[*x], |v| v[0] * v[0])))
+        .collect();
+    b.lookup_pairs(1, &pairs);
+    let (gates, generator) = b.build();
+    let cs = create(gates, vec![range_table(4), square_table(16)], 0).unwrap();
+
+    let private = [1u64, 2, 3, 5, 15].map(Fp::from);
+    let witness = generator.generate(&[], &private);
+    check_witness(&witness, &cs, &[]).unwrap();
+
+    // a wrong square is rejected
+    let row = cs
+        .gates
+        .iter()
+        .position(|gate| gate.typ == GateType::Lookup)
+        .unwrap();
+    let mut wrong = witness.clone();
+    wrong[1][row] += Fp::one();
+    assert!(check_witness(&wrong, &cs, &[]).is_err());
+
+    let index = new_index_for_test_with_cs(cs);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&index.verifier_index(), &proof)],
+    )
+    .unwrap();
+}
+
+/// Proves bytes and squares in the same circuit, with the two tables of [test_range_lookup].
+#[test]
+fn test_several_lookup_tables() {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let bytes: Vec<_> = (0..4).map(|_| b.private_input()).collect();
+    b.lookup(0, &bytes);
+    let x = b.private_input();
+    let square = b.compute_one(&[x], |v| v[0] * v[0]);
+    b.lookup_pairs(1, &[(x, square)]);
+    let (gates, generator) = b.build();
+    let cs = create(gates, vec![range_table(256), square_table(16)], 0).unwrap();
+    let lcs = cs.lookup_constraint_system.as_ref().unwrap();
+    assert!(lcs.table_ids);
+    // the domain holds the entries of both tables
+    assert!(cs.domain.d1.size >= 256 + 16);
+
+    let private = [0u64, 17, 128, 255, 13].map(Fp::from);
+    let witness = generator.generate(&[], &private);
+    check_witness(&witness, &cs, &[]).unwrap();
+
+    let index = new_index_for_test_with_cs(cs);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &index,
+        vec![],
+    )
+    .unwrap();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&index.verifier_index(), &proof)],
+    )
+    .unwrap();
+
+    // a byte is not a square
+    let mut b = CircuitBuilder::<Fp>::new();
+    let bytes: Vec<_> = (0..2).map(|_| b.private_input()).collect();
+    b.lookup(0, &bytes);
+    b.lookup_pairs(1, &[(bytes[0], bytes[1])]);
+    let (gates, generator) = b.build();
+    let cs = create(gates, vec![range_table(256), square_table(16)], 0).unwrap();
+    // (3, 0) is an entry of the range table, whose entries have a zero second cell
+    let witness = generator.generate(&[], &[3u64, 0].map(Fp::from));
+    assert!(matches!(
+        check_witness(&witness, &cs, &[]),
+        Err(GateError::Custom { .. })
+    ));
+    let index = new_index_for_test_with_cs(cs);
+    let err =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![]).err();
+    assert_eq!(err, Some(ProofError::ValueNotInTable));
+}
+
+#[test]
+fn test_invalid_lookup_tables() {
+    let circuit = |ids: &[usize]| {
+        let mut b = CircuitBuilder::<Fp>::new();
+        let x = b.private_input();
+        for id in ids {
+            b.lookup(*id, &[x]);
+        }
+        b.build().0
+    };
+
+    assert!(matches!(
+        create(circuit(&[1]), vec![range_table(4)], 0),
+        Err(SetupError::UnknownLookupTable { .. })
+    ));
+    let runtime = || RuntimeTableCfg {
+        first_column: (0..4u64).map(Fp::from).collect(),
+    };
+    assert_eq!(
+        ConstraintSystem::create_with_runtime_tables(
+            circuit(&[1, 2]),
+            vec![range_table(4)],
+            vec![runtime(), runtime()],
+            oracle::pasta::fp_kimchi::params(),
+            0
+        )
+        .err(),
+        Some(SetupError::SeveralRuntimeTables)
+    );
+
+    let mut wide = square_table(4);
+    wide.push(range_table(4).remove(0));
+    assert!(matches!(
+        create(circuit(&[0]), vec![wide], 0),
+        Err(SetupError::InvalidLookupTable { id: 0, .. })
+    ));
+    let mut ragged = square_table(4);
+    ragged[1].pop();
+    assert!(matches!(
+        create(circuit(&[0]), vec![ragged], 0),
+        Err(SetupError::InvalidLookupTable { id: 0, .. })
+    ));
+}
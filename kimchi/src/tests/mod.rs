@@ -1,7 +1,21 @@
+mod boolean;
+mod boundary;
 mod chacha;
+mod curves;
+mod debug;
+mod digest_sponge;
 mod ec;
 mod endomul;
 mod endomul_scalar;
 mod generic;
+mod linear_combination;
+mod lookup_gate;
 mod poseidon;
+mod properties;
+mod public_computation;
+mod public_input;
+mod reference_verifier;
+mod runtime_tables;
+mod serialization;
+mod threads;
 mod varbasemul;
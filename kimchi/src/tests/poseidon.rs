@@ -1,3 +1,4 @@
+use crate::circuits::check::check_witness;
 use crate::{
     circuits::{
         gate::CircuitGate,
@@ -19,7 +20,7 @@ use mina_curves::pasta::{
     vesta::{Affine, VestaParameters},
 };
 use oracle::{
-    poseidon::{PlonkSpongeConstantsKimchi, SpongeConstants},
+    poseidon::{sbox, PlonkSpongeConstantsKimchi, SpongeConstants},
     sponge::{DefaultFqSponge, DefaultFrSponge},
 };
 use rand::{rngs::StdRng, SeedableRng};
@@ -79,6 +80,69 @@ fn test_poseidon() {
     positive(&index);
 }
 
+#[test]
+fn test_poseidon_partial_rounds() {
+    // 4 full rounds, 12 partial rounds then 4 full rounds,
+    // so that the first and last rows mix full and partial rounds
+    let partial: Vec<bool> = (0..20).map(|round| (4..16).contains(&round)).collect();
+    let rows = partial.len() / ROUNDS_PER_ROW;
+    let params = oracle::pasta::fp_kimchi::params();
+    let circuit = |partial: &[bool]| {
+        let (gates, _) = CircuitGate::<Fp>::create_poseidon_gadget_with_rounds(
+            0,
+            [Wire::new(0), Wire::new(rows)],
+            &params.round_constants,
+            partial,
+        );
+        new_index_for_test(gates, PUBLIC)
+    };
+    let index = circuit(&partial);
+    assert!(index.cs.partial_rounds8.is_some());
+
+    let rng = &mut StdRng::from_seed([0u8; 32]);
+    let input = [Fp::rand(rng), Fp::rand(rng), Fp::rand(rng)];
+    let mut witness: [Vec<Fp>; COLUMNS] = array_init(|_| vec![Fp::zero(); rows + 1]);
+    poseidon::generate_witness_with_rounds(0, &params, &mut witness, input, &partial);
+
+    // the output is the one of the permutation
+    let mut state = input;
+    for (round, partial) in partial.iter().enumerate() {
+        for (i, s) in state.iter_mut().enumerate() {
+            if i == 0 || !partial {
+                *s = sbox::<Fp, SpongeParams>(*s);
+            }
+        }
+        state = array_init(|i| {
+            params.mds[i]
+                .iter()
+                .zip(&state)
+                .fold(params.round_constants[round][i], |acc, (m, s)| acc + *m * s)
+        });
+    }
+    let output: [Fp; 3] = array_init(|i| witness[i][rows]);
+    assert_eq!(output, state);
+
+    index.cs.verify(&witness, &[]).unwrap();
+    check_witness(&witness, &index.cs, &[]).unwrap();
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &index,
+        vec![],
+    )
+    .unwrap();
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // the same rounds as full rounds do not accept the witness
+    let full = circuit(&[false; 20]);
+    assert!(full.cs.partial_rounds8.is_none());
+    assert!(check_witness(&witness, &full.cs, &[]).is_err());
+}
+
 /// creates a proof and verifies it
 fn positive(index: &ProverIndex<Affine>) {
     // constant
@@ -130,6 +194,7 @@ fn positive(index: &ProverIndex<Affine>) {
 
         // verify the circuit satisfiability by the computed witness
         index.cs.verify(&witness_cols, &[]).unwrap();
+        check_witness(&witness_cols, &index.cs, &[]).unwrap();
 
         //
         let prev = {
@@ -0,0 +1,205 @@
+//! Property-based tests over random small circuits of generic gates:
+//! honest witnesses satisfy the constraints and their proofs verify,
+//! while a mutated witness breaks a constraint and a mutated proof is rejected.
+
+use crate::circuits::{
+    builder::CircuitBuilder,
+    constraints::ConstraintSystem,
+    gate::{CircuitGate, GateType},
+    polynomials::generic::{GENERIC_COEFFS, GENERIC_REGISTERS},
+    wires::COLUMNS,
+};
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test;
+use crate::verifier::batch_verify;
+use ark_ff::{One, Zero};
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use proptest::prelude::*;
+
+type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+/// An operation of a random circuit, on the indices (modulo the number of variables) of the variables so far.
+#[derive(Clone, Debug)]
+enum Op {
+    Add(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    AddConstant(usize, u64),
+}
+
+fn arb_op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (any::<usize>(), any::<usize>()).prop_map(|(a, b)| Op::Add(a, b)),
+        (any::<usize>(), any::<usize>()).prop_map(|(a, b)| Op::Sub(a, b)),
+        (any::<usize>(), any::<usize>()).prop_map(|(a, b)| Op::Mul(a, b)),
+        (any::<usize>(), any::<u64>()).prop_map(|(a, c)| Op::AddConstant(a, c)),
+    ]
+}
+
+/// A random circuit, with a satisfying witness.
+#[derive(Debug)]
+struct RandomCircuit {
+    gates: Vec<CircuitGate<Fp>>,
+    public: Vec<Fp>,
+    witness: [Vec<Fp>; COLUMNS],
+}
+
+/// Builds the circuit applying `ops` to the inputs, whose result is its last public input.
+fn build(public: &[Fp], private: &[Fp], ops: &[Op]) -> RandomCircuit {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let mut vars: Vec<_> = public.iter().map(|_| b.public_input()).collect();
+    let output = b.public_input();
+    vars.extend(private.iter().map(|_| b.private_input()));
+
+    let mut values: Vec<Fp> = public.iter().chain(private).copied().collect();
+    for op in ops {
+        let n = vars.len();
+        let (var, value) = match *op {
+            Op::Add(i, j) => (
+                b.add(vars[i % n], vars[j % n]),
+                values[i % n] + values[j % n],
+            ),
+            Op::Sub(i, j) => (
+                b.sub(vars[i % n], vars[j % n]),
+                values[i % n] - values[j % n],
+            ),
+            Op::Mul(i, j) => (
+                b.mul(vars[i % n], vars[j % n]),
+                values[i % n] * values[j % n],
+            ),
+            Op::AddConstant(i, c) => (
+                b.add_constant(vars[i % n], Fp::from(c)),
+                values[i % n] + Fp::from(c),
+            ),
+        };
+        vars.push(var);
+        values.push(value);
+    }
+    b.assert_equal(*vars.last().unwrap(), output);
+    let (gates, generator) = b.build();
+
+    let mut public = public.to_vec();
+    public.push(*values.last().unwrap());
+    let witness = generator.generate(&public, private);
+    RandomCircuit {
+        gates,
+        public,
+        witness,
+    }
+}
+
+prop_compose! {
+    fn arb_circuit()(
+        public in prop::collection::vec(any::<u64>(), 0..3),
+        private in prop::collection::vec(any::<u64>(), 1..4),
+        ops in prop::collection::vec(arb_op(), 1..12),
+    ) -> RandomCircuit {
+        let public: Vec<_> = public.into_iter().map(Fp::from).collect();
+        let private: Vec<_> = private.into_iter().map(Fp::from).collect();
+        build(&public, &private, &ops)
+    }
+}
+
+/// Returns the cells of the generic gates of `circuit` that weigh on their gate:
+/// changing the value of one of them alone breaks the constraint of its gate.
+fn constrained_cells(circuit: &RandomCircuit) -> Vec<(usize, usize)> {
+    let mut cells = vec![];
+    for (row, gate) in circuit.gates.iter().enumerate() {
+        if gate.typ != GateType::Generic {
+            continue;
+        }
+        for half in 0..2 {
+            let coeff = |i| {
+                gate.coeffs
+                    .get(half * GENERIC_COEFFS + i)
+                    .copied()
+                    .unwrap_or_else(Fp::zero)
+            };
+            let cell = |i: usize| circuit.witness[half * GENERIC_REGISTERS + i][row];
+            // the derivatives of the constraint with respect to the left, right and output cells
+            let weights = [
+                coeff(0) + coeff(3) * cell(1),
+                coeff(1) + coeff(3) * cell(0),
+                coeff(2),
+            ];
+            for (i, weight) in weights.iter().enumerate() {
+                if !weight.is_zero() {
+                    cells.push((row, half * GENERIC_REGISTERS + i));
+                }
+            }
+        }
+    }
+    cells
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_mutated_witness_breaks_constraints(
+        circuit in arb_circuit(),
+        which: usize,
+        delta in 1..u64::MAX,
+    ) {
+        let params = oracle::pasta::fp_kimchi::params();
+        let cs = ConstraintSystem::create(
+            circuit.gates.clone(),
+            vec![],
+            params,
+            circuit.public.len(),
+        )
+        .unwrap();
+        prop_assert!(cs.verify(&circuit.witness, &circuit.public).is_ok());
+
+        let cells = constrained_cells(&circuit);
+        prop_assert!(!cells.is_empty());
+        let (row, col) = cells[which % cells.len()];
+        let mut witness = circuit.witness.clone();
+        witness[col][row] += Fp::from(delta);
+        prop_assert!(cs.verify(&witness, &circuit.public).is_err());
+    }
+}
+
+proptest! {
+    // proofs are slower to create
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn test_mutated_proof_is_rejected(circuit in arb_circuit(), mutation in 0..6usize, col in 0..COLUMNS) {
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let index = new_index_for_test(circuit.gates.clone(), circuit.public.len());
+        let verifier_index = index.verifier_index();
+        let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+            &group_map,
+            circuit.witness.clone(),
+            &index,
+            vec![],
+        )
+        .unwrap();
+        let verify = |proof: &ProverProof<Affine>| {
+            batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, proof)])
+        };
+        prop_assert_eq!(verify(&proof), Ok(()));
+
+        let mut mutated = proof.clone();
+        match mutation {
+            0 => mutated.evals[0].w[col][0] += Fp::one(),
+            1 => mutated.evals[1].z[0] += Fp::one(),
+            2 => mutated.ft_eval1 += Fp::one(),
+            3 => *mutated.public.last_mut().unwrap() += Fp::one(),
+            4 => mutated.commitments.w_comm.swap(col, (col + 1) % COLUMNS),
+            _ => mutated.proof.z1 += Fp::one(),
+        }
+        prop_assert!(verify(&mutated).is_err());
+    }
+}
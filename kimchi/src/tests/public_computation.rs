@@ -0,0 +1,67 @@
+use crate::circuits::builder::{testing::verify_generic_circuit, CircuitBuilder};
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test;
+use crate::verifier::batch_verify;
+use ark_ff::One;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// Proves the public claim "the `n`-th Fibonacci number, starting from `f0` and `f1`, is `out`":
+/// every wire of the circuit is determined by the public inputs.
+#[test]
+fn test_public_fibonacci_claim() {
+    let n = 50;
+
+    let mut b = CircuitBuilder::<Fp>::new();
+    let f0 = b.public_input();
+    let f1 = b.public_input();
+    let out = b.public_input();
+    let (_, fib) = b.unroll(n - 1, (f0, f1), |b, _, (x, y)| (y, b.add(x, y)));
+    b.assert_equal(fib, out);
+    let (gates, generator) = b.build();
+    assert_eq!(generator.private_inputs(), 0);
+
+    // the 50th Fibonacci number
+    let public: [Fp; 3] = [0u64.into(), 1u64.into(), 12586269025u64.into()];
+    let witness = generator.generate_public(&public);
+    verify_generic_circuit(&gates, &witness, &public).unwrap();
+
+    // a false claim has no valid witness
+    let mut wrong = public;
+    wrong[2] += Fp::one();
+    let wrong_witness = generator.generate_public(&wrong);
+    assert!(verify_generic_circuit(&gates, &wrong_witness, &wrong).is_err());
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public.len());
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+
+    // the verifier only needs the claim, which is part of the transcript
+    assert_eq!(proof.public, public);
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // the proof does not verify against another claim
+    let mut tampered = proof;
+    tampered.public = wrong.to_vec();
+    assert!(batch_verify::<Affine, BaseSponge, ScalarSponge>(
+        &group_map,
+        &[(&verifier_index, &tampered)]
+    )
+    .is_err());
+}
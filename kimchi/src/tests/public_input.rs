@@ -0,0 +1,121 @@
+use crate::circuits::{builder::CircuitBuilder, public_input::PublicInputLayout};
+use crate::error::{PublicInputError, SetupError, VerifyError};
+use crate::prover::ProverProof;
+use crate::prover_index::testing::new_index_for_test;
+use crate::verifier::{batch_verify, verify_named, verify_prepared};
+use crate::verifier_index::VerifierIndex;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use std::collections::HashMap;
+
+type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+type ScalarSponge = DefaultFrSponge<Fp, PlonkSpongeConstantsKimchi>;
+
+#[test]
+fn test_named_public_inputs() {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+    // root = x^2 and nullifier = x + 1, for a private x
+    let mut b = CircuitBuilder::<Fp>::new();
+    let root = b.named_public_input("root");
+    let nullifier = b.named_public_input("nullifier");
+    let x = b.private_input();
+    let x2 = b.mul(x, x);
+    b.assert_equal(x2, root);
+    let x1 = b.add_constant(x, Fp::from(1u64));
+    b.assert_equal(x1, nullifier);
+    let (gates, generator) = b.build();
+    let layout = generator.public_layout().clone();
+    assert_eq!(
+        layout,
+        PublicInputLayout::named(&["root", "nullifier"]).unwrap()
+    );
+
+    let mut index = new_index_for_test(gates, layout.len());
+    let unnamed_verifier_index = index.verifier_index();
+    assert_eq!(unnamed_verifier_index.public_layout, None);
+
+    // the layout must cover all the public inputs
+    let short = PublicInputLayout::named(&["root"]).unwrap();
+    assert_eq!(
+        index.set_public_layout(short),
+        Err(SetupError::PublicInputLayout {
+            slots: 1,
+            public: 2
+        })
+    );
+    index.set_public_layout(layout).unwrap();
+    let verifier_index = index.verifier_index();
+
+    let values: HashMap<_, _> = [("nullifier", Fp::from(4u64)), ("root", Fp::from(9u64))]
+        .into_iter()
+        .collect();
+    let public = verifier_index.public_input(&values).unwrap();
+    let witness = generator.generate(&public, &[Fp::from(3u64)]);
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+
+    let verify = |verifier_index: &VerifierIndex<Affine>, values: &HashMap<&str, Fp>| {
+        verify_named::<Affine, BaseSponge, ScalarSponge>(&group_map, verifier_index, &proof, values)
+    };
+    assert_eq!(verify(&verifier_index, &values), Ok(()));
+
+    let mut wrong = values.clone();
+    wrong.insert("root", Fp::from(10u64));
+    assert_eq!(
+        verify(&verifier_index, &wrong),
+        Err(VerifyError::PublicInput(PublicInputError::ValueMismatch(
+            "root".to_string()
+        )))
+    );
+    assert_eq!(
+        verify(&unnamed_verifier_index, &values),
+        Err(VerifyError::PublicInput(PublicInputError::NoLayout))
+    );
+}
+
+#[test]
+fn test_prepared_verifier_index() {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+
+    // y = x^3 for a public y and a private x
+    let mut b = CircuitBuilder::<Fp>::new();
+    let y = b.public_input();
+    let x = b.private_input();
+    let x2 = b.mul(x, x);
+    let x3 = b.mul(x2, x);
+    b.assert_equal(x3, y);
+    let (gates, generator) = b.build();
+
+    let index = new_index_for_test(gates, 1);
+    let witness = generator.generate(&[Fp::from(8u64)], &[Fp::from(2u64)]);
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+
+    let prepared = index.verifier_index().prepare();
+    assert_eq!(
+        verify_prepared::<Affine, BaseSponge, ScalarSponge>(&group_map, &prepared, &proof),
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&prepared.index, &proof)])
+    );
+    assert_eq!(
+        verify_prepared::<Affine, BaseSponge, ScalarSponge>(&group_map, &prepared, &proof),
+        Ok(())
+    );
+
+    // the precomputed data does not make the verifier accept another public input
+    let mut wrong = proof.clone();
+    wrong.public[0] = Fp::from(9u64);
+    assert!(
+        verify_prepared::<Affine, BaseSponge, ScalarSponge>(&group_map, &prepared, &wrong).is_err()
+    );
+}
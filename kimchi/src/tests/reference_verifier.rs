@@ -0,0 +1,388 @@
+//! A deliberately naive verifier, used to differentially test the prover and the optimized verifier.
+//!
+//! Instead of relying on the linearization and on the batched opening proof,
+//! [reference_verify] recomputes everything it can from the polynomials kept by the prover
+//! (see [ProverProof::create_with_polynomials]):
+//! the commitments are recomputed from the polynomials and their blinders,
+//! the evaluations are recomputed by evaluating the polynomials,
+//! and the constraints are checked one argument at a time at $\zeta$,
+//! using the full (unchunked) polynomials of the witness and of the index.
+//! Only the challenges are derived as in the optimized verifier, as they define the transcript.
+//! `ft_eval1` and the opening proof are not checked, as they only exist for the optimized verifier.
+
+use crate::circuits::{
+    argument::ArgumentType,
+    expr::{Column, Constants, Variable},
+    gate::{CircuitGate, GateType},
+    polynomials::{
+        generic::{self, FUSED_REGISTER, GENERIC_COEFFS, GENERIC_REGISTERS},
+        permutation,
+    },
+    wires::*,
+};
+use crate::linearization::constraints_expr;
+use crate::prover::{testing::ProverPolynomials, ProverProof};
+use crate::prover_index::{testing::new_index_for_test, ProverIndex};
+use crate::verifier::batch_verify;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_poly::{univariate::DensePolynomial, EvaluationDomain, Evaluations, Polynomial};
+use array_init::array_init;
+use commitment_dlog::{commitment::CommitmentCurve, PolyComm};
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    pallas::Affine as Other,
+    vesta::{Affine, VestaParameters},
+};
+use o1_utils::ExtendedDensePolynomial;
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// Checks `proof` against the polynomials `polys` the prover committed to,
+/// without using the optimizations of [batch_verify].
+pub(crate) fn reference_verify(
+    index: &ProverIndex<Affine>,
+    proof: &ProverProof<Affine>,
+    polys: &ProverPolynomials<Affine>,
+) -> Result<(), String> {
+    let cs = &index.cs;
+    let d1 = cs.domain.d1;
+    if cs.lookup_constraint_system.is_some() {
+        return Err("lookups are not supported".into());
+    }
+    if !proof.prev_challenges.is_empty() {
+        return Err("recursion is not supported".into());
+    }
+
+    //
+    // polynomials and commitments
+    //
+
+    let public = -Evaluations::from_vec_and_domain(proof.public.clone(), d1).interpolate();
+    if public != polys.public {
+        return Err("public input polynomial".into());
+    }
+
+    let hiding = |poly: &DensePolynomial<Fp>, blinders: &PolyComm<Fp>| {
+        let mut comm = index.srs.commit_non_hiding(poly, None);
+        if comm.unshifted.len() > blinders.unshifted.len() {
+            return None;
+        }
+        // the missing chunks are commitments to zero
+        comm.unshifted
+            .resize(blinders.unshifted.len(), Affine::zero());
+        index.srs.mask_custom(comm, blinders)
+    };
+    let check_commitment = |name: &str,
+                            poly: &DensePolynomial<Fp>,
+                            blinders: &PolyComm<Fp>,
+                            comm: &PolyComm<Affine>| {
+        match hiding(poly, blinders) {
+            Some(c) if c.unshifted == comm.unshifted && c.shifted == comm.shifted => Ok(()),
+            _ => Err(format!("commitment to {}", name)),
+        }
+    };
+    for col in 0..COLUMNS {
+        check_commitment(
+            &format!("witness column {}", col),
+            &polys.witness[col],
+            &polys.witness_blinders[col],
+            &proof.commitments.w_comm[col],
+        )?;
+    }
+    check_commitment(
+        "permutation",
+        &polys.z,
+        &polys.z_blinders,
+        &proof.commitments.z_comm,
+    )?;
+    check_commitment(
+        "quotient",
+        &polys.t,
+        &polys.t_blinders,
+        &proof.commitments.t_comm,
+    )?;
+
+    //
+    // challenges
+    //
+
+    let verifier_index = index.verifier_index();
+    let public_comm = index.srs.commit_non_hiding(&public, None);
+    let oracles_result = proof.oracles::<BaseSponge, ScalarSponge>(&verifier_index, &public_comm);
+    let oracles = oracles_result.oracles;
+    let all_alphas = oracles_result.all_alphas;
+    let zeta = oracles.zeta;
+    let points = [zeta, zeta * d1.group_gen];
+
+    //
+    // evaluations
+    //
+
+    for (row, (evals, pt)) in proof.evals.iter().zip(points).enumerate() {
+        let chunked = |poly: &DensePolynomial<Fp>| poly.eval(pt, index.max_poly_size);
+        let mut expected = vec![("permutation", chunked(&polys.z), &evals.z)];
+        expected.push((
+            "generic selector",
+            chunked(&cs.genericm),
+            &evals.generic_selector,
+        ));
+        expected.push((
+            "poseidon selector",
+            chunked(&cs.psm),
+            &evals.poseidon_selector,
+        ));
+        for (col, poly) in polys.witness.iter().enumerate() {
+            expected.push(("witness", chunked(poly), &evals.w[col]));
+        }
+        for (col, poly) in cs.sigmam[..PERMUTS - 1].iter().enumerate() {
+            expected.push(("sigma", chunked(poly), &evals.s[col]));
+        }
+        for (name, expected, actual) in expected {
+            if expected != *actual {
+                return Err(format!("evaluation of the {} at row {}", name, row));
+            }
+        }
+    }
+
+    //
+    // constraints at zeta
+    //
+
+    let w: [[Fp; COLUMNS]; 2] =
+        array_init(|row| array_init(|col| polys.witness[col].evaluate(&points[row])));
+    let z = [polys.z.evaluate(&points[0]), polys.z.evaluate(&points[1])];
+    let coefficients: [Fp; COLUMNS] =
+        array_init(|i| cs.coefficients8[i].interpolate_by_ref().evaluate(&zeta));
+    let vanishing = d1.evaluate_vanishing_polynomial(zeta);
+
+    // generic gates
+    let generic = {
+        let mut alphas =
+            all_alphas.get_alphas(ArgumentType::Gate(GateType::Generic), generic::CONSTRAINTS);
+        let gate = |alpha: Fp, c: &[Fp], w: &[Fp]| {
+            let sum: Fp = (0..GENERIC_REGISTERS).map(|i| c[i] * w[i]).sum();
+            alpha * (sum + c[GENERIC_REGISTERS] * w[0] * w[1] + c[GENERIC_REGISTERS + 1])
+        };
+        let alpha = alphas.next().unwrap();
+        let mut first = gate(alpha, &coefficients, &w[0]);
+        if cs.fused_generic {
+            let c = &coefficients[2 * GENERIC_COEFFS..];
+            first += alpha * (c[0] * w[0][0] + c[1] * w[0][1] + c[2]) * w[0][FUSED_REGISTER];
+        }
+        let second = gate(
+            alphas.next().unwrap(),
+            &coefficients[GENERIC_COEFFS..],
+            &w[0][GENERIC_REGISTERS..],
+        );
+        cs.genericm.evaluate(&zeta) * (first + second)
+    };
+
+    // permutation
+    let perm = {
+        let mut alphas = all_alphas.get_alphas(ArgumentType::Permutation, permutation::CONSTRAINTS);
+        let (beta, gamma) = (oracles.beta, oracles.gamma);
+        let shifts = (0..PERMUTS)
+            .map(|i| w[0][i] + gamma + beta * zeta * cs.shift[i])
+            .fold(z[0], |acc, x| acc * x);
+        let sigmas = (0..PERMUTS)
+            .map(|i| w[0][i] + gamma + beta * cs.sigmam[i].evaluate(&zeta))
+            .fold(z[1], |acc, x| acc * x);
+        let (alpha0, alpha1, alpha2) = (
+            alphas.next().unwrap(),
+            alphas.next().unwrap(),
+            alphas.next().unwrap(),
+        );
+        let last = cs.sid[d1.size() - 3];
+        alpha0 * cs.zkpm.evaluate(&zeta) * (shifts - sigmas)
+            + (z[0] - Fp::one())
+                * vanishing
+                * (alpha1 / (zeta - Fp::one()) + alpha2 / (zeta - last))
+    };
+
+    // the other gates, from their constraint expressions
+    let gates = {
+        let mut selectors = HashMap::new();
+        use GateType::*;
+        selectors.insert(Generic, cs.genericm.clone());
+        selectors.insert(Poseidon, cs.psm.clone());
+        selectors.insert(CompleteAdd, cs.complete_addl4.interpolate_by_ref());
+        selectors.insert(VarBaseMul, cs.mull8.interpolate_by_ref());
+        selectors.insert(EndoMul, cs.emull.interpolate_by_ref());
+        selectors.insert(EndoMulScalar, cs.endomul_scalar8.interpolate_by_ref());
+        if let Some(chacha) = &cs.chacha8 {
+            for (typ, evals) in [ChaCha0, ChaCha1, ChaCha2, ChaChaFinal].iter().zip(chacha) {
+                selectors.insert(*typ, evals.interpolate_by_ref());
+            }
+        }
+        if let Some(boolean) = &cs.boolean8 {
+            selectors.insert(Boolean, boolean.interpolate_by_ref());
+        }
+        let weights: Vec<Fp> = cs
+            .linear_combination8
+            .iter()
+            .flatten()
+            .map(|evals| evals.interpolate_by_ref().evaluate(&zeta))
+            .collect();
+        let partial_rounds: Vec<Fp> = cs
+            .partial_rounds8
+            .iter()
+            .flatten()
+            .map(|evals| evals.interpolate_by_ref().evaluate(&zeta))
+            .collect();
+
+        let cell = |v: &Variable| {
+            let row = v.row.shift();
+            match v.col {
+                Column::Witness(i) => Ok(w[row][i]),
+                Column::Z => Ok(z[row]),
+                Column::Coefficient(i) if row == 0 => Ok(coefficients[i]),
+                Column::Weight(i) if row == 0 => weights
+                    .get(i)
+                    .copied()
+                    .ok_or_else(|| "no weights for the linear combination gate".to_string()),
+                Column::PartialRound(i) if row == 0 => partial_rounds
+                    .get(i)
+                    .copied()
+                    .ok_or_else(|| "no selectors for the partial rounds".to_string()),
+                Column::Index(typ) => selectors
+                    .get(&typ)
+                    .map(|p| p.evaluate(&points[row]))
+                    .ok_or_else(|| format!("no selector for {:?}", typ)),
+                col => Err(format!("unexpected column {:?}", col)),
+            }
+        };
+        let constants = Constants {
+            alpha: oracles.alpha,
+            beta: oracles.beta,
+            gamma: oracles.gamma,
+            joint_combiner: Fp::zero(),
+            endo_coefficient: cs.endo,
+            mds: cs.fr_sponge_params.mds.clone(),
+        };
+        let (expr, _) = constraints_expr(
+            d1,
+            cs.chacha8.is_some(),
+            cs.linear_combination8.is_some(),
+            cs.partial_rounds8.is_some(),
+            cs.boolean8.is_some(),
+            &None,
+            &cs.custom_gates,
+            &cs.boundary,
+        );
+        expr.evaluate_cells(d1, zeta, &cell, &constants)?
+    };
+
+    let f = generic + perm + gates + public.evaluate(&zeta);
+    if f != polys.t.evaluate(&zeta) * vanishing {
+        return Err("the constraints do not hold at zeta".into());
+    }
+
+    Ok(())
+}
+
+/// Creates a proof, and checks that both verifiers accept it, and reject a tampered version of it.
+fn differential_test(gates: Vec<CircuitGate<Fp>>, witness: [Vec<Fp>; COLUMNS], public: usize) {
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let index = new_index_for_test(gates, public);
+    let (proof, polys) = ProverProof::create_with_polynomials::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness,
+        &index,
+        vec![],
+    )
+    .unwrap();
+
+    let verifier_index = index.verifier_index();
+    let optimized = |proof: &ProverProof<Affine>| {
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, proof)])
+    };
+
+    optimized(&proof).unwrap();
+    reference_verify(&index, &proof, &polys).unwrap();
+
+    // both verifiers catch a wrong witness evaluation
+    let mut tampered = proof.clone();
+    tampered.evals[0].w[0][0] += Fp::one();
+    assert!(optimized(&tampered).is_err());
+    assert!(reference_verify(&index, &tampered, &polys).is_err());
+
+    // and a wrong public input
+    if public > 0 {
+        let mut tampered = proof;
+        tampered.public[0] += Fp::one();
+        assert!(optimized(&tampered).is_err());
+        assert!(reference_verify(&index, &tampered, &polys).is_err());
+    }
+}
+
+#[test]
+fn reference_verifier_generic_gates() {
+    use crate::circuits::polynomials::generic::testing::create_circuit_and_witness;
+
+    for public in [0, 5] {
+        let (gates, witness, _) = create_circuit_and_witness(public);
+        differential_test(gates, witness, public);
+    }
+}
+
+#[test]
+fn reference_verifier_fused_generic_gates() {
+    use crate::circuits::builder::CircuitBuilder;
+
+    let mut b = CircuitBuilder::<Fp>::new();
+    let out = b.public_input();
+    let x = b.private_input();
+    let y = b.private_input();
+    let m = b.private_input();
+    let prod = b.affine_mul(2u64.into(), x, 3u64.into(), y, Fp::one(), m);
+    b.assert_equal(prod, out);
+    let (gates, generator) = b.build();
+    let witness = generator.generate(&[90u64.into()], &[4u64.into(), 3u64.into(), 5u64.into()]);
+    differential_test(gates, witness, 1);
+}
+
+#[test]
+fn reference_verifier_complete_add() {
+    let rng = &mut StdRng::from_seed([0; 32]);
+    let doubles = 10;
+
+    let gates = (0..doubles)
+        .map(|row| CircuitGate {
+            typ: GateType::CompleteAdd,
+            wires: Wire::new(row),
+            coeffs: vec![],
+        })
+        .collect();
+
+    let mut witness: [Vec<Fp>; COLUMNS] = array_init(|_| vec![]);
+    let mut p = Other::prime_subgroup_generator()
+        .into_projective()
+        .mul(<Other as AffineCurve>::ScalarField::rand(rng).into_repr())
+        .into_affine();
+    for _ in 0..doubles {
+        let p2 = p + p;
+        // 2 * s * y1 = 3 * x1^2
+        let x1_squared = p.x.square();
+        let s = (x1_squared.double() + x1_squared) / p.y.double();
+        let row = [p.x, p.y, p.x, p.y, p2.x, p2.y, Fp::zero(), Fp::one(), s];
+        for (col, value) in witness
+            .iter_mut()
+            .zip(row.iter().chain([Fp::zero(); 6].iter()))
+        {
+            col.push(*value);
+        }
+        p = p2;
+    }
+
+    differential_test(gates, witness, 0);
+}
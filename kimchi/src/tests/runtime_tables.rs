@@ -0,0 +1,155 @@
+use crate::circuits::{
+    builder::{CircuitBuilder, WitnessBuilder},
+    constraints::ConstraintSystem,
+    gate::CircuitGate,
+    polynomials::runtime_tables::{RuntimeTable, RuntimeTableCfg},
+};
+use crate::error::{ProofError, VerifyError};
+use crate::prover::ProverProof;
+use crate::prover_index::{testing::new_index_for_test_with_cs, ProverIndex};
+use crate::verifier::batch_verify;
+use ark_ff::{Field, One};
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// The number of entries of the memory.
+const ENTRIES: u64 = 16;
+
+/// The index of a circuit of a single fixed table of the values `0..4`,
+/// followed by a runtime table of the addresses of the memory.
+fn index(gates: Vec<CircuitGate<Fp>>) -> ProverIndex<Affine> {
+    let addresses = RuntimeTableCfg {
+        first_column: (0..ENTRIES).map(Fp::from).collect(),
+    };
+    let cs = ConstraintSystem::create_with_runtime_tables(
+        gates,
+        vec![vec![(0..4u64).map(Fp::from).collect()]],
+        vec![addresses],
+        oracle::pasta::fp_kimchi::params(),
+        0,
+    )
+    .unwrap();
+    new_index_for_test_with_cs(cs)
+}
+
+/// A memory of `entries` entries, holding the cube of each address.
+fn memory(entries: u64) -> RuntimeTable<Fp> {
+    RuntimeTable {
+        id: 1,
+        data: (0..entries).map(|a| Fp::from(a).pow([3])).collect(),
+    }
+}
+
+/// A circuit reading the memory at the `addresses`, with the values that it reads as private inputs.
+fn reads(addresses: &[u64]) -> (Vec<CircuitGate<Fp>>, WitnessBuilder<Fp>) {
+    let mut b = CircuitBuilder::<Fp>::new();
+    let reads: Vec<_> = addresses
+        .iter()
+        .map(|a| (b.constant(Fp::from(*a)), b.private_input()))
+        .collect();
+    b.lookup_pairs(1, &reads);
+    b.build()
+}
+
+/// Reads a memory given by the prover.
+#[test]
+fn test_runtime_table() {
+    let addresses = [0u64, 3, 3, 7, 15];
+    let (gates, generator) = reads(&addresses);
+    let values: Vec<_> = addresses.iter().map(|a| Fp::from(*a).pow([3])).collect();
+    let witness = generator.generate(&[], &values);
+
+    let index = index(gates);
+    assert!(index.cs.runtime_table().is_some());
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof = ProverProof::create_with_runtime_tables::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &[memory(ENTRIES)],
+        &index,
+        vec![],
+    )
+    .unwrap();
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+
+    // a proof must commit to the memory
+    let mut stripped = proof;
+    stripped.commitments.lookup.as_mut().unwrap().runtime = None;
+    assert!(matches!(
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(
+            &group_map,
+            &[(&verifier_index, &stripped)]
+        ),
+        Err(VerifyError::TranscriptMismatch(_))
+    ));
+
+    // the values read must be those of the memory
+    let mut other = memory(ENTRIES);
+    other.data[7] += Fp::one();
+    let err = ProverProof::create_with_runtime_tables::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &[other],
+        &index,
+        vec![],
+    )
+    .err();
+    assert_eq!(err, Some(ProofError::ValueNotInTable));
+}
+
+#[test]
+fn test_missing_runtime_table() {
+    let (gates, generator) = reads(&[2]);
+    let witness = generator.generate(&[], &[Fp::from(8u64)]);
+    let index = index(gates);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let missing = Some(ProofError::MissingRuntimeTable {
+        id: 1,
+        len: ENTRIES as usize,
+    });
+
+    // the memory must have as many entries as its addresses
+    let err = ProverProof::create_with_runtime_tables::<BaseSponge, ScalarSponge>(
+        &group_map,
+        witness.clone(),
+        &[memory(ENTRIES - 1)],
+        &index,
+        vec![],
+    )
+    .err();
+    assert_eq!(err, missing);
+    let err =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![]).err();
+    assert_eq!(err, missing);
+
+    // a circuit that only looks up its fixed table does not take a memory
+    let mut b = CircuitBuilder::<Fp>::new();
+    let x = b.private_input();
+    b.lookup(0, &[x]);
+    let (gates, generator) = b.build();
+    let index = self::index(gates);
+    assert!(index.cs.runtime_table().is_none());
+    let err = ProverProof::create_with_runtime_tables::<BaseSponge, ScalarSponge>(
+        &group_map,
+        generator.generate(&[], &[Fp::from(2u64)]),
+        &[memory(ENTRIES)],
+        &index,
+        vec![],
+    )
+    .err();
+    assert_eq!(err, Some(ProofError::UnknownRuntimeTable { id: 1 }));
+}
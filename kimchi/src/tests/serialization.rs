@@ -0,0 +1,320 @@
+use crate::circuits::{
+    polynomials::generic::testing::{create_circuit, create_circuit_and_witness},
+    public_input::PublicInputLayout,
+    wires::{COLUMNS, PERMUTS},
+};
+use crate::prover::ProverProof;
+use crate::prover_index::{testing::new_index_for_test, ProverIndex};
+use crate::verifier::batch_verify;
+use crate::verifier_index::{LookupVerifierIndex, TranscriptVersion, VerifierIndex};
+use ark_ec::AffineCurve;
+use ark_ff::One;
+use ark_poly::Radix2EvaluationDomain as D;
+use commitment_dlog::{
+    commitment::{CommitmentCurve, PolyComm},
+    srs::SRS,
+};
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use o1_utils::serialization::{SerdeAs, SerdeAsUncompressed};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use serde::Serialize;
+use serde_with::{ser::SerializeAsWrap, serde_as, SerializeAs};
+use std::sync::Arc;
+
+// aliases
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+#[test]
+fn test_prover_index_serialization() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let index = new_index_for_test(gates, public.len());
+
+    // round trip through a file
+    let path = std::env::temp_dir().join("kimchi_test_prover_index_serialization");
+    index.to_file(&path).unwrap();
+    let index = ProverIndex::<Affine>::from_file(
+        index.srs.clone(),
+        &path,
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // the deserialized index can still be used to prove
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+}
+
+#[test]
+fn test_proof_serialization() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let index = new_index_for_test(gates, public.len());
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+
+    // round trip through bytes
+    let bytes = rmp_serde::to_vec(&proof).unwrap();
+    let proof: ProverProof<Affine> = rmp_serde::from_read_ref(&bytes).unwrap();
+
+    // round trip of the verifier index
+    let mut index_bytes = vec![];
+    index.verifier_index().write(&mut index_bytes).unwrap();
+    let verifier_index = VerifierIndex::<Affine>::read(
+        &index_bytes[..],
+        |_| index.srs.clone(),
+        index.cs.endo,
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+    .unwrap();
+
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+        .unwrap();
+}
+
+#[test]
+fn test_point_encoding() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let index = new_index_for_test(gates, public.len());
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    let verifier_index = index.verifier_index();
+
+    // the verifier reads proofs whose commitments are written in either encoding
+    let compressed = rmp_serde::to_vec(&proof).unwrap();
+    let uncompressed = proof
+        .commitments
+        .w_comm
+        .iter()
+        .flat_map(|comm| &comm.unshifted)
+        .fold(compressed.clone(), |bytes, point| {
+            splice(
+                &bytes,
+                &encode::<SerdeAs>(point),
+                &encode::<SerdeAsUncompressed>(point),
+            )
+        });
+    assert!(compressed.len() < uncompressed.len());
+    for bytes in [&compressed, &uncompressed] {
+        let proof: ProverProof<Affine> = rmp_serde::from_read_ref(bytes).unwrap();
+        batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_index, &proof)])
+            .unwrap();
+    }
+
+    // a commitment that is not on the curve is rejected when the proof is read
+    let comm = proof.commitments.w_comm[0].unshifted[0];
+    let invalid = Affine::new(
+        comm.x,
+        comm.y + <Affine as AffineCurve>::BaseField::one(),
+        false,
+    );
+    let bytes = splice(
+        &compressed,
+        &encode::<SerdeAs>(&comm),
+        &encode::<SerdeAsUncompressed>(&invalid),
+    );
+    assert!(rmp_serde::from_read_ref::<_, ProverProof<Affine>>(&bytes).is_err());
+}
+
+/// Serializes `point` with the adapter `U`.
+fn encode<U: SerializeAs<Affine>>(point: &Affine) -> Vec<u8> {
+    rmp_serde::to_vec(&SerializeAsWrap::<_, U>::new(point)).unwrap()
+}
+
+/// Replaces the first occurrence of `from` in `bytes` with `to`.
+fn splice(bytes: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let i = bytes
+        .windows(from.len())
+        .position(|window| window == from)
+        .expect("the bytes to replace are missing");
+    [&bytes[..i], to, &bytes[i + from.len()..]].concat()
+}
+
+#[test]
+fn test_verifier_index_digest() {
+    let index = new_index_for_test(create_circuit(0, 5), 5);
+    let digest = index.verifier_index().digest();
+    assert_eq!(index.verifier_index_digest(), digest);
+
+    // the digest survives a round trip through serialization
+    let mut index_bytes = vec![];
+    index.verifier_index().write(&mut index_bytes).unwrap();
+    let verifier_index = VerifierIndex::<Affine>::read(
+        &index_bytes[..],
+        |_| index.srs.clone(),
+        index.cs.endo,
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+    .unwrap();
+    assert_eq!(verifier_index.digest(), digest);
+
+    // a prover index read with a new SRS computes the Lagrange basis and the digest
+    let mut index_bytes = vec![];
+    index.write(&mut index_bytes).unwrap();
+    let read = ProverIndex::<Affine>::read(
+        &index_bytes[..],
+        |size| Ok(Arc::new(SRS::create(size))),
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+    .unwrap();
+    assert!(read
+        .srs
+        .lagrange_bases
+        .contains_key(&(read.cs.domain.d1.size as usize)));
+    assert_eq!(read.cs.coefficients_sparse, index.cs.coefficients_sparse);
+    assert_eq!(read.verifier_index_digest(), digest);
+
+    // and identifies the circuit
+    let other = new_index_for_test(create_circuit(0, 4), 4);
+    assert_ne!(other.verifier_index().digest(), digest);
+    let mut verifier_index = index.verifier_index();
+    verifier_index.generic_comm = verifier_index.psm_comm.clone();
+    assert_ne!(verifier_index.digest(), digest);
+}
+
+#[test]
+fn test_prover_and_verifier_keys() {
+    let (gates, witness, public) = create_circuit_and_witness(5);
+
+    let (prover_key, verifier_key) = new_index_for_test(gates, public.len()).into_keys();
+    assert_eq!(prover_key.verifier_index_digest(), verifier_key.digest());
+
+    // the verifying key does not borrow from the proving key
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &prover_key, vec![])
+            .unwrap();
+    drop(prover_key);
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&verifier_key, &proof)])
+        .unwrap();
+}
+
+#[test]
+fn test_reproducible_index() {
+    let index = new_index_for_test(create_circuit(0, 5), 5);
+    let digest = index.check_reproducible().unwrap();
+    assert_eq!(digest, index.verifier_index().digest());
+}
+
+/// The fields of the verifier indexes serialized before the ones that default when they are missing.
+#[serde_as]
+#[derive(Serialize)]
+struct LegacyVerifierIndex<'a> {
+    #[serde_as(as = "SerdeAs")]
+    domain: D<Fp>,
+    max_poly_size: usize,
+    max_quot_size: usize,
+    sigma_comm: &'a [PolyComm<Affine>; PERMUTS],
+    coefficients_comm: &'a [PolyComm<Affine>; COLUMNS],
+    generic_comm: &'a PolyComm<Affine>,
+    psm_comm: &'a PolyComm<Affine>,
+    complete_add_comm: &'a PolyComm<Affine>,
+    mul_comm: &'a PolyComm<Affine>,
+    emul_comm: &'a PolyComm<Affine>,
+    endomul_scalar_comm: &'a PolyComm<Affine>,
+    chacha_comm: &'a Option<[PolyComm<Affine>; 4]>,
+    #[serde_as(as = "[SerdeAs; PERMUTS]")]
+    shift: [Fp; PERMUTS],
+    lookup_index: &'a Option<LookupVerifierIndex<Affine>>,
+}
+
+/// Serializes `index` the way the verifier indexes were serialized before the fields that default.
+fn legacy_bytes(index: &VerifierIndex<Affine>) -> Vec<u8> {
+    let legacy = LegacyVerifierIndex {
+        domain: index.domain,
+        max_poly_size: index.max_poly_size,
+        max_quot_size: index.max_quot_size,
+        sigma_comm: &index.sigma_comm,
+        coefficients_comm: &index.coefficients_comm,
+        generic_comm: &index.generic_comm,
+        psm_comm: &index.psm_comm,
+        complete_add_comm: &index.complete_add_comm,
+        mul_comm: &index.mul_comm,
+        emul_comm: &index.emul_comm,
+        endomul_scalar_comm: &index.endomul_scalar_comm,
+        chacha_comm: &index.chacha_comm,
+        shift: index.shift,
+        lookup_index: &index.lookup_index,
+    };
+    rmp_serde::to_vec(&legacy).unwrap()
+}
+
+/// Reads a verifier index of `index` from `bytes`.
+fn read_verifier_index(index: &ProverIndex<Affine>, bytes: &[u8]) -> VerifierIndex<Affine> {
+    VerifierIndex::<Affine>::read(
+        bytes,
+        |_| index.srs.clone(),
+        index.cs.endo,
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_legacy_verifier_index() {
+    let index = new_index_for_test(create_circuit(0, 0), 0);
+    let verifier_index = index.verifier_index();
+    let read = read_verifier_index(&index, &legacy_bytes(&verifier_index));
+    assert_eq!(read.public, 0);
+    assert_eq!(read.public_layout, None);
+    assert_eq!(read.digest(), verifier_index.digest());
+
+    // the indexes serialized with a public layout but not the number of public inputs take it from the layout
+    let mut index = new_index_for_test(create_circuit(0, 5), 5);
+    index
+        .set_public_layout(PublicInputLayout::named(&["a", "b", "c", "d", "e"]).unwrap())
+        .unwrap();
+    let verifier_index = index.verifier_index();
+    let mut bytes = vec![];
+    verifier_index.write(&mut bytes).unwrap();
+    // the number of public inputs is the last field of the array, and a single byte small integer
+    assert_eq!(bytes[0], 0xdc);
+    let fields = u16::from_be_bytes([bytes[1], bytes[2]]) - 1;
+    bytes[1..3].copy_from_slice(&fields.to_be_bytes());
+    assert_eq!(bytes.pop(), Some(5));
+    let read = read_verifier_index(&index, &bytes);
+    assert_eq!(read.public, 5);
+    assert_eq!(read.digest(), verifier_index.digest());
+}
+
+#[test]
+fn test_legacy_verifier_index_transcript() {
+    let (gates, witness, public) = create_circuit_and_witness(0);
+    let mut index = new_index_for_test(gates, public.len());
+    index.set_transcript_version(TranscriptVersion::V0);
+
+    // an index serialized before the transcript was versioned verifies the proofs of the original transcript
+    let read = read_verifier_index(&index, &legacy_bytes(&index.verifier_index()));
+    assert_eq!(read.transcript_version, TranscriptVersion::V0);
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .unwrap();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&read, &proof)]).unwrap();
+}
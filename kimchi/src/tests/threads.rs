@@ -0,0 +1,69 @@
+use crate::circuits::polynomials::generic::testing::{create_circuit, fill_in_witness};
+use crate::circuits::wires::COLUMNS;
+use crate::prover::ProverProof;
+use crate::prover_index::{testing::new_index_for_test, ProverIndex};
+use crate::verifier::batch_verify;
+use crate::verifier_index::VerifierIndex;
+use ark_ff::Zero;
+use array_init::array_init;
+use commitment_dlog::commitment::CommitmentCurve;
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    fp::Fp,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use std::{sync::Arc, thread};
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_keys_are_send_and_sync() {
+    assert_send_sync::<ProverIndex<Affine>>();
+    assert_send_sync::<VerifierIndex<Affine>>();
+    assert_send_sync::<ProverProof<Affine>>();
+}
+
+#[test]
+fn test_concurrent_proofs() {
+    let public = vec![Fp::from(3u8); 5];
+    let gates = create_circuit(0, public.len());
+    let (prover_key, verifier_key) = new_index_for_test(gates, public.len()).into_keys();
+    let prover_key = Arc::new(prover_key);
+
+    // the threads share the proving key, each proving its own witness
+    let workers: Vec<_> = (0..4u64)
+        .map(|i| {
+            let prover_key = Arc::clone(&prover_key);
+            thread::spawn(move || {
+                let public = vec![Fp::from(i); 5];
+                let mut witness: [Vec<Fp>; COLUMNS] =
+                    array_init(|_| vec![Fp::zero(); prover_key.cs.gates.len()]);
+                fill_in_witness(0, &mut witness, &public);
+                let group_map = <Affine as CommitmentCurve>::Map::setup();
+                ProverProof::create::<BaseSponge, ScalarSponge>(
+                    &group_map,
+                    witness,
+                    &prover_key,
+                    vec![],
+                )
+                .unwrap()
+            })
+        })
+        .collect();
+    let proofs: Vec<_> = workers
+        .into_iter()
+        .map(|worker| worker.join().unwrap())
+        .collect();
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let batch: Vec<_> = proofs.iter().map(|proof| (&verifier_key, proof)).collect();
+    batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &batch).unwrap();
+}
@@ -1,3 +1,4 @@
+use crate::circuits::check::check_witness;
 use crate::prover::ProverProof;
 use crate::verifier::batch_verify;
 use crate::{
@@ -106,6 +107,8 @@ fn varbase_mul_test() {
         start.elapsed()
     );
 
+    check_witness(&witness, &index.cs, &[]).unwrap();
+
     let start = Instant::now();
     let proof =
         ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
@@ -6,28 +6,97 @@ use crate::{
         argument::ArgumentType,
         constraints::ConstraintSystem,
         expr::{Column, Constants, PolishToken},
-        gate::{GateType, LookupsUsed},
+        gate::{GateType, LookupInfo, LookupsUsed},
         polynomials::{generic, permutation},
-        scalars::RandomOracles,
+        scalars::{ProofEvaluations, RandomOracles},
         wires::*,
     },
-    error::{ProofError, Result},
+    error::{PublicInputError, VerifyError},
+    metrics,
     plonk_sponge::FrSponge,
-    prover::ProverProof,
-    verifier_index::{LookupVerifierIndex, VerifierIndex},
+    prover::{message_transcript, ProverProof},
+    verifier_index::{LookupVerifierIndex, PreparedVerifierIndex, VerifierIndex},
 };
 use ark_ec::AffineCurve;
 use ark_ff::{Field, One, PrimeField, Zero};
 use ark_poly::{EvaluationDomain, Polynomial};
+use array_init::array_init;
 use commitment_dlog::commitment::{
-    b_poly, b_poly_coefficients, combined_inner_product, CommitmentCurve, PolyComm,
+    b_poly, b_poly_coefficients, ceil_log2, combined_inner_product, CommitmentCurve, PolyComm,
 };
 use oracle::{sponge::ScalarChallenge, FqSponge};
 use rand::thread_rng;
+use rand_core::{CryptoRng, RngCore};
+use std::collections::HashMap;
 
 type Fr<G> = <G as AffineCurve>::ScalarField;
 type Fq<G> = <G as AffineCurve>::BaseField;
 
+/// What the verification of a proof derives from its [VerifierIndex] alone,
+/// which a [PreparedVerifierIndex] computes once for all the proofs it verifies.
+pub(crate) struct Precomputed<G: CommitmentCurve> {
+    /// the digest of the index, if it was computed beforehand
+    digest: Option<Fq<G>>,
+    /// the commitments to the Lagrange polynomials of the rows of the public input
+    public_comms: Vec<PolyComm<G>>,
+    /// the inverses $\omega^{-i}$ of the elements of the domain for the rows of the public input
+    omega_invs: Vec<Fr<G>>,
+    /// for the chunk starting with the coefficient $s$ and the row $i$ of the public input, $\omega^{-is} / n$
+    chunk_shifts: Vec<Vec<Fr<G>>>,
+}
+
+impl<G: CommitmentCurve> Precomputed<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Computes the data for `index`, including its digest if `digest` is set.
+    pub(crate) fn new(index: &VerifierIndex<G>, digest: bool) -> Self {
+        let mut omega_invs: Vec<_> = index.domain.elements().take(index.public).collect();
+        ark_ff::fields::batch_inversion::<Fr<G>>(&mut omega_invs);
+        let chunk_shifts = (0..index.domain.size())
+            .step_by(index.max_poly_size)
+            .map(|start| {
+                omega_invs
+                    .iter()
+                    .map(|inv| inv.pow(&[start as u64]) * index.domain.size_inv)
+                    .collect()
+            })
+            .collect();
+
+        Precomputed {
+            digest: if digest { Some(index.digest()) } else { None },
+            public_comms: lagrange_comms(index, index.public),
+            omega_invs,
+            chunk_shifts,
+        }
+    }
+}
+
+/// Returns the commitments to the Lagrange polynomials of the first `rows` rows of the domain of `index`.
+fn lagrange_comms<G: CommitmentCurve>(index: &VerifierIndex<G>, rows: usize) -> Vec<PolyComm<G>> {
+    let lgr_comm: Vec<_> = index
+        .srs
+        .lagrange_basis_chunks(index.domain.size())
+        .expect("pre-computed committed lagrange bases not found")
+        .collect();
+    (0..rows)
+        .map(|i| PolyComm {
+            unshifted: lgr_comm.iter().map(|chunk| chunk[i]).collect(),
+            shifted: None,
+        })
+        .collect()
+}
+
+/// Returns the commitment to the negated public input polynomial, given the commitments to the Lagrange polynomials.
+fn negated_public_comm<G: CommitmentCurve>(
+    lagrange: &[PolyComm<G>],
+    public: &[Fr<G>],
+) -> PolyComm<G> {
+    let com_ref: Vec<_> = lagrange.iter().take(public.len()).collect();
+    let elm: Vec<_> = public.iter().map(|s| -*s).collect();
+    PolyComm::<G>::multi_scalar_mul(&com_ref, &elm)
+}
+
 /// The result of running the oracle protocol
 pub struct OraclesResult<G, EFqSponge>
 where
@@ -106,23 +175,122 @@ where
             .collect()
     }
 
-    /// This function runs the random oracle argument
+    /// Returns the commitment to the negated public input polynomial,
+    /// the first element absorbed in the transcript.
+    pub fn public_comm(&self, index: &VerifierIndex<G>) -> PolyComm<G> {
+        negated_public_comm(&lagrange_comms(index, self.public.len()), &self.public)
+    }
+
+    /// Replays the transcript of the proof against `index`,
+    /// and returns the challenges a verifier would derive from it.
+    /// The proof itself is not checked:
+    /// this only fails if the proof does not have the shape `index` expects.
+    pub fn challenges<EFqSponge, EFrSponge>(
+        &self,
+        index: &VerifierIndex<G>,
+    ) -> Result<RandomOracles<Fr<G>>, VerifyError>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    {
+        check_shape(index, self)?;
+        let p_comm = self.public_comm(index);
+        Ok(self.oracles::<EFqSponge, EFrSponge>(index, &p_comm).oracles)
+    }
+
+    /// Absorbs the end of the proof (its evaluations and its opening proof) into `fq_sponge`,
+    /// which is the state of the Fq-Sponge of the proof before the evaluations.
+    /// This is the state of the transcript of a session after the proof (see [ProverProof::create_in_transcript]).
+    pub fn conclude_transcript<EFqSponge: FqSponge<Fq<G>, G, Fr<G>>>(
+        &self,
+        fq_sponge: &mut EFqSponge,
+    ) {
+        for evals in &self.evals {
+            fq_sponge.absorb_fr(&evals.z);
+            fq_sponge.absorb_fr(&evals.generic_selector);
+            fq_sponge.absorb_fr(&evals.poseidon_selector);
+            evals.w.iter().for_each(|w| fq_sponge.absorb_fr(w));
+            evals.s.iter().for_each(|s| fq_sponge.absorb_fr(s));
+            if let Some(lookup) = &evals.lookup {
+                lookup.sorted.iter().for_each(|s| fq_sponge.absorb_fr(s));
+                fq_sponge.absorb_fr(&lookup.aggreg);
+                fq_sponge.absorb_fr(&lookup.table);
+                if let Some(runtime) = &lookup.runtime {
+                    fq_sponge.absorb_fr(runtime);
+                }
+            }
+        }
+        fq_sponge.absorb_fr(&[self.ft_eval1]);
+
+        for (l, r) in &self.proof.lr {
+            fq_sponge.absorb_g(&[*l, *r]);
+        }
+        fq_sponge.absorb_g(&[self.proof.delta, self.proof.sg]);
+        fq_sponge.absorb_fr(&[self.proof.z1, self.proof.z2]);
+    }
+
+    /// This function runs the random oracle argument.
+    /// Panics if the proof does not have the shape `index` expects
+    /// (see [ProverProof::challenges] for a version that returns an error instead).
     pub fn oracles<EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>, EFrSponge: FrSponge<Fr<G>>>(
         &self,
         index: &VerifierIndex<G>,
         p_comm: &PolyComm<G>,
+    ) -> OraclesResult<G, EFqSponge> {
+        let fq_sponge = EFqSponge::new(index.fq_sponge_params.clone());
+        self.oracles_in_transcript::<EFqSponge, EFrSponge>(index, p_comm, fq_sponge)
+    }
+
+    /// Same as [ProverProof::oracles], for a proof whose transcript starts from the state `fq_sponge`
+    /// (see [ProverProof::create_in_transcript]).
+    pub fn oracles_in_transcript<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    >(
+        &self,
+        index: &VerifierIndex<G>,
+        p_comm: &PolyComm<G>,
+        fq_sponge: EFqSponge,
+    ) -> OraclesResult<G, EFqSponge> {
+        let precomputed = Precomputed::new(index, false);
+        self.oracles_precomputed::<EFqSponge, EFrSponge>(index, &precomputed, p_comm, fq_sponge)
+    }
+
+    /// Same as [ProverProof::oracles_in_transcript], with the data `precomputed` from `index`.
+    fn oracles_precomputed<
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        EFrSponge: FrSponge<Fr<G>>,
+    >(
+        &self,
+        index: &VerifierIndex<G>,
+        precomputed: &Precomputed<G>,
+        p_comm: &PolyComm<G>,
+        mut fq_sponge: EFqSponge,
     ) -> OraclesResult<G, EFqSponge> {
         let n = index.domain.size;
 
         // Run random oracle argument to sample verifier oracles
-        let mut fq_sponge = EFqSponge::new(index.fq_sponge_params.clone());
 
-        // absorb the public input, l, r, o polycommitments into the argument
+        // absorb the digest of the circuit and the public input (depending on the transcript version),
+        // then the public input, l, r, o polycommitments into the argument
+        index.transcript_version.absorb_header::<G, _, _>(
+            &mut fq_sponge,
+            || precomputed.digest.unwrap_or_else(|| index.digest()),
+            &self.public,
+        );
         fq_sponge.absorb_g(&p_comm.unshifted);
         self.commitments
             .w_comm
             .iter()
             .for_each(|c| fq_sponge.absorb_g(&c.unshifted));
+        if let Some(runtime) = self
+            .commitments
+            .lookup
+            .as_ref()
+            .and_then(|l| l.runtime.as_ref())
+        {
+            fq_sponge.absorb_g(&runtime.unshifted);
+        }
 
         let joint_combiner = {
             let s = match index.lookup_index {
@@ -159,7 +327,8 @@ where
         let alpha = alpha_chal.to_field(&index.srs.endo_r);
 
         // absorb the polycommitments into the argument and sample zeta
-        let expected_t_size = PERMUTS;
+        // (the verifier checks the number of chunks beforehand, see [check_shape])
+        let expected_t_size = index.quotient_chunks();
         assert_eq!(expected_t_size, self.commitments.t_comm.unshifted.len());
         fq_sponge.absorb_g(&self.commitments.t_comm.unshifted);
 
@@ -178,44 +347,9 @@ where
         let mut all_alphas = index.powers_of_alpha.clone();
         all_alphas.instantiate(alpha);
 
-        // compute Lagrange base evaluation denominators
-        let w = (0..self.public.len())
-            .zip(index.domain.elements())
-            .map(|(_, w)| w)
-            .collect::<Vec<_>>();
-        let mut lagrange = w.iter().map(|w| zeta - w).collect::<Vec<_>>();
-        (0..self.public.len())
-            .zip(w.iter())
-            .for_each(|(_, w)| lagrange.push(zetaw - w));
-        ark_ff::fields::batch_inversion::<Fr<G>>(&mut lagrange);
-
-        // evaluate public input polynomials
-        // NOTE: this works only in the case when the poly segment size is not smaller than that of the domain
+        // evaluate public input polynomials, chunk by chunk
         let p_eval = if !self.public.is_empty() {
-            [
-                vec![
-                    (self
-                        .public
-                        .iter()
-                        .zip(lagrange.iter())
-                        .zip(index.domain.elements())
-                        .map(|((p, l), w)| -*l * p * w)
-                        .fold(Fr::<G>::zero(), |x, y| x + y))
-                        * (zeta1 - Fr::<G>::one())
-                        * index.domain.size_inv,
-                ],
-                vec![
-                    (self
-                        .public
-                        .iter()
-                        .zip(lagrange[self.public.len()..].iter())
-                        .zip(index.domain.elements())
-                        .map(|((p, l), w)| -*l * p * w)
-                        .fold(Fr::<G>::zero(), |x, y| x + y))
-                        * index.domain.size_inv
-                        * (zetaw.pow(&[n as u64]) - Fr::<G>::one()),
-                ],
-            ]
+            [zeta, zetaw].map(|pt| public_eval_chunks(index, precomputed, &self.public, pt))
         } else {
             [Vec::<Fr<G>>::new(), Vec::<Fr<G>>::new()]
         };
@@ -274,11 +408,9 @@ where
                 .map(|(w, s)| (beta * s) + w + gamma)
                 .fold(init, |x, y| x * y);
 
-            ft_eval0 -= if !p_eval[0].is_empty() {
-                p_eval[0][0]
-            } else {
-                Fr::<G>::zero()
-            };
+            ft_eval0 -= p_eval[0].iter().rev().fold(Fr::<G>::zero(), |acc, e| {
+                acc * powers_of_eval_points_for_chunks[0] + e
+            });
 
             ft_eval0 -= evals[0]
                 .w
@@ -311,7 +443,7 @@ where
                 &evals,
                 &cs,
             )
-            .unwrap();
+            .expect("the verifier checks the shape of the proof beforehand");
 
             ft_eval0
         };
@@ -327,30 +459,12 @@ where
                 .collect();
             es.push((p_eval.iter().collect::<Vec<_>>(), None));
             es.push((vec![&ft_eval0, &ft_eval1], None));
-            es.push((self.evals.iter().map(|e| &e.z).collect::<Vec<_>>(), None));
-            es.push((
-                self.evals
-                    .iter()
-                    .map(|e| &e.generic_selector)
-                    .collect::<Vec<_>>(),
-                None,
-            ));
-            es.push((
-                self.evals
-                    .iter()
-                    .map(|e| &e.poseidon_selector)
-                    .collect::<Vec<_>>(),
-                None,
-            ));
-            es.extend(
-                (0..COLUMNS)
-                    .map(|c| (self.evals.iter().map(|e| &e.w[c]).collect::<Vec<_>>(), None))
-                    .collect::<Vec<_>>(),
-            );
+            let [e0, e1] = &self.evals;
             es.extend(
-                (0..PERMUTS - 1)
-                    .map(|c| (self.evals.iter().map(|e| &e.s[c]).collect::<Vec<_>>(), None))
-                    .collect::<Vec<_>>(),
+                e0.opened()
+                    .into_iter()
+                    .zip(e1.opened())
+                    .map(|(e0, e1)| (vec![e0, e1], None)),
             );
 
             combined_inner_product::<G>(&ep, &v, &u, &es, index.srs.g.len())
@@ -385,24 +499,345 @@ where
     }
 }
 
+/// Returns the evaluations at `pt` of the chunks of the negated public input polynomial $-\sum_i p_i L_i$,
+/// for the chunks of `index.max_poly_size` coefficients of the commitments.
+///
+/// As $L_i(X) = \frac{1}{n} \sum_{k < n} (X / \omega^i)^k$, the chunk of the coefficients $k$ from $jm$ to $jm + l$
+/// evaluates to $\frac{\omega^{-ijm}}{n} \frac{(x / \omega^i)^l - 1}{x / \omega^i - 1}$.
+/// Only the ratios $x / \omega^i$ depend on the point, the rest is `precomputed` from the index.
+fn public_eval_chunks<G: CommitmentCurve>(
+    index: &VerifierIndex<G>,
+    precomputed: &Precomputed<G>,
+    public: &[Fr<G>],
+    pt: Fr<G>,
+) -> Vec<Fr<G>> {
+    let n = index.domain.size();
+    let m = index.max_poly_size;
+    // x / w^i, and the denominators x / w^i - 1
+    let ratios: Vec<_> = precomputed.omega_invs.iter().map(|inv| pt * inv).collect();
+    let mut denominators: Vec<_> = ratios.iter().map(|r| *r - Fr::<G>::one()).collect();
+    ark_ff::fields::batch_inversion::<Fr<G>>(&mut denominators);
+
+    precomputed
+        .chunk_shifts
+        .iter()
+        .zip((0..n).step_by(m))
+        .map(|(shifts, start)| {
+            let len = std::cmp::min(m, n - start) as u64;
+            public
+                .iter()
+                .zip(shifts)
+                .zip(ratios.iter().zip(&denominators))
+                .map(|((p, shift), (r, d))| -*p * shift * (r.pow(&[len]) - Fr::<G>::one()) * d)
+                .fold(Fr::<G>::zero(), |x, y| x + y)
+        })
+        .collect()
+}
+
+/// Rejects a proof whose vectors do not have the lengths `index` expects, as the verifier indexes into them.
+pub(crate) fn check_shape<G: CommitmentCurve>(
+    index: &VerifierIndex<G>,
+    proof: &ProverProof<G>,
+) -> Result<(), VerifyError> {
+    if proof.public.len() != index.public {
+        return Err(VerifyError::PublicInputMismatch {
+            expected: index.public,
+            got: proof.public.len(),
+        });
+    }
+
+    if proof.commitments.lookup.is_some() != index.lookup_index.is_some() {
+        return Err(VerifyError::TranscriptMismatch(
+            "lookup commitments do not match the use of lookups by the circuit",
+        ));
+    }
+    if proof
+        .evals
+        .iter()
+        .any(|e| e.lookup.is_some() != index.lookup_index.is_some())
+    {
+        return Err(VerifyError::TranscriptMismatch(
+            "lookup evaluations do not match the use of lookups by the circuit",
+        ));
+    }
+
+    let t_chunks = proof.commitments.t_comm.unshifted.len();
+    if t_chunks != index.quotient_chunks() {
+        return Err(VerifyError::QuotientMismatch {
+            expected: index.quotient_chunks(),
+            got: t_chunks,
+        });
+    }
+
+    // the evaluations of a polynomial at both points must have the same number of chunks
+    let [e0, e1] = &proof.evals;
+    let same_chunks = |a: &Vec<Fr<G>>, b: &Vec<Fr<G>>| a.len() == b.len();
+    if !(e0.w.iter().zip(&e1.w).all(|(a, b)| same_chunks(a, b))
+        && e0.s.iter().zip(&e1.s).all(|(a, b)| same_chunks(a, b))
+        && same_chunks(&e0.z, &e1.z)
+        && same_chunks(&e0.generic_selector, &e1.generic_selector)
+        && same_chunks(&e0.poseidon_selector, &e1.poseidon_selector))
+    {
+        return Err(VerifyError::MalformedProof(
+            "the evaluations at both points have different numbers of chunks",
+        ));
+    }
+
+    // the runtime table is committed to and evaluated if and only if the circuit looks it up
+    if let Some(lookup) = &proof.commitments.lookup {
+        let runtime = index
+            .lookup_index
+            .as_ref()
+            .map_or(false, |l| l.runtime_selector.is_some());
+        if lookup.runtime.is_some() != runtime
+            || proof.evals.iter().any(|e| {
+                e.lookup
+                    .as_ref()
+                    .map_or(true, |l| l.runtime.is_some() != runtime)
+            })
+        {
+            return Err(VerifyError::TranscriptMismatch(
+                "the runtime table commitment does not match the use of a runtime table by the circuit",
+            ));
+        }
+    }
+
+    // the linearization refers to each of the sorted lookup polynomials
+    if let Some(lookup) = &proof.commitments.lookup {
+        let sorted = LookupInfo::<Fr<G>>::create().max_per_row + 1;
+        if lookup.sorted.len() != sorted
+            || proof
+                .evals
+                .iter()
+                .any(|e| e.lookup.as_ref().map_or(true, |l| l.sorted.len() != sorted))
+        {
+            return Err(VerifyError::MalformedProof(
+                "the proof does not have one sorted lookup polynomial per lookup of a row",
+            ));
+        }
+    }
+
+    // the opening proof has one round per halving of the SRS,
+    // and the previous challenges index the coefficients of polynomials of at most the size of the SRS
+    let rounds = ceil_log2(index.srs.g.len());
+    if proof.proof.lr.len() != rounds {
+        return Err(VerifyError::MalformedProof(
+            "the opening proof does not have the number of rounds of the SRS",
+        ));
+    }
+    if proof
+        .prev_challenges
+        .iter()
+        .any(|(chals, _)| chals.len() > rounds)
+    {
+        return Err(VerifyError::MalformedProof(
+            "a previous challenge has more rounds than the SRS",
+        ));
+    }
+
+    Ok(())
+}
+
 /// This function verifies the batch of zk-proofs
 ///     proofs: vector of Plonk proofs
 ///     index: VerifierIndex
-///     RETURN: verification status
+///     RETURN: verification status, or the reason why a proof was rejected
 #[allow(clippy::type_complexity)]
 pub fn batch_verify<G, EFqSponge, EFrSponge>(
     group_map: &G::Map,
     proofs: &[(&VerifierIndex<G>, &ProverProof<G>)],
-) -> Result<()>
+) -> Result<(), VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    batch_verify_with_rng::<G, EFqSponge, EFrSponge, _>(group_map, proofs, &mut thread_rng())
+}
+
+/// Verifies a single proof, checking that its public input is given by the named `public` values
+/// according to the layout of the circuit (see [PublicInputLayout](crate::circuits::public_input::PublicInputLayout)).
+pub fn verify_named<G, EFqSponge, EFrSponge>(
+    group_map: &G::Map,
+    index: &VerifierIndex<G>,
+    proof: &ProverProof<G>,
+    public: &HashMap<&str, Fr<G>>,
+) -> Result<(), VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    let checked = match &index.public_layout {
+        Some(layout) => layout.check(&proof.public, public),
+        None => Err(PublicInputError::NoLayout),
+    };
+    if let Err(e) = checked {
+        let res = Err(VerifyError::PublicInput(e));
+        metrics::record_verification(1, &res);
+        return res;
+    }
+    batch_verify::<G, EFqSponge, EFrSponge>(group_map, &[(index, proof)])
+}
+
+/// Verifies a proof created with [ProverProof::create_in_transcript],
+/// whose transcript starts from the state `transcript`.
+/// Returns the state of the transcript at the end of the proof, from which the next proof of the session is verified.
+pub fn verify_in_transcript<G, EFqSponge, EFrSponge>(
+    group_map: &G::Map,
+    index: &VerifierIndex<G>,
+    proof: &ProverProof<G>,
+    transcript: EFqSponge,
+) -> Result<EFqSponge, VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    let res = batch_verify_unrecorded::<G, EFqSponge, EFrSponge, _>(
+        group_map,
+        &[(index, proof)],
+        &[None],
+        vec![transcript],
+        &mut thread_rng(),
+    );
+    let res = res.map(|mut sponges| {
+        let mut transcript = sponges.remove(0);
+        proof.conclude_transcript(&mut transcript);
+        transcript
+    });
+    metrics::record_verification(1, &res);
+    res
+}
+
+/// Verifies a proof created with [ProverProof::create_with_message], bound to the application `message`.
+pub fn verify_with_message<G, EFqSponge, EFrSponge>(
+    group_map: &G::Map,
+    index: &VerifierIndex<G>,
+    proof: &ProverProof<G>,
+    message: &[u8],
+) -> Result<(), VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    let transcript =
+        message_transcript::<Fq<G>, G, Fr<G>, EFqSponge>(index.fq_sponge_params.clone(), message);
+    verify_in_transcript::<G, EFqSponge, EFrSponge>(group_map, index, proof, transcript).map(|_| ())
+}
+
+/// Same as [batch_verify], but the randomness used to batch the opening proofs is drawn from `rng`
+/// instead of the thread-local generator (for example, `rng` can be seeded from a hardware source inside an enclave).
+/// Note that the verifier still requires `std`, through rayon and the parallel features of arkworks.
+/// The outcome is reported to the metrics (see [crate::metrics]).
+#[allow(clippy::type_complexity)]
+pub fn batch_verify_with_rng<G, EFqSponge, EFrSponge, RNG>(
+    group_map: &G::Map,
+    proofs: &[(&VerifierIndex<G>, &ProverProof<G>)],
+    rng: &mut RNG,
+) -> Result<(), VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+    RNG: RngCore + CryptoRng,
+{
+    let transcripts = proofs
+        .iter()
+        .map(|(index, _)| EFqSponge::new(index.fq_sponge_params.clone()))
+        .collect();
+    let precomputed = vec![None; proofs.len()];
+    let res = batch_verify_unrecorded::<G, EFqSponge, EFrSponge, _>(
+        group_map,
+        proofs,
+        &precomputed,
+        transcripts,
+        rng,
+    )
+    .map(|_| ());
+    metrics::record_verification(proofs.len(), &res);
+    res
+}
+
+/// Verifies a single proof against a [PreparedVerifierIndex],
+/// reusing what the index precomputed instead of deriving it from the verifier index again.
+pub fn verify_prepared<G, EFqSponge, EFrSponge>(
+    group_map: &G::Map,
+    index: &PreparedVerifierIndex<G>,
+    proof: &ProverProof<G>,
+) -> Result<(), VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    batch_verify_prepared::<G, EFqSponge, EFrSponge>(group_map, &[(index, proof)])
+}
+
+/// Same as [batch_verify], for proofs verified against [PreparedVerifierIndex]es.
+#[allow(clippy::type_complexity)]
+pub fn batch_verify_prepared<G, EFqSponge, EFrSponge>(
+    group_map: &G::Map,
+    proofs: &[(&PreparedVerifierIndex<G>, &ProverProof<G>)],
+) -> Result<(), VerifyError>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+    EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+    EFrSponge: FrSponge<Fr<G>>,
+{
+    let transcripts = proofs
+        .iter()
+        .map(|(index, _)| EFqSponge::new(index.fq_sponge_params.clone()))
+        .collect();
+    let unprepared: Vec<_> = proofs
+        .iter()
+        .map(|(index, proof)| (&index.index, *proof))
+        .collect();
+    let precomputed: Vec<_> = proofs
+        .iter()
+        .map(|(index, _)| Some(&index.precomputed))
+        .collect();
+    let res = batch_verify_unrecorded::<G, EFqSponge, EFrSponge, _>(
+        group_map,
+        &unprepared,
+        &precomputed,
+        transcripts,
+        &mut thread_rng(),
+    )
+    .map(|_| ());
+    metrics::record_verification(proofs.len(), &res);
+    res
+}
+
+/// Verifies the batch, the transcript of each proof starting from the corresponding state of `transcripts`,
+/// with the data precomputed from its index if given in `precomputed`.
+/// Returns the states of the transcripts before the evaluations (see [ProverProof::conclude_transcript]).
+#[allow(clippy::type_complexity)]
+fn batch_verify_unrecorded<G, EFqSponge, EFrSponge, RNG>(
+    group_map: &G::Map,
+    proofs: &[(&VerifierIndex<G>, &ProverProof<G>)],
+    precomputed: &[Option<&Precomputed<G>>],
+    transcripts: Vec<EFqSponge>,
+    rng: &mut RNG,
+) -> Result<Vec<EFqSponge>, VerifyError>
 where
     G: CommitmentCurve,
     G::BaseField: PrimeField,
     EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
     EFrSponge: FrSponge<Fr<G>>,
+    RNG: RngCore + CryptoRng,
 {
     // if there's no proof to verify, return early
     if proofs.is_empty() {
-        return Ok(());
+        return Ok(vec![]);
     }
 
     // TODO: Account for the different SRS lengths
@@ -414,24 +849,21 @@ where
     // Validate each proof separately (f(zeta) = t(zeta) * Z_H(zeta))
     // + build objects required to batch verify all the evaluation proofs
     let mut params = vec![];
-    for (index, proof) in proofs {
+    for (((index, proof), precomputed), transcript) in
+        proofs.iter().zip(precomputed).zip(transcripts)
+    {
+        check_shape(index, proof)?;
+        let computed;
+        let precomputed = match precomputed {
+            Some(precomputed) => *precomputed,
+            None => {
+                computed = Precomputed::new(index, false);
+                &computed
+            }
+        };
+
         // commit to public input polynomial
-        let lgr_comm = index
-            .srs
-            .lagrange_bases
-            .get(&index.domain.size())
-            .expect("pre-computed committed lagrange bases not found");
-        let com: Vec<_> = lgr_comm
-            .iter()
-            .map(|c| PolyComm {
-                unshifted: vec![*c],
-                shifted: None,
-            })
-            .take(proof.public.len())
-            .collect();
-        let com_ref: Vec<_> = com.iter().collect();
-        let elm: Vec<_> = proof.public.iter().map(|s| -*s).collect();
-        let p_comm = PolyComm::<G>::multi_scalar_mul(&com_ref, &elm);
+        let p_comm = negated_public_comm(&precomputed.public_comms, &proof.public);
 
         // run the oracles argument
         let OraclesResult {
@@ -444,7 +876,12 @@ where
             zeta1: zeta_to_domain_size,
             ft_eval0,
             ..
-        } = proof.oracles::<EFqSponge, EFrSponge>(index, &p_comm);
+        } = proof.oracles_precomputed::<EFqSponge, EFrSponge>(
+            index,
+            precomputed,
+            &p_comm,
+            transcript,
+        );
 
         // combine the committed chunked polynomials
         // with the right powers of zeta^n or (zeta * omega)^n
@@ -477,8 +914,12 @@ where
                 let alphas = all_alphas
                     .get_alphas(ArgumentType::Gate(GateType::Generic), generic::CONSTRAINTS);
 
-                let generic_scalars =
-                    &ConstraintSystem::gnrc_scalars(alphas, &evals[0].w, evals[0].generic_selector);
+                let generic_scalars = &ConstraintSystem::gnrc_scalars(
+                    alphas,
+                    &evals[0].w,
+                    evals[0].generic_selector,
+                    index.fused_generic,
+                );
 
                 let generic_com = index.coefficients_comm.iter().take(generic_scalars.len());
 
@@ -508,7 +949,9 @@ where
                         &evals,
                         &constants,
                     )
-                    .expect("should evaluate");
+                    .map_err(|_| {
+                        VerifyError::MalformedProof("the linearization could not be evaluated")
+                    })?;
                     let l = proof.commitments.lookup.as_ref();
                     use Column::*;
                     match col {
@@ -526,11 +969,18 @@ where
                         }
                         LookupSorted(i) => {
                             scalars.push(scalar);
-                            commitments.push(&l.unwrap().sorted[*i])
+                            commitments.push(l.and_then(|l| l.sorted.get(*i)).ok_or(
+                                VerifyError::MalformedProof("missing sorted lookup commitment"),
+                            )?)
                         }
                         LookupAggreg => {
                             scalars.push(scalar);
-                            commitments.push(&l.unwrap().aggreg)
+                            commitments.push(
+                                &l.ok_or(VerifyError::MalformedProof(
+                                    "missing lookup commitments",
+                                ))?
+                                .aggreg,
+                            )
                         }
                         LookupKindIndex(i) => match index.lookup_index.as_ref() {
                             None => {
@@ -554,12 +1004,65 @@ where
                                     scalars.push(scalar * j);
                                     commitments.push(t);
                                 }
+                                // the runtime table adds to the second column of the table
+                                if let Some(runtime) = l.and_then(|l| l.runtime.as_ref()) {
+                                    scalars.push(scalar * constants.joint_combiner);
+                                    commitments.push(runtime);
+                                }
                             }
                         },
+                        LookupRuntimeSelector => {
+                            scalars.push(scalar);
+                            commitments.push(
+                                index
+                                    .lookup_index
+                                    .as_ref()
+                                    .and_then(|l| l.runtime_selector.as_ref())
+                                    .ok_or(VerifyError::MalformedProof(
+                                        "the circuit has no runtime table",
+                                    ))?,
+                            )
+                        }
+                        LookupRuntimeTable => {
+                            scalars.push(scalar);
+                            commitments.push(l.and_then(|l| l.runtime.as_ref()).ok_or(
+                                VerifyError::MalformedProof("missing runtime table commitment"),
+                            )?)
+                        }
+                        CustomIndex(i) => {
+                            scalars.push(scalar);
+                            commitments.push(&index.custom_comm[*i])
+                        }
+                        Weight(i) => {
+                            scalars.push(scalar);
+                            commitments.push(
+                                index
+                                    .linear_combination_comm
+                                    .as_ref()
+                                    .and_then(|comm| comm.get(*i))
+                                    .ok_or(VerifyError::MalformedProof(
+                                        "the circuit has no linear combination weight",
+                                    ))?,
+                            )
+                        }
+                        PartialRound(i) => {
+                            scalars.push(scalar);
+                            commitments.push(
+                                index
+                                    .partial_rounds_comm
+                                    .as_ref()
+                                    .and_then(|comm| comm.get(*i))
+                                    .ok_or(VerifyError::MalformedProof(
+                                        "the circuit has no partial round",
+                                    ))?,
+                            )
+                        }
                         Index(t) => {
                             use GateType::*;
                             let c = match t {
-                                Zero | Generic => panic!("Selector for {:?} not defined", t),
+                                Zero | Generic | Custom | LinearCombination | Lookup => {
+                                    panic!("Selector for {:?} not defined", t)
+                                }
                                 CompleteAdd => &index.complete_add_comm,
                                 VarBaseMul => &index.mul_comm,
                                 EndoMul => &index.emul_comm,
@@ -569,6 +1072,7 @@ where
                                 ChaCha1 => &index.chacha_comm.as_ref().unwrap()[1],
                                 ChaCha2 => &index.chacha_comm.as_ref().unwrap()[2],
                                 ChaChaFinal => &index.chacha_comm.as_ref().unwrap()[3],
+                                Boolean => index.boolean_comm.as_ref().unwrap(),
                             };
                             scalars.push(scalar);
                             commitments.push(c);
@@ -599,11 +1103,11 @@ where
         ));
     }
 
-    // batch verify all the evaluation proofs
-    let mut batch = vec![];
-    for (proof, params) in proofs.iter().zip(params.iter()) {
-        let (index, proof) = proof;
-        let (p_eval, p_comm, ft_comm, fq_sponge, oracles, ft_eval0, ft_eval1, polys) = params;
+    // the opening of the evaluation proof of each proof
+    // (the sponges are consumed by the verification, so each opening can be built again to find the faulty proof)
+    let opening = |i: usize| {
+        let (index, proof) = proofs[i];
+        let (p_eval, p_comm, ft_comm, fq_sponge, oracles, ft_eval0, ft_eval1, polys) = &params[i];
 
         // recursion stuff
         let mut polynomials = polys
@@ -617,79 +1121,55 @@ where
         // ft commitment (chunks of it)
         polynomials.push((ft_comm, vec![ft_eval0, ft_eval1], None));
 
-        // permutation commitment
-        polynomials.push((
-            &proof.commitments.z_comm,
-            proof.evals.iter().map(|e| &e.z).collect::<Vec<_>>(),
-            None,
-        ));
-
-        // index commitments that use the coefficients
-        polynomials.push((
-            &index.generic_comm,
-            proof
-                .evals
-                .iter()
-                .map(|e| &e.generic_selector)
-                .collect::<Vec<_>>(),
-            None,
-        ));
-        polynomials.push((
-            &index.psm_comm,
-            proof
-                .evals
-                .iter()
-                .map(|e| &e.poseidon_selector)
-                .collect::<Vec<_>>(),
-            None,
-        ));
-
-        // witness commitments
-        polynomials.extend(
-            proof
-                .commitments
-                .w_comm
-                .iter()
-                .zip(
-                    (0..COLUMNS)
-                        .map(|i| proof.evals.iter().map(|e| &e.w[i]).collect::<Vec<_>>())
-                        .collect::<Vec<_>>()
-                        .iter(),
-                )
-                .map(|(c, e)| (c, e.clone(), None))
-                .collect::<Vec<_>>(),
-        );
-
-        // sigma commitments
+        // the commitments of the evaluations of the proof,
+        // in the order in which they are combined in the opening
+        let commitments = ProofEvaluations {
+            w: array_init(|i| &proof.commitments.w_comm[i]),
+            z: &proof.commitments.z_comm,
+            s: array_init(|i| &index.sigma_comm[i]),
+            lookup: None,
+            generic_selector: &index.generic_comm,
+            poseidon_selector: &index.psm_comm,
+        };
+        let [e0, e1] = &proof.evals;
         polynomials.extend(
-            index
-                .sigma_comm
-                .iter()
-                .zip(
-                    (0..PERMUTS - 1)
-                        .map(|i| proof.evals.iter().map(|e| &e.s[i]).collect::<Vec<_>>())
-                        .collect::<Vec<_>>()
-                        .iter(),
-                )
-                .map(|(c, e)| (c, e.clone(), None))
-                .collect::<Vec<_>>(),
+            commitments
+                .opened()
+                .into_iter()
+                .zip(e0.opened().into_iter().zip(e1.opened()))
+                .map(|(comm, (e0, e1))| (*comm, vec![e0, e1], None)),
         );
 
         // prepare for the opening proof verification
         let omega = index.domain.group_gen;
-        batch.push((
+        (
             fq_sponge.clone(),
             vec![oracles.zeta, oracles.zeta * omega],
             oracles.v,
             oracles.u,
             polynomials,
             &proof.proof,
-        ));
-    }
+        )
+    };
 
-    // final check to verify the evaluation proofs
-    match srs.verify::<EFqSponge, _>(group_map, &mut batch, &mut thread_rng()) {
-        false => Err(ProofError::OpenProof),
-        true => Ok(()),
+    // batch verify all the evaluation proofs
+    let mut batch: Vec<_> = (0..proofs.len()).map(opening).collect();
+    if srs.verify::<EFqSponge, _>(group_map, &mut batch, rng) {
+        drop(batch);
+        return Ok(params
+            .into_iter()
+            .map(|(_, _, _, fq_sponge, ..)| fq_sponge)
+            .collect());
     }
+
+    // the batch check only fails if one of the proofs is invalid,
+    // except with negligible probability over the randomness of the batching
+    let which = if proofs.len() > 1 {
+        (0..proofs.len())
+            .position(|i| !srs.verify::<EFqSponge, _>(group_map, &mut vec![opening(i)], rng))
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    Err(VerifyError::CommitmentOpeningFailed { which })
 }
@@ -3,27 +3,37 @@
 
 use crate::alphas::Alphas;
 use crate::circuits::{
-    constraints::{zk_polynomial, zk_w3},
+    constraints::{zk_polynomial, zk_w3, Shifts},
     expr::{Linearization, PolishToken},
     gate::LookupsUsed,
+    gates::poseidon::ROUNDS_PER_ROW,
+    polynomials::boundary::BoundaryConstraint,
+    public_input::PublicInputLayout,
     wires::*,
 };
-use crate::prover_index::ProverIndex;
+use crate::error::{PublicInputError, SetupError};
+use crate::prover_index::{quotient_chunks, ProverIndex};
+use crate::verifier::Precomputed;
 use ark_ec::AffineCurve;
-use ark_ff::PrimeField;
+use ark_ff::{BigInteger, One, PrimeField, Zero};
 use ark_poly::{univariate::DensePolynomial, Radix2EvaluationDomain as D};
 use array_init::array_init;
 use commitment_dlog::{
     commitment::{CommitmentCurve, PolyComm},
     srs::SRS,
 };
-use oracle::poseidon::ArithmeticSpongeParams;
+use oracle::{
+    poseidon::{ArithmeticSponge, ArithmeticSpongeParams, PlonkSpongeConstantsKimchi, Sponge},
+    FqSponge,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::serde_as;
 use std::io::SeekFrom::Start;
 use std::{
+    collections::HashMap,
     fs::{File, OpenOptions},
-    io::{BufReader, BufWriter, Seek},
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    ops::Deref,
     path::Path,
     sync::Arc,
 };
@@ -39,6 +49,55 @@ pub struct LookupVerifierIndex<G: CommitmentCurve> {
     pub lookup_tables: Vec<Vec<PolyComm<G>>>,
     #[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
     pub lookup_selectors: Vec<PolyComm<G>>,
+    /// The selector of the rows of the runtime table, if the circuit looks up one
+    #[serde(default, bound = "PolyComm<G>: Serialize + DeserializeOwned")]
+    pub runtime_selector: Option<PolyComm<G>>,
+}
+
+/// The format of the Fiat-Shamir transcript of the proofs of an index,
+/// that is, what the proofs absorb before the commitments to the witness.
+/// The prover and the verifier use the version of their index,
+/// so that a verifier can still check the proofs created in an older format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TranscriptVersion {
+    /// The transcript starts with the commitment to the public input.
+    V0,
+    /// The transcript starts with the [digest](VerifierIndex::digest) of the index,
+    /// followed by the commitment to the public input.
+    V1,
+    /// The transcript starts with the digest of the index and the values of the public input,
+    /// followed by the commitment to the public input.
+    V2,
+}
+
+impl TranscriptVersion {
+    /// The version of the transcript used by new indexes.
+    pub const LATEST: Self = Self::V2;
+
+    /// The version of the indexes serialized before the version was recorded.
+    fn unversioned() -> Self {
+        Self::V0
+    }
+
+    /// Absorbs what comes before the commitment to the public input in the transcript,
+    /// given the digest of the index (only computed if the version uses it) and the public input.
+    pub(crate) fn absorb_header<G, EFqSponge, Digest>(
+        self,
+        sponge: &mut EFqSponge,
+        digest: Digest,
+        public: &[Fr<G>],
+    ) where
+        G: CommitmentCurve,
+        EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+        Digest: FnOnce() -> Fq<G>,
+    {
+        if self >= Self::V1 {
+            sponge.absorb_fq(&[digest()]);
+        }
+        if self >= Self::V2 {
+            sponge.absorb_fr(public);
+        }
+    }
 }
 
 #[serde_as]
@@ -107,6 +166,50 @@ pub struct VerifierIndex<G: CommitmentCurve> {
     #[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
     pub lookup_index: Option<LookupVerifierIndex<G>>,
 
+    /// the names of the public inputs, if the circuit declares them
+    /// (missing from the indexes serialized before they could be named)
+    #[serde(default)]
+    pub public_layout: Option<PublicInputLayout>,
+
+    /// custom gates selector polynomial commitments, in registration order
+    #[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
+    #[serde(default)]
+    pub custom_comm: Vec<PolyComm<G>>,
+
+    /// linear combination weight polynomial commitments, if the circuit uses the gate
+    #[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
+    #[serde(default)]
+    pub linear_combination_comm: Option<[PolyComm<G>; COLUMNS]>,
+
+    /// commitments to the selectors of the partial rounds of the Poseidon gates, if the circuit has some
+    #[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
+    #[serde(default)]
+    pub partial_rounds_comm: Option<[PolyComm<G>; ROUNDS_PER_ROW]>,
+
+    /// boolean selector polynomial commitment, if the circuit uses the gate
+    #[serde(bound = "PolyComm<G>: Serialize + DeserializeOwned")]
+    #[serde(default)]
+    pub boolean_comm: Option<PolyComm<G>>,
+
+    /// the cells that the circuit pins to values, in order
+    #[serde(bound = "BoundaryConstraint<Fr<G>>: Serialize + DeserializeOwned")]
+    #[serde(default)]
+    pub boundary: Vec<BoundaryConstraint<Fr<G>>>,
+
+    /// the format of the transcript of the proofs
+    #[serde(default = "TranscriptVersion::unversioned")]
+    pub transcript_version: TranscriptVersion,
+
+    /// whether the constraint of the generic gates has the fused product
+    /// (see [generic](crate::circuits::polynomials::generic))
+    #[serde(default)]
+    pub fused_generic: bool,
+
+    /// number of public inputs
+    /// (missing from the indexes serialized before it was recorded, which [VerifierIndex::read] takes from their public layout)
+    #[serde(default)]
+    pub public: usize,
+
     #[serde(skip)]
     pub linearization: Linearization<Vec<PolishToken<Fr<G>>>>,
     /// The mapping between powers of alpha and constraints
@@ -148,6 +251,10 @@ where
                                 .collect()
                         })
                         .collect(),
+                    runtime_selector: cs
+                        .runtime_selector
+                        .as_ref()
+                        .map(|e| self.srs.commit_evaluations_non_hiding(domain, e, None)),
                 })
         };
 
@@ -156,13 +263,13 @@ where
             domain,
             max_poly_size: self.max_poly_size,
             max_quot_size: self.max_quot_size,
+            public: self.cs.public,
             powers_of_alpha: self.powers_of_alpha.clone(),
             srs: Arc::clone(&self.srs),
 
             sigma_comm: array_init(|i| self.srs.commit_non_hiding(&self.cs.sigmam[i], None)),
             coefficients_comm: array_init(|i| {
-                self.srs
-                    .commit_evaluations_non_hiding(domain, &self.cs.coefficients8[i], None)
+                self.cs.coefficients_sparse[i].commit(&self.srs, domain)
             }),
             generic_comm: self.srs.commit_non_hiding(&self.cs.genericm, None),
 
@@ -189,23 +296,272 @@ where
             chacha_comm: self.cs.chacha8.as_ref().map(|c| {
                 array_init(|i| self.srs.commit_evaluations_non_hiding(domain, &c[i], None))
             }),
+            linear_combination_comm: self.cs.linear_combination8.as_ref().map(|q| {
+                array_init(|i| self.srs.commit_evaluations_non_hiding(domain, &q[i], None))
+            }),
+            partial_rounds_comm: self.cs.partial_rounds8.as_ref().map(|p| {
+                array_init(|i| self.srs.commit_evaluations_non_hiding(domain, &p[i], None))
+            }),
+            boolean_comm: self
+                .cs
+                .boolean8
+                .as_ref()
+                .map(|b| self.srs.commit_evaluations_non_hiding(domain, b, None)),
+            boundary: self.cs.boundary.clone(),
+            transcript_version: self.transcript_version,
+            fused_generic: self.cs.fused_generic,
 
             shift: self.cs.shift,
             zkpm: self.cs.zkpm.clone(),
             w: zk_w3(self.cs.domain.d1),
             endo: self.cs.endo,
             lookup_index,
+            public_layout: self.public_layout.clone(),
+            custom_comm: self
+                .cs
+                .custom8
+                .iter()
+                .map(|c| self.srs.commit_evaluations_non_hiding(domain, c, None))
+                .collect(),
             linearization: self.linearization.clone(),
             fr_sponge_params: self.cs.fr_sponge_params.clone(),
             fq_sponge_params: self.fq_sponge_params.clone(),
         }
     }
+
+    /// Splits the index into the keys of the prover and of the verifier.
+    /// The keys share the SRS, and otherwise own their data,
+    /// so that each of them can be kept, serialized or dropped independently of the other.
+    pub fn into_keys(self) -> (ProverIndex<G>, VerifierIndex<G>) {
+        let verifier_key = self.verifier_index();
+        (self, verifier_key)
+    }
+
+    /// Creates the index again, twice, from its constraint system, and checks that both have the same linearization
+    /// and the same verifier index as this one, both its digest and its serialization.
+    /// This checks that another machine creating the index of the same circuit (or reading it back) derives the same verifier key.
+    /// Returns the digest of the verifier index.
+    pub fn check_reproducible(&self) -> Result<Fq<G>, SetupError> {
+        let recreate = || {
+            let create = if self.zk {
+                Self::try_create
+            } else {
+                Self::try_create_without_zk
+            };
+            let mut index = create(
+                self.cs.clone(),
+                self.fq_sponge_params.clone(),
+                self.cs.endo,
+                Arc::clone(&self.srs),
+            )?;
+            index.public_layout = self.public_layout.clone();
+            index.transcript_version = self.transcript_version;
+            Ok(index)
+        };
+        let serialize = |index: &VerifierIndex<G>| {
+            let mut bytes = vec![];
+            index
+                .write(&mut bytes)
+                .map(|_| bytes)
+                .map_err(|_| SetupError::NotReproducible("serialization"))
+        };
+
+        let verifier_index = self.verifier_index();
+        let digest = verifier_index.digest();
+        let bytes = serialize(&verifier_index)?;
+        for _ in 0..2 {
+            let index = recreate()?;
+            if index.linearization != self.linearization {
+                return Err(SetupError::NotReproducible("linearization"));
+            }
+            let other = index.verifier_index();
+            if other.digest() != digest {
+                return Err(SetupError::NotReproducible("verifier index digest"));
+            }
+            if serialize(&other)? != bytes {
+                return Err(SetupError::NotReproducible("verifier index serialization"));
+            }
+        }
+        Ok(digest)
+    }
+}
+
+/// Absorbs the parameters and the commitments of a [VerifierIndex] into a Poseidon sponge over the base field,
+/// each of them prefixed by what it takes to read them back unambiguously.
+struct IndexHasher<G: CommitmentCurve>(ArithmeticSponge<Fq<G>, PlonkSpongeConstantsKimchi>);
+
+impl<G: CommitmentCurve> IndexHasher<G>
+where
+    G::BaseField: PrimeField,
+{
+    fn integer(&mut self, x: usize) {
+        self.0.absorb(&[Fq::<G>::from(x as u64)]);
+    }
+
+    fn flag(&mut self, x: bool) {
+        self.0
+            .absorb(&[if x { Fq::<G>::one() } else { Fq::<G>::zero() }]);
+    }
+
+    /// A scalar does not necessarily fit in the base field:
+    /// it is absorbed as its bits but the lowest one, then that bit.
+    fn scalar(&mut self, x: &Fr<G>) {
+        let bits = x.into_repr().to_bits_le();
+        let high = <Fq<G> as PrimeField>::BigInt::from_bits_le(&bits[1..]);
+        self.0.absorb(&[
+            Fq::<G>::from_repr(high).expect("a scalar is smaller than twice the base field")
+        ]);
+        self.flag(bits[0]);
+    }
+
+    fn point(&mut self, g: &G) {
+        match g.to_coordinates() {
+            None => self.flag(false),
+            Some((x, y)) => {
+                self.flag(true);
+                self.0.absorb(&[x, y]);
+            }
+        }
+    }
+
+    fn comm(&mut self, comm: &PolyComm<G>) {
+        self.integer(comm.unshifted.len());
+        comm.unshifted.iter().for_each(|g| self.point(g));
+        self.flag(comm.shifted.is_some());
+        comm.shifted.iter().for_each(|g| self.point(g));
+    }
+
+    fn comms<'a>(&mut self, comms: impl ExactSizeIterator<Item = &'a PolyComm<G>>)
+    where
+        G: 'a,
+    {
+        self.integer(comms.len());
+        comms.for_each(|comm| self.comm(comm));
+    }
+}
+
+impl<G> VerifierIndex<G>
+where
+    G: CommitmentCurve,
+    G::BaseField: PrimeField,
+{
+    /// Returns the digest of the circuit of this index, a base field element binding
+    /// the size of the domain, the size of the commitments, the number of public inputs,
+    /// the shifts of the permutation, all the commitments to the polynomials of the circuit and its boundary constraints.
+    /// It does not depend on the serialization of the index, and can be computed in a circuit
+    /// or by a smart contract from the same values with the Poseidon sponge of the index.
+    /// Proofs absorb it first in their transcript, so that a proof only verifies against its circuit.
+    pub fn digest(&self) -> Fq<G> {
+        let mut h = IndexHasher::<G>(ArithmeticSponge::new(self.fq_sponge_params.clone()));
+        h.integer(self.domain.size as usize);
+        h.integer(self.max_poly_size);
+        h.integer(self.max_quot_size);
+        h.integer(self.public);
+        self.shift.iter().for_each(|s| h.scalar(s));
+
+        h.comms(self.sigma_comm.iter());
+        h.comms(self.coefficients_comm.iter());
+        h.comm(&self.generic_comm);
+        h.comm(&self.psm_comm);
+        h.comm(&self.complete_add_comm);
+        h.comm(&self.mul_comm);
+        h.comm(&self.emul_comm);
+        h.comm(&self.endomul_scalar_comm);
+        h.flag(self.chacha_comm.is_some());
+        if let Some(chacha_comm) = &self.chacha_comm {
+            h.comms(chacha_comm.iter());
+        }
+        h.flag(self.linear_combination_comm.is_some());
+        if let Some(linear_combination_comm) = &self.linear_combination_comm {
+            h.comms(linear_combination_comm.iter());
+        }
+        h.flag(self.partial_rounds_comm.is_some());
+        if let Some(partial_rounds_comm) = &self.partial_rounds_comm {
+            h.comms(partial_rounds_comm.iter());
+        }
+        h.flag(self.boolean_comm.is_some());
+        if let Some(boolean_comm) = &self.boolean_comm {
+            h.comms(std::iter::once(boolean_comm));
+        }
+        h.flag(self.lookup_index.is_some());
+        if let Some(lookup) = &self.lookup_index {
+            h.flag(matches!(lookup.lookup_used, LookupsUsed::Joint));
+            h.integer(lookup.lookup_tables.len());
+            lookup.lookup_tables.iter().for_each(|t| h.comms(t.iter()));
+            h.comms(lookup.lookup_selectors.iter());
+            h.flag(lookup.runtime_selector.is_some());
+            h.comms(lookup.runtime_selector.iter());
+        }
+        h.comms(self.custom_comm.iter());
+        h.integer(self.boundary.len());
+        for b in &self.boundary {
+            h.integer(b.row);
+            h.integer(b.column_index());
+            h.scalar(&b.value);
+        }
+        // only absorbed when set, so that the digests of the circuits without fused products do not change
+        if self.fused_generic {
+            h.flag(true);
+        }
+        h.0.squeeze()
+    }
+
+    /// Precomputes what the verification of proofs against this index derives from the index alone
+    /// (see [PreparedVerifierIndex]).
+    /// The SRS of the index must contain the Lagrange basis of its domain.
+    pub fn prepare(self) -> PreparedVerifierIndex<G> {
+        let digest = self.transcript_version >= TranscriptVersion::V1;
+        let precomputed = Precomputed::new(&self, digest);
+        PreparedVerifierIndex {
+            index: self,
+            precomputed,
+        }
+    }
+}
+
+/// A [VerifierIndex] along with what the verification of a proof derives from the index alone,
+/// computed once by [VerifierIndex::prepare] rather than for each proof
+/// (see [verify_prepared](crate::verifier::verify_prepared)):
+/// the digest of the index absorbed first by the transcripts,
+/// the commitments to the Lagrange polynomials of the rows of the public input,
+/// and the powers of the generator of the domain the evaluations of the public input polynomial are made of.
+///
+/// With the inner product argument, the opening proofs are checked with multi-scalar multiplications
+/// rather than pairings, so there are no group elements to prepare beyond these.
+pub struct PreparedVerifierIndex<G: CommitmentCurve> {
+    /// The prepared index
+    pub index: VerifierIndex<G>,
+    pub(crate) precomputed: Precomputed<G>,
+}
+
+impl<G: CommitmentCurve> Deref for PreparedVerifierIndex<G> {
+    type Target = VerifierIndex<G>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.index
+    }
 }
 
 impl<G> VerifierIndex<G>
 where
     G: CommitmentCurve,
 {
+    /// The number of chunks of the commitment to the quotient polynomial in the proofs of this index.
+    pub fn quotient_chunks(&self) -> usize {
+        quotient_chunks(self.max_quot_size, self.max_poly_size)
+    }
+
+    /// Orders the named public inputs `values` according to the layout of the circuit.
+    pub fn public_input(
+        &self,
+        values: &HashMap<&str, Fr<G>>,
+    ) -> Result<Vec<Fr<G>>, PublicInputError> {
+        self.public_layout
+            .as_ref()
+            .ok_or(PublicInputError::NoLayout)?
+            .assemble(values)
+    }
+
     /// Deserializes a [VerifierIndex] from a file, given a pointer to an SRS and an optional offset in the file.
     pub fn from_file(
         srs: Arc<SRS<G>>,
@@ -225,12 +581,34 @@ where
             reader.seek(Start(offset)).map_err(|e| e.to_string())?;
         }
 
+        Self::read(reader, |_| srs, endo, fq_sponge_params, fr_sponge_params)
+    }
+
+    /// Deserializes a [VerifierIndex] from `reader`.
+    /// As the SRS is not part of the serialized index, `srs` is given the deserialized index
+    /// and returns the SRS to use, which must contain the Lagrange basis of the domain of the index.
+    pub fn read<R: Read>(
+        reader: R,
+        srs: impl FnOnce(&Self) -> Arc<SRS<G>>,
+        // TODO: we shouldn't have to pass these
+        endo: G::ScalarField,
+        fq_sponge_params: ArithmeticSpongeParams<Fq<G>>,
+        fr_sponge_params: ArithmeticSpongeParams<Fr<G>>,
+    ) -> Result<Self, String> {
         // deserialize
         let mut verifier_index = Self::deserialize(&mut rmp_serde::Deserializer::new(reader))
             .map_err(|e| e.to_string())?;
+        Shifts::check(&verifier_index.domain, &verifier_index.shift).map_err(|e| e.to_string())?;
+
+        // the indexes serialized before the number of public inputs was recorded only have their layout
+        if verifier_index.public == 0 {
+            if let Some(layout) = &verifier_index.public_layout {
+                verifier_index.public = layout.len();
+            }
+        }
 
         // fill in the rest
-        verifier_index.srs = srs;
+        verifier_index.srs = srs(&verifier_index);
         verifier_index.endo = endo;
         verifier_index.fq_sponge_params = fq_sponge_params;
         verifier_index.fr_sponge_params = fr_sponge_params;
@@ -249,8 +627,11 @@ where
             .open(path)
             .map_err(|e| e.to_string())?;
 
-        let writer = BufWriter::new(file);
+        self.write(BufWriter::new(file))
+    }
 
+    /// Serializes a [VerifierIndex] to `writer`, in the format read by [VerifierIndex::read].
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), String> {
         self.serialize(&mut rmp_serde::Serializer::new(writer))
             .map_err(|e| e.to_string())
     }
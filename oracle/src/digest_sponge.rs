@@ -0,0 +1,229 @@
+//! This module implements Fiat-Shamir sponges over a hash function (Blake2b or SHA-256)
+//! instead of the Poseidon permutation, to match verifiers that derive their challenges this way.
+//!
+//! The transcript is a running hash of the absorbed values:
+//! a group element is absorbed as a tag byte (0 for the point at infinity, 1 otherwise)
+//! followed by its coordinates, and a field element as its canonical representation,
+//! all in little-endian.
+//! Squeezing outputs the hash of the transcript, and restarts the transcript from that output,
+//! so that the following challenges depend on all the previous ones.
+//!
+//! The Poseidon parameters given to [FqSponge::new] are ignored.
+//! These sponges are not meant to be verified in a circuit.
+
+use crate::poseidon::ArithmeticSpongeParams;
+use crate::sponge::CHALLENGE_LENGTH_IN_LIMBS;
+use crate::FqSponge;
+use ark_ec::{short_weierstrass_jacobian::GroupAffine, SWModelParameters};
+use ark_ff::{BigInteger, Field, PrimeField};
+use blake2::{Blake2b512, Digest};
+use sha2::Sha256;
+use std::marker::PhantomData;
+
+/// A transcript hashed with `D`.
+#[derive(Clone, Default)]
+pub struct DigestSponge<D> {
+    hasher: D,
+    squeezed: Vec<u8>,
+}
+
+impl<D: Digest + Clone> DigestSponge<D> {
+    /// Creates an empty transcript.
+    pub fn new() -> Self {
+        Self {
+            hasher: D::new(),
+            squeezed: vec![],
+        }
+    }
+
+    /// Absorbs raw bytes.
+    pub fn absorb_bytes(&mut self, bytes: &[u8]) {
+        self.squeezed.clear();
+        self.hasher.update(bytes);
+    }
+
+    /// Absorbs a field element.
+    pub fn absorb_field<F: PrimeField>(&mut self, x: &F) {
+        self.absorb_bytes(&x.into_repr().to_bytes_le());
+    }
+
+    /// Absorbs a group element.
+    pub fn absorb_point<P: SWModelParameters>(&mut self, g: &GroupAffine<P>)
+    where
+        P::BaseField: PrimeField,
+    {
+        if g.infinity {
+            self.absorb_bytes(&[0]);
+        } else {
+            self.absorb_bytes(&[1]);
+            self.absorb_field(&g.x);
+            self.absorb_field(&g.y);
+        }
+    }
+
+    /// Returns the next `n` bytes of output.
+    pub fn squeeze_bytes(&mut self, n: usize) -> Vec<u8> {
+        while self.squeezed.len() < n {
+            let output = self.hasher.clone().finalize();
+            self.hasher = D::new();
+            self.hasher.update(&output);
+            self.squeezed.extend_from_slice(&output);
+        }
+        self.squeezed.drain(..n).collect()
+    }
+
+    /// Returns a uniform field element, reducing 128 more bits than the field has.
+    pub fn squeeze_field<F: PrimeField>(&mut self) -> F {
+        let bytes = (F::size_in_bits() + 128 + 7) / 8;
+        F::from_le_bytes_mod_order(&self.squeeze_bytes(bytes))
+    }
+
+    /// Returns a field element of [CHALLENGE_LENGTH_IN_LIMBS] limbs, like the challenges of the Poseidon sponges.
+    pub fn squeeze_challenge<F: PrimeField>(&mut self) -> F {
+        F::from_le_bytes_mod_order(&self.squeeze_bytes(8 * CHALLENGE_LENGTH_IN_LIMBS))
+    }
+}
+
+/// A [FqSponge] hashing its transcript with `D`.
+pub struct DigestFqSponge<P, D> {
+    pub sponge: DigestSponge<D>,
+    params: PhantomData<P>,
+}
+
+// not derived, as the curve parameters do not implement Clone
+impl<P, D: Clone> Clone for DigestFqSponge<P, D> {
+    fn clone(&self) -> Self {
+        Self {
+            sponge: self.sponge.clone(),
+            params: PhantomData,
+        }
+    }
+}
+
+/// A scalar field sponge hashing its transcript with `D`.
+#[derive(Clone)]
+pub struct DigestFrSponge<Fr, D> {
+    pub sponge: DigestSponge<D>,
+    field: PhantomData<Fr>,
+}
+
+impl<Fr: Field, D: Digest + Clone> DigestFrSponge<Fr, D> {
+    /// Creates an empty transcript.
+    pub fn new() -> Self {
+        Self {
+            sponge: DigestSponge::new(),
+            field: PhantomData,
+        }
+    }
+}
+
+impl<Fr: Field, D: Digest + Clone> Default for DigestFrSponge<Fr, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The base field sponge over Blake2b.
+pub type Blake2bFqSponge<P> = DigestFqSponge<P, Blake2b512>;
+/// The scalar field sponge over Blake2b.
+pub type Blake2bFrSponge<Fr> = DigestFrSponge<Fr, Blake2b512>;
+/// The base field sponge over SHA-256.
+pub type Sha256FqSponge<P> = DigestFqSponge<P, Sha256>;
+/// The scalar field sponge over SHA-256.
+pub type Sha256FrSponge<Fr> = DigestFrSponge<Fr, Sha256>;
+
+impl<P: SWModelParameters, D: Digest + Clone> FqSponge<P::BaseField, GroupAffine<P>, P::ScalarField>
+    for DigestFqSponge<P, D>
+where
+    P::BaseField: PrimeField,
+{
+    fn new(_params: ArithmeticSpongeParams<P::BaseField>) -> Self {
+        Self {
+            sponge: DigestSponge::new(),
+            params: PhantomData,
+        }
+    }
+
+    fn absorb_g(&mut self, g: &[GroupAffine<P>]) {
+        for g in g {
+            self.sponge.absorb_point(g);
+        }
+    }
+
+    fn absorb_fr(&mut self, x: &[P::ScalarField]) {
+        for x in x {
+            self.sponge.absorb_field(x);
+        }
+    }
+
+    fn absorb_fq(&mut self, x: &[P::BaseField]) {
+        for x in x {
+            self.sponge.absorb_field(x);
+        }
+    }
+
+    fn challenge(&mut self) -> P::ScalarField {
+        self.sponge.squeeze_challenge()
+    }
+
+    /// The challenge reduces 128 more bits than the scalar field has,
+    /// so that its statistical distance to a uniform scalar is at most $2^{-128}$.
+    fn challenge_full(&mut self) -> P::ScalarField {
+        self.sponge.squeeze_field()
+    }
+
+    fn challenge_fq(&mut self) -> P::BaseField {
+        self.sponge.squeeze_field()
+    }
+
+    fn digest(mut self) -> P::ScalarField {
+        self.sponge.squeeze_field()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use ark_ff::Zero;
+    use mina_curves::pasta::{
+        vesta::{Affine, VestaParameters},
+        Fp,
+    };
+
+    fn transcript<D: Digest + Clone>(points: &[Affine]) -> (Fp, Fp) {
+        let mut sponge = <DigestFqSponge<VestaParameters, D> as FqSponge<_, _, _>>::new(
+            ArithmeticSpongeParams::default(),
+        );
+        sponge.absorb_g(points);
+        let challenge = sponge.challenge();
+        sponge.absorb_fr(&[challenge]);
+        (challenge, sponge.digest())
+    }
+
+    #[test]
+    fn test_digest_sponge() {
+        let g = Affine::prime_subgroup_generator();
+        let h = g.mul(Fp::from(2u64)).into_affine();
+        let points = [g, Affine::zero(), h];
+
+        // deterministic, and binding to the absorbed values and their order
+        let (challenge, digest) = transcript::<Blake2b512>(&points);
+        assert_eq!((challenge, digest), transcript::<Blake2b512>(&points));
+        assert_ne!(challenge, transcript::<Blake2b512>(&points[..2]).0);
+        assert_ne!(
+            challenge,
+            transcript::<Blake2b512>(&[h, Affine::zero(), g]).0
+        );
+        assert_ne!(challenge, transcript::<Sha256>(&points).0);
+
+        // the challenges fit in two limbs
+        assert!(challenge.into_repr().num_bits() <= 128);
+
+        // the outputs of successive squeezes differ
+        let mut sponge = DigestSponge::<Sha256>::new();
+        let first: Fp = sponge.squeeze_field();
+        let second: Fp = sponge.squeeze_field();
+        assert_ne!(first, second);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod digest_sponge;
+pub mod params;
 pub mod pasta;
 pub mod poseidon;
 pub mod sponge;
@@ -8,7 +10,21 @@ pub trait FqSponge<Fq: Field, G, Fr> {
     fn new(p: poseidon::ArithmeticSpongeParams<Fq>) -> Self;
     fn absorb_g(&mut self, g: &[G]);
     fn absorb_fr(&mut self, x: &[Fr]);
+    fn absorb_fq(&mut self, x: &[Fq]);
+
+    /// Squeezes a challenge of [sponge::CHALLENGE_LENGTH_IN_LIMBS] limbs (128 bits),
+    /// which is meant to be used as a scalar challenge (see [sponge::ScalarChallenge]).
     fn challenge(&mut self) -> Fr;
+
+    /// Squeezes `k` challenges, the same ones as `k` successive calls to [FqSponge::challenge].
+    fn challenges(&mut self, k: usize) -> Vec<Fr> {
+        (0..k).map(|_| self.challenge()).collect()
+    }
+
+    /// Squeezes a challenge spanning the whole scalar field, rather than 128 bits.
+    /// Its distribution is close to uniform, the implementations document how close.
+    fn challenge_full(&mut self) -> Fr;
+
     fn challenge_fq(&mut self) -> Fq;
 
     fn digest(self) -> Fr;
@@ -0,0 +1,378 @@
+//! This module generates Poseidon parameters for any prime field,
+//! so that new curves do not have to ship their own round constants and MDS matrices.
+//!
+//! The parameters are derived following the reference implementation of Poseidon
+//! (`generate_parameters_grain.sage`, from <https://eprint.iacr.org/2019/458>):
+//! the Grain LFSR ([Grain]) is seeded with the shape of the permutation,
+//! and its output is used to draw the round constants and then the Cauchy MDS matrix.
+//! As the reference, the number of rounds for a given security level ([PoseidonConfig::new])
+//! is the cheapest one satisfying the bounds of the paper (statistical, interpolation and Gröbner basis attacks),
+//! with a security margin of two full rounds and 7.5% of partial rounds.
+//!
+//! Unlike the reference, the MDS matrix is the first Cauchy matrix drawn,
+//! without checking it against invariant subspace trails:
+//! this only matters for partial rounds, which kimchi does not use.
+//!
+//! The parameters of the pasta fields in [crate::pasta] were generated differently (see `params.sage`),
+//! and are kept as they are.
+
+use crate::poseidon::{ArithmeticSpongeParams, SpongeConstants};
+use ark_ff::{BigInteger, PrimeField};
+use serde::{Deserialize, Serialize};
+
+/// The shape of a Poseidon permutation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoseidonConfig {
+    /// the number of field elements of the state
+    pub width: usize,
+    /// the exponent of the S-box
+    pub alpha: u32,
+    /// the number of rounds applying the S-box to the whole state
+    pub full_rounds: usize,
+    /// the number of rounds applying the S-box to the first element of the state
+    pub partial_rounds: usize,
+}
+
+impl PoseidonConfig {
+    /// Returns the cheapest permutation (in number of S-boxes) of the given `width` and `alpha`
+    /// that provides `security_level` bits of security over `F`.
+    /// `alpha` must be at least 3, and coprime with the order of the multiplicative group of `F`.
+    pub fn new<F: PrimeField>(width: usize, alpha: u32, security_level: usize) -> Self {
+        assert!(width >= 2, "the state must have at least two elements");
+        assert!(alpha >= 3, "the S-box must be of degree at least 3");
+
+        let mut best: Option<(usize, Self)> = None;
+        for partial_rounds in 1..500 {
+            // the smallest even number of full rounds for this number of partial rounds
+            let full_rounds = (4..100).step_by(2).find(|full| {
+                is_secure::<F>(width, alpha, *full, partial_rounds, security_level as f64)
+            });
+            if let Some(full_rounds) = full_rounds {
+                let config = Self {
+                    width,
+                    alpha,
+                    full_rounds: full_rounds + 2,
+                    partial_rounds: (partial_rounds as f64 * 1.075).ceil() as usize,
+                };
+                let cost = config.full_rounds * width + config.partial_rounds;
+                if best.map_or(true, |(best_cost, best)| {
+                    cost < best_cost || (cost == best_cost && config.full_rounds < best.full_rounds)
+                }) {
+                    best = Some((cost, config));
+                }
+            }
+        }
+        best.expect("no number of rounds is secure").1
+    }
+
+    /// Returns the shape of the permutation of the sponge constants `SC`.
+    /// For the permutation made of full rounds only, the initial round constants count as a round.
+    pub fn from_constants<SC: SpongeConstants>(alpha: u32) -> Self {
+        let (full_rounds, partial_rounds) = if SC::PERM_HALF_ROUNDS_FULL == 0 {
+            (
+                SC::PERM_ROUNDS_FULL + SC::PERM_INITIAL_ARK as usize,
+                SC::PERM_ROUNDS_PARTIAL,
+            )
+        } else {
+            (2 * SC::PERM_HALF_ROUNDS_FULL, SC::PERM_ROUNDS_PARTIAL)
+        };
+        Self {
+            width: SC::SPONGE_WIDTH,
+            alpha,
+            full_rounds,
+            partial_rounds,
+        }
+    }
+
+    /// The number of rounds, which is the number of rows of round constants.
+    pub fn rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+
+    /// Generates the round constants and the MDS matrix of the permutation over `F`.
+    pub fn generate<F: PrimeField>(&self) -> ArithmeticSpongeParams<F> {
+        let bits = F::size_in_bits();
+        let mut grain = Grain::new(self, bits);
+
+        let round_constants = (0..self.rounds())
+            .map(|_| (0..self.width).map(|_| grain.field_element()).collect())
+            .collect();
+
+        let mds = loop {
+            let elems: Vec<F> = (0..2 * self.width)
+                .map(|_| grain.field_element_mod_order())
+                .collect();
+            let distinct = elems
+                .iter()
+                .enumerate()
+                .all(|(i, x)| elems[..i].iter().all(|y| x != y));
+            if !distinct {
+                continue;
+            }
+            let (xs, ys) = elems.split_at(self.width);
+            let mds: Option<Vec<Vec<F>>> = xs
+                .iter()
+                .map(|x| ys.iter().map(|y| (*x + y).inverse()).collect())
+                .collect();
+            if let Some(mds) = mds {
+                break mds;
+            }
+        };
+
+        ArithmeticSpongeParams {
+            round_constants,
+            mds,
+        }
+    }
+}
+
+/// Returns log2 of the modulus of `F`.
+fn log2_modulus<F: PrimeField>() -> f64 {
+    let limbs = F::characteristic();
+    let top = limbs.iter().rposition(|limb| *limb != 0).unwrap_or(0);
+    let mut lead = limbs[top] as f64;
+    if top > 0 {
+        lead += limbs[top - 1] as f64 / 2f64.powi(64);
+    }
+    lead.log2() + 64.0 * top as f64
+}
+
+/// Returns true if the permutation resists the attacks considered by the Poseidon paper,
+/// before adding the security margin.
+fn is_secure<F: PrimeField>(
+    width: usize,
+    alpha: u32,
+    full_rounds: usize,
+    partial_rounds: usize,
+    security_level: f64,
+) -> bool {
+    let log2_p = log2_modulus::<F>();
+    let n = F::size_in_bits() as f64;
+    let t = width as f64;
+    let r_p = partial_rounds as f64;
+    let alpha = alpha as f64;
+    let log_alpha_2 = 1.0 / alpha.log2();
+
+    // statistical attacks
+    let statistical = if security_level <= (log2_p - (alpha - 1.0) / 2.0).floor() * (t + 1.0) {
+        6.0
+    } else {
+        10.0
+    };
+    // interpolation attack
+    let interpolation =
+        1.0 + (log_alpha_2 * security_level.min(n)).ceil() + (t.ln() / alpha.ln()).ceil() - r_p;
+    // Gröbner basis attacks
+    let groebner_1 = log_alpha_2 * security_level.min(log2_p) - r_p;
+    let groebner_2 = t - 1.0 + log_alpha_2 * (security_level / (t + 1.0)).min(log2_p / 2.0) - r_p;
+    let groebner_3 = (t - 2.0 + security_level / (2.0 * alpha.log2()) - r_p) / (t - 1.0);
+
+    let bound = [
+        statistical,
+        interpolation,
+        groebner_1,
+        groebner_2,
+        groebner_3,
+    ]
+    .iter()
+    .map(|bound| bound.ceil())
+    .fold(f64::MIN, f64::max);
+    full_rounds as f64 >= bound
+}
+
+/// The Grain LFSR used by the reference implementation of Poseidon to generate its parameters.
+pub struct Grain {
+    state: [bool; 80],
+    bits: usize,
+}
+
+impl Grain {
+    /// Seeds the LFSR with the shape of the permutation, for a field of `bits` bits.
+    pub fn new(config: &PoseidonConfig, bits: usize) -> Self {
+        let mut seed = Vec::with_capacity(80);
+        let mut push = |value: usize, len: usize| {
+            seed.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+        };
+        // a prime field, and the S-box x^alpha
+        push(1, 2);
+        push(0, 4);
+        push(bits, 12);
+        push(config.width, 12);
+        push(config.full_rounds, 10);
+        push(config.partial_rounds, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut state = [false; 80];
+        state.copy_from_slice(&seed);
+        let mut grain = Self { state, bits };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    /// Returns the next output bit: the bits are produced in pairs,
+    /// and the second bit of a pair is only output if the first one is set.
+    pub fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Returns the next `self.bits` output bits, most significant first.
+    fn next_bits(&mut self) -> Vec<bool> {
+        (0..self.bits).map(|_| self.next_bit()).collect()
+    }
+
+    /// Returns the next field element, sampled by rejection.
+    pub fn field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            if let Some(x) = F::from_repr(F::BigInt::from_bits_be(&self.next_bits())) {
+                return x;
+            }
+        }
+    }
+
+    /// Returns the next field element, reducing the output bits modulo the order of the field.
+    pub fn field_element_mod_order<F: PrimeField>(&mut self) -> F {
+        let bits = self.next_bits();
+        let padding = (8 - bits.len() % 8) % 8;
+        let bytes: Vec<u8> = std::iter::repeat(false)
+            .take(padding)
+            .chain(bits)
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | *bit as u8))
+            .collect();
+        F::from_be_bytes_mod_order(&bytes)
+    }
+}
+
+/// A Poseidon instance: the shape of the permutation along with its parameters, for serialization.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoseidonInstance<F: PrimeField> {
+    pub config: PoseidonConfig,
+    #[serde(bound = "ArithmeticSpongeParams<F>: Serialize + serde::de::DeserializeOwned")]
+    pub params: ArithmeticSpongeParams<F>,
+}
+
+impl<F: PrimeField> PoseidonInstance<F> {
+    /// Generates the instance of `config`.
+    pub fn generate(config: PoseidonConfig) -> Self {
+        Self {
+            config,
+            params: config.generate(),
+        }
+    }
+
+    /// Checks that the instance can be used by a sponge with the constants `SC`.
+    pub fn check<SC: SpongeConstants>(&self) -> Result<(), String> {
+        let expected = PoseidonConfig::from_constants::<SC>(self.config.alpha);
+        if SC::PERM_SBOX != self.config.alpha || expected != self.config {
+            return Err(format!(
+                "the sponge constants describe {expected:?}, but the instance is {:?}",
+                self.config
+            ));
+        }
+        let width = self.config.width;
+        if SC::SPONGE_CAPACITY + SC::SPONGE_RATE != width
+            || self.params.mds.len() != width
+            || self.params.mds.iter().any(|row| row.len() != width)
+        {
+            return Err(format!("the MDS matrix is not of width {width}"));
+        }
+        if self.params.round_constants.len() != self.config.rounds()
+            || self
+                .params
+                .round_constants
+                .iter()
+                .any(|row| row.len() != width)
+        {
+            return Err(format!(
+                "expected {} rounds of {width} round constants",
+                self.config.rounds()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::{
+        ArithmeticSponge, PlonkSpongeConstantsKimchi, PlonkSpongeConstantsLegacy, Sponge,
+    };
+    use ark_ff::{One, Zero};
+    use mina_curves::pasta::{Fp, Fq};
+
+    /// The permutation of Poseidon-128 over a 255-bit field, with partial rounds.
+    #[derive(Clone)]
+    struct Pow5Width3;
+
+    impl SpongeConstants for Pow5Width3 {
+        const PERM_ROUNDS_FULL: usize = 8;
+        const PERM_ROUNDS_PARTIAL: usize = 56;
+        const PERM_HALF_ROUNDS_FULL: usize = 4;
+        const PERM_SBOX: u32 = 5;
+        const PERM_FULL_MDS: bool = true;
+        const PERM_INITIAL_ARK: bool = false;
+    }
+
+    #[test]
+    fn test_round_numbers() {
+        // the round numbers of the pasta instances of the reference implementation
+        let config = PoseidonConfig::new::<Fp>(3, 5, 128);
+        assert_eq!((config.full_rounds, config.partial_rounds), (8, 56));
+        assert_eq!(config, PoseidonConfig::from_constants::<Pow5Width3>(5));
+
+        assert_eq!(
+            PoseidonConfig::from_constants::<PlonkSpongeConstantsKimchi>(7).rounds(),
+            crate::pasta::fp_kimchi::params().round_constants.len()
+        );
+        // the legacy permutation adds round constants before its 63 rounds
+        assert_eq!(
+            PoseidonConfig::from_constants::<PlonkSpongeConstantsLegacy>(5).rounds(),
+            64
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let config = PoseidonConfig::new::<Fq>(3, 5, 128);
+        let instance = PoseidonInstance::<Fq>::generate(config);
+        instance.check::<Pow5Width3>().unwrap();
+        assert!(instance.check::<PlonkSpongeConstantsKimchi>().is_err());
+
+        // the generation is deterministic, and depends on the shape of the permutation
+        assert_eq!(instance.params.mds, config.generate::<Fq>().mds);
+        let wider = PoseidonConfig::new::<Fq>(5, 5, 128).generate::<Fq>();
+        assert_ne!(
+            wider.round_constants[0][0],
+            instance.params.round_constants[0][0]
+        );
+
+        // the MDS matrix is invertible (its first 2x2 minor, at least)
+        let m = &instance.params.mds;
+        assert!(!(m[0][0] * m[1][1] - m[0][1] * m[1][0]).is_zero());
+
+        let mut sponge = ArithmeticSponge::<Fq, Pow5Width3>::new(instance.params.clone());
+        sponge.absorb(&[Fq::one(), Fq::from(2u64)]);
+        let digest = sponge.squeeze();
+        let mut again = ArithmeticSponge::<Fq, Pow5Width3>::new(instance.params);
+        again.absorb(&[Fq::one(), Fq::from(2u64)]);
+        assert_eq!(digest, again.squeeze());
+    }
+}
@@ -140,7 +140,7 @@ fn half_rounds<F: Field, SC: SpongeConstants>(
         for state_i in state.iter_mut() {
             *state_i = sbox::<F, SC>(*state_i);
         }
-        apply_mds_matrix::<F, SC>(params, state);
+        *state = apply_mds_matrix::<F, SC>(params, state);
     }
 
     for r in 0..SC::PERM_ROUNDS_PARTIAL {
@@ -151,7 +151,7 @@ fn half_rounds<F: Field, SC: SpongeConstants>(
             state[i].add_assign(x);
         }
         state[0] = sbox::<F, SC>(state[0]);
-        apply_mds_matrix::<F, SC>(params, state);
+        *state = apply_mds_matrix::<F, SC>(params, state);
     }
 
     for r in 0..SC::PERM_HALF_ROUNDS_FULL {
@@ -165,7 +165,7 @@ fn half_rounds<F: Field, SC: SpongeConstants>(
         for state_i in state.iter_mut() {
             *state_i = sbox::<F, SC>(*state_i);
         }
-        apply_mds_matrix::<F, SC>(params, state);
+        *state = apply_mds_matrix::<F, SC>(params, state);
     }
 }
 
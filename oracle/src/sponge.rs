@@ -151,6 +151,11 @@ where
         }
     }
 
+    fn absorb_fq(&mut self, x: &[P::BaseField]) {
+        self.last_squeezed = vec![];
+        self.sponge.absorb(x);
+    }
+
     fn absorb_fr(&mut self, x: &[P::ScalarField]) {
         self.last_squeezed = vec![];
 
@@ -198,11 +203,62 @@ where
         self.squeeze(CHALLENGE_LENGTH_IN_LIMBS)
     }
 
+    /// The challenge is the next element of the base field squeezed by the sponge, reduced modulo the scalar field.
+    /// With $p$ and $q$ the moduli of the base and scalar fields, with $p < 2q$,
+    /// its statistical distance to a uniform scalar is at most $|p - q| / \max(p, q)$,
+    /// which is about $2^{-168}$ for the Pasta curves.
+    fn challenge_full(&mut self) -> P::ScalarField {
+        let x = self.squeeze_field().into_repr();
+        P::ScalarField::from_le_bytes_mod_order(&x.to_bytes_le())
+    }
+
     fn challenge_fq(&mut self) -> P::BaseField {
         self.squeeze_field()
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pasta::fq_kimchi::params as spongeFqParams;
+    use crate::poseidon::PlonkSpongeConstantsKimchi as SC;
+    use ark_ec::AffineCurve;
+    use mina_curves::pasta::{
+        vesta::{Affine, VestaParameters},
+        Fp,
+    };
+
+    type Sponge = DefaultFqSponge<VestaParameters, SC>;
+
+    /// The transcript of a prover or a verifier, which both absorb the same values.
+    fn transcript() -> Sponge {
+        let mut sponge = Sponge::new(spongeFqParams());
+        sponge.absorb_g(&[Affine::prime_subgroup_generator()]);
+        sponge.absorb_fr(&[Fp::from(42u64)]);
+        sponge
+    }
+
+    #[test]
+    fn test_challenge_sequences() {
+        let (mut prover, mut verifier) = (transcript(), transcript());
+
+        // squeezing several challenges at once is the same as squeezing them one by one
+        let challenges = prover.challenges(3);
+        let one_by_one: Vec<_> = (0..3).map(|_| verifier.challenge()).collect();
+        assert_eq!(challenges, one_by_one);
+        assert!(challenges.iter().all(|c| c.into_repr().num_bits() <= 128));
+        assert_ne!(challenges[0], challenges[1]);
+
+        // full-field challenges
+        let full = prover.challenge_full();
+        assert_eq!(full, verifier.challenge_full());
+        assert!(full.into_repr().num_bits() > 128);
+
+        // the transcripts stay in sync
+        assert_eq!(prover.challenge(), verifier.challenge());
+    }
+}
+
 //
 // OCaml types
 //
@@ -0,0 +1,20 @@
+use mina_curves::pasta::Fp;
+use oracle::params::{PoseidonConfig, PoseidonInstance};
+use oracle::poseidon::PlonkSpongeConstantsKimchi;
+
+#[test]
+fn poseidon_instance_serialization() {
+    // a fresh instance with the shape of the kimchi permutation
+    let config = PoseidonConfig::from_constants::<PlonkSpongeConstantsKimchi>(7);
+    let instance = PoseidonInstance::<Fp>::generate(config);
+    instance.check::<PlonkSpongeConstantsKimchi>().unwrap();
+
+    let serialized = serde_json::to_string(&instance).unwrap();
+    let deserialized: PoseidonInstance<Fp> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.config, config);
+    assert_eq!(deserialized.params.mds, instance.params.mds);
+    assert_eq!(
+        deserialized.params.round_constants,
+        instance.params.round_constants
+    );
+}
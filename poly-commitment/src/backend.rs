@@ -0,0 +1,158 @@
+//! This module implements the hooks through which the two dominant kernels of the prover,
+//! the multi-scalar multiplications of the commitments and the FFTs, can be offloaded.
+//!
+//! A backend implements [MsmBackend] for a curve or [FftBackend] for a field,
+//! and is installed for the whole process with [set_msm_backend] or [set_fft_backend]
+//! (for example by a crate wrapping a CUDA or Metal implementation).
+//! The commitments (see [CommitmentCurve::msm](crate::commitment::CommitmentCurve::msm))
+//! and the FFT helpers of the prover then use the installed backend instead of their CPU implementation.
+//! Until a backend is installed, the hooks only cost a check that none is.
+//!
+//! [CpuBackend] implements both traits with arkworks, and can be used as a fallback by the other backends.
+
+use ark_ec::{msm::VariableBaseMSM, AffineCurve};
+use ark_ff::{FftField, PrimeField};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain as D};
+use std::{
+    any::{Any, TypeId},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once, RwLock,
+    },
+};
+
+/// Computes multi-scalar multiplications over the curve `G`.
+pub trait MsmBackend<G: AffineCurve>: Send + Sync {
+    /// Returns the multi-scalar multiplication of `bases` by `scalars`,
+    /// ignoring the bases or scalars past the shortest of the two.
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> G::Projective;
+}
+
+/// Computes FFTs over the field `F`.
+pub trait FftBackend<F: FftField>: Send + Sync {
+    /// Evaluates the polynomial of coefficients `coeffs` over `domain`.
+    fn fft(&self, domain: D<F>, coeffs: &[F]) -> Vec<F>;
+
+    /// Interpolates the evaluations `evals` over `domain`, returning the coefficients of the polynomial.
+    fn ifft(&self, domain: D<F>, evals: &[F]) -> Vec<F>;
+}
+
+/// The CPU implementation of the kernels, from arkworks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuBackend;
+
+impl<G: AffineCurve> MsmBackend<G> for CpuBackend {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> G::Projective {
+        let scalars: Vec<_> = scalars.iter().map(|s| s.into_repr()).collect();
+        VariableBaseMSM::multi_scalar_mul(bases, &scalars)
+    }
+}
+
+impl<F: FftField> FftBackend<F> for CpuBackend {
+    fn fft(&self, domain: D<F>, coeffs: &[F]) -> Vec<F> {
+        domain.fft(coeffs)
+    }
+
+    fn ifft(&self, domain: D<F>, evals: &[F]) -> Vec<F> {
+        domain.ifft(evals)
+    }
+}
+
+/// The installed backends, keyed by the type of their trait object.
+type Registry = RwLock<Vec<(TypeId, Box<dyn Any + Send + Sync>)>>;
+
+static INIT: Once = Once::new();
+static mut REGISTRY: Option<Registry> = None;
+/// Set once a backend is installed, so that the hooks skip the registry until then.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+fn registry() -> &'static Registry {
+    // SAFETY: `REGISTRY` is only written once, before `INIT` completes
+    INIT.call_once(|| unsafe { REGISTRY = Some(RwLock::new(vec![])) });
+    unsafe { REGISTRY.as_ref().expect("initialized above") }
+}
+
+fn install<B: ?Sized + 'static>(backend: &'static B)
+where
+    &'static B: Send + Sync,
+{
+    let mut backends = registry().write().unwrap();
+    let key = TypeId::of::<B>();
+    backends.retain(|(k, _)| *k != key);
+    backends.push((key, Box::new(backend)));
+    INSTALLED.store(true, Ordering::Release);
+}
+
+fn installed<B: ?Sized + 'static>() -> Option<&'static B> {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return None;
+    }
+    let backends = registry().read().unwrap();
+    let key = TypeId::of::<B>();
+    backends
+        .iter()
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, backend)| backend.downcast_ref::<&'static B>())
+        .copied()
+}
+
+/// Installs the backend of the multi-scalar multiplications over `G`, replacing the previous one if any.
+pub fn set_msm_backend<G: AffineCurve>(backend: &'static dyn MsmBackend<G>) {
+    install(backend)
+}
+
+/// Installs the backend of the FFTs over `F`, replacing the previous one if any.
+pub fn set_fft_backend<F: FftField>(backend: &'static dyn FftBackend<F>) {
+    install(backend)
+}
+
+/// Returns the installed backend of the multi-scalar multiplications over `G`, if any.
+pub fn msm_backend<G: AffineCurve>() -> Option<&'static dyn MsmBackend<G>> {
+    installed::<dyn MsmBackend<G>>()
+}
+
+/// Returns the installed backend of the FFTs over `F`, if any.
+pub fn fft_backend<F: FftField>() -> Option<&'static dyn FftBackend<F>> {
+    installed::<dyn FftBackend<F>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commitment::CommitmentCurve, srs::SRS};
+    use ark_ec::ProjectiveCurve;
+    use ark_ff::UniformRand;
+    use ark_poly::{univariate::DensePolynomial, UVPolynomial};
+    use mina_curves::pasta::{fq::Fq, pallas::Affine};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::sync::atomic::AtomicUsize;
+
+    /// Counts the multiplications it delegates to the CPU.
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl MsmBackend<Affine> for Counting {
+        fn msm(&self, bases: &[Affine], scalars: &[Fq]) -> <Affine as AffineCurve>::Projective {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            CpuBackend.msm(bases, scalars)
+        }
+    }
+
+    #[test]
+    fn test_msm_backend() {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let srs = SRS::<Affine>::create(16);
+        let scalars: Vec<_> = (0..16).map(|_| Fq::rand(rng)).collect();
+        let expected = Affine::msm(&srs.g, &scalars).into_affine();
+
+        let counting: &'static Counting = Box::leak(Box::new(Counting::default()));
+        set_msm_backend::<Affine>(counting);
+        assert!(msm_backend::<Affine>().is_some());
+
+        // the commitments go through the backend
+        let poly = DensePolynomial::from_coefficients_vec(scalars);
+        let comm = srs.commit_non_hiding(&poly, None);
+        assert_eq!(comm.unshifted, vec![expected]);
+        assert!(counting.0.load(Ordering::SeqCst) > 0);
+    }
+}
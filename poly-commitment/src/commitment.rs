@@ -6,7 +6,7 @@
 //!     producing the batched opening proof
 //! 3. Verify batch of batched opening proofs
 
-use crate::srs::SRS;
+use crate::{msm::GlvParameters, srs::SRS};
 use ark_ec::{
     models::short_weierstrass_jacobian::GroupAffine as SWJAffine, msm::VariableBaseMSM,
     AffineCurve, ProjectiveCurve, SWModelParameters,
@@ -184,7 +184,9 @@ impl<C: AffineCurve> PolyComm<C> {
             shifted: self.shifted.map(|g| g.mul(c).into_affine()),
         }
     }
+}
 
+impl<C: CommitmentCurve> PolyComm<C> {
     pub fn multi_scalar_mul(com: &[&PolyComm<C>], elm: &[C::ScalarField]) -> Self {
         assert_eq!(com.len(), elm.len());
         PolyComm::<C> {
@@ -198,8 +200,8 @@ impl<C: AffineCurve> PolyComm<C> {
                     None
                 } else {
                     let points = pairs.iter().map(|(c, _)| *c).collect::<Vec<_>>();
-                    let scalars = pairs.iter().map(|(_, s)| s.into_repr()).collect::<Vec<_>>();
-                    Some(VariableBaseMSM::multi_scalar_mul(&points, &scalars).into_affine())
+                    let scalars = pairs.iter().map(|(_, s)| **s).collect::<Vec<_>>();
+                    Some(C::msm(&points, &scalars).into_affine())
                 }
             },
             unshifted: {
@@ -214,10 +216,10 @@ impl<C: AffineCurve> PolyComm<C> {
                             com.iter().zip(elm.iter()).for_each(|(p, s)| {
                                 if i < p.unshifted.len() {
                                     points.push(p.unshifted[i]);
-                                    scalars.push(s.into_repr())
+                                    scalars.push(*s)
                                 }
                             });
-                            VariableBaseMSM::multi_scalar_mul(&points, &scalars).into_affine()
+                            C::msm(&points, &scalars).into_affine()
                         })
                         .collect::<Vec<_>>()
                 }
@@ -316,6 +318,22 @@ pub trait CommitmentCurve: AffineCurve {
     fn to_coordinates(&self) -> Option<(Self::BaseField, Self::BaseField)>;
     fn of_coordinates(x: Self::BaseField, y: Self::BaseField) -> Self;
 
+    /// Returns the multi-scalar multiplication of `bases` by `scalars`.
+    /// The curves with an efficient endomorphism override it with the GLV method (see [crate::msm]).
+    /// Both defer to the backend installed with [crate::backend::set_msm_backend], if any.
+    fn msm(bases: &[Self], scalars: &[Self::ScalarField]) -> Self::Projective {
+        o1_utils::metrics::histogram(
+            crate::msm::MSM_SIZE,
+            &[],
+            std::cmp::min(bases.len(), scalars.len()) as f64,
+        );
+        if let Some(backend) = crate::backend::msm_backend::<Self>() {
+            return backend.msm(bases, scalars);
+        }
+        let scalars: Vec<_> = scalars.iter().map(|s| s.into_repr()).collect();
+        VariableBaseMSM::multi_scalar_mul(bases, &scalars)
+    }
+
     /// Combine where x1 = one
     fn combine_one(g1: &[Self], g2: &[Self], x2: Self::ScalarField) -> Vec<Self> {
         crate::combine::window_combine(g1, g2, Self::ScalarField::one(), x2)
@@ -342,7 +360,7 @@ pub trait CommitmentCurve: AffineCurve {
     }
 }
 
-impl<P: SWModelParameters> CommitmentCurve for SWJAffine<P>
+impl<P: GlvParameters> CommitmentCurve for SWJAffine<P>
 where
     P::BaseField: PrimeField,
 {
@@ -361,6 +379,10 @@ where
         SWJAffine::<P>::new(x, y, false)
     }
 
+    fn msm(bases: &[Self], scalars: &[Self::ScalarField]) -> Self::Projective {
+        crate::msm::msm(bases, scalars)
+    }
+
     fn combine_one(g1: &[Self], g2: &[Self], x2: Self::ScalarField) -> Vec<Self> {
         crate::combine::affine_window_combine_one(g1, g2, x2)
     }
@@ -532,6 +554,59 @@ impl<G: CommitmentCurve> SRS<G> {
         .unzip()
     }
 
+    /// Turns a non-hiding polynomial commitment into a hiding one using the given blinding factors,
+    /// transforming each `<a, G>` into `<a, G> + wH`.
+    /// Returns `None` if the blinding factors and the commitment do not have the same number of chunks.
+    pub fn mask_custom(&self, c: PolyComm<G>, blinders: &PolyComm<Fr<G>>) -> Option<PolyComm<G>> {
+        if c.unshifted.len() != blinders.unshifted.len()
+            || c.shifted.is_some() != blinders.shifted.is_some()
+        {
+            return None;
+        }
+        let mask = |g: &G, w: &Fr<G>| {
+            let mut g_masked = self.h.mul(*w);
+            g_masked.add_assign_mixed(g);
+            g_masked.into_affine()
+        };
+        Some(PolyComm {
+            unshifted: c
+                .unshifted
+                .iter()
+                .zip(&blinders.unshifted)
+                .map(|(g, w)| mask(g, w))
+                .collect(),
+            shifted: c.shifted.zip(blinders.shifted).map(|(g, w)| mask(&g, &w)),
+        })
+    }
+
+    /// Commits a polynomial with the given blinding factors (one per chunk, see [SRS::commit_non_hiding]),
+    /// for callers that need to choose or to later reveal the randomness of a commitment.
+    /// Returns `None` if the number of blinding factors does not match the number of chunks.
+    pub fn commit_hiding(
+        &self,
+        plnm: &DensePolynomial<Fr<G>>,
+        max: Option<usize>,
+        blinders: &PolyComm<Fr<G>>,
+    ) -> Option<PolyComm<G>> {
+        self.mask_custom(self.commit_non_hiding(plnm, max), blinders)
+    }
+
+    /// Checks that `comm` is a hiding commitment to `plnm` with the blinding factors `blinders`.
+    pub fn verify_hiding(
+        &self,
+        comm: &PolyComm<G>,
+        plnm: &DensePolynomial<Fr<G>>,
+        max: Option<usize>,
+        blinders: &PolyComm<Fr<G>>,
+    ) -> bool {
+        match self.commit_hiding(plnm, max, blinders) {
+            Some(expected) => {
+                expected.unshifted == comm.unshifted && expected.shifted == comm.shifted
+            }
+            None => false,
+        }
+    }
+
     /// This function commits a polynomial using the SRS' basis of size `n`.
     /// - `plnm`: polynomial to commit to with max size of sections
     /// - `max`: maximal degree of the polynomial (not inclusive), if none, no degree bound
@@ -564,16 +639,7 @@ impl<G: CommitmentCurve> SRS<G> {
             Vec::new()
         } else {
             (0..p / n + if p % n != 0 { 1 } else { 0 })
-                .map(|i| {
-                    VariableBaseMSM::multi_scalar_mul(
-                        basis,
-                        &scalars[i * n..p]
-                            .iter()
-                            .map(|s| s.into_repr())
-                            .collect::<Vec<_>>(),
-                    )
-                    .into_affine()
-                })
+                .map(|i| G::msm(basis, &scalars[i * n..p]).into_affine())
                 .collect()
         };
 
@@ -587,16 +653,7 @@ impl<G: CommitmentCurve> SRS<G> {
                 } else if max % n == 0 {
                     None
                 } else {
-                    Some(
-                        VariableBaseMSM::multi_scalar_mul(
-                            &basis[n - (max % n)..],
-                            &scalars[start..p]
-                                .iter()
-                                .map(|s| s.into_repr())
-                                .collect::<Vec<_>>(),
-                        )
-                        .into_affine(),
-                    )
+                    Some(G::msm(&basis[n - (max % n)..], &scalars[start..p]).into_affine())
                 }
             }
         };
@@ -604,6 +661,12 @@ impl<G: CommitmentCurve> SRS<G> {
         PolyComm::<G> { unshifted, shifted }
     }
 
+    /// Commits to the polynomial given by its evaluations `plnm`, without interpolating it,
+    /// using the Lagrange basis of the SRS for `domain` (see [SRS::add_lagrange_basis]).
+    /// The evaluations can be over a domain larger than `domain`, in which case they are subsampled.
+    /// The commitment is the same as the one [SRS::commit_non_hiding] computes from the coefficients,
+    /// including when the domain is larger than the SRS and the commitment has several chunks
+    /// (a degree bound `max` is then not supported).
     pub fn commit_evaluations_non_hiding(
         &self,
         domain: D<Fr<G>>,
@@ -611,27 +674,78 @@ impl<G: CommitmentCurve> SRS<G> {
         max: Option<usize>,
     ) -> PolyComm<G> {
         let is_zero = plnm.evals.iter().all(|x| x.is_zero());
-        let basis = match self.lagrange_bases.get(&domain.size()) {
+        let chunks = match self.lagrange_basis_chunks(domain.size()) {
             None => panic!("lagrange bases for size {} not found", domain.size()),
-            Some(v) => &v[..],
+            Some(chunks) => chunks,
         };
-        match domain.size.cmp(&plnm.domain().size) {
+        let subsampled;
+        let evals = match domain.size.cmp(&plnm.domain().size) {
             std::cmp::Ordering::Less => {
                 let s = (plnm.domain().size / domain.size) as usize;
-                let v: Vec<_> = (0..(domain.size as usize))
+                subsampled = (0..(domain.size as usize))
                     .map(|i| plnm.evals[s * i])
-                    .collect();
-                Self::commit_helper(&v[..], basis, None, is_zero, max)
-            }
-            std::cmp::Ordering::Equal => {
-                Self::commit_helper(&plnm.evals[..], basis, None, is_zero, max)
+                    .collect::<Vec<_>>();
+                &subsampled[..]
             }
+            std::cmp::Ordering::Equal => &plnm.evals[..],
             std::cmp::Ordering::Greater => {
                 panic!("desired commitment domain size greater than evaluations' domain size")
             }
+        };
+        if chunks.len() == 1 {
+            let basis = &self.lagrange_bases[&domain.size()];
+            return Self::commit_helper(evals, basis, None, is_zero, max);
+        }
+        assert!(
+            max.is_none(),
+            "degree bounds are not supported for domains larger than the SRS"
+        );
+        let unshifted = if is_zero {
+            vec![]
+        } else {
+            chunks
+                .map(|basis| G::msm(basis, evals).into_affine())
+                .collect()
+        };
+        PolyComm {
+            unshifted,
+            shifted: None,
+        }
+    }
+
+    /// Same as [SRS::commit_evaluations_non_hiding], for the evaluations over `domain`
+    /// that are zero everywhere but at the rows of `entries`, given with their values.
+    /// The multi-scalar multiplication only goes through the points of the Lagrange basis of these rows,
+    /// which is much faster than the dense commitment for the sparse polynomials
+    /// (e.g. the selectors of gates that only appear on a few rows).
+    pub fn commit_sparse_evaluations_non_hiding(
+        &self,
+        domain: D<Fr<G>>,
+        entries: &[(usize, Fr<G>)],
+    ) -> PolyComm<G> {
+        let chunks = match self.lagrange_basis_chunks(domain.size()) {
+            None => panic!("lagrange bases for size {} not found", domain.size()),
+            Some(chunks) => chunks,
+        };
+        let entries: Vec<_> = entries.iter().filter(|(_, x)| !x.is_zero()).collect();
+        let scalars: Vec<_> = entries.iter().map(|(_, x)| *x).collect();
+        let unshifted = if scalars.is_empty() {
+            vec![]
+        } else {
+            chunks
+                .map(|basis| {
+                    let points: Vec<_> = entries.iter().map(|(row, _)| basis[*row]).collect();
+                    G::msm(&points, &scalars).into_affine()
+                })
+                .collect()
+        };
+        PolyComm {
+            unshifted,
+            shifted: None,
         }
     }
 
+    /// Same as [SRS::commit_evaluations_non_hiding], with the commitment masked as in [SRS::commit].
     pub fn commit_evaluations(
         &self,
         domain: D<Fr<G>>,
@@ -853,8 +967,7 @@ impl<G: CommitmentCurve> SRS<G> {
         }
 
         // verify the equation
-        let scalars: Vec<_> = scalars.iter().map(|x| x.into_repr()).collect();
-        VariableBaseMSM::multi_scalar_mul(&points, &scalars) == G::Projective::zero()
+        G::msm(&points, &scalars) == G::Projective::zero()
     }
 }
 
@@ -899,6 +1012,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_srs_is_transparent() {
+        let mut srs = SRS::<VestaG>::create(32);
+        assert!(srs.is_transparent());
+
+        // an SRS with a point of known discrete logarithm
+        srs.g[1] = srs.g[0].mul(Fp::from(2u64)).into_affine();
+        assert!(!srs.is_transparent());
+    }
+
+    #[test]
+    fn test_srs_read() {
+        let srs = SRS::<VestaG>::create(32);
+        let mut bytes = vec![];
+        srs.write(&mut bytes).unwrap();
+        let read = SRS::<VestaG>::read(&bytes[..]).unwrap();
+        assert_eq!(read.g, srs.g);
+        assert_eq!(read.endo_r, srs.endo_r);
+
+        let mut tampered = srs;
+        tampered.g.swap(0, 1);
+        let mut bytes = vec![];
+        tampered.write(&mut bytes).unwrap();
+        assert!(SRS::<VestaG>::read(&bytes[..]).is_err());
+    }
+
     #[test]
     fn test_lagrange_commitments() {
         let n = 64;
@@ -928,6 +1067,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_commit_evaluations() {
+        let n = 32;
+        let domain = D::<Fp>::new(n).unwrap();
+        let mut srs = SRS::<VestaG>::create(n);
+        srs.add_lagrange_basis(domain);
+
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let evals: Vec<_> = (0..n).map(|_| Fp::rand(rng)).collect();
+        let evals = Evaluations::<Fp, D<Fp>>::from_vec_and_domain(evals, domain);
+        let poly = evals.interpolate_by_ref();
+        // PolyComm does not implement PartialEq
+        let parts = |c: PolyComm<VestaG>| (c.unshifted, c.shifted);
+        let expected = parts(srs.commit_non_hiding(&poly, None));
+
+        assert_eq!(
+            parts(srs.commit_evaluations_non_hiding(domain, &evals, None)),
+            expected.clone()
+        );
+
+        // evaluations over a larger domain are subsampled
+        let evals4 = poly.evaluate_over_domain_by_ref(D::<Fp>::new(4 * n).unwrap());
+        assert_eq!(
+            parts(srs.commit_evaluations_non_hiding(domain, &evals4, None)),
+            expected
+        );
+
+        // only the given rows of sparse evaluations are committed to
+        let entries = vec![(3, Fp::rand(rng)), (17, Fp::zero()), (30, Fp::rand(rng))];
+        let mut dense = vec![Fp::zero(); n];
+        for (row, x) in &entries {
+            dense[*row] = *x;
+        }
+        let dense = Evaluations::<Fp, D<Fp>>::from_vec_and_domain(dense, domain);
+        assert_eq!(
+            parts(srs.commit_sparse_evaluations_non_hiding(domain, &entries)),
+            parts(srs.commit_evaluations_non_hiding(domain, &dense, None))
+        );
+        assert!(srs
+            .commit_sparse_evaluations_non_hiding(domain, &[])
+            .unshifted
+            .is_empty());
+    }
+
+    #[test]
+    fn test_commit_evaluations_in_chunks() {
+        // a domain four times larger than the SRS
+        let n = 64;
+        let domain = D::<Fp>::new(n).unwrap();
+        let mut srs = SRS::<VestaG>::create(n / 4);
+        srs.add_lagrange_basis(domain);
+        assert_eq!(srs.lagrange_basis_chunks(n).unwrap().len(), 4);
+
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let evals: Vec<_> = (0..n).map(|_| Fp::rand(rng)).collect();
+        let evals = Evaluations::<Fp, D<Fp>>::from_vec_and_domain(evals, domain);
+        let expected = srs.commit_non_hiding(&evals.interpolate_by_ref(), None);
+        assert_eq!(expected.unshifted.len(), 4);
+        assert_eq!(
+            srs.commit_evaluations_non_hiding(domain, &evals, None)
+                .unshifted,
+            expected.unshifted
+        );
+
+        let entries = vec![(5, Fp::rand(rng)), (40, Fp::rand(rng))];
+        let mut dense = vec![Fp::zero(); n];
+        for (row, x) in &entries {
+            dense[*row] = *x;
+        }
+        let dense = Evaluations::<Fp, D<Fp>>::from_vec_and_domain(dense, domain);
+        assert_eq!(
+            srs.commit_sparse_evaluations_non_hiding(domain, &entries)
+                .unshifted,
+            srs.commit_non_hiding(&dense.interpolate(), None).unshifted
+        );
+    }
+
     #[test]
     fn test_opening_proof() {
         // create two polynomials
@@ -997,6 +1213,39 @@ mod tests {
 
         assert!(srs.verify(&group_map, &mut batch, rng));
     }
+
+    #[test]
+    fn test_commit_hiding() {
+        let coeffs: [Fp; 30] = array_init(|i| Fp::from(i as u32));
+        let poly = DensePolynomial::<Fp>::from_coefficients_slice(&coeffs);
+        let srs = SRS::<VestaG>::create(16);
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+
+        // the randomness of a regular commitment opens it
+        let (comm, blinders) = srs.commit(&poly, Some(30), rng);
+        assert!(srs.verify_hiding(&comm, &poly, Some(30), &blinders));
+
+        // chosen blinders
+        let blinders = PolyComm {
+            unshifted: vec![Fp::rand(rng), Fp::rand(rng)],
+            shifted: None,
+        };
+        let comm = srs.commit_hiding(&poly, None, &blinders).unwrap();
+        assert!(srs.verify_hiding(&comm, &poly, None, &blinders));
+
+        let other = PolyComm {
+            unshifted: vec![blinders.unshifted[0], Fp::rand(rng)],
+            shifted: None,
+        };
+        assert!(!srs.verify_hiding(&comm, &poly, None, &other));
+
+        // the number of blinders must match the number of chunks
+        let short = PolyComm {
+            unshifted: vec![Fp::rand(rng)],
+            shifted: None,
+        };
+        assert!(srs.commit_hiding(&poly, None, &short).is_none());
+    }
 }
 
 //
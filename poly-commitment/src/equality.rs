@@ -0,0 +1,282 @@
+//! This module implements small protocols relating two hiding commitments without opening them.
+//!
+//! Two hiding commitments $C_1 = \langle p_1, G \rangle + r_1 H$ and $C_2 = \langle p_2, G \rangle + r_2 H$
+//! are to the same polynomial exactly when $C_1 - C_2 = (r_1 - r_2) H$,
+//! which the owner of the blinders proves with a Schnorr proof of knowledge of $r_1 - r_2$
+//! (see [SRS::prove_equal_commitments]).
+//! Their polynomials take the same value at a point $x$ exactly when $C_1 - C_2$,
+//! a commitment to $p_1 - p_2$, opens to zero at $x$ (see [SRS::prove_equal_evaluations]).
+//!
+//! This is useful to bridge two systems committing to the same data with independent randomness.
+//! Both protocols require the commitments to have the same number of chunks and no degree bound.
+
+use crate::commitment::{CommitmentCurve, PolyComm};
+use crate::evaluation_proof::OpeningProof;
+use crate::srs::SRS;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{One, PrimeField, UniformRand, Zero};
+use ark_poly::univariate::DensePolynomial;
+use oracle::FqSponge;
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// A proof that two hiding commitments are to the same polynomial.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EqualityProof<G: AffineCurve> {
+    /// The commitment to the nonce of the Schnorr proof
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
+    pub nonce: G,
+    /// The response of the Schnorr proof
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
+    pub response: Fr<G>,
+}
+
+/// Returns `true` if the commitments have the same number of chunks and no degree bound.
+fn comparable<A, B>(c1: &PolyComm<A>, c2: &PolyComm<B>) -> bool
+where
+    A: ark_serialize::CanonicalSerialize + ark_serialize::CanonicalDeserialize,
+    B: ark_serialize::CanonicalSerialize + ark_serialize::CanonicalDeserialize,
+{
+    c1.unshifted.len() == c2.unshifted.len() && c1.shifted.is_none() && c2.shifted.is_none()
+}
+
+/// Returns the difference of the blinders of two commitments.
+fn sub_blinders<F>(b1: &PolyComm<F>, b2: &PolyComm<F>) -> PolyComm<F>
+where
+    F: PrimeField,
+{
+    PolyComm {
+        unshifted: b1
+            .unshifted
+            .iter()
+            .zip(&b2.unshifted)
+            .map(|(x, y)| *x - y)
+            .collect(),
+        shifted: None,
+    }
+}
+
+impl<G: CommitmentCurve> SRS<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Absorbs both commitments and combines the chunks of their difference
+    /// (as well as the chunks of `blinders`, the difference of their blinders, if given) with the powers of a challenge.
+    fn combine_difference<EFqSponge>(
+        sponge: &mut EFqSponge,
+        c1: &PolyComm<G>,
+        c2: &PolyComm<G>,
+        blinders: Option<&PolyComm<Fr<G>>>,
+    ) -> (G::Projective, Fr<G>)
+    where
+        EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+    {
+        sponge.absorb_g(&c1.unshifted);
+        sponge.absorb_g(&c2.unshifted);
+        let scale = sponge.challenge();
+
+        let mut diff = G::Projective::zero();
+        let mut blinder = Fr::<G>::zero();
+        let mut scale_i = Fr::<G>::one();
+        for (i, (g1, g2)) in c1.unshifted.iter().zip(&c2.unshifted).enumerate() {
+            diff += g1.mul(scale_i) - g2.mul(scale_i);
+            if let Some(blinders) = blinders {
+                blinder += scale_i * blinders.unshifted[i];
+            }
+            scale_i *= scale;
+        }
+        (diff, blinder)
+    }
+
+    /// Proves that `c1` and `c2`, two hiding commitments with the blinders `b1` and `b2` (as returned by [SRS::commit]),
+    /// are commitments to the same polynomial.
+    /// The proof is checked by [SRS::verify_equal_commitments], with a sponge in the same state as `sponge`.
+    ///
+    /// Panics if the commitments have different numbers of chunks or a degree bound.
+    pub fn prove_equal_commitments<EFqSponge, RNG>(
+        &self,
+        (c1, b1): (&PolyComm<G>, &PolyComm<Fr<G>>),
+        (c2, b2): (&PolyComm<G>, &PolyComm<Fr<G>>),
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> EqualityProof<G>
+    where
+        EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        assert!(comparable(c1, c2) && comparable(c1, b1) && comparable(c2, b2));
+        let blinders = sub_blinders(b1, b2);
+        let (_, blinder) = Self::combine_difference(&mut sponge, c1, c2, Some(&blinders));
+
+        let k = Fr::<G>::rand(rng);
+        let nonce = self.h.mul(k).into_affine();
+        sponge.absorb_g(&[nonce]);
+        let challenge = sponge.challenge();
+
+        EqualityProof {
+            nonce,
+            response: k + challenge * blinder,
+        }
+    }
+
+    /// Verifies a proof created by [SRS::prove_equal_commitments] that `c1` and `c2` commit to the same polynomial.
+    pub fn verify_equal_commitments<EFqSponge>(
+        &self,
+        c1: &PolyComm<G>,
+        c2: &PolyComm<G>,
+        proof: &EqualityProof<G>,
+        mut sponge: EFqSponge,
+    ) -> bool
+    where
+        EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+    {
+        if !comparable(c1, c2) {
+            return false;
+        }
+        let (diff, _) = Self::combine_difference(&mut sponge, c1, c2, None);
+        sponge.absorb_g(&[proof.nonce]);
+        let challenge = sponge.challenge();
+
+        // response * H = nonce + challenge * (C1 - C2)
+        let mut expected = diff.into_affine().mul(challenge);
+        expected.add_assign_mixed(&proof.nonce);
+        self.h.mul(proof.response) == expected
+    }
+
+    /// Proves that the polynomials `p1` and `p2`, committed to in `c1` and `c2` with the blinders `b1` and `b2`
+    /// (as returned by [SRS::commit]), take the same value at `point`, without revealing this value.
+    /// The proof is checked by [SRS::verify_equal_evaluations], with a sponge in the same state as `sponge`.
+    ///
+    /// Panics if the polynomials do not fit in the SRS.
+    #[allow(clippy::type_complexity)]
+    pub fn prove_equal_evaluations<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        (p1, c1, b1): (&DensePolynomial<Fr<G>>, &PolyComm<G>, &PolyComm<Fr<G>>),
+        (p2, c2, b2): (&DensePolynomial<Fr<G>>, &PolyComm<G>, &PolyComm<Fr<G>>),
+        point: Fr<G>,
+        sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> OpeningProof<G>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        assert!(comparable(c1, c2) && comparable(c1, b1) && comparable(c2, b2));
+        let diff = p1 - p2;
+        self.open_at(
+            group_map,
+            &diff,
+            &(c1 - c2),
+            sub_blinders(b1, b2),
+            point,
+            sponge,
+            rng,
+        )
+    }
+
+    /// Verifies a proof created by [SRS::prove_equal_evaluations]
+    /// that the polynomials committed to in `c1` and `c2` take the same value at `point`.
+    pub fn verify_equal_evaluations<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        c1: &PolyComm<G>,
+        c2: &PolyComm<G>,
+        point: Fr<G>,
+        proof: &OpeningProof<G>,
+        sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        comparable(c1, c2)
+            && self.verify_opening(
+                group_map,
+                &(c1 - c2),
+                point,
+                Fr::<G>::zero(),
+                proof,
+                sponge,
+                rng,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_poly::{Polynomial, UVPolynomial};
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{fp::Fp, vesta::Affine as VestaG};
+    use oracle::poseidon::PlonkSpongeConstantsKimchi as SC;
+    use oracle::{pasta::fq_kimchi::params as spongeFqParams, sponge::DefaultFqSponge};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_equal_commitments() {
+        // the polynomial spans two chunks
+        let srs = SRS::<VestaG>::create(8);
+        let sponge = DefaultFqSponge::<_, SC>::new(spongeFqParams());
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+
+        let poly = DensePolynomial::<Fp>::rand(12, rng);
+        let (c1, b1) = srs.commit(&poly, None, rng);
+        let (c2, b2) = srs.commit(&poly, None, rng);
+        assert_ne!(c1.unshifted, c2.unshifted);
+
+        let proof = srs.prove_equal_commitments((&c1, &b1), (&c2, &b2), sponge.clone(), rng);
+        assert!(srs.verify_equal_commitments(&c1, &c2, &proof, sponge.clone()));
+
+        // another polynomial
+        let other = DensePolynomial::<Fp>::rand(12, rng);
+        let (c3, b3) = srs.commit(&other, None, rng);
+        let proof = srs.prove_equal_commitments((&c1, &b1), (&c3, &b3), sponge.clone(), rng);
+        assert!(!srs.verify_equal_commitments(&c1, &c3, &proof, sponge));
+    }
+
+    #[test]
+    fn test_equal_evaluations() {
+        let srs = SRS::<VestaG>::create(16);
+        let group_map = <VestaG as CommitmentCurve>::Map::setup();
+        let sponge = DefaultFqSponge::<_, SC>::new(spongeFqParams());
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+
+        // two polynomials that agree at a point
+        let point = Fp::rand(rng);
+        let p1 = DensePolynomial::<Fp>::rand(10, rng);
+        let mut p2 = DensePolynomial::<Fp>::rand(10, rng);
+        p2.coeffs[0] += p1.evaluate(&point) - p2.evaluate(&point);
+        assert_eq!(p1.evaluate(&point), p2.evaluate(&point));
+
+        let (c1, b1) = srs.commit(&p1, None, rng);
+        let (c2, b2) = srs.commit(&p2, None, rng);
+        let proof = srs.prove_equal_evaluations(
+            &group_map,
+            (&p1, &c1, &b1),
+            (&p2, &c2, &b2),
+            point,
+            sponge.clone(),
+            rng,
+        );
+        assert!(srs.verify_equal_evaluations(
+            &group_map,
+            &c1,
+            &c2,
+            point,
+            &proof,
+            sponge.clone(),
+            rng
+        ));
+
+        // the proof does not hold at another point
+        let other = point + Fp::one();
+        assert!(!srs.verify_equal_evaluations(&group_map, &c1, &c2, other, &proof, sponge, rng));
+    }
+}
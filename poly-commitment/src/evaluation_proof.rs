@@ -1,11 +1,13 @@
 use crate::commitment::*;
 use crate::srs::SRS;
-use ark_ec::{msm::VariableBaseMSM, AffineCurve, ProjectiveCurve};
+use ark_ec::{AffineCurve, ProjectiveCurve};
 use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
 use ark_poly::univariate::DensePolynomial;
 use oracle::{sponge::ScalarChallenge, FqSponge};
 use rand_core::{CryptoRng, RngCore};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 use std::iter::Iterator;
 
 type Fr<G> = <G as AffineCurve>::ScalarField;
@@ -155,23 +157,15 @@ impl<G: CommitmentCurve> SRS<G> {
             let rand_l = Fr::<G>::rand(rng);
             let rand_r = Fr::<G>::rand(rng);
 
-            let l = VariableBaseMSM::multi_scalar_mul(
+            let l = G::msm(
                 &[&g[0..n], &[self.h, u]].concat(),
-                &[&a[n..], &[rand_l, inner_prod(a_hi, b_lo)]]
-                    .concat()
-                    .iter()
-                    .map(|x| x.into_repr())
-                    .collect::<Vec<_>>(),
+                &[&a[n..], &[rand_l, inner_prod(a_hi, b_lo)]].concat(),
             )
             .into_affine();
 
-            let r = VariableBaseMSM::multi_scalar_mul(
+            let r = G::msm(
                 &[&g[n..], &[self.h, u]].concat(),
-                &[&a[0..n], &[rand_r, inner_prod(a_lo, b_hi)]]
-                    .concat()
-                    .iter()
-                    .map(|x| x.into_repr())
-                    .collect::<Vec<_>>(),
+                &[&a[0..n], &[rand_r, inner_prod(a_lo, b_hi)]].concat(),
             )
             .into_affine();
 
@@ -249,13 +243,19 @@ impl<G: CommitmentCurve> SRS<G> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OpeningProof<G: AffineCurve> {
     /// vector of rounds of L & R commitments
+    #[serde_as(as = "Vec<(o1_utils::serialization::SerdeAs, o1_utils::serialization::SerdeAs)>")]
     pub lr: Vec<(G, G)>,
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub delta: G,
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub z1: G::ScalarField,
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub z2: G::ScalarField,
+    #[serde_as(as = "o1_utils::serialization::SerdeAs")]
     pub sg: G,
 }
 
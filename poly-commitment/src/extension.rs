@@ -0,0 +1,253 @@
+//! This module implements openings of polynomial commitments at points of a quadratic extension of the scalar field.
+//!
+//! A point of the extension is written `x = c0 + c1 u` with `u^2 = nonresidue`.
+//! As the committed polynomials have their coefficients in the base field,
+//! the evaluation `p(x) = e0 + e1 u` is entirely determined by the remainder of `p` modulo
+//! the minimal polynomial `m(X) = (X - x)(X - x')` of `x`, where `x' = c0 - c1 u` is the conjugate of `x`.
+//! Writing `p(X) = q(X) m(X) + r1 X + r0`, we have `e1 = r1 c1` and `e0 = r1 c0 + r0`.
+//!
+//! The prover thus commits to the quotient `q` of each polynomial,
+//! and the verifier checks the division at a random point `z` of the base field,
+//! using a regular (batched) opening of the polynomials and their quotients at `z`.
+
+use crate::commitment::{CommitmentCurve, PolyComm};
+use crate::evaluation_proof::OpeningProof;
+use crate::srs::SRS;
+use ark_ec::AffineCurve;
+use ark_ff::{Field, LegendreSymbol, PrimeField, SquareRootField, Zero};
+use ark_poly::univariate::{DenseOrSparsePolynomial, DensePolynomial};
+use ark_poly::{Polynomial, UVPolynomial};
+use o1_utils::ExtendedDensePolynomial as _;
+use oracle::FqSponge;
+use rand_core::{CryptoRng, RngCore};
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// A point `c0 + c1 u` of the quadratic extension `F[u] / (u^2 - nonresidue)`,
+/// which does not lie in the base field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExtensionPoint<F> {
+    pub c0: F,
+    pub c1: F,
+    pub nonresidue: F,
+}
+
+impl<F: SquareRootField> ExtensionPoint<F> {
+    /// Creates the point `c0 + c1 u`, where `u^2 = nonresidue`.
+    /// Panics if `nonresidue` is a square, or if the point lies in the base field (`c1 = 0`).
+    pub fn new(c0: F, c1: F, nonresidue: F) -> Self {
+        assert_eq!(
+            nonresidue.legendre(),
+            LegendreSymbol::QuadraticNonResidue,
+            "the extension is not a field"
+        );
+        assert!(!c1.is_zero(), "the point lies in the base field");
+        Self { c0, c1, nonresidue }
+    }
+}
+
+impl<F: Field> ExtensionPoint<F> {
+    /// Returns the coefficients of the minimal polynomial `X^2 - 2 c0 X + c0^2 - nonresidue c1^2` of the point.
+    pub fn minimal_polynomial(&self) -> DensePolynomial<F> {
+        let constant = self.c0.square() - self.nonresidue * self.c1.square();
+        DensePolynomial::from_coefficients_vec(vec![constant, -self.c0.double(), F::one()])
+    }
+
+    /// Evaluates `poly` at the point, and returns the two coordinates `(e0, e1)` of the evaluation.
+    pub fn evaluate(&self, poly: &DensePolynomial<F>) -> (F, F) {
+        // Horner's method, in the extension
+        poly.coeffs
+            .iter()
+            .rev()
+            .fold((F::zero(), F::zero()), |(a0, a1), c| {
+                (
+                    a0 * self.c0 + self.nonresidue * a1 * self.c1 + c,
+                    a0 * self.c1 + a1 * self.c0,
+                )
+            })
+    }
+
+    /// Returns the evaluation at `z` of the remainder `r1 X + r0` of any polynomial
+    /// evaluating to `(e0, e1)` at the point, modulo the minimal polynomial.
+    fn remainder(&self, (e0, e1): (F, F), z: F) -> F {
+        let r1 = e1 / self.c1;
+        let r0 = e0 - r1 * self.c0;
+        r1 * z + r0
+    }
+}
+
+/// An opening of a batch of polynomial commitments at an [ExtensionPoint].
+#[derive(Clone, Debug)]
+pub struct ExtensionOpening<G: AffineCurve> {
+    /// the evaluations of the polynomials at the extension point
+    pub evals: Vec<(Fr<G>, Fr<G>)>,
+    /// the commitments to the quotients of the polynomials by the minimal polynomial of the point
+    pub quotients: Vec<PolyComm<G>>,
+    /// the (chunked) evaluations of the polynomials at the random point `z`
+    pub polys_at_z: Vec<Vec<Fr<G>>>,
+    /// the (chunked) evaluations of the quotients at the random point `z`
+    pub quotients_at_z: Vec<Vec<Fr<G>>>,
+    /// the opening of the polynomials and of the quotients at `z`
+    pub proof: OpeningProof<G>,
+}
+
+/// Absorbs the point and the claimed evaluations, then the quotient commitments,
+/// and squeezes the evaluation point `z`, the polyscale and the evalscale.
+fn absorb_and_squeeze<G, EFqSponge>(
+    sponge: &mut EFqSponge,
+    point: &ExtensionPoint<Fr<G>>,
+    evals: &[(Fr<G>, Fr<G>)],
+    quotients: &[PolyComm<G>],
+) -> (Fr<G>, Fr<G>, Fr<G>)
+where
+    G: CommitmentCurve,
+    EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+{
+    sponge.absorb_fr(&[point.c0, point.c1]);
+    for (e0, e1) in evals {
+        sponge.absorb_fr(&[*e0, *e1]);
+    }
+    for quotient in quotients {
+        sponge.absorb_g(&quotient.unshifted);
+    }
+    let z = sponge.challenge();
+    let polyscale = sponge.challenge();
+    let evalscale = sponge.challenge();
+    (z, polyscale, evalscale)
+}
+
+impl<G: CommitmentCurve> SRS<G> {
+    /// Opens the commitments to `plnms` (given with their commitment randomness) at the extension point `point`.
+    /// Degree bounds are not supported.
+    pub fn open_extension<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        plnms: &[(&DensePolynomial<Fr<G>>, PolyComm<Fr<G>>)],
+        point: &ExtensionPoint<Fr<G>>,
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> ExtensionOpening<G>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+        G::BaseField: PrimeField,
+    {
+        let minimal = DenseOrSparsePolynomial::from(point.minimal_polynomial());
+        let evals: Vec<_> = plnms.iter().map(|(p, _)| point.evaluate(p)).collect();
+
+        let quotients: Vec<_> = plnms
+            .iter()
+            .map(|(p, _)| {
+                let (q, _) = DenseOrSparsePolynomial::from(*p)
+                    .divide_with_q_and_r(&minimal)
+                    .expect("the minimal polynomial is not zero");
+                let (comm, blinders) = self.commit(&q, None, rng);
+                (q, comm, blinders)
+            })
+            .collect();
+        let quotient_comms: Vec<_> = quotients.iter().map(|(_, comm, _)| comm.clone()).collect();
+
+        let (z, polyscale, evalscale) =
+            absorb_and_squeeze(&mut sponge, point, &evals, &quotient_comms);
+
+        let n = self.g.len();
+        let polys_at_z = plnms.iter().map(|(p, _)| p.eval(z, n)).collect();
+        let quotients_at_z = quotients.iter().map(|(q, _, _)| q.eval(z, n)).collect();
+
+        let mut batch: Vec<_> = plnms
+            .iter()
+            .map(|(p, blinders)| (*p, None, blinders.clone()))
+            .collect();
+        batch.extend(
+            quotients
+                .iter()
+                .map(|(q, _, blinders)| (q, None, blinders.clone())),
+        );
+        let proof = self.open(group_map, &batch, &[z], polyscale, evalscale, sponge, rng);
+
+        ExtensionOpening {
+            evals,
+            quotients: quotient_comms,
+            polys_at_z,
+            quotients_at_z,
+            proof,
+        }
+    }
+
+    /// Verifies an opening of the commitments `comms` at the extension point `point`,
+    /// produced by [SRS::open_extension] with a sponge in the same state as `sponge`.
+    ///
+    /// On top of the regular opening at `z`, the verifier checks that `p(z) = q(z) m(z) + r(z)`
+    /// for each polynomial `p`, where `m` is the minimal polynomial of the point and `r` is the remainder
+    /// derived from the claimed evaluation.
+    pub fn verify_extension<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        comms: &[PolyComm<G>],
+        point: &ExtensionPoint<Fr<G>>,
+        mut sponge: EFqSponge,
+        opening: &ExtensionOpening<G>,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+        G::BaseField: PrimeField,
+    {
+        let len = comms.len();
+        if [
+            opening.evals.len(),
+            opening.quotients.len(),
+            opening.polys_at_z.len(),
+            opening.quotients_at_z.len(),
+        ]
+        .iter()
+        .any(|l| *l != len)
+        {
+            return false;
+        }
+
+        let (z, polyscale, evalscale) =
+            absorb_and_squeeze(&mut sponge, point, &opening.evals, &opening.quotients);
+
+        // the chunks are recombined with powers of z^n
+        let z_n = z.pow(&[self.g.len() as u64]);
+        let recombine = |chunks: &[Fr<G>]| {
+            chunks
+                .iter()
+                .rev()
+                .fold(Fr::<G>::zero(), |acc, chunk| acc * z_n + chunk)
+        };
+        let m_z = point.minimal_polynomial().evaluate(&z);
+        let divides = opening
+            .evals
+            .iter()
+            .zip(&opening.polys_at_z)
+            .zip(&opening.quotients_at_z)
+            .all(|((evals, p_z), q_z)| {
+                recombine(p_z) == recombine(q_z) * m_z + point.remainder(*evals, z)
+            });
+        if !divides {
+            return false;
+        }
+
+        let mut polys: Vec<_> = comms
+            .iter()
+            .zip(&opening.polys_at_z)
+            .map(|(comm, evals)| (comm, vec![evals], None))
+            .collect();
+        polys.extend(
+            opening
+                .quotients
+                .iter()
+                .zip(&opening.quotients_at_z)
+                .map(|(comm, evals)| (comm, vec![evals], None)),
+        );
+        self.verify(
+            group_map,
+            &mut vec![(sponge, vec![z], polyscale, evalscale, polys, &opening.proof)],
+            rng,
+        )
+    }
+}
@@ -1,7 +1,14 @@
+pub mod backend;
 pub mod chunked;
 mod combine;
 pub mod commitment;
+pub mod equality;
 pub mod evaluation_proof;
+pub mod extension;
+pub mod msm;
+pub mod opening;
+pub mod provider;
 pub mod srs;
+pub mod vector;
 
 pub use commitment::PolyComm;
@@ -0,0 +1,308 @@
+//! This module implements the multi-scalar multiplications of the commitments,
+//! using the GLV method on the curves that have an efficient endomorphism.
+//!
+//! On such a curve, the map $\phi(x, y) = (\beta x, y)$ is the multiplication by a scalar $\lambda$,
+//! and every scalar $k$ can be written $k = k_1 + \lambda k_2$ with $k_1$ and $k_2$ of half the size of $k$
+//! (see [Glv::decompose]).
+//! Multiplying $n$ points by full-size scalars then amounts to multiplying
+//! the $2n$ points $P_i, \phi(P_i)$ by half-size scalars,
+//! which halves the number of windows of Pippenger's algorithm for the price of doubling the points of each window.
+//!
+//! Curves opt in by setting [GlvParameters::GLV], the other curves keep using the multi-scalar multiplication of arkworks.
+
+use ark_ec::{
+    models::short_weierstrass_jacobian::{GroupAffine as SWJAffine, GroupProjective},
+    msm::VariableBaseMSM,
+    AffineCurve, ModelParameters, ProjectiveCurve, SWModelParameters,
+};
+use ark_ff::{field_new, BigInteger, PrimeField, Zero};
+use mina_curves::pasta::{pallas::PallasParameters, vesta::VestaParameters, Fp, Fq};
+use o1_utils::metrics::histogram;
+use rayon::prelude::*;
+
+/// The histogram of the number of points of the multi-scalar multiplications
+/// (see [o1_utils::metrics]).
+pub const MSM_SIZE: &str = "commitment_msm_size";
+
+type BigInt<P> = <<P as ModelParameters>::ScalarField as PrimeField>::BigInt;
+
+/// The parameters of the GLV decomposition of the scalars of a curve.
+pub struct Glv<P: ModelParameters> {
+    /// the cube root of unity of the base field defining the endomorphism $\phi(x, y) = (\beta x, y)$
+    pub beta: P::BaseField,
+    /// the scalar such that $\phi(P) = \lambda P$
+    pub lambda: P::ScalarField,
+    /// a short basis $(a_1, b_1), (a_2, b_2)$ of the lattice of the $(a, b)$ such that $a + \lambda b = 0$
+    pub a1: P::ScalarField,
+    pub b1: P::ScalarField,
+    pub a2: P::ScalarField,
+    pub b2: P::ScalarField,
+    /// $2^{256} b_2 / r$ and $-2^{256} b_1 / r$ rounded to the nearest integer, where $r$ is the order of the curve,
+    /// in little-endian 64-bit limbs (both are positive)
+    pub g1: [u64; 3],
+    pub g2: [u64; 3],
+}
+
+/// A curve whose multi-scalar multiplications can use the GLV method.
+pub trait GlvParameters: SWModelParameters {
+    /// The GLV parameters of the curve, or [None] if it has no efficient endomorphism.
+    const GLV: Option<Glv<Self>> = None;
+}
+
+/// Returns $\lfloor k g / 2^{256} \rfloor$, for a 256-bit `k`.
+fn mul_shift<F: PrimeField>(k: &[u64], g: &[u64; 3]) -> F {
+    debug_assert_eq!(k.len(), 4);
+    let mut product = [0u64; 7];
+    for (i, k) in k.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, g) in g.iter().enumerate() {
+            let t = (*k as u128) * (*g as u128) + product[i + j] as u128 + carry;
+            product[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        product[i + g.len()] = carry as u64;
+    }
+    debug_assert_eq!(product[6], 0);
+    F::from(((product[5] as u128) << 64) | product[4] as u128)
+}
+
+impl<P: SWModelParameters> Glv<P> {
+    /// Returns $k_1$ and $k_2$ such that $k = k_1 + \lambda k_2$, as their signs (true if negative) and absolute values.
+    /// Both are smaller than the square root of the order of the curve, up to a small factor.
+    pub fn decompose(&self, k: P::ScalarField) -> [(bool, BigInt<P>); 2] {
+        let repr = k.into_repr();
+        let c1: P::ScalarField = mul_shift(repr.as_ref(), &self.g1);
+        let c2: P::ScalarField = mul_shift(repr.as_ref(), &self.g2);
+        let k2 = -(c1 * self.b1 + c2 * self.b2);
+        let k1 = k - self.lambda * k2;
+
+        let half = P::ScalarField::modulus_minus_one_div_two();
+        [k1, k2].map(|x| {
+            let repr = x.into_repr();
+            if repr > half {
+                (true, (-x).into_repr())
+            } else {
+                (false, repr)
+            }
+        })
+    }
+
+    /// Returns $\phi(p)$.
+    pub fn endo(&self, p: &SWJAffine<P>) -> SWJAffine<P> {
+        SWJAffine::new(p.x * self.beta, p.y, p.infinity)
+    }
+}
+
+/// Returns the window size of arkworks, which grows with the logarithm of the number of points.
+fn window_size(size: usize) -> usize {
+    if size < 32 {
+        3
+    } else {
+        let log2 = (usize::BITS - (size - 1).leading_zeros()) as usize;
+        log2 * 69 / 100 + 2
+    }
+}
+
+/// Returns the `c` bits of `limbs` starting at bit `start`.
+fn digit(limbs: &[u64], start: usize, c: usize) -> usize {
+    let limb = start / 64;
+    let shift = start % 64;
+    if limb >= limbs.len() {
+        return 0;
+    }
+    let mut bits = limbs[limb] >> shift;
+    if shift + c > 64 && limb + 1 < limbs.len() {
+        bits |= limbs[limb + 1] << (64 - shift);
+    }
+    (bits & ((1 << c) - 1)) as usize
+}
+
+/// Pippenger's algorithm, with as many windows as the largest scalar needs
+/// (the one of arkworks always goes through all the bits of the scalar field).
+fn pippenger<G: AffineCurve>(
+    bases: &[G],
+    scalars: &[<G::ScalarField as PrimeField>::BigInt],
+) -> G::Projective {
+    let size = std::cmp::min(bases.len(), scalars.len());
+    let num_bits = scalars[..size]
+        .iter()
+        .map(|s| s.num_bits() as usize)
+        .max()
+        .unwrap_or(0);
+    let c = window_size(size);
+    let zero = G::Projective::zero();
+
+    let window_starts: Vec<_> = (0..num_bits).step_by(c).collect();
+    let window_sums: Vec<_> = window_starts
+        .into_par_iter()
+        .map(|start| {
+            let mut buckets = vec![zero; (1 << c) - 1];
+            for (base, scalar) in bases.iter().zip(scalars) {
+                let d = digit(scalar.as_ref(), start, c);
+                if d != 0 {
+                    buckets[d - 1].add_assign_mixed(base);
+                }
+            }
+
+            // sum_d d * buckets[d - 1], as a sum of running sums
+            let mut res = zero;
+            let mut running_sum = zero;
+            for bucket in buckets.into_iter().rev() {
+                running_sum += &bucket;
+                res += &running_sum;
+            }
+            res
+        })
+        .collect();
+
+    window_sums.iter().rev().fold(zero, |mut total, sum| {
+        for _ in 0..c {
+            total.double_in_place();
+        }
+        total + sum
+    })
+}
+
+/// Returns $\sum_i s_i b_i$ for the `bases` $b_i$ and the `scalars` $s_i$,
+/// ignoring the elements of the longer slice that have no counterpart in the other.
+pub fn msm<P: GlvParameters>(
+    bases: &[SWJAffine<P>],
+    scalars: &[P::ScalarField],
+) -> GroupProjective<P> {
+    histogram(
+        MSM_SIZE,
+        &[],
+        std::cmp::min(bases.len(), scalars.len()) as f64,
+    );
+    if let Some(backend) = crate::backend::msm_backend::<SWJAffine<P>>() {
+        return backend.msm(bases, scalars);
+    }
+    match &P::GLV {
+        None => {
+            let scalars: Vec<_> = scalars.iter().map(|s| s.into_repr()).collect();
+            VariableBaseMSM::multi_scalar_mul(bases, &scalars)
+        }
+        Some(glv) => {
+            let (bases, scalars): (Vec<_>, Vec<_>) = bases
+                .par_iter()
+                .zip(scalars)
+                .flat_map_iter(|(base, scalar)| {
+                    let [(neg1, k1), (neg2, k2)] = glv.decompose(*scalar);
+                    let endo = glv.endo(base);
+                    [
+                        (if neg1 { -*base } else { *base }, k1),
+                        (if neg2 { -endo } else { endo }, k2),
+                    ]
+                })
+                .unzip();
+            pippenger(&bases, &scalars)
+        }
+    }
+}
+
+impl GlvParameters for PallasParameters {
+    const GLV: Option<Glv<Self>> = Some(Glv {
+        beta: field_new!(
+            Fp,
+            "20444556541222657078399132219657928148671392403212669005631716460534733845831"
+        ),
+        lambda: field_new!(
+            Fq,
+            "26005156700822196841419187675678338661165322343552424574062261873906994770353"
+        ),
+        a1: field_new!(Fq, "98231058071100081932162823354453065728"),
+        b1: field_new!(
+            Fq,
+            "28948022309329048855892746252171976963264825423870460634022513940995514564608"
+        ),
+        a2: field_new!(Fq, "196462116142286827589391630752301449217"),
+        b2: field_new!(Fq, "98231058071100081932162823354453065728"),
+        g1: [0x32c49e4bffffffff, 0x279a745902a2654e, 0x1],
+        g2: [0xff2b871c00000003, 0x279a745903c12455, 0x1],
+    });
+}
+
+impl GlvParameters for VestaParameters {
+    const GLV: Option<Glv<Self>> = Some(Glv {
+        beta: field_new!(
+            Fq,
+            "2942865608506852014473558576493638302197734138389222805617480874486368177743"
+        ),
+        lambda: field_new!(
+            Fp,
+            "8503465768106391777493614032514048814691664078728891710322960303815233784505"
+        ),
+        a1: field_new!(Fp, "98231058071186745657228807397848383488"),
+        b1: field_new!(
+            Fp,
+            "28948022309329048855892746252171976963264825423870460634022513940995514564608"
+        ),
+        a2: field_new!(Fp, "98231058071100081932162823354453065729"),
+        b2: field_new!(Fp, "196462116142286827589391630752301449217"),
+        g1: [0x31f0256800000003, 0x4f34e8b2066389a4, 0x2],
+        g2: [0x32c49e4c00000003, 0x279a745902a2654e, 0x1],
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::srs::endos;
+    use ark_ff::UniformRand;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn test_glv<P: GlvParameters>()
+    where
+        P::BaseField: PrimeField,
+    {
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let glv = P::GLV.as_ref().unwrap();
+
+        // the endomorphism is the one of the SRS
+        let (endo_q, endo_r) = endos::<SWJAffine<P>>();
+        assert_eq!(glv.beta, endo_q);
+        assert_eq!(glv.lambda, endo_r);
+
+        let mut scalars: Vec<_> = (0..100).map(|_| P::ScalarField::rand(rng)).collect();
+        scalars.extend([
+            P::ScalarField::zero(),
+            -P::ScalarField::from(1u64),
+            glv.lambda,
+        ]);
+        for k in &scalars {
+            let [(neg1, k1), (neg2, k2)] = glv.decompose(*k);
+            assert!(k1.num_bits() <= 128 && k2.num_bits() <= 128);
+            let sign = |neg, x| {
+                let x = P::ScalarField::from_repr(x).unwrap();
+                if neg {
+                    -x
+                } else {
+                    x
+                }
+            };
+            assert_eq!(sign(neg1, k1) + glv.lambda * sign(neg2, k2), *k);
+        }
+
+        let g = SWJAffine::<P>::prime_subgroup_generator();
+        let mut bases: Vec<_> = (0..scalars.len())
+            .map(|_| g.mul(P::ScalarField::rand(rng)).into_affine())
+            .collect();
+        bases[0] = SWJAffine::<P>::zero();
+        let reprs: Vec<_> = scalars.iter().map(|s| s.into_repr()).collect();
+        assert_eq!(
+            msm(&bases, &scalars),
+            VariableBaseMSM::multi_scalar_mul(&bases, &reprs)
+        );
+        assert_eq!(msm::<P>(&[], &[]), GroupProjective::zero());
+    }
+
+    #[test]
+    fn test_glv_pallas() {
+        test_glv::<PallasParameters>();
+    }
+
+    #[test]
+    fn test_glv_vesta() {
+        test_glv::<VestaParameters>();
+    }
+}
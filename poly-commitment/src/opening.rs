@@ -0,0 +1,430 @@
+//! This module implements openings of single polynomial commitments, independently of any proof system.
+//!
+//! [SRS::open] and [SRS::verify] open several polynomials at several points at once,
+//! and expect the caller to have bound the commitments and the evaluation points to its sponge beforehand
+//! (in kimchi, this is done by the rest of the transcript).
+//! The functions of this module open a single commitment at a single point,
+//! and absorb the commitment, the point and the evaluation themselves,
+//! so that the commitment scheme can be used on its own (for example as a vector commitment).
+//! [SRS::open_multi] similarly opens several commitments at an arbitrary set of points with a single proof,
+//! deriving the factors combining the polynomials and the points from the sponge.
+//!
+//! The committed polynomials must fit in the SRS (they must have fewer coefficients than the size of the SRS),
+//! so that their commitments consist of a single group element.
+
+use crate::commitment::{CommitmentCurve, PolyComm};
+use crate::evaluation_proof::OpeningProof;
+use crate::srs::SRS;
+use ark_ec::AffineCurve;
+use ark_ff::{One, PrimeField};
+use ark_poly::univariate::DensePolynomial;
+use oracle::FqSponge;
+use rand_core::{CryptoRng, RngCore};
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// A claim that the polynomial committed to in `commitment` evaluates to `eval` at `point`.
+#[derive(Clone, Debug)]
+pub struct Opening<'a, G: AffineCurve> {
+    pub commitment: &'a PolyComm<G>,
+    pub point: Fr<G>,
+    pub eval: Fr<G>,
+}
+
+/// A claim that the polynomials committed to in `commitments` evaluate to `evals` at `points`,
+/// where `evals[i][j]` is the evaluation of the `i`-th polynomial at the `j`-th point.
+#[derive(Clone, Debug)]
+pub struct MultiOpening<'a, G: AffineCurve> {
+    pub commitments: Vec<&'a PolyComm<G>>,
+    pub points: Vec<Fr<G>>,
+    pub evals: Vec<Vec<Fr<G>>>,
+}
+
+/// Absorbs the claim in `sponge`, and returns the factors combining the polynomials and the points.
+/// Returns `None` if the claim is malformed, or if a commitment is not made of a single group element.
+fn absorb_multi_opening<G, EFqSponge>(
+    sponge: &mut EFqSponge,
+    opening: &MultiOpening<G>,
+) -> Option<(Fr<G>, Fr<G>)>
+where
+    G: CommitmentCurve,
+    EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+{
+    if opening.evals.len() != opening.commitments.len()
+        || opening
+            .evals
+            .iter()
+            .any(|evals| evals.len() != opening.points.len())
+    {
+        return None;
+    }
+    for comm in &opening.commitments {
+        match (&comm.unshifted[..], comm.shifted) {
+            ([comm], None) => sponge.absorb_g(&[*comm]),
+            _ => return None,
+        }
+    }
+    sponge.absorb_fr(&opening.points);
+    for evals in &opening.evals {
+        sponge.absorb_fr(evals);
+    }
+    let polyscale = sponge.challenge();
+    let evalscale = sponge.challenge();
+    Some((polyscale, evalscale))
+}
+
+/// Absorbs the claim in `sponge`, so that the proof cannot be reused for another claim.
+/// Returns `None` if the commitment is not made of a single group element.
+fn absorb_opening<G, EFqSponge>(sponge: &mut EFqSponge, opening: &Opening<G>) -> Option<()>
+where
+    G: CommitmentCurve,
+    EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+{
+    match (
+        &opening.commitment.unshifted[..],
+        opening.commitment.shifted,
+    ) {
+        ([comm], None) => {
+            sponge.absorb_g(&[*comm]);
+            sponge.absorb_fr(&[opening.point, opening.eval]);
+            Some(())
+        }
+        _ => None,
+    }
+}
+
+impl<G: CommitmentCurve> SRS<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Proves that the polynomial `poly`, committed to in `commitment` with the blinders `blinders`
+    /// (as returned by [SRS::commit]), evaluates to `poly(point)` at `point`.
+    /// The proof is checked by [SRS::verify_opening], with a sponge in the same state as `sponge`.
+    ///
+    /// Panics if the polynomial does not fit in the SRS.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_at<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        poly: &DensePolynomial<Fr<G>>,
+        commitment: &PolyComm<G>,
+        blinders: PolyComm<Fr<G>>,
+        point: Fr<G>,
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> OpeningProof<G>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        use ark_poly::Polynomial;
+
+        let opening = Opening {
+            commitment,
+            point,
+            eval: poly.evaluate(&point),
+        };
+        absorb_opening(&mut sponge, &opening).expect("the polynomial does not fit in the SRS");
+
+        // with a single polynomial and a single point, the scaling factors are not used
+        self.open(
+            group_map,
+            &[(poly, None, blinders)],
+            &[point],
+            Fr::<G>::one(),
+            Fr::<G>::one(),
+            sponge,
+            rng,
+        )
+    }
+
+    /// Verifies a proof created by [SRS::open_at] that the polynomial committed to in `commitment`
+    /// evaluates to `eval` at `point`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_opening<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        commitment: &PolyComm<G>,
+        point: Fr<G>,
+        eval: Fr<G>,
+        proof: &OpeningProof<G>,
+        sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        let opening = Opening {
+            commitment,
+            point,
+            eval,
+        };
+        self.verify_batch_openings(group_map, &[(opening, proof)], sponge, rng)
+    }
+
+    /// Verifies several proofs created by [SRS::open_at] at once,
+    /// which is cheaper than verifying them one by one with [SRS::verify_opening].
+    /// All the proofs must have been created with a sponge in the same state as `sponge`.
+    pub fn verify_batch_openings<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        openings: &[(Opening<G>, &OpeningProof<G>)],
+        sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        let evals: Vec<_> = openings
+            .iter()
+            .map(|(opening, _)| vec![opening.eval])
+            .collect();
+
+        let mut batch = vec![];
+        for ((opening, proof), eval) in openings.iter().zip(&evals) {
+            let mut sponge = sponge.clone();
+            if absorb_opening(&mut sponge, opening).is_none() {
+                return false;
+            }
+            batch.push((
+                sponge,
+                vec![opening.point],
+                Fr::<G>::one(),
+                Fr::<G>::one(),
+                vec![(opening.commitment, vec![eval], None)],
+                *proof,
+            ));
+        }
+
+        self.verify(group_map, &mut batch, rng)
+    }
+
+    /// Proves with a single proof that the polynomials `polys`, given along with their commitments and blinders
+    /// (as returned by [SRS::commit]), evaluate at each of the `points` to what they evaluate to.
+    /// The proof is checked by [SRS::verify_multi_opening], with a sponge in the same state as `sponge`.
+    ///
+    /// Panics if a polynomial does not fit in the SRS.
+    #[allow(clippy::type_complexity)]
+    pub fn open_multi<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        polys: &[(&DensePolynomial<Fr<G>>, &PolyComm<G>, PolyComm<Fr<G>>)],
+        points: &[Fr<G>],
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> OpeningProof<G>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        use ark_poly::Polynomial;
+
+        let opening = MultiOpening {
+            commitments: polys.iter().map(|(_, comm, _)| *comm).collect(),
+            points: points.to_vec(),
+            evals: polys
+                .iter()
+                .map(|(poly, _, _)| points.iter().map(|x| poly.evaluate(x)).collect())
+                .collect(),
+        };
+        let (polyscale, evalscale) = absorb_multi_opening(&mut sponge, &opening)
+            .expect("a polynomial does not fit in the SRS");
+
+        let polys: Vec<_> = polys
+            .iter()
+            .map(|(poly, _, blinders)| (*poly, None, blinders.clone()))
+            .collect();
+        self.open(group_map, &polys, points, polyscale, evalscale, sponge, rng)
+    }
+
+    /// Verifies a proof created by [SRS::open_multi] of the claim `opening`.
+    pub fn verify_multi_opening<EFqSponge, RNG>(
+        &self,
+        group_map: &G::Map,
+        opening: &MultiOpening<G>,
+        proof: &OpeningProof<G>,
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        let (polyscale, evalscale) = match absorb_multi_opening(&mut sponge, opening) {
+            Some(scales) => scales,
+            None => return false,
+        };
+
+        // the evaluations of the chunks of each polynomial (a single one) at each point
+        let evals: Vec<Vec<_>> = opening
+            .evals
+            .iter()
+            .map(|evals| evals.iter().map(|e| vec![*e]).collect())
+            .collect();
+        let polys = opening
+            .commitments
+            .iter()
+            .zip(&evals)
+            .map(|(comm, evals)| (*comm, evals.iter().collect(), None))
+            .collect();
+
+        let mut batch = vec![(
+            sponge,
+            opening.points.clone(),
+            polyscale,
+            evalscale,
+            polys,
+            proof,
+        )];
+        self.verify(group_map, &mut batch, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_poly::{Polynomial, UVPolynomial};
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{fp::Fp, vesta::Affine as VestaG};
+    use oracle::poseidon::PlonkSpongeConstantsKimchi as SC;
+    use oracle::{pasta::fq_kimchi::params as spongeFqParams, sponge::DefaultFqSponge};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_verify_openings() {
+        let srs = SRS::<VestaG>::create(16);
+        let group_map = <VestaG as CommitmentCurve>::Map::setup();
+        let sponge = DefaultFqSponge::<_, SC>::new(spongeFqParams());
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+
+        // a vector committed to as the coefficients of a polynomial, opened at two points
+        let polys: Vec<_> = (0..2)
+            .map(|_| DensePolynomial::<Fp>::rand(15, rng))
+            .collect();
+        let commitments: Vec<_> = polys.iter().map(|p| srs.commit(p, None, rng)).collect();
+        let points = [Fp::rand(rng), Fp::rand(rng)];
+        let proofs: Vec<_> = polys
+            .iter()
+            .zip(&commitments)
+            .zip(points)
+            .map(|((poly, (comm, blinders)), point)| {
+                srs.open_at(
+                    &group_map,
+                    poly,
+                    comm,
+                    blinders.clone(),
+                    point,
+                    sponge.clone(),
+                    rng,
+                )
+            })
+            .collect();
+
+        let eval = polys[0].evaluate(&points[0]);
+        let comm = &commitments[0].0;
+        assert!(srs.verify_opening(
+            &group_map,
+            comm,
+            points[0],
+            eval,
+            &proofs[0],
+            sponge.clone(),
+            rng
+        ));
+
+        // the proof only holds for the claimed evaluation, point and commitment
+        let wrong = eval + Fp::one();
+        assert!(!srs.verify_opening(
+            &group_map,
+            comm,
+            points[0],
+            wrong,
+            &proofs[0],
+            sponge.clone(),
+            rng
+        ));
+        assert!(!srs.verify_opening(
+            &group_map,
+            comm,
+            points[1],
+            eval,
+            &proofs[0],
+            sponge.clone(),
+            rng
+        ));
+        let other = &commitments[1].0;
+        assert!(!srs.verify_opening(
+            &group_map,
+            other,
+            points[0],
+            eval,
+            &proofs[0],
+            sponge.clone(),
+            rng
+        ));
+
+        // both openings at once
+        let openings: Vec<_> = (0..2)
+            .map(|i| {
+                let opening = Opening {
+                    commitment: &commitments[i].0,
+                    point: points[i],
+                    eval: polys[i].evaluate(&points[i]),
+                };
+                (opening, &proofs[i])
+            })
+            .collect();
+        assert!(srs.verify_batch_openings(&group_map, &openings, sponge.clone(), rng));
+
+        // a single wrong opening makes the batch fail
+        let mut openings = openings;
+        openings[1].0.eval = wrong;
+        assert!(!srs.verify_batch_openings(&group_map, &openings, sponge, rng));
+    }
+
+    #[test]
+    fn test_verify_multi_opening() {
+        let srs = SRS::<VestaG>::create(16);
+        let group_map = <VestaG as CommitmentCurve>::Map::setup();
+        let sponge = DefaultFqSponge::<_, SC>::new(spongeFqParams());
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+
+        // three polynomials opened at three points
+        let polys: Vec<_> = [15, 7, 0]
+            .iter()
+            .map(|&d| DensePolynomial::<Fp>::rand(d, rng))
+            .collect();
+        let commitments: Vec<_> = polys.iter().map(|p| srs.commit(p, None, rng)).collect();
+        let points: Vec<_> = (0..3).map(|_| Fp::rand(rng)).collect();
+
+        let to_open: Vec<_> = polys
+            .iter()
+            .zip(&commitments)
+            .map(|(poly, (comm, blinders))| (poly, comm, blinders.clone()))
+            .collect();
+        let proof = srs.open_multi(&group_map, &to_open, &points, sponge.clone(), rng);
+
+        let mut opening = MultiOpening {
+            commitments: commitments.iter().map(|(comm, _)| comm).collect(),
+            points: points.clone(),
+            evals: polys
+                .iter()
+                .map(|poly| points.iter().map(|x| poly.evaluate(x)).collect())
+                .collect(),
+        };
+        assert!(srs.verify_multi_opening(&group_map, &opening, &proof, sponge.clone(), rng));
+
+        // a wrong evaluation
+        opening.evals[1][2] += Fp::one();
+        assert!(!srs.verify_multi_opening(&group_map, &opening, &proof, sponge.clone(), rng));
+        opening.evals[1][2] -= Fp::one();
+
+        // a malformed claim
+        opening.points.pop();
+        assert!(!srs.verify_multi_opening(&group_map, &opening, &proof, sponge, rng));
+    }
+}
@@ -0,0 +1,293 @@
+//! This module abstracts the access to the points of an [SRS] behind the [SrsProvider] trait,
+//! so that very large SRS do not have to be fully resident in memory.
+//!
+//! An [SRS] is its own provider. A [StoredSrs] instead reads the points from a [KeyValueStore]
+//! in chunks, as they are needed: the store can be in memory ([MemoryStore]), on disk ([DirectoryStore]),
+//! or remote, by implementing [KeyValueStore] over the client of the remote store.
+//! [commit_streamed] then commits to a polynomial by streaming the points of the SRS chunk by chunk.
+
+use crate::{commitment::CommitmentCurve, srs::SRS, PolyComm};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::Zero;
+use ark_poly::univariate::DensePolynomial;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::{collections::HashMap, fs, io, ops::Range, path::PathBuf, sync::RwLock};
+
+/// Gives access to the points of an SRS.
+pub trait SrsProvider<G> {
+    /// The number of points `g` of the SRS.
+    fn size(&self) -> usize;
+
+    /// The blinding point `h` of the SRS.
+    fn h(&self) -> io::Result<G>;
+
+    /// The points `g[range]` of the SRS.
+    fn points(&self, range: Range<usize>) -> io::Result<Vec<G>>;
+}
+
+impl<G: CommitmentCurve> SrsProvider<G> for SRS<G> {
+    fn size(&self) -> usize {
+        self.g.len()
+    }
+
+    fn h(&self) -> io::Result<G> {
+        Ok(self.h)
+    }
+
+    fn points(&self, range: Range<usize>) -> io::Result<Vec<G>> {
+        self.g
+            .get(range.clone())
+            .map(<[G]>::to_vec)
+            .ok_or_else(|| out_of_bounds(range, self.g.len()))
+    }
+}
+
+fn out_of_bounds(range: Range<usize>, size: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("points {range:?} out of the SRS of size {size}"),
+    )
+}
+
+fn invalid_data(err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// A store of binary values indexed by strings.
+pub trait KeyValueStore {
+    /// Returns the value of `key`, if any.
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Sets the value of `key`.
+    fn put(&self, key: &str, value: &[u8]) -> io::Result<()>;
+}
+
+/// A [KeyValueStore] in memory.
+#[derive(Default)]
+pub struct MemoryStore(RwLock<HashMap<String, Vec<u8>>>);
+
+impl KeyValueStore for MemoryStore {
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.0.read().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> io::Result<()> {
+        self.0
+            .write()
+            .unwrap()
+            .insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+}
+
+/// A [KeyValueStore] on disk, storing each value in a file of a directory.
+/// The keys must be valid file names.
+pub struct DirectoryStore {
+    root: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Opens the store in the directory `root`, creating the directory if needed.
+    pub fn new(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+}
+
+impl KeyValueStore for DirectoryStore {
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.root.join(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> io::Result<()> {
+        fs::write(self.root.join(key), value)
+    }
+}
+
+/// The key of the size of the SRS and of its chunks.
+const META_KEY: &str = "srs-meta";
+/// The key of the point `h`.
+const H_KEY: &str = "srs-h";
+
+fn chunk_key(i: usize) -> String {
+    format!("srs-g-{i}")
+}
+
+/// An SRS stored in a [KeyValueStore], in chunks of `chunk_size` points.
+/// The points are read from the store each time they are requested, and are not cached.
+pub struct StoredSrs<S> {
+    store: S,
+    size: usize,
+    chunk_size: usize,
+}
+
+impl<S: KeyValueStore> StoredSrs<S> {
+    /// Writes the points of `srs` to `store`, in chunks of `chunk_size` points.
+    pub fn write<G: CommitmentCurve>(
+        store: S,
+        srs: &SRS<G>,
+        chunk_size: usize,
+    ) -> io::Result<Self> {
+        assert!(chunk_size > 0, "the chunks of an SRS cannot be empty");
+        for (i, chunk) in srs.g.chunks(chunk_size).enumerate() {
+            let mut bytes = vec![];
+            chunk.to_vec().serialize(&mut bytes).map_err(invalid_data)?;
+            store.put(&chunk_key(i), &bytes)?;
+        }
+        let mut bytes = vec![];
+        srs.h.serialize(&mut bytes).map_err(invalid_data)?;
+        store.put(H_KEY, &bytes)?;
+
+        let meta = [srs.g.len() as u64, chunk_size as u64];
+        let meta: Vec<u8> = meta.iter().flat_map(|x| x.to_le_bytes()).collect();
+        store.put(META_KEY, &meta)?;
+
+        Ok(Self {
+            store,
+            size: srs.g.len(),
+            chunk_size,
+        })
+    }
+
+    /// Opens an SRS previously written to `store` with [StoredSrs::write].
+    pub fn open(store: S) -> io::Result<Self> {
+        let meta = store
+            .get(META_KEY)?
+            .ok_or_else(|| invalid_data("the store does not contain an SRS"))?;
+        if meta.len() != 16 {
+            return Err(invalid_data("invalid SRS metadata"));
+        }
+        let read = |i: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&meta[8 * i..8 * (i + 1)]);
+            u64::from_le_bytes(bytes) as usize
+        };
+        Ok(Self {
+            size: read(0),
+            chunk_size: read(1),
+            store,
+        })
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.store
+            .get(key)?
+            .ok_or_else(|| invalid_data(format!("missing SRS entry {key}")))
+    }
+}
+
+impl<G: CommitmentCurve, S: KeyValueStore> SrsProvider<G> for StoredSrs<S> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn h(&self) -> io::Result<G> {
+        G::deserialize(&self.get(H_KEY)?[..]).map_err(invalid_data)
+    }
+
+    fn points(&self, range: Range<usize>) -> io::Result<Vec<G>> {
+        if range.start > range.end || range.end > self.size {
+            return Err(out_of_bounds(range, self.size));
+        }
+        let mut points = Vec::with_capacity(range.len());
+        if range.is_empty() {
+            return Ok(points);
+        }
+        for i in range.start / self.chunk_size..=(range.end - 1) / self.chunk_size {
+            let chunk: Vec<G> = CanonicalDeserialize::deserialize(&self.get(&chunk_key(i))?[..])
+                .map_err(invalid_data)?;
+            let offset = i * self.chunk_size;
+            let start = range.start.max(offset) - offset;
+            let end = range.end.min(offset + chunk.len()) - offset;
+            points.extend_from_slice(&chunk[start..end]);
+        }
+        Ok(points)
+    }
+}
+
+/// Commits to `plnm` without hiding and without degree bound, like [SRS::commit_non_hiding],
+/// reading at most `chunk_size` points of the SRS at a time from `provider`.
+pub fn commit_streamed<G: CommitmentCurve>(
+    provider: &impl SrsProvider<G>,
+    plnm: &DensePolynomial<G::ScalarField>,
+    chunk_size: usize,
+) -> io::Result<PolyComm<G>> {
+    assert!(chunk_size > 0, "cannot stream empty chunks of the SRS");
+    let n = provider.size();
+    if plnm.is_zero() {
+        return Ok(PolyComm {
+            unshifted: vec![],
+            shifted: None,
+        });
+    }
+
+    // each segment of n coefficients is committed to separately, as in commit_non_hiding
+    let unshifted = plnm
+        .coeffs
+        .chunks(n)
+        .map(|segment| {
+            let mut comm = G::Projective::zero();
+            for start in (0..segment.len()).step_by(chunk_size) {
+                let end = (start + chunk_size).min(segment.len());
+                let points = provider.points(start..end)?;
+                comm += G::msm(&points, &segment[start..end]);
+            }
+            Ok(comm.into_affine())
+        })
+        .collect::<io::Result<_>>()?;
+
+    Ok(PolyComm {
+        unshifted,
+        shifted: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_poly::UVPolynomial;
+    use mina_curves::pasta::{fp::Fp, vesta::Affine};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn check_provider(provider: &impl SrsProvider<Affine>, srs: &SRS<Affine>) {
+        assert_eq!(provider.size(), srs.g.len());
+        assert_eq!(provider.h().unwrap(), srs.h);
+        assert_eq!(provider.points(5..23).unwrap(), srs.g[5..23]);
+        assert_eq!(provider.points(8..8).unwrap(), vec![]);
+        assert!(provider.points(30..40).is_err());
+
+        // a polynomial of two segments
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        let coeffs: Vec<_> = (0..50).map(|_| Fp::rand(rng)).collect();
+        let plnm = DensePolynomial::from_coefficients_vec(coeffs);
+        let expected = srs.commit_non_hiding(&plnm, None);
+        let comm = commit_streamed(provider, &plnm, 7).unwrap();
+        assert_eq!(comm.unshifted, expected.unshifted);
+        assert!(comm.shifted.is_none());
+    }
+
+    #[test]
+    fn test_srs_providers() {
+        let srs = SRS::<Affine>::create(32);
+        check_provider(&srs, &srs);
+
+        let stored = StoredSrs::write(MemoryStore::default(), &srs, 10).unwrap();
+        check_provider(&stored, &srs);
+        let reopened = StoredSrs::open(stored.store).unwrap();
+        check_provider(&reopened, &srs);
+
+        let dir = std::env::temp_dir().join(format!("srs-provider-{}", std::process::id()));
+        StoredSrs::write(DirectoryStore::new(&dir).unwrap(), &srs, 10).unwrap();
+        let on_disk = StoredSrs::open(DirectoryStore::new(&dir).unwrap()).unwrap();
+        check_provider(&on_disk, &srs);
+        fs::remove_dir_all(dir).unwrap();
+
+        assert!(StoredSrs::open(MemoryStore::default()).is_err());
+    }
+}
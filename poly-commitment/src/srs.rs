@@ -1,8 +1,13 @@
 //! This module implements the Marlin structured reference string primitive
+//!
+//! The points of the SRS are hashed to the curve, so that it is not the output of a setup ceremony:
+//! the SRSs of ceremonies such as Powers of Tau or Aztec Ignition are powers of a secret in a pairing-friendly group,
+//! for KZG commitments, and cannot be used (or converted) for the commitments of this crate.
+//! An SRS obtained from a third party is instead checked to be the one of [SRS::create] (see [SRS::read]).
 
 use crate::commitment::CommitmentCurve;
 use ark_ec::{AffineCurve, ProjectiveCurve};
-use ark_ff::{BigInteger, PrimeField};
+use ark_ff::{BigInteger, PrimeField, Zero};
 use ark_poly::{EvaluationDomain, Radix2EvaluationDomain as D};
 use array_init::array_init;
 use blake2::{Blake2b512, Digest};
@@ -10,6 +15,7 @@ use groupmap::GroupMap;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use std::collections::HashMap;
+use std::io::{Read, Write};
 
 #[serde_as]
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -22,7 +28,10 @@ pub struct SRS<G: CommitmentCurve> {
     pub h: G,
 
     // TODO: the following field should be separated, as they are optimization values
-    /// Commitments to Lagrange bases, per domain size
+    /// Commitments to Lagrange bases, per domain size.
+    /// When the domain is larger than the SRS, the Lagrange polynomials are committed in chunks
+    /// (like [SRS::commit_non_hiding] does): the commitments to their first chunks come first,
+    /// then the commitments to their second chunks, and so on (see [SRS::lagrange_basis_chunks]).
     #[serde(skip)]
     pub lagrange_bases: HashMap<usize, Vec<G>>,
     /// Coefficient for the curve endomorphism
@@ -79,18 +88,17 @@ where
         self.g.len()
     }
 
+    /// Returns the commitments to the chunks of the Lagrange basis of the domain of size `n`, chunk by chunk:
+    /// a single chunk of `n` commitments if the domain is not larger than the SRS.
+    pub fn lagrange_basis_chunks(&self, n: usize) -> Option<std::slice::Chunks<'_, G>> {
+        self.lagrange_bases.get(&n).map(|basis| basis.chunks(n))
+    }
+
     /// Compute commitments to the lagrange basis corresponding to the given domain and
-    /// cache them in the SRS
+    /// cache them in the SRS.
+    /// The domain can be larger than the SRS, in which case the basis is committed in chunks.
     pub fn add_lagrange_basis(&mut self, domain: D<G::ScalarField>) {
         let n = domain.size();
-        if n > self.g.len() {
-            panic!(
-                "add_lagrange_basis: Domain size {} larger than SRS size {}",
-                n,
-                self.g.len()
-            );
-        }
-
         if self.lagrange_bases.contains_key(&n) {
             return;
         }
@@ -150,13 +158,24 @@ where
         // because the commitment to the polynomial x^i is just self.g[i], we can obtain
         // commitments to the normalized Lagrange polynomials by applying IFFT to the
         // vector self.g[0..n].
-        let mut lg: Vec<<G as AffineCurve>::Projective> =
-            self.g[0..n].iter().map(|g| g.into_projective()).collect();
-        domain.ifft_in_place(&mut lg);
-
-        <G as AffineCurve>::Projective::batch_normalization(lg.as_mut_slice());
-        self.lagrange_bases
-            .insert(n, lg.iter().map(|g| g.into_affine()).collect());
+        //
+        // When the domain is larger than the SRS, the chunk j of a polynomial gathers
+        // its coefficients from j * m to (j + 1) * m, for the size m of the SRS,
+        // and the commitment to x^i in that chunk is self.g[i - j * m] (or zero outside of the chunk):
+        // the same holds for the vector of these commitments.
+        let m = self.g.len();
+        let mut bases = Vec::with_capacity(n * ((n + m - 1) / m));
+        for start in (0..n).step_by(m) {
+            let mut lg = vec![<G as AffineCurve>::Projective::zero(); n];
+            for (l, g) in lg[start..].iter_mut().zip(&self.g) {
+                *l = g.into_projective();
+            }
+            domain.ifft_in_place(&mut lg);
+
+            <G as AffineCurve>::Projective::batch_normalization(lg.as_mut_slice());
+            bases.extend(lg.iter().map(|g| g.into_affine()));
+        }
+        self.lagrange_bases.insert(n, bases);
     }
 
     /// This function creates SRS instance for circuits with number of rows up to `depth`.
@@ -189,4 +208,36 @@ where
             endo_q,
         }
     }
+
+    /// Returns true if the SRS is the one created by [SRS::create] for its size.
+    ///
+    /// The points of the SRS are obtained by hashing to the curve, so that nobody knows discrete logarithm relations between them.
+    /// As there is no trapdoor to hide, the SRS does not need to be produced by a setup ceremony:
+    /// anyone can instead check that an SRS obtained from a third party (for example, read from a file) was honestly generated.
+    pub fn is_transparent(&self) -> bool {
+        let expected = Self::create(self.g.len());
+        self.g == expected.g && self.h == expected.h
+    }
+
+    /// Writes the points of the SRS, without its Lagrange bases.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), String> {
+        let mut serializer = rmp_serde::Serializer::new(writer);
+        self.serialize(&mut serializer).map_err(|e| e.to_string())
+    }
+
+    /// Reads an SRS written by [SRS::write], and fails if it is not [transparent](SRS::is_transparent).
+    /// Checking the SRS costs about as much as creating it.
+    pub fn read<R: Read>(reader: R) -> Result<Self, String> {
+        let mut srs: Self = rmp_serde::from_read(reader).map_err(|e| e.to_string())?;
+        if !srs.is_transparent() {
+            return Err(format!(
+                "the SRS of size {} is not the one hashed to the curve",
+                srs.g.len()
+            ));
+        }
+        let (endo_q, endo_r) = endos::<G>();
+        srs.endo_q = endo_q;
+        srs.endo_r = endo_r;
+        Ok(srs)
+    }
 }
@@ -0,0 +1,284 @@
+//! This module implements vector commitments on top of the polynomial commitment scheme.
+//!
+//! A vector of `n` values is committed to as the polynomial that takes these values over a domain of size `n`
+//! (rounded up to a power of two, the vector being padded with zeros),
+//! that is, as the polynomial whose coefficients in the Lagrange basis of the domain are the values.
+//! A position `i` of the vector is then opened by opening the polynomial at $\omega^i$,
+//! and several positions are opened with a single proof by opening the polynomial at several points.
+//!
+//! The commitments are hiding, the values that are not opened are not revealed.
+//!
+//! With a pairing-based scheme like KZG, the proofs of all the positions could be precomputed at once
+//! (with the technique of Feist and Khovratovich), since each of them is the commitment to a quotient polynomial.
+//! The opening proofs of the inner product argument used here are instead produced interactively
+//! (their challenges depend on the points and on the values being opened), so that they cannot be precomputed.
+//! Opening many positions is instead amortized by [CommittedVector::open_positions],
+//! whose single proof has the same size whatever the number of positions.
+
+use crate::commitment::{CommitmentCurve, PolyComm};
+use crate::evaluation_proof::OpeningProof;
+use crate::srs::SRS;
+use ark_ec::AffineCurve;
+use ark_ff::{One, PrimeField, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, EvaluationDomain, Evaluations, Radix2EvaluationDomain as D,
+};
+use oracle::FqSponge;
+use rand_core::{CryptoRng, RngCore};
+
+type Fr<G> = <G as AffineCurve>::ScalarField;
+type Fq<G> = <G as AffineCurve>::BaseField;
+
+/// The commitment to a vector, which is all the verifier needs to check the openings of its positions.
+#[derive(Clone, Debug)]
+pub struct VectorCommitment<G: CommitmentCurve> {
+    /// The commitment to the polynomial taking the values of the vector
+    pub commitment: PolyComm<G>,
+    /// The domain over which the polynomial takes the values of the vector
+    pub domain: D<Fr<G>>,
+}
+
+/// A committed vector, with what its owner needs to open its positions.
+#[derive(Clone, Debug)]
+pub struct CommittedVector<G: CommitmentCurve> {
+    /// The public commitment to the vector
+    pub commitment: VectorCommitment<G>,
+    /// The values of the vector, padded with zeros to the size of the domain
+    pub values: Vec<Fr<G>>,
+    poly: DensePolynomial<Fr<G>>,
+    blinders: PolyComm<Fr<G>>,
+}
+
+impl<G: CommitmentCurve> VectorCommitment<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Absorbs the commitment and the claimed values of the positions `openings` in `sponge`,
+    /// and returns the points to open the polynomial at.
+    fn absorb_positions<EFqSponge>(
+        &self,
+        sponge: &mut EFqSponge,
+        openings: &[(usize, Fr<G>)],
+    ) -> Vec<Fr<G>>
+    where
+        EFqSponge: FqSponge<Fq<G>, G, Fr<G>>,
+    {
+        sponge.absorb_g(&self.commitment.unshifted);
+        openings
+            .iter()
+            .map(|&(i, value)| {
+                let point = self.domain.element(i);
+                sponge.absorb_fr(&[point, value]);
+                point
+            })
+            .collect()
+    }
+
+    /// Verifies a proof created by [CommittedVector::open] that the position `i` of the vector holds `value`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify<EFqSponge, RNG>(
+        &self,
+        srs: &SRS<G>,
+        group_map: &G::Map,
+        i: usize,
+        value: Fr<G>,
+        proof: &OpeningProof<G>,
+        sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        self.verify_positions(srs, group_map, &[(i, value)], proof, sponge, rng)
+    }
+
+    /// Verifies a proof created by [CommittedVector::open_positions] that each position `i` of `openings`
+    /// holds the value given along with it.
+    pub fn verify_positions<EFqSponge, RNG>(
+        &self,
+        srs: &SRS<G>,
+        group_map: &G::Map,
+        openings: &[(usize, Fr<G>)],
+        proof: &OpeningProof<G>,
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> bool
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        if self.commitment.unshifted.len() != 1
+            || self.commitment.shifted.is_some()
+            || openings.iter().any(|&(i, _)| i >= self.domain.size())
+        {
+            return false;
+        }
+
+        let points = self.absorb_positions(&mut sponge, openings);
+        let evalscale = sponge.challenge();
+        let values: Vec<_> = openings.iter().map(|&(_, value)| vec![value]).collect();
+
+        let mut batch = vec![(
+            sponge,
+            points,
+            Fr::<G>::one(),
+            evalscale,
+            vec![(&self.commitment, values.iter().collect(), None)],
+            proof,
+        )];
+        srs.verify(group_map, &mut batch, rng)
+    }
+}
+
+impl<G: CommitmentCurve> CommittedVector<G>
+where
+    G::BaseField: PrimeField,
+{
+    /// Commits to `values`.
+    /// Returns `None` if the vector is empty or if its padded length is larger than the SRS.
+    pub fn commit(
+        srs: &SRS<G>,
+        mut values: Vec<Fr<G>>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Option<Self> {
+        let domain = D::<Fr<G>>::new(values.len())?;
+        if values.is_empty() || domain.size() > srs.g.len() {
+            return None;
+        }
+        values.resize(domain.size(), Fr::<G>::zero());
+
+        let poly = Evaluations::from_vec_and_domain(values.clone(), domain).interpolate();
+        let (commitment, blinders) = srs.commit(&poly, None, rng);
+        Some(CommittedVector {
+            commitment: VectorCommitment { commitment, domain },
+            values,
+            poly,
+            blinders,
+        })
+    }
+
+    /// Proves that the position `i` of the vector holds `self.values[i]`,
+    /// which is checked by [VectorCommitment::verify] with a sponge in the same state as `sponge`.
+    pub fn open<EFqSponge, RNG>(
+        &self,
+        srs: &SRS<G>,
+        group_map: &G::Map,
+        i: usize,
+        sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> OpeningProof<G>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        self.open_positions(srs, group_map, &[i], sponge, rng)
+    }
+
+    /// Proves with a single proof that each position of `positions` holds its value,
+    /// which is checked by [VectorCommitment::verify_positions] with a sponge in the same state as `sponge`.
+    ///
+    /// Panics if a position is out of the vector.
+    pub fn open_positions<EFqSponge, RNG>(
+        &self,
+        srs: &SRS<G>,
+        group_map: &G::Map,
+        positions: &[usize],
+        mut sponge: EFqSponge,
+        rng: &mut RNG,
+    ) -> OpeningProof<G>
+    where
+        EFqSponge: Clone + FqSponge<Fq<G>, G, Fr<G>>,
+        RNG: RngCore + CryptoRng,
+    {
+        let openings: Vec<_> = positions.iter().map(|&i| (i, self.values[i])).collect();
+        let points = self.commitment.absorb_positions(&mut sponge, &openings);
+        let evalscale = sponge.challenge();
+
+        // with a single polynomial (of a single chunk), the polynomial scaling factor is not used
+        srs.open(
+            group_map,
+            &[(&self.poly, None, self.blinders.clone())],
+            &points,
+            Fr::<G>::one(),
+            evalscale,
+            sponge,
+            rng,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use groupmap::GroupMap;
+    use mina_curves::pasta::{fp::Fp, vesta::Affine as VestaG};
+    use oracle::poseidon::PlonkSpongeConstantsKimchi as SC;
+    use oracle::{pasta::fq_kimchi::params as spongeFqParams, sponge::DefaultFqSponge};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_vector_commitment() {
+        let srs = SRS::<VestaG>::create(16);
+        let group_map = <VestaG as CommitmentCurve>::Map::setup();
+        let sponge = DefaultFqSponge::<_, SC>::new(spongeFqParams());
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+
+        // 11 values, padded to 16
+        let values: Vec<_> = (0..11).map(|_| Fp::rand(rng)).collect();
+        let vector = CommittedVector::commit(&srs, values.clone(), rng).unwrap();
+        let commitment = &vector.commitment;
+        assert_eq!(vector.values.len(), 16);
+
+        // a single position
+        let proof = vector.open(&srs, &group_map, 3, sponge.clone(), rng);
+        assert!(commitment.verify(&srs, &group_map, 3, values[3], &proof, sponge.clone(), rng));
+        assert!(!commitment.verify(&srs, &group_map, 3, values[4], &proof, sponge.clone(), rng));
+        assert!(!commitment.verify(&srs, &group_map, 4, values[3], &proof, sponge.clone(), rng));
+
+        // several positions, including a padding one
+        let positions = [0, 5, 10, 15];
+        let proof = vector.open_positions(&srs, &group_map, &positions, sponge.clone(), rng);
+        let mut openings: Vec<_> = positions.iter().map(|&i| (i, vector.values[i])).collect();
+        assert_eq!(openings[3].1, Fp::zero());
+        assert!(commitment.verify_positions(
+            &srs,
+            &group_map,
+            &openings,
+            &proof,
+            sponge.clone(),
+            rng
+        ));
+
+        // the proof does not hold for another subset or for other values
+        assert!(!commitment.verify_positions(
+            &srs,
+            &group_map,
+            &openings[..3],
+            &proof,
+            sponge.clone(),
+            rng
+        ));
+        openings[1].1 += Fp::one();
+        assert!(!commitment.verify_positions(
+            &srs,
+            &group_map,
+            &openings,
+            &proof,
+            sponge.clone(),
+            rng
+        ));
+
+        // positions out of the vector
+        assert!(!commitment.verify(&srs, &group_map, 16, Fp::zero(), &proof, sponge, rng));
+    }
+
+    #[test]
+    fn test_vector_too_large() {
+        let srs = SRS::<VestaG>::create(16);
+        let rng = &mut StdRng::from_seed([0u8; 32]);
+        assert!(CommittedVector::commit(&srs, vec![Fp::one(); 17], rng).is_none());
+        assert!(CommittedVector::<VestaG>::commit(&srs, vec![], rng).is_none());
+    }
+}
@@ -0,0 +1,72 @@
+use ark_ff::{One, UniformRand};
+use ark_poly::{univariate::DensePolynomial, UVPolynomial};
+use commitment_dlog::{
+    commitment::CommitmentCurve,
+    extension::{ExtensionOpening, ExtensionPoint},
+    srs::SRS,
+};
+use groupmap::GroupMap;
+use mina_curves::pasta::{
+    vesta::{Affine, VestaParameters},
+    Fp,
+};
+use oracle::poseidon::PlonkSpongeConstantsKimchi as SC;
+use oracle::sponge::DefaultFqSponge;
+use oracle::FqSponge as _;
+
+type Sponge = DefaultFqSponge<VestaParameters, SC>;
+
+#[test]
+/// Tests the opening of chunked polynomial commitments at a point of a quadratic extension of the scalar field
+fn test_extension_opening() {
+    let mut rng = rand::thread_rng();
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let sponge = Sponge::new(oracle::pasta::fq_kimchi::params());
+    let srs = SRS::<Affine>::create(1 << 5);
+
+    // 5 generates the multiplicative group of Fp, so it is not a square
+    let point = ExtensionPoint::new(Fp::rand(&mut rng), Fp::rand(&mut rng), Fp::from(5u64));
+
+    // polynomials of a single chunk, of several chunks, and of degree less than the minimal polynomial
+    let polys: Vec<_> = [20, 100, 2]
+        .iter()
+        .map(|len| DensePolynomial::<Fp>::rand(*len, &mut rng))
+        .collect();
+    let comms: Vec<_> = polys
+        .iter()
+        .map(|p| srs.commit(p, None, &mut rng))
+        .collect();
+
+    let plnms: Vec<_> = polys
+        .iter()
+        .zip(&comms)
+        .map(|(p, (_, blinders))| (p, blinders.clone()))
+        .collect();
+    let opening = srs.open_extension(&group_map, &plnms, &point, sponge.clone(), &mut rng);
+    let comms: Vec<_> = comms.into_iter().map(|(comm, _)| comm).collect();
+
+    // the evaluations are the ones of the polynomials in the extension
+    let (e0, e1) = point.evaluate(&polys[1]);
+    assert_eq!(opening.evals[1], (e0, e1));
+
+    let verify = |opening: &ExtensionOpening<Affine>| {
+        srs.verify_extension(
+            &group_map,
+            &comms,
+            &point,
+            sponge.clone(),
+            opening,
+            &mut rand::thread_rng(),
+        )
+    };
+    assert!(verify(&opening));
+
+    // a wrong evaluation is rejected
+    let mut tampered = opening.clone();
+    tampered.evals[1].1 += Fp::one();
+    assert!(!verify(&tampered));
+
+    // so is an opening at a different point
+    let other = ExtensionPoint::new(point.c0, point.c1 + Fp::one(), point.nonresidue);
+    assert!(!srs.verify_extension(&group_map, &comms, &other, sponge, &opening, &mut rng));
+}
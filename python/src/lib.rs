@@ -0,0 +1,299 @@
+//! This crate exports the [CircuitBuilder] of kimchi, the solving of the witnesses,
+//! the prover and the verifier to Python through [pyo3], for circuits over the Vesta curve.
+//!
+//! The module is built with [maturin](https://github.com/PyO3/maturin) and the `extension-module` feature:
+//!
+//! ```python
+//! from kimchi_python import CircuitBuilder
+//!
+//! b = CircuitBuilder()
+//! y = b.public_input()
+//! x = b.private_input()
+//! b.assert_equal(b.mul(b.mul(x, x), x), y)
+//! circuit = b.build()
+//! witness = circuit.solve([27], [3])
+//! assert circuit.verify(circuit.prove(witness))
+//! ```
+//!
+//! Field elements are Python integers, reduced modulo the order of the field.
+//! Proofs and verifier indexes are bytes, serialized with MessagePack.
+
+use commitment_dlog::{
+    commitment::CommitmentCurve,
+    srs::{endos, SRS},
+};
+use groupmap::GroupMap;
+use kimchi::{
+    circuits::{
+        builder::{CircuitBuilder, Var, WitnessBuilder},
+        constraints::ConstraintSystem,
+        wires::COLUMNS,
+    },
+    prover::ProverProof,
+    prover_index::ProverIndex,
+    verifier::batch_verify,
+    verifier_index::VerifierIndex,
+};
+use mina_curves::pasta::{
+    fp::Fp,
+    pallas::Affine as Other,
+    vesta::{Affine, VestaParameters},
+};
+use num_bigint::BigUint;
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use std::sync::Arc;
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn fields(values: Vec<BigUint>) -> Vec<Fp> {
+    values.into_iter().map(Fp::from).collect()
+}
+
+/// A variable of a [PyCircuitBuilder].
+#[pyclass(name = "Var")]
+#[derive(Clone, Copy)]
+pub struct PyVar(Var);
+
+/// Builds a circuit, whose variables are only used with the builder that created them.
+#[pyclass(name = "CircuitBuilder")]
+pub struct PyCircuitBuilder(Option<CircuitBuilder<Fp>>);
+
+impl PyCircuitBuilder {
+    fn builder(&mut self) -> PyResult<&mut CircuitBuilder<Fp>> {
+        self.0
+            .as_mut()
+            .ok_or_else(|| value_error("the circuit was already built"))
+    }
+}
+
+#[pymethods]
+impl PyCircuitBuilder {
+    #[new]
+    fn new() -> Self {
+        Self(Some(CircuitBuilder::new()))
+    }
+
+    /// Declares a public input.
+    fn public_input(&mut self) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.public_input()))
+    }
+
+    /// Declares a private input.
+    fn private_input(&mut self) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.private_input()))
+    }
+
+    /// Returns a variable fixed to `value`.
+    fn constant(&mut self, value: BigUint) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.constant(Fp::from(value))))
+    }
+
+    fn add(&mut self, a: PyVar, b: PyVar) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.add(a.0, b.0)))
+    }
+
+    fn sub(&mut self, a: PyVar, b: PyVar) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.sub(a.0, b.0)))
+    }
+
+    fn mul(&mut self, a: PyVar, b: PyVar) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.mul(a.0, b.0)))
+    }
+
+    fn add_constant(&mut self, a: PyVar, c: BigUint) -> PyResult<PyVar> {
+        Ok(PyVar(self.builder()?.add_constant(a.0, Fp::from(c))))
+    }
+
+    fn assert_equal(&mut self, a: PyVar, b: PyVar) -> PyResult<()> {
+        self.builder()?.assert_equal(a.0, b.0);
+        Ok(())
+    }
+
+    fn assert_zero(&mut self, a: PyVar) -> PyResult<()> {
+        self.builder()?.assert_zero(a.0);
+        Ok(())
+    }
+
+    /// Builds the circuit, and creates its index.
+    fn build(&mut self) -> PyResult<PyCircuit> {
+        let builder = self
+            .0
+            .take()
+            .ok_or_else(|| value_error("the circuit was already built"))?;
+        let (gates, witness) = builder.build();
+        PyCircuit::create(gates, witness).map_err(value_error)
+    }
+}
+
+/// The witness of a circuit.
+#[pyclass(name = "Witness")]
+pub struct PyWitness([Vec<Fp>; COLUMNS]);
+
+#[pymethods]
+impl PyWitness {
+    /// The number of rows of the witness.
+    #[getter]
+    fn rows(&self) -> usize {
+        self.0[0].len()
+    }
+
+    /// Returns the value of the cell at `row` and `col`.
+    fn cell(&self, row: usize, col: usize) -> PyResult<BigUint> {
+        self.0
+            .get(col)
+            .and_then(|column| column.get(row))
+            .map(|value| (*value).into())
+            .ok_or_else(|| value_error(format!("no cell at row {row} and column {col}")))
+    }
+}
+
+/// A circuit, with its prover and verifier indexes.
+#[pyclass(name = "Circuit")]
+pub struct PyCircuit {
+    prover_key: ProverIndex<Affine>,
+    verifier_key: VerifierIndex<Affine>,
+    witness: WitnessBuilder<Fp>,
+}
+
+impl PyCircuit {
+    fn create(
+        gates: Vec<kimchi::circuits::gate::CircuitGate<Fp>>,
+        witness: WitnessBuilder<Fp>,
+    ) -> Result<Self, String> {
+        let cs = ConstraintSystem::<Fp>::create(
+            gates,
+            vec![],
+            oracle::pasta::fp_kimchi::params(),
+            witness.public_inputs(),
+        )
+        .map_err(|e| e.to_string())?;
+        let mut srs = SRS::<Affine>::create(cs.domain.d1.size as usize);
+        srs.add_lagrange_basis(cs.domain.d1);
+        let (endo_q, _) = endos::<Other>();
+        let index = ProverIndex::<Affine>::create(
+            cs,
+            oracle::pasta::fq_kimchi::params(),
+            endo_q,
+            Arc::new(srs),
+        );
+        let (prover_key, verifier_key) = index.into_keys();
+        Ok(Self {
+            prover_key,
+            verifier_key,
+            witness,
+        })
+    }
+
+    fn solve_witness(&self, public: &[Fp], private: &[Fp]) -> Result<PyWitness, String> {
+        self.witness
+            .solve(public, private)
+            .map(PyWitness)
+            .map_err(|e| e.to_string())
+    }
+
+    fn prove_bytes(&self, witness: &PyWitness) -> Result<Vec<u8>, String> {
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        let proof = ProverProof::create::<BaseSponge, ScalarSponge>(
+            &group_map,
+            witness.0.clone(),
+            &self.prover_key,
+            vec![],
+        )
+        .map_err(|e| e.to_string())?;
+        rmp_serde::to_vec(&proof).map_err(|e| e.to_string())
+    }
+
+    fn verify_bytes(&self, proof: &[u8]) -> Result<bool, String> {
+        let proof: ProverProof<Affine> = rmp_serde::from_slice(proof).map_err(|e| e.to_string())?;
+        let group_map = <Affine as CommitmentCurve>::Map::setup();
+        Ok(batch_verify::<Affine, BaseSponge, ScalarSponge>(
+            &group_map,
+            &[(&self.verifier_key, &proof)],
+        )
+        .is_ok())
+    }
+}
+
+#[pymethods]
+impl PyCircuit {
+    /// The number of gates of the circuit.
+    #[getter]
+    fn gates(&self) -> usize {
+        self.prover_key.cs.gates.len()
+    }
+
+    /// Solves the witness of the circuit for the `public` and `private` inputs.
+    fn solve(&self, public: Vec<BigUint>, private: Vec<BigUint>) -> PyResult<PyWitness> {
+        self.solve_witness(&fields(public), &fields(private))
+            .map_err(value_error)
+    }
+
+    /// Proves `witness`, and returns the serialized proof.
+    fn prove<'py>(&self, py: Python<'py>, witness: PyRef<PyWitness>) -> PyResult<&'py PyBytes> {
+        let proof = self.prove_bytes(&witness).map_err(value_error)?;
+        Ok(PyBytes::new(py, &proof))
+    }
+
+    /// Verifies the serialized `proof`. Fails if the proof cannot be deserialized.
+    fn verify(&self, proof: &[u8]) -> PyResult<bool> {
+        self.verify_bytes(proof).map_err(value_error)
+    }
+
+    /// Returns the serialized verifier index of the circuit.
+    fn verifier_index<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        let mut bytes = vec![];
+        self.verifier_key.write(&mut bytes).map_err(value_error)?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+}
+
+#[pymodule]
+fn kimchi_python(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyVar>()?;
+    m.add_class::<PyCircuitBuilder>()?;
+    m.add_class::<PyCircuit>()?;
+    m.add_class::<PyWitness>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_circuit() {
+        let mut b = PyCircuitBuilder::new();
+        let y = b.public_input().unwrap();
+        let x = b.private_input().unwrap();
+        let x2 = b.mul(x, x).unwrap();
+        let x3 = b.mul(x2, x).unwrap();
+        b.assert_equal(x3, y).unwrap();
+        let circuit = b.build().unwrap();
+        assert!(b.build().is_err());
+
+        let witness = circuit
+            .solve_witness(&[Fp::from(27u8)], &[Fp::from(3u8)])
+            .unwrap();
+        assert_eq!(witness.cell(0, 0).unwrap(), BigUint::from(27u8));
+        let proof = circuit.prove_bytes(&witness).unwrap();
+        assert!(circuit.verify_bytes(&proof).unwrap());
+        assert!(circuit.verify_bytes(&proof[1..]).is_err());
+
+        // a witness for another output is rejected
+        let witness = circuit
+            .solve_witness(&[Fp::from(28u8)], &[Fp::from(3u8)])
+            .unwrap();
+        assert!(circuit.prove_bytes(&witness).is_err());
+        assert!(circuit.solve_witness(&[], &[]).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! This module implements a facade for the metrics of the prover and the verifier,
+//! in the style of Prometheus counters and histograms.
+//!
+//! Nothing is recorded until a service installs a [Recorder] with [set_recorder],
+//! forwarding the metrics to its own backend.
+//! Until then, reporting a metric only costs a check that no recorder is installed.
+//!
+//! The durations are measured with [std::time::Instant], which is only read once a recorder is installed
+//! (or when the durations are collected, see [Phases::collect]):
+//! targets without a clock (like `wasm32-unknown-unknown`) must not install one.
+//!
+//! The memory used by the process can be tracked by installing [PeakAllocator] as the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Once,
+    },
+    time::{Duration, Instant},
+};
+
+/// The labels of a metric, as `(name, value)` pairs.
+pub type Labels<'a> = &'a [(&'static str, &'static str)];
+
+/// A metrics backend.
+pub trait Recorder: Send + Sync {
+    /// Adds `value` to the counter `name` with the given `labels`.
+    fn increment_counter(&self, name: &'static str, labels: Labels, value: u64);
+
+    /// Records an observation of `value` in the histogram `name` with the given `labels`.
+    fn record_histogram(&self, name: &'static str, labels: Labels, value: f64);
+}
+
+/// The error returned by [set_recorder] when a recorder is already installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetRecorderError;
+
+impl fmt::Display for SetRecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a metrics recorder is already installed")
+    }
+}
+
+impl std::error::Error for SetRecorderError {}
+
+static INIT: Once = Once::new();
+static mut RECORDER: Option<&'static dyn Recorder> = None;
+
+/// Installs the recorder that receives all the metrics of the process.
+/// A recorder can only be installed once.
+pub fn set_recorder(recorder: &'static dyn Recorder) -> Result<(), SetRecorderError> {
+    let mut installed = false;
+    INIT.call_once(|| {
+        // SAFETY: this is the only write, and reads only happen once `INIT` is completed
+        unsafe { RECORDER = Some(recorder) };
+        installed = true;
+    });
+    if installed {
+        Ok(())
+    } else {
+        Err(SetRecorderError)
+    }
+}
+
+/// Same as [set_recorder], for a recorder that is not `'static`, which is then leaked.
+pub fn set_boxed_recorder(recorder: Box<dyn Recorder>) -> Result<(), SetRecorderError> {
+    set_recorder(Box::leak(recorder))
+}
+
+/// Returns the installed recorder, if any.
+pub fn recorder() -> Option<&'static dyn Recorder> {
+    if INIT.is_completed() {
+        // SAFETY: `RECORDER` is written before `INIT` completes, and never after
+        unsafe { RECORDER }
+    } else {
+        None
+    }
+}
+
+/// Adds `value` to the counter `name`.
+pub fn counter(name: &'static str, labels: Labels, value: u64) {
+    if let Some(recorder) = recorder() {
+        recorder.increment_counter(name, labels, value);
+    }
+}
+
+/// Records `value` in the histogram `name`.
+pub fn histogram(name: &'static str, labels: Labels, value: f64) {
+    if let Some(recorder) = recorder() {
+        recorder.record_histogram(name, labels, value);
+    }
+}
+
+/// Measures the consecutive phases of a computation,
+/// recording the duration of each phase in seconds in a histogram labelled by `phase`.
+pub struct Phases {
+    name: &'static str,
+    start: Option<Instant>,
+    durations: Option<Vec<(&'static str, Duration)>>,
+}
+
+impl Phases {
+    /// Starts the first phase. The durations go to the histogram `name`.
+    pub fn start(name: &'static str) -> Self {
+        Self {
+            name,
+            start: recorder().map(|_| Instant::now()),
+            durations: None,
+        }
+    }
+
+    /// Same as [Phases::start], but the durations are also kept (see [Phases::durations]),
+    /// and thus measured even if no recorder is installed.
+    pub fn collect(name: &'static str) -> Self {
+        Self {
+            name,
+            start: Some(Instant::now()),
+            durations: Some(vec![]),
+        }
+    }
+
+    /// Ends the current phase, named `phase`, and starts the next one.
+    /// Returns the duration of the phase, if it is measured.
+    pub fn end(&mut self, phase: &'static str) -> Option<Duration> {
+        let start = self.start?;
+        let now = Instant::now();
+        let elapsed = now - start;
+        histogram(self.name, &[("phase", phase)], elapsed.as_secs_f64());
+        if let Some(durations) = &mut self.durations {
+            durations.push((phase, elapsed));
+        }
+        self.start = Some(now);
+        Some(elapsed)
+    }
+
+    /// Returns the phases ended so far and their durations, in order,
+    /// if they are collected.
+    pub fn durations(self) -> Option<Vec<(&'static str, Duration)>> {
+        self.durations
+    }
+}
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, keeping track of the memory allocated by the process and of its peak.
+/// It is installed with
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: o1_utils::metrics::PeakAllocator = o1_utils::metrics::PeakAllocator;
+/// ```
+pub struct PeakAllocator;
+
+impl PeakAllocator {
+    fn grow(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            TRACKING.store(true, Ordering::Relaxed);
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            Self::grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Returns the number of bytes currently allocated by the process,
+/// if [PeakAllocator] is the global allocator.
+pub fn allocated_memory() -> Option<usize> {
+    TRACKING
+        .load(Ordering::Relaxed)
+        .then(|| ALLOCATED.load(Ordering::Relaxed))
+}
+
+/// Returns the largest number of bytes allocated at once by the process since the last [reset_peak_memory],
+/// if [PeakAllocator] is the global allocator.
+pub fn peak_memory() -> Option<usize> {
+    TRACKING
+        .load(Ordering::Relaxed)
+        .then(|| PEAK.load(Ordering::Relaxed))
+}
+
+/// Restarts the measure of the peak from the memory currently allocated.
+/// The peak is that of the whole process: it includes the allocations of the other threads.
+pub fn reset_peak_memory() {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Memory {
+        counters: Mutex<Vec<(&'static str, String, u64)>>,
+        histograms: Mutex<Vec<(&'static str, String)>>,
+    }
+
+    impl Recorder for Memory {
+        fn increment_counter(&self, name: &'static str, labels: Labels, value: u64) {
+            let labels = format!("{:?}", labels);
+            self.counters.lock().unwrap().push((name, labels, value));
+        }
+
+        fn record_histogram(&self, name: &'static str, labels: Labels, _value: f64) {
+            let labels = format!("{:?}", labels);
+            self.histograms.lock().unwrap().push((name, labels));
+        }
+    }
+
+    #[test]
+    fn test_recorder() {
+        let mut phases = Phases::start("before");
+        assert_eq!(phases.end("nothing"), None);
+
+        let mut collected = Phases::collect("before");
+        assert!(collected.end("first").is_some());
+        collected.end("second");
+        let durations = collected.durations().unwrap();
+        assert_eq!(
+            durations
+                .iter()
+                .map(|(phase, _)| *phase)
+                .collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+
+        // the global allocator of the tests is the system one
+        assert_eq!(peak_memory(), None);
+
+        let memory: &'static Memory = Box::leak(Box::new(Memory::default()));
+        set_recorder(memory).unwrap();
+        assert_eq!(
+            set_boxed_recorder(Box::new(Memory::default())),
+            Err(SetRecorderError)
+        );
+
+        counter("proofs", &[("reason", "test")], 2);
+        let mut phases = Phases::start("phase_seconds");
+        assert!(phases.end("first").is_some());
+        assert!(phases.end("second").is_some());
+
+        assert_eq!(
+            *memory.counters.lock().unwrap(),
+            vec![("proofs", r#"[("reason", "test")]"#.to_string(), 2)]
+        );
+        assert_eq!(
+            *memory.histograms.lock().unwrap(),
+            vec![
+                ("phase_seconds", r#"[("phase", "first")]"#.to_string()),
+                ("phase_seconds", r#"[("phase", "second")]"#.to_string()),
+            ]
+        );
+    }
+}
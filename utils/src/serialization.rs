@@ -1,9 +1,60 @@
 //! This adds a few utility functions for serializing and deserializing
 //! [arkworks](http://arkworks.rs/) types that implement [CanonicalSerialize] and [CanonicalDeserialize].
+//!
+//! Curve points are written compressed with [ser] and [SerdeAs], and uncompressed with [SerdeAsUncompressed]
+//! (both of their coordinates, which are faster to serialize and twice as long).
+//! Deserialization accepts both encodings, and only returns points of the curve and of its prime-order subgroup.
 
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use serde_with::Bytes;
 
+/// Serializes `val`, with its curve points compressed or not.
+fn to_bytes(
+    val: &impl CanonicalSerialize,
+    compressed: bool,
+) -> Result<Vec<u8>, SerializationError> {
+    let mut bytes = vec![];
+    if compressed {
+        val.serialize(&mut bytes)?;
+    } else {
+        val.serialize_uncompressed(&mut bytes)?;
+    }
+    Ok(bytes)
+}
+
+/// Deserializes all the `bytes`, with their curve points compressed or not.
+///
+/// A compressed point is recomputed from its x-coordinate, and arkworks checks its subgroup,
+/// but arkworks does not check that an uncompressed point is on the curve.
+/// An uncompressed point is thus only accepted if it also reads back from its compressed encoding,
+/// which costs a square root.
+fn from_bytes<T>(bytes: &[u8]) -> Result<T, SerializationError>
+where
+    T: CanonicalSerialize + CanonicalDeserialize,
+{
+    let mut reader = bytes;
+    if let Ok(val) = T::deserialize(&mut reader) {
+        if reader.is_empty() {
+            return Ok(val);
+        }
+    }
+
+    let mut reader = bytes;
+    let val = T::deserialize_uncompressed(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(SerializationError::InvalidData);
+    }
+    let mut compressed = vec![];
+    val.serialize(&mut compressed)?;
+    let checked = T::deserialize(&compressed[..])?;
+    let mut uncompressed = vec![];
+    checked.serialize_uncompressed(&mut uncompressed)?;
+    if uncompressed != bytes {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok(checked)
+}
+
 //
 // Serialization with serde
 //
@@ -22,10 +73,7 @@ pub mod ser {
     where
         S: serde::Serializer,
     {
-        let mut bytes = vec![];
-        val.serialize(&mut bytes)
-            .map_err(serde::ser::Error::custom)?;
-
+        let bytes = to_bytes(&val, true).map_err(serde::ser::Error::custom)?;
         Bytes::serialize_as(&bytes, serializer)
     }
 
@@ -33,11 +81,11 @@ pub mod ser {
     /// See <https://serde.rs/field-attrs.html>
     pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
     where
-        T: CanonicalDeserialize,
+        T: CanonicalSerialize + CanonicalDeserialize,
         D: serde::Deserializer<'de>,
     {
         let bytes: Vec<u8> = Bytes::deserialize_as(deserializer)?;
-        T::deserialize(&mut &bytes[..]).map_err(serde::de::Error::custom)
+        from_bytes(&bytes).map_err(serde::de::Error::custom)
     }
 }
 
@@ -59,23 +107,107 @@ where
     where
         S: serde::Serializer,
     {
-        let mut bytes = vec![];
-        val.serialize(&mut bytes)
-            .map_err(serde::ser::Error::custom)?;
-
+        let bytes = to_bytes(val, true).map_err(serde::ser::Error::custom)?;
         Bytes::serialize_as(&bytes, serializer)
     }
 }
 
 impl<'de, T> serde_with::DeserializeAs<'de, T> for SerdeAs
 where
-    T: CanonicalDeserialize,
+    T: CanonicalSerialize + CanonicalDeserialize,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bytes: Vec<u8> = Bytes::deserialize_as(deserializer)?;
+        from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Same as [SerdeAs], but the curve points are written uncompressed.
+/// Simply add annotations like `#[serde_as(as = "o1_utils::serialization::SerdeAsUncompressed")]`
+pub struct SerdeAsUncompressed;
+
+impl<T> serde_with::SerializeAs<T> for SerdeAsUncompressed
+where
+    T: CanonicalSerialize,
+{
+    fn serialize_as<S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let bytes = to_bytes(val, false).map_err(serde::ser::Error::custom)?;
+        Bytes::serialize_as(&bytes, serializer)
+    }
+}
+
+impl<'de, T> serde_with::DeserializeAs<'de, T> for SerdeAsUncompressed
+where
+    T: CanonicalSerialize + CanonicalDeserialize,
 {
     fn deserialize_as<D>(deserializer: D) -> Result<T, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         let bytes: Vec<u8> = Bytes::deserialize_as(deserializer)?;
-        T::deserialize(&mut &bytes[..]).map_err(serde::de::Error::custom)
+        from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::AffineCurve;
+    use ark_ff::{One, Zero};
+    use mina_curves::pasta::pallas::Affine;
+    use serde_with::{de::DeserializeAsWrap, ser::SerializeAsWrap};
+
+    #[test]
+    fn test_point_encodings() {
+        let g = Affine::prime_subgroup_generator();
+        for p in [g, Affine::zero()] {
+            let compressed = to_bytes(&p, true).unwrap();
+            let uncompressed = to_bytes(&p, false).unwrap();
+            assert!(compressed.len() < uncompressed.len());
+            assert_eq!(from_bytes::<Affine>(&compressed).unwrap(), p);
+            assert_eq!(from_bytes::<Affine>(&uncompressed).unwrap(), p);
+        }
+    }
+
+    #[test]
+    fn test_serde_as_encodings() {
+        let points = vec![Affine::prime_subgroup_generator(), Affine::zero()];
+        let compressed = bcs::to_bytes(&SerializeAsWrap::<_, Vec<SerdeAs>>::new(&points)).unwrap();
+        let uncompressed = bcs::to_bytes(&SerializeAsWrap::<_, Vec<SerdeAsUncompressed>>::new(
+            &points,
+        ))
+        .unwrap();
+        assert!(compressed.len() < uncompressed.len());
+
+        // either adapter reads either encoding
+        for bytes in [&compressed, &uncompressed] {
+            let read = bcs::from_bytes::<DeserializeAsWrap<Vec<Affine>, Vec<SerdeAs>>>(bytes);
+            assert_eq!(read.unwrap().into_inner(), points);
+            let read =
+                bcs::from_bytes::<DeserializeAsWrap<Vec<Affine>, Vec<SerdeAsUncompressed>>>(bytes);
+            assert_eq!(read.unwrap().into_inner(), points);
+        }
+    }
+
+    #[test]
+    fn test_invalid_points() {
+        let g = Affine::prime_subgroup_generator();
+        let off_curve = Affine::new(g.x, g.y + <Affine as AffineCurve>::BaseField::one(), false);
+        let mut bytes = vec![];
+        off_curve.serialize_uncompressed(&mut bytes).unwrap();
+        assert!(from_bytes::<Affine>(&bytes).is_err());
+
+        // a truncated or extended point
+        let mut bytes = vec![];
+        g.serialize_uncompressed(&mut bytes).unwrap();
+        assert!(from_bytes::<Affine>(&bytes[..bytes.len() - 1]).is_err());
+        bytes.push(0);
+        assert!(from_bytes::<Affine>(&bytes).is_err());
     }
 }
@@ -0,0 +1,186 @@
+//! This crate exports the kimchi prover and verifier to JavaScript through [wasm_bindgen],
+//! for circuits over the Vesta curve.
+//!
+//! Indexes are created natively, and passed around in their serialized form:
+//! a prover index as written by [ProverIndex::write], and a verifier index as written by [VerifierIndex::write]
+//! (or as returned by [verifier_index]).
+//! Witnesses are built from JavaScript with a [WitnessBuilder].
+//! As the SRS is not serialized with the indexes, it is recreated (deterministically) on each call.
+
+use ark_ff::Zero;
+use array_init::array_init;
+use commitment_dlog::{
+    commitment::CommitmentCurve,
+    srs::{endos, SRS},
+};
+use groupmap::GroupMap;
+use kimchi::{
+    circuits::wires::COLUMNS, prover::ProverProof, prover_index::ProverIndex,
+    verifier::batch_verify, verifier_index::VerifierIndex,
+};
+use mina_curves::pasta::{
+    fp::Fp,
+    pallas::Affine as Other,
+    vesta::{Affine, VestaParameters},
+};
+use oracle::{
+    poseidon::PlonkSpongeConstantsKimchi,
+    sponge::{DefaultFqSponge, DefaultFrSponge},
+};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use std::{str::FromStr, sync::Arc};
+use wasm_bindgen::prelude::*;
+
+type SpongeParams = PlonkSpongeConstantsKimchi;
+type BaseSponge = DefaultFqSponge<VestaParameters, SpongeParams>;
+type ScalarSponge = DefaultFrSponge<Fp, SpongeParams>;
+
+/// The serialized form of a witness.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct Witness(
+    #[serde_as(as = "[Vec<o1_utils::serialization::SerdeAs>; COLUMNS]")] [Vec<Fp>; COLUMNS],
+);
+
+/// Builds the witness of a circuit, cell by cell.
+#[wasm_bindgen]
+pub struct WitnessBuilder {
+    columns: [Vec<Fp>; COLUMNS],
+}
+
+impl WitnessBuilder {
+    fn set_cell(&mut self, row: usize, col: usize, value: &str) -> Result<(), String> {
+        let value = Fp::from_str(value).map_err(|_| format!("invalid field element {value}"))?;
+        let cell = self
+            .columns
+            .get_mut(col)
+            .and_then(|column| column.get_mut(row))
+            .ok_or_else(|| format!("no cell at row {row} and column {col}"))?;
+        *cell = value;
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl WitnessBuilder {
+    /// Creates a witness of `rows` rows, with all cells set to zero.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rows: usize) -> Self {
+        Self {
+            columns: array_init(|_| vec![Fp::zero(); rows]),
+        }
+    }
+
+    /// Sets the cell at `row` and `col` to `value`, a field element written in decimal.
+    pub fn set(&mut self, row: usize, col: usize, value: &str) -> Result<(), JsValue> {
+        self.set_cell(row, col, value)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Returns the serialized witness, to pass to [prove].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
+        rmp_serde::to_vec(&Witness(self.columns.clone()))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+fn read_prover_index(index_bytes: &[u8]) -> Result<ProverIndex<Affine>, String> {
+    ProverIndex::read(
+        index_bytes,
+        |srs_size| Ok(Arc::new(SRS::create(srs_size))),
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )
+}
+
+fn verifier_index_bytes(index_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let index = read_prover_index(index_bytes)?;
+    let mut bytes = vec![];
+    index.verifier_index().write(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn prove_bytes(index_bytes: &[u8], witness_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let index = read_prover_index(index_bytes)?;
+    let Witness(witness) = rmp_serde::from_read_ref(witness_bytes).map_err(|e| e.to_string())?;
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    let proof =
+        ProverProof::create::<BaseSponge, ScalarSponge>(&group_map, witness, &index, vec![])
+            .map_err(|e| e.to_string())?;
+    rmp_serde::to_vec(&proof).map_err(|e| e.to_string())
+}
+
+fn verify_bytes(vk_bytes: &[u8], proof_bytes: &[u8]) -> Result<bool, String> {
+    let (endo_q, _) = endos::<Other>();
+    let index = VerifierIndex::<Affine>::read(
+        vk_bytes,
+        |index| {
+            let mut srs = SRS::create(index.max_poly_size);
+            srs.add_lagrange_basis(index.domain);
+            Arc::new(srs)
+        },
+        endo_q,
+        oracle::pasta::fq_kimchi::params(),
+        oracle::pasta::fp_kimchi::params(),
+    )?;
+    let proof: ProverProof<Affine> =
+        rmp_serde::from_read_ref(proof_bytes).map_err(|e| e.to_string())?;
+
+    let group_map = <Affine as CommitmentCurve>::Map::setup();
+    Ok(batch_verify::<Affine, BaseSponge, ScalarSponge>(&group_map, &[(&index, &proof)]).is_ok())
+}
+
+/// Returns the serialized verifier index of the serialized prover index `index_bytes`.
+#[wasm_bindgen]
+pub fn verifier_index(index_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    verifier_index_bytes(index_bytes).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Creates a proof for the serialized prover index `index_bytes` and the serialized witness `witness_bytes`,
+/// and returns the serialized proof.
+#[wasm_bindgen]
+pub fn prove(index_bytes: &[u8], witness_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    prove_bytes(index_bytes, witness_bytes).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Verifies the serialized proof `proof_bytes` against the serialized verifier index `vk_bytes`.
+/// Fails if the index or the proof can not be deserialized.
+#[wasm_bindgen]
+pub fn verify(vk_bytes: &[u8], proof_bytes: &[u8]) -> Result<bool, JsValue> {
+    verify_bytes(vk_bytes, proof_bytes).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kimchi::circuits::polynomials::generic::testing::create_circuit_and_witness;
+    use kimchi::prover_index::testing::new_index_for_test;
+
+    #[test]
+    fn test_prove_and_verify_bytes() {
+        let (gates, columns, public) = create_circuit_and_witness(5);
+        let mut builder = WitnessBuilder { columns };
+
+        let index = new_index_for_test(gates, public.len());
+        let mut index_bytes = vec![];
+        index.write(&mut index_bytes).unwrap();
+        let vk_bytes = verifier_index_bytes(&index_bytes).unwrap();
+
+        let witness_bytes = rmp_serde::to_vec(&Witness(builder.columns.clone())).unwrap();
+        let proof_bytes = prove_bytes(&index_bytes, &witness_bytes).unwrap();
+        assert!(verify_bytes(&vk_bytes, &proof_bytes).unwrap());
+
+        // a proof does not verify for other public inputs
+        let mut proof: ProverProof<Affine> = rmp_serde::from_read_ref(&proof_bytes).unwrap();
+        proof.public[0] = Fp::from(4u8);
+        let proof_bytes = rmp_serde::to_vec(&proof).unwrap();
+        assert!(!verify_bytes(&vk_bytes, &proof_bytes).unwrap());
+
+        // the witness builder rejects cells out of the witness and invalid field elements
+        assert!(builder.set_cell(0, 0, "4").is_ok());
+        assert!(builder.set_cell(usize::MAX, 0, "1").is_err());
+        assert!(builder.set_cell(0, 0, "not a number").is_err());
+    }
+}